@@ -1093,6 +1093,8 @@ fn create_column_alteration(
         rename,
         nullable,
         data_type,
+        materialize: false,
+        default_expression: None,
     })
 }
 
@@ -395,6 +395,7 @@ impl FromJObjectWithEnv<Fragment> for JObject<'_> {
             deletion_file,
             physical_rows: Some(physical_rows),
             row_id_meta,
+            partition: vec![],
         })
     }
 }
@@ -472,6 +473,7 @@ impl FromJObjectWithEnv<DataFile> for JObject<'_> {
             file_major_version,
             file_minor_version,
             file_size_bytes,
+            column_stats: vec![],
         })
     }
 }
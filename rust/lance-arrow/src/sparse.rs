@@ -0,0 +1,384 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Sparse vector support for Apache Arrow.
+//!
+//! A sparse vector (e.g. a SPLADE embedding) is stored as
+//! `List<Struct<index: UInt32, value: Float32>>`, with the entries of each
+//! vector sorted by dimension index. A column is a sparse vector column only
+//! if its field is tagged with the [`SPARSE_VECTOR_EXT_NAME`] extension name.
+//!
+//! There is no dedicated encoding for sparse vectors: the entry struct is
+//! marked as packed, so Lance stores the `(index, value)` pairs contiguously
+//! with the packed struct encoding, and the lists with the list encoding.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow_array::{
+    cast::AsArray,
+    types::{Float32Type, UInt32Type},
+    Array, ArrayRef, Float32Array, ListArray, StructArray, UInt32Array,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow_schema::{ArrowError, DataType, Field as ArrowField, Fields};
+
+use crate::bfloat16::ARROW_EXT_NAME_KEY;
+
+type Result<T> = std::result::Result<T, ArrowError>;
+
+pub const SPARSE_VECTOR_EXT_NAME: &str = "lance.sparse_vector";
+pub const SPARSE_INDEX_FIELD: &str = "index";
+pub const SPARSE_VALUE_FIELD: &str = "value";
+
+/// The struct metadata key that makes Lance store a struct as packed.
+///
+/// This is the legacy key, which is understood by all file versions.
+const PACKED_STRUCT_META_KEY: &str = "packed";
+
+fn sparse_entry_fields() -> Fields {
+    Fields::from(vec![
+        ArrowField::new(SPARSE_INDEX_FIELD, DataType::UInt32, false),
+        ArrowField::new(SPARSE_VALUE_FIELD, DataType::Float32, false),
+    ])
+}
+
+/// The item field of a sparse vector list.
+pub fn sparse_vector_entry_field() -> ArrowField {
+    ArrowField::new("item", DataType::Struct(sparse_entry_fields()), false).with_metadata(
+        HashMap::from([(PACKED_STRUCT_META_KEY.to_string(), "true".to_string())]),
+    )
+}
+
+/// The data type of a sparse vector column.
+pub fn sparse_vector_data_type() -> DataType {
+    DataType::List(Arc::new(sparse_vector_entry_field()))
+}
+
+/// Create a sparse vector field, tagged with the sparse vector extension name.
+pub fn sparse_vector_field(name: &str, nullable: bool) -> ArrowField {
+    ArrowField::new(name, sparse_vector_data_type(), nullable).with_metadata(HashMap::from([(
+        ARROW_EXT_NAME_KEY.to_string(),
+        SPARSE_VECTOR_EXT_NAME.to_string(),
+    )]))
+}
+
+/// Check whether the data type has the layout of a sparse vector.
+///
+/// Any list of `(UInt32, Float32)` structs has this layout, use
+/// [`is_sparse_vector_field`] to check whether a column is a sparse vector.
+pub fn is_sparse_vector_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::List(item) => match item.data_type() {
+            DataType::Struct(fields) => {
+                fields.len() == 2
+                    && fields[0].data_type() == &DataType::UInt32
+                    && fields[1].data_type() == &DataType::Float32
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Check whether the given field is a sparse vector field, with the layout of
+/// a sparse vector and the sparse vector extension name.
+pub fn is_sparse_vector_field(field: &ArrowField) -> bool {
+    is_sparse_vector_type(field.data_type())
+        && field
+            .metadata()
+            .get(ARROW_EXT_NAME_KEY)
+            .map(|name| name == SPARSE_VECTOR_EXT_NAME)
+            .unwrap_or_default()
+}
+
+/// A borrowed sparse vector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SparseVectorRef<'a> {
+    pub indices: &'a [u32],
+    pub values: &'a [f32],
+}
+
+impl SparseVectorRef<'_> {
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, f32)> + '_ {
+        self.indices
+            .iter()
+            .copied()
+            .zip(self.values.iter().copied())
+    }
+
+    pub fn to_owned(&self) -> SparseVector {
+        SparseVector {
+            indices: self.indices.to_vec(),
+            values: self.values.to_vec(),
+        }
+    }
+}
+
+/// An owned sparse vector, with strictly increasing indices.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SparseVector {
+    indices: Vec<u32>,
+    values: Vec<f32>,
+}
+
+impl SparseVector {
+    /// Create a sparse vector from sorted, unique indices and their values.
+    pub fn try_new(indices: Vec<u32>, values: Vec<f32>) -> Result<Self> {
+        if indices.len() != values.len() {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "Sparse vector has {} indices but {} values",
+                indices.len(),
+                values.len()
+            )));
+        }
+        if indices.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(ArrowError::InvalidArgumentError(
+                "Sparse vector indices must be strictly increasing".to_string(),
+            ));
+        }
+        Ok(Self { indices, values })
+    }
+
+    /// Create a sparse vector from `(index, value)` pairs in any order.
+    ///
+    /// Values of duplicated indices are summed.
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (u32, f32)>) -> Self {
+        let mut pairs = pairs.into_iter().collect::<Vec<_>>();
+        pairs.sort_unstable_by_key(|(index, _)| *index);
+        let mut indices = Vec::with_capacity(pairs.len());
+        let mut values: Vec<f32> = Vec::with_capacity(pairs.len());
+        for (index, value) in pairs {
+            if indices.last() == Some(&index) {
+                *values.last_mut().unwrap() += value;
+            } else {
+                indices.push(index);
+                values.push(value);
+            }
+        }
+        Self { indices, values }
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn as_ref(&self) -> SparseVectorRef<'_> {
+        SparseVectorRef {
+            indices: &self.indices,
+            values: &self.values,
+        }
+    }
+}
+
+/// A typed view over a sparse vector column.
+#[derive(Debug, Clone)]
+pub struct SparseVectorArray {
+    inner: ListArray,
+    indices: UInt32Array,
+    values: Float32Array,
+}
+
+impl SparseVectorArray {
+    /// Wrap an array with the sparse vector layout.
+    ///
+    /// Fails if the indices of a vector are not strictly increasing, since
+    /// the dot product and the sparse index rely on sorted, unique indices.
+    pub fn try_new(array: &dyn Array) -> Result<Self> {
+        if !is_sparse_vector_type(array.data_type()) {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "Expect a sparse vector array, got {}",
+                array.data_type()
+            )));
+        }
+        let inner = array.as_list::<i32>().clone();
+        let entries = inner.values().as_struct();
+        let indices = entries.column(0).as_primitive::<UInt32Type>().clone();
+        let values = entries.column(1).as_primitive::<Float32Type>().clone();
+        let array = Self {
+            inner,
+            indices,
+            values,
+        };
+        if let Some(i) =
+            (0..array.len()).find(|i| array.value(*i).indices.windows(2).any(|w| w[0] >= w[1]))
+        {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "Sparse vector {} has indices {:?}, but indices must be strictly increasing",
+                i,
+                array.value(i).indices
+            )));
+        }
+        Ok(array)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        self.inner.is_null(i)
+    }
+
+    /// Returns the `i`-th sparse vector. A null vector is returned as empty.
+    pub fn value(&self, i: usize) -> SparseVectorRef<'_> {
+        if self.inner.is_null(i) {
+            return SparseVectorRef {
+                indices: &[],
+                values: &[],
+            };
+        }
+        let offsets = self.inner.value_offsets();
+        let start = offsets[i] as usize;
+        let end = offsets[i + 1] as usize;
+        SparseVectorRef {
+            indices: &self.indices.values()[start..end],
+            values: &self.values.values()[start..end],
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Option<SparseVectorRef<'_>>> + '_ {
+        (0..self.len()).map(|i| (!self.is_null(i)).then(|| self.value(i)))
+    }
+
+    pub fn into_inner(self) -> ListArray {
+        self.inner
+    }
+}
+
+impl<V: Borrow<SparseVector>> FromIterator<Option<V>> for SparseVectorArray {
+    fn from_iter<I: IntoIterator<Item = Option<V>>>(iter: I) -> Self {
+        let mut offsets = vec![0_i32];
+        let mut validity = Vec::new();
+        let mut indices = Vec::new();
+        let mut values = Vec::new();
+        for vector in iter {
+            match vector {
+                Some(vector) => {
+                    let vector = vector.borrow();
+                    indices.extend_from_slice(vector.indices());
+                    values.extend_from_slice(vector.values());
+                    validity.push(true);
+                }
+                None => validity.push(false),
+            }
+            offsets.push(indices.len() as i32);
+        }
+        let indices = UInt32Array::from(indices);
+        let values = Float32Array::from(values);
+        let entries = StructArray::new(
+            sparse_entry_fields(),
+            vec![
+                Arc::new(indices.clone()) as ArrayRef,
+                Arc::new(values.clone()) as ArrayRef,
+            ],
+            None,
+        );
+        let nulls = validity
+            .iter()
+            .any(|valid| !valid)
+            .then(|| NullBuffer::from(validity));
+        let inner = ListArray::new(
+            Arc::new(sparse_vector_entry_field()),
+            OffsetBuffer::new(ScalarBuffer::from(offsets)),
+            Arc::new(entries),
+            nulls,
+        );
+        Self {
+            inner,
+            indices,
+            values,
+        }
+    }
+}
+
+impl From<SparseVectorArray> for ArrayRef {
+    fn from(array: SparseVectorArray) -> Self {
+        Arc::new(array.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_vector_array() {
+        let vectors = [
+            Some(SparseVector::from_pairs([(7, 0.5), (1, 1.0), (7, 0.25)])),
+            None,
+            Some(SparseVector::default()),
+            Some(SparseVector::try_new(vec![0, 3], vec![2.0, 3.0]).unwrap()),
+        ];
+        let array = vectors.iter().cloned().collect::<SparseVectorArray>();
+        assert_eq!(array.len(), 4);
+        assert_eq!(array.value(0).indices, &[1, 7]);
+        assert_eq!(array.value(0).values, &[1.0, 0.75]);
+        assert!(array.is_null(1));
+        assert!(array.value(2).is_empty());
+        assert_eq!(array.value(3).to_owned(), vectors[3].clone().unwrap());
+
+        let array: ArrayRef = array.into();
+        assert!(is_sparse_vector_type(array.data_type()));
+        let array = SparseVectorArray::try_new(array.slice(1, 3).as_ref()).unwrap();
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.value(2).indices, &[0, 3]);
+
+        assert!(is_sparse_vector_field(&sparse_vector_field("vec", true)));
+        // the layout alone doesn't make a sparse vector field
+        assert!(!is_sparse_vector_field(&ArrowField::new(
+            "vec",
+            sparse_vector_data_type(),
+            true
+        )));
+        assert!(SparseVector::try_new(vec![3, 1], vec![1.0, 1.0]).is_err());
+        assert!(SparseVector::try_new(vec![1], vec![]).is_err());
+    }
+
+    #[test]
+    fn test_sparse_vector_array_rejects_unsorted_indices() {
+        let make_array = |indices: Vec<u32>| {
+            let values = Float32Array::from(vec![1.0; indices.len()]);
+            let entries = StructArray::new(
+                sparse_entry_fields(),
+                vec![
+                    Arc::new(UInt32Array::from(indices)) as ArrayRef,
+                    Arc::new(values) as ArrayRef,
+                ],
+                None,
+            );
+            ListArray::new(
+                Arc::new(sparse_vector_entry_field()),
+                OffsetBuffer::from_lengths([2, 3]),
+                Arc::new(entries),
+                None,
+            )
+        };
+        assert!(SparseVectorArray::try_new(&make_array(vec![1, 2, 0, 4, 5])).is_ok());
+        // unsorted
+        let err = SparseVectorArray::try_new(&make_array(vec![1, 2, 5, 4, 6])).unwrap_err();
+        assert!(err.to_string().contains("Sparse vector 1"), "{}", err);
+        // duplicated
+        assert!(SparseVectorArray::try_new(&make_array(vec![1, 1, 0, 4, 5])).is_err());
+        // only the vectors of a slice are checked
+        let array = make_array(vec![1, 1, 0, 4, 5]);
+        assert!(SparseVectorArray::try_new(&array.slice(1, 1)).is_ok());
+    }
+}
@@ -7,7 +7,7 @@ use std::fmt::{self, Debug, Formatter};
 use std::sync::Arc;
 
 use arrow_array::ArrayRef;
-use arrow_schema::{DataType, Field as ArrowField, Fields, TimeUnit};
+use arrow_schema::{DataType, Field as ArrowField, Fields, TimeUnit, UnionMode};
 use deepsize::DeepSizeOf;
 use lance_arrow::bfloat16::{
     is_bfloat16_field, ARROW_EXT_META_KEY, ARROW_EXT_NAME_KEY, BFLOAT16_EXT_NAME,
@@ -20,6 +20,7 @@ mod schema;
 use crate::{Error, Result};
 pub use field::{
     Encoding, Field, NullabilityComparison, OnTypeMismatch, SchemaCompareOptions, StorageClass,
+    LANCE_DEFAULT_EXPRESSION_META_KEY, LANCE_GENERATED_EXPRESSION_META_KEY,
     LANCE_STORAGE_CLASS_SCHEMA_META_KEY,
 };
 pub use schema::{OnMissing, Projectable, Projection, Schema};
@@ -67,6 +68,40 @@ impl LogicalType {
     fn is_struct(&self) -> bool {
         self.0 == "struct"
     }
+
+    fn is_map(&self) -> bool {
+        self.0 == "map" || self.0 == "map:sorted"
+    }
+
+    fn is_union(&self) -> bool {
+        self.0.starts_with("union:")
+    }
+
+    /// The mode and type ids of a union, encoded as `union:<mode>:<id>,<id>,...`
+    fn union_mode_and_type_ids(&self) -> Result<(UnionMode, Vec<i8>)> {
+        let invalid = || Error::Schema {
+            message: format!("Unsupported union type: {}", self),
+            location: location!(),
+        };
+        let splits = self.0.split(':').collect::<Vec<_>>();
+        if splits.len() != 3 || splits[0] != "union" {
+            return Err(invalid());
+        }
+        let mode = match splits[1] {
+            "sparse" => UnionMode::Sparse,
+            "dense" => UnionMode::Dense,
+            _ => return Err(invalid()),
+        };
+        let type_ids = if splits[2].is_empty() {
+            vec![]
+        } else {
+            splits[2]
+                .split(',')
+                .map(|id| id.parse::<i8>().map_err(|_| invalid()))
+                .collect::<Result<Vec<_>>>()?
+        };
+        Ok((mode, type_ids))
+    }
 }
 
 impl From<&str> for LogicalType {
@@ -121,6 +156,8 @@ impl TryFrom<&DataType> for LogicalType {
             DataType::Binary => "binary".to_string(),
             DataType::LargeUtf8 => "large_string".to_string(),
             DataType::LargeBinary => "large_binary".to_string(),
+            DataType::Utf8View => "string_view".to_string(),
+            DataType::BinaryView => "binary_view".to_string(),
             DataType::Date32 => "date32:day".to_string(),
             DataType::Date64 => "date64:ms".to_string(),
             DataType::Time32(tu) => format!("time32:{}", timeunit_to_str(tu)),
@@ -165,6 +202,25 @@ impl TryFrom<&DataType> for LogicalType {
                 }
             }
             DataType::FixedSizeBinary(len) => format!("fixed_size_binary:{}", *len),
+            DataType::Map(_, keys_sorted) => {
+                if *keys_sorted {
+                    "map:sorted".to_string()
+                } else {
+                    "map".to_string()
+                }
+            }
+            DataType::Union(fields, mode) => format!(
+                "union:{}:{}",
+                match mode {
+                    UnionMode::Sparse => "sparse",
+                    UnionMode::Dense => "dense",
+                },
+                fields
+                    .iter()
+                    .map(|(type_id, _)| type_id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
             _ => {
                 return Err(Error::Schema {
                     message: format!("Unsupported data type: {:?}", dt),
@@ -200,6 +256,8 @@ impl TryFrom<&LogicalType> for DataType {
             "binary" => Some(Binary),
             "large_string" => Some(LargeUtf8),
             "large_binary" => Some(LargeBinary),
+            "string_view" => Some(Utf8View),
+            "binary_view" => Some(BinaryView),
             "date32:day" => Some(Date32),
             "date64:ms" => Some(Date64),
             "time32:s" => Some(Time32(TimeUnit::Second)),
@@ -18,7 +18,7 @@ use arrow_array::{
     },
     ArrayRef,
 };
-use arrow_schema::{DataType, Field as ArrowField};
+use arrow_schema::{DataType, Field as ArrowField, UnionFields};
 use deepsize::DeepSizeOf;
 use lance_arrow::{bfloat16::ARROW_EXT_NAME_KEY, *};
 use snafu::location;
@@ -30,6 +30,10 @@ use super::{
 use crate::{Error, Result};
 
 pub const LANCE_STORAGE_CLASS_SCHEMA_META_KEY: &str = "lance-schema:storage-class";
+/// Field metadata key holding the SQL expression of a generated column.
+pub const LANCE_GENERATED_EXPRESSION_META_KEY: &str = "lance-schema:generated-expression";
+/// Field metadata key holding the SQL expression of the default value of a field.
+pub const LANCE_DEFAULT_EXPRESSION_META_KEY: &str = "lance-schema:default-expression";
 
 #[derive(Debug, Default)]
 pub enum NullabilityComparison {
@@ -152,10 +156,56 @@ impl Field {
             lt if lt.is_struct() => {
                 DataType::Struct(self.children.iter().map(ArrowField::from).collect())
             }
+            lt if lt.is_map() => DataType::Map(
+                Arc::new(ArrowField::from(&self.children[0])),
+                lt.0 == "map:sorted",
+            ),
+            lt if lt.is_union() => {
+                let (mode, type_ids) = lt
+                    .union_mode_and_type_ids()
+                    .expect("the logical types are validated when the schema is loaded");
+                DataType::Union(
+                    UnionFields::new(type_ids, self.children.iter().map(ArrowField::from)),
+                    mode,
+                )
+            }
             lt => DataType::try_from(lt).unwrap(),
         }
     }
 
+    /// Check that the logical types of the field and its children describe
+    /// valid Arrow data types, see [`Self::data_type`].
+    pub fn validate_logical_types(&self) -> Result<()> {
+        let lt = &self.logical_type;
+        let num_children = if lt.is_list() || lt.is_large_list() || lt.is_map() {
+            Some(1)
+        } else if lt.is_union() {
+            Some(lt.union_mode_and_type_ids()?.1.len())
+        } else if lt.is_struct() {
+            None
+        } else {
+            DataType::try_from(lt)?;
+            None
+        };
+        if let Some(num_children) = num_children {
+            if self.children.len() != num_children {
+                return Err(Error::Schema {
+                    message: format!(
+                        "Field {} of type {} has {} children, expected {}",
+                        self.name,
+                        lt,
+                        self.children.len(),
+                        num_children
+                    ),
+                    location: location!(),
+                });
+            }
+        }
+        self.children
+            .iter()
+            .try_for_each(|child| child.validate_logical_types())
+    }
+
     pub fn has_dictionary_types(&self) -> bool {
         matches!(self.data_type(), DataType::Dictionary(_, _))
             || self.children.iter().any(Self::has_dictionary_types)
@@ -384,6 +434,35 @@ impl Field {
         self.metadata.get(ARROW_EXT_NAME_KEY).map(String::as_str)
     }
 
+    /// The SQL expression computing the values of this field, if it is a
+    /// generated column.
+    pub fn generated_expression(&self) -> Option<&str> {
+        self.metadata
+            .get(LANCE_GENERATED_EXPRESSION_META_KEY)
+            .map(String::as_str)
+    }
+
+    /// The SQL expression computing the value of this field for rows written
+    /// without it, if the field has a default.
+    pub fn default_expression(&self) -> Option<&str> {
+        self.metadata
+            .get(LANCE_DEFAULT_EXPRESSION_META_KEY)
+            .map(String::as_str)
+    }
+
+    /// Set or, with `None`, remove the default expression of this field.
+    pub fn set_default_expression(&mut self, expression: Option<String>) {
+        match expression {
+            Some(expression) => {
+                self.metadata
+                    .insert(LANCE_DEFAULT_EXPRESSION_META_KEY.to_string(), expression);
+            }
+            None => {
+                self.metadata.remove(LANCE_DEFAULT_EXPRESSION_META_KEY);
+            }
+        }
+    }
+
     pub fn child(&self, name: &str) -> Option<&Self> {
         self.children.iter().find(|f| f.name == name)
     }
@@ -489,6 +568,15 @@ impl Field {
         if path_components.is_empty() {
             // Project stops here, copy all the remaining children.
             f.children.clone_from(&self.children)
+        } else if self.logical_type.is_map() {
+            // The entries struct can be omitted from the path, e.g. `attrs.value`.
+            let entries = &self.children[0];
+            let path = if path_components[0] == entries.name {
+                &path_components[1..]
+            } else {
+                path_components
+            };
+            f.children.push(self.with_map_keys(entries.project(path)?));
         } else {
             let first = path_components[0];
             for c in self.children.as_slice() {
@@ -544,11 +632,17 @@ impl Field {
     /// If the ids are `[2]`, then this will include the parent `0` and the
     /// child `3`.
     pub(crate) fn project_by_ids(&self, ids: &[i32], include_all_children: bool) -> Option<Self> {
-        let children = self
+        let mut children = self
             .children
             .iter()
             .filter_map(|c| c.project_by_ids(ids, include_all_children))
             .collect::<Vec<_>>();
+        if self.logical_type.is_map() {
+            children = children
+                .into_iter()
+                .map(|entries| self.with_map_keys(entries))
+                .collect();
+        }
         if ids.contains(&self.id) && (children.is_empty() || include_all_children) {
             Some(self.clone())
         } else if !children.is_empty() {
@@ -561,6 +655,18 @@ impl Field {
         }
     }
 
+    /// Add the keys of a map back to a projection of its entries.
+    ///
+    /// A map cannot be read without its keys, so projecting only (part of) the
+    /// values of a map still reads the keys.
+    fn with_map_keys(&self, mut entries: Self) -> Self {
+        let keys = &self.children[0].children[0];
+        if !entries.children.iter().any(|c| c.name == keys.name) {
+            entries.children.insert(0, keys.clone());
+        }
+        entries
+    }
+
     /// Project by a field.
     ///
     pub fn project_by_field(&self, other: &Self, on_type_mismatch: OnTypeMismatch) -> Result<Self> {
@@ -617,6 +723,14 @@ impl Field {
                 cloned.children = vec![projected];
                 Ok(cloned)
             }
+            (DataType::Map(_, _), DataType::Map(_, _)) => {
+                let projected =
+                    self.children[0].project_by_field(&other.children[0], on_type_mismatch)?;
+                let mut cloned = self.clone();
+                cloned.children = vec![self.with_map_keys(projected)];
+                Ok(cloned)
+            }
+            (DataType::Union(_, _), DataType::Union(_, _)) => Ok(self.clone()),
             (DataType::FixedSizeList(dt, n), DataType::FixedSizeList(other_dt, m))
                 if dt == other_dt && n == m =>
             {
@@ -627,6 +741,8 @@ impl Field {
                 DataType::Dictionary(other_key, other_value),
             ) if self_key == other_key && self_value == other_value => Ok(self.clone()),
             (DataType::Null, DataType::Null) => Ok(self.clone()),
+            (DataType::Utf8View, DataType::Utf8View)
+            | (DataType::BinaryView, DataType::BinaryView) => Ok(self.clone()),
             (DataType::FixedSizeBinary(self_width), DataType::FixedSizeBinary(other_width))
                 if self_width == other_width =>
             {
@@ -782,7 +898,8 @@ impl Field {
                 }
             }
             (DataType::List(_), DataType::List(_))
-            | (DataType::LargeList(_), DataType::LargeList(_)) => {
+            | (DataType::LargeList(_), DataType::LargeList(_))
+            | (DataType::Map(_, _), DataType::Map(_, _)) => {
                 self.children[0].merge(&other.children[0])?;
             }
             (
@@ -892,6 +1009,26 @@ impl Field {
     pub fn is_leaf(&self) -> bool {
         self.children.is_empty()
     }
+
+    /// Switch string and binary fields (including nested ones) to `Utf8View` / `BinaryView`
+    ///
+    /// Blob fields are left unchanged.
+    pub fn use_view_types(&mut self) {
+        if self.metadata.contains_key(super::BLOB_META_KEY) {
+            return;
+        }
+        let view_type = match self.data_type() {
+            DataType::Utf8 | DataType::LargeUtf8 => Some(DataType::Utf8View),
+            DataType::Binary | DataType::LargeBinary => Some(DataType::BinaryView),
+            _ => None,
+        };
+        if let Some(view_type) = view_type {
+            self.logical_type = LogicalType::try_from(&view_type).unwrap();
+        }
+        for child in self.children.iter_mut() {
+            child.use_view_types();
+        }
+    }
 }
 
 impl fmt::Display for Field {
@@ -929,6 +1066,11 @@ impl TryFrom<&ArrowField> for Field {
                 .collect::<Result<_>>()?,
             DataType::List(item) => vec![Self::try_from(item.as_ref())?],
             DataType::LargeList(item) => vec![Self::try_from(item.as_ref())?],
+            DataType::Map(entries, _) => vec![Self::try_from(entries.as_ref())?],
+            DataType::Union(fields, _) => fields
+                .iter()
+                .map(|(_, f)| Self::try_from(f.as_ref()))
+                .collect::<Result<_>>()?,
             _ => vec![],
         };
         let storage_class = field
@@ -989,7 +1131,7 @@ mod tests {
     use super::*;
 
     use arrow_array::{DictionaryArray, StringArray, UInt32Array};
-    use arrow_schema::{Fields, TimeUnit};
+    use arrow_schema::{Fields, TimeUnit, UnionMode};
 
     #[test]
     fn arrow_field_to_field() {
@@ -1101,6 +1243,122 @@ mod tests {
         assert_eq!(ArrowField::from(&field), arrow_field);
     }
 
+    fn map_field() -> ArrowField {
+        let value = ArrowField::new(
+            "value",
+            DataType::Struct(Fields::from(vec![
+                ArrowField::new("score", DataType::Float32, true),
+                ArrowField::new("label", DataType::Utf8, true),
+            ])),
+            true,
+        );
+        let entries = ArrowField::new(
+            "entries",
+            DataType::Struct(Fields::from(vec![
+                ArrowField::new("key", DataType::Utf8, false),
+                value,
+            ])),
+            false,
+        );
+        ArrowField::new("attrs", DataType::Map(Arc::new(entries), false), true)
+    }
+
+    #[test]
+    fn map_field_round_trip() {
+        let arrow_field = map_field();
+        let field = Field::try_from(&arrow_field).unwrap();
+        assert_eq!(field.logical_type.0, "map");
+        assert_eq!(field.children.len(), 1);
+        assert_eq!(field.children[0].children.len(), 2);
+        assert_eq!(&field.data_type(), arrow_field.data_type());
+        assert_eq!(ArrowField::from(&field), arrow_field);
+    }
+
+    #[test]
+    fn union_field_round_trip() {
+        for mode in [UnionMode::Sparse, UnionMode::Dense] {
+            let arrow_field = ArrowField::new(
+                "u",
+                DataType::Union(
+                    UnionFields::new(
+                        vec![0, 3],
+                        vec![
+                            ArrowField::new("int", DataType::Int32, true),
+                            ArrowField::new("str", DataType::Utf8, true),
+                        ],
+                    ),
+                    mode,
+                ),
+                false,
+            );
+            let field = Field::try_from(&arrow_field).unwrap();
+            let expected = match mode {
+                UnionMode::Sparse => "union:sparse:0,3",
+                UnionMode::Dense => "union:dense:0,3",
+            };
+            assert_eq!(field.logical_type.0, expected);
+            assert_eq!(field.children.len(), 2);
+            assert_eq!(&field.data_type(), arrow_field.data_type());
+            field.validate_logical_types().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_validate_malformed_union() {
+        let arrow_field = ArrowField::new(
+            "u",
+            DataType::Union(
+                UnionFields::new(
+                    vec![0, 3],
+                    vec![
+                        ArrowField::new("int", DataType::Int32, true),
+                        ArrowField::new("str", DataType::Utf8, true),
+                    ],
+                ),
+                UnionMode::Sparse,
+            ),
+            false,
+        );
+        let field = Field::try_from(&arrow_field).unwrap();
+        for logical_type in [
+            "union:sparse",
+            "union:other:0,3",
+            "union:dense:0,x",
+            "union:dense:0,3,4",
+        ] {
+            let mut malformed = field.clone();
+            malformed.logical_type = LogicalType::from(logical_type);
+            let err = malformed.validate_logical_types().unwrap_err();
+            assert!(matches!(err, Error::Schema { .. }), "{}", err);
+        }
+
+        // nested in another field
+        let mut parent = Field::try_from(&ArrowField::new(
+            "s",
+            DataType::Struct(Fields::from(vec![arrow_field])),
+            true,
+        ))
+        .unwrap();
+        parent.validate_logical_types().unwrap();
+        parent.children[0].logical_type = LogicalType::from("union:sparse:0");
+        assert!(parent.validate_logical_types().is_err());
+    }
+
+    #[test]
+    fn test_project_map_values() {
+        let field = Field::try_from(&map_field()).unwrap();
+        // The keys are always kept, with or without the entries in the path.
+        for path in [["value", "score"], ["entries", "value"]] {
+            let projected = field.project(&path).unwrap();
+            let entries = &projected.children[0];
+            assert_eq!(entries.children[0].name, "key");
+            assert_eq!(entries.children[1].name, "value");
+            assert!(matches!(projected.data_type(), DataType::Map(_, false)));
+        }
+        let projected = field.project(&["value", "score"]).unwrap();
+        assert_eq!(projected.children[0].children[1].children.len(), 1);
+    }
+
     #[test]
     fn test_project_by_field_null_type() {
         let f1: Field = ArrowField::new("a", DataType::Null, true)
@@ -88,6 +88,17 @@ impl Schema {
         }
     }
 
+    /// Returns a copy of the schema with string and binary fields switched to view types
+    ///
+    /// See [`Field::use_view_types`].
+    pub fn with_view_types(&self) -> Self {
+        let mut schema = self.clone();
+        for field in schema.fields.iter_mut() {
+            field.use_view_types();
+        }
+        schema
+    }
+
     pub fn retain_storage_class(&self, storage_class: StorageClass) -> Self {
         let fields = self
             .fields
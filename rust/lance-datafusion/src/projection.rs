@@ -27,7 +27,7 @@ use crate::{
     planner::Planner,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProjectionPlan {
     /// The physical schema (before dynamic projection) that must be loaded from the dataset
     pub physical_schema: Arc<Schema>,
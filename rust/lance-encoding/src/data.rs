@@ -21,7 +21,8 @@ use std::{
 
 use arrow::array::{ArrayData, ArrayDataBuilder, AsArray};
 use arrow_array::{new_empty_array, new_null_array, Array, ArrayRef, UInt64Array};
-use arrow_buffer::{ArrowNativeType, BooleanBuffer, BooleanBufferBuilder, NullBuffer};
+use arrow_buffer::{ArrowNativeType, BooleanBuffer, BooleanBufferBuilder, Buffer, NullBuffer};
+use arrow_data::ByteView;
 use arrow_schema::DataType;
 use bytemuck::try_cast_slice;
 use lance_arrow::DataTypeExt;
@@ -658,6 +659,9 @@ pub struct VariableWidthBlock {
 
 impl VariableWidthBlock {
     fn into_arrow(self, data_type: DataType, validate: bool) -> Result<ArrayData> {
+        if matches!(data_type, DataType::Utf8View | DataType::BinaryView) {
+            return self.into_arrow_view(data_type, validate);
+        }
         let data_buffer = self.data.into_buffer();
         let offsets_buffer = self.offsets.into_buffer();
         let builder = ArrayDataBuilder::new(data_type)
@@ -672,6 +676,56 @@ impl VariableWidthBlock {
         }
     }
 
+    /// Builds a view array directly over the data buffer
+    ///
+    /// Values of up to 12 bytes are inlined into their view and longer values
+    /// reference the data buffer so the data is never copied.
+    fn into_arrow_view(mut self, data_type: DataType, validate: bool) -> Result<ArrayData> {
+        if self.data.len() > u32::MAX as usize {
+            return Err(Error::NotSupported {
+                source: format!(
+                    "cannot decode {} bytes of variable width data into a view array",
+                    self.data.len()
+                )
+                .into(),
+                location: location!(),
+            });
+        }
+        let data_buffer = self.data.into_buffer();
+        let views = match self.bits_per_offset {
+            32 => make_views(
+                self.offsets
+                    .borrow_to_typed_slice::<u32>()
+                    .iter()
+                    .map(|offset| *offset as usize),
+                &data_buffer,
+            ),
+            64 => make_views(
+                self.offsets
+                    .borrow_to_typed_slice::<u64>()
+                    .iter()
+                    .map(|offset| *offset as usize),
+                &data_buffer,
+            ),
+            _ => {
+                return Err(Error::Internal {
+                    message: format!("unexpected bits per offset {}", self.bits_per_offset),
+                    location: location!(),
+                })
+            }
+        };
+        let builder = ArrayDataBuilder::new(data_type)
+            .add_buffer(Buffer::from_vec(views))
+            .add_buffer(data_buffer)
+            .len(self.num_values as usize)
+            .null_count(0);
+        if validate {
+            Ok(builder.build()?)
+        } else {
+            Ok(unsafe { builder.build_unchecked() })
+        }
+    }
+
     fn into_buffers(self) -> Vec<LanceBuffer> {
         vec![self.offsets, self.data]
     }
@@ -1245,6 +1299,74 @@ fn stitch_offsets<T: ArrowNativeType + std::ops::Add<Output = T> + std::ops::Sub
     (LanceBuffer::reinterpret_vec(dest), byte_ranges)
 }
 
+/// Values of up to this many bytes are stored inline in the view of a view array
+const MAX_INLINE_VIEW_LEN: usize = 12;
+
+fn make_views(mut offsets: impl Iterator<Item = usize>, data: &[u8]) -> Vec<u128> {
+    let Some(mut start) = offsets.next() else {
+        return Vec::new();
+    };
+    offsets
+        .map(|end| {
+            let value = &data[start..end];
+            let view = if value.len() <= MAX_INLINE_VIEW_LEN {
+                let mut inline = [0_u8; 16];
+                inline[..4].copy_from_slice(&(value.len() as u32).to_le_bytes());
+                inline[4..4 + value.len()].copy_from_slice(value);
+                u128::from_le_bytes(inline)
+            } else {
+                ByteView::new(value.len() as u32, &value[..4])
+                    .with_buffer_index(0)
+                    .with_offset(start as u32)
+                    .as_u128()
+            };
+            start = end;
+            view
+        })
+        .collect()
+}
+
+fn arrow_binary_view_to_data_block(arrays: &[ArrayRef], num_values: u64) -> DataBlock {
+    let mut offsets = Vec::with_capacity(num_values as usize + 1);
+    offsets.push(0_u64);
+    let mut bytes = Vec::new();
+    for arr in arrays {
+        let data = arr.to_data();
+        let views = &data.buffer::<u128>(0)[..data.len()];
+        for (idx, view) in views.iter().enumerate() {
+            // The views of null values are not guaranteed to be valid
+            if data.is_valid(idx) {
+                let len = *view as u32 as usize;
+                if len <= MAX_INLINE_VIEW_LEN {
+                    bytes.extend_from_slice(&view.to_le_bytes()[4..4 + len]);
+                } else {
+                    let view = ByteView::from(*view);
+                    let start = view.offset as usize;
+                    let buffer = &data.buffers()[1 + view.buffer_index as usize];
+                    bytes.extend_from_slice(&buffer[start..start + len]);
+                }
+            }
+            offsets.push(bytes.len() as u64);
+        }
+    }
+    let (offsets, bits_per_offset) = if bytes.len() <= i32::MAX as usize {
+        let offsets = offsets
+            .into_iter()
+            .map(|offset| offset as u32)
+            .collect::<Vec<_>>();
+        (LanceBuffer::reinterpret_vec(offsets), 32)
+    } else {
+        (LanceBuffer::reinterpret_vec(offsets), 64)
+    };
+    DataBlock::VariableWidth(VariableWidthBlock {
+        data: LanceBuffer::Owned(bytes),
+        offsets,
+        bits_per_offset,
+        num_values,
+        block_info: BlockInfo::new(),
+    })
+}
+
 fn arrow_binary_to_data_block(
     arrays: &[ArrayRef],
     num_values: u64,
@@ -1525,7 +1647,7 @@ impl DataBlock {
         let mut encoded = match data_type {
             DataType::Binary | DataType::Utf8 => arrow_binary_to_data_block(arrays, num_values, 32),
             DataType::BinaryView | DataType::Utf8View => {
-                todo!()
+                arrow_binary_view_to_data_block(arrays, num_values)
             }
             DataType::LargeBinary | DataType::LargeUtf8 => {
                 arrow_binary_to_data_block(arrays, num_values, 64)
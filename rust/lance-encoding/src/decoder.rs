@@ -248,6 +248,9 @@ use crate::encodings::logical::primitive::{
 use crate::encodings::logical::r#struct::{
     SimpleStructDecoder, SimpleStructScheduler, StructuralStructDecoder, StructuralStructScheduler,
 };
+use crate::encodings::logical::union::{
+    union_storage_fields, UnionFieldScheduler, UNION_TYPE_IDS_FIELD_NAME,
+};
 use crate::encodings::physical::binary::{
     BinaryBlockDecompressor, BinaryMiniBlockDecompressor, VariableDecoder,
 };
@@ -747,20 +750,26 @@ impl CoreFieldDecoderStrategy {
         let items_field = match list_field.data_type() {
             DataType::List(inner) => inner,
             DataType::LargeList(inner) => inner,
+            DataType::Map(entries, _) => entries,
             _ => unreachable!(),
         };
-        let offset_type = if matches!(list_field.data_type(), DataType::List(_)) {
-            DataType::Int32
-        } else {
+        let offset_type = if matches!(list_field.data_type(), DataType::LargeList(_)) {
             DataType::Int64
+        } else {
+            DataType::Int32
         };
-        Ok(Box::new(ListFieldScheduler::new(
+        let scheduler = ListFieldScheduler::new(
             inner,
             items_scheduler.into(),
             items_field,
             offset_type,
             null_offset_adjustments,
-        )))
+        );
+        if let DataType::Map(_, keys_sorted) = list_field.data_type() {
+            Ok(Box::new(scheduler.with_map_type(keys_sorted)))
+        } else {
+            Ok(Box::new(scheduler))
+        }
     }
 
     fn unwrap_blob(column_info: &ColumnInfo) -> Option<ColumnInfo> {
@@ -820,7 +829,7 @@ impl CoreFieldDecoderStrategy {
                         as Box<dyn StructuralFieldScheduler>,
                 )
             }
-            DataType::Binary | DataType::Utf8 => {
+            DataType::Binary | DataType::Utf8 | DataType::BinaryView | DataType::Utf8View => {
                 let column_info = column_infos.expect_next()?;
                 let scheduler = Box::new(StructuralPrimitiveFieldScheduler::try_new(
                     column_info.as_ref(),
@@ -829,7 +838,7 @@ impl CoreFieldDecoderStrategy {
                 column_infos.next_top_level();
                 Ok(scheduler)
             }
-            DataType::List(_) | DataType::LargeList(_) => {
+            DataType::List(_) | DataType::LargeList(_) | DataType::Map(_, _) => {
                 let child = field
                     .children
                     .first()
@@ -854,7 +863,9 @@ impl CoreFieldDecoderStrategy {
             let column_info = column_infos.expect_next()?;
             let scheduler = self.create_primitive_scheduler(field, column_info, buffers)?;
             return Ok(scheduler);
-        } else if data_type.is_binary_like() {
+        } else if data_type.is_binary_like()
+            || matches!(data_type, DataType::Utf8View | DataType::BinaryView)
+        {
             let column_info = column_infos.next().unwrap().clone();
             // Column is blob and user is asking for binary data
             if let Some(blob_col) = Self::unwrap_blob(column_info.as_ref()) {
@@ -870,7 +881,13 @@ impl CoreFieldDecoderStrategy {
                         array_encoding: Some(pb::array_encoding::ArrayEncoding::List(..))
                     }
                 ) {
-                    let list_type = if matches!(data_type, DataType::Utf8 | DataType::Binary) {
+                    let list_type = if matches!(
+                        data_type,
+                        DataType::Utf8
+                            | DataType::Binary
+                            | DataType::Utf8View
+                            | DataType::BinaryView
+                    ) {
                         DataType::List(Arc::new(ArrowField::new("item", DataType::UInt8, false)))
                     } else {
                         DataType::LargeList(Arc::new(ArrowField::new(
@@ -935,7 +952,7 @@ impl CoreFieldDecoderStrategy {
                     })
                 }
             }
-            DataType::List(_) | DataType::LargeList(_) => {
+            DataType::List(_) | DataType::LargeList(_) | DataType::Map(_, _) => {
                 let offsets_column = column_infos.expect_next()?.clone();
                 column_infos.next_top_level();
                 self.create_list_scheduler(field, column_infos, buffers, &offsets_column)
@@ -976,6 +993,38 @@ impl CoreFieldDecoderStrategy {
                     )))
                 }
             }
+            DataType::Union(union_fields, _) => {
+                let type_ids_column = column_infos.expect_next()?.clone();
+                let type_ids_field = Field::try_from(ArrowField::new(
+                    UNION_TYPE_IDS_FIELD_NAME,
+                    DataType::Int8,
+                    false,
+                ))?;
+                let type_ids_scheduler =
+                    self.create_primitive_scheduler(&type_ids_field, &type_ids_column, buffers)?;
+                let num_rows = type_ids_column
+                    .page_infos
+                    .iter()
+                    .map(|page| page.num_rows)
+                    .sum();
+                let mut child_schedulers = Vec::with_capacity(field.children.len() + 1);
+                child_schedulers.push(Arc::from(type_ids_scheduler));
+                for field in &field.children {
+                    column_infos.next_top_level();
+                    let field_scheduler =
+                        self.create_legacy_field_scheduler(field, column_infos, buffers)?;
+                    child_schedulers.push(Arc::from(field_scheduler));
+                }
+                let storage_scheduler = Arc::new(SimpleStructScheduler::new(
+                    child_schedulers,
+                    union_storage_fields(union_fields),
+                    num_rows,
+                ));
+                Ok(Box::new(UnionFieldScheduler::new(
+                    storage_scheduler,
+                    data_type.clone(),
+                )))
+            }
             // TODO: Still need support for RLE
             _ => todo!(),
         }
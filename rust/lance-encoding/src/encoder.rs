@@ -5,7 +5,7 @@ use std::{collections::HashMap, env, sync::Arc};
 use arrow::array::AsArray;
 use arrow::datatypes::UInt64Type;
 use arrow_array::{Array, ArrayRef, RecordBatch, UInt8Array};
-use arrow_schema::DataType;
+use arrow_schema::{DataType, Field as ArrowField};
 use bytes::{Bytes, BytesMut};
 use futures::future::BoxFuture;
 use lance_core::datatypes::{
@@ -24,6 +24,7 @@ use crate::encodings::logical::list::ListStructuralEncoder;
 use crate::encodings::logical::primitive::PrimitiveStructuralEncoder;
 use crate::encodings::logical::r#struct::StructFieldEncoder;
 use crate::encodings::logical::r#struct::StructStructuralEncoder;
+use crate::encodings::logical::union::{UnionFieldEncoder, UNION_TYPE_IDS_FIELD_NAME};
 use crate::encodings::physical::binary::{BinaryMiniBlockEncoder, VariableEncoder};
 use crate::encodings::physical::bitpack_fastlanes::BitpackedForNonNegArrayEncoder;
 use crate::encodings::physical::bitpack_fastlanes::{
@@ -607,6 +608,9 @@ impl CoreArrayEncodingStrategy {
                     Self::default_binary_encoder(arrays, data_type, field_meta, data_size, version)
                 }
             }
+            DataType::Utf8View | DataType::BinaryView => {
+                Self::default_binary_encoder(arrays, data_type, field_meta, data_size, version)
+            }
             DataType::Struct(fields) => {
                 let num_fields = fields.len();
                 let mut inner_encoders = Vec::new();
@@ -1091,7 +1095,9 @@ impl CoreFieldEncodingStrategy {
                 | DataType::Binary
                 | DataType::LargeBinary
                 | DataType::Utf8
-                | DataType::LargeUtf8,
+                | DataType::LargeUtf8
+                | DataType::BinaryView
+                | DataType::Utf8View,
         )
     }
 }
@@ -1129,7 +1135,7 @@ impl FieldEncodingStrategy for CoreFieldEncodingStrategy {
             }
         } else {
             match data_type {
-                DataType::List(_child) | DataType::LargeList(_child) => {
+                DataType::List(_) | DataType::LargeList(_) | DataType::Map(_, _) => {
                     let list_idx = column_index.next_column_index(field.id as u32);
                     let inner_encoding = encoding_strategy_root.create_field_encoder(
                         encoding_strategy_root,
@@ -1198,6 +1204,36 @@ impl FieldEncodingStrategy for CoreFieldEncodingStrategy {
                         Err(Error::NotSupported { source: format!("cannot encode a dictionary column whose value type is a logical type ({})", value_type).into(), location: location!() })
                     }
                 }
+                DataType::Union(union_fields, _) => {
+                    let type_ids_field = Field::try_from(ArrowField::new(
+                        UNION_TYPE_IDS_FIELD_NAME,
+                        DataType::Int8,
+                        false,
+                    ))?;
+                    let type_ids_encoder = Box::new(PrimitiveFieldEncoder::try_new(
+                        options,
+                        self.array_encoding_strategy.clone(),
+                        column_index.next_column_index(field.id as u32),
+                        type_ids_field,
+                    )?);
+                    let children_encoders = field
+                        .children
+                        .iter()
+                        .map(|field| {
+                            self.create_field_encoder(
+                                encoding_strategy_root,
+                                field,
+                                column_index,
+                                options,
+                            )
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(Box::new(UnionFieldEncoder::new(
+                        type_ids_encoder,
+                        children_encoders,
+                        union_fields,
+                    )))
+                }
                 _ => todo!("Implement encoding for field {}", field),
             }
         }
@@ -1254,7 +1290,9 @@ impl StructuralEncodingStrategy {
                 | DataType::Binary
                 | DataType::LargeBinary
                 | DataType::Utf8
-                | DataType::LargeUtf8,
+                | DataType::LargeUtf8
+                | DataType::BinaryView
+                | DataType::Utf8View,
         )
     }
 
@@ -1277,7 +1315,7 @@ impl StructuralEncodingStrategy {
             )?))
         } else {
             match data_type {
-                DataType::List(_) | DataType::LargeList(_) => {
+                DataType::List(_) | DataType::LargeList(_) | DataType::Map(_, _) => {
                     let child = field.children.first().expect("List should have a child");
                     let child_encoder = self.do_create_field_encoder(
                         _encoding_strategy_root,
@@ -1339,6 +1377,14 @@ impl StructuralEncodingStrategy {
                         Err(Error::NotSupported { source: format!("cannot encode a dictionary column whose value type is a logical type ({})", value_type).into(), location: location!() })
                     }
                 }
+                DataType::Union(_, _) => Err(Error::NotSupported {
+                    source: format!(
+                        "union fields are only supported in the 2.0 file format (field {})",
+                        field.name
+                    )
+                    .into(),
+                    location: location!(),
+                }),
                 _ => todo!("Implement encoding for field {}", field),
             }
         }
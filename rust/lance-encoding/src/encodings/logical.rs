@@ -6,3 +6,4 @@ pub mod blob;
 pub mod list;
 pub mod primitive;
 pub mod r#struct;
+pub mod union;
@@ -176,6 +176,14 @@ impl DecodeArrayTask for BinaryArrayDecoder {
             )),
             DataType::Utf8 => Ok(Self::from_list_array::<Utf8Type>(arr.as_list::<i32>())),
             DataType::LargeUtf8 => Ok(Self::from_list_array::<LargeUtf8Type>(arr.as_list::<i64>())),
+            DataType::BinaryView | DataType::Utf8View => {
+                let offsets_arr = if data_type == DataType::BinaryView {
+                    Self::from_list_array::<BinaryType>(arr.as_list::<i32>())
+                } else {
+                    Self::from_list_array::<Utf8Type>(arr.as_list::<i32>())
+                };
+                Ok(arrow_cast::cast(&offsets_arr, &data_type)?)
+            }
             _ => panic!("Binary decoder does not support this data type"),
         }
     }
@@ -7,7 +7,8 @@ use arrow_array::{
     cast::AsArray,
     new_empty_array,
     types::{Int32Type, Int64Type, UInt64Type},
-    Array, ArrayRef, BooleanArray, Int32Array, Int64Array, LargeListArray, ListArray, UInt64Array,
+    Array, ArrayRef, BooleanArray, Int32Array, Int64Array, LargeListArray, ListArray, MapArray,
+    UInt64Array,
 };
 use arrow_buffer::{BooleanBuffer, BooleanBufferBuilder, Buffer, NullBuffer, OffsetBuffer};
 use arrow_schema::{DataType, Field, Fields};
@@ -41,6 +42,35 @@ use crate::{
 
 use super::{primitive::AccumulationQueue, r#struct::SimpleStructDecoder};
 
+/// Maps are encoded as lists of their entries.  This reinterprets a map array as
+/// a list array and leaves any other array unchanged.
+pub(crate) fn map_to_list(array: ArrayRef) -> ArrayRef {
+    match array.data_type() {
+        DataType::Map(entries_field, _) => {
+            let map_arr = array.as_map();
+            Arc::new(ListArray::new(
+                entries_field.clone(),
+                map_arr.offsets().clone(),
+                Arc::new(map_arr.entries().clone()),
+                map_arr.nulls().cloned(),
+            ))
+        }
+        _ => array,
+    }
+}
+
+/// Restore the map semantics of a list of map entries
+fn list_to_map(list_arr: ListArray, keys_sorted: bool) -> Result<MapArray> {
+    let (entries_field, offsets, entries, nulls) = list_arr.into_parts();
+    Ok(MapArray::try_new(
+        entries_field,
+        offsets,
+        entries.as_struct().clone(),
+        nulls,
+        keys_sorted,
+    )?)
+}
+
 // Scheduling lists is tricky.  Imagine the following scenario:
 //
 // * There are 2000 offsets per offsets page
@@ -557,6 +587,13 @@ impl ListFieldScheduler {
             list_type,
         }
     }
+
+    /// Decode the lists as maps, the items must be the map entries
+    pub fn with_map_type(mut self, keys_sorted: bool) -> Self {
+        debug_assert_eq!(self.offset_type, DataType::Int32);
+        self.list_type = DataType::Map(self.items_field.clone(), keys_sorted);
+        self
+    }
 }
 
 impl FieldScheduler for ListFieldScheduler {
@@ -616,6 +653,7 @@ struct ListDecodeTask {
     items: Option<Box<dyn DecodeArrayTask>>,
     items_field: Arc<Field>,
     offset_type: DataType,
+    data_type: DataType,
 }
 
 impl DecodeArrayTask for ListDecodeTask {
@@ -650,12 +688,13 @@ impl DecodeArrayTask for ListDecodeTask {
                 let offsets_i32 = offsets.as_primitive::<Int32Type>();
                 let offsets = OffsetBuffer::new(offsets_i32.values().clone());
 
-                Ok(Arc::new(ListArray::try_new(
-                    self.items_field.clone(),
-                    offsets,
-                    items,
-                    validity,
-                )?))
+                let list_arr =
+                    ListArray::try_new(self.items_field.clone(), offsets, items, validity)?;
+                if let DataType::Map(_, keys_sorted) = &self.data_type {
+                    Ok(Arc::new(list_to_map(list_arr, *keys_sorted)?))
+                } else {
+                    Ok(Arc::new(list_arr))
+                }
             }
             DataType::Int64 => {
                 let offsets = arrow_cast::cast(&offsets, &DataType::Int64)?;
@@ -794,6 +833,7 @@ impl LogicalPageDecoder for ListPageDecoder {
                 items_field: self.items_field.clone(),
                 items: item_decode,
                 offset_type: self.offset_type.clone(),
+                data_type: self.data_type.clone(),
             }) as Box<dyn DecodeArrayTask>,
         })
     }
@@ -1184,6 +1224,7 @@ impl FieldEncoder for ListFieldEncoder {
         row_number: u64,
         num_rows: u64,
     ) -> Result<Vec<EncodeTask>> {
+        let array = map_to_list(array);
         // The list may have an offset / shorter length which means the underlying
         // values array could be longer than what we need to encode and so we need
         // to slice down to the region of interest.
@@ -1290,6 +1331,7 @@ impl FieldEncoder for ListStructuralEncoder {
         row_number: u64,
         num_rows: u64,
     ) -> Result<Vec<EncodeTask>> {
+        let array = map_to_list(array);
         let values = if let Some(list_arr) = array.as_list_opt::<i32>() {
             let has_garbage_values = if self.keep_original_array {
                 repdef.add_offsets(list_arr.offsets().clone(), array.nulls().cloned())
@@ -1448,6 +1490,15 @@ impl StructuralDecodeArrayTask for StructuralListDecodeTask {
                     repdef,
                 })
             }
+            DataType::Map(entries_field, keys_sorted) => {
+                let (offsets, validity) = repdef.unravel_offsets::<i32>()?;
+                let list_array =
+                    ListArray::try_new(entries_field.clone(), offsets, array, validity)?;
+                Ok(DecodedArray {
+                    array: Arc::new(list_to_map(list_array, *keys_sorted)?),
+                    repdef,
+                })
+            }
             DataType::LargeList(child_field) => {
                 let (offsets, validity) = repdef.unravel_offsets::<i64>()?;
                 let list_array =
@@ -1467,7 +1518,7 @@ mod tests {
 
     use std::{collections::HashMap, sync::Arc};
 
-    use arrow::array::{Int64Builder, LargeListBuilder, StringBuilder};
+    use arrow::array::{Int64Builder, LargeListBuilder, MapBuilder, StringBuilder};
     use arrow_array::{
         builder::{Int32Builder, ListBuilder},
         Array, ArrayRef, BooleanArray, DictionaryArray, LargeStringArray, ListArray, StructArray,
@@ -1611,6 +1662,34 @@ mod tests {
             .await;
     }
 
+    #[rstest]
+    #[test_log::test(tokio::test)]
+    async fn test_simple_map(
+        #[values(LanceFileVersion::V2_0, LanceFileVersion::V2_1)] version: LanceFileVersion,
+    ) {
+        let mut map_builder = MapBuilder::new(None, StringBuilder::new(), Int32Builder::new());
+        map_builder.keys().append_value("a");
+        map_builder.values().append_value(1);
+        map_builder.keys().append_value("b");
+        map_builder.values().append_null();
+        map_builder.append(true).unwrap();
+        map_builder.append(false).unwrap();
+        map_builder.append(true).unwrap();
+        map_builder.keys().append_value("c");
+        map_builder.values().append_value(3);
+        map_builder.append(true).unwrap();
+        let map_array = map_builder.finish();
+
+        let test_cases = TestCases::default()
+            .with_range(0..2)
+            .with_range(1..4)
+            .with_indices(vec![0, 3])
+            .with_indices(vec![1])
+            .with_file_version(version);
+        check_round_trip_encoding_of_data(vec![Arc::new(map_array)], &test_cases, HashMap::new())
+            .await;
+    }
+
     #[rstest]
     #[test_log::test(tokio::test)]
     async fn test_simple_nested_list_ends_with_null(
@@ -711,7 +711,9 @@ impl StructuralStructDecoder {
                     Box::new(Self::new(fields.clone(), should_validate, false))
                 }
             }
-            DataType::List(child_field) | DataType::LargeList(child_field) => {
+            DataType::List(child_field)
+            | DataType::LargeList(child_field)
+            | DataType::Map(child_field, _) => {
                 let child_decoder = Self::field_to_decoder(child_field, should_validate);
                 Box::new(StructuralListDecoder::new(
                     child_decoder,
@@ -720,7 +722,6 @@ impl StructuralStructDecoder {
             }
             DataType::RunEndEncoded(_, _) => todo!(),
             DataType::ListView(_) | DataType::LargeListView(_) => todo!(),
-            DataType::Map(_, _) => todo!(),
             DataType::Union(_, _) => todo!(),
             _ => Box::new(StructuralPrimitiveFieldDecoder::new(field, should_validate)),
         }
@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Unions are stored like a struct.  The union field owns a column of type ids
+//! and each variant is stored as a child column with one value per row.  This
+//! is the layout of a sparse union.  Dense unions are converted to sparse
+//! unions on write and back to dense unions on read.
+
+use std::sync::Arc;
+
+use arrow_array::{
+    cast::AsArray, types::Int8Type, Array, ArrayRef, BooleanArray, Int8Array, UInt32Array,
+    UnionArray,
+};
+use arrow_buffer::ScalarBuffer;
+use arrow_schema::{DataType, Field, Fields, UnionFields, UnionMode};
+use futures::{future::BoxFuture, stream::FuturesOrdered, FutureExt, StreamExt};
+use lance_core::{Error, Result};
+use log::trace;
+use snafu::location;
+
+use crate::{
+    decoder::{
+        DecodeArrayTask, DecoderReady, FieldScheduler, FilterExpression, LogicalPageDecoder,
+        MessageType, NextDecodeTask, PriorityRange, ScheduledScanLine, SchedulerContext,
+        SchedulingJob,
+    },
+    encoder::{EncodeTask, EncodedColumn, FieldEncoder, OutOfLineBuffers},
+    repdef::RepDefBuilder,
+};
+
+/// The name of the type ids column in the storage struct of a union
+pub const UNION_TYPE_IDS_FIELD_NAME: &str = "type_ids";
+
+/// The fields of the struct a union is stored as
+///
+/// The variants are always nullable since a row only has a value for the
+/// variant that is selected by its type id.
+pub fn union_storage_fields(union_fields: &UnionFields) -> Fields {
+    std::iter::once(Arc::new(Field::new(
+        UNION_TYPE_IDS_FIELD_NAME,
+        DataType::Int8,
+        false,
+    )))
+    .chain(
+        union_fields
+            .iter()
+            .map(|(_, field)| Arc::new(field.as_ref().clone().with_nullable(true))),
+    )
+    .collect()
+}
+
+/// Split a union array into its type ids and one array per variant with the
+/// same length as the union
+fn union_to_sparse(
+    array: &UnionArray,
+    union_fields: &UnionFields,
+) -> Result<(ArrayRef, Vec<ArrayRef>)> {
+    let type_ids = Arc::new(Int8Array::new(array.type_ids().clone(), None)) as ArrayRef;
+    let children = union_fields
+        .iter()
+        .map(|(type_id, _)| {
+            let child = array.child(type_id);
+            if array.offsets().is_none() {
+                return Ok(child.clone());
+            }
+            // Dense union, spread the values of the variant over all the rows
+            let indices = (0..array.len())
+                .map(|row| (array.type_id(row) == type_id).then(|| array.value_offset(row) as u32))
+                .collect::<UInt32Array>();
+            Ok(arrow_select::take::take(child.as_ref(), &indices, None)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((type_ids, children))
+}
+
+/// Rebuild a union array from the columns of its storage struct
+fn sparse_to_union(columns: &[ArrayRef], data_type: &DataType) -> Result<ArrayRef> {
+    let DataType::Union(union_fields, mode) = data_type else {
+        return Err(Error::Internal {
+            message: format!("Expected a union type but got {}", data_type),
+            location: location!(),
+        });
+    };
+    let type_ids = columns[0].as_primitive::<Int8Type>().values().clone();
+    let children = &columns[1..];
+    let union_arr = match mode {
+        UnionMode::Sparse => {
+            UnionArray::try_new(union_fields.clone(), type_ids, None, children.to_vec())?
+        }
+        UnionMode::Dense => {
+            let mut counts = [0_i32; 128];
+            let offsets = type_ids
+                .iter()
+                .map(|type_id| {
+                    let count = &mut counts[*type_id as usize];
+                    *count += 1;
+                    *count - 1
+                })
+                .collect::<ScalarBuffer<i32>>();
+            let children = union_fields
+                .iter()
+                .zip(children)
+                .map(|((type_id, _), child)| {
+                    let selected = type_ids
+                        .iter()
+                        .map(|id| Some(*id == type_id))
+                        .collect::<BooleanArray>();
+                    Ok(arrow_select::filter::filter(child.as_ref(), &selected)?)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            UnionArray::try_new(union_fields.clone(), type_ids, Some(offsets), children)?
+        }
+    };
+    Ok(Arc::new(union_arr))
+}
+
+/// Encodes a union as a column of type ids followed by a column per variant
+pub struct UnionFieldEncoder {
+    type_ids_encoder: Box<dyn FieldEncoder>,
+    children: Vec<Box<dyn FieldEncoder>>,
+    union_fields: UnionFields,
+}
+
+impl UnionFieldEncoder {
+    pub fn new(
+        type_ids_encoder: Box<dyn FieldEncoder>,
+        children: Vec<Box<dyn FieldEncoder>>,
+        union_fields: UnionFields,
+    ) -> Self {
+        Self {
+            type_ids_encoder,
+            children,
+            union_fields,
+        }
+    }
+}
+
+impl FieldEncoder for UnionFieldEncoder {
+    fn maybe_encode(
+        &mut self,
+        array: ArrayRef,
+        external_buffers: &mut OutOfLineBuffers,
+        repdef: RepDefBuilder,
+        row_number: u64,
+        num_rows: u64,
+    ) -> Result<Vec<EncodeTask>> {
+        let (type_ids, children) = union_to_sparse(array.as_union(), &self.union_fields)?;
+        let mut tasks = self.type_ids_encoder.maybe_encode(
+            type_ids,
+            external_buffers,
+            repdef.clone(),
+            row_number,
+            num_rows,
+        )?;
+        for (encoder, child) in self.children.iter_mut().zip(children) {
+            tasks.extend(encoder.maybe_encode(
+                child,
+                external_buffers,
+                repdef.clone(),
+                row_number,
+                num_rows,
+            )?);
+        }
+        Ok(tasks)
+    }
+
+    fn flush(&mut self, external_buffers: &mut OutOfLineBuffers) -> Result<Vec<EncodeTask>> {
+        let mut tasks = self.type_ids_encoder.flush(external_buffers)?;
+        for encoder in self.children.iter_mut() {
+            tasks.extend(encoder.flush(external_buffers)?);
+        }
+        Ok(tasks)
+    }
+
+    fn num_columns(&self) -> u32 {
+        self.type_ids_encoder.num_columns()
+            + self
+                .children
+                .iter()
+                .map(|child| child.num_columns())
+                .sum::<u32>()
+    }
+
+    fn finish(
+        &mut self,
+        external_buffers: &mut OutOfLineBuffers,
+    ) -> BoxFuture<'_, Result<Vec<EncodedColumn>>> {
+        let mut columns = std::iter::once(&mut self.type_ids_encoder)
+            .chain(self.children.iter_mut())
+            .map(|encoder| encoder.finish(external_buffers))
+            .collect::<FuturesOrdered<_>>();
+        async move {
+            let mut all_columns = Vec::new();
+            while let Some(encoded) = columns.next().await {
+                all_columns.extend(encoded?);
+            }
+            Ok(all_columns)
+        }
+        .boxed()
+    }
+}
+
+/// Wraps the scheduler of the storage struct and converts the decoded structs
+/// into unions
+#[derive(Debug)]
+pub struct UnionFieldScheduler {
+    storage_scheduler: Arc<dyn FieldScheduler>,
+    data_type: DataType,
+}
+
+impl UnionFieldScheduler {
+    pub fn new(storage_scheduler: Arc<dyn FieldScheduler>, data_type: DataType) -> Self {
+        Self {
+            storage_scheduler,
+            data_type,
+        }
+    }
+}
+
+impl FieldScheduler for UnionFieldScheduler {
+    fn schedule_ranges<'a>(
+        &'a self,
+        ranges: &[std::ops::Range<u64>],
+        filter: &FilterExpression,
+    ) -> Result<Box<dyn SchedulingJob + 'a>> {
+        trace!("Scheduling union for {} ranges", ranges.len());
+        let storage_job = self.storage_scheduler.schedule_ranges(ranges, filter)?;
+        Ok(Box::new(UnionSchedulingJob {
+            scheduler: self,
+            inner: storage_job,
+            initialized: false,
+        }))
+    }
+
+    fn num_rows(&self) -> u64 {
+        self.storage_scheduler.num_rows()
+    }
+
+    fn initialize<'a>(
+        &'a self,
+        _filter: &'a FilterExpression,
+        _context: &'a SchedulerContext,
+    ) -> BoxFuture<'a, Result<()>> {
+        // 2.0 schedulers do not need to initialize
+        std::future::ready(Ok(())).boxed()
+    }
+}
+
+#[derive(Debug)]
+struct UnionSchedulingJob<'a> {
+    scheduler: &'a UnionFieldScheduler,
+    inner: Box<dyn SchedulingJob + 'a>,
+    initialized: bool,
+}
+
+impl SchedulingJob for UnionSchedulingJob<'_> {
+    fn schedule_next(
+        &mut self,
+        context: &mut SchedulerContext,
+        priority: &dyn PriorityRange,
+    ) -> Result<ScheduledScanLine> {
+        let inner_scan = self.inner.schedule_next(context, priority)?;
+        // The first decoder of the storage struct is the struct decoder itself, the rest
+        // are decoders for its children which are routed through the struct decoder
+        let mut wrapped_decoders = Vec::with_capacity(inner_scan.decoders.len());
+        for message in inner_scan.decoders {
+            if self.initialized {
+                wrapped_decoders.push(message);
+                continue;
+            }
+            let decoder = message.into_legacy();
+            wrapped_decoders.push(MessageType::DecoderReady(DecoderReady {
+                path: decoder.path,
+                decoder: Box::new(UnionPageDecoder {
+                    inner: decoder.decoder,
+                    data_type: self.scheduler.data_type.clone(),
+                }),
+            }));
+            self.initialized = true;
+        }
+        Ok(ScheduledScanLine {
+            decoders: wrapped_decoders,
+            rows_scheduled: inner_scan.rows_scheduled,
+        })
+    }
+
+    fn num_rows(&self) -> u64 {
+        self.inner.num_rows()
+    }
+}
+
+#[derive(Debug)]
+struct UnionPageDecoder {
+    inner: Box<dyn LogicalPageDecoder>,
+    data_type: DataType,
+}
+
+impl LogicalPageDecoder for UnionPageDecoder {
+    fn accept_child(&mut self, child: DecoderReady) -> Result<()> {
+        self.inner.accept_child(child)
+    }
+
+    fn wait_for_loaded(&mut self, num_rows: u64) -> BoxFuture<Result<()>> {
+        self.inner.wait_for_loaded(num_rows)
+    }
+
+    fn drain(&mut self, num_rows: u64) -> Result<NextDecodeTask> {
+        let inner_task = self.inner.drain(num_rows)?;
+        Ok(NextDecodeTask {
+            num_rows: inner_task.num_rows,
+            task: Box::new(UnionArrayDecoder {
+                inner: inner_task.task,
+                data_type: self.data_type.clone(),
+            }),
+        })
+    }
+
+    fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    fn rows_loaded(&self) -> u64 {
+        self.inner.rows_loaded()
+    }
+
+    fn num_rows(&self) -> u64 {
+        self.inner.num_rows()
+    }
+
+    fn rows_drained(&self) -> u64 {
+        self.inner.rows_drained()
+    }
+}
+
+struct UnionArrayDecoder {
+    inner: Box<dyn DecodeArrayTask>,
+    data_type: DataType,
+}
+
+impl DecodeArrayTask for UnionArrayDecoder {
+    fn decode(self: Box<Self>) -> Result<ArrayRef> {
+        let storage = self.inner.decode()?;
+        sparse_to_union(storage.as_struct().columns(), &self.data_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Float64Array, Int32Array, StringArray, UnionArray};
+    use arrow_buffer::ScalarBuffer;
+    use arrow_schema::{DataType, Field, UnionFields};
+
+    use crate::{
+        testing::{check_round_trip_encoding_of_data, TestCases},
+        version::LanceFileVersion,
+    };
+
+    fn union_fields() -> UnionFields {
+        UnionFields::new(
+            vec![0, 5, 7],
+            vec![
+                Field::new("int", DataType::Int32, true),
+                Field::new("float", DataType::Float64, false),
+                Field::new("str", DataType::Utf8, true),
+            ],
+        )
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_sparse_union() {
+        let type_ids = ScalarBuffer::from(vec![0_i8, 5, 7, 7, 0]);
+        let children = vec![
+            Arc::new(Int32Array::from(vec![Some(1), None, None, None, None])) as ArrayRef,
+            Arc::new(Float64Array::from(vec![0.0, 2.5, 0.0, 0.0, 0.0])),
+            Arc::new(StringArray::from(vec![
+                None,
+                None,
+                Some("abc"),
+                Some("d"),
+                None,
+            ])),
+        ];
+        let array = UnionArray::try_new(union_fields(), type_ids, None, children).unwrap();
+        let test_cases = TestCases::default()
+            .with_range(0..3)
+            .with_range(1..5)
+            .with_indices(vec![1, 3, 4])
+            .with_file_version(LanceFileVersion::V2_0);
+        check_round_trip_encoding_of_data(vec![Arc::new(array)], &test_cases, Default::default())
+            .await;
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_dense_union() {
+        let type_ids = ScalarBuffer::from(vec![7_i8, 0, 5, 7, 0, 0]);
+        let offsets = ScalarBuffer::from(vec![0_i32, 0, 0, 1, 1, 2]);
+        let children = vec![
+            Arc::new(Int32Array::from(vec![Some(1), None, Some(3)])) as ArrayRef,
+            Arc::new(Float64Array::from(vec![2.5])),
+            Arc::new(StringArray::from(vec![Some("abc"), None])),
+        ];
+        let array = UnionArray::try_new(union_fields(), type_ids, Some(offsets), children).unwrap();
+        let test_cases = TestCases::default()
+            .with_range(0..3)
+            .with_range(2..6)
+            .with_indices(vec![0, 3, 5])
+            .with_file_version(LanceFileVersion::V2_0);
+        check_round_trip_encoding_of_data(vec![Arc::new(array)], &test_cases, Default::default())
+            .await;
+    }
+}
@@ -278,7 +278,7 @@ pub fn decoder_from_array_encoding(
             let bytes_scheduler = decoder_from_array_encoding(bytes_encoding, buffers, data_type);
             let bytes_per_offset = match data_type {
                 DataType::LargeBinary | DataType::LargeUtf8 => 8,
-                DataType::Binary | DataType::Utf8 => 4,
+                DataType::Binary | DataType::Utf8 | DataType::BinaryView | DataType::Utf8View => 4,
                 _ => panic!("FixedSizeBinary only supports binary and utf8 types"),
             };
 
@@ -374,7 +374,10 @@ impl BinaryEncoder {
     // In 2.1 we will materialize nulls higher up (in the primitive encoder).  Unfortunately,
     // in 2.0 we actually need to write the offsets.
     fn all_null_variable_width(data_type: &DataType, num_values: u64) -> VariableWidthBlock {
-        if matches!(data_type, DataType::Binary | DataType::Utf8) {
+        if matches!(
+            data_type,
+            DataType::Binary | DataType::Utf8 | DataType::BinaryView | DataType::Utf8View
+        ) {
             VariableWidthBlock {
                 bits_per_offset: 32,
                 data: LanceBuffer::empty(),
@@ -845,7 +848,7 @@ impl BlockDecompressor for BinaryBlockDecompressor {
 pub mod tests {
     use arrow_array::{
         builder::{LargeStringBuilder, StringBuilder},
-        ArrayRef, StringArray,
+        ArrayRef, StringArray, StringViewArray,
     };
     use arrow_schema::{DataType, Field};
 
@@ -959,7 +962,13 @@ pub mod tests {
         #[values(LanceFileVersion::V2_0, LanceFileVersion::V2_1)] version: LanceFileVersion,
         #[values(STRUCTURAL_ENCODING_MINIBLOCK, STRUCTURAL_ENCODING_FULLZIP)]
         structural_encoding: &str,
-        #[values(DataType::Utf8, DataType::Binary)] data_type: DataType,
+        #[values(
+            DataType::Utf8,
+            DataType::Binary,
+            DataType::Utf8View,
+            DataType::BinaryView
+        )]
+        data_type: DataType,
     ) {
         use lance_core::datatypes::STRUCTURAL_ENCODING_META_KEY;
 
@@ -986,6 +995,43 @@ pub mod tests {
         .await;
     }
 
+    #[rstest]
+    #[test_log::test(tokio::test)]
+    async fn test_utf8_view(
+        #[values(LanceFileVersion::V2_0, LanceFileVersion::V2_1)] version: LanceFileVersion,
+        #[values(STRUCTURAL_ENCODING_MINIBLOCK, STRUCTURAL_ENCODING_FULLZIP)]
+        structural_encoding: &str,
+    ) {
+        // Mix of values that are inlined in the view and values that are not
+        let string_array = StringViewArray::from(vec![
+            Some("a string that is too long to inline"),
+            Some("short"),
+            None,
+            Some(""),
+            Some("exactly12byt"),
+            Some("thirteen byte"),
+        ]);
+        let string_array = string_array.slice(1, 5);
+
+        let mut field_metadata = HashMap::new();
+        field_metadata.insert(
+            STRUCTURAL_ENCODING_META_KEY.to_string(),
+            structural_encoding.into(),
+        );
+
+        let test_cases = TestCases::default()
+            .with_range(0..2)
+            .with_range(1..5)
+            .with_indices(vec![0, 3, 4])
+            .with_file_version(version);
+        check_round_trip_encoding_of_data(
+            vec![Arc::new(string_array)],
+            &test_cases,
+            field_metadata,
+        )
+        .await;
+    }
+
     #[rstest]
     #[test_log::test(tokio::test)]
     async fn test_sliced_utf8(
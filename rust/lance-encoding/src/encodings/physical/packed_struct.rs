@@ -263,6 +263,7 @@ pub mod tests {
         Array, ArrayRef, FixedSizeListArray, Int32Array, StructArray, UInt64Array, UInt8Array,
     };
     use arrow_schema::{DataType, Field, Fields};
+    use lance_arrow::sparse::{sparse_vector_field, SparseVector, SparseVectorArray};
     use std::{collections::HashMap, sync::Arc, vec};
 
     use crate::{
@@ -349,6 +350,33 @@ pub mod tests {
         .await;
     }
 
+    #[rstest]
+    #[test_log::test(tokio::test)]
+    async fn test_sparse_vector_packed_struct(
+        #[values(LanceFileVersion::V2_0, LanceFileVersion::V2_1)] version: LanceFileVersion,
+    ) {
+        let vectors = (0..100)
+            .map(|i: u32| {
+                (i % 7 != 3).then(|| {
+                    SparseVector::from_pairs((0..i % 5).map(|j| (i * 13 + j * 101, j as f32 + 0.5)))
+                })
+            })
+            .collect::<SparseVectorArray>();
+        let field = sparse_vector_field("", true);
+
+        let test_cases = TestCases::default()
+            .with_range(0..10)
+            .with_range(40..90)
+            .with_indices(vec![0, 3, 17, 99])
+            .with_file_version(version);
+        check_round_trip_encoding_of_data(
+            vec![vectors.into()],
+            &test_cases,
+            field.metadata().clone(),
+        )
+        .await;
+    }
+
     // the current Lance V2.1 `packed-struct encoding` doesn't support `fixed size list`.
     // the current Lance V2.0 test is disabled for now as we don't have statistics for `FixedSizeList`
     #[rstest]
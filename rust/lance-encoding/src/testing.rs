@@ -91,7 +91,7 @@ fn column_indices_from_schema_helper(
                     is_structural_encoding,
                 );
             }
-            DataType::List(inner) => {
+            DataType::List(inner) | DataType::Map(inner, _) => {
                 if !is_structural_encoding {
                     column_indices.push(*column_counter);
                     *column_counter += 1;
@@ -115,6 +115,21 @@ fn column_indices_from_schema_helper(
                     is_structural_encoding,
                 );
             }
+            DataType::Union(fields, _) => {
+                // Unions are only supported in the old style and the type ids get a column
+                column_indices.push(*column_counter);
+                *column_counter += 1;
+                let children = fields
+                    .iter()
+                    .map(|(_, field)| field.clone())
+                    .collect::<Vec<_>>();
+                column_indices_from_schema_helper(
+                    &children,
+                    column_indices,
+                    column_counter,
+                    is_structural_encoding,
+                );
+            }
             DataType::FixedSizeList(inner, _) => {
                 // FSL(primitive) does not get its own column in either approach
                 column_indices_from_schema_helper(
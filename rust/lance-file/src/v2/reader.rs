@@ -309,6 +309,17 @@ impl ReaderProjection {
             column_indices,
         })
     }
+
+    /// Reads string and binary columns as `Utf8View` / `BinaryView` arrays
+    ///
+    /// The views are built directly over the decoded pages, which is cheaper than
+    /// casting the offset based arrays after the read.  Blob columns are not changed.
+    pub fn with_view_types(self) -> Self {
+        Self {
+            schema: Arc::new(self.schema.with_view_types()),
+            column_indices: self.column_indices,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -560,6 +571,10 @@ impl FileReader {
             metadata: pb_schema.metadata,
         };
         let schema = lance_core::datatypes::Schema::from(fields_with_meta);
+        schema
+            .fields
+            .iter()
+            .try_for_each(|field| field.validate_logical_types())?;
         Ok((num_rows, schema))
     }
 
@@ -1524,8 +1539,10 @@ pub mod tests {
     use std::{collections::BTreeMap, pin::Pin, sync::Arc};
 
     use arrow_array::{
+        builder::{Float32Builder, MapBuilder, StringBuilder},
+        cast::AsArray,
         types::{Float64Type, Int32Type},
-        RecordBatch, UInt32Array,
+        ArrayRef, Int32Array, RecordBatch, RecordBatchIterator, UInt32Array,
     };
     use arrow_schema::{DataType, Field, Fields, Schema as ArrowSchema};
     use bytes::Bytes;
@@ -1890,6 +1907,155 @@ pub mod tests {
         .is_err());
     }
 
+    #[rstest]
+    #[test_log::test(tokio::test)]
+    async fn test_map_projection(
+        #[values(LanceFileVersion::V2_0, LanceFileVersion::V2_1)] version: LanceFileVersion,
+    ) {
+        let fs = FsFixture::default();
+
+        let mut attrs_builder = MapBuilder::new(None, StringBuilder::new(), Float32Builder::new());
+        for i in 0..100 {
+            for key in 0..(i % 4) {
+                attrs_builder.keys().append_value(format!("key{}", key));
+                attrs_builder.values().append_value(i as f32 * key as f32);
+            }
+            attrs_builder.append(i % 7 != 0).unwrap();
+        }
+        let attrs = Arc::new(attrs_builder.finish()) as ArrayRef;
+        let ids = Arc::new(Int32Array::from_iter_values(0..100)) as ArrayRef;
+        let data = RecordBatch::try_from_iter(vec![("id", ids), ("attrs", attrs.clone())]).unwrap();
+        let reader = RecordBatchIterator::new(vec![Ok(data.clone())], data.schema());
+
+        let written_file = write_lance_file(
+            reader,
+            &fs,
+            FileWriterOptions {
+                format_version: Some(version),
+                ..Default::default()
+            },
+        )
+        .await;
+        let field_id_mapping = written_file
+            .field_id_mapping
+            .iter()
+            .copied()
+            .collect::<BTreeMap<_, _>>();
+
+        let file_scheduler = fs
+            .scheduler
+            .open_file(&fs.tmp_path, &CachedFileSize::unknown())
+            .await
+            .unwrap();
+        let file_reader = FileReader::try_open(
+            file_scheduler,
+            None,
+            Arc::<DecoderPlugins>::default(),
+            &test_cache(),
+            FileReaderOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        // Projecting the map values keeps the keys so the map can be rebuilt
+        let projected_schema = written_file.schema.project(&["attrs.values"]).unwrap();
+        let projection = ReaderProjection::from_field_ids(
+            file_reader.metadata.version(),
+            &projected_schema,
+            &field_id_mapping,
+        )
+        .unwrap();
+        let batches = file_reader
+            .read_stream_projected(
+                lance_io::ReadBatchParams::RangeFull,
+                1024,
+                16,
+                projection,
+                FilterExpression::no_filter(),
+            )
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_columns(), 1);
+        assert_eq!(batches[0].column(0), &attrs);
+    }
+
+    #[rstest]
+    #[test_log::test(tokio::test)]
+    async fn test_read_view_types(
+        #[values(LanceFileVersion::V2_0, LanceFileVersion::V2_1)] version: LanceFileVersion,
+    ) {
+        let fs = FsFixture::default();
+
+        let reader = gen()
+            .col("str", array::rand_utf8(ByteCount::from(20), false))
+            .col("bin", array::rand_type(&DataType::Binary))
+            .col("int", array::step::<Int32Type>())
+            .into_reader_rows(RowCount::from(1000), BatchCount::from(4));
+        let written_file = write_lance_file(
+            reader,
+            &fs,
+            FileWriterOptions {
+                format_version: Some(version),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let file_scheduler = fs
+            .scheduler
+            .open_file(&fs.tmp_path, &CachedFileSize::unknown())
+            .await
+            .unwrap();
+        let file_reader = FileReader::try_open(
+            file_scheduler,
+            None,
+            Arc::<DecoderPlugins>::default(),
+            &test_cache(),
+            FileReaderOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let projection =
+            ReaderProjection::from_whole_schema(&written_file.schema, version).with_view_types();
+        let batches = file_reader
+            .read_stream_projected(
+                lance_io::ReadBatchParams::RangeFull,
+                1024,
+                16,
+                projection,
+                FilterExpression::no_filter(),
+            )
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let expected = arrow_select::concat::concat_batches(
+            written_file.data[0].schema_ref(),
+            &written_file.data,
+        )
+        .unwrap();
+        let actual =
+            arrow_select::concat::concat_batches(batches[0].schema_ref(), &batches).unwrap();
+        assert_eq!(actual.schema().field(0).data_type(), &DataType::Utf8View);
+        assert_eq!(actual.schema().field(1).data_type(), &DataType::BinaryView);
+        assert!(actual
+            .column(0)
+            .as_string_view()
+            .iter()
+            .eq(expected.column(0).as_string::<i32>().iter()));
+        assert!(actual
+            .column(1)
+            .as_binary_view()
+            .iter()
+            .eq(expected.column(1).as_binary::<i32>().iter()));
+        assert_eq!(actual.column(2), expected.column(2));
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_compressing_buffer() {
         let fs = FsFixture::default();
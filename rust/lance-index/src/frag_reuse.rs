@@ -178,6 +178,7 @@ pub mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             }],
             new_frags: vec![2, 3],
         };
@@ -191,6 +192,7 @@ pub mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             }],
             new_frags: vec![4, 5],
         };
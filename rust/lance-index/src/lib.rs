@@ -91,6 +91,8 @@ pub enum IndexType {
 
     FragmentReuse = 6,
 
+    Sparse = 7, // Sparse vector
+
     // 100+ and up for vector index.
     /// Flat vector index.
     Vector = 100, // Legacy vector index, alias to IvfPq
@@ -99,6 +101,9 @@ pub enum IndexType {
     IvfPq = 103,
     IvfHnswSq = 104,
     IvfHnswPq = 105,
+    IvfRq = 106,
+    /// DiskANN graph index, not partitioned by IVF.
+    DiskAnn = 107,
 }
 
 impl std::fmt::Display for IndexType {
@@ -110,11 +115,14 @@ impl std::fmt::Display for IndexType {
             Self::Inverted => write!(f, "Inverted"),
             Self::NGram => write!(f, "NGram"),
             Self::FragmentReuse => write!(f, "FragmentReuse"),
+            Self::Sparse => write!(f, "Sparse"),
             Self::Vector | Self::IvfPq => write!(f, "IVF_PQ"),
             Self::IvfFlat => write!(f, "IVF_FLAT"),
             Self::IvfSq => write!(f, "IVF_SQ"),
             Self::IvfHnswSq => write!(f, "IVF_HNSW_SQ"),
             Self::IvfHnswPq => write!(f, "IVF_HNSW_PQ"),
+            Self::IvfRq => write!(f, "IVF_RQ"),
+            Self::DiskAnn => write!(f, "DISKANN"),
         }
     }
 }
@@ -130,12 +138,15 @@ impl TryFrom<i32> for IndexType {
             v if v == Self::LabelList as i32 => Ok(Self::LabelList),
             v if v == Self::NGram as i32 => Ok(Self::NGram),
             v if v == Self::Inverted as i32 => Ok(Self::Inverted),
+            v if v == Self::Sparse as i32 => Ok(Self::Sparse),
             v if v == Self::Vector as i32 => Ok(Self::Vector),
             v if v == Self::IvfFlat as i32 => Ok(Self::IvfFlat),
             v if v == Self::IvfSq as i32 => Ok(Self::IvfSq),
             v if v == Self::IvfPq as i32 => Ok(Self::IvfPq),
             v if v == Self::IvfHnswSq as i32 => Ok(Self::IvfHnswSq),
             v if v == Self::IvfHnswPq as i32 => Ok(Self::IvfHnswPq),
+            v if v == Self::IvfRq as i32 => Ok(Self::IvfRq),
+            v if v == Self::DiskAnn as i32 => Ok(Self::DiskAnn),
             _ => Err(Error::InvalidInput {
                 source: format!("the input value {} is not a valid IndexType", value).into(),
                 location: location!(),
@@ -154,6 +165,7 @@ impl IndexType {
                 | Self::LabelList
                 | Self::Inverted
                 | Self::NGram
+                | Self::Sparse
         )
     }
 
@@ -166,6 +178,8 @@ impl IndexType {
                 | Self::IvfHnswPq
                 | Self::IvfFlat
                 | Self::IvfSq
+                | Self::IvfRq
+                | Self::DiskAnn
         )
     }
 
@@ -183,6 +197,7 @@ impl IndexType {
             Self::Inverted => 0,
             Self::NGram => 0,
             Self::FragmentReuse => 0,
+            Self::Sparse => 0,
 
             // for now all vector indices are built by the same builder,
             // so they share the same version.
@@ -191,7 +206,9 @@ impl IndexType {
             | Self::IvfSq
             | Self::IvfPq
             | Self::IvfHnswSq
-            | Self::IvfHnswPq => 0,
+            | Self::IvfHnswPq
+            | Self::IvfRq
+            | Self::DiskAnn => 0,
         }
     }
 }
@@ -19,7 +19,9 @@ use datafusion_common::{scalar::ScalarValue, Column};
 use datafusion_expr::expr::ScalarFunction;
 use datafusion_expr::Expr;
 use deepsize::DeepSizeOf;
+use inverted::highlight::HighlightParams;
 use inverted::query::{fill_fts_query_column, FtsQuery, FtsQueryNode, FtsSearchParams, MatchQuery};
+use inverted::scorer::ScoringModel;
 use lance_core::utils::mask::RowIdTreeMap;
 use lance_core::{Error, Result};
 use snafu::location;
@@ -35,6 +37,7 @@ pub mod inverted;
 pub mod label_list;
 pub mod lance_format;
 pub mod ngram;
+pub mod sparse;
 
 pub use inverted::tokenizer::InvertedIndexParams;
 
@@ -47,6 +50,7 @@ pub enum ScalarIndexType {
     LabelList,
     NGram,
     Inverted,
+    Sparse,
 }
 
 impl TryFrom<IndexType> for ScalarIndexType {
@@ -59,6 +63,7 @@ impl TryFrom<IndexType> for ScalarIndexType {
             IndexType::LabelList => Ok(Self::LabelList),
             IndexType::NGram => Ok(Self::NGram),
             IndexType::Inverted => Ok(Self::Inverted),
+            IndexType::Sparse => Ok(Self::Sparse),
             _ => Err(Error::InvalidInput {
                 source: format!("Index type {:?} is not a scalar index", value).into(),
                 location: location!(),
@@ -75,6 +80,7 @@ impl From<ScalarIndexType> for IndexType {
             ScalarIndexType::LabelList => Self::LabelList,
             ScalarIndexType::NGram => Self::NGram,
             ScalarIndexType::Inverted => Self::Inverted,
+            ScalarIndexType::Sparse => Self::Sparse,
         }
     }
 }
@@ -105,6 +111,7 @@ impl IndexParams for ScalarIndexParams {
             Some(ScalarIndexType::LabelList) => IndexType::LabelList,
             Some(ScalarIndexType::Inverted) => IndexType::Inverted,
             Some(ScalarIndexType::NGram) => IndexType::NGram,
+            Some(ScalarIndexType::Sparse) => IndexType::Sparse,
         }
     }
 
@@ -236,6 +243,13 @@ pub struct FullTextSearchQuery {
     /// Increasing this value will reduce the recall and improve the performance
     /// 1.0 is the value that would give the best performance without recall loss
     pub wand_factor: Option<f32>,
+
+    /// How the matched terms are highlighted in the results
+    /// if None, the results are not highlighted
+    pub highlight: Option<HighlightParams>,
+
+    /// How the matched documents are scored, BM25 by default
+    pub scoring: ScoringModel,
 }
 
 impl FullTextSearchQuery {
@@ -246,6 +260,8 @@ impl FullTextSearchQuery {
             query,
             limit: None,
             wand_factor: None,
+            highlight: None,
+            scoring: ScoringModel::default(),
         }
     }
 
@@ -256,6 +272,8 @@ impl FullTextSearchQuery {
             query,
             limit: None,
             wand_factor: None,
+            highlight: None,
+            scoring: ScoringModel::default(),
         }
     }
 
@@ -265,9 +283,17 @@ impl FullTextSearchQuery {
             query,
             limit: None,
             wand_factor: None,
+            highlight: None,
+            scoring: ScoringModel::default(),
         }
     }
 
+    /// Create a query from a Lucene-like query string,
+    /// see [`inverted::parser`] for the syntax
+    pub fn parse(query: &str) -> Result<Self> {
+        Ok(Self::new_query(query.parse()?))
+    }
+
     /// Set the column to search over
     /// This is available for only MatchQuery and PhraseQuery
     pub fn with_column(mut self, column: String) -> Result<Self> {
@@ -294,6 +320,21 @@ impl FullTextSearchQuery {
         self
     }
 
+    /// Highlight the matched terms in the results.
+    ///
+    /// The results have the highlighted snippet in the `_highlight` column,
+    /// and the byte offsets of the matched terms in the `_match_offsets` column.
+    pub fn highlight(mut self, params: HighlightParams) -> Self {
+        self.highlight = Some(params);
+        self
+    }
+
+    /// Score the matched documents with the scoring model
+    pub fn scoring(mut self, scoring: ScoringModel) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
     pub fn columns(&self) -> HashSet<String> {
         self.query.columns()
     }
@@ -301,7 +342,8 @@ impl FullTextSearchQuery {
     pub fn params(&self) -> FtsSearchParams {
         let params = FtsSearchParams::new()
             .with_limit(self.limit.map(|limit| limit as usize))
-            .with_wand_factor(self.wand_factor.unwrap_or(1.0));
+            .with_wand_factor(self.wand_factor.unwrap_or(1.0))
+            .with_scoring(self.scoring.clone());
         match self.query {
             FtsQuery::Phrase(ref query) => params.with_phrase_slop(Some(query.slop)),
             _ => params,
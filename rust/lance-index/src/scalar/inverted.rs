@@ -3,11 +3,14 @@
 
 pub mod builder;
 mod encoding;
+pub mod highlight;
 mod index;
 mod iter;
 mod merger;
+pub mod parser;
+pub mod pattern;
 pub mod query;
-mod scorer;
+pub mod scorer;
 pub mod tokenizer;
 mod wand;
 
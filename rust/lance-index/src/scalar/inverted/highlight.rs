@@ -0,0 +1,494 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Highlighting of the terms matched by full text search queries
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow_array::{Array, ArrayRef, ListArray, StringArray, StructArray, UInt32Array};
+use arrow_schema::{DataType, Field, Fields};
+use fst::automaton::Levenshtein;
+use fst::Automaton;
+use lance_arrow::iter_str_array;
+use lance_core::{Error, Result};
+use lazy_static::lazy_static;
+use snafu::location;
+use tantivy::tokenizer::TextAnalyzer;
+
+use super::pattern::TokenPattern;
+use super::query::{collect_tokens, FtsQuery, MatchQuery};
+
+/// The column of the highlighted snippets
+pub const HIGHLIGHT_COL: &str = "_highlight";
+/// The column of the byte offsets of the matched terms
+pub const MATCH_OFFSETS_COL: &str = "_match_offsets";
+
+lazy_static! {
+    pub static ref MATCH_OFFSET_FIELDS: Fields = Fields::from(vec![
+        Field::new("start", DataType::UInt32, false),
+        Field::new("end", DataType::UInt32, false),
+    ]);
+    pub static ref HIGHLIGHT_FIELD: Field = Field::new(HIGHLIGHT_COL, DataType::Utf8, true);
+    pub static ref MATCH_OFFSETS_FIELD: Field = Field::new(
+        MATCH_OFFSETS_COL,
+        DataType::List(Arc::new(Field::new(
+            "item",
+            DataType::Struct(MATCH_OFFSET_FIELDS.clone()),
+            false
+        ))),
+        true,
+    );
+}
+
+/// How the matched terms are highlighted in the results of a full text search.
+///
+/// The matches are always found by re-tokenizing the text of the returned rows,
+/// the positions stored by an index built `with_position` are never used because
+/// they are token ordinals, not the byte offsets needed to highlight the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighlightParams {
+    /// The column to highlight.
+    /// If None, the column searched by the query is highlighted, there must be only one.
+    pub column: Option<String>,
+    /// The tag inserted before each matched term
+    pub pre_tag: String,
+    /// The tag inserted after each matched term
+    pub post_tag: String,
+    /// The max length of the snippet in bytes, excluding the tags.
+    /// The snippet is the part of the text with the most matched terms.
+    /// 0 means the whole text is returned.
+    pub max_snippet_len: usize,
+}
+
+impl Default for HighlightParams {
+    fn default() -> Self {
+        Self {
+            column: None,
+            pre_tag: "<em>".to_owned(),
+            post_tag: "</em>".to_owned(),
+            max_snippet_len: 200,
+        }
+    }
+}
+
+impl HighlightParams {
+    pub fn with_column(mut self, column: String) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    pub fn with_tags(mut self, pre_tag: String, post_tag: String) -> Self {
+        self.pre_tag = pre_tag;
+        self.post_tag = post_tag;
+        self
+    }
+
+    pub fn with_max_snippet_len(mut self, max_snippet_len: usize) -> Self {
+        self.max_snippet_len = max_snippet_len;
+        self
+    }
+}
+
+struct FuzzyTerm {
+    automaton: Levenshtein,
+    prefix: String,
+}
+
+impl FuzzyTerm {
+    fn is_match(&self, token: &str) -> bool {
+        if !token.starts_with(&self.prefix) {
+            return false;
+        }
+        let mut state = self.automaton.start();
+        for byte in token.bytes() {
+            state = self.automaton.accept(&state, byte);
+            if !self.automaton.can_match(&state) {
+                return false;
+            }
+        }
+        self.automaton.is_match(&state)
+    }
+}
+
+/// Finds the terms of a full text search query in the text of a column,
+/// and highlights them.
+///
+/// The text is re-tokenized with the tokenizer of the inverted index, so the terms are
+/// matched the same way as the search does, e.g. "running" matches "runs" if the
+/// tokens are stemmed. The terms of phrase queries are highlighted wherever they appear,
+/// not only in the phrase.
+pub struct Highlighter {
+    tokenizer: TextAnalyzer,
+    terms: HashSet<String>,
+    fuzzy_terms: Vec<FuzzyTerm>,
+    patterns: Vec<TokenPattern>,
+    params: HighlightParams,
+}
+
+impl Highlighter {
+    /// Create a highlighter for the terms of the query that search the column.
+    ///
+    /// The terms of negative boost queries are not highlighted.
+    pub fn try_new(
+        query: &FtsQuery,
+        column: &str,
+        tokenizer: TextAnalyzer,
+        params: HighlightParams,
+    ) -> Result<Self> {
+        let mut highlighter = Self {
+            tokenizer,
+            terms: HashSet::new(),
+            fuzzy_terms: Vec::new(),
+            patterns: Vec::new(),
+            params,
+        };
+        highlighter.collect_terms(query, column)?;
+        Ok(highlighter)
+    }
+
+    fn collect_terms(&mut self, query: &FtsQuery, column: &str) -> Result<()> {
+        match query {
+            FtsQuery::Match(query) => self.collect_match_terms(query, column)?,
+            FtsQuery::Phrase(query) => {
+                if query.column.as_deref() == Some(column) {
+                    self.terms
+                        .extend(collect_tokens(&query.terms, &mut self.tokenizer, None));
+                }
+            }
+            FtsQuery::Prefix(_) | FtsQuery::Wildcard(_) | FtsQuery::Regex(_) => {
+                let query = query.as_pattern_query().unwrap();
+                if query.column() == Some(column) {
+                    // the tokens are highlighted in any case
+                    self.patterns.push(query.token_pattern(true)?);
+                }
+            }
+            FtsQuery::Boost(query) => self.collect_terms(&query.positive, column)?,
+            FtsQuery::MultiMatch(query) => {
+                for query in &query.match_queries {
+                    self.collect_match_terms(query, column)?;
+                }
+            }
+            FtsQuery::Boolean(query) => {
+                for query in query.must.iter().chain(query.should.iter()) {
+                    self.collect_terms(query, column)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn collect_match_terms(&mut self, query: &MatchQuery, column: &str) -> Result<()> {
+        if query.column.as_deref() != Some(column) {
+            return Ok(());
+        }
+        match query.fuzziness {
+            Some(0) => {
+                self.terms
+                    .extend(collect_tokens(&query.terms, &mut self.tokenizer, None));
+            }
+            fuzziness => {
+                // same as the search, the fuzzy terms are not processed by the tokenizer
+                let mut tokenizer =
+                    TextAnalyzer::from(tantivy::tokenizer::SimpleTokenizer::default());
+                for token in collect_tokens(&query.terms, &mut tokenizer, None) {
+                    let fuzziness = fuzziness.unwrap_or_else(|| MatchQuery::auto_fuzziness(&token));
+                    let automaton = Levenshtein::new(&token, fuzziness).map_err(|e| {
+                        Error::invalid_input(
+                            format!("failed to construct the fuzzy query: {}", e),
+                            location!(),
+                        )
+                    })?;
+                    let prefix = token
+                        .chars()
+                        .take(query.prefix_length as usize)
+                        .collect::<String>();
+                    self.fuzzy_terms.push(FuzzyTerm { automaton, prefix });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_match(&self, token: &str) -> bool {
+        self.terms.contains(token)
+            || self.fuzzy_terms.iter().any(|term| term.is_match(token))
+            || self.patterns.iter().any(|pattern| pattern.is_match(token))
+    }
+
+    /// The byte offsets `[start, end)` of the matched terms in the text
+    pub fn match_offsets(&mut self, text: &str) -> Vec<(usize, usize)> {
+        let mut tokens = Vec::new();
+        {
+            let mut stream = self.tokenizer.token_stream(text);
+            while let Some(token) = stream.next() {
+                tokens.push((token.text.clone(), token.offset_from, token.offset_to));
+            }
+        }
+        tokens
+            .into_iter()
+            .filter(|(token, _, _)| self.is_match(token))
+            .map(|(_, start, end)| (start, end))
+            .collect()
+    }
+
+    /// Highlight the matched terms in the text.
+    ///
+    /// Returns the highlighted snippet and the byte offsets of the matched terms
+    /// in the whole text.
+    pub fn highlight(&mut self, text: &str) -> (String, Vec<(usize, usize)>) {
+        let offsets = self.match_offsets(text);
+        let (start, end) = snippet_range(text, &offsets, self.params.max_snippet_len);
+
+        let mut snippet = String::with_capacity(end - start);
+        let mut pos = start;
+        for &(term_start, term_end) in offsets
+            .iter()
+            .filter(|(term_start, term_end)| *term_start >= start && *term_end <= end)
+        {
+            snippet.push_str(&text[pos..term_start]);
+            snippet.push_str(&self.params.pre_tag);
+            snippet.push_str(&text[term_start..term_end]);
+            snippet.push_str(&self.params.post_tag);
+            pos = term_end;
+        }
+        snippet.push_str(&text[pos..end]);
+        (snippet, offsets)
+    }
+
+    /// Highlight each text of a string array.
+    ///
+    /// Returns the [`HIGHLIGHT_COL`] and [`MATCH_OFFSETS_COL`] columns,
+    /// which are null for null texts.
+    pub fn highlight_array(&mut self, texts: &dyn Array) -> Result<(ArrayRef, ArrayRef)> {
+        if !matches!(
+            texts.data_type(),
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+        ) {
+            return Err(Error::invalid_input(
+                format!(
+                    "highlighting requires a string column, but got {}",
+                    texts.data_type()
+                ),
+                location!(),
+            ));
+        }
+
+        let mut snippets = Vec::with_capacity(texts.len());
+        let mut list_offsets = Vec::with_capacity(texts.len() + 1);
+        list_offsets.push(0);
+        let mut starts = Vec::new();
+        let mut ends = Vec::new();
+        for text in iter_str_array(texts) {
+            if let Some(text) = text {
+                let (snippet, offsets) = self.highlight(text);
+                snippets.push(Some(snippet));
+                for (start, end) in offsets {
+                    starts.push(start as u32);
+                    ends.push(end as u32);
+                }
+            } else {
+                snippets.push(None);
+            }
+            list_offsets.push(starts.len() as i32);
+        }
+
+        let match_offsets = StructArray::new(
+            MATCH_OFFSET_FIELDS.clone(),
+            vec![
+                Arc::new(UInt32Array::from(starts)),
+                Arc::new(UInt32Array::from(ends)),
+            ],
+            None,
+        );
+        let DataType::List(item_field) = MATCH_OFFSETS_FIELD.data_type() else {
+            unreachable!()
+        };
+        let match_offsets = ListArray::new(
+            item_field.clone(),
+            OffsetBuffer::new(list_offsets.into()),
+            Arc::new(match_offsets),
+            texts
+                .logical_nulls()
+                .map(|nulls| NullBuffer::new(nulls.into_inner())),
+        );
+        Ok((
+            Arc::new(StringArray::from(snippets)),
+            Arc::new(match_offsets),
+        ))
+    }
+}
+
+/// The byte range of the snippet, which is the window of at most `max_len` bytes
+/// covering the most matched terms.
+fn snippet_range(text: &str, offsets: &[(usize, usize)], max_len: usize) -> (usize, usize) {
+    if max_len == 0 || text.len() <= max_len {
+        return (0, text.len());
+    }
+
+    // the window starts at a matched term, so that the term is visible
+    let mut best = None;
+    let mut best_count = 0;
+    let mut next = 0;
+    for (i, &(start, _)) in offsets.iter().enumerate() {
+        next = next.max(i);
+        while next < offsets.len() && offsets[next].1 <= start + max_len {
+            next += 1;
+        }
+        if next - i > best_count {
+            best = Some(i);
+            best_count = next - i;
+        }
+    }
+    let (anchor_start, anchor_end) = best.map(|i| offsets[i]).unwrap_or_default();
+
+    // fill the snippet if the window reaches the end of the text
+    let mut start = anchor_start.min(text.len() - max_len);
+    let mut end = start + max_len;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    // don't split the words at the boundaries of the snippet
+    if !text[..start].ends_with(char::is_whitespace) {
+        if let Some((pos, c)) = text[start..anchor_start.max(start)]
+            .char_indices()
+            .find(|(_, c)| c.is_whitespace())
+        {
+            start += pos + c.len_utf8();
+        }
+    }
+    if !text[end..].starts_with(char::is_whitespace) && anchor_end < end {
+        if let Some(pos) = text[anchor_end..end].rfind(char::is_whitespace) {
+            end = anchor_end + pos;
+        }
+    }
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::UInt32Type;
+    use arrow_array::StringViewArray;
+
+    use super::*;
+    use crate::scalar::inverted::query::PhraseQuery;
+    use crate::scalar::inverted::InvertedIndexParams;
+
+    fn make_highlighter(query: FtsQuery, params: HighlightParams) -> Highlighter {
+        let tokenizer = InvertedIndexParams::default().build().unwrap();
+        Highlighter::try_new(
+            &query.with_column("text".to_owned()),
+            "text",
+            tokenizer,
+            params,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_highlight() {
+        let mut highlighter = make_highlighter(
+            MatchQuery::new("lazy DOGS".to_owned()).into(),
+            HighlightParams::default(),
+        );
+        let text = "The quick brown fox jumps over the lazy dog";
+        let (snippet, offsets) = highlighter.highlight(text);
+        assert_eq!(
+            snippet,
+            "The quick brown fox jumps over the <em>lazy</em> <em>dog</em>"
+        );
+        assert_eq!(offsets, vec![(35, 39), (40, 43)]);
+
+        // the terms of the other columns are not highlighted
+        let tokenizer = InvertedIndexParams::default().build().unwrap();
+        let query =
+            FtsQuery::from(MatchQuery::new("lazy".to_owned())).with_column("other".to_owned());
+        let mut highlighter =
+            Highlighter::try_new(&query, "text", tokenizer, HighlightParams::default()).unwrap();
+        assert!(highlighter.match_offsets(text).is_empty());
+    }
+
+    #[test]
+    fn test_highlight_fuzzy_and_phrase() {
+        let mut highlighter = make_highlighter(
+            MatchQuery::new("quik".to_owned())
+                .with_fuzziness(Some(1))
+                .into(),
+            HighlightParams::default().with_tags("[".to_owned(), "]".to_owned()),
+        );
+        let (snippet, _) = highlighter.highlight("The quick brown fox");
+        assert_eq!(snippet, "The [quick] brown fox");
+
+        let mut highlighter = make_highlighter(
+            PhraseQuery::new("brown fox".to_owned()).into(),
+            HighlightParams::default(),
+        );
+        let (snippet, _) = highlighter.highlight("brown fox, and a fox");
+        assert_eq!(snippet, "<em>brown</em> <em>fox</em>, and a <em>fox</em>");
+    }
+
+    #[test]
+    fn test_snippet() {
+        let mut highlighter = make_highlighter(
+            MatchQuery::new("needle".to_owned()).into(),
+            HighlightParams::default().with_max_snippet_len(20),
+        );
+        let text = format!(
+            "{} needle in a haystack {}",
+            "hay ".repeat(20),
+            "hay ".repeat(20)
+        );
+        let (snippet, offsets) = highlighter.highlight(&text);
+        assert_eq!(snippet, "<em>needle</em> in a haystack");
+        assert_eq!(offsets, vec![(81, 87)]);
+
+        // the window is moved back to fill the snippet at the end of the text,
+        // without splitting the words
+        let (snippet, _) = highlighter.highlight("hay hay hay hay hay needle");
+        assert_eq!(snippet, "hay hay hay <em>needle</em>");
+
+        // without matched terms, the snippet is the beginning of the text
+        let (snippet, offsets) = highlighter.highlight(&"hay ".repeat(10));
+        assert_eq!(snippet, "hay hay hay hay hay");
+        assert!(offsets.is_empty());
+    }
+
+    #[test]
+    fn test_highlight_array() {
+        let mut highlighter = make_highlighter(
+            MatchQuery::new("fox".to_owned()).into(),
+            HighlightParams::default(),
+        );
+        let texts = StringArray::from(vec![Some("a fox"), None, Some("no match")]);
+        let (snippets, offsets) = highlighter.highlight_array(&texts).unwrap();
+        let snippets = snippets.as_string::<i32>();
+        assert_eq!(snippets.value(0), "a <em>fox</em>");
+        assert!(snippets.is_null(1));
+        assert_eq!(snippets.value(2), "no match");
+
+        let offsets = offsets.as_list::<i32>();
+        assert_eq!(offsets.data_type(), MATCH_OFFSETS_FIELD.data_type());
+        assert!(offsets.is_null(1));
+        assert_eq!(offsets.value(2).len(), 0);
+        let first = offsets.value(0);
+        let first = first.as_struct();
+        assert_eq!(first.column(0).as_primitive::<UInt32Type>().values(), &[2]);
+        assert_eq!(first.column(1).as_primitive::<UInt32Type>().values(), &[5]);
+
+        let views = StringViewArray::from(vec![Some("a fox"), None]);
+        let (view_snippets, _) = highlighter.highlight_array(&views).unwrap();
+        let view_snippets = view_snippets.as_string::<i32>();
+        assert_eq!(view_snippets.value(0), "a <em>fox</em>");
+        assert!(view_snippets.is_null(1));
+
+        assert!(highlighter
+            .highlight_array(&UInt32Array::from(vec![1]))
+            .is_err());
+    }
+}
@@ -5,7 +5,7 @@ use std::fmt::Debug;
 use std::sync::Arc;
 use std::{
     cmp::{min, Reverse},
-    collections::BinaryHeap,
+    collections::{BTreeSet, BinaryHeap},
     ops::RangeInclusive,
 };
 use std::{
@@ -55,7 +55,7 @@ use super::{
     },
     iter::PlainPostingListIterator,
     query::*,
-    scorer::{idf, BM25Scorer, Scorer, B, K1},
+    scorer::{idf, CorpusStats, FieldFreqs, IndexStats, Scorer, ScoringModel, B, K1},
 };
 use super::{
     builder::{InnerBuilder, PositionRecorder},
@@ -147,6 +147,81 @@ impl InvertedIndex {
         &self.params
     }
 
+    /// The statistics of the indexed documents
+    pub fn stats(&self) -> IndexStats<'_> {
+        IndexStats::new(self.partitions.iter().map(|part| part.as_ref()))
+    }
+
+    /// The frequencies of the tokens in the documents that contain any of them, keyed by row id.
+    ///
+    /// Unlike [`Self::bm25_search`] this reads the whole posting lists without pruning,
+    /// it's for scoring the documents across fields with BM25F.
+    pub async fn token_freqs(
+        &self,
+        tokens: &[String],
+        prefilter: Arc<dyn PreFilter>,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<HashMap<u64, FieldFreqs>> {
+        let mask = prefilter.mask();
+        let mut docs = HashMap::new();
+        for part in &self.partitions {
+            for (i, token) in tokens.iter().enumerate() {
+                let Some(token_id) = part.map(token) else {
+                    continue;
+                };
+                let posting = part
+                    .inverted_list
+                    .posting_list(token_id, false, metrics)
+                    .await?;
+                let is_located = matches!(posting, PostingList::Plain(_));
+                for (doc_id, freq, _) in posting.iter() {
+                    // the legacy posting lists store the row ids instead of the doc ids
+                    let (row_id, num_tokens) = match is_located {
+                        true => (doc_id, part.docs.num_tokens_by_row_id(doc_id)),
+                        false => (
+                            part.docs.row_id(doc_id as u32),
+                            part.docs.num_tokens(doc_id as u32),
+                        ),
+                    };
+                    if !mask.selected(row_id) {
+                        continue;
+                    }
+                    let doc = docs.entry(row_id).or_insert_with(|| FieldFreqs {
+                        num_tokens,
+                        freqs: vec![0; tokens.len()],
+                    });
+                    doc.freqs[i] += freq;
+                }
+            }
+        }
+        Ok(docs)
+    }
+
+    /// Expand the prefix, wildcard or regex query to the matched tokens,
+    /// at most `max_expansions` tokens are returned in lexicographical order
+    pub fn expand_pattern(&self, query: &dyn PatternQueryNode) -> Result<Vec<String>> {
+        let pattern = query.token_pattern(self.params.lower_case)?;
+        let mut tokens = BTreeSet::new();
+        for part in &self.partitions {
+            let TokenMap::Fst(ref map) = part.tokens.tokens else {
+                return Err(Error::Index {
+                    message: "tokens is not fst, which is not expected".to_owned(),
+                    location: location!(),
+                });
+            };
+            // the tokens are streamed in order,
+            // so the first tokens of each partition cover the first tokens of the index
+            let mut part_tokens = Vec::new();
+            take_fst_keys(
+                map.search(&pattern),
+                &mut part_tokens,
+                query.max_expansions(),
+            );
+            tokens.extend(part_tokens);
+        }
+        Ok(tokens.into_iter().take(query.max_expansions()).collect())
+    }
+
     // search the documents that contain the query
     // return the row ids of the documents sorted by bm25 score
     // ref: https://en.wikipedia.org/wiki/Okapi_BM25
@@ -189,7 +264,9 @@ impl InvertedIndex {
             })
             .collect::<Vec<_>>();
         let mut parts = stream::iter(parts).buffer_unordered(get_num_compute_intensive_cpus());
-        let scorer = BM25Scorer::new(self.partitions.iter().map(|part| part.as_ref()));
+        let scorer = params.scoring.scorer(Box::new(IndexStats::new(
+            self.partitions.iter().map(|part| part.as_ref()),
+        )));
         while let Some(res) = parts.try_next().await? {
             for (row_id, freq, length) in res? {
                 let mut score = 0.0;
@@ -546,7 +623,9 @@ impl InvertedPartition {
             .buffered(self.store.io_parallelism())
             .try_collect::<Vec<_>>()
             .await?;
-        let scorer = BM25Scorer::new(std::iter::once(self));
+        let scorer = params
+            .scoring
+            .scorer(Box::new(IndexStats::new(std::iter::once(self))));
         let mut wand = Wand::new(operator, postings.into_iter(), &self.docs, scorer);
         wand.search(params, mask, metrics)
     }
@@ -1843,15 +1922,34 @@ fn do_flat_full_text_search<Offset: OffsetSizeTrait>(
     Ok(results)
 }
 
-#[allow(clippy::too_many_arguments)]
+/// The statistics of the indexed documents for scoring the unindexed documents,
+/// the tokens that are not indexed are counted as if they are in one document
+struct FlatStats {
+    num_docs: usize,
+    avgdl: f32,
+    nq: HashMap<String, usize>,
+}
+
+impl CorpusStats for FlatStats {
+    fn num_docs(&self) -> usize {
+        self.num_docs
+    }
+
+    fn avgdl(&self) -> f32 {
+        self.avgdl
+    }
+
+    fn nq(&self, token: &str) -> usize {
+        self.nq.get(token).copied().unwrap_or(1)
+    }
+}
+
 pub fn flat_bm25_search(
     batch: RecordBatch,
     doc_col: &str,
     query_tokens: &HashSet<String>,
-    nq: &HashMap<String, usize>,
+    scorer: &dyn Scorer,
     tokenizer: &mut tantivy::tokenizer::TextAnalyzer,
-    avgdl: f32,
-    num_docs: usize,
 ) -> std::result::Result<RecordBatch, DataFusionError> {
     let doc_iter = iter_str_array(&batch[doc_col]);
     let mut scores = Vec::with_capacity(batch.num_rows());
@@ -1861,22 +1959,21 @@ pub fn flat_bm25_search(
             continue;
         };
 
-        let doc_tokens = collect_tokens(doc, tokenizer, Some(query_tokens));
-        let doc_norm = K1 * (1.0 - B + B * doc_tokens.len() as f32 / avgdl);
+        // The length of the document counts all its tokens, as in the index
+        let doc_tokens = collect_tokens(doc, tokenizer, None);
+        let num_doc_tokens = doc_tokens.len() as u32;
         let mut doc_token_count = HashMap::new();
-        for token in doc_tokens {
-            doc_token_count
-                .entry(token)
-                .and_modify(|count| *count += 1)
-                .or_insert(1);
-        }
-        let mut score = 0.0;
-        for token in query_tokens.iter() {
-            let freq = doc_token_count.get(token).copied().unwrap_or_default() as f32;
-
-            let idf = idf(nq[token], num_docs);
-            score += idf * (freq * (K1 + 1.0) / (freq + doc_norm));
+        for token in doc_tokens
+            .into_iter()
+            .filter(|token| query_tokens.contains(token))
+        {
+            *doc_token_count.entry(token).or_insert(0) += 1;
         }
+        // Only the query tokens in the document are scored, as in the index
+        let score = doc_token_count
+            .iter()
+            .map(|(token, freq)| scorer.score(token, *freq, num_doc_tokens))
+            .sum::<f32>();
         scores.push(score);
     }
 
@@ -1891,6 +1988,7 @@ pub fn flat_bm25_search_stream(
     input: SendableRecordBatchStream,
     doc_col: String,
     query: String,
+    scoring: &ScoringModel,
     index: &InvertedIndex,
 ) -> SendableRecordBatchStream {
     let mut tokenizer = index.tokenizer.clone();
@@ -1899,40 +1997,147 @@ pub fn flat_bm25_search_stream(
         .sorted_unstable()
         .collect::<HashSet<_>>();
 
-    let bm25_scorer = BM25Scorer::new(index.partitions.iter().map(|p| p.as_ref()));
-    let num_docs = bm25_scorer.num_docs();
-    let avgdl = bm25_scorer.avgdl();
+    let index_stats = IndexStats::new(index.partitions.iter().map(|p| p.as_ref()));
     let mut nq = HashMap::with_capacity(tokens.len());
     for token in &tokens {
-        let token_nq = bm25_scorer.nq(token).max(1);
+        let token_nq = index_stats.nq(token).max(1);
         nq.insert(token.clone(), token_nq);
     }
+    let scorer = scoring.scorer(Box::new(FlatStats {
+        num_docs: index_stats.num_docs(),
+        avgdl: index_stats.avgdl(),
+        nq,
+    }));
     let stream = input.map(move |batch| {
         let batch = batch?;
-        let batch = flat_bm25_search(
-            batch,
-            &doc_col,
-            &tokens,
-            &nq,
-            &mut tokenizer,
-            avgdl,
-            num_docs,
-        )?;
-
-        // filter out rows with score 0
-        let score_col = batch[SCORE_COL].as_primitive::<Float32Type>();
-        let mask = score_col
-            .iter()
-            .map(|score| score.is_some_and(|score| score > 0.0))
-            .collect::<Vec<_>>();
-        let mask = BooleanArray::from(mask);
-        let batch = arrow::compute::filter_record_batch(&batch, &mask)?;
-        Ok(batch)
+        let batch = flat_bm25_search(batch, &doc_col, &tokens, &scorer, &mut tokenizer)?;
+        filter_unmatched(batch)
     });
 
     Box::pin(RecordBatchStreamAdapter::new(FTS_SCHEMA.clone(), stream)) as SendableRecordBatchStream
 }
 
+/// Scores the unindexed documents for the prefix, wildcard or regex query,
+/// the tokens of the documents matching the pattern are scored like the query tokens.
+///
+/// The tokens are the ones the pattern expands to in the index. Matching tokens
+/// that are only in the unindexed documents are added as they are seen, while the
+/// total stays within `max_expansions`.
+pub fn flat_pattern_search_stream(
+    input: SendableRecordBatchStream,
+    doc_col: String,
+    query: &dyn PatternQueryNode,
+    scoring: &ScoringModel,
+    index: &InvertedIndex,
+) -> Result<SendableRecordBatchStream> {
+    let pattern = query.token_pattern(index.params.lower_case)?;
+    let mut tokenizer = index.tokenizer.clone();
+
+    let index_stats = IndexStats::new(index.partitions.iter().map(|p| p.as_ref()));
+    let max_expansions = query.max_expansions();
+    let mut tokens = HashSet::new();
+    let mut nq = HashMap::new();
+    for token in index.expand_pattern(query)? {
+        let token_nq = index_stats.nq(&token).max(1);
+        nq.insert(token.clone(), token_nq);
+        tokens.insert(token);
+    }
+    let scorer = scoring.scorer(Box::new(FlatStats {
+        num_docs: index_stats.num_docs(),
+        avgdl: index_stats.avgdl(),
+        nq,
+    }));
+    let stream = input.map(move |batch| {
+        let batch = batch?;
+        let doc_iter = iter_str_array(&batch[doc_col.as_str()]);
+        let mut scores = Vec::with_capacity(batch.num_rows());
+        for doc in doc_iter {
+            let Some(doc) = doc else {
+                scores.push(0.0);
+                continue;
+            };
+            let doc_tokens = collect_tokens(doc, &mut tokenizer, None);
+            let num_doc_tokens = doc_tokens.len() as u32;
+            let mut doc_token_count = HashMap::new();
+            for token in doc_tokens {
+                if !tokens.contains(&token) {
+                    if tokens.len() >= max_expansions || !pattern.is_match(&token) {
+                        continue;
+                    }
+                    tokens.insert(token.clone());
+                }
+                *doc_token_count.entry(token).or_insert(0) += 1;
+            }
+            let score = doc_token_count
+                .iter()
+                .map(|(token, freq)| scorer.score(token, *freq, num_doc_tokens))
+                .sum::<f32>();
+            scores.push(score);
+        }
+
+        let score_col = Arc::new(Float32Array::from(scores)) as ArrayRef;
+        let batch = batch
+            .try_with_column(SCORE_FIELD.clone(), score_col)?
+            .project_by_schema(&FTS_SCHEMA)?;
+        filter_unmatched(batch)
+    });
+
+    Ok(
+        Box::pin(RecordBatchStreamAdapter::new(FTS_SCHEMA.clone(), stream))
+            as SendableRecordBatchStream,
+    )
+}
+
+/// The frequencies of the tokens in the unindexed documents that contain any of them,
+/// keyed by row id, see [`InvertedIndex::token_freqs`].
+pub fn flat_token_freqs(
+    batch: &RecordBatch,
+    doc_col: &str,
+    tokens: &[String],
+    tokenizer: &mut tantivy::tokenizer::TextAnalyzer,
+) -> Result<Vec<(u64, FieldFreqs)>> {
+    let row_ids = batch
+        .column_by_name(ROW_ID)
+        .ok_or_else(|| Error::invalid_input("the row id column is missing", location!()))?
+        .as_primitive::<UInt64Type>();
+    let mut docs = Vec::new();
+    for (row_id, doc) in row_ids.values().iter().zip(iter_str_array(&batch[doc_col])) {
+        let Some(doc) = doc else {
+            continue;
+        };
+        let doc_tokens = collect_tokens(doc, tokenizer, None);
+        let mut freqs = vec![0; tokens.len()];
+        for doc_token in &doc_tokens {
+            for (freq, token) in freqs.iter_mut().zip(tokens) {
+                if doc_token == token {
+                    *freq += 1;
+                }
+            }
+        }
+        if freqs.iter().any(|freq| *freq > 0) {
+            docs.push((
+                *row_id,
+                FieldFreqs {
+                    num_tokens: doc_tokens.len() as u32,
+                    freqs,
+                },
+            ));
+        }
+    }
+    Ok(docs)
+}
+
+// filter out rows with score 0
+fn filter_unmatched(batch: RecordBatch) -> std::result::Result<RecordBatch, DataFusionError> {
+    let score_col = batch[SCORE_COL].as_primitive::<Float32Type>();
+    let mask = score_col
+        .iter()
+        .map(|score| score.is_some_and(|score| score > 0.0))
+        .collect::<Vec<_>>();
+    let mask = BooleanArray::from(mask);
+    Ok(arrow::compute::filter_record_batch(&batch, &mask)?)
+}
+
 pub fn is_phrase_query(query: &str) -> bool {
     query.starts_with('\"') && query.ends_with('\"')
 }
@@ -0,0 +1,877 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! A parser of Lucene-like query strings for full text search
+//!
+//! The syntax:
+//! - `rust lance`: terms, the documents matching any of them are returned by default
+//! - `title:rust`, `title:(rust OR lance)`: search the terms in the column
+//! - `"vector database"`, `"vector database"~2`: phrases, with optional slop
+//! - `rust~`, `rust~1`: fuzzy terms, with optional max edit distance
+//! - `lan*`, `l?nce`, `/lan(ce)?/`: prefix, wildcard and regex terms
+//! - `rust^2`, `(rust lance)^2`: boost the scores
+//! - `+rust`, `-draft`, `NOT draft`: required and prohibited clauses, the documents
+//!   matching a prohibited clause are excluded whatever its boost, e.g. `-draft^0.5`
+//! - `rust AND lance`, `rust && lance`, `rust OR lance`, `rust || lance`, `(...)`:
+//!   boolean operators and grouping, `AND` binds tighter than `OR`
+//!
+//! Special characters can be escaped with `\`. The groups can be nested up to
+//! [`MAX_QUERY_DEPTH`] levels.
+
+use std::str::FromStr;
+
+use lance_core::{Error, Result};
+use snafu::location;
+
+use super::query::{
+    BooleanQuery, FtsQuery, MatchQuery, Occur, Operator, PhraseQuery, PrefixQuery, RegexQuery,
+    WildcardQuery,
+};
+
+/// The max number of nested groups in a query string.
+pub const MAX_QUERY_DEPTH: usize = 64;
+
+/// Parses query strings into [`FtsQuery`].
+#[derive(Debug, Clone, Default)]
+pub struct QueryParser {
+    default_operator: Operator,
+}
+
+impl QueryParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How the clauses without boolean operators between them are combined,
+    /// `Or` by default.
+    pub fn with_default_operator(mut self, operator: Operator) -> Self {
+        self.default_operator = operator;
+        self
+    }
+
+    /// Parse the query string.
+    ///
+    /// The errors report the character position in the query where parsing failed.
+    pub fn parse(&self, query: &str) -> Result<FtsQuery> {
+        let tokens = Lexer::new(query)
+            .tokenize()
+            .map_err(|e| e.into_error(query))?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: query.chars().count(),
+            default_operator: self.default_operator,
+        };
+        parser.parse().map_err(|e| e.into_error(query))
+    }
+}
+
+impl FromStr for FtsQuery {
+    type Err = Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        QueryParser::new().parse(query)
+    }
+}
+
+#[derive(Debug)]
+struct ParseError {
+    position: usize,
+    message: String,
+}
+
+impl ParseError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+
+    fn into_error(self, query: &str) -> Error {
+        Error::invalid_input(
+            format!(
+                "failed to parse query \"{}\" at position {}: {}",
+                query, self.position, self.message
+            ),
+            location!(),
+        )
+    }
+}
+
+type ParseResult<T> = std::result::Result<T, ParseError>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Plus,
+    Minus,
+    Not,
+    And,
+    Or,
+    Field(String),
+    Term(Term),
+    Phrase(String),
+    Regex(String),
+    // fuzziness or phrase slop
+    Tilde(Option<u32>),
+    Caret(f32),
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LParen => write!(f, "'('"),
+            Self::RParen => write!(f, "')'"),
+            Self::Plus => write!(f, "'+'"),
+            Self::Minus => write!(f, "'-'"),
+            Self::Not => write!(f, "NOT"),
+            Self::And => write!(f, "AND"),
+            Self::Or => write!(f, "OR"),
+            Self::Field(field) => write!(f, "field {}", field),
+            Self::Term(term) => write!(f, "term {}", term.text),
+            Self::Phrase(phrase) => write!(f, "phrase \"{}\"", phrase),
+            Self::Regex(regex) => write!(f, "regex /{}/", regex),
+            Self::Tilde(_) => write!(f, "'~'"),
+            Self::Caret(_) => write!(f, "'^'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    // the unescaped text
+    text: String,
+    // whether there are unescaped `*` or `?`
+    has_wildcard: bool,
+    // whether there are escaped `*` or `?`
+    has_escaped_wildcard: bool,
+    // whether there are escaped characters
+    escaped: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn new(query: &str) -> Self {
+        Self {
+            chars: query.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        self.chars.get(self.pos + 1).copied()
+    }
+
+    fn tokenize(mut self) -> ParseResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+                continue;
+            }
+
+            let position = self.pos;
+            let kind = match c {
+                '(' => {
+                    self.pos += 1;
+                    TokenKind::LParen
+                }
+                ')' => {
+                    self.pos += 1;
+                    TokenKind::RParen
+                }
+                '+' => {
+                    self.pos += 1;
+                    TokenKind::Plus
+                }
+                '-' => {
+                    self.pos += 1;
+                    TokenKind::Minus
+                }
+                '!' => {
+                    self.pos += 1;
+                    TokenKind::Not
+                }
+                '&' if self.peek_next() == Some('&') => {
+                    self.pos += 2;
+                    TokenKind::And
+                }
+                '|' if self.peek_next() == Some('|') => {
+                    self.pos += 2;
+                    TokenKind::Or
+                }
+                '"' => TokenKind::Phrase(self.read_quoted('"', "phrase")?),
+                '/' => TokenKind::Regex(self.read_quoted('/', "regex")?),
+                '~' => {
+                    self.pos += 1;
+                    let digits = self.read_while(|c| c.is_ascii_digit());
+                    if digits.is_empty() {
+                        TokenKind::Tilde(None)
+                    } else {
+                        let n = digits.parse().map_err(|_| {
+                            ParseError::new(position + 1, format!("invalid number {}", digits))
+                        })?;
+                        TokenKind::Tilde(Some(n))
+                    }
+                }
+                '^' => {
+                    self.pos += 1;
+                    let digits = self.read_while(|c| c.is_ascii_digit() || c == '.');
+                    let boost = digits.parse::<f32>().map_err(|_| {
+                        ParseError::new(position + 1, "expected a non-negative number after '^'")
+                    })?;
+                    TokenKind::Caret(boost)
+                }
+                ':' => {
+                    return Err(ParseError::new(
+                        position,
+                        "expected a field name before ':'",
+                    ))
+                }
+                _ => {
+                    let term = self.read_term()?;
+                    if self.peek() == Some(':') {
+                        self.pos += 1;
+                        if term.has_wildcard {
+                            return Err(ParseError::new(
+                                position,
+                                "the field name can't contain wildcards",
+                            ));
+                        }
+                        TokenKind::Field(term.text)
+                    } else {
+                        match term.text.as_str() {
+                            "AND" if !term.escaped => TokenKind::And,
+                            "OR" if !term.escaped => TokenKind::Or,
+                            "NOT" if !term.escaped => TokenKind::Not,
+                            _ => TokenKind::Term(term),
+                        }
+                    }
+                }
+            };
+            tokens.push(Token { kind, position });
+        }
+        Ok(tokens)
+    }
+
+    fn read_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    // read the text between the delimiters, the delimiter can be escaped with `\`
+    fn read_quoted(&mut self, delimiter: char, name: &str) -> ParseResult<String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(ParseError::new(
+                        start,
+                        format!("the {} is not closed by {}", name, delimiter),
+                    ))
+                }
+                Some('\\') if self.peek_next() == Some(delimiter) => {
+                    text.push(delimiter);
+                    self.pos += 2;
+                }
+                Some(c) if c == delimiter => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn read_term(&mut self) -> ParseResult<Term> {
+        let mut term = Term {
+            text: String::new(),
+            has_wildcard: false,
+            has_escaped_wildcard: false,
+            escaped: false,
+        };
+        while let Some(c) = self.peek() {
+            match c {
+                c if c.is_whitespace() => break,
+                '(' | ')' | '"' | '^' | '~' | ':' => break,
+                '\\' => {
+                    let Some(escaped) = self.peek_next() else {
+                        return Err(ParseError::new(self.pos, "nothing to escape after '\\'"));
+                    };
+                    term.escaped = true;
+                    term.has_escaped_wildcard |= escaped == '*' || escaped == '?';
+                    term.text.push(escaped);
+                    self.pos += 2;
+                }
+                c => {
+                    term.has_wildcard |= c == '*' || c == '?';
+                    term.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(term)
+    }
+}
+
+struct Clause {
+    occur: Occur,
+    query: FtsQuery,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // the position of the end of the query
+    end: usize,
+    default_operator: Operator,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|token| &token.kind)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|token| token.position)
+            .unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_clause_start(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(
+                TokenKind::LParen
+                    | TokenKind::Plus
+                    | TokenKind::Minus
+                    | TokenKind::Not
+                    | TokenKind::Field(_)
+                    | TokenKind::Term(_)
+                    | TokenKind::Phrase(_)
+                    | TokenKind::Regex(_)
+            )
+        )
+    }
+
+    fn parse(&mut self) -> ParseResult<FtsQuery> {
+        let clauses = self.parse_clauses(None, 0)?;
+        if let Some(token) = self.peek() {
+            return Err(ParseError::new(
+                self.position(),
+                format!("unexpected {}", token),
+            ));
+        }
+        build_query(clauses, 0)
+    }
+
+    // parse the clauses until the end of the query or the group
+    fn parse_clauses(&mut self, field: Option<&str>, depth: usize) -> ParseResult<Vec<Clause>> {
+        let mut clauses = Vec::new();
+        loop {
+            match self.peek() {
+                None => break,
+                Some(TokenKind::RParen) if depth > 0 => break,
+                Some(TokenKind::RParen) => {
+                    return Err(ParseError::new(self.position(), "unmatched ')'"));
+                }
+                _ => {}
+            }
+
+            // the clauses combined by AND
+            let mut conjunction = vec![self.parse_clause(field, depth)?];
+            loop {
+                match self.peek() {
+                    Some(TokenKind::And) => {
+                        self.next();
+                    }
+                    _ if self.default_operator == Operator::And && self.is_clause_start() => {}
+                    _ => break,
+                }
+                conjunction.push(self.parse_clause(field, depth)?);
+            }
+
+            if conjunction.len() == 1 {
+                clauses.extend(conjunction);
+            } else {
+                let position = self.position();
+                let conjunction = conjunction
+                    .into_iter()
+                    .map(|mut clause| {
+                        if clause.occur == Occur::Should {
+                            clause.occur = Occur::Must;
+                        }
+                        clause
+                    })
+                    .collect();
+                clauses.push(Clause {
+                    occur: Occur::Should,
+                    query: build_query(conjunction, position)?,
+                });
+            }
+
+            if let Some(TokenKind::Or) = self.peek() {
+                self.next();
+                if !self.is_clause_start() {
+                    return Err(ParseError::new(
+                        self.position(),
+                        "expected a query after OR",
+                    ));
+                }
+            }
+        }
+        Ok(clauses)
+    }
+
+    fn parse_clause(&mut self, field: Option<&str>, depth: usize) -> ParseResult<Clause> {
+        let occur = match self.peek() {
+            Some(TokenKind::Plus) => Occur::Must,
+            Some(TokenKind::Minus | TokenKind::Not) => Occur::MustNot,
+            _ => Occur::Should,
+        };
+        if occur != Occur::Should {
+            self.next();
+        }
+
+        let (query, boost) = self.parse_primary(field, depth)?;
+        // the boost of a prohibited clause doesn't matter, its documents are excluded
+        let query = match boost {
+            Some(boost) if occur != Occur::MustNot => apply_boost(query, boost),
+            _ => query,
+        };
+        Ok(Clause { occur, query })
+    }
+
+    // parse a term, phrase or group, and its boost
+    fn parse_primary(
+        &mut self,
+        field: Option<&str>,
+        depth: usize,
+    ) -> ParseResult<(FtsQuery, Option<f32>)> {
+        let position = self.position();
+        let Some(token) = self.next() else {
+            return Err(ParseError::new(
+                position,
+                "unexpected end of the query, expected a term",
+            ));
+        };
+        let column = field.map(str::to_owned);
+        let query = match token.kind {
+            TokenKind::Field(field) => {
+                if matches!(self.peek(), Some(TokenKind::Field(_))) {
+                    return Err(ParseError::new(
+                        self.position(),
+                        "expected a term after the field",
+                    ));
+                }
+                return self.parse_primary(Some(&field), depth);
+            }
+            TokenKind::LParen => {
+                if depth >= MAX_QUERY_DEPTH {
+                    return Err(ParseError::new(
+                        position,
+                        format!(
+                            "the groups are nested too deeply, at most {} levels are supported",
+                            MAX_QUERY_DEPTH
+                        ),
+                    ));
+                }
+                let clauses = self.parse_clauses(field, depth + 1)?;
+                if !matches!(self.next().map(|token| token.kind), Some(TokenKind::RParen)) {
+                    return Err(ParseError::new(position, "the group is not closed by ')'"));
+                }
+                build_query(clauses, position)?
+            }
+            TokenKind::Phrase(phrase) => {
+                let mut query = PhraseQuery::new(phrase).with_column(column);
+                if let Some(TokenKind::Tilde(slop)) = self.peek() {
+                    let slop = slop.ok_or_else(|| {
+                        ParseError::new(self.position() + 1, "expected the slop after '~'")
+                    })?;
+                    query = query.with_slop(slop);
+                    self.next();
+                }
+                query.into()
+            }
+            TokenKind::Regex(regex) => RegexQuery::new(regex).with_column(column).into(),
+            TokenKind::Term(term) => {
+                let query = term_query(term, column, position)?;
+                if let Some(TokenKind::Tilde(fuzziness)) = self.peek() {
+                    let FtsQuery::Match(query) = query else {
+                        return Err(ParseError::new(
+                            self.position(),
+                            "fuzzy matching is not supported for wildcard terms",
+                        ));
+                    };
+                    let fuzziness = *fuzziness;
+                    self.next();
+                    query.with_fuzziness(fuzziness).into()
+                } else {
+                    query
+                }
+            }
+            kind => {
+                return Err(ParseError::new(
+                    position,
+                    format!("unexpected {}, expected a term", kind),
+                ))
+            }
+        };
+
+        let boost = match self.peek() {
+            Some(TokenKind::Caret(boost)) => {
+                let boost = *boost;
+                self.next();
+                Some(boost)
+            }
+            _ => None,
+        };
+        Ok((query, boost))
+    }
+}
+
+fn term_query(term: Term, column: Option<String>, position: usize) -> ParseResult<FtsQuery> {
+    if !term.has_wildcard {
+        return Ok(MatchQuery::new(term.text).with_column(column).into());
+    }
+    if term.has_escaped_wildcard {
+        return Err(ParseError::new(
+            position,
+            "escaped '*' or '?' can't be used in wildcard terms",
+        ));
+    }
+
+    let text = term.text;
+    match text.strip_suffix('*') {
+        Some(prefix) if !prefix.contains(['*', '?']) => {
+            if prefix.is_empty() {
+                return Err(ParseError::new(position, "the prefix must not be empty"));
+            }
+            Ok(PrefixQuery::new(prefix.to_owned())
+                .with_column(column)
+                .into())
+        }
+        _ => Ok(WildcardQuery::new(text).with_column(column).into()),
+    }
+}
+
+// combine the clauses into a query
+fn build_query(clauses: Vec<Clause>, position: usize) -> ParseResult<FtsQuery> {
+    if clauses.is_empty() {
+        return Err(ParseError::new(position, "expected a query"));
+    }
+
+    let mut clauses = clauses
+        .into_iter()
+        .map(|clause| (clause.occur, clause.query))
+        .collect::<Vec<_>>();
+    if clauses.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+        return Err(ParseError::new(
+            position,
+            "the query must contain at least one clause that is not prohibited",
+        ));
+    }
+
+    if clauses.len() == 1 {
+        Ok(clauses.pop().unwrap().1)
+    } else {
+        Ok(BooleanQuery::new(clauses).into())
+    }
+}
+
+// multiply the scores of the query by the boost
+fn apply_boost(query: FtsQuery, boost: f32) -> FtsQuery {
+    match query {
+        FtsQuery::Match(mut query) => {
+            query.boost *= boost;
+            query.into()
+        }
+        FtsQuery::Phrase(mut query) => {
+            query.boost *= boost;
+            query.into()
+        }
+        FtsQuery::Prefix(mut query) => {
+            query.boost *= boost;
+            query.into()
+        }
+        FtsQuery::Wildcard(mut query) => {
+            query.boost *= boost;
+            query.into()
+        }
+        FtsQuery::Regex(mut query) => {
+            query.boost *= boost;
+            query.into()
+        }
+        FtsQuery::MultiMatch(mut query) => {
+            for match_query in &mut query.match_queries {
+                match_query.boost *= boost;
+            }
+            query.into()
+        }
+        // the score of a boost query is the score of its positive query, maybe demoted
+        FtsQuery::Boost(mut query) => {
+            query.positive = Box::new(apply_boost(*query.positive, boost));
+            query.into()
+        }
+        // the score of a boolean query is the sum of the scores of its must and should queries
+        FtsQuery::Boolean(mut query) => {
+            query.should = query
+                .should
+                .into_iter()
+                .map(|query| apply_boost(query, boost))
+                .collect();
+            query.must = query
+                .must
+                .into_iter()
+                .map(|query| apply_boost(query, boost))
+                .collect();
+            query.into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> FtsQuery {
+        query.parse().unwrap()
+    }
+
+    fn parse_err(query: &str) -> String {
+        query.parse::<FtsQuery>().unwrap_err().to_string()
+    }
+
+    fn term(text: &str) -> MatchQuery {
+        MatchQuery::new(text.to_owned())
+    }
+
+    #[test]
+    fn test_parse_terms() {
+        assert_eq!(parse("rust"), term("rust").into());
+        assert_eq!(
+            parse("title:rust"),
+            term("rust").with_column(Some("title".to_owned())).into()
+        );
+        assert_eq!(
+            parse("rust lance"),
+            BooleanQuery::new([
+                (Occur::Should, term("rust").into()),
+                (Occur::Should, term("lance").into()),
+            ])
+            .into()
+        );
+        assert_eq!(
+            QueryParser::new()
+                .with_default_operator(Operator::And)
+                .parse("rust lance")
+                .unwrap(),
+            BooleanQuery::new([
+                (Occur::Must, term("rust").into()),
+                (Occur::Must, term("lance").into()),
+            ])
+            .into()
+        );
+        assert_eq!(parse("rust~"), term("rust").with_fuzziness(None).into());
+        assert_eq!(parse("rust~1"), term("rust").with_fuzziness(Some(1)).into());
+        assert_eq!(parse("rust^2"), term("rust").with_boost(2.0).into());
+        assert_eq!(parse(r"a\:b\*"), term("a:b*").into());
+        assert_eq!(parse("state-of-the-art"), term("state-of-the-art").into());
+        assert_eq!(parse(r"\AND"), term("AND").into());
+
+        assert_eq!(
+            parse("\"vector database\"~2"),
+            PhraseQuery::new("vector database".to_owned())
+                .with_slop(2)
+                .into()
+        );
+        assert_eq!(
+            parse(r#""say \"hi\""^1.5"#),
+            PhraseQuery::new("say \"hi\"".to_owned())
+                .with_boost(1.5)
+                .into()
+        );
+        assert_eq!(parse("sku12*"), PrefixQuery::new("sku12".to_owned()).into());
+        assert_eq!(
+            parse("s?u*12"),
+            WildcardQuery::new("s?u*12".to_owned()).into()
+        );
+        assert_eq!(
+            parse(r"name:/sku[0-9]+\/x/"),
+            RegexQuery::new("sku[0-9]+/x".to_owned())
+                .with_column(Some("name".to_owned()))
+                .into()
+        );
+    }
+
+    #[test]
+    fn test_parse_boolean() {
+        let title =
+            |text: &str| -> FtsQuery { term(text).with_column(Some("title".to_owned())).into() };
+        let query = parse(r#"title:(rust OR lance) AND "vector database"~2 -draft^0.5"#);
+        let expected = BooleanQuery::new([
+            (
+                Occur::Should,
+                BooleanQuery::new([
+                    (
+                        Occur::Must,
+                        BooleanQuery::new([
+                            (Occur::Should, title("rust")),
+                            (Occur::Should, title("lance")),
+                        ])
+                        .into(),
+                    ),
+                    (
+                        Occur::Must,
+                        PhraseQuery::new("vector database".to_owned())
+                            .with_slop(2)
+                            .into(),
+                    ),
+                ])
+                .into(),
+            ),
+            // the boost of a prohibited clause is ignored, the documents are excluded
+            (Occur::MustNot, term("draft").into()),
+        ]);
+        assert_eq!(query, expected.into());
+        assert_eq!(parse("rust -draft^0.5"), parse("rust -draft"));
+
+        // AND binds tighter than OR
+        assert_eq!(
+            parse("a AND b OR c"),
+            BooleanQuery::new([
+                (
+                    Occur::Should,
+                    BooleanQuery::new([
+                        (Occur::Must, term("a").into()),
+                        (Occur::Must, term("b").into()),
+                    ])
+                    .into()
+                ),
+                (Occur::Should, term("c").into()),
+            ])
+            .into()
+        );
+        assert_eq!(
+            parse("+a b -c NOT d"),
+            BooleanQuery::new([
+                (Occur::Must, term("a").into()),
+                (Occur::Should, term("b").into()),
+                (Occur::MustNot, term("c").into()),
+                (Occur::MustNot, term("d").into()),
+            ])
+            .into()
+        );
+        // the boost of a group is applied to its terms
+        assert_eq!(
+            parse("title:(a content:b)^2"),
+            BooleanQuery::new([
+                (
+                    Occur::Should,
+                    term("a")
+                        .with_column(Some("title".to_owned()))
+                        .with_boost(2.0)
+                        .into()
+                ),
+                (
+                    Occur::Should,
+                    term("b")
+                        .with_column(Some("content".to_owned()))
+                        .with_boost(2.0)
+                        .into()
+                ),
+            ])
+            .into()
+        );
+        assert_eq!(parse("(((a)))"), term("a").into());
+    }
+
+    #[test]
+    fn test_parse_nesting_limit() {
+        let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(parse(&nested(MAX_QUERY_DEPTH)), term("a").into());
+
+        let err = parse_err(&nested(MAX_QUERY_DEPTH + 1));
+        assert!(
+            err.contains(&format!(
+                "at position {}: the groups are nested too deeply",
+                MAX_QUERY_DEPTH
+            )),
+            "{}",
+            err
+        );
+        // deeply nested queries are rejected rather than overflowing the stack
+        let err = parse_err(&"(".repeat(100_000));
+        assert!(err.contains("the groups are nested too deeply"), "{}", err);
+        let err = parse_err(&"a AND (".repeat(100_000));
+        assert!(err.contains("the groups are nested too deeply"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (query, position, message) in [
+            ("(rust", 0, "the group is not closed by ')'"),
+            ("rust)", 4, "unmatched ')'"),
+            ("\"rust", 0, "the phrase is not closed by \""),
+            ("/rust", 0, "the regex is not closed by /"),
+            (
+                "rust AND",
+                8,
+                "unexpected end of the query, expected a term",
+            ),
+            ("rust OR", 7, "expected a query after OR"),
+            ("rust^", 5, "expected a non-negative number after '^'"),
+            ("-rust", 0, "the query must contain at least one clause"),
+            ("a (-b)", 2, "the query must contain at least one clause"),
+            ("()", 0, "expected a query"),
+            (":rust", 0, "expected a field name before ':'"),
+            ("*", 0, "the prefix must not be empty"),
+            (
+                "ru*st~",
+                5,
+                "fuzzy matching is not supported for wildcard terms",
+            ),
+            ("AND rust", 0, "unexpected AND, expected a term"),
+            ("title:content:rust", 6, "expected a term after the field"),
+        ] {
+            let err = parse_err(query);
+            assert!(
+                err.contains(&format!("at position {}: {}", position, message)),
+                "query: {}, error: {}",
+                query,
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Matching the tokens of the inverted index by prefix, wildcard and regex patterns
+
+use lance_core::{Error, Result};
+use regex_automata::dfa::{dense, Automaton, StartKind};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::{start, syntax};
+use regex_automata::{Anchored, MatchKind};
+use snafu::location;
+
+// the limit of the memory used by the DFA of a pattern
+const DFA_SIZE_LIMIT: usize = 10 * 1024 * 1024;
+
+/// A DFA that matches whole tokens, it's used to search the token dictionary
+/// of the inverted index as an [`fst::Automaton`].
+#[derive(Debug, Clone)]
+pub struct TokenPattern {
+    dfa: dense::DFA<Vec<u32>>,
+    start: StateID,
+}
+
+impl TokenPattern {
+    /// Matches the tokens starting with the prefix
+    pub fn prefix(prefix: &str, case_insensitive: bool) -> Result<Self> {
+        let mut regex = escape(prefix);
+        regex.push_str("(?s:.)*");
+        Self::try_new(&regex, case_insensitive)
+    }
+
+    /// Matches the tokens by the wildcard pattern,
+    /// `*` matches any sequence of characters and `?` matches any single character
+    pub fn wildcard(pattern: &str, case_insensitive: bool) -> Result<Self> {
+        let mut regex = String::with_capacity(pattern.len() * 2);
+        for c in pattern.chars() {
+            match c {
+                '*' => regex.push_str("(?s:.)*"),
+                '?' => regex.push_str("(?s:.)"),
+                c => push_escaped(&mut regex, c),
+            }
+        }
+        Self::try_new(&regex, case_insensitive)
+    }
+
+    /// Matches the tokens that fully match the regular expression
+    pub fn regex(pattern: &str, case_insensitive: bool) -> Result<Self> {
+        Self::try_new(pattern, case_insensitive)
+    }
+
+    fn try_new(regex: &str, case_insensitive: bool) -> Result<Self> {
+        let dfa = dense::Builder::new()
+            .configure(
+                dense::Config::new()
+                    .match_kind(MatchKind::All)
+                    .start_kind(StartKind::Anchored)
+                    .dfa_size_limit(Some(DFA_SIZE_LIMIT))
+                    .determinize_size_limit(Some(DFA_SIZE_LIMIT)),
+            )
+            .syntax(syntax::Config::new().case_insensitive(case_insensitive))
+            .build(&format!("(?:{})$", regex))
+            .map_err(|e| {
+                Error::invalid_input(
+                    format!("failed to build the pattern {}: {}", regex, e),
+                    location!(),
+                )
+            })?;
+        let start = dfa
+            .start_state(&start::Config::new().anchored(Anchored::Yes))
+            .map_err(|e| {
+                Error::invalid_input(
+                    format!("failed to build the pattern {}: {}", regex, e),
+                    location!(),
+                )
+            })?;
+        Ok(Self { dfa, start })
+    }
+
+    /// Whether the whole token matches the pattern
+    pub fn is_match(&self, token: &str) -> bool {
+        let mut state = self.start;
+        for byte in token.bytes() {
+            state = self.dfa.next_state(state, byte);
+            if self.dfa.is_dead_state(state) {
+                return false;
+            }
+        }
+        fst::Automaton::is_match(self, &state)
+    }
+}
+
+impl fst::Automaton for TokenPattern {
+    type State = StateID;
+
+    fn start(&self) -> Self::State {
+        self.start
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        // the DFA reports the matches with one byte delay,
+        // so feed the end of input to see whether the token ends with a match
+        self.dfa.is_match_state(self.dfa.next_eoi_state(*state))
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        !self.dfa.is_dead_state(*state)
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        self.dfa.next_state(*state, byte)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        push_escaped(&mut escaped, c);
+    }
+    escaped
+}
+
+fn push_escaped(regex: &mut String, c: char) {
+    if "\\.+*?()|[]{}^$#&-~".contains(c) {
+        regex.push('\\');
+    }
+    regex.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use fst::{IntoStreamer, Streamer};
+
+    use super::*;
+
+    #[test]
+    fn test_token_pattern() {
+        let prefix = TokenPattern::prefix("lan", false).unwrap();
+        assert!(prefix.is_match("lan"));
+        assert!(prefix.is_match("lance"));
+        assert!(!prefix.is_match("lake"));
+        assert!(!prefix.is_match("Lance"));
+        assert!(TokenPattern::prefix("lan", true).unwrap().is_match("Lance"));
+        // the meta characters are matched literally
+        let prefix = TokenPattern::prefix("a.b", false).unwrap();
+        assert!(prefix.is_match("a.bc"));
+        assert!(!prefix.is_match("axbc"));
+
+        let wildcard = TokenPattern::wildcard("l?n*e", false).unwrap();
+        assert!(wildcard.is_match("lance"));
+        assert!(wildcard.is_match("line"));
+        assert!(!wildcard.is_match("lne"));
+        assert!(!wildcard.is_match("lances"));
+
+        let regex = TokenPattern::regex("sku-[0-9]+", false).unwrap();
+        assert!(regex.is_match("sku-123"));
+        assert!(!regex.is_match("sku-12a"));
+        assert!(!regex.is_match("xsku-1"));
+        assert!(TokenPattern::regex("(", false).is_err());
+    }
+
+    #[test]
+    fn test_search_fst() {
+        let map = fst::Map::from_iter(
+            ["apple", "apply", "banana", "lance", "lancedb", "lane"]
+                .iter()
+                .enumerate()
+                .map(|(i, token)| (*token, i as u64)),
+        )
+        .unwrap();
+        let search = |pattern: TokenPattern| {
+            let mut stream = map.search(pattern).into_stream();
+            let mut tokens = Vec::new();
+            while let Some((token, _)) = stream.next() {
+                tokens.push(String::from_utf8(token.to_vec()).unwrap());
+            }
+            tokens
+        };
+        assert_eq!(
+            search(TokenPattern::prefix("lance", false).unwrap()),
+            vec!["lance", "lancedb"]
+        );
+        assert_eq!(
+            search(TokenPattern::wildcard("appl?", false).unwrap()),
+            vec!["apple", "apply"]
+        );
+        assert_eq!(
+            search(TokenPattern::regex("lanc?e", false).unwrap()),
+            vec!["lance", "lane"]
+        );
+    }
+}
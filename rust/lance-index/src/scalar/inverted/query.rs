@@ -8,6 +8,9 @@ use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize};
 use snafu::location;
 
+use super::pattern::TokenPattern;
+use super::scorer::ScoringModel;
+
 #[derive(Debug, Clone)]
 pub struct FtsSearchParams {
     pub limit: Option<usize>,
@@ -19,6 +22,8 @@ pub struct FtsSearchParams {
     pub phrase_slop: Option<u32>,
     /// The number of beginning characters being unchanged for fuzzy matching.
     pub prefix_length: u32,
+    /// How the matched documents are scored
+    pub scoring: ScoringModel,
 }
 
 impl FtsSearchParams {
@@ -30,6 +35,7 @@ impl FtsSearchParams {
             max_expansions: 50,
             phrase_slop: None,
             prefix_length: 0,
+            scoring: ScoringModel::default(),
         }
     }
 
@@ -62,6 +68,11 @@ impl FtsSearchParams {
         self.prefix_length = prefix_length;
         self
     }
+
+    pub fn with_scoring(mut self, scoring: ScoringModel) -> Self {
+        self.scoring = scoring;
+        self
+    }
 }
 
 impl Default for FtsSearchParams {
@@ -106,6 +117,9 @@ pub enum FtsQuery {
     // leaf queries
     Match(MatchQuery),
     Phrase(PhraseQuery),
+    Prefix(PrefixQuery),
+    Wildcard(WildcardQuery),
+    Regex(RegexQuery),
 
     // compound queries
     Boost(BoostQuery),
@@ -118,6 +132,9 @@ impl std::fmt::Display for FtsQuery {
         match self {
             Self::Match(query) => write!(f, "Match({:?})", query),
             Self::Phrase(query) => write!(f, "Phrase({:?})", query),
+            Self::Prefix(query) => write!(f, "Prefix({:?})", query),
+            Self::Wildcard(query) => write!(f, "Wildcard({:?})", query),
+            Self::Regex(query) => write!(f, "Regex({:?})", query),
             Self::Boost(query) => write!(
                 f,
                 "Boosting(positive={}, negative={}, negative_boost={})",
@@ -127,8 +144,8 @@ impl std::fmt::Display for FtsQuery {
             Self::Boolean(query) => {
                 write!(
                     f,
-                    "Boolean(must={:?}, should={:?})",
-                    query.must, query.should
+                    "Boolean(must={:?}, should={:?}, must_not={:?})",
+                    query.must, query.should, query.must_not
                 )
             }
         }
@@ -140,6 +157,9 @@ impl FtsQueryNode for FtsQuery {
         match self {
             Self::Match(query) => query.columns(),
             Self::Phrase(query) => query.columns(),
+            Self::Prefix(query) => query.columns(),
+            Self::Wildcard(query) => query.columns(),
+            Self::Regex(query) => query.columns(),
             Self::Boost(query) => {
                 let mut columns = query.positive.columns();
                 columns.extend(query.negative.columns());
@@ -152,16 +172,7 @@ impl FtsQueryNode for FtsQuery {
                 }
                 columns
             }
-            Self::Boolean(query) => {
-                let mut columns = HashSet::new();
-                for query in &query.must {
-                    columns.extend(query.columns());
-                }
-                for query in &query.should {
-                    columns.extend(query.columns());
-                }
-                columns
-            }
+            Self::Boolean(query) => query.columns(),
         }
     }
 }
@@ -171,6 +182,9 @@ impl FtsQuery {
         match self {
             Self::Match(query) => query.terms.clone(),
             Self::Phrase(query) => format!("\"{}\"", query.terms), // Phrase queries are quoted
+            Self::Prefix(query) => format!("{}*", query.prefix),
+            Self::Wildcard(query) => query.pattern.clone(),
+            Self::Regex(query) => format!("/{}/", query.pattern),
             Self::Boost(query) => query.positive.query(),
             Self::MultiMatch(query) => query.match_queries[0].terms.clone(),
             Self::Boolean(_) => {
@@ -180,10 +194,23 @@ impl FtsQuery {
         }
     }
 
+    /// The prefix, wildcard or regex query, which matches the tokens by a pattern
+    pub fn as_pattern_query(&self) -> Option<&dyn PatternQueryNode> {
+        match self {
+            Self::Prefix(query) => Some(query),
+            Self::Wildcard(query) => Some(query),
+            Self::Regex(query) => Some(query),
+            _ => None,
+        }
+    }
+
     pub fn is_missing_column(&self) -> bool {
         match self {
             Self::Match(query) => query.column.is_none(),
             Self::Phrase(query) => query.column.is_none(),
+            Self::Prefix(query) => query.column.is_none(),
+            Self::Wildcard(query) => query.column.is_none(),
+            Self::Regex(query) => query.column.is_none(),
             Self::Boost(query) => {
                 query.positive.is_missing_column() || query.negative.is_missing_column()
             }
@@ -191,6 +218,7 @@ impl FtsQuery {
             Self::Boolean(query) => {
                 query.must.iter().any(|q| q.is_missing_column())
                     || query.should.iter().any(|q| q.is_missing_column())
+                    || query.must_not.iter().any(|q| q.is_missing_column())
             }
         }
     }
@@ -199,6 +227,9 @@ impl FtsQuery {
         match self {
             Self::Match(query) => Self::Match(query.with_column(Some(column))),
             Self::Phrase(query) => Self::Phrase(query.with_column(Some(column))),
+            Self::Prefix(query) => Self::Prefix(query.with_column(Some(column))),
+            Self::Wildcard(query) => Self::Wildcard(query.with_column(Some(column))),
+            Self::Regex(query) => Self::Regex(query.with_column(Some(column))),
             Self::Boost(query) => {
                 let positive = query.positive.with_column(column.clone());
                 let negative = query.negative.with_column(column);
@@ -227,7 +258,16 @@ impl FtsQuery {
                     .into_iter()
                     .map(|q| q.with_column(column.clone()))
                     .collect();
-                Self::Boolean(BooleanQuery { must, should })
+                let must_not = query
+                    .must_not
+                    .into_iter()
+                    .map(|q| q.with_column(column.clone()))
+                    .collect();
+                Self::Boolean(BooleanQuery {
+                    must,
+                    should,
+                    must_not,
+                })
             }
         }
     }
@@ -245,6 +285,24 @@ impl From<PhraseQuery> for FtsQuery {
     }
 }
 
+impl From<PrefixQuery> for FtsQuery {
+    fn from(query: PrefixQuery) -> Self {
+        Self::Prefix(query)
+    }
+}
+
+impl From<WildcardQuery> for FtsQuery {
+    fn from(query: WildcardQuery) -> Self {
+        Self::Wildcard(query)
+    }
+}
+
+impl From<RegexQuery> for FtsQuery {
+    fn from(query: RegexQuery) -> Self {
+        Self::Regex(query)
+    }
+}
+
 impl From<BoostQuery> for FtsQuery {
     fn from(query: BoostQuery) -> Self {
         Self::Boost(query)
@@ -378,6 +436,8 @@ pub struct PhraseQuery {
     pub terms: String,
     #[serde(default = "u32::default")]
     pub slop: u32,
+    #[serde(default = "MatchQuery::default_boost")]
+    pub boost: f32,
 }
 
 impl PhraseQuery {
@@ -386,9 +446,15 @@ impl PhraseQuery {
             column: None,
             terms,
             slop: 0,
+            boost: 1.0,
         }
     }
 
+    pub fn with_boost(mut self, boost: f32) -> Self {
+        self.boost = boost;
+        self
+    }
+
     pub fn with_column(mut self, column: Option<String>) -> Self {
         self.column = column;
         self
@@ -410,6 +476,126 @@ impl FtsQueryNode for PhraseQuery {
     }
 }
 
+/// The term queries that match the indexed tokens by a [`TokenPattern`],
+/// the matched tokens are searched like the terms of a match query with the `Or` operator.
+pub trait PatternQueryNode: std::fmt::Debug + Send + Sync {
+    fn column(&self) -> Option<&str>;
+    fn boost(&self) -> f32;
+    /// The maximum number of tokens the pattern expands to
+    fn max_expansions(&self) -> usize;
+    fn token_pattern(&self, case_insensitive: bool) -> Result<TokenPattern>;
+}
+
+macro_rules! impl_pattern_query {
+    ($query:ident, $value:ident, $pattern:path) => {
+        impl $query {
+            pub fn new($value: String) -> Self {
+                Self {
+                    column: None,
+                    $value,
+                    boost: 1.0,
+                    max_expansions: 50,
+                }
+            }
+
+            pub fn with_column(mut self, column: Option<String>) -> Self {
+                self.column = column;
+                self
+            }
+
+            pub fn with_boost(mut self, boost: f32) -> Self {
+                self.boost = boost;
+                self
+            }
+
+            pub fn with_max_expansions(mut self, max_expansions: usize) -> Self {
+                self.max_expansions = max_expansions;
+                self
+            }
+        }
+
+        impl FtsQueryNode for $query {
+            fn columns(&self) -> HashSet<String> {
+                let mut columns = HashSet::new();
+                if let Some(column) = &self.column {
+                    columns.insert(column.clone());
+                }
+                columns
+            }
+        }
+
+        impl PatternQueryNode for $query {
+            fn column(&self) -> Option<&str> {
+                self.column.as_deref()
+            }
+
+            fn boost(&self) -> f32 {
+                self.boost
+            }
+
+            fn max_expansions(&self) -> usize {
+                self.max_expansions
+            }
+
+            fn token_pattern(&self, case_insensitive: bool) -> Result<TokenPattern> {
+                $pattern(&self.$value, case_insensitive)
+            }
+        }
+    };
+}
+
+/// Matches the tokens starting with the prefix, e.g. `sku12*`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefixQuery {
+    // The column to search in.
+    // If None, it will be determined at query time.
+    pub column: Option<String>,
+    pub prefix: String,
+    #[serde(default = "MatchQuery::default_boost")]
+    pub boost: f32,
+    /// The maximum number of tokens to expand the prefix to.
+    /// Default to 50.
+    #[serde(default = "MatchQuery::default_max_expansions")]
+    pub max_expansions: usize,
+}
+
+impl_pattern_query!(PrefixQuery, prefix, TokenPattern::prefix);
+
+/// Matches the tokens by a wildcard pattern, e.g. `sk?12*`.
+/// `*` matches any sequence of characters and `?` matches any single character.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WildcardQuery {
+    // The column to search in.
+    // If None, it will be determined at query time.
+    pub column: Option<String>,
+    pub pattern: String,
+    #[serde(default = "MatchQuery::default_boost")]
+    pub boost: f32,
+    /// The maximum number of tokens to expand the pattern to.
+    /// Default to 50.
+    #[serde(default = "MatchQuery::default_max_expansions")]
+    pub max_expansions: usize,
+}
+
+impl_pattern_query!(WildcardQuery, pattern, TokenPattern::wildcard);
+
+/// Matches the tokens that fully match the regular expression, e.g. `sku[0-9]+`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegexQuery {
+    // The column to search in.
+    // If None, it will be determined at query time.
+    pub column: Option<String>,
+    pub pattern: String,
+    #[serde(default = "MatchQuery::default_boost")]
+    pub boost: f32,
+    /// The maximum number of tokens to expand the pattern to.
+    /// Default to 50.
+    #[serde(default = "MatchQuery::default_max_expansions")]
+    pub max_expansions: usize,
+}
+
+impl_pattern_query!(RegexQuery, pattern, TokenPattern::regex);
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BoostQuery {
     pub positive: Box<FtsQuery>,
@@ -543,9 +729,11 @@ impl FtsQueryNode for MultiMatchQuery {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Occur {
     Should,
     Must,
+    MustNot,
 }
 
 impl TryFrom<&str> for Occur {
@@ -554,6 +742,7 @@ impl TryFrom<&str> for Occur {
         match value.to_ascii_uppercase().as_str() {
             "SHOULD" => Ok(Self::Should),
             "MUST" => Ok(Self::Must),
+            "MUST_NOT" => Ok(Self::MustNot),
             _ => Err(Error::invalid_input(
                 format!("Invalid occur value: {}", value),
                 location!(),
@@ -566,20 +755,28 @@ impl TryFrom<&str> for Occur {
 pub struct BooleanQuery {
     pub should: Vec<FtsQuery>,
     pub must: Vec<FtsQuery>,
-    // TODO: support must_not
+    /// The documents matching any of these queries are excluded
+    #[serde(default)]
+    pub must_not: Vec<FtsQuery>,
 }
 
 impl BooleanQuery {
     pub fn new(iter: impl IntoIterator<Item = (Occur, FtsQuery)>) -> Self {
         let mut should = Vec::new();
         let mut must = Vec::new();
+        let mut must_not = Vec::new();
         for (occur, query) in iter {
             match occur {
                 Occur::Should => should.push(query),
                 Occur::Must => must.push(query),
+                Occur::MustNot => must_not.push(query),
             }
         }
-        Self { should, must }
+        Self {
+            should,
+            must,
+            must_not,
+        }
     }
 
     pub fn with_should(mut self, query: FtsQuery) -> Self {
@@ -591,6 +788,11 @@ impl BooleanQuery {
         self.must.push(query);
         self
     }
+
+    pub fn with_must_not(mut self, query: FtsQuery) -> Self {
+        self.must_not.push(query);
+        self
+    }
 }
 
 impl FtsQueryNode for BooleanQuery {
@@ -602,6 +804,9 @@ impl FtsQueryNode for BooleanQuery {
         for query in &self.must {
             columns.extend(query.columns());
         }
+        for query in &self.must_not {
+            columns.extend(query.columns());
+        }
         columns
     }
 }
@@ -675,6 +880,27 @@ pub fn fill_fts_query_column(
                 }
             }
         }
+        FtsQuery::Prefix(_) | FtsQuery::Wildcard(_) | FtsQuery::Regex(_) => {
+            match columns.len() {
+                0 => {
+                    Err(Error::invalid_input(
+                        "Cannot perform full text search unless an INVERTED index has been created on at least one column".to_string(),
+                        location!(),
+                    ))
+                }
+                1 => Ok(query.clone().with_column(columns[0].clone())),
+                _ => {
+                    // search the pattern in all columns
+                    let should = columns
+                        .iter()
+                        .map(|column| query.clone().with_column(column.clone()))
+                        .collect::<Vec<_>>();
+                    Ok(FtsQuery::Boolean(BooleanQuery::new(
+                        should.into_iter().map(|query| (Occur::Should, query)),
+                    )))
+                }
+            }
+        }
        FtsQuery::Boost(boost_query) => {
             let positive = fill_fts_query_column(&boost_query.positive, columns, replace)?;
             let negative = fill_fts_query_column(&boost_query.negative, columns, replace)?;
@@ -712,7 +938,16 @@ pub fn fill_fts_query_column(
                 .iter()
                 .map(|query| fill_fts_query_column(query, columns, replace))
                 .collect::<Result<Vec<_>>>()?;
-            Ok(FtsQuery::Boolean(BooleanQuery { must, should }))
+            let must_not = bool_query
+                .must_not
+                .iter()
+                .map(|query| fill_fts_query_column(query, columns, replace))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(FtsQuery::Boolean(BooleanQuery {
+                must,
+                should,
+                must_not,
+            }))
         }
     }
 }
@@ -781,4 +1016,47 @@ mod tests {
         let query: PhraseQuery = serde_json::from_value(query).unwrap();
         assert_eq!(query, expected);
     }
+
+    #[test]
+    fn test_pattern_query_serde() {
+        use super::*;
+        use serde_json::json;
+
+        let query = json!({
+            "prefix": {
+                "prefix": "sku12",
+                "column": "text",
+            }
+        });
+        let expected = FtsQuery::Prefix(
+            PrefixQuery::new("sku12".to_string()).with_column(Some("text".to_string())),
+        );
+        let query: FtsQuery = serde_json::from_value(query).unwrap();
+        assert_eq!(query, expected);
+
+        let query = WildcardQuery::new("sk?12*".to_string())
+            .with_boost(2.0)
+            .with_max_expansions(10);
+        let serialized = serde_json::to_value(FtsQuery::from(query)).unwrap();
+        let expected = json!({
+            "wildcard": {
+                "column": null,
+                "pattern": "sk?12*",
+                "boost": 2.0,
+                "max_expansions": 10,
+            }
+        });
+        assert_eq!(serialized, expected);
+
+        let query: FtsQuery = serde_json::from_value(json!({
+            "regex": {
+                "pattern": "sku[0-9]+",
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            query,
+            FtsQuery::Regex(RegexQuery::new("sku[0-9]+".to_string()))
+        );
+    }
 }
@@ -1,6 +1,14 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-FileCopyrightText: Copyright The Lance Authors
 
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use lance_core::{Error, Result};
+use snafu::location;
+
+use super::query::Operator;
 use super::InvertedPartition;
 
 // the Scorer trait is used to calculate the score of a token in a document
@@ -18,17 +26,38 @@ pub trait Scorer: Send + Sync {
     }
 }
 
-// BM25 parameters
-pub const K1: f32 = 1.2;
-pub const B: f32 = 0.75;
+impl<S: Scorer + ?Sized> Scorer for Box<S> {
+    fn query_weight(&self, token: &str) -> f32 {
+        self.as_ref().query_weight(token)
+    }
 
-pub struct BM25Scorer<'a> {
+    fn doc_weight(&self, freq: u32, doc_tokens: u32) -> f32 {
+        self.as_ref().doc_weight(freq, doc_tokens)
+    }
+
+    fn score(&self, token: &str, freq: u32, doc_tokens: u32) -> f32 {
+        self.as_ref().score(token, freq, doc_tokens)
+    }
+}
+
+/// The statistics of the indexed documents, which the scores are calculated with.
+pub trait CorpusStats: Send + Sync {
+    /// The number of documents
+    fn num_docs(&self) -> usize;
+    /// The average number of tokens in a document
+    fn avgdl(&self) -> f32;
+    /// The number of documents that contain the token
+    fn nq(&self, token: &str) -> usize;
+}
+
+/// The statistics of the documents in the partitions of an inverted index
+pub struct IndexStats<'a> {
     partitions: Vec<&'a InvertedPartition>,
     num_docs: usize,
     avgdl: f32,
 }
 
-impl<'a> BM25Scorer<'a> {
+impl<'a> IndexStats<'a> {
     pub fn new(partitions: impl Iterator<Item = &'a InvertedPartition>) -> Self {
         let partitions = partitions.collect::<Vec<_>>();
         let num_docs = partitions.iter().map(|p| p.docs.len()).sum();
@@ -43,17 +72,18 @@ impl<'a> BM25Scorer<'a> {
             avgdl,
         }
     }
+}
 
-    pub fn num_docs(&self) -> usize {
+impl CorpusStats for IndexStats<'_> {
+    fn num_docs(&self) -> usize {
         self.num_docs
     }
 
-    pub fn avgdl(&self) -> f32 {
+    fn avgdl(&self) -> f32 {
         self.avgdl
     }
 
-    // the number of documents that contain the token
-    pub fn nq(&self, token: &str) -> usize {
+    fn nq(&self, token: &str) -> usize {
         self.partitions
             .iter()
             .map(|part| {
@@ -67,20 +97,326 @@ impl<'a> BM25Scorer<'a> {
     }
 }
 
+/// Creates the scorers of [`ScoringModel::Custom`].
+pub trait ScorerFactory: Debug + Send + Sync {
+    fn create_scorer<'a>(&self, stats: Box<dyn CorpusStats + 'a>) -> Box<dyn Scorer + 'a>;
+}
+
+// BM25 parameters
+pub const K1: f32 = 1.2;
+pub const B: f32 = 0.75;
+
+/// How the documents matched by a full text search are scored.
+#[derive(Debug, Clone)]
+pub enum ScoringModel {
+    /// Okapi BM25
+    BM25 { k1: f32, b: f32 },
+    /// BM25 with per-field weights.
+    ///
+    /// A `MultiMatchQuery` is scored by [`BM25FScorer`]: the frequencies of a term
+    /// in the fields are normalized by the field lengths, weighted and summed
+    /// before the saturation by `k1`. The weight of a field is its weight here
+    /// multiplied by the boost of the field in the query, and defaults to 1.0.
+    /// The queries on a single field are scored by BM25.
+    BM25F {
+        k1: f32,
+        b: f32,
+        field_weights: HashMap<String, f32>,
+    },
+    /// `idf * sqrt(freq / doc_tokens)`, where `idf = ln((num_docs + 1) / (nq + 1)) + 1`
+    TfIdf,
+    /// Every query term in a matched document scores 1.0, for filtering without ranking
+    Constant,
+    /// A scorer provided by the user
+    Custom(Arc<dyn ScorerFactory>),
+}
+
+impl Default for ScoringModel {
+    fn default() -> Self {
+        Self::BM25 { k1: K1, b: B }
+    }
+}
+
+impl PartialEq for ScoringModel {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::BM25 { k1, b }, Self::BM25 { k1: k1_, b: b_ }) => k1 == k1_ && b == b_,
+            (
+                Self::BM25F {
+                    k1,
+                    b,
+                    field_weights,
+                },
+                Self::BM25F {
+                    k1: k1_,
+                    b: b_,
+                    field_weights: field_weights_,
+                },
+            ) => k1 == k1_ && b == b_ && field_weights == field_weights_,
+            (Self::TfIdf, Self::TfIdf) => true,
+            (Self::Constant, Self::Constant) => true,
+            (Self::Custom(factory), Self::Custom(factory_)) => Arc::ptr_eq(factory, factory_),
+            _ => false,
+        }
+    }
+}
+
+impl ScoringModel {
+    /// BM25 with the given `k1` and `b`, `k1` must be non-negative and `b` must be in `[0, 1]`
+    pub fn bm25(k1: f32, b: f32) -> Result<Self> {
+        Self::check_bm25_params(k1, b)?;
+        Ok(Self::BM25 { k1, b })
+    }
+
+    /// BM25F with the given `k1`, `b` and field weights, the weights must be non-negative
+    pub fn bm25f(k1: f32, b: f32, field_weights: HashMap<String, f32>) -> Result<Self> {
+        Self::check_bm25_params(k1, b)?;
+        if let Some((field, weight)) = field_weights.iter().find(|(_, weight)| **weight < 0.0) {
+            return Err(Error::invalid_input(
+                format!(
+                    "the weight of field {} must be non-negative, got {}",
+                    field, weight
+                ),
+                location!(),
+            ));
+        }
+        Ok(Self::BM25F {
+            k1,
+            b,
+            field_weights,
+        })
+    }
+
+    fn check_bm25_params(k1: f32, b: f32) -> Result<()> {
+        if k1 < 0.0 || !(0.0..=1.0).contains(&b) {
+            return Err(Error::invalid_input(
+                format!(
+                    "BM25 requires k1 >= 0 and b in [0, 1], got k1={}, b={}",
+                    k1, b
+                ),
+                location!(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The weight of the field in BM25F, 1.0 for the other models
+    pub fn field_weight(&self, field: &str) -> f32 {
+        match self {
+            Self::BM25F { field_weights, .. } => field_weights.get(field).copied().unwrap_or(1.0),
+            _ => 1.0,
+        }
+    }
+
+    /// Whether the search can skip documents by the max scores stored in the index,
+    /// which are calculated by BM25 with the default parameters
+    pub fn can_prune(&self) -> bool {
+        match self {
+            Self::BM25 { k1, b } | Self::BM25F { k1, b, .. } => *k1 == K1 && *b == B,
+            _ => false,
+        }
+    }
+
+    pub fn scorer<'a>(&self, stats: Box<dyn CorpusStats + 'a>) -> Box<dyn Scorer + 'a> {
+        match self {
+            Self::BM25 { k1, b } | Self::BM25F { k1, b, .. } => {
+                Box::new(BM25Scorer::new(stats, *k1, *b))
+            }
+            Self::TfIdf => Box::new(TfIdfScorer { stats }),
+            Self::Constant => Box::new(ConstantScorer),
+            Self::Custom(factory) => factory.create_scorer(stats),
+        }
+    }
+}
+
+pub struct BM25Scorer<'a> {
+    stats: Box<dyn CorpusStats + 'a>,
+    k1: f32,
+    b: f32,
+}
+
+impl<'a> BM25Scorer<'a> {
+    pub fn new(stats: Box<dyn CorpusStats + 'a>, k1: f32, b: f32) -> Self {
+        Self { stats, k1, b }
+    }
+}
+
 impl Scorer for BM25Scorer<'_> {
     fn query_weight(&self, token: &str) -> f32 {
-        let nq = self.nq(token);
+        let nq = self.stats.nq(token);
         if nq == 0 {
             return 0.0;
         }
-        idf(nq, self.num_docs)
+        idf(nq, self.stats.num_docs())
     }
 
     fn doc_weight(&self, freq: u32, doc_tokens: u32) -> f32 {
         let freq = freq as f32;
         let doc_tokens = doc_tokens as f32;
-        let doc_norm = K1 * (1.0 - B + B * doc_tokens / self.avgdl);
-        (K1 + 1.0) * freq / (freq + doc_norm)
+        let doc_norm = self.k1 * (1.0 - self.b + self.b * doc_tokens / self.stats.avgdl());
+        (self.k1 + 1.0) * freq / (freq + doc_norm)
+    }
+}
+
+pub struct TfIdfScorer<'a> {
+    stats: Box<dyn CorpusStats + 'a>,
+}
+
+impl Scorer for TfIdfScorer<'_> {
+    fn query_weight(&self, token: &str) -> f32 {
+        let nq = self.stats.nq(token);
+        if nq == 0 {
+            return 0.0;
+        }
+        ((self.stats.num_docs() as f32 + 1.0) / (nq as f32 + 1.0)).ln() + 1.0
+    }
+
+    fn doc_weight(&self, freq: u32, doc_tokens: u32) -> f32 {
+        (freq as f32 / doc_tokens.max(1) as f32).sqrt()
+    }
+}
+
+pub struct ConstantScorer;
+
+impl Scorer for ConstantScorer {
+    fn query_weight(&self, _token: &str) -> f32 {
+        1.0
+    }
+
+    fn doc_weight(&self, _freq: u32, _doc_tokens: u32) -> f32 {
+        1.0
+    }
+}
+
+/// The number of tokens of a document in a field and the frequencies of the query tokens in it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldFreqs {
+    pub num_tokens: u32,
+    pub freqs: Vec<u32>,
+}
+
+/// A field of the documents scored by [`BM25FScorer`]
+pub struct BM25FField<'a> {
+    pub weight: f32,
+    /// The query tokens as tokenized for the field,
+    /// the i-th token of every field comes from the same query term
+    pub tokens: Vec<String>,
+    pub stats: Box<dyn CorpusStats + 'a>,
+}
+
+/// Scores the documents across fields with BM25F.
+///
+/// For each query token, its frequencies in the fields are normalized by the field lengths,
+/// weighted and summed, then saturated by `k1` once. So a token repeated across fields
+/// saturates like a token repeated in one field. The idf of a token is shared by the fields,
+/// it's calculated from the largest number of documents containing the token in any field.
+pub struct BM25FScorer {
+    k1: f32,
+    b: f32,
+    weights: Vec<f32>,
+    avgdls: Vec<f32>,
+    idfs: Vec<f32>,
+}
+
+impl BM25FScorer {
+    pub fn try_new(k1: f32, b: f32, fields: &[BM25FField]) -> Result<Self> {
+        let num_tokens = fields.first().map(|f| f.tokens.len()).unwrap_or_default();
+        if fields.iter().any(|f| f.tokens.len() != num_tokens) {
+            return Err(Error::invalid_input(
+                "BM25F requires the query to be tokenized into the same number of tokens in all fields",
+                location!(),
+            ));
+        }
+        let num_docs = fields
+            .iter()
+            .map(|f| f.stats.num_docs())
+            .max()
+            .unwrap_or_default();
+        let idfs = (0..num_tokens)
+            .map(|i| {
+                let nq = fields
+                    .iter()
+                    .map(|f| f.stats.nq(&f.tokens[i]))
+                    .max()
+                    .unwrap_or_default();
+                if nq == 0 {
+                    return 0.0;
+                }
+                idf(nq, num_docs)
+            })
+            .collect();
+        Ok(Self {
+            k1,
+            b,
+            weights: fields.iter().map(|f| f.weight).collect(),
+            avgdls: fields.iter().map(|f| f.stats.avgdl()).collect(),
+            idfs,
+        })
+    }
+
+    /// `docs[i]` is the document in the i-th field, `None` if the field doesn't contain any query token
+    pub fn score(&self, docs: &[Option<&FieldFreqs>]) -> f32 {
+        let mut score = 0.0;
+        for (i, idf) in self.idfs.iter().enumerate() {
+            let tf = self.weighted_tf(docs, i);
+            if tf > 0.0 {
+                score += idf * (self.k1 + 1.0) * tf / (self.k1 + tf);
+            }
+        }
+        score
+    }
+
+    /// The documents scored across the fields, sorted by score in descending order.
+    /// `fields[i]` maps the row ids of the documents containing any query token in the i-th field
+    /// to their frequencies. With `Operator::And` a document must contain every query token
+    /// in at least one of the fields.
+    pub fn search(
+        &self,
+        fields: &[HashMap<u64, FieldFreqs>],
+        operator: Operator,
+        limit: Option<usize>,
+    ) -> (Vec<u64>, Vec<f32>) {
+        let mut docs = Vec::with_capacity(fields.len());
+        let mut scored = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            for row_id in field.keys() {
+                // the document is scored once, with the first field containing it
+                if fields[..i].iter().any(|f| f.contains_key(row_id)) {
+                    continue;
+                }
+                docs.clear();
+                docs.extend(fields.iter().map(|f| f.get(row_id)));
+                if operator == Operator::And
+                    && (0..self.idfs.len()).any(|i| self.weighted_tf(&docs, i) == 0.0)
+                {
+                    continue;
+                }
+                scored.push((*row_id, self.score(&docs)));
+            }
+        }
+        scored.sort_unstable_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scored.truncate(limit.unwrap_or(usize::MAX));
+        scored.into_iter().unzip()
+    }
+
+    fn weighted_tf(&self, docs: &[Option<&FieldFreqs>], token: usize) -> f32 {
+        let mut tf = 0.0;
+        for ((doc, weight), avgdl) in docs.iter().zip(&self.weights).zip(&self.avgdls) {
+            let Some(doc) = doc else {
+                continue;
+            };
+            let freq = doc.freqs[token];
+            if freq == 0 {
+                continue;
+            }
+            let norm = match avgdl.is_normal() {
+                true => 1.0 - self.b + self.b * doc.num_tokens as f32 / avgdl,
+                // no indexed documents in the field
+                false => 1.0,
+            };
+            tf += weight * freq as f32 / norm;
+        }
+        tf
     }
 }
 
@@ -89,3 +425,105 @@ pub fn idf(nq: usize, num_docs: usize) -> f32 {
     let num_docs = num_docs as f32;
     ((num_docs - nq as f32 + 0.5) / (nq as f32 + 0.5) + 1.0).ln()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestStats;
+
+    impl CorpusStats for TestStats {
+        fn num_docs(&self) -> usize {
+            10
+        }
+
+        fn avgdl(&self) -> f32 {
+            5.0
+        }
+
+        fn nq(&self, token: &str) -> usize {
+            match token {
+                "common" => 9,
+                "rare" => 1,
+                _ => 0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_scorers() {
+        let bm25 = ScoringModel::default().scorer(Box::new(TestStats));
+        assert!(bm25.score("rare", 1, 5) > bm25.score("common", 1, 5));
+        assert!(bm25.score("rare", 2, 5) > bm25.score("rare", 1, 5));
+        assert!(bm25.score("rare", 1, 5) > bm25.score("rare", 1, 10));
+        assert_eq!(bm25.score("missing", 1, 5), 0.0);
+
+        // b = 0 disables the length normalization
+        let bm25 = ScoringModel::bm25(1.2, 0.0)
+            .unwrap()
+            .scorer(Box::new(TestStats));
+        assert_eq!(bm25.score("rare", 1, 5), bm25.score("rare", 1, 10));
+
+        let tfidf = ScoringModel::TfIdf.scorer(Box::new(TestStats));
+        assert!(tfidf.score("rare", 1, 5) > tfidf.score("common", 1, 5));
+        assert_eq!(tfidf.score("rare", 1, 4) * 2.0, tfidf.score("rare", 4, 4));
+
+        let constant = ScoringModel::Constant.scorer(Box::new(TestStats));
+        assert_eq!(
+            constant.score("rare", 3, 5),
+            constant.score("common", 1, 10)
+        );
+
+        assert!(ScoringModel::bm25(-1.0, 0.5).is_err());
+        assert!(ScoringModel::bm25(1.2, 1.5).is_err());
+        assert!(ScoringModel::bm25f(1.2, 0.75, HashMap::from([("a".to_owned(), -1.0)])).is_err());
+        assert!(ScoringModel::default().can_prune());
+        assert!(!ScoringModel::bm25(2.0, 0.75).unwrap().can_prune());
+        assert!(!ScoringModel::TfIdf.can_prune());
+    }
+
+    #[test]
+    fn test_bm25f_scorer() {
+        let field = |weight: f32| BM25FField {
+            weight,
+            tokens: vec!["rare".to_owned(), "common".to_owned()],
+            stats: Box::new(TestStats),
+        };
+        let doc = |freqs: Vec<u32>| FieldFreqs {
+            num_tokens: 5,
+            freqs,
+        };
+        let scorer = BM25FScorer::try_new(K1, B, &[field(1.0), field(1.0)]).unwrap();
+        let (rare, rare_twice) = (doc(vec![1, 0]), doc(vec![2, 0]));
+        let rare_in_both_fields = [Some(&rare), Some(&rare)];
+        let rare_twice_in_one_field = [Some(&rare_twice), None];
+        // the frequencies are summed across the fields before the saturation,
+        // so repeating the token in another field is the same as repeating it in one field
+        assert_eq!(
+            scorer.score(&rare_in_both_fields),
+            scorer.score(&rare_twice_in_one_field)
+        );
+        // and a repeated token saturates, unlike the sum of the BM25 scores of the fields
+        let bm25 = ScoringModel::default().scorer(Box::new(TestStats));
+        assert!(scorer.score(&rare_in_both_fields) < 2.0 * bm25.score("rare", 1, 5));
+
+        // the weights scale the frequencies of the fields
+        let scorer = BM25FScorer::try_new(K1, B, &[field(2.0), field(1.0)]).unwrap();
+        assert!(scorer.score(&[Some(&rare), None]) > scorer.score(&[None, Some(&rare)]));
+
+        let fields = [
+            HashMap::from([(0, doc(vec![1, 0])), (1, doc(vec![1, 0]))]),
+            HashMap::from([(1, doc(vec![0, 1])), (2, doc(vec![0, 1]))]),
+        ];
+        assert_eq!(scorer.search(&fields, Operator::Or, None).0, vec![1, 0, 2]);
+        assert_eq!(scorer.search(&fields, Operator::Or, Some(1)).0, vec![1]);
+        assert_eq!(scorer.search(&fields, Operator::And, None).0, vec![1]);
+
+        let mismatched = BM25FField {
+            weight: 1.0,
+            tokens: vec!["rare".to_owned()],
+            stats: Box::new(TestStats),
+        };
+        assert!(BM25FScorer::try_new(K1, B, &[field(1.0), mismatched]).is_err());
+    }
+}
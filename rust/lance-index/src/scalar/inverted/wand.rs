@@ -363,7 +363,11 @@ impl<'a, S: Scorer> Wand<'a, S> {
                     doc.frequency(),
                     doc_length,
                 )));
-                self.threshold = candidates.peek().unwrap().0 .0.score.0 * params.wand_factor;
+                // the max scores in the index are calculated by BM25,
+                // they can't bound the scores of the other models
+                if params.scoring.can_prune() {
+                    self.threshold = candidates.peek().unwrap().0 .0.score.0 * params.wand_factor;
+                }
             }
             self.move_preceding(pivot, doc.doc_id() + 1);
         }
@@ -0,0 +1,638 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Inverted-list index over sparse vectors (e.g. SPLADE embeddings)
+//!
+//! The index keeps one posting list per dimension, holding the row ids that have a
+//! non-zero weight in that dimension, sorted by row id. Each posting list is split
+//! into blocks of [`BLOCK_SIZE`] entries and the max weight of each block is stored,
+//! so that top-k dot product search can skip blocks with block-max WAND.
+
+mod wand;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::AsArray;
+use arrow::datatypes::{Float32Type, UInt32Type, UInt64Type};
+use arrow_array::{ArrayRef, Float32Array, ListArray, RecordBatch, UInt32Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use deepsize::DeepSizeOf;
+use futures::TryStreamExt;
+use lance_arrow::sparse::{SparseVector, SparseVectorArray, SparseVectorRef};
+use lance_core::utils::mask::RowIdMask;
+use lance_core::{Error, Result};
+use roaring::RoaringBitmap;
+use snafu::location;
+use tracing::instrument;
+
+use crate::metrics::MetricsCollector;
+use crate::{Index, IndexType};
+
+use super::btree::TrainingSource;
+use super::{AnyQuery, IndexStore, ScalarIndex, SearchResult};
+use wand::Wand;
+
+pub const SPARSE_POSTINGS_FILE: &str = "sparse_postings.lance";
+
+const DIM_COL: &str = "dim";
+const MAX_WEIGHT_COL: &str = "max_weight";
+const ROW_IDS_COL: &str = "row_ids";
+const WEIGHTS_COL: &str = "weights";
+const BLOCK_MAX_COL: &str = "block_max_weights";
+
+/// Number of entries in each block of a posting list
+pub const BLOCK_SIZE: usize = 128;
+
+// Flush the posting lists to the index file once this many entries are buffered
+const MAX_ENTRIES_PER_BATCH: usize = 1024 * 1024;
+const MAX_ROWS_PER_CHUNK: usize = 1024;
+
+fn postings_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(DIM_COL, DataType::UInt32, false),
+        Field::new(MAX_WEIGHT_COL, DataType::Float32, false),
+        Field::new(
+            ROW_IDS_COL,
+            DataType::List(Arc::new(Field::new("item", DataType::UInt64, false))),
+            false,
+        ),
+        Field::new(
+            WEIGHTS_COL,
+            DataType::List(Arc::new(Field::new("item", DataType::Float32, false))),
+            false,
+        ),
+        Field::new(
+            BLOCK_MAX_COL,
+            DataType::List(Arc::new(Field::new("item", DataType::Float32, false))),
+            false,
+        ),
+    ]))
+}
+
+/// A top-k dot product query over a sparse vector column
+#[derive(Debug, Clone)]
+pub struct SparseQuery {
+    pub column: String,
+    pub vector: SparseVector,
+    pub k: usize,
+}
+
+/// The rows that have a non-zero weight in one dimension
+#[derive(Debug, Clone, DeepSizeOf)]
+pub struct SparsePostingList {
+    row_ids: Vec<u64>,
+    weights: Vec<f32>,
+    block_max_weights: Vec<f32>,
+    max_weight: f32,
+}
+
+impl SparsePostingList {
+    fn new(mut entries: Vec<(u64, f32)>) -> Self {
+        entries.sort_unstable_by_key(|(row_id, _)| *row_id);
+        let (row_ids, weights): (Vec<_>, Vec<_>) = entries.into_iter().unzip();
+        let block_max_weights = weights
+            .chunks(BLOCK_SIZE)
+            .map(|block| block.iter().copied().fold(0.0, f32::max))
+            .collect::<Vec<_>>();
+        let max_weight = block_max_weights.iter().copied().fold(0.0, f32::max);
+        Self {
+            row_ids,
+            weights,
+            block_max_weights,
+            max_weight,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.row_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.row_ids.is_empty()
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (u64, f32)> + '_ {
+        self.row_ids
+            .iter()
+            .copied()
+            .zip(self.weights.iter().copied())
+    }
+}
+
+/// An inverted-list index for top-k dot product search over a sparse vector column
+#[derive(Debug, Clone, DeepSizeOf)]
+pub struct SparseIndex {
+    postings: HashMap<u32, SparsePostingList>,
+}
+
+impl SparseIndex {
+    pub fn num_dimensions(&self) -> usize {
+        self.postings.len()
+    }
+
+    /// Search the `limit` rows with the highest dot product with the query.
+    ///
+    /// Only rows selected by the mask and with a positive score are returned,
+    /// sorted by descending score.
+    #[instrument(level = "debug", skip_all)]
+    pub fn search_sparse(
+        &self,
+        query: SparseVectorRef,
+        limit: usize,
+        mask: &RowIdMask,
+        metrics: &dyn MetricsCollector,
+    ) -> (Vec<u64>, Vec<f32>) {
+        let postings = query.iter().filter_map(|(dim, weight)| {
+            self.postings
+                .get(&dim)
+                .map(|list| wand::PostingIterator::new(weight, list))
+        });
+        Wand::new(postings).search(limit, mask, metrics)
+    }
+
+    fn into_builder(self) -> SparseIndexBuilder {
+        SparseIndexBuilder {
+            postings: self
+                .postings
+                .into_iter()
+                .map(|(dim, list)| (dim, list.entries().collect()))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Index for SparseIndex {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_index(self: Arc<Self>) -> Arc<dyn Index> {
+        self
+    }
+
+    fn as_vector_index(self: Arc<Self>) -> Result<Arc<dyn crate::vector::VectorIndex>> {
+        Err(Error::invalid_input(
+            "sparse index cannot be cast to vector index",
+            location!(),
+        ))
+    }
+
+    fn statistics(&self) -> Result<serde_json::Value> {
+        let num_postings = self.postings.values().map(|list| list.len()).sum::<usize>();
+        Ok(serde_json::json!({
+            "num_dimensions": self.postings.len(),
+            "num_postings": num_postings,
+        }))
+    }
+
+    async fn prewarm(&self) -> Result<()> {
+        // The posting lists are all loaded in memory when the index is opened
+        Ok(())
+    }
+
+    fn index_type(&self) -> IndexType {
+        IndexType::Sparse
+    }
+
+    async fn calculate_included_frags(&self) -> Result<RoaringBitmap> {
+        let mut frag_ids = RoaringBitmap::new();
+        for list in self.postings.values() {
+            frag_ids.extend(list.row_ids.iter().map(|row_id| (row_id >> 32) as u32));
+        }
+        Ok(frag_ids)
+    }
+}
+
+#[async_trait]
+impl ScalarIndex for SparseIndex {
+    async fn search(
+        &self,
+        _query: &dyn AnyQuery,
+        _metrics: &dyn MetricsCollector,
+    ) -> Result<SearchResult> {
+        Err(Error::NotSupported {
+            source: "sparse index only supports top-k sparse vector search".into(),
+            location: location!(),
+        })
+    }
+
+    fn can_answer_exact(&self, _: &dyn AnyQuery) -> bool {
+        false
+    }
+
+    async fn load(store: Arc<dyn IndexStore>) -> Result<Arc<Self>> {
+        let reader = store.open_index_file(SPARSE_POSTINGS_FILE).await?;
+        let num_rows = reader.num_rows();
+        let mut postings = HashMap::with_capacity(num_rows);
+        for start in (0..num_rows).step_by(MAX_ROWS_PER_CHUNK) {
+            let end = (start + MAX_ROWS_PER_CHUNK).min(num_rows);
+            let batch = reader.read_range(start..end, None).await?;
+            let dims = batch[DIM_COL].as_primitive::<UInt32Type>();
+            let max_weights = batch[MAX_WEIGHT_COL].as_primitive::<Float32Type>();
+            let row_ids = batch[ROW_IDS_COL].as_list::<i32>();
+            let weights = batch[WEIGHTS_COL].as_list::<i32>();
+            let block_max_weights = batch[BLOCK_MAX_COL].as_list::<i32>();
+            for i in 0..batch.num_rows() {
+                let list = SparsePostingList {
+                    row_ids: row_ids
+                        .value(i)
+                        .as_primitive::<UInt64Type>()
+                        .values()
+                        .to_vec(),
+                    weights: weights
+                        .value(i)
+                        .as_primitive::<Float32Type>()
+                        .values()
+                        .to_vec(),
+                    block_max_weights: block_max_weights
+                        .value(i)
+                        .as_primitive::<Float32Type>()
+                        .values()
+                        .to_vec(),
+                    max_weight: max_weights.value(i),
+                };
+                postings.insert(dims.value(i), list);
+            }
+        }
+        Ok(Arc::new(Self { postings }))
+    }
+
+    async fn remap(
+        &self,
+        mapping: &HashMap<u64, Option<u64>>,
+        dest_store: &dyn IndexStore,
+    ) -> Result<()> {
+        let mut builder = SparseIndexBuilder::default();
+        for (dim, list) in &self.postings {
+            let entries = list
+                .entries()
+                .filter_map(|(row_id, weight)| match mapping.get(&row_id) {
+                    Some(Some(new_row_id)) => Some((*new_row_id, weight)),
+                    Some(None) => None,
+                    None => Some((row_id, weight)),
+                })
+                .collect::<Vec<_>>();
+            if !entries.is_empty() {
+                builder.postings.insert(*dim, entries);
+            }
+        }
+        builder.write(dest_store).await
+    }
+
+    async fn update(
+        &self,
+        new_data: SendableRecordBatchStream,
+        dest_store: &dyn IndexStore,
+    ) -> Result<()> {
+        let mut builder = self.clone().into_builder();
+        builder.update(new_data).await?;
+        builder.write(dest_store).await
+    }
+}
+
+/// Accumulates the posting lists of a sparse index in memory
+#[derive(Debug, Default)]
+pub struct SparseIndexBuilder {
+    postings: HashMap<u32, Vec<(u64, f32)>>,
+}
+
+impl SparseIndexBuilder {
+    /// Add a batch of `(sparse vector, row id)` to the index.
+    ///
+    /// The weights must be non-negative, which is what makes block-max WAND
+    /// pruning valid.
+    pub fn add_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        let vectors =
+            SparseVectorArray::try_new(batch.column(0).as_ref()).map_err(|e| Error::Index {
+                message: format!("sparse index can only be built on sparse vectors: {}", e),
+                location: location!(),
+            })?;
+        let row_ids = batch.column(1).as_primitive::<UInt64Type>();
+        for (vector, row_id) in vectors.iter().zip(row_ids.values()) {
+            let Some(vector) = vector else {
+                continue;
+            };
+            for (dim, weight) in vector.iter() {
+                if !(weight >= 0.0 && weight.is_finite()) {
+                    return Err(Error::invalid_input(
+                        format!(
+                            "sparse index requires non-negative finite weights, but row {} has weight {} in dimension {}",
+                            row_id, weight, dim
+                        ),
+                        location!(),
+                    ));
+                }
+                if weight > 0.0 {
+                    self.postings
+                        .entry(dim)
+                        .or_default()
+                        .push((*row_id, weight));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn update(&mut self, mut stream: SendableRecordBatchStream) -> Result<()> {
+        while let Some(batch) = stream.try_next().await? {
+            self.add_batch(&batch)?;
+        }
+        Ok(())
+    }
+
+    pub async fn write(self, store: &dyn IndexStore) -> Result<()> {
+        let schema = postings_schema();
+        let mut writer = store
+            .new_index_file(SPARSE_POSTINGS_FILE, schema.clone())
+            .await?;
+
+        let mut postings = self.postings.into_iter().collect::<Vec<_>>();
+        postings.sort_unstable_by_key(|(dim, _)| *dim);
+        let mut buffer = Vec::new();
+        let mut num_entries = 0;
+        for (dim, entries) in postings {
+            num_entries += entries.len();
+            buffer.push((dim, SparsePostingList::new(entries)));
+            if num_entries >= MAX_ENTRIES_PER_BATCH || buffer.len() >= MAX_ROWS_PER_CHUNK {
+                let batch = postings_to_batch(schema.clone(), std::mem::take(&mut buffer))?;
+                writer.write_record_batch(batch).await?;
+                num_entries = 0;
+            }
+        }
+        if !buffer.is_empty() {
+            let batch = postings_to_batch(schema, buffer)?;
+            writer.write_record_batch(batch).await?;
+        }
+        writer.finish().await
+    }
+}
+
+fn postings_to_batch(
+    schema: SchemaRef,
+    postings: Vec<(u32, SparsePostingList)>,
+) -> Result<RecordBatch> {
+    let dims = UInt32Array::from_iter_values(postings.iter().map(|(dim, _)| *dim));
+    let max_weights = Float32Array::from_iter_values(postings.iter().map(|(_, l)| l.max_weight));
+    let row_ids = ListArray::from_iter_primitive::<UInt64Type, _, _>(
+        postings
+            .iter()
+            .map(|(_, l)| Some(l.row_ids.iter().copied().map(Some))),
+    );
+    let weights = ListArray::from_iter_primitive::<Float32Type, _, _>(
+        postings
+            .iter()
+            .map(|(_, l)| Some(l.weights.iter().copied().map(Some))),
+    );
+    let block_max_weights = ListArray::from_iter_primitive::<Float32Type, _, _>(
+        postings
+            .iter()
+            .map(|(_, l)| Some(l.block_max_weights.iter().copied().map(Some))),
+    );
+    // `from_iter_primitive` creates nullable items, so cast the lists to the schema
+    let columns = [
+        Arc::new(dims) as ArrayRef,
+        Arc::new(max_weights),
+        Arc::new(row_ids),
+        Arc::new(weights),
+        Arc::new(block_max_weights),
+    ]
+    .into_iter()
+    .zip(schema.fields())
+    .map(|(array, field)| Ok(arrow::compute::cast(&array, field.data_type())?))
+    .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+pub async fn train_sparse_index(
+    data_source: Box<dyn TrainingSource + Send>,
+    index_store: &dyn IndexStore,
+) -> Result<()> {
+    let batches_source = data_source.scan_unordered_chunks(4096).await?;
+    let mut builder = SparseIndexBuilder::default();
+    builder.update(batches_source).await?;
+    builder.write(index_store).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::UInt64Array;
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use futures::stream;
+    use lance_arrow::sparse::sparse_vector_field;
+    use lance_core::cache::FileMetadataCache;
+    use lance_io::object_store::ObjectStore;
+    use lance_linalg::distance::sparse::sparse_dot;
+    use object_store::path::Path;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use tempfile::tempdir;
+
+    use crate::metrics::NoOpMetricsCollector;
+    use crate::scalar::lance_format::LanceIndexStore;
+
+    fn random_vectors(num_rows: usize, rng: &mut StdRng) -> Vec<SparseVector> {
+        (0..num_rows)
+            .map(|_| {
+                let nnz = rng.gen_range(0..20);
+                // skew the dimensions so that some posting lists span many blocks
+                SparseVector::from_pairs(
+                    (0..nnz).map(|_| (rng.gen_range(0..1000_u32).pow(2) / 2000, rng.gen::<f32>())),
+                )
+            })
+            .collect()
+    }
+
+    fn to_stream(vectors: &[SparseVector], row_ids: Vec<u64>) -> SendableRecordBatchStream {
+        let schema = Arc::new(Schema::new(vec![
+            sparse_vector_field("vector", true),
+            Field::new("row_id", DataType::UInt64, false),
+        ]));
+        let array: ArrayRef = vectors
+            .iter()
+            .map(Some)
+            .collect::<SparseVectorArray>()
+            .into();
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![array, Arc::new(UInt64Array::from(row_ids))],
+        )
+        .unwrap();
+        Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream::once(std::future::ready(Ok(batch))),
+        ))
+    }
+
+    fn brute_force(
+        vectors: &[(u64, SparseVector)],
+        query: &SparseVector,
+        k: usize,
+    ) -> Vec<(u64, f32)> {
+        let mut scores = vectors
+            .iter()
+            .map(|(row_id, v)| (*row_id, sparse_dot(query.as_ref(), v.as_ref())))
+            .filter(|(_, score)| *score > 0.0)
+            .collect::<Vec<_>>();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scores.truncate(k);
+        scores
+    }
+
+    fn check_results(index: &SparseIndex, vectors: &[(u64, SparseVector)], rng: &mut StdRng) {
+        for query in random_vectors(20, rng) {
+            for k in [1, 10, 100] {
+                let (row_ids, scores) = index.search_sparse(
+                    query.as_ref(),
+                    k,
+                    &RowIdMask::all_rows(),
+                    &NoOpMetricsCollector,
+                );
+                let expected = brute_force(vectors, &query, k);
+                assert_eq!(row_ids.len(), expected.len());
+                for (score, (_, expected_score)) in scores.iter().zip(expected.iter()) {
+                    assert!(
+                        (score - expected_score).abs() < 1e-4,
+                        "score {} != expected {}",
+                        score,
+                        expected_score
+                    );
+                }
+                for (row_id, score) in row_ids.iter().zip(scores.iter()) {
+                    let (_, vector) = vectors.iter().find(|(id, _)| id == row_id).unwrap();
+                    assert!((sparse_dot(query.as_ref(), vector.as_ref()) - score).abs() < 1e-4);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sparse_index() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let tmpdir = tempdir().unwrap();
+        let store = Arc::new(LanceIndexStore::new(
+            Arc::new(ObjectStore::local()),
+            Path::from_filesystem_path(tmpdir.path()).unwrap(),
+            FileMetadataCache::no_cache(),
+        ));
+
+        let vectors = random_vectors(2000, &mut rng);
+        let row_ids = (0..vectors.len() as u64).rev().collect::<Vec<_>>();
+        let mut builder = SparseIndexBuilder::default();
+        builder
+            .update(to_stream(&vectors, row_ids.clone()))
+            .await
+            .unwrap();
+        builder.write(store.as_ref()).await.unwrap();
+        let index = SparseIndex::load(store).await.unwrap();
+
+        let mut expected = row_ids.into_iter().zip(vectors).collect::<Vec<_>>();
+        check_results(&index, &expected, &mut rng);
+
+        // the mask is respected
+        let query = SparseVector::from_pairs((0..500).map(|dim| (dim, 1.0)));
+        let mask = RowIdMask::from_allowed((0..1000).collect());
+        let (row_ids, _) = index.search_sparse(query.as_ref(), 10, &mask, &NoOpMetricsCollector);
+        assert_eq!(row_ids.len(), 10);
+        assert!(row_ids.iter().all(|row_id| *row_id < 1000));
+
+        // update with new rows
+        let new_dir = tempdir().unwrap();
+        let new_store = Arc::new(LanceIndexStore::new(
+            Arc::new(ObjectStore::local()),
+            Path::from_filesystem_path(new_dir.path()).unwrap(),
+            FileMetadataCache::no_cache(),
+        ));
+        let new_vectors = random_vectors(500, &mut rng);
+        let new_row_ids = (1 << 32..(1 << 32) + new_vectors.len() as u64).collect::<Vec<_>>();
+        index
+            .update(
+                to_stream(&new_vectors, new_row_ids.clone()),
+                new_store.as_ref(),
+            )
+            .await
+            .unwrap();
+        let index = SparseIndex::load(new_store).await.unwrap();
+        expected.extend(new_row_ids.into_iter().zip(new_vectors));
+        check_results(&index, &expected, &mut rng);
+        let frags = index.calculate_included_frags().await.unwrap();
+        assert_eq!(frags.iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        // remap: drop the even rows of the first fragment
+        let mapping = (0..2000_u64)
+            .step_by(2)
+            .map(|row_id| (row_id, None))
+            .collect::<HashMap<_, _>>();
+        let remap_dir = tempdir().unwrap();
+        let remap_store = Arc::new(LanceIndexStore::new(
+            Arc::new(ObjectStore::local()),
+            Path::from_filesystem_path(remap_dir.path()).unwrap(),
+            FileMetadataCache::no_cache(),
+        ));
+        index.remap(&mapping, remap_store.as_ref()).await.unwrap();
+        let index = SparseIndex::load(remap_store).await.unwrap();
+        expected.retain(|(row_id, _)| !mapping.contains_key(row_id));
+        check_results(&index, &expected, &mut rng);
+    }
+
+    #[test]
+    fn test_sparse_index_rejects_negative_weights() {
+        let vectors = [SparseVector::try_new(vec![1, 2], vec![0.5, -0.5]).unwrap()];
+        let schema = Arc::new(Schema::new(vec![
+            sparse_vector_field("vector", true),
+            Field::new("row_id", DataType::UInt64, false),
+        ]));
+        let array: ArrayRef = vectors
+            .iter()
+            .map(Some)
+            .collect::<SparseVectorArray>()
+            .into();
+        let batch = RecordBatch::try_new(schema, vec![array, Arc::new(UInt64Array::from(vec![0]))])
+            .unwrap();
+        let mut builder = SparseIndexBuilder::default();
+        assert!(builder.add_batch(&batch).is_err());
+    }
+    #[test]
+    fn test_sparse_index_rejects_unsorted_indices() {
+        use arrow::buffer::OffsetBuffer;
+        use arrow_array::StructArray;
+        use lance_arrow::sparse::sparse_vector_data_type;
+
+        let DataType::List(item) = sparse_vector_data_type() else {
+            unreachable!()
+        };
+        let DataType::Struct(fields) = item.data_type().clone() else {
+            unreachable!()
+        };
+        let entries = StructArray::new(
+            fields,
+            vec![
+                Arc::new(UInt32Array::from(vec![1, 2, 5, 4])) as ArrayRef,
+                Arc::new(Float32Array::from(vec![0.5; 4])) as ArrayRef,
+            ],
+            None,
+        );
+        let array = ListArray::new(
+            item,
+            OffsetBuffer::from_lengths([2, 2]),
+            Arc::new(entries),
+            None,
+        );
+        let schema = Arc::new(Schema::new(vec![
+            sparse_vector_field("vector", true),
+            Field::new("row_id", DataType::UInt64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(array), Arc::new(UInt64Array::from(vec![0, 1]))],
+        )
+        .unwrap();
+        let mut builder = SparseIndexBuilder::default();
+        let err = builder.add_batch(&batch).unwrap_err();
+        assert!(err.to_string().contains("strictly increasing"), "{}", err);
+    }
+}
@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Block-max WAND over the posting lists of a sparse index.
+//!
+//! This follows the same approach as the full text search WAND, but the score of
+//! a document is the dot product `sum(query_weight * doc_weight)` instead of BM25.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use lance_core::utils::mask::RowIdMask;
+
+use crate::metrics::MetricsCollector;
+use crate::scalar::inverted::builder::ScoredDoc;
+
+use super::{SparsePostingList, BLOCK_SIZE};
+
+pub(super) struct PostingIterator<'a> {
+    // the weight of this dimension in the query
+    weight: f32,
+    list: &'a SparsePostingList,
+    index: usize,
+    upper_bound: f32,
+}
+
+impl<'a> PostingIterator<'a> {
+    pub(super) fn new(weight: f32, list: &'a SparsePostingList) -> Self {
+        // doc weights are non-negative, so a negative query weight can't increase the score
+        let upper_bound = weight.max(0.0) * list.max_weight;
+        Self {
+            weight,
+            list,
+            index: 0,
+            upper_bound,
+        }
+    }
+
+    #[inline]
+    fn doc(&self) -> Option<u64> {
+        self.list.row_ids.get(self.index).copied()
+    }
+
+    #[inline]
+    fn score(&self) -> f32 {
+        self.weight * self.list.weights[self.index]
+    }
+
+    // move to the first doc that is greater than or equal to least_id
+    fn next(&mut self, least_id: u64) {
+        self.index += self.list.row_ids[self.index..].partition_point(|&id| id < least_id);
+    }
+
+    // the block that would contain the given doc, the doc must not precede the current doc
+    fn block_of(&self, doc_id: u64) -> usize {
+        let pos = self.index + self.list.row_ids[self.index..].partition_point(|&id| id <= doc_id);
+        debug_assert!(pos > self.index);
+        (pos - 1) / BLOCK_SIZE
+    }
+
+    fn block_max_score(&self, doc_id: u64) -> f32 {
+        self.weight.max(0.0) * self.list.block_max_weights[self.block_of(doc_id)]
+    }
+
+    fn next_block_first_doc(&self, doc_id: u64) -> Option<u64> {
+        let next_block = self.block_of(doc_id) + 1;
+        self.list.row_ids.get(next_block * BLOCK_SIZE).copied()
+    }
+}
+
+pub(super) struct Wand<'a> {
+    // the minimum score of the top-k documents, 0 until there are k candidates
+    threshold: f32,
+    // sorted by the current doc, exhausted iterators are removed
+    postings: Vec<PostingIterator<'a>>,
+}
+
+impl<'a> Wand<'a> {
+    pub(super) fn new(postings: impl Iterator<Item = PostingIterator<'a>>) -> Self {
+        let mut postings = postings
+            .filter(|posting| posting.doc().is_some())
+            .collect::<Vec<_>>();
+        postings.sort_unstable_by_key(|posting| posting.doc());
+        Self {
+            threshold: 0.0,
+            postings,
+        }
+    }
+
+    // search the top-k documents with positive scores,
+    // returns the row ids and scores sorted by descending score
+    pub(super) fn search(
+        mut self,
+        limit: usize,
+        mask: &RowIdMask,
+        metrics: &dyn MetricsCollector,
+    ) -> (Vec<u64>, Vec<f32>) {
+        if limit == 0 {
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut candidates = BinaryHeap::with_capacity(limit + 1);
+        let mut num_comparisons = 0;
+        while let Some(pivot) = self.next_candidate() {
+            // all the postings up to the pivot are at the candidate doc
+            let doc_id = self.postings[pivot].doc().unwrap();
+            num_comparisons += 1;
+            if mask.selected(doc_id) {
+                let score = self.postings[..=pivot]
+                    .iter()
+                    .map(|posting| posting.score())
+                    .sum::<f32>();
+                if score > self.threshold {
+                    if candidates.len() == limit {
+                        candidates.pop();
+                    }
+                    candidates.push(Reverse(ScoredDoc::new(doc_id, score)));
+                    if candidates.len() == limit {
+                        self.threshold = candidates.peek().unwrap().0.score.0;
+                    }
+                }
+            }
+            self.advance(pivot, doc_id + 1);
+        }
+        metrics.record_comparisons(num_comparisons);
+
+        candidates
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(doc)| (doc.row_id, doc.score.0))
+            .unzip()
+    }
+
+    // find the next doc that may score above the threshold,
+    // returns the index of the last posting that is at this doc
+    fn next_candidate(&mut self) -> Option<usize> {
+        loop {
+            let pivot = self.find_pivot()?;
+            let doc_id = self.postings[pivot].doc().unwrap();
+            let block_bound = self.postings[..=pivot]
+                .iter()
+                .map(|posting| posting.block_max_score(doc_id))
+                .sum::<f32>();
+            if block_bound > self.threshold {
+                if self.postings[0].doc() == Some(doc_id) {
+                    return Some(pivot);
+                }
+                // the docs before the pivot doc can't exceed the threshold,
+                // so move the preceding postings up to the pivot doc
+                self.advance(pivot, doc_id);
+            } else {
+                // no doc can exceed the threshold until one of the pivot postings
+                // enters a new block, or a posting after the pivot is reached
+                let least_id = self.postings[..=pivot]
+                    .iter()
+                    .filter_map(|posting| posting.next_block_first_doc(doc_id))
+                    .chain(self.postings.get(pivot + 1).and_then(|p| p.doc()))
+                    .min()?;
+                self.advance(pivot, least_id);
+            }
+        }
+    }
+
+    // find the first posting that the sum of the upper bounds of it and all preceding postings
+    // exceeds the threshold, then extend it to the last posting at the same doc
+    fn find_pivot(&self) -> Option<usize> {
+        let mut acc = 0.0;
+        let mut pivot = self.postings.iter().position(|posting| {
+            acc += posting.upper_bound;
+            acc > self.threshold
+        })?;
+        let doc_id = self.postings[pivot].doc();
+        while pivot + 1 < self.postings.len() && self.postings[pivot + 1].doc() == doc_id {
+            pivot += 1;
+        }
+        Some(pivot)
+    }
+
+    // move the postings up to the pivot to the first doc that is not less than least_id
+    fn advance(&mut self, pivot: usize, least_id: u64) {
+        for posting in self.postings[..=pivot].iter_mut() {
+            posting.next(least_id);
+        }
+        self.postings.retain(|posting| posting.doc().is_some());
+        self.postings.sort_unstable_by_key(|posting| posting.doc());
+    }
+}
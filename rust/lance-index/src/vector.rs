@@ -14,16 +14,18 @@ use async_trait::async_trait;
 use datafusion::execution::SendableRecordBatchStream;
 use deepsize::DeepSizeOf;
 use ivf::storage::IvfModel;
-use lance_core::{Result, ROW_ID_FIELD};
+use lance_core::{Error, Result, ROW_ID_FIELD};
 use lance_io::object_store::ObjectStore;
 use lance_io::traits::Reader;
 use lance_linalg::distance::DistanceType;
 use lazy_static::lazy_static;
 use object_store::path::Path;
 use quantizer::{QuantizationType, Quantizer};
+use snafu::location;
 use v3::subindex::SubIndexType;
 
 pub mod bq;
+pub mod diskann;
 pub mod flat;
 pub mod graph;
 pub mod hnsw;
@@ -50,7 +52,11 @@ pub const INDEX_UUID_COLUMN: &str = "__index_uuid";
 pub const PART_ID_COLUMN: &str = "__ivf_part_id";
 pub const PQ_CODE_COLUMN: &str = "__pq_code";
 pub const SQ_CODE_COLUMN: &str = "__sq_code";
+pub const RQ_CODE_COLUMN: &str = "__rq_code";
 pub const LOSS_METADATA_KEY: &str = "_loss";
+pub const TOKEN_POOL_FACTOR_KEY: &str = "token_pool_factor";
+/// The column of the batched vector search results that refers to the query.
+pub const QUERY_INDEX_COL: &str = "query_index";
 
 lazy_static! {
     pub static ref VECTOR_RESULT_SCHEMA: arrow_schema::SchemaRef =
@@ -182,6 +188,52 @@ pub trait VectorIndex: Send + Sync + std::fmt::Debug + Index {
         metrics: &dyn MetricsCollector,
     ) -> Result<RecordBatch>;
 
+    /// Search a single partition for the nearest neighbors of each of the queries.
+    ///
+    /// Returns one [RecordBatch] per query, in the order of `queries`. Indices that load
+    /// partitions on demand should override this so the partition is loaded only once.
+    async fn search_in_partition_batch(
+        &self,
+        partition_id: usize,
+        queries: &[Query],
+        pre_filter: Arc<dyn PreFilter>,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<Vec<RecordBatch>> {
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries {
+            results.push(
+                self.search_in_partition(partition_id, query, pre_filter.clone(), metrics)
+                    .await?,
+            );
+        }
+        Ok(results)
+    }
+
+    /// Search a multivector query, the vectors of the query are flattened in `query.key`.
+    ///
+    /// The documents in the closest `query.minimum_nprobes` partitions of each query vector
+    /// are first scored by the distances between the query vectors and the centroids of
+    /// their partitions. Only the best `num_candidates` documents are then scored by the
+    /// sum of the minimum distances between each query vector and their (quantized) vectors.
+    ///
+    /// The returned [RecordBatch] has the schema of [VECTOR_RESULT_SCHEMA].
+    async fn multivector_search(
+        &self,
+        _query: &Query,
+        _num_candidates: usize,
+        _pre_filter: Arc<dyn PreFilter>,
+        _metrics: &dyn MetricsCollector,
+    ) -> Result<RecordBatch> {
+        Err(Error::NotSupported {
+            source: format!(
+                "multivector search is not supported by index type {}",
+                self.index_type()
+            )
+            .into(),
+            location: location!(),
+        })
+    }
+
     /// If the index is loadable by IVF, so it can be a sub-index that
     /// is loaded on demand by IVF.
     fn is_loadable(&self) -> bool;
@@ -259,6 +311,11 @@ pub trait VectorIndex: Send + Sync + std::fmt::Debug + Index {
     fn ivf_model(&self) -> &IvfModel;
     fn quantizer(&self) -> Quantizer;
 
+    /// The factor that the multivectors are pooled by before being indexed.
+    fn token_pool_factor(&self) -> Option<usize> {
+        None
+    }
+
     /// the index type of this vector index.
     fn sub_index_type(&self) -> (SubIndexType, QuantizationType);
 }
@@ -2,16 +2,42 @@
 // SPDX-FileCopyrightText: Copyright The Lance Authors
 
 //! Binary Quantization (BQ)
+//!
+//! [BinaryQuantization] keeps the sign bit of each dimension.
+//!
+//! [RabitQuantizer] is a RaBitQ quantizer: the (residual) vectors are normalized,
+//! randomly rotated and then binary quantized, and two factors are kept with
+//! each code, the norm of the vector and the inner product between the
+//! normalized vector and its quantized form.  They give an unbiased estimation
+//! of the distance to a query, together with a bound of its error.
 
 use std::iter::once;
 use std::sync::Arc;
 
-use arrow_array::types::Float32Type;
-use arrow_array::{cast::AsArray, Array, ArrayRef, UInt8Array};
+use arrow::datatypes::Float32Type;
+use arrow_array::{cast::AsArray, Array, ArrayRef, FixedSizeListArray, UInt8Array};
+use arrow_schema::DataType;
+use deepsize::DeepSizeOf;
+use lance_arrow::FixedSizeListArrayExt;
 use lance_core::{Error, Result};
+use lance_linalg::distance::{DistanceType, Normalize};
 use num_traits::Float;
 use snafu::location;
 
+use self::builder::RQBuildParams;
+use self::storage::{RabitQuantizationMetadata, RabitQuantizationStorage, RQ_METADATA_KEY};
+use super::quantizer::{
+    Quantization, QuantizationMetadata, QuantizationType, Quantizer, QuantizerBuildParams,
+};
+use super::RQ_CODE_COLUMN;
+
+pub mod builder;
+pub mod storage;
+pub mod transform;
+
+/// Number of rounds of the random rotation.
+const NUM_ROTATION_ROUNDS: usize = 4;
+
 #[derive(Clone, Default)]
 pub struct BinaryQuantization {}
 
@@ -74,6 +100,323 @@ fn binary_quantization<T: Float>(data: &[T]) -> impl Iterator<Item = u8> + '_ {
         }))
 }
 
+/// Number of bytes of a RaBitQ code: the sign bits of the rotated vector,
+/// followed by the norm of the vector and the inner product between the
+/// normalized vector and its quantized form, both as little endian `f32`.
+pub fn rabit_code_width(dim: usize) -> usize {
+    dim.div_ceil(8) + 2 * std::mem::size_of::<f32>()
+}
+
+/// Deterministic generator of the random rotation, so that the rotation can be
+/// restored from its seed regardless of the version of the `rand` crate.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// A random orthogonal transformation of the vectors.
+///
+/// Each round permutes the dimensions, flips the signs of a random half of
+/// them, and applies a normalized Walsh-Hadamard transform to each block of
+/// the dimensions, the blocks being the powers of two summing to `dim`.
+#[derive(Debug, Clone)]
+pub struct RandomRotation {
+    dim: usize,
+    // (permutation, signs) of each round
+    rounds: Vec<(Vec<u32>, Vec<bool>)>,
+}
+
+impl DeepSizeOf for RandomRotation {
+    fn deep_size_of_children(&self, _context: &mut deepsize::Context) -> usize {
+        self.rounds.len() * self.dim * (std::mem::size_of::<u32>() + std::mem::size_of::<bool>())
+    }
+}
+
+impl RandomRotation {
+    pub fn new(dim: usize, seed: u64) -> Self {
+        let mut rng = SplitMix64(seed);
+        let rounds = (0..NUM_ROTATION_ROUNDS)
+            .map(|_| {
+                let mut permutation = (0..dim as u32).collect::<Vec<_>>();
+                for i in (1..dim).rev() {
+                    let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+                    permutation.swap(i, j);
+                }
+                let signs = (0..dim).map(|_| rng.next_u64() & 1 == 1).collect();
+                (permutation, signs)
+            })
+            .collect();
+        Self { dim, rounds }
+    }
+
+    pub fn rotate(&self, vector: &[f32]) -> Vec<f32> {
+        debug_assert_eq!(vector.len(), self.dim);
+        let mut current = vector.to_vec();
+        let mut next = vec![0.0; self.dim];
+        for (permutation, signs) in self.rounds.iter() {
+            for (i, (&p, &flip)) in permutation.iter().zip(signs).enumerate() {
+                let v = current[p as usize];
+                next[i] = if flip { -v } else { v };
+            }
+            hadamard_blocks(&mut next);
+            std::mem::swap(&mut current, &mut next);
+        }
+        current
+    }
+
+    pub fn inverse_rotate(&self, vector: &[f32]) -> Vec<f32> {
+        debug_assert_eq!(vector.len(), self.dim);
+        let mut current = vector.to_vec();
+        let mut next = vec![0.0; self.dim];
+        for (permutation, signs) in self.rounds.iter().rev() {
+            // the normalized Hadamard transform is its own inverse
+            hadamard_blocks(&mut current);
+            for (i, (&p, &flip)) in permutation.iter().zip(signs).enumerate() {
+                let v = current[i];
+                next[p as usize] = if flip { -v } else { v };
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        current
+    }
+}
+
+/// In-place normalized Walsh-Hadamard transform over the blocks of `vector`,
+/// whose sizes are the powers of two of the binary representation of its length.
+fn hadamard_blocks(vector: &mut [f32]) {
+    let mut offset = 0;
+    let mut remaining = vector.len();
+    while remaining > 0 {
+        let size = 1 << (usize::BITS - 1 - remaining.leading_zeros());
+        let block = &mut vector[offset..offset + size];
+        let mut h = 1;
+        while h < size {
+            for start in (0..size).step_by(2 * h) {
+                for i in start..start + h {
+                    let (a, b) = (block[i], block[i + h]);
+                    block[i] = a + b;
+                    block[i + h] = a - b;
+                }
+            }
+            h *= 2;
+        }
+        let scale = 1.0 / (size as f32).sqrt();
+        block.iter_mut().for_each(|v| *v *= scale);
+        offset += size;
+        remaining -= size;
+    }
+}
+
+/// RaBitQ quantizer, with 1 bit per dimension.
+#[derive(Debug, Clone)]
+pub struct RabitQuantizer {
+    dim: usize,
+    rotation_seed: u64,
+    refine_factor: u32,
+    rotation: Arc<RandomRotation>,
+}
+
+impl DeepSizeOf for RabitQuantizer {
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.rotation.deep_size_of_children(context)
+    }
+}
+
+impl RabitQuantizer {
+    pub fn new(dim: usize, rotation_seed: u64, refine_factor: u32) -> Self {
+        Self {
+            dim,
+            rotation_seed,
+            refine_factor,
+            rotation: Arc::new(RandomRotation::new(dim, rotation_seed)),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// The default number of candidates reranked with the raw vectors,
+    /// as a multiple of `k`.
+    pub fn refine_factor(&self) -> u32 {
+        self.refine_factor
+    }
+
+    pub fn rotation(&self) -> &RandomRotation {
+        &self.rotation
+    }
+
+    /// Number of bytes of each code, see [rabit_code_width].
+    pub fn code_width(&self) -> usize {
+        rabit_code_width(self.dim)
+    }
+
+    /// Quantize the vectors into the codes described in [rabit_code_width].
+    pub fn transform(&self, vectors: &dyn Array) -> Result<ArrayRef> {
+        let fsl = vectors.as_fixed_size_list_opt().ok_or(Error::Index {
+            message: format!(
+                "RQ transform: input is not a FixedSizeList: {}",
+                vectors.data_type()
+            ),
+            location: location!(),
+        })?;
+        if fsl.value_length() as usize != self.dim {
+            return Err(Error::Index {
+                message: format!(
+                    "RQ transform: expect vectors of dimension {}, got {}",
+                    self.dim,
+                    fsl.value_length()
+                ),
+                location: location!(),
+            });
+        }
+        let values = match fsl.value_type() {
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+                arrow::compute::cast(fsl.values(), &DataType::Float32)?
+            }
+            value_type => {
+                return Err(Error::invalid_input(
+                    format!("unsupported data type {} for RaBitQ quantizer", value_type),
+                    location!(),
+                ))
+            }
+        };
+        let values = values.as_primitive::<Float32Type>().values();
+
+        let sqrt_dim = (self.dim as f32).sqrt();
+        let mut codes = Vec::with_capacity(fsl.len() * rabit_code_width(self.dim));
+        for vector in values.chunks_exact(self.dim) {
+            let norm = f32::norm_l2(vector);
+            let (bits, dot_factor) = if norm > 0.0 {
+                let unit = vector.iter().map(|v| v / norm).collect::<Vec<_>>();
+                let rotated = self.rotation.rotate(&unit);
+                let dot_factor = rotated.iter().map(|v| v.abs()).sum::<f32>() / sqrt_dim;
+                // binary_quantization always ends with a byte of the remaining
+                // dimensions, even if there are none
+                let bits = binary_quantization(&rotated)
+                    .take(self.dim.div_ceil(8))
+                    .collect::<Vec<_>>();
+                (bits, dot_factor)
+            } else {
+                (vec![0; self.dim.div_ceil(8)], 1.0)
+            };
+            codes.extend(bits);
+            codes.extend_from_slice(&norm.to_le_bytes());
+            codes.extend_from_slice(&dot_factor.to_le_bytes());
+        }
+
+        Ok(Arc::new(FixedSizeListArray::try_new_from_values(
+            UInt8Array::from(codes),
+            rabit_code_width(self.dim) as i32,
+        )?))
+    }
+}
+
+impl TryFrom<Quantizer> for RabitQuantizer {
+    type Error = Error;
+    fn try_from(value: Quantizer) -> Result<Self> {
+        match value {
+            Quantizer::Rabit(rq) => Ok(rq),
+            _ => Err(Error::Index {
+                message: "Expect to be a RabitQuantizer".to_string(),
+                location: location!(),
+            }),
+        }
+    }
+}
+
+impl From<RabitQuantizer> for Quantizer {
+    fn from(rq: RabitQuantizer) -> Self {
+        Self::Rabit(rq)
+    }
+}
+
+impl Quantization for RabitQuantizer {
+    type BuildParams = RQBuildParams;
+    type Metadata = RabitQuantizationMetadata;
+    type Storage = RabitQuantizationStorage;
+
+    fn build(data: &dyn Array, _: DistanceType, params: &Self::BuildParams) -> Result<Self> {
+        let fsl = data.as_fixed_size_list_opt().ok_or(Error::Index {
+            message: format!(
+                "RQ builder: input is not a FixedSizeList: {}",
+                data.data_type()
+            ),
+            location: location!(),
+        })?;
+        if !fsl.value_type().is_floating() {
+            return Err(Error::Index {
+                message: format!("RQ builder: unsupported data type: {}", fsl.value_type()),
+                location: location!(),
+            });
+        }
+        if params.refine_factor == 0 {
+            return Err(Error::invalid_input(
+                "RQ builder: refine factor must be positive",
+                location!(),
+            ));
+        }
+
+        Ok(Self::new(
+            fsl.value_length() as usize,
+            rand::random(),
+            params.refine_factor,
+        ))
+    }
+
+    fn retrain(&mut self, _: &dyn Array) -> Result<()> {
+        // The rotation is data independent
+        Ok(())
+    }
+
+    fn code_dim(&self) -> usize {
+        rabit_code_width(self.dim)
+    }
+
+    fn column(&self) -> &'static str {
+        RQ_CODE_COLUMN
+    }
+
+    fn use_residual(distance_type: DistanceType) -> bool {
+        RQBuildParams::use_residual(distance_type)
+    }
+
+    fn quantize(&self, vectors: &dyn Array) -> Result<ArrayRef> {
+        self.transform(vectors)
+    }
+
+    fn metadata_key() -> &'static str {
+        RQ_METADATA_KEY
+    }
+
+    fn quantization_type() -> QuantizationType {
+        QuantizationType::Rabit
+    }
+
+    fn metadata(&self, _: Option<QuantizationMetadata>) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(RabitQuantizationMetadata {
+            dim: self.dim,
+            rotation_seed: self.rotation_seed,
+            refine_factor: self.refine_factor,
+        })?)
+    }
+
+    fn from_metadata(metadata: &Self::Metadata, _: DistanceType) -> Result<Quantizer> {
+        Ok(Quantizer::Rabit(Self::new(
+            metadata.dim,
+            metadata.rotation_seed,
+            metadata.refine_factor,
+        )))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +440,48 @@ mod tests {
         test_bq::<f32>();
         test_bq::<f64>();
     }
+
+    #[test]
+    fn test_random_rotation() {
+        for dim in [1, 7, 64, 100, 1536] {
+            let rotation = RandomRotation::new(dim, 42);
+            let vector = (0..dim)
+                .map(|i| (i as f32 * 0.37 + 1.0).sin())
+                .collect::<Vec<_>>();
+            let rotated = rotation.rotate(&vector);
+            // orthogonal: the norm is preserved
+            let norm = f32::norm_l2(&vector);
+            assert!(
+                (f32::norm_l2(&rotated) - norm).abs() < 1e-3 * norm,
+                "dim {}: {} vs {}",
+                dim,
+                f32::norm_l2(&rotated),
+                norm
+            );
+
+            let restored = rotation.inverse_rotate(&rotated);
+            for (a, b) in vector.iter().zip(restored.iter()) {
+                assert!((a - b).abs() < 1e-4, "{} vs {}", a, b);
+            }
+
+            // the rotation is restored from its seed
+            assert_eq!(RandomRotation::new(dim, 42).rotate(&vector), rotated);
+        }
+    }
+
+    #[test]
+    fn test_rabit_code_width() {
+        let dim = 100;
+        let rq = RabitQuantizer::new(dim, 7, 10);
+        let vectors = FixedSizeListArray::try_new_from_values(
+            arrow_array::Float32Array::from_iter_values((0..dim * 3).map(|v| v as f32 - 150.0)),
+            dim as i32,
+        )
+        .unwrap();
+        let codes = rq.quantize(&vectors).unwrap();
+        let codes = codes.as_fixed_size_list();
+        assert_eq!(codes.len(), 3);
+        assert_eq!(codes.value_length() as usize, 13 + 8);
+        assert_eq!(rq.code_dim(), 21);
+    }
 }
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+use lance_linalg::distance::DistanceType;
+
+use crate::vector::quantizer::QuantizerBuildParams;
+
+#[derive(Debug, Clone)]
+pub struct RQBuildParams {
+    /// Number of candidates reranked with the raw vectors, as a multiple of `k`,
+    /// for the queries that do not set a refine factor.
+    pub refine_factor: u32,
+}
+
+impl Default for RQBuildParams {
+    fn default() -> Self {
+        Self { refine_factor: 10 }
+    }
+}
+
+impl RQBuildParams {
+    pub fn new(refine_factor: u32) -> Self {
+        Self { refine_factor }
+    }
+}
+
+impl QuantizerBuildParams for RQBuildParams {
+    fn sample_size(&self) -> usize {
+        // The quantizer is not trained, the sample only gives the dimension
+        256
+    }
+
+    fn use_residual(distance_type: DistanceType) -> bool {
+        matches!(distance_type, DistanceType::L2 | DistanceType::Cosine)
+    }
+}
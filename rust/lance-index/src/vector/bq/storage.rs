@@ -0,0 +1,446 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+use arrow::compute::concat_batches;
+use arrow::datatypes::Float32Type;
+use arrow_array::{
+    cast::AsArray,
+    types::{UInt64Type, UInt8Type},
+    ArrayRef, RecordBatch, UInt64Array, UInt8Array,
+};
+use arrow_schema::SchemaRef;
+use async_trait::async_trait;
+use deepsize::DeepSizeOf;
+use lance_core::{Error, Result, ROW_ID};
+use lance_file::reader::FileReader;
+use lance_linalg::distance::{DistanceType, Normalize};
+use serde::{Deserialize, Serialize};
+use snafu::location;
+
+use crate::vector::quantizer::{QuantizerMetadata, QuantizerStorage};
+use crate::vector::storage::{DistCalculator, VectorStore, STORAGE_METADATA_KEY};
+use crate::vector::transform::Transformer;
+use crate::vector::RQ_CODE_COLUMN;
+
+use super::{transform::RQTransformer, RabitQuantizer};
+
+pub const RQ_METADATA_KEY: &str = "lance:rq";
+
+/// Confidence multiplier of the error bound of the estimated inner product,
+/// the default of the RaBitQ paper.
+const ERROR_BOUND_EPSILON: f32 = 1.9;
+
+#[derive(Debug, Clone, Serialize, Deserialize, DeepSizeOf)]
+pub struct RabitQuantizationMetadata {
+    pub dim: usize,
+    pub rotation_seed: u64,
+    pub refine_factor: u32,
+}
+
+#[async_trait]
+impl QuantizerMetadata for RabitQuantizationMetadata {
+    async fn load(reader: &FileReader) -> Result<Self> {
+        let metadata_str = reader
+            .schema()
+            .metadata
+            .get(RQ_METADATA_KEY)
+            .ok_or(Error::Index {
+                message: format!(
+                    "Reading RQ metadata: metadata key {} not found",
+                    RQ_METADATA_KEY
+                ),
+                location: location!(),
+            })?;
+        serde_json::from_str(metadata_str).map_err(|_| Error::Index {
+            message: format!("Failed to parse index metadata: {}", metadata_str),
+            location: location!(),
+        })
+    }
+}
+
+/// In-memory storage of RaBitQ codes.
+#[derive(Debug, Clone)]
+pub struct RabitQuantizationStorage {
+    quantizer: RabitQuantizer,
+    distance_type: DistanceType,
+    batch: RecordBatch,
+
+    // Helper fields, references to the batch
+    row_ids: UInt64Array,
+    codes: UInt8Array,
+}
+
+impl DeepSizeOf for RabitQuantizationStorage {
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.batch.get_array_memory_size() + self.quantizer.deep_size_of_children(context)
+    }
+}
+
+impl RabitQuantizationStorage {
+    pub fn try_new(
+        quantizer: RabitQuantizer,
+        distance_type: DistanceType,
+        batch: RecordBatch,
+    ) -> Result<Self> {
+        let row_ids = batch
+            .column_by_name(ROW_ID)
+            .ok_or(Error::Index {
+                message: "Row ID column not found in the batch".to_owned(),
+                location: location!(),
+            })?
+            .as_primitive::<UInt64Type>()
+            .clone();
+        let fsl = batch
+            .column_by_name(RQ_CODE_COLUMN)
+            .ok_or(Error::Index {
+                message: "RQ code column not found in the batch".to_owned(),
+                location: location!(),
+            })?
+            .as_fixed_size_list_opt()
+            .ok_or(Error::Index {
+                message: "RQ code column is not FixedSizeList<u8>".to_owned(),
+                location: location!(),
+            })?;
+        if fsl.value_length() as usize != quantizer.code_width() {
+            return Err(Error::Index {
+                message: format!(
+                    "RQ code width {} does not match the dimension {}",
+                    fsl.value_length(),
+                    quantizer.dim()
+                ),
+                location: location!(),
+            });
+        }
+        let codes = fsl
+            .values()
+            .as_primitive_opt::<UInt8Type>()
+            .ok_or(Error::Index {
+                message: "RQ code column is not FixedSizeList<u8>".to_owned(),
+                location: location!(),
+            })?
+            .clone();
+
+        Ok(Self {
+            quantizer,
+            distance_type,
+            batch,
+            row_ids,
+            codes,
+        })
+    }
+
+    pub fn quantizer(&self) -> &RabitQuantizer {
+        &self.quantizer
+    }
+
+    /// The sign bits, the norm and the inner product factor of vector `id`.
+    #[inline]
+    fn code(&self, id: u32) -> (&[u8], f32, f32) {
+        let width = self.quantizer.code_width();
+        let code = &self.codes.values()[id as usize * width..(id as usize + 1) * width];
+        decode(code)
+    }
+}
+
+#[inline]
+fn decode(code: &[u8]) -> (&[u8], f32, f32) {
+    let (bits, factors) = code.split_at(code.len() - 8);
+    let norm = f32::from_le_bytes(factors[..4].try_into().unwrap());
+    let dot_factor = f32::from_le_bytes(factors[4..].try_into().unwrap());
+    (bits, norm, dot_factor)
+}
+
+#[async_trait]
+impl QuantizerStorage for RabitQuantizationStorage {
+    type Metadata = RabitQuantizationMetadata;
+
+    async fn load_partition(
+        reader: &FileReader,
+        range: std::ops::Range<usize>,
+        distance_type: DistanceType,
+        metadata: &Self::Metadata,
+    ) -> Result<Self> {
+        let schema = reader.schema();
+        let batch = reader.read_range(range, schema).await?;
+        let quantizer =
+            RabitQuantizer::new(metadata.dim, metadata.rotation_seed, metadata.refine_factor);
+        Self::try_new(quantizer, distance_type, batch)
+    }
+}
+
+impl VectorStore for RabitQuantizationStorage {
+    type DistanceCalculator<'a> = RabitDistCalculator<'a>;
+
+    fn try_from_batch(batch: RecordBatch, distance_type: DistanceType) -> Result<Self> {
+        let metadata_json = batch
+            .schema_ref()
+            .metadata()
+            .get(STORAGE_METADATA_KEY)
+            .ok_or(Error::Schema {
+                message: "metadata not found".to_string(),
+                location: location!(),
+            })?;
+        let metadata: RabitQuantizationMetadata = serde_json::from_str(metadata_json)?;
+        let quantizer =
+            RabitQuantizer::new(metadata.dim, metadata.rotation_seed, metadata.refine_factor);
+        Self::try_new(quantizer, distance_type, batch)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn schema(&self) -> &SchemaRef {
+        self.batch.schema_ref()
+    }
+
+    fn to_batches(&self) -> Result<impl Iterator<Item = RecordBatch>> {
+        Ok(std::iter::once(self.batch.clone()))
+    }
+
+    fn len(&self) -> usize {
+        self.row_ids.len()
+    }
+
+    fn distance_type(&self) -> DistanceType {
+        self.distance_type
+    }
+
+    fn row_id(&self, id: u32) -> u64 {
+        self.row_ids.value(id as usize)
+    }
+
+    fn row_ids(&self) -> impl Iterator<Item = &u64> {
+        self.row_ids.values().iter()
+    }
+
+    fn append_batch(&self, batch: RecordBatch, vector_column: &str) -> Result<Self> {
+        let transformer = RQTransformer::new(
+            self.quantizer.clone(),
+            vector_column.to_string(),
+            RQ_CODE_COLUMN.to_string(),
+        );
+        let new_batch = transformer.transform(&batch)?;
+        let new_batch = new_batch.with_schema(self.schema().clone())?;
+        let batch = concat_batches(self.schema(), [&self.batch, &new_batch])?;
+        Self::try_new(self.quantizer.clone(), self.distance_type, batch)
+    }
+
+    fn dist_calculator(&self, query: ArrayRef) -> Self::DistanceCalculator<'_> {
+        // the query is cast to float32 by the index
+        RabitDistCalculator::new(query.as_primitive::<Float32Type>().values(), self)
+    }
+
+    fn dist_calculator_from_id(&self, id: u32) -> Self::DistanceCalculator<'_> {
+        // Reconstruct the vector from its code
+        let (bits, norm, _) = self.code(id);
+        let dim = self.quantizer.dim();
+        let scale = norm / (dim as f32).sqrt();
+        let rotated = (0..dim)
+            .map(|i| {
+                if bits[i / 8] >> (i % 8) & 1 == 1 {
+                    scale
+                } else {
+                    -scale
+                }
+            })
+            .collect::<Vec<_>>();
+        let vector = self.quantizer.rotation().inverse_rotate(&rotated);
+        RabitDistCalculator::new(&vector, self)
+    }
+}
+
+/// Distance calculator of RaBitQ.
+///
+/// The distance is estimated from the inner product between the normalized
+/// query and the normalized vector, as in the RaBitQ paper, shifted by its error
+/// bound.  So the distances are lower bounds of the true distances with a high
+/// probability, which ranks the uncertain candidates first: the results must be
+/// reranked with the raw vectors.
+pub struct RabitDistCalculator<'a> {
+    storage: &'a RabitQuantizationStorage,
+
+    // lut[i * 256 + b] is the sum of the rotated query over the set bits of `b`
+    // at the i-th byte of the codes.
+    lut: Vec<f32>,
+    query_sum: f32,
+    query_norm: f32,
+    sqrt_dim: f32,
+    error_scale: f32,
+}
+
+impl<'a> RabitDistCalculator<'a> {
+    fn new(query: &[f32], storage: &'a RabitQuantizationStorage) -> Self {
+        let dim = storage.quantizer.dim();
+        let query_norm = f32::norm_l2(query);
+        let rotated = if query_norm > 0.0 {
+            let unit = query.iter().map(|v| v / query_norm).collect::<Vec<_>>();
+            storage.quantizer.rotation().rotate(&unit)
+        } else {
+            vec![0.0; dim]
+        };
+
+        let num_bytes = dim.div_ceil(8);
+        let mut lut = vec![0.0; num_bytes * 256];
+        for (byte, table) in lut.chunks_exact_mut(256).enumerate() {
+            for b in 1..256_usize {
+                let lowest = b.trailing_zeros() as usize;
+                let value = rotated.get(byte * 8 + lowest).copied().unwrap_or_default();
+                table[b] = table[b & (b - 1)] + value;
+            }
+        }
+
+        Self {
+            storage,
+            lut,
+            query_sum: rotated.iter().sum(),
+            query_norm,
+            sqrt_dim: (dim as f32).sqrt(),
+            error_scale: ERROR_BOUND_EPSILON / ((dim.max(2) - 1) as f32).sqrt(),
+        }
+    }
+
+    #[inline]
+    fn estimate(&self, code: &[u8]) -> f32 {
+        let (bits, norm, dot_factor) = decode(code);
+        let sum = bits
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| self.lut[i * 256 + b as usize])
+            .sum::<f32>();
+        // <quantized vector, query>, the quantized vector is (2 * bits - 1) / sqrt(dim)
+        let ip = (2.0 * sum - self.query_sum) / self.sqrt_dim;
+        let estimated = ip / dot_factor;
+        let error = self.error_scale * (1.0 - dot_factor * dot_factor).max(0.0).sqrt() / dot_factor;
+        let upper_ip = (estimated + error) * norm * self.query_norm;
+
+        match self.storage.distance_type {
+            DistanceType::L2 | DistanceType::Cosine => {
+                (norm * norm + self.query_norm * self.query_norm - 2.0 * upper_ip).max(0.0)
+            }
+            DistanceType::Dot => 1.0 - upper_ip,
+            _ => panic!("We should not reach here: rq distance can only be L2 or Dot"),
+        }
+    }
+}
+
+impl DistCalculator for RabitDistCalculator<'_> {
+    fn distance(&self, id: u32) -> f32 {
+        let width = self.storage.quantizer.code_width();
+        let code = &self.storage.codes.values()[id as usize * width..(id as usize + 1) * width];
+        self.estimate(code)
+    }
+
+    fn distance_all(&self, _k_hint: usize) -> Vec<f32> {
+        let width = self.storage.quantizer.code_width();
+        self.storage
+            .codes
+            .values()
+            .chunks_exact(width)
+            .map(|code| self.estimate(code))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use arrow_array::{Array, FixedSizeListArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use lance_arrow::FixedSizeListArrayExt;
+    use lance_linalg::distance::l2_distance_batch;
+    use lance_testing::datagen::{generate_random_array, generate_scaled_random_array};
+
+    use crate::vector::quantizer::Quantization;
+    use crate::vector::storage::StorageBuilder;
+
+    const DIM: usize = 64;
+    const NUM_ROWS: usize = 1000;
+
+    fn make_batch(vectors: &FixedSizeListArray) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(ROW_ID, DataType::UInt64, false),
+            Field::new("vector", vectors.data_type().clone(), false),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(UInt64Array::from_iter_values(0..vectors.len() as u64)),
+                Arc::new(vectors.clone()),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rq_distance_is_lower_bound() {
+        // centered, as the residuals of the IVF partitions
+        let vectors = FixedSizeListArray::try_new_from_values(
+            generate_scaled_random_array(NUM_ROWS * DIM, -1.0, 1.0),
+            DIM as i32,
+        )
+        .unwrap();
+        let rq = RabitQuantizer::build(&vectors, DistanceType::L2, &Default::default()).unwrap();
+        let storage = StorageBuilder::new("vector".to_owned(), DistanceType::L2, rq)
+            .unwrap()
+            .build(vec![make_batch(&vectors)])
+            .unwrap();
+        assert_eq!(storage.len(), NUM_ROWS);
+
+        let query = generate_scaled_random_array(DIM, -1.0, 1.0);
+        let estimated = storage
+            .dist_calculator(Arc::new(query.clone()))
+            .distance_all(10);
+        let exact = l2_distance_batch(
+            query.values(),
+            vectors.values().as_primitive::<Float32Type>().values(),
+            DIM,
+        )
+        .collect::<Vec<_>>();
+        let num_bounded = estimated
+            .iter()
+            .zip(exact.iter())
+            .filter(|(e, d)| **e <= **d + 1e-4)
+            .count();
+        assert!(num_bounded >= NUM_ROWS * 95 / 100, "{}", num_bounded);
+
+        // The nearest neighbor is within the candidates reranked for k = 10
+        let nearest = (0..NUM_ROWS)
+            .min_by(|&a, &b| exact[a].total_cmp(&exact[b]))
+            .unwrap();
+        let rank = estimated
+            .iter()
+            .filter(|e| **e < estimated[nearest])
+            .count();
+        assert!(rank < 100, "rank of the nearest neighbor: {}", rank);
+    }
+
+    #[test]
+    fn test_rq_storage_round_trip() {
+        let vectors =
+            FixedSizeListArray::try_new_from_values(generate_random_array(100 * DIM), DIM as i32)
+                .unwrap();
+        let rq = RabitQuantizer::build(&vectors, DistanceType::Dot, &Default::default()).unwrap();
+        let storage = StorageBuilder::new("vector".to_owned(), DistanceType::Dot, rq)
+            .unwrap()
+            .build(vec![make_batch(&vectors)])
+            .unwrap();
+
+        let batch = storage.to_batches().unwrap().next().unwrap();
+        let restored = RabitQuantizationStorage::try_from_batch(batch, DistanceType::Dot).unwrap();
+        let query: ArrayRef = Arc::new(generate_random_array(DIM));
+        assert_eq!(
+            storage.dist_calculator(query.clone()).distance_all(10),
+            restored.dist_calculator(query).distance_all(10)
+        );
+
+        let storage = storage
+            .append_batch(make_batch(&vectors), "vector")
+            .unwrap();
+        assert_eq!(storage.len(), 200);
+        assert_eq!(storage.row_id(150), 50);
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+use std::fmt::{Debug, Formatter};
+
+use arrow_array::RecordBatch;
+use arrow_schema::Field;
+use lance_arrow::RecordBatchExt;
+use lance_core::{Error, Result};
+use snafu::location;
+use tracing::instrument;
+
+use crate::vector::transform::Transformer;
+
+use super::RabitQuantizer;
+
+pub struct RQTransformer {
+    quantizer: RabitQuantizer,
+    input_column: String,
+    output_column: String,
+}
+
+impl RQTransformer {
+    pub fn new(quantizer: RabitQuantizer, input_column: String, output_column: String) -> Self {
+        Self {
+            quantizer,
+            input_column,
+            output_column,
+        }
+    }
+}
+
+impl Debug for RQTransformer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RQTransformer(input={}, output={})",
+            self.input_column, self.output_column
+        )
+    }
+}
+
+impl Transformer for RQTransformer {
+    #[instrument(name = "RQTransformer::transform", level = "debug", skip_all)]
+    fn transform(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let input = batch
+            .column_by_name(&self.input_column)
+            .ok_or(Error::Index {
+                message: format!(
+                    "RQ Transform: column {} not found in batch",
+                    self.input_column
+                ),
+                location: location!(),
+            })?;
+        let rq_code = self.quantizer.transform(input)?;
+
+        let rq_field = Field::new(&self.output_column, rq_code.data_type().clone(), false);
+        let batch = batch
+            .try_with_column(rq_field, rq_code)?
+            .drop_column(&self.input_column)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,275 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! DiskANN graph index.
+//!
+//! A Vamana graph whose full vectors and adjacency lists live in a graph file on
+//! object storage, while the PQ codes of the vectors are kept in memory.
+//!
+//! The graph file is a sequence of fixed size blocks, one per node:
+//!
+//! ```text
+//! | vector: f32 x dimension | degree: u32 | neighbors: u32 x r |
+//! ```
+//!
+//! The search walks the graph with the PQ distances, reading the blocks of the
+//! closest candidates a few at a time, and ranks the visited nodes with the
+//! exact distances to the full vectors read from their blocks.
+
+use deepsize::DeepSizeOf;
+use serde::{Deserialize, Serialize};
+
+use super::graph::OrderedNode;
+
+pub mod builder;
+pub mod index;
+
+pub use builder::{build_vamana_graph, VamanaGraph};
+pub use index::DiskANNIndex;
+
+/// Version of the graph file layout.
+pub const DISKANN_SPEC_VERSION: u32 = 1;
+
+/// Name of the graph file in the index directory.
+pub const DISKANN_GRAPH_FILE_NAME: &str = "graph.diskann";
+
+/// Parameters of building a DiskANN index.
+#[derive(Debug, Clone, Serialize, Deserialize, DeepSizeOf)]
+pub struct DiskANNParams {
+    /// Maximum number of neighbors of each node.
+    pub r: usize,
+
+    /// Pruning factor, values above 1 keep longer edges which makes
+    /// the graph easier to navigate.
+    pub alpha: f32,
+
+    /// Size of the candidate list while building the graph,
+    /// also the default size of the candidate list while searching.
+    pub l: usize,
+
+    /// Maximum number of vectors of a graph built in memory.
+    ///
+    /// Larger indices are built over overlapping shards whose graphs are
+    /// merged, so only the vectors of one shard are in memory at a time.
+    #[serde(default = "default_shard_size")]
+    pub shard_size: usize,
+}
+
+fn default_shard_size() -> usize {
+    1_000_000
+}
+
+impl Default for DiskANNParams {
+    fn default() -> Self {
+        Self {
+            r: 64,
+            alpha: 1.2,
+            l: 100,
+            shard_size: default_shard_size(),
+        }
+    }
+}
+
+impl DiskANNParams {
+    pub fn new(r: usize, alpha: f32, l: usize) -> Self {
+        Self {
+            r,
+            alpha,
+            l,
+            shard_size: default_shard_size(),
+        }
+    }
+
+    /// The maximum number of neighbors of each node.
+    /// The default value is `64`.
+    pub fn r(mut self, r: usize) -> Self {
+        self.r = r;
+        self
+    }
+
+    /// The pruning factor.
+    /// The default value is `1.2`.
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// The size of the candidate list.
+    /// The default value is `100`.
+    pub fn l(mut self, l: usize) -> Self {
+        self.l = l;
+        self
+    }
+
+    /// The maximum number of vectors of a graph built in memory.
+    /// The default value is `1_000_000`.
+    pub fn shard_size(mut self, shard_size: usize) -> Self {
+        self.shard_size = shard_size;
+        self
+    }
+}
+
+/// Layout of the node blocks in the graph file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DeepSizeOf)]
+pub struct BlockLayout {
+    pub dimension: usize,
+    pub r: usize,
+}
+
+impl BlockLayout {
+    pub fn new(dimension: usize, r: usize) -> Self {
+        Self { dimension, r }
+    }
+
+    /// Size of a node block in bytes.
+    pub fn block_size(&self) -> usize {
+        4 * self.dimension + 4 + 4 * self.r
+    }
+
+    /// Byte range of the block of node `id`.
+    pub fn range(&self, id: u32) -> std::ops::Range<u64> {
+        let start = id as u64 * self.block_size() as u64;
+        start..start + self.block_size() as u64
+    }
+
+    /// Append the block of a node to `buf`.
+    pub fn encode(&self, vector: &[f32], neighbors: &[u32], buf: &mut Vec<u8>) {
+        debug_assert_eq!(vector.len(), self.dimension);
+        debug_assert!(neighbors.len() <= self.r);
+        buf.reserve(self.block_size());
+        for v in vector {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        buf.extend_from_slice(&(neighbors.len() as u32).to_le_bytes());
+        for n in neighbors {
+            buf.extend_from_slice(&n.to_le_bytes());
+        }
+        buf.resize(buf.len() + 4 * (self.r - neighbors.len()), 0);
+    }
+
+    /// Decode a node block into the vector and the neighbors of the node.
+    pub fn decode(&self, block: &[u8]) -> (Vec<f32>, Vec<u32>) {
+        debug_assert_eq!(block.len(), self.block_size());
+        let (vector, rest) = block.split_at(4 * self.dimension);
+        let vector = vector
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        let degree = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        let neighbors = rest[4..4 + 4 * degree.min(self.r)]
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        (vector, neighbors)
+    }
+}
+
+/// Bounded list of the closest candidates found so far, sorted by distance.
+pub(crate) struct CandidateList {
+    capacity: usize,
+    // (node, expanded)
+    nodes: Vec<(OrderedNode, bool)>,
+}
+
+impl CandidateList {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            nodes: Vec::with_capacity(capacity + 1),
+        }
+    }
+
+    /// Insert a node, unless the list is full of closer nodes.
+    pub(crate) fn insert(&mut self, node: OrderedNode) {
+        if self.nodes.len() >= self.capacity
+            && self.nodes.last().is_some_and(|(last, _)| *last <= node)
+        {
+            return;
+        }
+        let pos = self.nodes.partition_point(|(n, _)| *n <= node);
+        self.nodes.insert(pos, (node, false));
+        self.nodes.truncate(self.capacity);
+    }
+
+    /// Mark the `n` closest nodes that were not expanded yet as expanded
+    /// and return them.
+    pub(crate) fn next_unexpanded(&mut self, n: usize) -> Vec<OrderedNode> {
+        let mut nodes = Vec::with_capacity(n);
+        for (node, expanded) in self.nodes.iter_mut() {
+            if nodes.len() >= n {
+                break;
+            }
+            if !*expanded {
+                *expanded = true;
+                nodes.push(node.clone());
+            }
+        }
+        nodes
+    }
+}
+
+/// Robust prune of the Vamana paper.
+///
+/// Select at most `r` neighbors from the `candidates` of a node, sorted by
+/// their distances to the node.  A candidate is skipped if it is `alpha` times
+/// closer to a selected neighbor than to the node.
+pub fn robust_prune(
+    candidates: &[OrderedNode],
+    alpha: f32,
+    r: usize,
+    dist_between: impl Fn(u32, u32) -> f32,
+) -> Vec<u32> {
+    let mut selected: Vec<u32> = Vec::with_capacity(r);
+    for candidate in candidates {
+        if selected.len() >= r {
+            break;
+        }
+        if selected.contains(&candidate.id) {
+            continue;
+        }
+        if selected
+            .iter()
+            .all(|&s| alpha * dist_between(s, candidate.id) > candidate.dist.0)
+        {
+            selected.push(candidate.id);
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_round_trip() {
+        let layout = BlockLayout::new(3, 4);
+        assert_eq!(layout.block_size(), 32);
+        assert_eq!(layout.range(2), 64..96);
+
+        let mut buf = vec![];
+        layout.encode(&[1.0, 2.0, 3.0], &[7, 9], &mut buf);
+        layout.encode(&[4.0, 5.0, 6.0], &[], &mut buf);
+        assert_eq!(buf.len(), 2 * layout.block_size());
+
+        let (vector, neighbors) = layout.decode(&buf[..32]);
+        assert_eq!(vector, vec![1.0, 2.0, 3.0]);
+        assert_eq!(neighbors, vec![7, 9]);
+        let (vector, neighbors) = layout.decode(&buf[32..]);
+        assert_eq!(vector, vec![4.0, 5.0, 6.0]);
+        assert!(neighbors.is_empty());
+    }
+
+    #[test]
+    fn test_candidate_list() {
+        let mut list = CandidateList::new(3);
+        for (id, dist) in [(0, 5.0), (1, 1.0), (2, 3.0), (3, 4.0), (4, 6.0)] {
+            list.insert(OrderedNode::new(id, dist.into()));
+        }
+        let ids = |nodes: Vec<OrderedNode>| nodes.into_iter().map(|n| n.id).collect::<Vec<_>>();
+        assert_eq!(ids(list.next_unexpanded(2)), vec![1, 2]);
+        list.insert(OrderedNode::new(5, 2.0.into()));
+        assert_eq!(ids(list.next_unexpanded(2)), vec![5]);
+        assert!(list.next_unexpanded(2).is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Builder of the Vamana graph.
+
+use std::collections::{HashMap, HashSet};
+
+use arrow::array::AsArray;
+use arrow::datatypes::Float32Type;
+use arrow_array::{Array, FixedSizeListArray};
+use arrow_schema::DataType;
+use lance_core::{Error, Result};
+use lance_linalg::distance::DistanceType;
+use rand::seq::{index::sample, SliceRandom};
+use rand::thread_rng;
+use rayon::prelude::*;
+use snafu::location;
+use tracing::instrument;
+
+use super::{robust_prune, CandidateList, DiskANNParams};
+use crate::vector::graph::{OrderedFloat, OrderedNode};
+
+/// Upper bound of the number of nodes inserted in parallel.
+const MAX_BATCH_SIZE: usize = 100_000;
+
+/// A Vamana graph.
+#[derive(Debug, Clone)]
+pub struct VamanaGraph {
+    /// Neighbors of each node.
+    pub neighbors: Vec<Vec<u32>>,
+
+    /// Entry point of the searches, the medoid of the vectors.
+    pub entry_point: u32,
+}
+
+impl VamanaGraph {
+    pub fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbors.is_empty()
+    }
+}
+
+/// Build a Vamana graph over `vectors`.
+///
+/// The vectors must be `Float32`, and normalized for the cosine distance.
+/// The graph is built in memory, in two passes over the vectors: the first one
+/// prunes with `alpha = 1` and the second one with `params.alpha`.  Within a
+/// pass the nodes are inserted in batches of growing size, each batch in
+/// parallel.
+#[instrument(level = "debug", skip(vectors))]
+pub fn build_vamana_graph(
+    vectors: &FixedSizeListArray,
+    distance_type: DistanceType,
+    params: &DiskANNParams,
+) -> Result<VamanaGraph> {
+    if vectors.value_type() != DataType::Float32 {
+        return Err(Error::Index {
+            message: format!(
+                "DiskANN: vectors must be Float32, got {}",
+                vectors.value_type()
+            ),
+            location: location!(),
+        });
+    }
+    // The vectors are normalized for cosine, so L2 gives the same order.
+    let distance_type = match distance_type {
+        DistanceType::L2 | DistanceType::Cosine => DistanceType::L2,
+        DistanceType::Dot => DistanceType::Dot,
+        DistanceType::Hamming => {
+            return Err(Error::NotSupported {
+                source: "DiskANN index does not support hamming distance".into(),
+                location: location!(),
+            })
+        }
+    };
+    if params.r == 0 || params.l == 0 {
+        return Err(Error::Index {
+            message: format!("DiskANN: r and L must be positive, got {:?}", params),
+            location: location!(),
+        });
+    }
+
+    let num_nodes = vectors.len();
+    if num_nodes == 0 {
+        return Ok(VamanaGraph {
+            neighbors: vec![],
+            entry_point: 0,
+        });
+    }
+
+    let dim = vectors.value_length() as usize;
+    let values = vectors.values().as_primitive::<Float32Type>().values();
+    let vector = |id: u32| &values[id as usize * dim..(id as usize + 1) * dim];
+    let dist_func = distance_type.func::<f32>();
+    let dist_between = |u: u32, v: u32| dist_func(vector(u), vector(v));
+
+    let entry_point = medoid(values, dim, num_nodes);
+
+    // Start from a random graph
+    let mut rng = thread_rng();
+    let degree = params.r.min(num_nodes - 1);
+    let mut neighbors = (0..num_nodes)
+        .map(|id| {
+            sample(&mut rng, num_nodes, (degree + 1).min(num_nodes))
+                .into_iter()
+                .map(|n| n as u32)
+                .filter(|&n| n != id as u32)
+                .take(degree)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let max_batch_size = (num_nodes / 50).clamp(1, MAX_BATCH_SIZE);
+    let mut order = (0..num_nodes as u32).collect::<Vec<_>>();
+    for alpha in [1.0, params.alpha] {
+        order.shuffle(&mut rng);
+
+        let mut start = 0;
+        let mut batch_size = 1;
+        while start < num_nodes {
+            let end = (start + batch_size).min(num_nodes);
+            let graph = &neighbors;
+
+            let updates = order[start..end]
+                .par_iter()
+                .map(|&id| {
+                    let query = vector(id);
+                    let mut candidates = greedy_search(
+                        graph,
+                        |n| dist_func(query, vector(n)),
+                        entry_point,
+                        params.l,
+                    );
+                    candidates.extend(
+                        graph[id as usize]
+                            .iter()
+                            .map(|&n| OrderedNode::new(n, dist_between(id, n).into())),
+                    );
+                    candidates.retain(|n| n.id != id);
+                    candidates.sort_unstable();
+                    (id, robust_prune(&candidates, alpha, params.r, dist_between))
+                })
+                .collect::<Vec<_>>();
+
+            // Add the reverse edges, and prune the nodes with too many neighbors
+            let mut reverse_edges: HashMap<u32, Vec<u32>> = HashMap::new();
+            for (id, selected) in updates {
+                for &n in selected.iter() {
+                    reverse_edges.entry(n).or_default().push(id);
+                }
+                neighbors[id as usize] = selected;
+            }
+            let graph = &neighbors;
+            let updates = reverse_edges
+                .into_par_iter()
+                .map(|(id, new_neighbors)| {
+                    let mut selected = graph[id as usize].clone();
+                    for n in new_neighbors {
+                        if !selected.contains(&n) {
+                            selected.push(n);
+                        }
+                    }
+                    if selected.len() > params.r {
+                        let mut candidates = selected
+                            .iter()
+                            .map(|&n| OrderedNode::new(n, dist_between(id, n).into()))
+                            .collect::<Vec<_>>();
+                        candidates.sort_unstable();
+                        selected = robust_prune(&candidates, alpha, params.r, dist_between);
+                    }
+                    (id, selected)
+                })
+                .collect::<Vec<_>>();
+            for (id, selected) in updates {
+                neighbors[id as usize] = selected;
+            }
+
+            start = end;
+            batch_size = (batch_size * 2).min(max_batch_size);
+        }
+    }
+
+    Ok(VamanaGraph {
+        neighbors,
+        entry_point,
+    })
+}
+
+/// The node closest to the mean of the vectors.
+fn medoid(values: &[f32], dim: usize, num_nodes: usize) -> u32 {
+    let mut centroid = vec![0.0_f64; dim];
+    for vector in values.chunks_exact(dim) {
+        for (c, v) in centroid.iter_mut().zip(vector) {
+            *c += *v as f64;
+        }
+    }
+    let centroid = centroid
+        .into_iter()
+        .map(|c| (c / num_nodes as f64) as f32)
+        .collect::<Vec<_>>();
+    let l2 = DistanceType::L2.func::<f32>();
+    values
+        .chunks_exact(dim)
+        .enumerate()
+        .map(|(id, vector)| (OrderedFloat(l2(&centroid, vector)), id as u32))
+        .min()
+        .map(|(_, id)| id)
+        .unwrap_or_default()
+}
+
+/// Greedy search of the Vamana paper over the in-memory graph.
+///
+/// Returns all the nodes expanded during the search.
+fn greedy_search(
+    graph: &[Vec<u32>],
+    dist_to: impl Fn(u32) -> f32,
+    entry_point: u32,
+    l: usize,
+) -> Vec<OrderedNode> {
+    let mut candidates = CandidateList::new(l);
+    let mut seen = HashSet::new();
+    seen.insert(entry_point);
+    candidates.insert(OrderedNode::new(entry_point, dist_to(entry_point).into()));
+
+    let mut expanded = Vec::new();
+    while let Some(node) = candidates.next_unexpanded(1).pop() {
+        for &n in graph[node.id as usize].iter() {
+            if seen.insert(n) {
+                candidates.insert(OrderedNode::new(n, dist_to(n).into()));
+            }
+        }
+        expanded.push(node);
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::Float32Array;
+    use lance_arrow::FixedSizeListArrayExt;
+    use lance_testing::datagen::generate_random_array;
+
+    #[test]
+    fn test_build_vamana_graph() {
+        const DIM: usize = 16;
+        const NUM_NODES: usize = 1000;
+        let values = generate_random_array(DIM * NUM_NODES);
+        let vectors = FixedSizeListArray::try_new_from_values(values, DIM as i32).unwrap();
+        let params = DiskANNParams::new(16, 1.2, 32);
+        let graph = build_vamana_graph(&vectors, DistanceType::L2, &params).unwrap();
+
+        assert_eq!(graph.len(), NUM_NODES);
+        for (id, neighbors) in graph.neighbors.iter().enumerate() {
+            assert!(!neighbors.is_empty() && neighbors.len() <= params.r);
+            assert!(!neighbors.contains(&(id as u32)));
+        }
+
+        // Every node is reachable from the entry point
+        let mut seen = HashSet::from([graph.entry_point]);
+        let mut stack = vec![graph.entry_point];
+        while let Some(id) = stack.pop() {
+            for &n in graph.neighbors[id as usize].iter() {
+                if seen.insert(n) {
+                    stack.push(n);
+                }
+            }
+        }
+        assert_eq!(seen.len(), NUM_NODES);
+
+        // The greedy search finds the nearest neighbor of most vectors
+        let values = vectors.values().as_primitive::<Float32Type>().values();
+        let l2 = DistanceType::L2.func::<f32>();
+        let found = (0..NUM_NODES)
+            .step_by(10)
+            .filter(|&id| {
+                let query = &values[id * DIM..(id + 1) * DIM];
+                let results = greedy_search(
+                    &graph.neighbors,
+                    |n| l2(query, &values[n as usize * DIM..(n as usize + 1) * DIM]),
+                    graph.entry_point,
+                    params.l,
+                );
+                results.iter().any(|n| n.id == id as u32)
+            })
+            .count();
+        assert!(found >= 95, "found {} of 100", found);
+    }
+
+    #[test]
+    fn test_build_vamana_graph_small() {
+        let vectors = FixedSizeListArray::try_new_from_values(
+            Float32Array::from(vec![0.0, 0.0, 1.0, 1.0]),
+            2,
+        )
+        .unwrap();
+        let graph =
+            build_vamana_graph(&vectors, DistanceType::L2, &DiskANNParams::default()).unwrap();
+        assert_eq!(graph.neighbors, vec![vec![1], vec![0]]);
+
+        let vectors =
+            FixedSizeListArray::try_new_from_values(Float32Array::from(Vec::<f32>::new()), 2)
+                .unwrap();
+        let graph =
+            build_vamana_graph(&vectors, DistanceType::L2, &DiskANNParams::default()).unwrap();
+        assert!(graph.is_empty());
+    }
+}
@@ -0,0 +1,544 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! DiskANN index searching the graph file through the I/O scheduler.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::ops::Range;
+use std::{any::Any, sync::Arc};
+
+use arrow::array::AsArray;
+use arrow::compute::cast;
+use arrow::datatypes::Float32Type;
+use arrow_array::{ArrayRef, Float32Array, RecordBatch, UInt32Array, UInt64Array};
+use arrow_schema::DataType;
+use async_trait::async_trait;
+use bytes::Bytes;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use deepsize::DeepSizeOf;
+use lance_arrow::RecordBatchExt;
+use lance_core::utils::address::RowAddress;
+use lance_core::{Error, Result, ROW_ID};
+use lance_io::scheduler::FileScheduler;
+use lance_io::traits::Reader;
+use lance_linalg::distance::DistanceType;
+use roaring::RoaringBitmap;
+use serde_json::json;
+use snafu::location;
+use tracing::instrument;
+
+use super::{BlockLayout, CandidateList, DiskANNParams};
+use crate::metrics::{MetricsCollector, NoOpMetricsCollector};
+use crate::prefilter::PreFilter;
+use crate::vector::graph::OrderedNode;
+use crate::vector::ivf::storage::IvfModel;
+use crate::vector::pq::storage::ProductQuantizationStorage;
+use crate::vector::pq::ProductQuantizer;
+use crate::vector::quantizer::{QuantizationType, Quantizer};
+use crate::vector::storage::{DistCalculator, VectorStore};
+use crate::vector::v3::subindex::SubIndexType;
+use crate::vector::{Query, VectorIndex, VECTOR_RESULT_SCHEMA};
+use crate::{Index, IndexType};
+
+/// Number of node blocks read in each round of the beam search.
+const BEAM_WIDTH: usize = 4;
+
+lazy_static::lazy_static! {
+    /// Prefilters that select a smaller fraction of the rows are searched by
+    /// brute force over the PQ codes of the selected rows, instead of the graph.
+    pub static ref DISKANN_BRUTE_FORCE_SELECTIVITY: f64 = std::env::var("LANCE_DISKANN_BRUTE_FORCE_SELECTIVITY")
+        .map(|val| val.parse().unwrap()).unwrap_or(0.05);
+}
+
+/// DiskANN index.
+///
+/// The PQ codes and the row ids of the vectors are in memory, the full vectors
+/// and the neighbors of the nodes are read from the graph file while searching.
+#[derive(Clone)]
+pub struct DiskANNIndex {
+    params: DiskANNParams,
+    distance_type: DistanceType,
+
+    /// Entry points of the searches.
+    entries: Vec<u32>,
+
+    pq: ProductQuantizer,
+    /// PQ codes and row ids of the nodes.
+    storage: ProductQuantizationStorage,
+
+    graph: FileScheduler,
+    layout: BlockLayout,
+}
+
+impl DeepSizeOf for DiskANNIndex {
+    fn deep_size_of_children(&self, context: &mut deepsize::Context) -> usize {
+        self.entries.deep_size_of_children(context)
+            + self.pq.deep_size_of_children(context)
+            + self.storage.deep_size_of_children(context)
+    }
+}
+
+impl Debug for DiskANNIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "DiskANN(r={}, alpha={}, L={}, {}) -> PQ(m={}, nbits={})",
+            self.params.r,
+            self.params.alpha,
+            self.params.l,
+            self.distance_type,
+            self.pq.num_sub_vectors,
+            self.pq.num_bits,
+        )
+    }
+}
+
+impl DiskANNIndex {
+    /// Create a DiskANN index.
+    ///
+    /// - `entries`: the entry points of the searches.
+    /// - `storage`: the PQ codes and row ids, in the order of the nodes of the graph.
+    /// - `graph`: the graph file.
+    pub fn try_new(
+        params: DiskANNParams,
+        distance_type: DistanceType,
+        entries: Vec<u32>,
+        pq: ProductQuantizer,
+        storage: ProductQuantizationStorage,
+        graph: FileScheduler,
+    ) -> Result<Self> {
+        if entries.iter().any(|&e| e as usize >= storage.len()) {
+            return Err(Error::Index {
+                message: format!(
+                    "DiskANN: entry points {:?} out of range for {} nodes",
+                    entries,
+                    storage.len()
+                ),
+                location: location!(),
+            });
+        }
+        let layout = BlockLayout::new(pq.dimension, params.r);
+        Ok(Self {
+            params,
+            distance_type,
+            entries,
+            pq,
+            storage,
+            graph,
+            layout,
+        })
+    }
+
+    pub fn params(&self) -> &DiskANNParams {
+        &self.params
+    }
+
+    pub fn entries(&self) -> &[u32] {
+        &self.entries
+    }
+
+    pub fn pq(&self) -> &ProductQuantizer {
+        &self.pq
+    }
+
+    pub fn storage(&self) -> &ProductQuantizationStorage {
+        &self.storage
+    }
+
+    /// Search the graph for the nodes closest to `key`, which must be normalized
+    /// for the cosine distance.
+    ///
+    /// Returns the nodes expanded by the search with their exact distances to
+    /// `key`, sorted.  These are the candidate neighbors of a node inserted
+    /// with the vector `key`.
+    pub async fn search_nodes(
+        &self,
+        key: &Float32Array,
+        list_size: usize,
+    ) -> Result<Vec<OrderedNode>> {
+        if self.storage.len() == 0 {
+            return Ok(vec![]);
+        }
+        self.beam_search(
+            Arc::new(key.clone()),
+            key.values(),
+            list_size,
+            &NoOpMetricsCollector,
+        )
+        .await
+    }
+
+    /// Read the vectors and the neighbors of the nodes in `nodes`.
+    pub async fn read_nodes(&self, nodes: Range<u32>) -> Result<Vec<(Vec<f32>, Vec<u32>)>> {
+        if nodes.is_empty() {
+            return Ok(vec![]);
+        }
+        let range = self.layout.range(nodes.start).start..self.layout.range(nodes.end - 1).end;
+        let blocks = self.graph.submit_single(range, 0).await?;
+        Ok(blocks
+            .chunks_exact(self.layout.block_size())
+            .map(|block| self.layout.decode(block))
+            .collect())
+    }
+
+    /// Read the blocks of `nodes`, returned in the order of the node ids.
+    async fn read_blocks(&self, mut nodes: Vec<OrderedNode>) -> Result<Vec<(u32, Bytes)>> {
+        // The scheduler coalesces sorted requests
+        nodes.sort_unstable_by_key(|n| n.id);
+        let ranges = nodes.iter().map(|n| self.layout.range(n.id)).collect();
+        let blocks = self.graph.submit_request(ranges, 0).await?;
+        Ok(nodes.into_iter().map(|n| n.id).zip(blocks).collect())
+    }
+
+    /// Beam search over the graph.
+    ///
+    /// The candidates are ranked by their PQ distances.  Returns the expanded
+    /// nodes with their exact distances to `key`, sorted.
+    async fn beam_search(
+        &self,
+        query: ArrayRef,
+        key: &[f32],
+        list_size: usize,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<Vec<OrderedNode>> {
+        let dist_calc = self.storage.dist_calculator(query);
+        let dist_func = self.distance_type.func::<f32>();
+
+        let mut candidates = CandidateList::new(list_size);
+        let mut seen = HashSet::new();
+        for &entry in self.entries.iter() {
+            if seen.insert(entry) {
+                candidates.insert(OrderedNode::new(entry, dist_calc.distance(entry).into()));
+            }
+        }
+
+        let mut results = Vec::new();
+        loop {
+            let nodes = candidates.next_unexpanded(BEAM_WIDTH);
+            if nodes.is_empty() {
+                break;
+            }
+            metrics.record_parts_loaded(nodes.len());
+            let mut num_comparisons = 0;
+            for (id, block) in self.read_blocks(nodes).await? {
+                let (vector, neighbors) = self.layout.decode(&block);
+                results.push(OrderedNode::new(id, dist_func(key, &vector).into()));
+                for n in neighbors {
+                    if seen.insert(n) {
+                        candidates.insert(OrderedNode::new(n, dist_calc.distance(n).into()));
+                        num_comparisons += 1;
+                    }
+                }
+            }
+            metrics.record_comparisons(num_comparisons);
+        }
+        results.sort_unstable();
+        Ok(results)
+    }
+
+    /// Search the `selected` nodes without traversing the graph.
+    ///
+    /// The nodes are ranked by their PQ distances, and only the blocks of the
+    /// best `list_size` of them are read to compute their exact distances.
+    /// Returns these nodes with their exact distances to `key`, sorted.
+    async fn flat_search(
+        &self,
+        query: ArrayRef,
+        key: &[f32],
+        selected: impl Iterator<Item = u32>,
+        list_size: usize,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<Vec<OrderedNode>> {
+        let dist_calc = self.storage.dist_calculator(query);
+        let dist_func = self.distance_type.func::<f32>();
+
+        let mut candidates = selected
+            .map(|id| OrderedNode::new(id, dist_calc.distance(id).into()))
+            .collect::<Vec<_>>();
+        metrics.record_comparisons(candidates.len());
+        if candidates.len() > list_size {
+            candidates.select_nth_unstable(list_size);
+            candidates.truncate(list_size);
+        }
+        if candidates.is_empty() {
+            return Ok(vec![]);
+        }
+        metrics.record_parts_loaded(candidates.len());
+        let mut results = self
+            .read_blocks(candidates)
+            .await?
+            .into_iter()
+            .map(|(id, block)| {
+                let (vector, _) = self.layout.decode(&block);
+                OrderedNode::new(id, dist_func(key, &vector).into())
+            })
+            .collect::<Vec<_>>();
+        results.sort_unstable();
+        Ok(results)
+    }
+
+    /// The first `k` of the sorted `(distance, row id)` pairs that are within
+    /// the distance bounds of the query.
+    fn search_result(
+        candidates: impl Iterator<Item = (f32, u64)>,
+        query: &Query,
+        k: usize,
+    ) -> Result<RecordBatch> {
+        let mut distances = Vec::with_capacity(k);
+        let mut ids = Vec::with_capacity(k);
+        for (dist, row_id) in candidates {
+            // Deleted rows stay in the graph to keep it connected
+            if row_id == RowAddress::TOMBSTONE_ROW {
+                continue;
+            }
+            if query.lower_bound.is_some_and(|lb| dist < lb) {
+                continue;
+            }
+            if query.upper_bound.is_some_and(|ub| dist >= ub) {
+                break;
+            }
+            distances.push(dist);
+            ids.push(row_id);
+            if ids.len() >= k {
+                break;
+            }
+        }
+        Ok(RecordBatch::try_new(
+            VECTOR_RESULT_SCHEMA.clone(),
+            vec![
+                Arc::new(Float32Array::from(distances)),
+                Arc::new(UInt64Array::from(ids)),
+            ],
+        )?)
+    }
+}
+
+#[async_trait]
+impl Index for DiskANNIndex {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_index(self: Arc<Self>) -> Arc<dyn Index> {
+        self
+    }
+
+    fn as_vector_index(self: Arc<Self>) -> Result<Arc<dyn VectorIndex>> {
+        Ok(self)
+    }
+
+    fn statistics(&self) -> Result<serde_json::Value> {
+        Ok(json!({
+            "index_type": "DISKANN",
+            "metric_type": self.distance_type.to_string(),
+            "num_nodes": self.storage.len(),
+            "r": self.params.r,
+            "alpha": self.params.alpha,
+            "L": self.params.l,
+            "num_sub_vectors": self.pq.num_sub_vectors,
+            "nbits": self.pq.num_bits,
+            "dimension": self.pq.dimension,
+        }))
+    }
+
+    async fn prewarm(&self) -> Result<()> {
+        // The PQ codes are loaded with the index, and the graph is read on demand
+        Ok(())
+    }
+
+    fn index_type(&self) -> IndexType {
+        IndexType::DiskAnn
+    }
+
+    async fn calculate_included_frags(&self) -> Result<RoaringBitmap> {
+        let mut frag_ids = self
+            .storage
+            .row_ids()
+            .filter(|&&row_id| row_id != RowAddress::TOMBSTONE_ROW)
+            .map(|&row_id| RowAddress::from(row_id).fragment_id())
+            .collect::<Vec<_>>();
+        frag_ids.sort();
+        frag_ids.dedup();
+        Ok(RoaringBitmap::from_sorted_iter(frag_ids).unwrap())
+    }
+}
+
+#[async_trait]
+impl VectorIndex for DiskANNIndex {
+    #[instrument(level = "debug", skip_all, name = "DiskANNIndex::search")]
+    async fn search(
+        &self,
+        query: &Query,
+        pre_filter: Arc<dyn PreFilter>,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<RecordBatch> {
+        pre_filter.wait_for_ready().await?;
+
+        let num_nodes = self.storage.len();
+        let k = query.k * query.refine_factor.unwrap_or(1) as usize;
+        if num_nodes == 0 || k == 0 {
+            return Ok(RecordBatch::new_empty(VECTOR_RESULT_SCHEMA.clone()));
+        }
+        let key = cast(query.key.as_ref(), &DataType::Float32)?;
+        let key = key.as_primitive::<Float32Type>().values();
+
+        let target = query.ef.unwrap_or(self.params.l).max(k).min(num_nodes);
+        if !pre_filter.is_empty() {
+            let selected = pre_filter.filter_row_ids(Box::new(self.storage.row_ids()));
+            // Finding enough selected nodes in the graph would take many
+            // reads, it's cheaper to rank the few selected nodes directly
+            if selected.len() <= target
+                || (selected.len() as f64) < *DISKANN_BRUTE_FORCE_SELECTIVITY * num_nodes as f64
+            {
+                let nodes = self
+                    .flat_search(
+                        query.key.clone(),
+                        key,
+                        selected.into_iter().map(|idx| idx as u32),
+                        target,
+                        metrics,
+                    )
+                    .await?;
+                return Self::search_result(
+                    nodes.iter().map(|n| (n.dist.0, self.storage.row_id(n.id))),
+                    query,
+                    k,
+                );
+            }
+        }
+
+        // Widen the search until as many of the visited nodes pass the filter
+        // as the list size of an unfiltered search, to keep the same recall
+        let mut list_size = target;
+        loop {
+            let nodes = self
+                .beam_search(query.key.clone(), key, list_size, metrics)
+                .await?;
+            let row_ids = nodes
+                .iter()
+                .map(|n| self.storage.row_id(n.id))
+                .collect::<Vec<_>>();
+            let selected: Vec<usize> = if pre_filter.is_empty() {
+                (0..row_ids.len()).collect()
+            } else {
+                pre_filter
+                    .filter_row_ids(Box::new(row_ids.iter()))
+                    .into_iter()
+                    .map(|idx| idx as usize)
+                    .collect()
+            };
+
+            if selected.len() >= target || pre_filter.is_empty() || list_size >= num_nodes {
+                return Self::search_result(
+                    selected
+                        .into_iter()
+                        .map(|idx| (nodes[idx].dist.0, row_ids[idx])),
+                    query,
+                    k,
+                );
+            }
+            list_size = (list_size * 2).min(num_nodes);
+        }
+    }
+
+    fn find_partitions(&self, _: &Query) -> Result<UInt32Array> {
+        Ok(UInt32Array::from(vec![0]))
+    }
+
+    fn total_partitions(&self) -> usize {
+        1
+    }
+
+    async fn search_in_partition(
+        &self,
+        _: usize,
+        query: &Query,
+        pre_filter: Arc<dyn PreFilter>,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<RecordBatch> {
+        self.search(query, pre_filter, metrics).await
+    }
+
+    fn is_loadable(&self) -> bool {
+        false
+    }
+
+    fn use_residual(&self) -> bool {
+        false
+    }
+
+    async fn load(&self, _: Arc<dyn Reader>, _: usize, _: usize) -> Result<Box<dyn VectorIndex>> {
+        Err(Error::NotSupported {
+            source: "DiskANN index is not loadable as a sub-index".into(),
+            location: location!(),
+        })
+    }
+
+    async fn to_batch_stream(&self, with_vector: bool) -> Result<SendableRecordBatchStream> {
+        let batch = self.storage.batch().clone();
+        let batch = if with_vector {
+            batch
+        } else {
+            batch.project(&[batch.schema().index_of(ROW_ID)?])?
+        };
+        let stream = RecordBatchStreamAdapter::new(
+            batch.schema(),
+            futures::stream::once(futures::future::ready(Ok(batch))),
+        );
+        Ok(Box::pin(stream))
+    }
+
+    fn num_rows(&self) -> u64 {
+        self.storage.len() as u64
+    }
+
+    fn row_ids(&self) -> Box<dyn Iterator<Item = &'_ u64> + '_> {
+        Box::new(self.storage.row_ids())
+    }
+
+    /// Remap the row ids of the nodes.
+    ///
+    /// The nodes of the deleted rows are kept in the graph, with a tombstone
+    /// row id, so the graph file can be reused.
+    async fn remap(&mut self, mapping: &HashMap<u64, Option<u64>>) -> Result<()> {
+        let row_ids = UInt64Array::from_iter_values(self.storage.row_ids().map(|row_id| {
+            match mapping.get(row_id) {
+                Some(Some(new_row_id)) => *new_row_id,
+                Some(None) => RowAddress::TOMBSTONE_ROW,
+                None => *row_id,
+            }
+        }));
+        let batch = self
+            .storage
+            .batch()
+            .replace_column_by_name(ROW_ID, Arc::new(row_ids))?;
+        self.storage = ProductQuantizationStorage::new(
+            self.pq.codebook.clone(),
+            batch,
+            self.pq.num_bits,
+            self.pq.num_sub_vectors,
+            self.pq.dimension,
+            self.distance_type,
+            true,
+        )?;
+        Ok(())
+    }
+
+    fn metric_type(&self) -> DistanceType {
+        self.distance_type
+    }
+
+    fn ivf_model(&self) -> &IvfModel {
+        unimplemented!("only for IVF")
+    }
+
+    fn quantizer(&self) -> Quantizer {
+        Quantizer::Product(self.pq.clone())
+    }
+
+    fn sub_index_type(&self) -> (SubIndexType, QuantizationType) {
+        unimplemented!("only for IVF")
+    }
+}
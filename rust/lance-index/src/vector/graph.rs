@@ -329,6 +329,93 @@ pub fn beam_search(
     results.into_sorted_vec()
 }
 
+/// Predicate-aware beam search over a graph (ACORN-1)
+///
+/// Unlike [`beam_search`], only the nodes selected by the bitset are visited.
+/// The neighbors of a node are expanded through its filtered-out neighbors to the
+/// two-hop neighborhood, so the search can still traverse the graph when the
+/// filter is very selective. The expanded neighbors are truncated to the number of
+/// the neighbors of the node.
+///
+/// Returns a descending sorted list of the nearest `k` selected nodes.
+///
+/// WARNING: Internal API,  API stability is not guaranteed
+pub fn filtered_beam_search(
+    graph: &dyn Graph,
+    ep: &OrderedNode,
+    k: usize,
+    dist_calc: &impl DistCalculator,
+    bitset: &Visited,
+    prefetch_distance: Option<usize>,
+    visited: &mut Visited,
+) -> Vec<OrderedNode> {
+    let mut candidates = BinaryHeap::with_capacity(k);
+    visited.insert(ep.id);
+    candidates.push(Reverse(ep.clone()));
+
+    let mut results = BinaryHeap::with_capacity(k);
+    if bitset.contains(ep.id) {
+        results.push(ep.clone());
+    }
+
+    while let Some(Reverse(current)) = candidates.pop() {
+        let furthest = results
+            .peek()
+            .map(|node: &OrderedNode| node.dist)
+            .unwrap_or(OrderedFloat(f32::INFINITY));
+        if current.dist > furthest && results.len() == k {
+            break;
+        }
+
+        let neighbors = graph.neighbors(current.id);
+        let max_expansion = neighbors.len();
+        let mut expanded = Vec::with_capacity(max_expansion);
+        let expand = |node: u32, expanded: &mut Vec<u32>| {
+            if bitset.contains(node) && !visited.contains(node) && !expanded.contains(&node) {
+                expanded.push(node);
+            }
+        };
+        for &neighbor in neighbors.iter() {
+            if expanded.len() >= max_expansion {
+                break;
+            }
+            if bitset.contains(neighbor) {
+                expand(neighbor, &mut expanded);
+            } else {
+                // the filtered-out neighbor is only a bridge to its own neighbors
+                for &two_hop in graph.neighbors(neighbor).iter() {
+                    if expanded.len() >= max_expansion {
+                        break;
+                    }
+                    expand(two_hop, &mut expanded);
+                }
+            }
+        }
+
+        let process_neighbor = |neighbor: u32| {
+            visited.insert(neighbor);
+            let dist = dist_calc.distance(neighbor).into();
+            if dist <= furthest || results.len() < k {
+                if results.len() < k {
+                    results.push((dist, neighbor).into());
+                } else if dist < results.peek().unwrap().dist {
+                    results.pop();
+                    results.push((dist, neighbor).into());
+                }
+                candidates.push(Reverse((dist, neighbor).into()));
+            }
+        };
+        process_neighbors_with_look_ahead(
+            &expanded,
+            process_neighbor,
+            prefetch_distance,
+            dist_calc,
+        );
+    }
+
+    results.into_sorted_vec()
+}
+
 /// Greedy search over a graph
 ///
 /// This searches for only one result, only used for finding the entry point
@@ -34,7 +34,7 @@ use crate::metrics::MetricsCollector;
 use crate::prefilter::PreFilter;
 use crate::vector::flat::storage::FlatFloatStorage;
 use crate::vector::graph::builder::GraphBuilderNode;
-use crate::vector::graph::{greedy_search, Visited};
+use crate::vector::graph::{filtered_beam_search, greedy_search, Visited};
 use crate::vector::graph::{
     Graph, OrderedFloat, OrderedNode, VisitedGenerator, DISTS_FIELD, NEIGHBORS_COL, NEIGHBORS_FIELD,
 };
@@ -44,6 +44,42 @@ use crate::vector::{Query, DIST_COL, VECTOR_RESULT_SCHEMA};
 
 pub const HNSW_METADATA_KEY: &str = "lance:hnsw";
 
+lazy_static::lazy_static! {
+    /// Prefilters that select a smaller fraction of the rows are searched by brute force.
+    pub static ref HNSW_BRUTE_FORCE_SELECTIVITY: f64 = std::env::var("LANCE_HNSW_BRUTE_FORCE_SELECTIVITY")
+        .map(|val| val.parse().unwrap()).unwrap_or(0.02);
+
+    /// Prefilters that select a smaller fraction of the rows are searched by
+    /// the filtered graph search.
+    pub static ref HNSW_FILTERED_SEARCH_SELECTIVITY: f64 = std::env::var("LANCE_HNSW_FILTERED_SEARCH_SELECTIVITY")
+        .map(|val| val.parse().unwrap()).unwrap_or(0.5);
+}
+
+/// How the HNSW graph is searched with a prefilter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilteredSearchStrategy {
+    /// Traverse all the nodes of the graph, and only return the selected ones.
+    Graph,
+    /// Traverse only the selected nodes, expanding through the filtered-out nodes
+    /// to the two-hop neighborhood, see [`filtered_beam_search`].
+    FilteredGraph,
+    /// Compute the distances of all the selected nodes.
+    BruteForce,
+}
+
+impl FilteredSearchStrategy {
+    /// Choose the strategy by the fraction of the rows selected by the prefilter.
+    pub fn choose(selectivity: f64) -> Self {
+        if selectivity < *HNSW_BRUTE_FORCE_SELECTIVITY {
+            Self::BruteForce
+        } else if selectivity < *HNSW_FILTERED_SEARCH_SELECTIVITY {
+            Self::FilteredGraph
+        } else {
+            Self::Graph
+        }
+    }
+}
+
 /// Parameters of building HNSW index
 #[derive(Debug, Clone, Serialize, Deserialize, DeepSizeOf)]
 pub struct HnswBuildParams {
@@ -170,6 +206,31 @@ impl HNSW {
         visited_generator: &mut VisitedGenerator,
         storage: &impl VectorStore,
         prefetch_distance: Option<usize>,
+    ) -> Result<Vec<OrderedNode>> {
+        self.search_graph(
+            query,
+            k,
+            ef,
+            bitset,
+            false,
+            visited_generator,
+            storage,
+            prefetch_distance,
+        )
+    }
+
+    // search the graph, only the nodes selected by the bitset are traversed if `filtered`
+    #[allow(clippy::too_many_arguments)]
+    fn search_graph(
+        &self,
+        query: ArrayRef,
+        k: usize,
+        ef: usize,
+        bitset: Option<Visited>,
+        filtered: bool,
+        visited_generator: &mut VisitedGenerator,
+        storage: &impl VectorStore,
+        prefetch_distance: Option<usize>,
     ) -> Result<Vec<OrderedNode>> {
         let dist_calc = storage.dist_calculator(query);
         let mut ep = OrderedNode::new(0, dist_calc.distance(0).into());
@@ -186,18 +247,27 @@ impl HNSW {
 
         let bottom_level = HnswBottomView::new(nodes);
         let mut visited = visited_generator.generate(storage.len());
-        Ok(beam_search(
-            &bottom_level,
-            &ep,
-            ef,
-            &dist_calc,
-            bitset.as_ref(),
-            prefetch_distance,
-            &mut visited,
-        )
-        .into_iter()
-        .take(k)
-        .collect())
+        let results = match (&bitset, filtered) {
+            (Some(bitset), true) => filtered_beam_search(
+                &bottom_level,
+                &ep,
+                ef,
+                &dist_calc,
+                bitset,
+                prefetch_distance,
+                &mut visited,
+            ),
+            _ => beam_search(
+                &bottom_level,
+                &ep,
+                ef,
+                &dist_calc,
+                bitset.as_ref(),
+                prefetch_distance,
+                &mut visited,
+            ),
+        };
+        Ok(results.into_iter().take(k).collect())
     }
 
     #[instrument(level = "debug", skip(self, query, bitset, storage))]
@@ -208,17 +278,44 @@ impl HNSW {
         ef: usize,
         bitset: Option<Visited>,
         storage: &impl VectorStore,
+    ) -> Result<Vec<OrderedNode>> {
+        self.search_with_strategy(query, k, ef, bitset, false, storage)
+    }
+
+    /// Search the graph and only traverse the nodes selected by the bitset,
+    /// see [`filtered_beam_search`].
+    #[instrument(level = "debug", skip(self, query, bitset, storage))]
+    pub fn search_filtered(
+        &self,
+        query: ArrayRef,
+        k: usize,
+        ef: usize,
+        bitset: Visited,
+        storage: &impl VectorStore,
+    ) -> Result<Vec<OrderedNode>> {
+        self.search_with_strategy(query, k, ef, Some(bitset), true, storage)
+    }
+
+    fn search_with_strategy(
+        &self,
+        query: ArrayRef,
+        k: usize,
+        ef: usize,
+        bitset: Option<Visited>,
+        filtered: bool,
+        storage: &impl VectorStore,
     ) -> Result<Vec<OrderedNode>> {
         let mut visited_generator = self
             .inner
             .visited_generator_queue
             .pop()
             .unwrap_or_else(|| VisitedGenerator::new(storage.len()));
-        let result = self.search_inner(
+        let result = self.search_graph(
             query,
             k,
             ef,
             bitset,
+            filtered,
             &mut visited_generator,
             storage,
             Some(2),
@@ -683,27 +780,34 @@ impl IvfSubIndex for HNSW {
             .visited_generator_queue
             .pop()
             .unwrap_or_else(|| VisitedGenerator::new(storage.len()));
-        let prefilter_bitset = if prefilter.is_empty() {
-            None
+        let (prefilter_bitset, strategy) = if prefilter.is_empty() {
+            (None, FilteredSearchStrategy::Graph)
         } else {
             let indices = prefilter.filter_row_ids(Box::new(storage.row_ids()));
             let mut bitset = prefilter_generator.generate(storage.len());
             for indices in indices {
                 bitset.insert(indices as u32);
             }
-            Some(bitset)
+            // it's cheaper to compute the distances of the few selected nodes directly
+            let num_selected = bitset.count_ones();
+            let strategy = if num_selected <= params.ef {
+                FilteredSearchStrategy::BruteForce
+            } else {
+                FilteredSearchStrategy::choose(num_selected as f64 / storage.len() as f64)
+            };
+            (Some(bitset), strategy)
         };
 
-        let remained = prefilter_bitset
-            .as_ref()
-            .map(|b| b.count_ones())
-            .unwrap_or(storage.len());
-        let results = if remained < self.len() * 10 / 100 {
-            let prefilter_bitset =
-                prefilter_bitset.expect("the prefilter bitset must be set for flat search");
-            self.flat_search(storage, query, k, prefilter_bitset)
-        } else {
-            self.search_basic(query, k, params.ef, prefilter_bitset, storage)?
+        let results = match (prefilter_bitset, strategy) {
+            (Some(prefilter_bitset), FilteredSearchStrategy::BruteForce) => {
+                self.flat_search(storage, query, k, prefilter_bitset)
+            }
+            (Some(prefilter_bitset), FilteredSearchStrategy::FilteredGraph) => {
+                self.search_filtered(query, k, params.ef, prefilter_bitset, storage)?
+            }
+            (prefilter_bitset, _) => {
+                self.search_basic(query, k, params.ef, prefilter_bitset, storage)?
+            }
         };
         // if the queue is full, we just don't push it back, so ignore the error here
         let _ = self.inner.visited_generator_queue.push(prefilter_generator);
@@ -887,4 +991,79 @@ mod tests {
             .unwrap();
         assert_eq!(builder_results, loaded_results);
     }
+
+    #[test]
+    fn test_choose_filtered_search_strategy() {
+        use super::FilteredSearchStrategy;
+
+        assert_eq!(
+            FilteredSearchStrategy::choose(0.01),
+            FilteredSearchStrategy::BruteForce
+        );
+        assert_eq!(
+            FilteredSearchStrategy::choose(0.1),
+            FilteredSearchStrategy::FilteredGraph
+        );
+        assert_eq!(
+            FilteredSearchStrategy::choose(0.9),
+            FilteredSearchStrategy::Graph
+        );
+    }
+
+    #[test]
+    fn test_filtered_search() {
+        use std::collections::HashSet;
+
+        use itertools::Itertools;
+
+        use crate::vector::graph::VisitedGenerator;
+        use crate::vector::storage::{DistCalculator, VectorStore};
+
+        const DIM: usize = 16;
+        const TOTAL: usize = 4096;
+        let data = generate_random_array(TOTAL * DIM);
+        let fsl = FixedSizeListArray::try_new_from_values(data, DIM as i32).unwrap();
+        let store = FlatFloatStorage::new(fsl.clone(), DistanceType::L2);
+        let hnsw = HNSW::index_vectors(
+            &store,
+            HnswBuildParams::default()
+                .num_edges(16)
+                .ef_construction(100),
+        )
+        .unwrap();
+
+        // a selective filter, which selects 2.5% of the nodes
+        let selected = (0..TOTAL as u32).filter(|id| id % 40 == 0).collect_vec();
+        let k = 10;
+        let ef = 50;
+        let mut recall = 0.0;
+        let num_queries = 20;
+        for i in 0..num_queries {
+            let query = fsl.value(i * 7 + 1);
+            let dist_calc = store.dist_calculator(query.clone());
+            let expected = selected
+                .iter()
+                .map(|&id| (dist_calc.distance(id), id))
+                .sorted_by(|a, b| a.0.total_cmp(&b.0))
+                .take(k)
+                .map(|(_, id)| id)
+                .collect::<HashSet<_>>();
+
+            let mut generator = VisitedGenerator::new(TOTAL);
+            let mut bitset = generator.generate(TOTAL);
+            for &id in &selected {
+                bitset.insert(id);
+            }
+            let results = hnsw.search_filtered(query, k, ef, bitset, &store).unwrap();
+            assert_eq!(results.len(), k);
+            assert!(results.iter().all(|node| node.id % 40 == 0));
+            let found = results
+                .iter()
+                .filter(|node| expected.contains(&node.id))
+                .count();
+            recall += found as f32 / k as f32;
+        }
+        let recall = recall / num_queries as f32;
+        assert!(recall >= 0.9, "recall: {}", recall);
+    }
 }
@@ -19,6 +19,7 @@ use tracing::instrument;
 use crate::vector::ivf::transform::PartitionTransformer;
 use crate::vector::{pq::ProductQuantizer, transform::Transformer};
 
+use super::bq::{transform::RQTransformer, RabitQuantizer};
 use super::flat::transform::FlatTransformer;
 use super::pq::transform::PQTransformer;
 use super::quantizer::Quantization;
@@ -27,7 +28,7 @@ use super::sq::transform::SQTransformer;
 use super::sq::ScalarQuantizer;
 use super::transform::KeepFiniteVectors;
 use super::{quantizer::Quantizer, residual::compute_residual};
-use super::{PART_ID_COLUMN, PQ_CODE_COLUMN, SQ_CODE_COLUMN};
+use super::{PART_ID_COLUMN, PQ_CODE_COLUMN, RQ_CODE_COLUMN, SQ_CODE_COLUMN};
 
 pub mod builder;
 pub mod shuffler;
@@ -79,6 +80,13 @@ pub fn new_ivf_transformer_with_quantizer(
             sq,
             range,
         )),
+        Quantizer::Rabit(rq) => Ok(IvfTransformer::with_rq(
+            centroids,
+            metric_type,
+            vector_column,
+            rq,
+            range,
+        )),
     }
 }
 
@@ -244,6 +252,56 @@ impl IvfTransformer {
         Self::new(centroids, distance_type, transforms)
     }
 
+    fn with_rq(
+        centroids: FixedSizeListArray,
+        metric_type: MetricType,
+        vector_column: &str,
+        rq: RabitQuantizer,
+        range: Option<Range<u32>>,
+    ) -> Self {
+        let mut transforms: Vec<Arc<dyn Transformer>> =
+            vec![Arc::new(super::transform::Flatten::new(vector_column))];
+
+        let distance_type = if metric_type == MetricType::Cosine {
+            transforms.push(Arc::new(super::transform::NormalizeTransformer::new(
+                vector_column,
+            )));
+            MetricType::L2
+        } else {
+            metric_type
+        };
+        transforms.push(Arc::new(KeepFiniteVectors::new(vector_column)));
+
+        let partition_transformer = Arc::new(PartitionTransformer::new(
+            centroids.clone(),
+            distance_type,
+            vector_column,
+        ));
+        transforms.push(partition_transformer);
+
+        if let Some(range) = range {
+            transforms.push(Arc::new(transform::PartitionFilter::new(
+                PART_ID_COLUMN,
+                range,
+            )));
+        }
+
+        if RabitQuantizer::use_residual(distance_type) {
+            transforms.push(Arc::new(ResidualTransform::new(
+                centroids.clone(),
+                PART_ID_COLUMN,
+                vector_column,
+            )));
+        }
+        transforms.push(Arc::new(RQTransformer::new(
+            rq,
+            vector_column.to_owned(),
+            RQ_CODE_COLUMN.to_owned(),
+        )));
+
+        Self::new(centroids, distance_type, transforms)
+    }
+
     #[inline]
     pub fn compute_residual(&self, data: &FixedSizeListArray) -> Result<FixedSizeListArray> {
         compute_residual(&self.centroids, data, Some(self.distance_type), None)
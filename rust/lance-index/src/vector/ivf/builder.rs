@@ -51,6 +51,13 @@ pub struct IvfBuildParams {
 
     /// Storage options used to load precomputed partitions.
     pub storage_options: Option<HashMap<String, String>>,
+
+    /// Pool the vectors of each multivector into `1 / token_pool_factor` of the
+    /// original number before indexing them.
+    ///
+    /// Similar vectors are clustered and replaced by their mean, which shrinks the
+    /// index of a multivector column. It's ignored for single vector columns.
+    pub token_pool_factor: Option<usize>,
 }
 
 impl Default for IvfBuildParams {
@@ -66,6 +73,7 @@ impl Default for IvfBuildParams {
             shuffle_partition_batches: 1024 * 10,
             shuffle_partition_concurrency: 2,
             storage_options: None,
+            token_pool_factor: None,
         }
     }
 }
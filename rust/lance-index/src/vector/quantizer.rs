@@ -19,6 +19,7 @@ use snafu::location;
 
 use crate::{IndexMetadata, INDEX_METADATA_SCHEMA_KEY};
 
+use super::bq::RabitQuantizer;
 use super::flat::index::{FlatBinQuantizer, FlatQuantizer};
 use super::pq::ProductQuantizer;
 use super::{ivf::storage::IvfModel, sq::ScalarQuantizer, storage::VectorStore};
@@ -58,6 +59,7 @@ pub enum QuantizationType {
     Flat,
     Product,
     Scalar,
+    Rabit,
 }
 
 impl FromStr for QuantizationType {
@@ -68,6 +70,7 @@ impl FromStr for QuantizationType {
             "FLAT" => Ok(Self::Flat),
             "PQ" => Ok(Self::Product),
             "SQ" => Ok(Self::Scalar),
+            "RQ" => Ok(Self::Rabit),
             _ => Err(Error::Index {
                 message: format!("Unknown quantization type: {}", s),
                 location: location!(),
@@ -82,6 +85,7 @@ impl std::fmt::Display for QuantizationType {
             Self::Flat => write!(f, "FLAT"),
             Self::Product => write!(f, "PQ"),
             Self::Scalar => write!(f, "SQ"),
+            Self::Rabit => write!(f, "RQ"),
         }
     }
 }
@@ -110,6 +114,7 @@ pub enum Quantizer {
     FlatBin(FlatBinQuantizer),
     Product(ProductQuantizer),
     Scalar(ScalarQuantizer),
+    Rabit(RabitQuantizer),
 }
 
 impl Quantizer {
@@ -119,6 +124,7 @@ impl Quantizer {
             Self::FlatBin(fq) => fq.code_dim(),
             Self::Product(pq) => pq.code_dim(),
             Self::Scalar(sq) => sq.code_dim(),
+            Self::Rabit(rq) => rq.code_dim(),
         }
     }
 
@@ -128,6 +134,7 @@ impl Quantizer {
             Self::FlatBin(fq) => fq.column(),
             Self::Product(pq) => pq.column(),
             Self::Scalar(sq) => sq.column(),
+            Self::Rabit(rq) => rq.column(),
         }
     }
 
@@ -137,6 +144,7 @@ impl Quantizer {
             Self::FlatBin(_) => FlatBinQuantizer::metadata_key(),
             Self::Product(_) => ProductQuantizer::metadata_key(),
             Self::Scalar(_) => ScalarQuantizer::metadata_key(),
+            Self::Rabit(_) => RabitQuantizer::metadata_key(),
         }
     }
 
@@ -146,6 +154,7 @@ impl Quantizer {
             Self::FlatBin(_) => QuantizationType::Flat,
             Self::Product(_) => QuantizationType::Product,
             Self::Scalar(_) => QuantizationType::Scalar,
+            Self::Rabit(_) => QuantizationType::Rabit,
         }
     }
 
@@ -155,6 +164,7 @@ impl Quantizer {
             Self::FlatBin(fq) => fq.metadata(args),
             Self::Product(pq) => pq.metadata(args),
             Self::Scalar(sq) => sq.metadata(args),
+            Self::Rabit(rq) => rq.metadata(args),
         }
     }
 }
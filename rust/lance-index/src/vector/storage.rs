@@ -299,6 +299,10 @@ impl IvfQuantizationStorage {
         self.ivf.num_partitions()
     }
 
+    pub fn ivf_model(&self) -> &IvfModel {
+        &self.ivf
+    }
+
     pub async fn load_partition<Q: Quantization>(&self, part_id: usize) -> Result<Q::Storage> {
         let range = self.ivf.row_range(part_id);
         let batch = if range.is_empty() {
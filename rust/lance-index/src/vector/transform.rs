@@ -7,16 +7,18 @@
 use std::fmt::Debug;
 use std::sync::Arc;
 
+use arrow::buffer::OffsetBuffer;
 use arrow::datatypes::UInt64Type;
 use arrow_array::types::{Float16Type, Float32Type, Float64Type};
-use arrow_array::UInt64Array;
 use arrow_array::{cast::AsArray, Array, ArrowPrimitiveType, RecordBatch, UInt32Array};
+use arrow_array::{FixedSizeListArray, ListArray, UInt64Array};
 use arrow_schema::{DataType, Field, Schema};
 use lance_arrow::RecordBatchExt;
 use num_traits::Float;
 use snafu::location;
 
 use lance_core::{Error, Result, ROW_ID, ROW_ID_FIELD};
+use lance_linalg::distance::l2::l2;
 use lance_linalg::kernels::normalize_fsl;
 use tracing::instrument;
 
@@ -236,6 +238,146 @@ impl Transformer for Flatten {
     }
 }
 
+/// The number of k-means iterations to pool the vectors of a multivector.
+const POOL_ITERATIONS: usize = 4;
+
+/// Pool the vectors of each multivector.
+///
+/// The vectors of a multivector are clustered into `ceil(len / pool_factor)` clusters,
+/// and each cluster is replaced by the mean of its vectors.
+#[derive(Debug)]
+pub struct PoolMultivectors {
+    column: String,
+    pool_factor: usize,
+}
+
+impl PoolMultivectors {
+    pub fn new(column: &str, pool_factor: usize) -> Self {
+        Self {
+            column: column.to_owned(),
+            pool_factor,
+        }
+    }
+}
+
+impl Transformer for PoolMultivectors {
+    #[instrument(name = "PoolMultivectors::transform", level = "debug", skip_all)]
+    fn transform(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let arr = batch.column_by_name(&self.column).ok_or(Error::Index {
+            message: format!(
+                "PoolMultivectors: column {} not found in RecordBatch",
+                self.column
+            ),
+            location: location!(),
+        })?;
+        let DataType::List(vector_field) = arr.data_type() else {
+            // single vectors are not pooled
+            return Ok(batch.clone());
+        };
+        if self.pool_factor <= 1 {
+            return Ok(batch.clone());
+        }
+        let multivectors = arr.as_list::<i32>();
+        let vectors = multivectors.values().as_fixed_size_list();
+        let DataType::FixedSizeList(item_field, dim) = vectors.data_type() else {
+            unreachable!()
+        };
+        if !item_field.data_type().is_floating() {
+            return Err(Error::Index {
+                message: format!(
+                    "PoolMultivectors: can't pool vectors of type {}",
+                    item_field.data_type()
+                ),
+                location: location!(),
+            });
+        }
+
+        let dim = *dim as usize;
+        let values = arrow::compute::cast(vectors.values(), &DataType::Float32)?;
+        let values = values.as_primitive::<Float32Type>().values();
+        let mut pooled = Vec::with_capacity(values.len() / self.pool_factor + dim);
+        let mut offsets = Vec::with_capacity(multivectors.len() + 1);
+        offsets.push(0);
+        for (i, window) in multivectors.value_offsets().windows(2).enumerate() {
+            let start = window[0] as usize;
+            let end = window[1] as usize;
+            if multivectors.is_valid(i) && end > start {
+                let num_clusters = (end - start).div_ceil(self.pool_factor);
+                pool_vectors(
+                    &values[start * dim..end * dim],
+                    dim,
+                    num_clusters,
+                    &mut pooled,
+                );
+            }
+            offsets.push((pooled.len() / dim) as i32);
+        }
+
+        let pooled = arrow::compute::cast(
+            &arrow_array::Float32Array::from(pooled),
+            item_field.data_type(),
+        )?;
+        let vectors = FixedSizeListArray::try_new(item_field.clone(), dim as i32, pooled, None)?;
+        let multivectors = ListArray::try_new(
+            vector_field.clone(),
+            OffsetBuffer::new(offsets.into()),
+            Arc::new(vectors),
+            multivectors.nulls().cloned(),
+        )?;
+        Ok(batch.replace_column_by_name(&self.column, Arc::new(multivectors))?)
+    }
+}
+
+// cluster the vectors with k-means, starting from evenly spaced vectors,
+// and append the centroids of the non-empty clusters to `pooled`
+fn pool_vectors(vectors: &[f32], dim: usize, num_clusters: usize, pooled: &mut Vec<f32>) {
+    let num_vectors = vectors.len() / dim;
+    if num_vectors <= num_clusters {
+        pooled.extend_from_slice(vectors);
+        return;
+    }
+
+    let mut centroids = (0..num_clusters)
+        .flat_map(|i| {
+            let start = i * num_vectors / num_clusters * dim;
+            vectors[start..start + dim].iter().copied()
+        })
+        .collect::<Vec<_>>();
+    let mut counts = vec![0_usize; num_clusters];
+    for _ in 0..POOL_ITERATIONS {
+        let mut sums = vec![0.0_f32; num_clusters * dim];
+        counts.fill(0);
+        for vector in vectors.chunks_exact(dim) {
+            let (cluster, _) = centroids
+                .chunks_exact(dim)
+                .map(|centroid| l2(vector, centroid))
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap();
+            counts[cluster] += 1;
+            sums[cluster * dim..(cluster + 1) * dim]
+                .iter_mut()
+                .zip(vector)
+                .for_each(|(sum, v)| *sum += v);
+        }
+        for (cluster, &count) in counts.iter().enumerate() {
+            if count > 0 {
+                let range = cluster * dim..(cluster + 1) * dim;
+                centroids[range.clone()]
+                    .iter_mut()
+                    .zip(&sums[range])
+                    .for_each(|(c, sum)| *c = sum / count as f32);
+            }
+        }
+    }
+
+    for (centroid, &count) in centroids.chunks_exact(dim).zip(&counts) {
+        if count > 0 {
+            pooled.extend_from_slice(centroid);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -355,4 +497,52 @@ mod tests {
         let dup_drop_result = transformer.transform(&output);
         assert!(dup_drop_result.is_ok());
     }
+
+    #[test]
+    fn test_pool_multivectors() {
+        let values = Float32Array::from(vec![
+            1.0, 0.0, 0.0, 1.0, 1.0, 0.2, 0.2, 1.0, // the first multivector
+            3.0, 3.0, // the third multivector
+        ]);
+        let vectors = FixedSizeListArray::try_new_from_values(values, 2).unwrap();
+        let vector_field = Arc::new(Field::new("item", vectors.data_type().clone(), true));
+        let multivectors = ListArray::new(
+            vector_field.clone(),
+            OffsetBuffer::from_lengths([4, 0, 1]),
+            Arc::new(vectors),
+            Some(vec![true, false, true].into()),
+        );
+        let schema = Schema::new(vec![Field::new("v", DataType::List(vector_field), true)]);
+        let batch = RecordBatch::try_new(schema.into(), vec![Arc::new(multivectors)]).unwrap();
+
+        let output = PoolMultivectors::new("v", 2).transform(&batch).unwrap();
+        assert_eq!(output.schema(), batch.schema());
+        let pooled = output["v"].as_list::<i32>();
+        assert_eq!(pooled.value_offsets(), &[0, 2, 2, 3]);
+        assert!(pooled.is_null(1));
+        let first = pooled.value(0);
+        let first = first
+            .as_fixed_size_list()
+            .values()
+            .as_primitive::<Float32Type>();
+        assert_relative_eq!(first.values()[..], [1.0, 0.1, 0.1, 1.0]);
+        let third = pooled.value(2);
+        let third = third
+            .as_fixed_size_list()
+            .values()
+            .as_primitive::<Float32Type>();
+        assert_eq!(third.values()[..], [3.0, 3.0]);
+
+        // pooling single vectors is a no-op
+        let output = PoolMultivectors::new("v", 2)
+            .transform(
+                &RecordBatch::try_new(
+                    Schema::new(vec![Field::new("v", pooled.value_type(), true)]).into(),
+                    vec![pooled.values().clone()],
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(output.num_rows(), 3);
+    }
 }
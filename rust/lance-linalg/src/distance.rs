@@ -21,6 +21,7 @@ pub mod dot;
 pub mod hamming;
 pub mod l2;
 pub mod norm_l2;
+pub mod sparse;
 
 pub use cosine::*;
 use deepsize::DeepSizeOf;
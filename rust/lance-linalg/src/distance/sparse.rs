@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Dot product of sparse vectors.
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use arrow_array::{Array, Float32Array};
+use lance_arrow::sparse::{SparseVectorArray, SparseVectorRef};
+
+use crate::Result;
+
+/// Dot product of two sparse vectors, whose indices are sorted in increasing order.
+pub fn sparse_dot(x: SparseVectorRef, y: SparseVectorRef) -> f32 {
+    let (mut i, mut j) = (0, 0);
+    let mut sum = 0.0;
+    while i < x.indices.len() && j < y.indices.len() {
+        match x.indices[i].cmp(&y.indices[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                sum += x.values[i] * y.values[j];
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    sum
+}
+
+/// Dot product between a query sparse vector and every vector of a sparse vector array.
+///
+/// Null vectors produce null scores.
+pub fn sparse_dot_arrow_batch(
+    query: SparseVectorRef,
+    vectors: &dyn Array,
+) -> Result<Arc<Float32Array>> {
+    let vectors = SparseVectorArray::try_new(vectors)?;
+    Ok(Arc::new(
+        vectors
+            .iter()
+            .map(|v| v.map(|v| sparse_dot(query, v)))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use lance_arrow::sparse::SparseVector;
+
+    #[test]
+    fn test_sparse_dot() {
+        let x = SparseVector::try_new(vec![1, 4, 9, 12], vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+        let y = SparseVector::try_new(vec![0, 4, 12, 20], vec![5.0, 6.0, 7.0, 8.0]).unwrap();
+        assert_eq!(sparse_dot(x.as_ref(), y.as_ref()), 2.0 * 6.0 + 4.0 * 7.0);
+        assert_eq!(
+            sparse_dot(x.as_ref(), SparseVector::default().as_ref()),
+            0.0
+        );
+
+        let vectors = [Some(y.clone()), None, Some(x)]
+            .into_iter()
+            .collect::<SparseVectorArray>();
+        let scores = sparse_dot_arrow_batch(y.as_ref(), &vectors.into_inner()).unwrap();
+        assert_eq!(
+            scores.value(0),
+            5.0 * 5.0 + 6.0 * 6.0 + 7.0 * 7.0 + 8.0 * 8.0
+        );
+        assert!(scores.is_null(1));
+        assert_eq!(scores.value(2), 40.0);
+    }
+}
@@ -41,6 +41,10 @@ pub struct DataFile {
 
     /// The size of the file in bytes, if known.
     pub file_size_bytes: CachedFileSize,
+
+    /// Statistics of the top-level columns in this file, if collected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_stats: Vec<ColumnStats>,
 }
 
 impl DataFile {
@@ -59,6 +63,7 @@ impl DataFile {
             file_major_version,
             file_minor_version,
             file_size_bytes: file_size_bytes.into(),
+            column_stats: vec![],
         }
     }
 
@@ -75,6 +80,7 @@ impl DataFile {
             file_major_version,
             file_minor_version,
             file_size_bytes: Default::default(),
+            column_stats: vec![],
         }
     }
 
@@ -110,6 +116,17 @@ impl DataFile {
         full_schema.project_by_ids(&self.fields, false)
     }
 
+    /// The statistics of the field with the given id, if collected.
+    ///
+    /// Returns None if the field is no longer stored in this file (e.g. it was
+    /// tombstoned by a partial column update), since the statistics are stale.
+    pub fn column_stats(&self, field_id: i32) -> Option<&ColumnStats> {
+        if !self.fields.contains(&field_id) {
+            return None;
+        }
+        self.column_stats.iter().find(|s| s.field_id == field_id)
+    }
+
     pub fn is_legacy_file(&self) -> bool {
         self.file_major_version == 0 && self.file_minor_version < 3
     }
@@ -143,6 +160,7 @@ impl From<&DataFile> for pb::DataFile {
             file_major_version: df.file_major_version,
             file_minor_version: df.file_minor_version,
             file_size_bytes: df.file_size_bytes.get().map_or(0, |v| v.get()),
+            column_stats: df.column_stats.iter().map(pb::ColumnStats::from).collect(),
         }
     }
 }
@@ -158,10 +176,94 @@ impl TryFrom<pb::DataFile> for DataFile {
             file_major_version: proto.file_major_version,
             file_minor_version: proto.file_minor_version,
             file_size_bytes: CachedFileSize::new(proto.file_size_bytes),
+            column_stats: proto.column_stats.iter().map(ColumnStats::from).collect(),
         })
     }
 }
 
+/// Statistics of a single column of a [`DataFile`].
+///
+/// These describe all the rows of the file, including deleted rows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, DeepSizeOf)]
+pub struct ColumnStats {
+    /// The id of the field.
+    pub field_id: i32,
+    /// The number of null values.
+    pub null_count: u64,
+    /// The smallest and largest non-null values, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_max: Option<(StatValue, StatValue)>,
+}
+
+/// A single value in [`ColumnStats`].
+///
+/// Dates, times and timestamps are stored as their integer representation.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize, DeepSizeOf)]
+pub enum StatValue {
+    Int(i64),
+    UInt(u64),
+    /// Never NaN
+    Float(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Bool(bool),
+}
+
+// Float values are never NaN
+impl Eq for StatValue {}
+
+impl From<&pb::StatValue> for Option<StatValue> {
+    fn from(p: &pb::StatValue) -> Self {
+        use pb::stat_value::Value;
+        Some(match p.value.as_ref()? {
+            Value::IntValue(v) => StatValue::Int(*v),
+            Value::UintValue(v) => StatValue::UInt(*v),
+            Value::FloatValue(v) => StatValue::Float(*v),
+            Value::StringValue(v) => StatValue::String(v.clone()),
+            Value::BinaryValue(v) => StatValue::Binary(v.clone()),
+            Value::BoolValue(v) => StatValue::Bool(*v),
+        })
+    }
+}
+
+impl From<&StatValue> for pb::StatValue {
+    fn from(v: &StatValue) -> Self {
+        use pb::stat_value::Value;
+        let value = match v {
+            StatValue::Int(v) => Value::IntValue(*v),
+            StatValue::UInt(v) => Value::UintValue(*v),
+            StatValue::Float(v) => Value::FloatValue(*v),
+            StatValue::String(v) => Value::StringValue(v.clone()),
+            StatValue::Binary(v) => Value::BinaryValue(v.clone()),
+            StatValue::Bool(v) => Value::BoolValue(*v),
+        };
+        Self { value: Some(value) }
+    }
+}
+
+impl From<&pb::ColumnStats> for ColumnStats {
+    fn from(p: &pb::ColumnStats) -> Self {
+        let min = p.min_value.as_ref().and_then(Option::<StatValue>::from);
+        let max = p.max_value.as_ref().and_then(Option::<StatValue>::from);
+        Self {
+            field_id: p.field_id,
+            null_count: p.null_count,
+            min_max: min.zip(max),
+        }
+    }
+}
+
+impl From<&ColumnStats> for pb::ColumnStats {
+    fn from(s: &ColumnStats) -> Self {
+        Self {
+            field_id: s.field_id,
+            null_count: s.null_count,
+            min_value: s.min_max.as_ref().map(|(min, _)| min.into()),
+            max_value: s.min_max.as_ref().map(|(_, max)| max.into()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, DeepSizeOf)]
 #[serde(rename_all = "lowercase")]
 pub enum DeletionFileType {
@@ -272,6 +374,53 @@ pub struct Fragment {
     /// unknown. This is only optional for legacy reasons. All new tables should
     /// have this set.
     pub physical_rows: Option<usize>,
+
+    /// The partition this fragment belongs to, with one value for each field of
+    /// the dataset's partition spec. Empty if the dataset is not partitioned or
+    /// the partition is unknown.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partition: Vec<PartitionValue>,
+}
+
+/// The value of one field of a partition spec for a fragment.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, DeepSizeOf,
+)]
+pub enum PartitionValue {
+    Null,
+    Int(i64),
+    String(String),
+}
+
+impl std::fmt::Display for PartitionValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Int(v) => write!(f, "{}", v),
+            Self::String(v) => write!(f, "{:?}", v),
+        }
+    }
+}
+
+impl From<&pb::PartitionValue> for PartitionValue {
+    fn from(p: &pb::PartitionValue) -> Self {
+        match &p.value {
+            None => Self::Null,
+            Some(pb::partition_value::Value::IntValue(v)) => Self::Int(*v),
+            Some(pb::partition_value::Value::StringValue(v)) => Self::String(v.clone()),
+        }
+    }
+}
+
+impl From<&PartitionValue> for pb::PartitionValue {
+    fn from(v: &PartitionValue) -> Self {
+        let value = match v {
+            PartitionValue::Null => None,
+            PartitionValue::Int(v) => Some(pb::partition_value::Value::IntValue(*v)),
+            PartitionValue::String(v) => Some(pb::partition_value::Value::StringValue(v.clone())),
+        };
+        Self { value }
+    }
 }
 
 impl Fragment {
@@ -282,6 +431,7 @@ impl Fragment {
             deletion_file: None,
             row_id_meta: None,
             physical_rows: None,
+            partition: vec![],
         }
     }
 
@@ -319,6 +469,7 @@ impl Fragment {
             deletion_file: None,
             physical_rows,
             row_id_meta: None,
+            partition: vec![],
         }
     }
 
@@ -437,6 +588,7 @@ impl TryFrom<pb::DataFragment> for Fragment {
             deletion_file: p.deletion_file.map(DeletionFile::try_from).transpose()?,
             row_id_meta: p.row_id_sequence.map(RowIdMeta::try_from).transpose()?,
             physical_rows,
+            partition: p.partition.iter().map(PartitionValue::from).collect(),
         })
     }
 }
@@ -473,6 +625,7 @@ impl From<&Fragment> for pb::DataFragment {
             deletion_file,
             row_id_sequence,
             physical_rows: f.physical_rows.unwrap_or_default() as u64,
+            partition: f.partition.iter().map(pb::PartitionValue::from).collect(),
         }
     }
 }
@@ -511,6 +511,10 @@ impl TryFrom<pb::Manifest> for Manifest {
         };
 
         let schema = Schema::from(fields_with_meta);
+        schema
+            .fields
+            .iter()
+            .try_for_each(|field| field.validate_logical_types())?;
         let local_schema = schema.retain_storage_class(StorageClass::Default);
 
         Ok(Self {
@@ -751,6 +755,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             },
             Fragment {
                 id: 1,
@@ -761,6 +766,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             },
         ];
 
@@ -95,6 +95,16 @@ impl From<Range<u64>> for RowIdSequence {
     }
 }
 
+impl From<&[u64]> for RowIdSequence {
+    fn from(row_ids: &[u64]) -> Self {
+        if row_ids.is_empty() {
+            Self(Vec::new())
+        } else {
+            Self(vec![U64Segment::from_slice(row_ids)])
+        }
+    }
+}
+
 impl RowIdSequence {
     pub fn iter(&self) -> impl DoubleEndedIterator<Item = u64> + '_ {
         self.0.iter().flat_map(|segment| segment.iter())
@@ -11,6 +11,7 @@ use futures::TryStreamExt;
 use snafu::location;
 
 use lance::dataset::Dataset;
+use lance::index::vector::tune::{tune_vector_index, TuningParams};
 use lance::index::vector::VectorIndexParams;
 use lance::{Error, Result};
 use lance_index::DatasetIndexExt;
@@ -72,12 +73,25 @@ enum Commands {
         /// Distance metric type. Only support 'l2' and 'cosine'.
         #[arg(short = 'm', long, value_name = "DISTANCE")]
         metric_type: Option<String>,
+
+        /// Number of nearest neighbors to evaluate recall@k. Only useful when tuning.
+        #[arg(short = 'k', long, default_value_t = 10, value_name = "NUM")]
+        k: usize,
+
+        /// Number of query vectors sampled from the dataset. Only useful when tuning.
+        #[arg(long, default_value_t = 100, value_name = "NUM")]
+        num_queries: usize,
+
+        /// The recall to reach with the recommended settings. Only useful when tuning.
+        #[arg(long, default_value_t = 0.95, value_name = "RECALL")]
+        target_recall: f64,
     },
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum IndexAction {
     Create,
+    Tune,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -124,6 +138,9 @@ async fn main() -> Result<()> {
             num_partitions,
             num_sub_vectors,
             metric_type,
+            k,
+            num_queries,
+            target_recall,
         } => {
             let mut dataset = Dataset::open(uri).await.unwrap();
             match action {
@@ -139,11 +156,70 @@ async fn main() -> Result<()> {
                     )
                     .await
                 }
+                IndexAction::Tune => {
+                    let params = TuningParams {
+                        k: *k,
+                        num_queries: *num_queries,
+                        target_recall: *target_recall,
+                        ..Default::default()
+                    };
+                    tune_index(&dataset, column, &params).await
+                }
             }
         }
     }
 }
 
+async fn tune_index(
+    dataset: &Dataset,
+    column: &Option<String>,
+    params: &TuningParams,
+) -> Result<()> {
+    let col = column.as_ref().ok_or_else(|| Error::Index {
+        message: "Must specify column".to_string(),
+        location: location!(),
+    })?;
+    let report = tune_vector_index(dataset, col, params).await?;
+    println!(
+        "recall@{} over {} queries, target recall {}",
+        report.k, report.num_queries, report.target_recall
+    );
+    println!(
+        "{:>8} {:>14} {:>8} {:>8} {:>14} {:>12} {:>8}",
+        "nprobes", "refine_factor", "ef", "recall", "latency (ms)", "bytes read", "iops"
+    );
+    for evaluation in &report.evaluations {
+        let settings = &evaluation.settings;
+        println!(
+            "{:>8} {:>14} {:>8} {:>8.4} {:>14.3} {:>12} {:>8}",
+            settings.nprobes,
+            settings
+                .refine_factor
+                .map(|factor| factor.to_string())
+                .unwrap_or("-".to_string()),
+            settings
+                .ef
+                .map(|ef| ef.to_string())
+                .unwrap_or("-".to_string()),
+            evaluation.recall,
+            evaluation.latency.as_secs_f64() * 1000.0,
+            evaluation.bytes_read,
+            evaluation.iops,
+        );
+    }
+    match report.recommendation() {
+        Some(evaluation) => println!(
+            "Recommended: {} (recall {:.4})",
+            evaluation.settings, evaluation.recall
+        ),
+        None => println!(
+            "No settings reach the target recall {}",
+            report.target_recall
+        ),
+    }
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn create_index(
     dataset: &mut Dataset,
@@ -8,6 +8,7 @@ use std::{
 
 use arrow_schema::{Schema, SchemaRef};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use datafusion::{
     catalog::{streaming::StreamingTable, Session},
     dataframe::DataFrame,
@@ -20,7 +21,7 @@ use datafusion::{
 use lance_arrow::SchemaExt;
 use lance_core::{ROW_ADDR_FIELD, ROW_ID_FIELD};
 
-use crate::Dataset;
+use crate::{Dataset, Result};
 
 #[derive(Debug)]
 pub struct LanceTableProvider {
@@ -50,6 +51,20 @@ impl LanceTableProvider {
             row_addr_idx,
         }
     }
+
+    /// Creates a provider over the latest version of `dataset` that was
+    /// committed at or before `timestamp`
+    ///
+    /// Registering this provider lets SQL query the table as it was at that time.
+    pub async fn new_as_of(
+        dataset: &Dataset,
+        timestamp: DateTime<Utc>,
+        with_row_id: bool,
+        with_row_addr: bool,
+    ) -> Result<Self> {
+        let dataset = dataset.checkout_as_of(timestamp).await?;
+        Ok(Self::new(Arc::new(dataset), with_row_id, with_row_addr))
+    }
 }
 
 #[async_trait]
@@ -208,8 +223,10 @@ pub mod tests {
         array::AsArray,
         datatypes::{Int32Type, Int64Type},
     };
+    use chrono::Duration;
     use datafusion::prelude::SessionContext;
-    use lance_datagen::array;
+    use lance_core::utils::testing::MockClock;
+    use lance_datagen::{array, BatchCount, RowCount};
     use tempfile::tempdir;
 
     use crate::{
@@ -253,4 +270,58 @@ pub mod tests {
         // SUM(0..100) - SUM(0..50) = 3675
         assert_eq!(results.column(0).as_primitive::<Int64Type>().value(0), 3675);
     }
+
+    #[tokio::test]
+    pub async fn test_table_provider_as_of() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let clock = MockClock::new();
+        clock.set_system_time(Duration::seconds(1));
+        let mut dataset = lance_datagen::gen()
+            .col("x", array::step::<Int32Type>())
+            .into_dataset(test_uri, FragmentCount::from(1), FragmentRowCount::from(10))
+            .await
+            .unwrap();
+        let as_of = dataset.version().timestamp;
+
+        clock.set_system_time(Duration::seconds(2));
+        let more_data = lance_datagen::gen()
+            .col("x", array::step::<Int32Type>())
+            .into_reader_rows(RowCount::from(5), BatchCount::from(1));
+        dataset.append(more_data, None).await.unwrap();
+
+        let ctx = SessionContext::new();
+        ctx.register_table(
+            "foo",
+            Arc::new(LanceTableProvider::new(
+                Arc::new(dataset.clone()),
+                false,
+                false,
+            )),
+        )
+        .unwrap();
+        ctx.register_table(
+            "foo_as_of",
+            Arc::new(
+                LanceTableProvider::new_as_of(&dataset, as_of, false, false)
+                    .await
+                    .unwrap(),
+            ),
+        )
+        .unwrap();
+
+        for (table, expected) in [("foo", 15), ("foo_as_of", 10)] {
+            let results = ctx
+                .sql(&format!("SELECT COUNT(x) FROM {}", table))
+                .await
+                .unwrap()
+                .collect()
+                .await
+                .unwrap();
+            assert_eq!(
+                results[0].column(0).as_primitive::<Int64Type>().value(0),
+                expected
+            );
+        }
+    }
 }
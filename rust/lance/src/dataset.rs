@@ -50,10 +50,14 @@ use tracing::{info, instrument};
 mod blob;
 pub mod builder;
 pub mod cleanup;
+mod diff;
 pub mod fragment;
 mod hash_joiner;
+pub mod hybrid;
 pub mod index;
+mod merge_versions;
 pub mod optimize;
+pub mod partition;
 pub mod progress;
 pub mod refs;
 pub(crate) mod rowids;
@@ -69,8 +73,9 @@ mod write;
 use self::builder::DatasetBuilder;
 use self::cleanup::RemovalStats;
 use self::fragment::FileFragment;
-use self::refs::Tags;
+use self::refs::{branch_base_path, Branches, Tags, MAIN_BRANCH};
 use self::scanner::{DatasetRecordBatchStream, Scanner};
+use self::statistics::{FragmentMatch, FragmentStatsPruner};
 use self::transaction::{Operation, Transaction};
 use self::write::write_fragments_internal;
 use crate::datatypes::Schema;
@@ -84,9 +89,11 @@ use crate::session::Session;
 use crate::utils::temporal::{timestamp_to_nanos, utc_now, SystemTime};
 use crate::{Error, Result};
 pub use blob::BlobFile;
+pub use diff::{ChangeType, CHANGE_TYPE_COLUMN};
 use hash_joiner::HashJoiner;
 pub use lance_core::ROW_ID;
 use lance_table::feature_flags::{apply_feature_flags, can_read_dataset};
+pub use merge_versions::{MergeConflict, MergeSide, MergeVersionsOutcome, MergeVersionsStats};
 pub use schema_evolution::{
     BatchInfo, BatchUDF, ColumnAlteration, NewColumnTransform, UDFCheckpointStore,
 };
@@ -126,6 +133,9 @@ pub struct Dataset {
     pub(crate) manifest_location: ManifestLocation,
     pub(crate) session: Arc<Session>,
     pub tags: Tags,
+    pub branches: Branches,
+    /// The checked out branch, `None` for the main branch.
+    pub(crate) branch: Option<String>,
 }
 
 impl std::fmt::Debug for Dataset {
@@ -134,6 +144,7 @@ impl std::fmt::Debug for Dataset {
             .field("uri", &self.uri)
             .field("base", &self.base)
             .field("version", &self.manifest.version)
+            .field("branch", &self.branch)
             .field("cache_num_items", &self.session.approx_num_items())
             .finish()
     }
@@ -320,9 +331,89 @@ impl Dataset {
         match ref_ {
             refs::Ref::Version(version) => self.checkout_by_version_number(version).await,
             refs::Ref::Tag(tag) => self.checkout_by_tag(tag.as_str()).await,
+            refs::Ref::Branch(branch) => self.checkout_branch(branch.as_str()).await,
+            refs::Ref::Timestamp(timestamp) => self.checkout_as_of(timestamp).await,
         }
     }
 
+    /// Check out the latest version of the checked out branch that was
+    /// committed at or before `timestamp`.
+    ///
+    /// Returns [`Error::VersionNotFound`] if every remaining version was
+    /// committed after `timestamp`.
+    pub async fn checkout_as_of(&self, timestamp: DateTime<Utc>) -> Result<Self> {
+        let version = refs::resolve_version_as_of(
+            &self.object_store,
+            self.commit_handler.as_ref(),
+            &self.manifest_base(),
+            timestamp,
+        )
+        .await?;
+        self.checkout_by_version_number(version).await
+    }
+
+    /// Check out the latest version of a branch.
+    ///
+    /// Use [`MAIN_BRANCH`] to return to the main history.
+    pub async fn checkout_branch(&self, branch: &str) -> Result<Self> {
+        let branch = if branch == MAIN_BRANCH {
+            None
+        } else {
+            self.branches.get(branch).await?;
+            Some(branch.to_string())
+        };
+        let manifest_base = branch_base_path(&self.base, branch.as_deref());
+        let manifest_location = self
+            .commit_handler
+            .resolve_latest_location(&manifest_base, &self.object_store)
+            .await?;
+        let manifest = Self::load_manifest(
+            self.object_store.as_ref(),
+            &manifest_location,
+            &manifest_base,
+            self.session.as_ref(),
+        )
+        .await?;
+        Self::checkout_manifest(
+            self.object_store.clone(),
+            self.base.clone(),
+            self.uri.clone(),
+            Arc::new(manifest),
+            manifest_location,
+            self.session.clone(),
+            self.commit_handler.clone(),
+            branch,
+        )
+    }
+
+    /// Create a branch from `from` and check it out.
+    ///
+    /// A version number is resolved against the currently checked out branch,
+    /// a tag always against the main branch.
+    /// The new branch shares all data files with its parent; commits to the
+    /// returned dataset only move the new branch.
+    pub async fn create_branch(
+        &mut self,
+        branch: &str,
+        from: impl Into<refs::Ref>,
+    ) -> Result<Self> {
+        let source = self.checkout_version(from).await?;
+        self.branches
+            .create(branch, source.branch.as_deref(), source.version().version)
+            .await?;
+        self.checkout_branch(branch).await
+    }
+
+    /// The name of the checked out branch.
+    pub fn branch(&self) -> &str {
+        self.branch.as_deref().unwrap_or(MAIN_BRANCH)
+    }
+
+    /// The root under which the manifests of the checked out branch are stored.
+    pub(crate) fn manifest_base(&self) -> Path {
+        branch_base_path(&self.base, self.branch.as_deref())
+    }
+
     /// Check out the latest version of the dataset
     pub async fn checkout_latest(&mut self) -> Result<()> {
         let (manifest, manifest_location) = self.latest_manifest().await?;
@@ -345,20 +436,26 @@ impl Dataset {
     }
 
     async fn checkout_by_version_number(&self, version: u64) -> Result<Self> {
+        self.checkout_branch_version(self.branch.clone(), version)
+            .await
+    }
+
+    async fn checkout_branch_version(&self, branch: Option<String>, version: u64) -> Result<Self> {
         let base_path = self.base.clone();
+        let manifest_base = branch_base_path(&self.base, branch.as_deref());
         let manifest_location = self
             .commit_handler
-            .resolve_version_location(&base_path, version, &self.object_store.inner)
+            .resolve_version_location(&manifest_base, version, &self.object_store.inner)
             .await?;
 
-        if self.already_checked_out(&manifest_location) {
+        if branch == self.branch && self.already_checked_out(&manifest_location) {
             return Ok(self.clone());
         }
 
         let manifest = Self::load_manifest(
             self.object_store.as_ref(),
             &manifest_location,
-            &base_path,
+            &manifest_base,
             self.session.as_ref(),
         )
         .await?;
@@ -370,12 +467,14 @@ impl Dataset {
             manifest_location,
             self.session.clone(),
             self.commit_handler.clone(),
+            branch,
         )
     }
 
     async fn checkout_by_tag(&self, tag: &str) -> Result<Self> {
+        // Tags always point at versions of the main branch
         let version = self.tags.get_version(tag).await?;
-        self.checkout_by_version_number(version).await
+        self.checkout_branch_version(None, version).await
     }
 
     async fn load_manifest(
@@ -479,12 +578,18 @@ impl Dataset {
         manifest_location: ManifestLocation,
         session: Arc<Session>,
         commit_handler: Arc<dyn CommitHandler>,
+        branch: Option<String>,
     ) -> Result<Self> {
         let tags = Tags::new(
             object_store.clone(),
             commit_handler.clone(),
             base_path.clone(),
         );
+        let branches = Branches::new(
+            object_store.clone(),
+            commit_handler.clone(),
+            base_path.clone(),
+        );
         Ok(Self {
             object_store,
             base: base_path,
@@ -494,6 +599,8 @@ impl Dataset {
             commit_handler,
             session,
             tags,
+            branches,
+            branch,
         })
     }
 
@@ -577,6 +684,7 @@ impl Dataset {
                 blob_manifest_location,
                 self.session.clone(),
                 self.commit_handler.clone(),
+                None,
             )?;
             Ok(Some(Arc::new(blobs_dataset)))
         } else {
@@ -595,7 +703,7 @@ impl Dataset {
     pub async fn latest_manifest(&self) -> Result<(Arc<Manifest>, ManifestLocation)> {
         let location = self
             .commit_handler
-            .resolve_latest_location(&self.base, &self.object_store)
+            .resolve_latest_location(&self.manifest_base(), &self.object_store)
             .await?;
 
         // Check if manifest is in cache before reading from storage
@@ -864,11 +972,31 @@ impl Dataset {
         if let Some(filter) = filter {
             let mut scanner = self.scan();
             scanner.filter(&filter)?;
-            Ok(scanner
-                .project::<String>(&[])?
-                .with_row_id() // TODO: fix scan plan to not require row_id for count_rows.
-                .count_rows()
-                .await? as usize)
+
+            // Fragments whose column statistics show that all or none of their
+            // rows match the filter are counted from the metadata alone.
+            let expr = scanner.get_filter()?.expect("filter was set");
+            let mut count = 0;
+            let mut remaining = Vec::new();
+            for fragment in self.fragments().iter() {
+                let matched = FragmentStatsPruner::new(self.schema(), fragment).evaluate(&expr);
+                match (matched, fragment.num_rows()) {
+                    (FragmentMatch::None, _) => {}
+                    (FragmentMatch::All, Some(num_rows)) => count += num_rows,
+                    _ => remaining.push(fragment.clone()),
+                }
+            }
+            if remaining.is_empty() {
+                return Ok(count);
+            }
+
+            Ok(count
+                + scanner
+                    .with_fragments(remaining)
+                    .project::<String>(&[])?
+                    .with_row_id() // TODO: fix scan plan to not require row_id for count_rows.
+                    .count_rows()
+                    .await? as usize)
         } else {
             self.count_all_rows().await
         }
@@ -1076,7 +1204,7 @@ impl Dataset {
     pub async fn versions(&self) -> Result<Vec<Version>> {
         let mut versions: Vec<Version> = self
             .commit_handler
-            .list_manifest_locations(&self.base, &self.object_store, false)
+            .list_manifest_locations(&self.manifest_base(), &self.object_store, false)
             .try_filter_map(|location| async move {
                 match read_manifest(&self.object_store, &location.path, location.size).await {
                     Ok(manifest) => Ok(Some(Version::from(&manifest))),
@@ -1098,7 +1226,7 @@ impl Dataset {
     pub async fn latest_version_id(&self) -> Result<u64> {
         Ok(self
             .commit_handler
-            .resolve_latest_location(&self.base, &self.object_store)
+            .resolve_latest_location(&self.manifest_base(), &self.object_store)
             .await?
             .version)
     }
@@ -1472,7 +1600,7 @@ pub(crate) fn load_new_transactions(dataset: &Dataset) -> NewTransactionResult<'
     let latest_version = dataset.manifest.version;
     let locations = dataset
         .commit_handler
-        .list_manifest_locations(&dataset.base, dataset.object_store(), true)
+        .list_manifest_locations(&dataset.manifest_base(), dataset.object_store(), true)
         .try_take_while(move |location| {
             futures::future::ready(Ok(location.version > latest_version))
         });
@@ -1486,6 +1614,7 @@ pub(crate) fn load_new_transactions(dataset: &Dataset) -> NewTransactionResult<'
             let latest_tx = latest_tx.take();
             async move {
                 let cache_path = manifest_cache_path(&location);
+                let manifest_base = dataset.manifest_base();
                 let manifest = dataset
                     .session
                     .file_metadata_cache
@@ -1493,7 +1622,7 @@ pub(crate) fn load_new_transactions(dataset: &Dataset) -> NewTransactionResult<'
                         Dataset::load_manifest(
                             dataset.object_store(),
                             &location,
-                            &dataset.base,
+                            &manifest_base,
                             dataset.session.as_ref(),
                         )
                     })
@@ -1510,7 +1639,8 @@ pub(crate) fn load_new_transactions(dataset: &Dataset) -> NewTransactionResult<'
         .try_buffer_unordered(io_parallelism / 2);
     let transactions = manifests
         .map_ok(move |(manifest, location)| async move {
-            let cache_path = transaction_file_cache_path(&dataset.base, manifest.version);
+            let cache_path =
+                transaction_file_cache_path(&dataset.manifest_base(), manifest.version);
             let manifest_copy = manifest.clone();
             let transaction = dataset
                 .session
@@ -1524,6 +1654,7 @@ pub(crate) fn load_new_transactions(dataset: &Dataset) -> NewTransactionResult<'
                         location,
                         dataset.session(),
                         dataset.commit_handler.clone(),
+                        dataset.branch.clone(),
                     )?;
                     let object_store = dataset_version.object_store();
                     let path = dataset_version
@@ -1558,6 +1689,7 @@ pub(crate) fn load_new_transactions(dataset: &Dataset) -> NewTransactionResult<'
                 location,
                 dataset.session(),
                 dataset.commit_handler.clone(),
+                dataset.branch.clone(),
             )
         } else {
             // If we didn't get the latest manifest, we can still return the dataset
@@ -1895,7 +2027,7 @@ mod tests {
 
     use arrow::array::{as_struct_array, AsArray, GenericListBuilder, GenericStringBuilder};
     use arrow::compute::concat_batches;
-    use arrow::datatypes::UInt64Type;
+    use arrow::datatypes::{UInt32Type, UInt64Type};
     use arrow_array::{
         builder::StringDictionaryBuilder,
         cast::as_string_array,
@@ -1904,8 +2036,8 @@ mod tests {
         Int8DictionaryArray, RecordBatchIterator, StringArray, UInt16Array, UInt32Array,
     };
     use arrow_array::{
-        Array, FixedSizeListArray, GenericStringArray, Int16Array, Int16DictionaryArray,
-        StructArray, UInt64Array,
+        Array, BinaryViewArray, FixedSizeListArray, GenericStringArray, Int16Array,
+        Int16DictionaryArray, StringViewArray, StructArray, UInt64Array,
     };
     use arrow_ord::sort::sort_to_indices;
     use arrow_schema::{
@@ -1913,12 +2045,19 @@ mod tests {
     };
     use lance_arrow::bfloat16::{self, ARROW_EXT_META_KEY, ARROW_EXT_NAME_KEY, BFLOAT16_EXT_NAME};
     use lance_core::datatypes::LANCE_STORAGE_CLASS_SCHEMA_META_KEY;
+    use lance_core::utils::testing::MockClock;
     use lance_datagen::{array, gen, BatchCount, Dimension, RowCount};
     use lance_file::v2::writer::FileWriter;
     use lance_file::version::LanceFileVersion;
     use lance_index::scalar::inverted::{
-        query::{BooleanQuery, MatchQuery, Occur, Operator, PhraseQuery},
+        highlight::{HighlightParams, HIGHLIGHT_COL, MATCH_OFFSETS_COL},
+        query::{
+            BooleanQuery, FtsQuery, MatchQuery, MultiMatchQuery, Occur, Operator, PhraseQuery,
+            PrefixQuery, RegexQuery, WildcardQuery,
+        },
+        scorer::{CorpusStats, Scorer, ScorerFactory, ScoringModel, B, K1},
         tokenizer::InvertedIndexParams,
+        SCORE_COL,
     };
     use lance_index::scalar::FullTextSearchQuery;
     use lance_index::{scalar::ScalarIndexParams, vector::DIST_COL, DatasetIndexExt, IndexType};
@@ -4105,6 +4244,253 @@ mod tests {
         assert_eq!(dataset.manifest.version, 1);
     }
 
+    #[rstest]
+    #[tokio::test]
+    async fn test_checkout_as_of(#[values(false, true)] enable_v2_manifest_paths: bool) {
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "i",
+            DataType::UInt32,
+            false,
+        )]));
+        let make_reader = |range: Range<u32>| {
+            let data = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(UInt32Array::from_iter_values(range))],
+            )
+            .unwrap();
+            RecordBatchIterator::new(vec![Ok(data)], schema.clone())
+        };
+
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let write_params = WriteParams {
+            enable_v2_manifest_paths,
+            ..Default::default()
+        };
+        let clock = MockClock::new();
+        clock.set_system_time(Duration::seconds(1));
+        let mut dataset = Dataset::write(make_reader(0..10), test_uri, Some(write_params))
+            .await
+            .unwrap();
+        for i in 1..5 {
+            clock.set_system_time(Duration::seconds(i as i64 + 1));
+            dataset
+                .append(make_reader(i * 10..(i + 1) * 10), None)
+                .await
+                .unwrap();
+        }
+
+        let versions = dataset.versions().await.unwrap();
+        assert_eq!(versions.len(), 5);
+        for version in &versions {
+            let checked_out = dataset.checkout_as_of(version.timestamp).await.unwrap();
+            assert_eq!(checked_out.version().version, version.version);
+            // An instant between two commits resolves to the earlier commit
+            let checked_out = dataset
+                .checkout_version(version.timestamp + Duration::microseconds(1))
+                .await
+                .unwrap();
+            assert_eq!(checked_out.version().version, version.version);
+            assert_eq!(
+                checked_out.count_rows(None).await.unwrap(),
+                version.version as usize * 10
+            );
+        }
+
+        let err = dataset
+            .checkout_as_of(versions[0].timestamp - Duration::seconds(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::VersionNotFound { .. }));
+
+        let opened = DatasetBuilder::from_uri(test_uri)
+            .with_timestamp(versions[2].timestamp)
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(opened.version().version, 3);
+    }
+
+    #[tokio::test]
+    async fn test_checkout_as_of_out_of_order_timestamps() {
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "i",
+            DataType::UInt32,
+            false,
+        )]));
+        let make_reader = |range: Range<u32>| {
+            let data = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(UInt32Array::from_iter_values(range))],
+            )
+            .unwrap();
+            RecordBatchIterator::new(vec![Ok(data)], schema.clone())
+        };
+
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        // The clocks of the writers may be skewed, so the timestamps of the
+        // versions 1 to 5 don't increase with the version
+        let seconds = [1, 2, 9, 3, 8];
+        let clock = MockClock::new();
+        clock.set_system_time(Duration::seconds(seconds[0]));
+        let mut dataset = Dataset::write(make_reader(0..10), test_uri, None)
+            .await
+            .unwrap();
+        for (i, seconds) in seconds.iter().enumerate().skip(1) {
+            clock.set_system_time(Duration::seconds(*seconds));
+            let i = i as u32;
+            dataset
+                .append(make_reader(i * 10..(i + 1) * 10), None)
+                .await
+                .unwrap();
+        }
+        let start = dataset
+            .checkout_version(1)
+            .await
+            .unwrap()
+            .version()
+            .timestamp;
+
+        // The latest version committed at or before the instant
+        for (offset, expected) in [(0, 1), (1, 2), (2, 4), (3, 4), (7, 5), (8, 5), (20, 5)] {
+            let checked_out = dataset
+                .checkout_as_of(start + Duration::seconds(offset))
+                .await
+                .unwrap();
+            assert_eq!(checked_out.version().version, expected, "offset {}", offset);
+        }
+        let err = dataset
+            .checkout_as_of(start - Duration::seconds(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::VersionNotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_branch() {
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "i",
+            DataType::UInt32,
+            false,
+        )]));
+        let make_reader = |range: Range<u32>| {
+            let data = RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(UInt32Array::from_iter_values(range))],
+            )
+            .unwrap();
+            RecordBatchIterator::new(vec![Ok(data)], schema.clone())
+        };
+
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = Dataset::write(make_reader(0..100), test_uri, None)
+            .await
+            .unwrap();
+        dataset.tags.create("v1", 1).await.unwrap();
+        dataset.append(make_reader(100..200), None).await.unwrap();
+        assert_eq!(dataset.manifest.version, 2);
+        assert_eq!(dataset.branch(), MAIN_BRANCH);
+        assert_eq!(dataset.branches.list().await.unwrap().len(), 0);
+
+        let bad_branch = dataset.create_branch(MAIN_BRANCH, 1).await;
+        assert_eq!(
+            bad_branch.err().unwrap().to_string(),
+            "Ref is invalid: Ref main is reserved for the main branch"
+        );
+
+        // Fork from the tagged version and commit to the branch.
+        let mut branch = dataset.create_branch("exp", "v1").await.unwrap();
+        assert_eq!(branch.branch(), "exp");
+        assert_eq!(branch.manifest.version, 1);
+        assert_eq!(branch.count_rows(None).await.unwrap(), 100);
+
+        branch.append(make_reader(1000..1050), None).await.unwrap();
+        branch.delete("i < 10").await.unwrap();
+        assert_eq!(branch.manifest.version, 3);
+        assert_eq!(branch.count_rows(None).await.unwrap(), 140);
+        assert_eq!(branch.versions().await.unwrap().len(), 3);
+        assert_eq!(branch.checkout_version(2).await.unwrap().branch(), "exp");
+
+        // Tags resolve against main even while a branch is checked out.
+        let tagged = branch.checkout_version("v1").await.unwrap();
+        assert_eq!(tagged.branch(), MAIN_BRANCH);
+        assert_eq!(tagged.manifest.version, 1);
+        assert_eq!(tagged.count_rows(None).await.unwrap(), 100);
+        dataset.tags.create("v2", 2).await.unwrap();
+        let tagged = branch.checkout_version("v2").await.unwrap();
+        assert_eq!(tagged.branch(), MAIN_BRANCH);
+        assert_eq!(tagged.count_rows(None).await.unwrap(), 200);
+        let from_tag = branch.create_branch("from_tag", "v2").await.unwrap();
+        assert_eq!(from_tag.manifest.version, 2);
+        assert_eq!(from_tag.count_rows(None).await.unwrap(), 200);
+        let contents = dataset.branches.get("from_tag").await.unwrap();
+        assert_eq!(contents.parent_branch, None);
+        assert_eq!(contents.parent_version, 2);
+        dataset.branches.delete("from_tag").await.unwrap();
+
+        // Main has not moved.
+        dataset.checkout_latest().await.unwrap();
+        assert_eq!(dataset.manifest.version, 2);
+        assert_eq!(dataset.count_rows(None).await.unwrap(), 200);
+        assert_eq!(dataset.latest_version_id().await.unwrap(), 2);
+
+        let contents = dataset.branches.get("exp").await.unwrap();
+        assert_eq!(contents.parent_branch, None);
+        assert_eq!(contents.parent_version, 1);
+
+        let checked_out = dataset
+            .checkout_version(refs::Ref::Branch("exp".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(checked_out.manifest.version, 3);
+        let loaded = DatasetBuilder::from_uri(test_uri)
+            .with_branch("exp")
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(loaded.count_rows(None).await.unwrap(), 140);
+        let main = loaded.checkout_branch(MAIN_BRANCH).await.unwrap();
+        assert_eq!(main.manifest.version, 2);
+
+        // Branch from a branch.
+        let nested = branch
+            .create_branch("exp2", refs::Ref::Branch("exp".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(nested.manifest.version, 3);
+        assert_eq!(
+            dataset.branches.get("exp2").await.unwrap().parent_branch,
+            Some("exp".to_string())
+        );
+        assert_eq!(dataset.branches.list().await.unwrap().len(), 2);
+
+        // Cleaning up main must not remove files that branches still use.
+        dataset
+            .cleanup_old_versions(Duration::seconds(-1), Some(true), Some(false))
+            .await
+            .unwrap();
+        let branch = dataset.checkout_branch("exp").await.unwrap();
+        assert_eq!(branch.count_rows(None).await.unwrap(), 140);
+        branch.validate().await.unwrap();
+
+        let bad_delete = dataset.branches.delete("exp").await;
+        assert_eq!(
+            bad_delete.err().unwrap().to_string(),
+            "Ref conflict error: branch exp cannot be deleted, it is the parent of branches [\"exp2\"]"
+        );
+        dataset.branches.delete("exp2").await.unwrap();
+        dataset.branches.delete("exp").await.unwrap();
+        assert_eq!(dataset.branches.list().await.unwrap().len(), 0);
+        let bad_checkout = dataset.checkout_branch("exp").await;
+        assert_eq!(
+            bad_checkout.err().unwrap().to_string(),
+            "Ref not found error: branch exp does not exist"
+        );
+        assert_eq!(dataset.count_rows(None).await.unwrap(), 200);
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_search_empty(
@@ -4378,6 +4764,95 @@ mod tests {
         assert_eq!(batches, result);
     }
 
+    #[rstest]
+    #[tokio::test]
+    async fn test_view_types(
+        #[values(LanceFileVersion::V2_0, LanceFileVersion::V2_1)]
+        data_storage_version: LanceFileVersion,
+    ) {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+
+        let arrow_schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("s", DataType::Utf8View, true),
+            ArrowField::new("b", DataType::BinaryView, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            arrow_schema.clone(),
+            vec![
+                Arc::new(StringViewArray::from(vec![
+                    Some("short"),
+                    None,
+                    Some("a value that does not fit in a view"),
+                ])),
+                Arc::new(BinaryViewArray::from_iter_values([
+                    b"a".as_slice(),
+                    b"".as_slice(),
+                    b"another value too long to inline".as_slice(),
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let batch_reader = RecordBatchIterator::new(vec![Ok(batch.clone())], arrow_schema.clone());
+        let write_params = WriteParams {
+            data_storage_version: Some(data_storage_version),
+            ..Default::default()
+        };
+        Dataset::write(batch_reader, test_uri, Some(write_params.clone()))
+            .await
+            .unwrap();
+
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(
+            ArrowSchema::from(dataset.schema()).fields(),
+            arrow_schema.fields()
+        );
+        let result = scan_dataset(test_uri).await.unwrap();
+        assert_eq!(vec![batch.clone()], result);
+
+        // Offset based columns can be read back as views through the scanner
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let offsets_schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("s", DataType::Utf8, true),
+            ArrowField::new("b", DataType::Binary, false),
+        ]));
+        let offsets_batch = RecordBatch::try_new(
+            offsets_schema.clone(),
+            vec![
+                arrow::compute::cast(batch.column(0), &DataType::Utf8).unwrap(),
+                arrow::compute::cast(batch.column(1), &DataType::Binary).unwrap(),
+            ],
+        )
+        .unwrap();
+        let batch_reader = RecordBatchIterator::new(vec![Ok(offsets_batch)], offsets_schema);
+        let dataset = Dataset::write(batch_reader, test_uri, Some(write_params))
+            .await
+            .unwrap();
+
+        let mut scanner = dataset.scan();
+        scanner.use_view_types(true);
+        assert_eq!(
+            scanner.schema().await.unwrap().as_ref(),
+            arrow_schema.as_ref()
+        );
+        let result = scanner.try_into_batch().await.unwrap();
+        assert_eq!(result, batch);
+
+        // Columns taken after the scan are cast to views as well
+        let mut scanner = dataset.scan();
+        scanner
+            .use_view_types(true)
+            .filter("b IS NOT NULL")
+            .unwrap()
+            .limit(Some(1), None)
+            .unwrap();
+        let result = scanner.try_into_batch().await.unwrap();
+        assert_eq!(result.schema().as_ref(), arrow_schema.as_ref());
+        assert_eq!(result, batch.slice(0, 1));
+    }
+
     async fn scan_dataset(uri: &str) -> Result<Vec<RecordBatch>> {
         let results = Dataset::open(uri)
             .await?
@@ -5071,6 +5546,624 @@ mod tests {
         assert_eq!(results.num_rows(), 1);
     }
 
+    #[tokio::test]
+    async fn test_fts_highlight() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let id_col = Int32Array::from(vec![0, 1, 2]);
+        let title_col = GenericStringArray::<i32>::from(vec!["dog", "cat", "bird"]);
+        let text_col = GenericStringArray::<i32>::from(vec![
+            "the lazy dog sleeps",
+            "a quick brown fox",
+            "dogs chase the fox",
+        ]);
+        let batch = RecordBatch::try_new(
+            arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("id", DataType::Int32, false),
+                arrow_schema::Field::new("title", title_col.data_type().to_owned(), false),
+                arrow_schema::Field::new("text", text_col.data_type().to_owned(), false),
+            ])
+            .into(),
+            vec![
+                Arc::new(id_col) as ArrayRef,
+                Arc::new(title_col) as ArrayRef,
+                Arc::new(text_col) as ArrayRef,
+            ],
+        )
+        .unwrap();
+        let schema = batch.schema();
+        let batches = RecordBatchIterator::new(vec![batch].into_iter().map(Ok), schema);
+        let mut dataset = Dataset::write(batches, tempdir.path().to_str().unwrap(), None)
+            .await
+            .unwrap();
+        let params = InvertedIndexParams::default().with_position(true);
+        for column in ["title", "text"] {
+            dataset
+                .create_index(&[column], IndexType::Inverted, None, &params, true)
+                .await
+                .unwrap();
+        }
+
+        let highlights = |results: &RecordBatch| {
+            let ids = results["id"].as_primitive::<Int32Type>();
+            let snippets = results[HIGHLIGHT_COL].as_string::<i32>();
+            let offsets = results[MATCH_OFFSETS_COL].as_list::<i32>();
+            (0..results.num_rows())
+                .map(|i| {
+                    let offsets = offsets.value(i);
+                    let offsets = offsets.as_struct();
+                    let offsets = offsets
+                        .column(0)
+                        .as_primitive::<UInt32Type>()
+                        .values()
+                        .iter()
+                        .zip(offsets.column(1).as_primitive::<UInt32Type>().values())
+                        .map(|(start, end)| (*start, *end))
+                        .collect::<Vec<_>>();
+                    (ids.value(i), (snippets.value(i).to_owned(), offsets))
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        let query = FullTextSearchQuery::new("fox dog".to_owned())
+            .with_column("text".to_owned())
+            .unwrap()
+            .highlight(HighlightParams::default());
+        let results = dataset
+            .scan()
+            .project(&["id"])
+            .unwrap()
+            .full_text_search(query.clone())
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        assert_eq!(
+            results
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| f.name().as_str())
+                .collect::<Vec<_>>(),
+            vec!["id", SCORE_COL, HIGHLIGHT_COL, MATCH_OFFSETS_COL]
+        );
+        let expected = HashMap::from([
+            (
+                0,
+                ("the lazy <em>dog</em> sleeps".to_owned(), vec![(9, 12)]),
+            ),
+            (1, ("a quick brown <em>fox</em>".to_owned(), vec![(14, 17)])),
+            (
+                2,
+                (
+                    "<em>dogs</em> chase the <em>fox</em>".to_owned(),
+                    vec![(0, 4), (15, 18)],
+                ),
+            ),
+        ]);
+        assert_eq!(highlights(&results), expected);
+
+        // the column is highlighted when read as a view type
+        let results = dataset
+            .scan()
+            .project(&["id", "text"])
+            .unwrap()
+            .use_view_types(true)
+            .full_text_search(query.clone())
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        assert_eq!(results["text"].data_type(), &DataType::Utf8View);
+        assert_eq!(highlights(&results), expected);
+
+        // only the results after the filter and limit are highlighted
+        let results = dataset
+            .scan()
+            .project(&["id"])
+            .unwrap()
+            .filter("id > 0")
+            .unwrap()
+            .limit(Some(1), None)
+            .unwrap()
+            .full_text_search(query)
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        assert_eq!(
+            highlights(&results),
+            HashMap::from([(2, expected[&2].clone())])
+        );
+
+        // the query searches both columns, so the column to highlight must be specified
+        let query = FullTextSearchQuery::new("fox dog".to_owned());
+        let result = dataset
+            .scan()
+            .full_text_search(query.clone().highlight(HighlightParams::default()))
+            .unwrap()
+            .try_into_batch()
+            .await;
+        assert!(result.is_err());
+        let results = dataset
+            .scan()
+            .full_text_search(
+                query.highlight(
+                    HighlightParams::default()
+                        .with_column("title".to_owned())
+                        .with_tags("[".to_owned(), "]".to_owned()),
+                ),
+            )
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        let highlights = highlights(&results);
+        assert_eq!(highlights.len(), 3);
+        assert_eq!(highlights[&0], ("[dog]".to_owned(), vec![(0, 3)]));
+        assert_eq!(highlights[&2], ("bird".to_owned(), vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_fts_scoring() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let id_col = Int32Array::from(vec![0, 1, 2]);
+        let title_col =
+            GenericStringArray::<i32>::from(vec!["lance format", "columnar database", "other"]);
+        let content_col = GenericStringArray::<i32>::from(vec![
+            "a columnar format",
+            "lance is a columnar format for machine learning",
+            "nothing here",
+        ]);
+        let batch = RecordBatch::try_new(
+            arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("id", DataType::Int32, false),
+                arrow_schema::Field::new("title", title_col.data_type().to_owned(), false),
+                arrow_schema::Field::new("content", content_col.data_type().to_owned(), false),
+            ])
+            .into(),
+            vec![
+                Arc::new(id_col) as ArrayRef,
+                Arc::new(title_col) as ArrayRef,
+                Arc::new(content_col) as ArrayRef,
+            ],
+        )
+        .unwrap();
+        let schema = batch.schema();
+        let batches =
+            RecordBatchIterator::new(vec![batch.clone()].into_iter().map(Ok), schema.clone());
+        let mut dataset = Dataset::write(batches, tempdir.path().to_str().unwrap(), None)
+            .await
+            .unwrap();
+        let params = InvertedIndexParams::default();
+        for column in ["title", "content"] {
+            dataset
+                .create_index(&[column], IndexType::Inverted, None, &params, true)
+                .await
+                .unwrap();
+        }
+
+        async fn search(dataset: &Dataset, query: FullTextSearchQuery) -> (Vec<i32>, Vec<f32>) {
+            let results = dataset
+                .scan()
+                .full_text_search(query)
+                .unwrap()
+                .try_into_batch()
+                .await
+                .unwrap();
+            let ids = results["id"].as_primitive::<Int32Type>().values().to_vec();
+            let scores = results[SCORE_COL]
+                .as_primitive::<Float32Type>()
+                .values()
+                .to_vec();
+            (ids, scores)
+        }
+        let content_query = |terms: &str| {
+            FullTextSearchQuery::new(terms.to_owned())
+                .with_column("content".to_owned())
+                .unwrap()
+        };
+
+        // shorter documents rank higher with BM25 and TF-IDF
+        let (ids, bm25_scores) = search(&dataset, content_query("columnar")).await;
+        assert_eq!(ids, vec![0, 1]);
+        let (ids, scores) = search(
+            &dataset,
+            content_query("columnar").scoring(ScoringModel::TfIdf),
+        )
+        .await;
+        assert_eq!(ids, vec![0, 1]);
+        assert_ne!(scores, bm25_scores);
+        // b = 0 disables the length normalization of BM25
+        let (_, scores) = search(
+            &dataset,
+            content_query("columnar").scoring(ScoringModel::bm25(1.2, 0.0).unwrap()),
+        )
+        .await;
+        assert_eq!(scores[0], scores[1]);
+
+        // the custom scorer ranks longer documents higher
+        #[derive(Debug)]
+        struct DocLengthScorerFactory;
+        struct DocLengthScorer;
+        impl Scorer for DocLengthScorer {
+            fn query_weight(&self, _token: &str) -> f32 {
+                1.0
+            }
+            fn doc_weight(&self, _freq: u32, doc_tokens: u32) -> f32 {
+                doc_tokens as f32
+            }
+        }
+        impl ScorerFactory for DocLengthScorerFactory {
+            fn create_scorer<'a>(&self, _stats: Box<dyn CorpusStats + 'a>) -> Box<dyn Scorer + 'a> {
+                Box::new(DocLengthScorer)
+            }
+        }
+        let (ids, _) = search(
+            &dataset,
+            content_query("columnar")
+                .scoring(ScoringModel::Custom(Arc::new(DocLengthScorerFactory))),
+        )
+        .await;
+        assert_eq!(ids, vec![1, 0]);
+
+        // BM25F weights the scores of the fields
+        for (title_weight, content_weight, expected) in [(10.0, 1.0, 0), (1.0, 10.0, 1)] {
+            let scoring = ScoringModel::bm25f(
+                1.2,
+                0.75,
+                HashMap::from([
+                    ("title".to_owned(), title_weight),
+                    ("content".to_owned(), content_weight),
+                ]),
+            )
+            .unwrap();
+            let (ids, _) = search(
+                &dataset,
+                FullTextSearchQuery::new("lance".to_owned()).scoring(scoring),
+            )
+            .await;
+            assert_eq!(ids.len(), 2);
+            assert_eq!(ids[0], expected);
+        }
+
+        // the constant score is the same for the indexed and unindexed documents
+        let batches = RecordBatchIterator::new(vec![batch].into_iter().map(Ok), schema);
+        dataset.append(batches, None).await.unwrap();
+        let (ids, scores) = search(
+            &dataset,
+            content_query("format").scoring(ScoringModel::Constant),
+        )
+        .await;
+        assert_eq!(ids.len(), 4);
+        assert!(scores.iter().all(|score| *score == 1.0));
+
+        // the unindexed copies of the documents score the same as the indexed ones,
+        // with the lengths of the whole documents and only the terms they contain
+        for scoring in [ScoringModel::TfIdf, ScoringModel::Constant] {
+            let (ids, scores) = search(
+                &dataset,
+                content_query("columnar learning").scoring(scoring.clone()),
+            )
+            .await;
+            assert_eq!(ids.len(), 4, "{:?}", scoring);
+            let mut scores_by_id = HashMap::<i32, Vec<f32>>::new();
+            for (id, score) in ids.into_iter().zip(scores) {
+                scores_by_id.entry(id).or_default().push(score);
+            }
+            for (id, scores) in scores_by_id {
+                assert_eq!(scores.len(), 2);
+                assert!(
+                    (scores[0] - scores[1]).abs() < 1e-6,
+                    "{:?} scores of document {}: {:?}",
+                    scoring,
+                    id,
+                    scores
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fts_bm25f_ranking() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let id_col = Int32Array::from(vec![0, 1, 2, 3]);
+        let title_col = GenericStringArray::<i32>::from(vec!["lance", "lance", "other", "other"]);
+        let content_col =
+            GenericStringArray::<i32>::from(vec!["lance", "database", "database", "other"]);
+        let batch = RecordBatch::try_new(
+            arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("id", DataType::Int32, false),
+                arrow_schema::Field::new("title", title_col.data_type().to_owned(), false),
+                arrow_schema::Field::new("content", content_col.data_type().to_owned(), false),
+            ])
+            .into(),
+            vec![
+                Arc::new(id_col) as ArrayRef,
+                Arc::new(title_col) as ArrayRef,
+                Arc::new(content_col) as ArrayRef,
+            ],
+        )
+        .unwrap();
+        let schema = batch.schema();
+        let batches =
+            RecordBatchIterator::new(vec![batch.clone()].into_iter().map(Ok), schema.clone());
+        let mut dataset = Dataset::write(batches, tempdir.path().to_str().unwrap(), None)
+            .await
+            .unwrap();
+        let params = InvertedIndexParams::default();
+        for column in ["title", "content"] {
+            dataset
+                .create_index(&[column], IndexType::Inverted, None, &params, true)
+                .await
+                .unwrap();
+        }
+
+        async fn search(dataset: &Dataset, operator: Operator) -> Vec<i32> {
+            let query = MultiMatchQuery::try_new(
+                "lance database".to_owned(),
+                vec!["title".to_owned(), "content".to_owned()],
+            )
+            .unwrap()
+            .with_operator(operator);
+            let scoring = ScoringModel::bm25f(K1, B, HashMap::new()).unwrap();
+            let results = dataset
+                .scan()
+                .full_text_search(FullTextSearchQuery::new_query(query.into()).scoring(scoring))
+                .unwrap()
+                .try_into_batch()
+                .await
+                .unwrap();
+            results["id"].as_primitive::<Int32Type>().values().to_vec()
+        }
+
+        // "lance" in both fields of document 0 saturates once,
+        // so document 1 matching both terms ranks first
+        assert_eq!(search(&dataset, Operator::Or).await, vec![1, 0, 2]);
+        assert_eq!(search(&dataset, Operator::And).await, vec![1]);
+
+        // the unindexed documents are scored the same way
+        let batches = RecordBatchIterator::new(vec![batch].into_iter().map(Ok), schema);
+        dataset.append(batches, None).await.unwrap();
+        assert_eq!(search(&dataset, Operator::Or).await, vec![1, 1, 0, 0, 2, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_fts_pattern_query() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let id_col = Int32Array::from(vec![0, 1, 2]);
+        let text_col = GenericStringArray::<i32>::from(vec![
+            "SKU12345 red shirt",
+            "SKU12399 blue shirt",
+            "SKU45678 green hat",
+        ]);
+        let batch = RecordBatch::try_new(
+            arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("id", DataType::Int32, false),
+                arrow_schema::Field::new("text", text_col.data_type().to_owned(), false),
+            ])
+            .into(),
+            vec![Arc::new(id_col) as ArrayRef, Arc::new(text_col) as ArrayRef],
+        )
+        .unwrap();
+        let schema = batch.schema();
+        let batches = RecordBatchIterator::new(vec![batch].into_iter().map(Ok), schema.clone());
+        let mut dataset = Dataset::write(batches, tempdir.path().to_str().unwrap(), None)
+            .await
+            .unwrap();
+        dataset
+            .create_index(
+                &["text"],
+                IndexType::Inverted,
+                None,
+                &InvertedIndexParams::default(),
+                true,
+            )
+            .await
+            .unwrap();
+
+        async fn search(dataset: &Dataset, query: impl Into<FtsQuery>) -> Vec<i32> {
+            let results = dataset
+                .scan()
+                .full_text_search(FullTextSearchQuery::new_query(query.into()))
+                .unwrap()
+                .try_into_batch()
+                .await
+                .unwrap();
+            let mut ids = results["id"].as_primitive::<Int32Type>().values().to_vec();
+            ids.sort();
+            ids
+        }
+
+        // the patterns are case insensitive because the tokens are lower cased
+        assert_eq!(
+            search(&dataset, PrefixQuery::new("SKU123".to_owned())).await,
+            vec![0, 1]
+        );
+        assert_eq!(
+            search(
+                &dataset,
+                PrefixQuery::new("sku".to_owned()).with_max_expansions(1)
+            )
+            .await,
+            vec![0]
+        );
+        assert_eq!(
+            search(&dataset, WildcardQuery::new("sku*99".to_owned())).await,
+            vec![1]
+        );
+        assert_eq!(
+            search(&dataset, WildcardQuery::new("sku4567?".to_owned())).await,
+            vec![2]
+        );
+        assert_eq!(
+            search(&dataset, RegexQuery::new("sku[0-9]{3}45".to_owned())).await,
+            vec![0]
+        );
+        assert!(search(&dataset, RegexQuery::new("sku[0-9]{3}".to_owned()))
+            .await
+            .is_empty());
+        let query = BooleanQuery::new([
+            (Occur::Must, PrefixQuery::new("sku".to_owned()).into()),
+            (Occur::Must, MatchQuery::new("blue".to_owned()).into()),
+        ]);
+        assert_eq!(search(&dataset, query).await, vec![1]);
+
+        // the unindexed documents are matched by the pattern too
+        let id_col = Int32Array::from(vec![3]);
+        let text_col = GenericStringArray::<i32>::from(vec!["SKU12377 yellow shirt"]);
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(id_col) as ArrayRef, Arc::new(text_col) as ArrayRef],
+        )
+        .unwrap();
+        let batches = RecordBatchIterator::new(vec![batch].into_iter().map(Ok), schema);
+        dataset.append(batches, None).await.unwrap();
+        assert_eq!(
+            search(&dataset, PrefixQuery::new("sku123".to_owned())).await,
+            vec![0, 1, 3]
+        );
+        // the tokens of the unindexed documents count towards the max expansions
+        assert_eq!(
+            search(
+                &dataset,
+                PrefixQuery::new("sku".to_owned()).with_max_expansions(1)
+            )
+            .await,
+            vec![0]
+        );
+        assert_eq!(
+            search(
+                &dataset,
+                PrefixQuery::new("sku".to_owned()).with_max_expansions(4)
+            )
+            .await,
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(
+            search(
+                &dataset,
+                PrefixQuery::new("sku".to_owned()).with_max_expansions(3)
+            )
+            .await,
+            vec![0, 1, 2]
+        );
+
+        let results = dataset
+            .scan()
+            .full_text_search(
+                FullTextSearchQuery::new_query(PrefixQuery::new("sku4".to_owned()).into())
+                    .highlight(HighlightParams::default()),
+            )
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        assert_eq!(
+            results[HIGHLIGHT_COL].as_string::<i32>().value(0),
+            "<em>SKU45678</em> green hat"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fts_query_string() {
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let id_col = Int32Array::from(vec![0, 1, 2, 3]);
+        let title_col = GenericStringArray::<i32>::from(vec![
+            "rust database",
+            "python database",
+            "rust tutorial",
+            "lance format",
+        ]);
+        let content_col = GenericStringArray::<i32>::from(vec![
+            "a columnar format written in rust",
+            "a python tutorial about columnar data",
+            "learn rust with a deprecated tutorial",
+            "columnar format for machine learning",
+        ]);
+        let batch = RecordBatch::try_new(
+            arrow_schema::Schema::new(vec![
+                arrow_schema::Field::new("id", DataType::Int32, false),
+                arrow_schema::Field::new("title", title_col.data_type().to_owned(), false),
+                arrow_schema::Field::new("content", content_col.data_type().to_owned(), false),
+            ])
+            .into(),
+            vec![
+                Arc::new(id_col) as ArrayRef,
+                Arc::new(title_col) as ArrayRef,
+                Arc::new(content_col) as ArrayRef,
+            ],
+        )
+        .unwrap();
+        let schema = batch.schema();
+        let batches = RecordBatchIterator::new(vec![batch].into_iter().map(Ok), schema);
+        let mut dataset = Dataset::write(batches, tempdir.path().to_str().unwrap(), None)
+            .await
+            .unwrap();
+        for column in ["title", "content"] {
+            dataset
+                .create_index(
+                    &[column],
+                    IndexType::Inverted,
+                    None,
+                    &InvertedIndexParams::default().with_position(true),
+                    true,
+                )
+                .await
+                .unwrap();
+        }
+
+        async fn search(dataset: &Dataset, query: &str) -> Vec<i32> {
+            let results = dataset
+                .scan()
+                .full_text_search(FullTextSearchQuery::parse(query).unwrap())
+                .unwrap()
+                .try_into_batch()
+                .await
+                .unwrap();
+            results["id"].as_primitive::<Int32Type>().values().to_vec()
+        }
+        fn sorted(mut ids: Vec<i32>) -> Vec<i32> {
+            ids.sort();
+            ids
+        }
+
+        assert_eq!(sorted(search(&dataset, "title:rust").await), vec![0, 2]);
+        assert_eq!(
+            sorted(search(&dataset, "title:database OR content:machine").await),
+            vec![0, 1, 3]
+        );
+        assert_eq!(
+            sorted(search(&dataset, "+title:rust -content:deprecated").await),
+            vec![0]
+        );
+        assert_eq!(
+            sorted(search(&dataset, "columnar AND NOT python").await),
+            vec![0, 3]
+        );
+        assert_eq!(
+            search(&dataset, "content:\"columnar format\"").await.len(),
+            2
+        );
+        assert_eq!(
+            sorted(search(&dataset, "title:(rust OR lance) AND content:format").await),
+            vec![0, 3]
+        );
+        assert_eq!(sorted(search(&dataset, "title:pyth*").await), vec![1]);
+
+        // the boost of a prohibited clause doesn't keep its documents
+        assert_eq!(
+            search(&dataset, "title:rust -title:tutorial^0.5").await,
+            vec![0]
+        );
+
+        assert!(FullTextSearchQuery::parse("title:(rust").is_err());
+    }
+
     #[tokio::test]
     async fn test_fts_unindexed_data() {
         let tempdir = tempfile::tempdir().unwrap();
@@ -6168,6 +7261,7 @@ mod tests {
             file_major_version: 2,
             file_minor_version: 0,
             file_size_bytes: CachedFileSize::unknown(),
+            column_stats: vec![],
         };
 
         let dataset = Dataset::commit(
@@ -6222,6 +7316,7 @@ mod tests {
             file_major_version: 2,
             file_minor_version: 0,
             file_size_bytes: CachedFileSize::unknown(),
+            column_stats: vec![],
         };
 
         let dataset = Dataset::commit(
@@ -6320,6 +7415,7 @@ mod tests {
             file_major_version: 2,
             file_minor_version: 0,
             file_size_bytes: CachedFileSize::unknown(),
+            column_stats: vec![],
         };
 
         let new_data_file = DataFile {
@@ -2,6 +2,7 @@
 // SPDX-FileCopyrightText: Copyright The Lance Authors
 use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use chrono::{DateTime, Utc};
 use lance_file::datatypes::populate_schema_dictionary;
 use lance_io::object_store::{
     ObjectStore, ObjectStoreParams, StorageOptions, DEFAULT_CLOUD_IO_PARALLELISM,
@@ -16,7 +17,7 @@ use snafu::location;
 use tracing::instrument;
 use url::Url;
 
-use super::refs::{Ref, Tags};
+use super::refs::{branch_base_path, resolve_version_as_of, Branches, Ref, Tags, MAIN_BRANCH};
 use super::{ReadParams, WriteParams, DEFAULT_INDEX_CACHE_SIZE, DEFAULT_METADATA_CACHE_SIZE};
 use crate::{
     error::{Error, Result},
@@ -91,6 +92,19 @@ impl DatasetBuilder {
         self
     }
 
+    /// Sets `version` for the builder to the latest version of a branch
+    pub fn with_branch(mut self, branch: &str) -> Self {
+        self.version = Some(Ref::Branch(branch.to_string()));
+        self
+    }
+
+    /// Sets `version` for the builder to the latest version committed at or
+    /// before `timestamp`
+    pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.version = Some(Ref::Timestamp(timestamp));
+        self
+    }
+
     pub fn with_commit_handler(mut self, commit_handler: Arc<dyn CommitHandler>) -> Self {
         self.commit_handler = Some(commit_handler);
         self
@@ -269,6 +283,7 @@ impl DatasetBuilder {
         };
 
         let mut version: Option<u64> = None;
+        let mut branch: Option<String> = None;
         let cloned_ref = self.version.clone();
         let table_uri = self.table_uri.clone();
 
@@ -289,12 +304,33 @@ impl DatasetBuilder {
                     );
                     Some(tags.get_version(t.as_str()).await?)
                 }
+                Ref::Branch(b) if b == MAIN_BRANCH => None,
+                Ref::Branch(b) => {
+                    let branches = Branches::new(
+                        object_store.clone(),
+                        commit_handler.clone(),
+                        base_path.clone(),
+                    );
+                    branches.get(b.as_str()).await?;
+                    branch = Some(b);
+                    None
+                }
+                Ref::Timestamp(timestamp) => Some(
+                    resolve_version_as_of(
+                        &object_store,
+                        commit_handler.as_ref(),
+                        &base_path,
+                        timestamp,
+                    )
+                    .await?,
+                ),
             }
         }
+        let manifest_base = branch_base_path(&base_path, branch.as_deref());
 
         let (manifest, location) = if let Some(mut manifest) = manifest {
             let location = commit_handler
-                .resolve_version_location(&base_path, manifest.version, &object_store.inner)
+                .resolve_version_location(&manifest_base, manifest.version, &object_store.inner)
                 .await?;
             if manifest.schema.has_dictionary_types() {
                 let reader = object_store.open(&location.path).await?;
@@ -305,11 +341,11 @@ impl DatasetBuilder {
             let manifest_location = match version {
                 Some(version) => {
                     commit_handler
-                        .resolve_version_location(&base_path, version, &object_store.inner)
+                        .resolve_version_location(&manifest_base, version, &object_store.inner)
                         .await?
                 }
                 None => commit_handler
-                    .resolve_latest_location(&base_path, &object_store)
+                    .resolve_latest_location(&manifest_base, &object_store)
                     .await
                     .map_err(|e| Error::DatasetNotFound {
                         source: Box::new(e),
@@ -321,7 +357,7 @@ impl DatasetBuilder {
             let manifest = Dataset::load_manifest(
                 &object_store,
                 &manifest_location,
-                &base_path,
+                &manifest_base,
                 session.as_ref(),
             )
             .await?;
@@ -336,6 +372,7 @@ impl DatasetBuilder {
             location,
             session,
             commit_handler,
+            branch,
         )
     }
 }
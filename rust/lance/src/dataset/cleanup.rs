@@ -61,7 +61,7 @@ use tracing::{info, instrument, Span};
 
 use crate::{utils::temporal::utc_now, Dataset};
 
-use super::refs::TagContents;
+use super::refs::{branch_base_path, TagContents};
 
 #[derive(Clone, Debug, Default)]
 struct ReferencedFiles {
@@ -140,7 +140,24 @@ impl<'a> CleanupTask<'a> {
         let tags = self.dataset.tags.list().await?;
         let tagged_versions: HashSet<u64> = tags.values().map(|v| v.version).collect();
 
-        let inspection = self.process_manifests(&tagged_versions).await?;
+        // Manifests of other branches (including main, when a branch is checked out)
+        // are never removed and everything they reference is kept.
+        let mut protected_bases = self
+            .dataset
+            .branches
+            .list()
+            .await?
+            .into_keys()
+            .filter(|branch| Some(branch.as_str()) != self.dataset.branch.as_deref())
+            .map(|branch| branch_base_path(&self.dataset.base, Some(&branch)))
+            .collect::<Vec<_>>();
+        if self.dataset.branch.is_some() {
+            protected_bases.push(self.dataset.base.clone());
+        }
+
+        let inspection = self
+            .process_manifests(&tagged_versions, &protected_bases)
+            .await?;
 
         if self.error_if_old_versions_tagged && !inspection.tagged_old_versions.is_empty() {
             return Err(tagged_old_versions_cleanup_error(
@@ -156,15 +173,29 @@ impl<'a> CleanupTask<'a> {
     async fn process_manifests(
         &'a self,
         tagged_versions: &HashSet<u64>,
+        protected_bases: &[Path],
     ) -> Result<CleanupInspection> {
         let inspection = Mutex::new(CleanupInspection::default());
         self.dataset
             .commit_handler
-            .list_manifest_locations(&self.dataset.base, &self.dataset.object_store, false)
+            .list_manifest_locations(
+                &self.dataset.manifest_base(),
+                &self.dataset.object_store,
+                false,
+            )
             .try_for_each_concurrent(self.dataset.object_store.io_parallelism(), |location| {
-                self.process_manifest_file(location, &inspection, tagged_versions)
+                self.process_manifest_file(location, &inspection, tagged_versions, false)
             })
             .await?;
+        for protected_base in protected_bases {
+            self.dataset
+                .commit_handler
+                .list_manifest_locations(protected_base, &self.dataset.object_store, false)
+                .try_for_each_concurrent(self.dataset.object_store.io_parallelism(), |location| {
+                    self.process_manifest_file(location, &inspection, tagged_versions, true)
+                })
+                .await?;
+        }
         Ok(inspection.into_inner().unwrap())
     }
 
@@ -173,6 +204,7 @@ impl<'a> CleanupTask<'a> {
         location: ManifestLocation,
         inspection: &Mutex<CleanupInspection>,
         tagged_versions: &HashSet<u64>,
+        protected: bool,
     ) -> Result<()> {
         // TODO: We can't cleanup invalid manifests.  There is no way to distinguish
         // between an invalid manifest and a temporary I/O error.  It's also not safe
@@ -182,16 +214,24 @@ impl<'a> CleanupTask<'a> {
 
         let manifest =
             read_manifest(&self.dataset.object_store, &location.path, location.size).await?;
+        let indexes =
+            read_manifest_indexes(&self.dataset.object_store, &location, &manifest).await?;
+
+        if protected {
+            let mut inspection = inspection.lock().unwrap();
+            return self.process_manifest(&manifest, &indexes, true, &mut inspection);
+        }
+
         let dataset_version = self.dataset.version().version;
 
         // Don't delete the latest version, even if it is old. Don't delete tagged versions,
         // regardless of age. Don't delete manifests if their version is newer than the dataset
         // version.  These are either in-progress or newly added since we started.
         let is_latest = dataset_version <= manifest.version;
-        let is_tagged = tagged_versions.contains(&manifest.version);
+        // Tags always point at versions of the main branch.
+        let is_tagged =
+            self.dataset.branch.is_none() && tagged_versions.contains(&manifest.version);
         let in_working_set = is_latest || manifest.timestamp() >= self.before || is_tagged;
-        let indexes =
-            read_manifest_indexes(&self.dataset.object_store, &location, &manifest).await?;
 
         let mut inspection = inspection.lock().unwrap();
 
@@ -0,0 +1,588 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Row-level change data feed between two versions of a dataset.
+//!
+//! The diff is computed from the stable row ids of the two versions: rows
+//! that are only live in the newer version were inserted and rows that are
+//! only live in the older version were deleted.  Lance implements updates by
+//! deleting the old row and writing a new one with a new row id.  The `Update`
+//! transactions committed in between record which rows their new rows replace,
+//! which is used to pair the old row with the row that replaced it.  Such a
+//! pair is reported as an `update_preimage` row (the old row id and values)
+//! immediately followed by an `update_postimage` row (the new row id and
+//! values).
+//!
+//! Updates that do not record the rows they replace, e.g. the upserts of a
+//! `merge_insert`, are reported as a delete and an insert.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use futures::{StreamExt, TryStreamExt};
+use lance_core::datatypes::Schema;
+use lance_core::ROW_ID;
+use lance_table::format::Fragment;
+use lance_table::io::manifest::read_manifest;
+use roaring::RoaringTreemap;
+use snafu::location;
+
+use super::fragment::FileFragment;
+use super::rowids::load_row_id_sequence;
+use super::transaction::{Operation, Transaction};
+use super::Dataset;
+use crate::io::commit::read_transaction_file;
+use crate::{Error, Result};
+
+/// Name of the column holding the [`ChangeType`] of each row in a diff.
+pub const CHANGE_TYPE_COLUMN: &str = "_change_type";
+
+const DIFF_BATCH_SIZE: usize = 1024;
+
+/// The kind of change a row in a diff represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeType {
+    Insert,
+    Delete,
+    /// The row as it was before an update.
+    UpdatePreimage,
+    /// The row as it is after an update.
+    UpdatePostimage,
+}
+
+impl ChangeType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Insert => "insert",
+            Self::Delete => "delete",
+            Self::UpdatePreimage => "update_preimage",
+            Self::UpdatePostimage => "update_postimage",
+        }
+    }
+}
+
+impl std::fmt::Display for ChangeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A change to a single row, identified by stable row ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowChange {
+    Delete(u64),
+    /// The row id before and after the update.
+    Update(u64, u64),
+    Insert(u64),
+}
+
+/// Row ids of the rows that are live in `fragments`.
+async fn live_row_ids<'a>(
+    dataset: &Dataset,
+    fragments: impl IntoIterator<Item = &'a Fragment>,
+) -> Result<RoaringTreemap> {
+    let mut row_ids = RoaringTreemap::new();
+    for fragment in fragments {
+        let sequence = load_row_id_sequence(dataset, fragment).await?;
+        let deletions = FileFragment::new(Arc::new(dataset.clone()), fragment.clone())
+            .get_deletion_vector()
+            .await?;
+        match deletions {
+            Some(deletions) => row_ids.extend(
+                sequence
+                    .iter()
+                    .enumerate()
+                    .filter(|(offset, _)| !deletions.contains(*offset as u32))
+                    .map(|(_, row_id)| row_id),
+            ),
+            None => row_ids.extend(sequence.iter()),
+        }
+    }
+    Ok(row_ids)
+}
+
+/// Map from each row replaced by an `Update` committed after `from`, up to
+/// and including `to_version`, to the row that replaced it.
+///
+/// Only the manifest and transaction file of each version in between are
+/// read; the versions are not checked out.
+async fn replaced_row_ids(
+    dataset: &Dataset,
+    from: &Dataset,
+    to_version: u64,
+) -> Result<HashMap<u64, u64>> {
+    let manifest_base = &dataset.manifest_base();
+    let object_store = dataset.object_store();
+    let mut versions = futures::stream::iter(from.manifest.version + 1..=to_version)
+        .map(|version| async move {
+            let location = dataset
+                .commit_handler
+                .resolve_version_location(manifest_base, version, &object_store.inner)
+                .await?;
+            let manifest = read_manifest(object_store, &location.path, location.size).await?;
+            let transaction = match &manifest.transaction_file {
+                Some(path) => Some(read_transaction_file(object_store, &dataset.base, path).await?),
+                None => None,
+            };
+            Result::Ok((manifest.next_row_id, transaction))
+        })
+        .buffered(object_store.io_parallelism())
+        .boxed();
+
+    let mut successors = HashMap::new();
+    let mut previous_next_row_id = from.manifest.next_row_id;
+    while let Some((next_row_id, transaction)) = versions.try_next().await? {
+        if let Some(Transaction {
+            operation:
+                Operation::Update {
+                    replaced_row_ids: Some(replaced_row_ids),
+                    ..
+                },
+            ..
+        }) = &transaction
+        {
+            // The new rows get the next row ids when the transaction is
+            // committed, in the order of the new fragments.
+            if next_row_id - previous_next_row_id == replaced_row_ids.len() {
+                successors.extend(
+                    replaced_row_ids
+                        .iter()
+                        .zip(previous_next_row_id..next_row_id),
+                );
+            }
+        }
+        previous_next_row_id = next_row_id;
+    }
+    Ok(successors)
+}
+
+/// The fragments of `dataset` that do not have the same rows in `other`.
+///
+/// A fragment with the same id, data files and deletion file in both versions
+/// has the same rows, so it cannot contribute changes.
+fn changed_fragments<'a>(
+    dataset: &'a Dataset,
+    other: &Dataset,
+) -> impl Iterator<Item = &'a Fragment> {
+    let other_fragments = other
+        .manifest
+        .fragments
+        .iter()
+        .map(|f| (f.id, (&f.files, &f.deletion_file)))
+        .collect::<HashMap<_, _>>();
+    dataset
+        .manifest
+        .fragments
+        .iter()
+        .filter(move |f| other_fragments.get(&f.id) != Some(&(&f.files, &f.deletion_file)))
+}
+
+/// The changes between the `deleted` and `inserted` rows, in row id order of
+/// the deleted rows followed by the inserted rows that are not the result of
+/// an update.
+///
+/// A deleted row is paired with an inserted row if following `successors`
+/// from the deleted row leads to it.  Rows that were updated and then deleted
+/// are reported as deleted, and rows that were inserted and then updated are
+/// reported as inserted.
+fn row_changes(
+    deleted: RoaringTreemap,
+    inserted: RoaringTreemap,
+    successors: HashMap<u64, u64>,
+) -> impl Iterator<Item = RowChange> + Send {
+    let last_successor = move |mut row_id: u64| {
+        while let Some(next) = successors.get(&row_id) {
+            row_id = *next;
+        }
+        row_id
+    };
+    let postimages = deleted
+        .iter()
+        .map(&last_successor)
+        .filter(|row_id| inserted.contains(*row_id))
+        .collect::<RoaringTreemap>();
+    let inserted = inserted - &postimages;
+    deleted
+        .into_iter()
+        .map(move |row_id| {
+            let last = last_successor(row_id);
+            if postimages.contains(last) {
+                RowChange::Update(row_id, last)
+            } else {
+                RowChange::Delete(row_id)
+            }
+        })
+        .chain(inserted.into_iter().map(RowChange::Insert))
+}
+
+async fn take_values(
+    dataset: &Dataset,
+    row_ids: &[u64],
+    projection: &Arc<Schema>,
+) -> Result<RecordBatch> {
+    if row_ids.is_empty() {
+        return Ok(RecordBatch::new_empty(Arc::new(ArrowSchema::from(
+            projection.as_ref(),
+        ))));
+    }
+    dataset.take_rows(row_ids, projection.clone()).await
+}
+
+/// Read the values of a chunk of changes, the old values from `from` and the
+/// new values from `to`, keeping the rows in the order of the changes.
+async fn read_changes(
+    from: &Dataset,
+    to: &Dataset,
+    from_projection: &Arc<Schema>,
+    to_projection: &Arc<Schema>,
+    schema: SchemaRef,
+    changes: Vec<RowChange>,
+) -> Result<RecordBatch> {
+    let mut row_ids = Vec::with_capacity(changes.len());
+    let mut change_types = Vec::with_capacity(changes.len());
+    // Each output row is taken from the old (0) or the new (1) values.
+    let mut indices = Vec::with_capacity(changes.len());
+    let mut from_row_ids = Vec::new();
+    let mut to_row_ids = Vec::new();
+    for change in changes {
+        let (before, after) = match change {
+            RowChange::Delete(row_id) => (Some((row_id, ChangeType::Delete)), None),
+            RowChange::Update(before, after) => (
+                Some((before, ChangeType::UpdatePreimage)),
+                Some((after, ChangeType::UpdatePostimage)),
+            ),
+            RowChange::Insert(row_id) => (None, Some((row_id, ChangeType::Insert))),
+        };
+        if let Some((row_id, change_type)) = before {
+            indices.push((0, from_row_ids.len()));
+            from_row_ids.push(row_id);
+            row_ids.push(row_id);
+            change_types.push(change_type);
+        }
+        if let Some((row_id, change_type)) = after {
+            indices.push((1, to_row_ids.len()));
+            to_row_ids.push(row_id);
+            row_ids.push(row_id);
+            change_types.push(change_type);
+        }
+    }
+
+    let from_values = take_values(from, &from_row_ids, from_projection).await?;
+    let to_values = take_values(to, &to_row_ids, to_projection).await?;
+
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(row_ids)),
+        Arc::new(StringArray::from_iter_values(
+            change_types.iter().map(|c| c.as_str()),
+        )),
+    ];
+    for (field, to_column) in to_values.schema().fields().iter().zip(to_values.columns()) {
+        let from_column =
+            from_values
+                .column_by_name(field.name())
+                .ok_or_else(|| Error::Internal {
+                    message: format!("column {} is missing from the old values", field.name()),
+                    location: location!(),
+                })?;
+        columns.push(arrow_select::interleave::interleave(
+            &[from_column.as_ref(), to_column.as_ref()],
+            &indices,
+        )?);
+    }
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+impl Dataset {
+    /// Compute the row-level changes between two versions of the dataset.
+    ///
+    /// Returns a stream of the changed rows, each with its stable row id in
+    /// `_rowid` and a [`ChangeType`] in [`CHANGE_TYPE_COLUMN`].  Deleted rows
+    /// and update preimages come first, in row id order and with the values
+    /// they had in `from_version`.  Each update preimage is immediately
+    /// followed by its update postimage, with the values in `to_version`.
+    /// Inserted rows come last.  Only the columns present in both versions are
+    /// returned.
+    ///
+    /// The changed rows are read lazily as the stream is polled.
+    ///
+    /// Both versions must belong to the checked out branch, and the dataset must
+    /// use stable row ids.
+    pub async fn diff(
+        &self,
+        from_version: u64,
+        to_version: u64,
+    ) -> Result<SendableRecordBatchStream> {
+        if from_version > to_version {
+            return Err(Error::invalid_input(
+                format!(
+                    "from_version ({}) must not be greater than to_version ({})",
+                    from_version, to_version
+                ),
+                location!(),
+            ));
+        }
+        let from = Arc::new(self.checkout_version(from_version).await?);
+        let to = Arc::new(self.checkout_version(to_version).await?);
+        if !from.manifest.uses_move_stable_row_ids() {
+            return Err(Error::NotSupported {
+                source: "diff requires a dataset with stable row ids".into(),
+                location: location!(),
+            });
+        }
+
+        let from_rows = live_row_ids(&from, changed_fragments(&from, &to)).await?;
+        let to_rows = live_row_ids(&to, changed_fragments(&to, &from)).await?;
+        let deleted = &from_rows - &to_rows;
+        let inserted = &to_rows - &from_rows;
+        let successors = if deleted.is_empty() || inserted.is_empty() {
+            // There is no update to pair
+            HashMap::new()
+        } else {
+            replaced_row_ids(self, &from, to_version).await?
+        };
+        let changes = row_changes(deleted, inserted, successors);
+
+        // Only the columns that exist in both versions can be returned for all rows.
+        let columns = to
+            .schema()
+            .fields
+            .iter()
+            .filter(|f| {
+                from.schema()
+                    .field(&f.name)
+                    .is_some_and(|from_field| from_field.data_type() == f.data_type())
+            })
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        let from_projection = Arc::new(from.schema().project(&columns)?);
+        let to_projection = Arc::new(to.schema().project(&columns)?);
+        let mut fields = vec![
+            ArrowField::new(ROW_ID, DataType::UInt64, false),
+            ArrowField::new(CHANGE_TYPE_COLUMN, DataType::Utf8, false),
+        ];
+        fields.extend(
+            ArrowSchema::from(to_projection.as_ref())
+                .fields
+                .iter()
+                .map(|f| f.as_ref().clone()),
+        );
+        let output_schema: SchemaRef = Arc::new(ArrowSchema::new(fields));
+
+        let schema = output_schema.clone();
+        let stream = futures::stream::iter(changes)
+            .chunks(DIFF_BATCH_SIZE)
+            .map(move |changes| {
+                let from = from.clone();
+                let to = to.clone();
+                let from_projection = from_projection.clone();
+                let to_projection = to_projection.clone();
+                let schema = schema.clone();
+                async move {
+                    read_changes(
+                        &from,
+                        &to,
+                        &from_projection,
+                        &to_projection,
+                        schema,
+                        changes,
+                    )
+                    .await
+                }
+            })
+            .buffered(self.object_store.io_parallelism())
+            .map_err(|e: Error| datafusion::error::DataFusionError::External(Box::new(e)));
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            output_schema,
+            stream.boxed(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{cast::AsArray, types::UInt64Type, RecordBatchIterator, UInt32Array};
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::dataset::{UpdateBuilder, WriteParams};
+
+    fn make_reader(
+        range: std::ops::Range<u32>,
+    ) -> impl arrow_array::RecordBatchReader + Send + 'static {
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "i",
+            DataType::UInt32,
+            false,
+        )]));
+        let data = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(UInt32Array::from_iter_values(range))],
+        )
+        .unwrap();
+        RecordBatchIterator::new(vec![Ok(data)], schema)
+    }
+
+    /// Collect a diff as a list of (row id, change type, value of `i`).
+    async fn collect_diff(dataset: &Dataset, from: u64, to: u64) -> Vec<(u64, String, u32)> {
+        let batches = dataset
+            .diff(from, to)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let mut changes = Vec::new();
+        for batch in batches {
+            assert_eq!(batch.schema().field(1).name(), CHANGE_TYPE_COLUMN);
+            let row_ids = batch[ROW_ID].as_primitive::<UInt64Type>();
+            let change_types = batch[CHANGE_TYPE_COLUMN].as_string::<i32>();
+            let values = batch["i"].as_primitive::<arrow_array::types::UInt32Type>();
+            for idx in 0..batch.num_rows() {
+                changes.push((
+                    row_ids.value(idx),
+                    change_types.value(idx).to_string(),
+                    values.value(idx),
+                ));
+            }
+        }
+        changes
+    }
+
+    #[tokio::test]
+    async fn test_diff() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let params = WriteParams {
+            enable_move_stable_row_ids: true,
+            ..Default::default()
+        };
+        let mut dataset = Dataset::write(make_reader(0..10), test_uri, Some(params))
+            .await
+            .unwrap();
+        dataset.append(make_reader(10..15), None).await.unwrap();
+        dataset.delete("i < 2").await.unwrap();
+        let dataset = UpdateBuilder::new(Arc::new(dataset))
+            .update_where("i = 5")
+            .unwrap()
+            .set("i", "i + 100")
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute()
+            .await
+            .unwrap()
+            .new_dataset;
+        assert_eq!(dataset.manifest.version, 4);
+
+        let changes = collect_diff(&dataset, 1, 4).await;
+        let mut expected = vec![
+            (0, "delete".to_string(), 0),
+            (1, "delete".to_string(), 1),
+            (5, "update_preimage".to_string(), 5),
+            (15, "update_postimage".to_string(), 105),
+        ];
+        expected.extend((10..15).map(|row_id| (row_id, "insert".to_string(), row_id as u32)));
+        assert_eq!(changes, expected);
+
+        // Nothing changes between a version and itself.
+        assert!(collect_diff(&dataset, 3, 3).await.is_empty());
+        // Only the update happened after version 3.
+        let changes = collect_diff(&dataset, 3, 4).await;
+        assert_eq!(
+            changes,
+            vec![
+                (5, "update_preimage".to_string(), 5),
+                (15, "update_postimage".to_string(), 105),
+            ]
+        );
+
+        // A row that is updated and then deleted is reported as deleted.
+        let dataset = UpdateBuilder::new(dataset)
+            .update_where("i = 3")
+            .unwrap()
+            .set("i", "i + 100")
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute()
+            .await
+            .unwrap()
+            .new_dataset;
+        let mut dataset = (*dataset).clone();
+        dataset.delete("i = 103").await.unwrap();
+        assert_eq!(dataset.manifest.version, 6);
+        let changes = collect_diff(&dataset, 4, 6).await;
+        assert_eq!(changes, vec![(3, "delete".to_string(), 3)]);
+        let changes = collect_diff(&dataset, 5, 6).await;
+        assert_eq!(changes, vec![(16, "delete".to_string(), 103)]);
+
+        let err = dataset.diff(4, 1).await.err().unwrap();
+        assert!(matches!(err, Error::InvalidInput { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_changed_fragments() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let params = WriteParams {
+            enable_move_stable_row_ids: true,
+            ..Default::default()
+        };
+        let mut dataset = Dataset::write(make_reader(0..10), test_uri, Some(params))
+            .await
+            .unwrap();
+        dataset.append(make_reader(10..15), None).await.unwrap();
+        dataset.delete("i < 2").await.unwrap();
+        let v1 = dataset.checkout_version(1).await.unwrap();
+        let v2 = dataset.checkout_version(2).await.unwrap();
+
+        let changed = |dataset: &Dataset, other: &Dataset| {
+            changed_fragments(dataset, other)
+                .map(|f| f.id)
+                .collect::<Vec<_>>()
+        };
+        // The appended fragment only exists in version 2.
+        assert_eq!(changed(&v2, &v1), vec![1]);
+        assert!(changed(&v1, &v2).is_empty());
+        // The delete only changed the deletion file of the first fragment.
+        assert_eq!(changed(&dataset, &v2), vec![0]);
+        assert_eq!(changed(&v2, &dataset), vec![0]);
+        assert!(changed(&dataset, &dataset).is_empty());
+    }
+
+    #[test]
+    fn test_row_changes() {
+        let deleted = RoaringTreemap::from_iter([0, 3, 5]);
+        let inserted = RoaringTreemap::from_iter([16, 20]);
+        // 5 was updated twice, to 15 and then to 16. 3 was updated to 17,
+        // which was then deleted. 20 was inserted as 18 and then updated.
+        let successors = HashMap::from([(5, 15), (15, 16), (3, 17), (18, 20)]);
+        let changes = row_changes(deleted, inserted, successors).collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                RowChange::Delete(0),
+                RowChange::Delete(3),
+                RowChange::Update(5, 16),
+                RowChange::Insert(20),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diff_requires_stable_row_ids() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = Dataset::write(make_reader(0..10), test_uri, None)
+            .await
+            .unwrap();
+        dataset.append(make_reader(10..15), None).await.unwrap();
+        let err = dataset.diff(1, 2).await.err().unwrap();
+        assert!(matches!(err, Error::NotSupported { .. }));
+    }
+}
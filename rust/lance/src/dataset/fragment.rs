@@ -614,6 +614,10 @@ pub struct FragReadConfig {
     ///
     /// operation_priority: u32 | reader_priority: u32 | file_position: u64
     pub reader_priority: Option<u32>,
+    /// Read string and binary columns as `Utf8View` / `BinaryView`
+    ///
+    /// Ignored for legacy (v1) files, which are always read with offset based types.
+    pub use_view_types: bool,
 }
 
 impl FragReadConfig {
@@ -636,6 +640,11 @@ impl FragReadConfig {
         self.reader_priority = Some(value);
         self
     }
+
+    pub fn with_view_types(mut self, value: bool) -> Self {
+        self.use_view_types = value;
+        self
+    }
 }
 
 impl FileFragment {
@@ -823,8 +832,11 @@ impl FileFragment {
     pub async fn open(
         &self,
         projection: &Schema,
-        read_config: FragReadConfig,
+        mut read_config: FragReadConfig,
     ) -> Result<FragmentReader> {
+        if self.dataset.is_legacy_storage() {
+            read_config.use_view_types = false;
+        }
         let open_files = self.open_readers(projection, &read_config);
         let deletion_vec_load = self.get_deletion_vector();
 
@@ -855,12 +867,17 @@ impl FileFragment {
 
         let num_physical_rows = self.physical_rows().await?;
 
+        let output_schema = if read_config.use_view_types {
+            ArrowSchema::from(&projection.with_view_types())
+        } else {
+            ArrowSchema::from(projection)
+        };
         let mut reader = FragmentReader::try_new(
             self.id(),
             deletion_vec,
             row_id_sequence,
             opened_files,
-            ArrowSchema::from(projection),
+            output_schema,
             self.count_rows(None).await?,
             num_physical_rows,
         )?;
@@ -890,7 +907,11 @@ impl FileFragment {
         let data_file_schema = data_file.schema(full_schema);
         let projection = projection.unwrap_or(full_schema);
         // Also remove any fields that are not part of the user's provided projection
-        let schema_per_file = Arc::new(projection.intersection_ignore_types(&data_file_schema)?);
+        let mut schema_per_file = projection.intersection_ignore_types(&data_file_schema)?;
+        if read_config.use_view_types && !data_file.is_legacy_file() {
+            schema_per_file = schema_per_file.with_view_types();
+        }
+        let schema_per_file = Arc::new(schema_per_file);
 
         if data_file.is_legacy_file() {
             let max_field_id = data_file.fields.iter().max().unwrap();
@@ -1006,7 +1027,10 @@ impl FileFragment {
         let mut missing_fields = projection.field_ids();
         missing_fields.retain(|f| !field_ids_in_files.contains(f) && *f >= 0);
         if !missing_fields.is_empty() {
-            let missing_projection = projection.project_by_ids(&missing_fields, true);
+            let mut missing_projection = projection.project_by_ids(&missing_fields, true);
+            if read_config.use_view_types {
+                missing_projection = missing_projection.with_view_types();
+            }
             let null_reader = NullReader::new(Arc::new(missing_projection), num_rows as u32);
             opened_files.push(Box::new(null_reader));
         }
@@ -19,7 +19,7 @@ use std::borrow::Cow;
 use uuid::Uuid;
 
 use crate::dataset::builder::DatasetBuilder;
-use crate::dataset::write::do_write_fragments;
+use crate::dataset::write::{do_write_fragments, with_write_checks};
 use crate::dataset::{WriteMode, WriteParams, DATA_DIR};
 use crate::Result;
 
@@ -160,6 +160,7 @@ impl<'a> FragmentCreateBuilder<'a> {
             &params.store_params.clone().unwrap_or_default(),
         )
         .await?;
+        let stream = with_write_checks(&schema, stream);
         do_write_fragments(
             object_store,
             &base_path,
@@ -0,0 +1,293 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Rerankers that fuse the results of a vector search and a full text search.
+//!
+//! A hybrid query (see [`crate::dataset::scanner::Scanner::hybrid`]) runs both searches
+//! and merges their results into one batch, with a row for each document found by either
+//! search. The [`Reranker`] then computes the `_relevance_score` of each row.
+
+use std::sync::Arc;
+
+use arrow::array::AsArray;
+use arrow::datatypes::Float32Type;
+use arrow_array::{Array, Float32Array, RecordBatch};
+use lance_index::scalar::inverted::SCORE_COL;
+use lance_index::vector::DIST_COL;
+use snafu::location;
+
+use crate::{Error, Result};
+
+pub const RELEVANCE_SCORE_COL: &str = "_relevance_score";
+
+/// The default `k` of [`RRFReranker`].
+pub const DEFAULT_RRF_K: f32 = 60.0;
+
+/// The default weight of the vector search in [`LinearCombinationReranker`].
+pub const DEFAULT_VECTOR_WEIGHT: f32 = 0.7;
+
+/// Fuses the results of a vector search and a full text search.
+pub trait Reranker: std::fmt::Debug + Send + Sync {
+    /// Computes the relevance score of each row, a higher score is more relevant.
+    ///
+    /// The batch has the `_rowid`, `_distance` and `_score` columns. `_distance` is null
+    /// for the documents not found by the vector search, and `_score` is null for the
+    /// documents not found by the full text search.
+    fn rerank(&self, results: &RecordBatch) -> Result<Float32Array>;
+}
+
+fn column<'a>(results: &'a RecordBatch, name: &str) -> Result<&'a Float32Array> {
+    results
+        .column_by_name(name)
+        .and_then(|col| col.as_primitive_opt::<Float32Type>())
+        .ok_or_else(|| Error::Internal {
+            message: format!("the results to rerank must have a float32 {} column", name),
+            location: location!(),
+        })
+}
+
+// the 1-based rank of each non-null value
+fn ranks(values: &Float32Array, descending: bool) -> Vec<Option<usize>> {
+    let mut indices = (0..values.len())
+        .filter(|&i| values.is_valid(i))
+        .collect::<Vec<_>>();
+    indices.sort_by(|&a, &b| {
+        let ord = values.value(a).total_cmp(&values.value(b));
+        if descending {
+            ord.reverse()
+        } else {
+            ord
+        }
+    });
+    let mut ranks = vec![None; values.len()];
+    for (rank, i) in indices.into_iter().enumerate() {
+        ranks[i] = Some(rank + 1);
+    }
+    ranks
+}
+
+// scale the non-null values into [0, 1], all values are `tied` if they are the same
+fn min_max_normalize(values: &Float32Array, tied: f32) -> Vec<Option<f32>> {
+    let (min, max) = values
+        .iter()
+        .flatten()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+    let range = max - min;
+    values
+        .iter()
+        .map(|v| {
+            v.map(|v| match range > 0.0 {
+                true => (v - min) / range,
+                false => tied,
+            })
+        })
+        .collect()
+}
+
+/// Reciprocal rank fusion, the relevance score is `sum(1 / (k + rank))` over the searches
+/// that found the document.
+#[derive(Debug, Clone)]
+pub struct RRFReranker {
+    k: f32,
+}
+
+impl Default for RRFReranker {
+    fn default() -> Self {
+        Self { k: DEFAULT_RRF_K }
+    }
+}
+
+impl RRFReranker {
+    pub fn new(k: f32) -> Self {
+        Self { k }
+    }
+}
+
+impl Reranker for RRFReranker {
+    fn rerank(&self, results: &RecordBatch) -> Result<Float32Array> {
+        let vector_ranks = ranks(column(results, DIST_COL)?, false);
+        let fts_ranks = ranks(column(results, SCORE_COL)?, true);
+        Ok(vector_ranks
+            .into_iter()
+            .zip(fts_ranks)
+            .map(|(vector_rank, fts_rank)| {
+                [vector_rank, fts_rank]
+                    .into_iter()
+                    .flatten()
+                    .map(|rank| 1.0 / (self.k + rank as f32))
+                    .sum::<f32>()
+            })
+            .collect())
+    }
+}
+
+/// Weighted linear combination of the normalized scores of both searches.
+///
+/// The distances and the full text scores are min-max normalized into `[0, 1]`, and the
+/// distances are converted to similarities by `1 - distance`. A document that is not found
+/// by a search gets 0 from it.
+#[derive(Debug, Clone)]
+pub struct LinearCombinationReranker {
+    vector_weight: f32,
+}
+
+impl Default for LinearCombinationReranker {
+    fn default() -> Self {
+        Self {
+            vector_weight: DEFAULT_VECTOR_WEIGHT,
+        }
+    }
+}
+
+impl LinearCombinationReranker {
+    /// Create a reranker that weights the vector search by `vector_weight`,
+    /// and the full text search by `1 - vector_weight`.
+    pub fn try_new(vector_weight: f32) -> Result<Self> {
+        if !(0.0..=1.0).contains(&vector_weight) {
+            return Err(Error::invalid_input(
+                format!(
+                    "vector_weight must be in the range [0, 1], got {}",
+                    vector_weight
+                ),
+                location!(),
+            ));
+        }
+        Ok(Self { vector_weight })
+    }
+}
+
+impl Reranker for LinearCombinationReranker {
+    fn rerank(&self, results: &RecordBatch) -> Result<Float32Array> {
+        // Equal distances are all the best match, as are equal scores
+        let vector_scores = min_max_normalize(column(results, DIST_COL)?, 0.0);
+        let fts_scores = min_max_normalize(column(results, SCORE_COL)?, 1.0);
+        Ok(vector_scores
+            .into_iter()
+            .zip(fts_scores)
+            .map(|(dist, score)| {
+                self.vector_weight * dist.map(|dist| 1.0 - dist).unwrap_or_default()
+                    + (1.0 - self.vector_weight) * score.unwrap_or_default()
+            })
+            .collect())
+    }
+}
+
+pub type RerankFunc = Arc<dyn Fn(&RecordBatch) -> Result<Float32Array> + Send + Sync>;
+
+/// Reranks the results with a user-supplied function over the batch of results.
+#[derive(Clone)]
+pub struct UdfReranker {
+    name: String,
+    func: RerankFunc,
+}
+
+impl UdfReranker {
+    pub fn new(name: impl Into<String>, func: RerankFunc) -> Self {
+        Self {
+            name: name.into(),
+            func,
+        }
+    }
+}
+
+impl std::fmt::Debug for UdfReranker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UdfReranker")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl Reranker for UdfReranker {
+    fn rerank(&self, results: &RecordBatch) -> Result<Float32Array> {
+        (self.func)(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::UInt64Array;
+    use lance_core::ROW_ID;
+
+    fn results() -> RecordBatch {
+        RecordBatch::try_from_iter(vec![
+            (ROW_ID, Arc::new(UInt64Array::from(vec![1, 2, 3, 4])) as _),
+            (
+                DIST_COL,
+                Arc::new(Float32Array::from(vec![
+                    Some(0.1),
+                    Some(0.5),
+                    None,
+                    Some(0.3),
+                ])) as _,
+            ),
+            (
+                SCORE_COL,
+                Arc::new(Float32Array::from(vec![
+                    None,
+                    Some(2.0),
+                    Some(4.0),
+                    Some(1.0),
+                ])) as _,
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rrf_reranker() {
+        let scores = RRFReranker::new(1.0).rerank(&results()).unwrap();
+        let expected = [
+            1.0 / 2.0,
+            1.0 / 4.0 + 1.0 / 3.0,
+            1.0 / 2.0,
+            1.0 / 3.0 + 1.0 / 4.0,
+        ];
+        for (score, expected) in scores.values().iter().zip(expected) {
+            assert!((score - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_linear_combination_reranker() {
+        let scores = LinearCombinationReranker::try_new(0.5)
+            .unwrap()
+            .rerank(&results())
+            .unwrap();
+        let expected = [0.5, 0.5 * 0.0 + 0.5 / 3.0, 0.5, 0.5 * 0.5 + 0.0];
+        for (score, expected) in scores.values().iter().zip(expected) {
+            assert!((score - expected).abs() < 1e-6);
+        }
+
+        assert!(LinearCombinationReranker::try_new(1.5).is_err());
+    }
+
+    #[test]
+    fn test_linear_combination_single_hit() {
+        // A single vector hit and a single full text hit are the best match of their search
+        let results = RecordBatch::try_from_iter(vec![
+            (ROW_ID, Arc::new(UInt64Array::from(vec![1, 2])) as _),
+            (
+                DIST_COL,
+                Arc::new(Float32Array::from(vec![Some(0.7), None])) as _,
+            ),
+            (
+                SCORE_COL,
+                Arc::new(Float32Array::from(vec![None, Some(3.0)])) as _,
+            ),
+        ])
+        .unwrap();
+        let scores = LinearCombinationReranker::try_new(0.7)
+            .unwrap()
+            .rerank(&results)
+            .unwrap();
+        let expected = [0.7, 0.3];
+        for (score, expected) in scores.values().iter().zip(expected) {
+            assert!((score - expected).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,977 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Merge two divergent versions of a dataset.
+//!
+//! Given a common ancestor (`base`) and two versions derived from it (`ours`, the
+//! latest version of the checked out branch, and `theirs`), the fragment-level
+//! differences of `theirs` are replayed on top of `ours`:
+//!
+//! * Fragments appended in `theirs` are appended, with new fragment ids (and new
+//!   row ids, if stable row ids are enabled).
+//! * Fragments removed in `theirs` (deleted or rewritten) are removed.
+//! * Deletion files are combined.  If both sides deleted rows from the same
+//!   fragment, a new deletion file with the union of both is written.  Rows
+//!   that were updated on both sides are a conflict, since each side appended
+//!   its own new version of them.
+//! * Data files added or replaced in `theirs` (for example by `DataReplacement`
+//!   or `Merge`) are carried over, together with any new fields.
+//!
+//! Changes that cannot be reconciled are reported as [`MergeConflict`]s and
+//! nothing is committed.  Indices and config changes made in `theirs` are not
+//! merged; fragments coming from `theirs` are left unindexed, and so are the
+//! fragments in which `theirs` replaced the data of an indexed field.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use lance_core::datatypes::{Field, Schema};
+use lance_core::utils::deletion::DeletionVector;
+use lance_table::format::{DataFile, Fragment, Manifest, RowIdMeta};
+use lance_table::io::deletion::{read_deletion_file, write_deletion_file};
+use lance_table::rowids::{write_row_ids, RowIdSequence};
+use roaring::RoaringBitmap;
+use snafu::location;
+
+use super::rowids::load_row_id_sequence;
+use super::transaction::{Operation, Transaction};
+use super::Dataset;
+use crate::{Error, Result};
+
+/// Which side of a merge made a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeSide {
+    /// The version being merged into.
+    Ours,
+    /// The version being merged.
+    Theirs,
+}
+
+/// A change that could not be reconciled while merging two versions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeConflict {
+    /// One side removed a fragment (by deleting all of its rows or rewriting
+    /// it) while the other side modified it.
+    FragmentRemovedAndModified {
+        fragment_id: u64,
+        removed_by: MergeSide,
+    },
+    /// Both sides rewrote the data of the same fields of a fragment.
+    FieldsModifiedOnBothSides {
+        fragment_id: u64,
+        field_ids: Vec<i32>,
+    },
+    /// Both sides updated rows of a fragment, so each side deleted them and
+    /// appended its own new version.  Keeping both would duplicate the rows.
+    RowsUpdatedOnBothSides { fragment_id: u64, num_rows: u64 },
+    /// The schemas diverged in a way that cannot be combined, e.g. both sides
+    /// added a field with the same name or one side dropped a field the other
+    /// changed.
+    SchemaConflict { field: String, message: String },
+}
+
+impl std::fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FragmentRemovedAndModified {
+                fragment_id,
+                removed_by,
+            } => write!(
+                f,
+                "fragment {} was removed by {:?} and modified by the other side",
+                fragment_id, removed_by
+            ),
+            Self::FieldsModifiedOnBothSides {
+                fragment_id,
+                field_ids,
+            } => write!(
+                f,
+                "fields {:?} of fragment {} were modified on both sides",
+                field_ids, fragment_id
+            ),
+            Self::RowsUpdatedOnBothSides {
+                fragment_id,
+                num_rows,
+            } => write!(
+                f,
+                "{} rows of fragment {} were updated on both sides",
+                num_rows, fragment_id
+            ),
+            Self::SchemaConflict { field, message } => {
+                write!(f, "field {}: {}", field, message)
+            }
+        }
+    }
+}
+
+/// Statistics about a successful merge.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeVersionsStats {
+    /// The version that was committed.
+    pub version: u64,
+    /// Fragments appended from `theirs`.
+    pub fragments_added: usize,
+    /// Fragments removed because `theirs` removed them.
+    pub fragments_removed: usize,
+    /// Fragments whose deletion or data files changed.
+    pub fragments_updated: usize,
+    /// Top-level fields added from `theirs`.
+    pub fields_added: usize,
+}
+
+/// The result of merging two versions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeVersionsOutcome {
+    /// The versions were combined and committed as a new version.
+    Merged(MergeVersionsStats),
+    /// The versions conflict; nothing was committed.
+    Conflicted(Vec<MergeConflict>),
+}
+
+/// Deletions that need to be combined before the merged manifest is committed.
+#[derive(Debug)]
+struct DeletionUnion {
+    fragment_id: u64,
+    base: Fragment,
+    ours: Fragment,
+    theirs: Fragment,
+}
+
+/// The fragment-level plan for a merge, computed from the manifests alone.
+#[derive(Debug)]
+struct MergePlan {
+    schema: Schema,
+    /// The final fragments, ordered by id.
+    fragments: Vec<Fragment>,
+    /// Fragments appended from `theirs`, as they were in `theirs`, with
+    /// their new ids.
+    appended: Vec<(Fragment, u64)>,
+    deletion_unions: Vec<DeletionUnion>,
+    stats: MergeVersionsStats,
+}
+
+fn file_fields(files: &[&DataFile]) -> HashSet<i32> {
+    files
+        .iter()
+        .flat_map(|file| file.fields.iter().copied())
+        .collect()
+}
+
+/// Data files of `side` that are not in `base`, and data files of `base`
+/// that are not in `side`.
+fn diff_files<'a>(
+    base: &'a Fragment,
+    side: &'a Fragment,
+) -> (Vec<&'a DataFile>, Vec<&'a DataFile>) {
+    let added = side
+        .files
+        .iter()
+        .filter(|file| !base.files.iter().any(|b| b.path == file.path))
+        .collect();
+    let removed = base
+        .files
+        .iter()
+        .filter(|file| !side.files.iter().any(|s| s.path == file.path))
+        .collect();
+    (added, removed)
+}
+
+fn fragment_modified(base: &Fragment, side: &Fragment) -> bool {
+    base.files
+        .iter()
+        .map(|f| &f.path)
+        .ne(side.files.iter().map(|f| &f.path))
+        || base.deletion_file != side.deletion_file
+}
+
+/// Shift the ids of fields created after the common ancestor by `offset`.
+fn remap_field(field: &mut Field, base_max_field_id: i32, offset: i32) {
+    if field.id > base_max_field_id {
+        field.id += offset;
+    }
+    if field.parent_id > base_max_field_id {
+        field.parent_id += offset;
+    }
+    for child in field.children.iter_mut() {
+        remap_field(child, base_max_field_id, offset);
+    }
+}
+
+fn remap_file(file: &DataFile, base_max_field_id: i32, offset: i32) -> DataFile {
+    let mut file = file.clone();
+    for field_id in file.fields.iter_mut() {
+        if *field_id > base_max_field_id {
+            *field_id += offset;
+        }
+    }
+    file
+}
+
+/// Work out the schema of the merged version.
+///
+/// Returns the merged schema and the offset that must be applied to the ids
+/// of fields added by `theirs`.
+fn merge_schemas(
+    base: &Manifest,
+    ours: &Manifest,
+    theirs: &Manifest,
+    conflicts: &mut Vec<MergeConflict>,
+) -> (Schema, i32) {
+    let base_max_field_id = base.max_field_id();
+    let mut schema = ours.schema.clone();
+
+    // Both sides allocate new field ids after the common ancestor, so the new
+    // fields of theirs are moved after the new fields of ours.
+    let offset = ours.max_field_id().max(base_max_field_id) - base_max_field_id;
+
+    for base_field in &base.schema.fields {
+        let ours_field = ours.schema.fields.iter().find(|f| f.id == base_field.id);
+        let theirs_field = theirs.schema.fields.iter().find(|f| f.id == base_field.id);
+        match (ours_field, theirs_field) {
+            (_, Some(theirs_field)) if theirs_field == base_field => {}
+            (Some(ours_field), Some(theirs_field)) if ours_field == base_field => {
+                // Only theirs altered the field
+                let pos = schema
+                    .fields
+                    .iter()
+                    .position(|f| f.id == base_field.id)
+                    .unwrap();
+                let mut field = theirs_field.clone();
+                remap_field(&mut field, base_max_field_id, offset);
+                schema.fields[pos] = field;
+            }
+            (None, None) => {}
+            (Some(ours_field), None) if ours_field == base_field => {
+                // Only theirs dropped the field
+                schema.fields.retain(|f| f.id != base_field.id);
+            }
+            (Some(ours_field), Some(theirs_field)) if ours_field == theirs_field => {}
+            (None, Some(_)) => conflicts.push(MergeConflict::SchemaConflict {
+                field: base_field.name.clone(),
+                message: "dropped by ours and altered by theirs".to_string(),
+            }),
+            (Some(_), None) => conflicts.push(MergeConflict::SchemaConflict {
+                field: base_field.name.clone(),
+                message: "altered by ours and dropped by theirs".to_string(),
+            }),
+            (Some(_), Some(_)) => conflicts.push(MergeConflict::SchemaConflict {
+                field: base_field.name.clone(),
+                message: "altered differently on both sides".to_string(),
+            }),
+        }
+    }
+
+    for theirs_field in theirs
+        .schema
+        .fields
+        .iter()
+        .filter(|f| f.id > base_max_field_id)
+    {
+        if schema.field(&theirs_field.name).is_some() {
+            conflicts.push(MergeConflict::SchemaConflict {
+                field: theirs_field.name.clone(),
+                message: "added on both sides".to_string(),
+            });
+            continue;
+        }
+        let mut field = theirs_field.clone();
+        remap_field(&mut field, base_max_field_id, offset);
+        schema.fields.push(field);
+    }
+
+    (schema, offset)
+}
+
+fn plan_merge(
+    base: &Manifest,
+    ours: &Manifest,
+    theirs: &Manifest,
+) -> std::result::Result<MergePlan, Vec<MergeConflict>> {
+    let mut conflicts = Vec::new();
+    let mut stats = MergeVersionsStats::default();
+    let (schema, field_offset) = merge_schemas(base, ours, theirs, &mut conflicts);
+    let base_max_field_id = base.max_field_id();
+    stats.fields_added = schema
+        .fields
+        .iter()
+        .filter(|f| f.id > ours.max_field_id())
+        .count();
+
+    let base_fragments = base
+        .fragments
+        .iter()
+        .map(|f| (f.id, f))
+        .collect::<HashMap<_, _>>();
+    let theirs_fragments = theirs
+        .fragments
+        .iter()
+        .map(|f| (f.id, f))
+        .collect::<HashMap<_, _>>();
+
+    let mut fragments = Vec::with_capacity(ours.fragments.len());
+    let mut deletion_unions = Vec::new();
+
+    // Fragments that ours added since the common ancestor are kept as-is.
+    for ours_fragment in ours
+        .fragments
+        .iter()
+        .filter(|f| !base_fragments.contains_key(&f.id))
+    {
+        fragments.push(ours_fragment.clone());
+    }
+
+    for base_fragment in base.fragments.iter() {
+        let ours_fragment = ours.fragments.iter().find(|f| f.id == base_fragment.id);
+        let theirs_fragment = theirs_fragments.get(&base_fragment.id).copied();
+        match (ours_fragment, theirs_fragment) {
+            (None, None) => {}
+            (Some(ours_fragment), None) => {
+                if fragment_modified(base_fragment, ours_fragment) {
+                    conflicts.push(MergeConflict::FragmentRemovedAndModified {
+                        fragment_id: base_fragment.id,
+                        removed_by: MergeSide::Theirs,
+                    });
+                } else {
+                    stats.fragments_removed += 1;
+                }
+            }
+            (None, Some(theirs_fragment)) => {
+                if fragment_modified(base_fragment, theirs_fragment) {
+                    conflicts.push(MergeConflict::FragmentRemovedAndModified {
+                        fragment_id: base_fragment.id,
+                        removed_by: MergeSide::Ours,
+                    });
+                }
+            }
+            (Some(ours_fragment), Some(theirs_fragment)) => {
+                if !fragment_modified(base_fragment, theirs_fragment) {
+                    fragments.push(ours_fragment.clone());
+                    continue;
+                }
+                stats.fragments_updated += 1;
+                let mut merged = ours_fragment.clone();
+
+                let (ours_added, ours_removed) = diff_files(base_fragment, ours_fragment);
+                let (theirs_added, theirs_removed) = diff_files(base_fragment, theirs_fragment);
+                if !theirs_added.is_empty() || !theirs_removed.is_empty() {
+                    let mut ours_touched = file_fields(&ours_added);
+                    ours_touched.extend(file_fields(&ours_removed));
+                    let mut theirs_touched = file_fields(&theirs_added);
+                    theirs_touched.extend(file_fields(&theirs_removed));
+                    // Fields added on both sides may share ids before renumbering.
+                    let theirs_touched = theirs_touched
+                        .into_iter()
+                        .map(|id| {
+                            if id > base_max_field_id {
+                                id + field_offset
+                            } else {
+                                id
+                            }
+                        })
+                        .collect::<HashSet<_>>();
+                    let mut overlap = ours_touched
+                        .intersection(&theirs_touched)
+                        .copied()
+                        .collect::<Vec<_>>();
+                    if !overlap.is_empty() {
+                        overlap.sort();
+                        conflicts.push(MergeConflict::FieldsModifiedOnBothSides {
+                            fragment_id: base_fragment.id,
+                            field_ids: overlap,
+                        });
+                        continue;
+                    }
+                    merged
+                        .files
+                        .retain(|file| !theirs_removed.iter().any(|r| r.path == file.path));
+                    merged.files.extend(
+                        theirs_added
+                            .iter()
+                            .map(|file| remap_file(file, base_max_field_id, field_offset)),
+                    );
+                }
+
+                if theirs_fragment.deletion_file != base_fragment.deletion_file {
+                    if ours_fragment.deletion_file == base_fragment.deletion_file {
+                        merged.deletion_file = theirs_fragment.deletion_file.clone();
+                    } else if ours_fragment.deletion_file != theirs_fragment.deletion_file {
+                        deletion_unions.push(DeletionUnion {
+                            fragment_id: base_fragment.id,
+                            base: base_fragment.clone(),
+                            ours: ours_fragment.clone(),
+                            theirs: theirs_fragment.clone(),
+                        });
+                    }
+                }
+                fragments.push(merged);
+            }
+        }
+    }
+
+    // Fragments that theirs added since the common ancestor, including the
+    // output of any compaction, are appended with new ids.
+    let mut next_fragment_id = ours
+        .max_fragment_id()
+        .into_iter()
+        .chain(base.max_fragment_id())
+        .max()
+        .map(|id| id + 1)
+        .unwrap_or(0);
+    let mut appended = Vec::new();
+    for theirs_fragment in theirs
+        .fragments
+        .iter()
+        .filter(|f| !base_fragments.contains_key(&f.id))
+    {
+        if theirs_fragment.files.iter().any(|f| f.is_legacy_file()) && field_offset != 0 {
+            conflicts.push(MergeConflict::SchemaConflict {
+                field: String::new(),
+                message: format!(
+                    "fragment {} uses the legacy file format and its new fields cannot be renumbered",
+                    theirs_fragment.id
+                ),
+            });
+            continue;
+        }
+        let mut fragment = theirs_fragment.clone();
+        fragment.id = next_fragment_id;
+        next_fragment_id += 1;
+        fragment.files = fragment
+            .files
+            .iter()
+            .map(|file| remap_file(file, base_max_field_id, field_offset))
+            .collect();
+        appended.push((theirs_fragment.clone(), fragment.id));
+        fragments.push(fragment);
+    }
+    stats.fragments_added = appended.len();
+
+    if !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+
+    // Drop the data files that no longer contain any field of the schema.
+    let field_ids = schema
+        .fields_pre_order()
+        .map(|f| f.id)
+        .collect::<HashSet<_>>();
+    for fragment in fragments.iter_mut() {
+        fragment
+            .files
+            .retain(|file| file.fields.iter().any(|id| field_ids.contains(id)));
+    }
+    fragments.sort_by_key(|f| f.id);
+
+    Ok(MergePlan {
+        schema,
+        fragments,
+        appended,
+        deletion_unions,
+        stats,
+    })
+}
+
+/// Shift the row ids created by `theirs` after the common ancestor past the row
+/// ids created by `ours`.
+fn remap_row_ids(sequence: &RowIdSequence, base_next_row_id: u64, offset: u64) -> RowIdSequence {
+    let mut remapped = RowIdSequence::from(0..0);
+    let mut run: Option<Range<u64>> = None;
+    for row_id in sequence.iter() {
+        let row_id = if row_id >= base_next_row_id {
+            row_id + offset
+        } else {
+            row_id
+        };
+        run = match run {
+            Some(range) if range.end == row_id => Some(range.start..row_id + 1),
+            Some(range) => {
+                remapped.extend(RowIdSequence::from(range));
+                Some(row_id..row_id + 1)
+            }
+            None => Some(row_id..row_id + 1),
+        };
+    }
+    if let Some(range) = run {
+        remapped.extend(RowIdSequence::from(range));
+    }
+    remapped
+}
+
+async fn read_deletions(dataset: &Dataset, fragment: &Fragment) -> Result<DeletionVector> {
+    match &fragment.deletion_file {
+        Some(deletion_file) => {
+            read_deletion_file(
+                fragment.id,
+                deletion_file,
+                &dataset.base,
+                dataset.object_store(),
+            )
+            .await
+        }
+        None => Ok(DeletionVector::NoDeletions),
+    }
+}
+
+/// The fragments that `Update`s committed after `base`, up to `side`, moved
+/// rows out of by deleting them and appending their new versions.
+async fn updated_fragment_ids(base: &Dataset, side: &Dataset) -> Result<HashSet<u64>> {
+    let mut fragment_ids = HashSet::new();
+    for version in base.manifest.version + 1..=side.manifest.version {
+        let dataset = side.checkout_version(version).await?;
+        if let Some(Transaction {
+            operation:
+                Operation::Update {
+                    removed_fragment_ids,
+                    updated_fragments,
+                    new_fragments,
+                    ..
+                },
+            ..
+        }) = dataset.read_transaction().await?
+        {
+            if !new_fragments.is_empty() {
+                fragment_ids.extend(removed_fragment_ids);
+                fragment_ids.extend(updated_fragments.iter().map(|f| f.id));
+            }
+        }
+    }
+    Ok(fragment_ids)
+}
+
+impl Dataset {
+    /// Merge the changes made on `branch` since it was created into the latest
+    /// version of the checked out branch.
+    ///
+    /// The common ancestor is the version `branch` was created from, so
+    /// `branch` must have been created from the checked out branch.
+    pub async fn merge_branch(&mut self, branch: &str) -> Result<MergeVersionsOutcome> {
+        let contents = self.branches.get(branch).await?;
+        if contents.parent_branch != self.branch {
+            return Err(Error::invalid_input(
+                format!(
+                    "branch {} was not created from branch {}",
+                    branch,
+                    self.branch()
+                ),
+                location!(),
+            ));
+        }
+        let base = self.checkout_version(contents.parent_version).await?;
+        let theirs = self.checkout_branch(branch).await?;
+        self.merge_versions(&base, &theirs).await
+    }
+
+    /// Merge the changes between `base` and `theirs` into the latest version of
+    /// the checked out branch.
+    ///
+    /// `base` must be a common ancestor of `theirs` and the latest version, e.g.
+    /// the version a branch was created from.  If the changes conflict, the
+    /// conflicts are returned and nothing is committed.
+    pub async fn merge_versions(
+        &mut self,
+        base: &Self,
+        theirs: &Self,
+    ) -> Result<MergeVersionsOutcome> {
+        if base.base != self.base || theirs.base != self.base {
+            return Err(Error::invalid_input(
+                "can only merge versions of the same dataset",
+                location!(),
+            ));
+        }
+        self.checkout_latest().await?;
+        let ours = self.manifest.clone();
+
+        let mut plan = match plan_merge(&base.manifest, &ours, &theirs.manifest) {
+            Ok(plan) => plan,
+            Err(conflicts) => return Ok(MergeVersionsOutcome::Conflicted(conflicts)),
+        };
+
+        // Rows deleted on both sides from a fragment that both sides updated
+        // were most likely replaced by a new version on each side.
+        if !plan.deletion_unions.is_empty() {
+            let ours_updated = updated_fragment_ids(base, self).await?;
+            let theirs_updated = updated_fragment_ids(base, theirs).await?;
+            let mut conflicts = Vec::new();
+            for union in plan.deletion_unions.iter().filter(|union| {
+                ours_updated.contains(&union.fragment_id)
+                    && theirs_updated.contains(&union.fragment_id)
+            }) {
+                let base_deleted = RoaringBitmap::from(&read_deletions(base, &union.base).await?);
+                let ours_deleted = RoaringBitmap::from(&read_deletions(self, &union.ours).await?);
+                let theirs_deleted =
+                    RoaringBitmap::from(&read_deletions(theirs, &union.theirs).await?);
+                let num_rows = ((ours_deleted & theirs_deleted) - base_deleted).len();
+                if num_rows > 0 {
+                    conflicts.push(MergeConflict::RowsUpdatedOnBothSides {
+                        fragment_id: union.fragment_id,
+                        num_rows,
+                    });
+                }
+            }
+            if !conflicts.is_empty() {
+                return Ok(MergeVersionsOutcome::Conflicted(conflicts));
+            }
+        }
+
+        for union in std::mem::take(&mut plan.deletion_unions) {
+            let mut deleted = read_deletions(self, &union.ours).await?;
+            deleted.extend(read_deletions(self, &union.theirs).await?);
+            let deletion_file = write_deletion_file(
+                &self.base,
+                union.fragment_id,
+                ours.version,
+                &deleted,
+                self.object_store(),
+            )
+            .await?;
+            let fragment = plan
+                .fragments
+                .iter_mut()
+                .find(|f| f.id == union.fragment_id)
+                .unwrap();
+            fragment.deletion_file = deletion_file;
+        }
+
+        // Deletion file paths are derived from the fragment id, so deletions
+        // of appended fragments must be rewritten under their new ids.
+        for (theirs_fragment, new_id) in plan.appended.iter() {
+            if theirs_fragment.deletion_file.is_none() {
+                continue;
+            }
+            let deleted = read_deletions(theirs, theirs_fragment).await?;
+            let deletion_file = write_deletion_file(
+                &self.base,
+                *new_id,
+                ours.version,
+                &deleted,
+                self.object_store(),
+            )
+            .await?;
+            let fragment = plan.fragments.iter_mut().find(|f| f.id == *new_id).unwrap();
+            fragment.deletion_file = deletion_file;
+        }
+
+        if ours.uses_move_stable_row_ids() {
+            let base_next_row_id = base.manifest.next_row_id;
+            let offset = ours.next_row_id.max(base_next_row_id) - base_next_row_id;
+            for (theirs_fragment, new_id) in plan.appended.iter() {
+                let sequence = load_row_id_sequence(theirs, theirs_fragment).await?;
+                let remapped = remap_row_ids(&sequence, base_next_row_id, offset);
+                let fragment = plan.fragments.iter_mut().find(|f| f.id == *new_id).unwrap();
+                fragment.row_id_meta = Some(RowIdMeta::Inline(write_row_ids(&remapped)));
+            }
+        }
+
+        let transaction = Transaction::new(
+            ours.version,
+            Operation::Merge {
+                fragments: plan.fragments,
+                schema: plan.schema,
+            },
+            /*blobs_op=*/ None,
+            None,
+        );
+        self.apply_commit(transaction, &Default::default(), &Default::default())
+            .await?;
+
+        plan.stats.version = self.manifest.version;
+        Ok(MergeVersionsOutcome::Merged(plan.stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        Int64Array, RecordBatch, RecordBatchIterator, RecordBatchReader, UInt32Array, UInt64Array,
+    };
+    use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema};
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::dataset::{NewColumnTransform, UpdateBuilder, WriteParams};
+
+    fn make_reader(range: Range<u32>) -> impl RecordBatchReader + Send + 'static {
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "i",
+            DataType::UInt32,
+            false,
+        )]));
+        let data = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(UInt32Array::from_iter_values(range))],
+        )
+        .unwrap();
+        RecordBatchIterator::new(vec![Ok(data)], schema)
+    }
+
+    async fn sorted_values(dataset: &Dataset, column: &str) -> Vec<u32> {
+        let batch = dataset
+            .scan()
+            .project(&[column])
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        let mut values = batch[column]
+            .as_any()
+            .downcast_ref::<UInt32Array>()
+            .unwrap()
+            .values()
+            .to_vec();
+        values.sort();
+        values
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_merge_branch(#[values(false, true)] enable_move_stable_row_ids: bool) {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let params = WriteParams {
+            enable_move_stable_row_ids,
+            ..Default::default()
+        };
+        let mut dataset = Dataset::write(make_reader(0..100), test_uri, Some(params))
+            .await
+            .unwrap();
+        dataset.append(make_reader(100..200), None).await.unwrap();
+
+        let mut branch = dataset.create_branch("exp", 2).await.unwrap();
+        branch.append(make_reader(1000..1050), None).await.unwrap();
+        branch
+            .delete("i < 10 OR (i >= 190 AND i < 200)")
+            .await
+            .unwrap();
+
+        dataset.append(make_reader(2000..2020), None).await.unwrap();
+        dataset.delete("i >= 5 AND i < 20").await.unwrap();
+
+        let outcome = dataset.merge_branch("exp").await.unwrap();
+        let MergeVersionsOutcome::Merged(stats) = outcome else {
+            panic!("unexpected outcome {:?}", outcome);
+        };
+        assert_eq!(stats.version, dataset.manifest.version);
+        assert_eq!(stats.fragments_added, 1);
+        assert_eq!(stats.fragments_removed, 0);
+        assert_eq!(stats.fragments_updated, 2);
+        assert_eq!(stats.fields_added, 0);
+
+        let expected = (20..190)
+            .chain(1000..1050)
+            .chain(2000..2020)
+            .collect::<Vec<_>>();
+        assert_eq!(sorted_values(&dataset, "i").await, expected);
+        dataset.validate().await.unwrap();
+
+        if enable_move_stable_row_ids {
+            let batch = dataset.scan().with_row_id().try_into_batch().await.unwrap();
+            let row_ids = batch["_rowid"]
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap();
+            let unique = row_ids.values().iter().collect::<HashSet<_>>();
+            assert_eq!(unique.len(), row_ids.len());
+            // New rows continue after the merged row ids.
+            dataset.append(make_reader(3000..3001), None).await.unwrap();
+            assert_eq!(dataset.manifest.next_row_id, 271);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge_deletions_in_appended_fragment() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = Dataset::write(make_reader(0..100), test_uri, None)
+            .await
+            .unwrap();
+
+        let mut branch = dataset.create_branch("exp", 1).await.unwrap();
+        branch.append(make_reader(1000..1050), None).await.unwrap();
+        branch.delete("i >= 1040").await.unwrap();
+
+        // Ours also appends, so the fragment of theirs gets a new id.
+        dataset.append(make_reader(2000..2020), None).await.unwrap();
+        dataset.delete("i >= 2010").await.unwrap();
+
+        let outcome = dataset.merge_branch("exp").await.unwrap();
+        assert!(matches!(outcome, MergeVersionsOutcome::Merged(_)));
+
+        let expected = (0..100)
+            .chain(1000..1040)
+            .chain(2000..2010)
+            .collect::<Vec<_>>();
+        assert_eq!(sorted_values(&dataset, "i").await, expected);
+        assert_eq!(dataset.count_rows(None).await.unwrap(), expected.len());
+        dataset.validate().await.unwrap();
+    }
+
+    async fn update(dataset: &Dataset, predicate: &str, value: &str) {
+        UpdateBuilder::new(Arc::new(dataset.clone()))
+            .update_where(predicate)
+            .unwrap()
+            .set("i", value)
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute()
+            .await
+            .unwrap();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_merge_rows_updated_on_both_sides(
+        #[values(false, true)] enable_move_stable_row_ids: bool,
+    ) {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let params = WriteParams {
+            enable_move_stable_row_ids,
+            ..Default::default()
+        };
+        let mut dataset = Dataset::write(make_reader(0..100), test_uri, Some(params))
+            .await
+            .unwrap();
+
+        // Both branches update row 7.
+        let mut branch = dataset.create_branch("exp", 1).await.unwrap();
+        update(&branch, "i = 7 OR i = 8", "i + 1000").await;
+        branch.checkout_latest().await.unwrap();
+        update(&dataset, "i = 7 OR i = 9", "i + 2000").await;
+        dataset.checkout_latest().await.unwrap();
+        let version = dataset.manifest.version;
+
+        let outcome = dataset.merge_branch("exp").await.unwrap();
+        assert_eq!(
+            outcome,
+            MergeVersionsOutcome::Conflicted(vec![MergeConflict::RowsUpdatedOnBothSides {
+                fragment_id: 0,
+                num_rows: 1,
+            }])
+        );
+        dataset.checkout_latest().await.unwrap();
+        assert_eq!(dataset.manifest.version, version);
+
+        // Updates of different rows are merged without duplicates.
+        let mut branch = dataset.create_branch("exp2", version).await.unwrap();
+        update(&branch, "i = 10", "i + 1000").await;
+        branch.checkout_latest().await.unwrap();
+        update(&dataset, "i = 11", "i + 2000").await;
+        let outcome = dataset.merge_branch("exp2").await.unwrap();
+        assert!(matches!(outcome, MergeVersionsOutcome::Merged(_)));
+        let mut expected = (0..7)
+            .chain([8])
+            .chain(12..100)
+            .chain([1010, 2007, 2009, 2011])
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(sorted_values(&dataset, "i").await, expected);
+        dataset.validate().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_added_column() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = Dataset::write(make_reader(0..100), test_uri, None)
+            .await
+            .unwrap();
+
+        let mut branch = dataset.create_branch("exp", 1).await.unwrap();
+        branch
+            .add_columns(
+                NewColumnTransform::SqlExpressions(vec![("x".into(), "i * 2".into())]),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        dataset
+            .add_columns(
+                NewColumnTransform::SqlExpressions(vec![("y".into(), "i + 1".into())]),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let outcome = dataset.merge_branch("exp").await.unwrap();
+        let MergeVersionsOutcome::Merged(stats) = outcome else {
+            panic!("unexpected outcome {:?}", outcome);
+        };
+        assert_eq!(stats.fields_added, 1);
+        assert_eq!(stats.fragments_updated, 1);
+        let field_ids = dataset
+            .schema()
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.id))
+            .collect::<Vec<_>>();
+        assert_eq!(field_ids, vec![("i", 0), ("y", 1), ("x", 2)]);
+
+        let batch = dataset.scan().try_into_batch().await.unwrap();
+        assert_eq!(batch.num_rows(), 100);
+        let i = batch["i"].as_any().downcast_ref::<UInt32Array>().unwrap();
+        let x = arrow::compute::cast(&batch["x"], &DataType::Int64).unwrap();
+        let x = x.as_any().downcast_ref::<Int64Array>().unwrap();
+        for (i, x) in i.values().iter().zip(x.values()) {
+            assert_eq!(*i as i64 * 2, *x);
+        }
+        dataset.validate().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_conflicts() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = Dataset::write(make_reader(0..100), test_uri, None)
+            .await
+            .unwrap();
+        let base = dataset.clone();
+
+        let mut branch = dataset.create_branch("exp", 1).await.unwrap();
+        branch
+            .add_columns(
+                NewColumnTransform::SqlExpressions(vec![("x".into(), "i * 2".into())]),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        dataset
+            .add_columns(
+                NewColumnTransform::SqlExpressions(vec![("x".into(), "i * 3".into())]),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        // Remove the only fragment on our side while theirs modified it.
+        dataset.delete("true").await.unwrap();
+        let version = dataset.manifest.version;
+
+        let outcome = dataset.merge_versions(&base, &branch).await.unwrap();
+        let MergeVersionsOutcome::Conflicted(conflicts) = outcome else {
+            panic!("unexpected outcome {:?}", outcome);
+        };
+        assert_eq!(
+            conflicts,
+            vec![
+                MergeConflict::SchemaConflict {
+                    field: "x".to_string(),
+                    message: "added on both sides".to_string(),
+                },
+                MergeConflict::FragmentRemovedAndModified {
+                    fragment_id: 0,
+                    removed_by: MergeSide::Ours,
+                },
+            ]
+        );
+        // Nothing was committed.
+        dataset.checkout_latest().await.unwrap();
+        assert_eq!(dataset.manifest.version, version);
+    }
+}
@@ -97,7 +97,7 @@ use serde::{Deserialize, Serialize};
 use crate::io::commit::{commit_transaction, migrate_fragments};
 use crate::Dataset;
 use crate::Result;
-use lance_table::format::{Fragment, RowIdMeta};
+use lance_table::format::{Fragment, PartitionValue, RowIdMeta};
 
 use super::fragment::FileFragment;
 use super::index::DatasetIndexRemapperOptions;
@@ -495,11 +495,15 @@ pub async fn plan_compaction(
     };
 
     let mut candidate_bins: Vec<CandidateBin> = Vec::new();
-    let mut current_bin: Option<CandidateBin> = None;
+    // Fragments from different partitions are never compacted together, so
+    // there is one bin in progress per partition.
+    let mut current_bins: HashMap<Vec<PartitionValue>, CandidateBin> = HashMap::new();
     let mut i = 0;
 
     while let Some(res) = fragment_metrics.next().await {
         let (fragment, metrics) = res?;
+        let partition = fragment.partition.clone();
+        let mut current_bin = current_bins.remove(&partition);
 
         let candidacy = if options.materialize_deletions
             && metrics.deletion_percentage() > options.materialize_deletions_threshold
@@ -554,14 +558,17 @@ pub async fn plan_compaction(
                 candidate_bins.push(current_bin.take().unwrap());
             }
         }
+        if let Some(bin) = current_bin {
+            current_bins.insert(partition, bin);
+        }
 
         i += 1;
     }
 
-    // Flush the last bin
-    if let Some(bin) = current_bin {
-        candidate_bins.push(bin);
-    }
+    // Flush the last bins
+    let mut last_bins = current_bins.into_values().collect::<Vec<_>>();
+    last_bins.sort_by_key(|bin| bin.pos_range.start);
+    candidate_bins.extend(last_bins);
 
     let final_bins = candidate_bins
         .into_iter()
@@ -737,6 +744,10 @@ async fn rewrite_files(
     // We should not be rewriting any blob data
     assert!(new_fragments.blob.is_none());
     let mut new_fragments = new_fragments.default.0;
+    // All the fragments of a task belong to the same partition
+    for fragment in new_fragments.iter_mut() {
+        fragment.partition.clone_from(&task.fragments[0].partition);
+    }
 
     log::info!("Compaction task {}: file written", task_id);
 
@@ -1022,6 +1033,7 @@ mod tests {
             deletion_file: None,
             row_id_meta: None,
             physical_rows: Some(0),
+            partition: vec![],
         };
         let single_bin = CandidateBin {
             fragments: vec![fragment.clone()],
@@ -343,6 +343,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: Some(5),
+                partition: vec![],
             },
             Fragment {
                 id: 3,
@@ -350,6 +351,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: Some(3),
+                partition: vec![],
             },
         ];
         let rows = [(0, 1), (0, 3), (0, 4), (3, 0), (3, 2)]
@@ -377,6 +379,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: Some(5),
+                partition: vec![],
             },
             Fragment {
                 id: 3,
@@ -384,6 +387,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: Some(3),
+                partition: vec![],
             },
             Fragment {
                 id: 1,
@@ -391,6 +395,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: Some(3),
+                partition: vec![],
             },
         ];
 
@@ -0,0 +1,1050 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Table-level partitioning of a dataset.
+//!
+//! A partitioned dataset has a [`PartitionSpec`] stored in the manifest config
+//! under [`PARTITION_SPEC_CONFIG_KEY`], for example `bucket(user_id, 64), day(ts)`.
+//! Each field of the spec applies a [`PartitionTransform`] to a column.  Writes
+//! route the rows of each partition into their own fragments and record the
+//! partition values on the fragment, so that scans can skip the fragments whose
+//! partition cannot match the filter, and compaction never mixes partitions.
+//!
+//! Fragments without partition values (e.g. written before the spec was set)
+//! are never pruned.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow::compute::take_record_batch;
+use arrow_array::{RecordBatch, UInt32Array};
+use arrow_schema::{DataType, TimeUnit};
+use chrono::{DateTime, Datelike};
+use datafusion::logical_expr::{Between, BinaryExpr, Cast, Expr, Operator};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use datafusion::scalar::ScalarValue;
+use futures::StreamExt;
+use lance_core::datatypes::Schema;
+use lance_io::object_store::ObjectStore;
+use lance_table::format::{Fragment, Manifest, PartitionValue};
+use object_store::path::Path;
+use snafu::location;
+
+use super::write::{write_fragments_internal, WriteParams, WrittenFragments};
+use super::Dataset;
+use crate::{Error, Result};
+
+/// The manifest config key holding the partition spec of a dataset.
+pub const PARTITION_SPEC_CONFIG_KEY: &str = "lance.partition_spec";
+
+const MICROS_PER_HOUR: i64 = 3_600_000_000;
+const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+/// A function from the values of a column to the partition values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionTransform {
+    /// The value itself.  Supported for integer and string columns.
+    Identity,
+    /// A hash of the value modulo the number of buckets.  Supported for integer
+    /// and string columns.  The hash is the 32-bit murmur3 hash used by Iceberg.
+    Bucket(u32),
+    /// Years since 1970.  Supported for date and timestamp columns.
+    Year,
+    /// Months since 1970-01.  Supported for date and timestamp columns.
+    Month,
+    /// Days since 1970-01-01.  Supported for date and timestamp columns.
+    Day,
+    /// Hours since 1970-01-01 00:00 UTC.  Supported for date and timestamp columns.
+    Hour,
+}
+
+impl PartitionTransform {
+    fn supports(&self, data_type: &DataType) -> bool {
+        match self {
+            Self::Identity | Self::Bucket(_) => {
+                data_type.is_integer()
+                    || matches!(
+                        data_type,
+                        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+                    )
+            }
+            Self::Year | Self::Month | Self::Day | Self::Hour => matches!(
+                data_type,
+                DataType::Date32 | DataType::Date64 | DataType::Timestamp(_, _)
+            ),
+        }
+    }
+
+    /// Whether `a <= b` implies `transform(a) <= transform(b)`.
+    fn preserves_order(&self) -> bool {
+        !matches!(self, Self::Bucket(_))
+    }
+
+    /// Whether the transform of a value cast from `from` to `to` is the
+    /// transform of the original value, so that a filter on the cast column
+    /// can prune partitions.
+    ///
+    /// Casts to the same or a finer unit are exact.  A cast to a date drops the
+    /// time of day, which only the transforms coarser than a day ignore.
+    /// Timestamps in a timezone other than UTC are cast to their local date,
+    /// while partitions are computed in UTC, so those casts are never exact.
+    fn commutes_with_cast(&self, from: &DataType, to: &DataType) -> bool {
+        if matches!(self, Self::Identity | Self::Bucket(_)) {
+            return false;
+        }
+        if let (DataType::Timestamp(_, Some(tz)), DataType::Date32 | DataType::Date64) = (from, to)
+        {
+            if !is_utc(tz) {
+                return false;
+            }
+        }
+        match (temporal_unit_nanos(from), temporal_unit_nanos(to)) {
+            (Some(from_unit), Some(to_unit)) => {
+                to_unit <= from_unit || (*to == DataType::Date32 && *self != Self::Hour)
+            }
+            _ => false,
+        }
+    }
+
+    /// Apply the transform to a single value.
+    ///
+    /// Returns `None` if the transform does not support the type of the value.
+    pub fn apply(&self, value: &ScalarValue) -> Option<PartitionValue> {
+        if value.is_null() {
+            return Some(PartitionValue::Null);
+        }
+        match self {
+            Self::Identity => match value {
+                ScalarValue::Utf8(Some(v))
+                | ScalarValue::LargeUtf8(Some(v))
+                | ScalarValue::Utf8View(Some(v)) => Some(PartitionValue::String(v.clone())),
+                _ => integer_value(value).map(PartitionValue::Int),
+            },
+            Self::Bucket(num_buckets) => {
+                let hash = match value {
+                    ScalarValue::Utf8(Some(v))
+                    | ScalarValue::LargeUtf8(Some(v))
+                    | ScalarValue::Utf8View(Some(v)) => murmur3_32(v.as_bytes()),
+                    ScalarValue::UInt64(Some(v)) => murmur3_32(&(*v as i64).to_le_bytes()),
+                    _ => murmur3_32(&integer_value(value)?.to_le_bytes()),
+                };
+                let bucket = (hash & i32::MAX as u32) % *num_buckets;
+                Some(PartitionValue::Int(bucket as i64))
+            }
+            Self::Year | Self::Month => {
+                let datetime = DateTime::from_timestamp_micros(timestamp_micros(value)?)?;
+                let years = datetime.year() as i64 - 1970;
+                match self {
+                    Self::Year => Some(PartitionValue::Int(years)),
+                    _ => Some(PartitionValue::Int(years * 12 + datetime.month0() as i64)),
+                }
+            }
+            Self::Day => Some(PartitionValue::Int(
+                timestamp_micros(value)?.div_euclid(MICROS_PER_DAY),
+            )),
+            Self::Hour => Some(PartitionValue::Int(
+                timestamp_micros(value)?.div_euclid(MICROS_PER_HOUR),
+            )),
+        }
+    }
+}
+
+fn integer_value(value: &ScalarValue) -> Option<i64> {
+    match value {
+        ScalarValue::Int8(Some(v)) => Some(*v as i64),
+        ScalarValue::Int16(Some(v)) => Some(*v as i64),
+        ScalarValue::Int32(Some(v)) => Some(*v as i64),
+        ScalarValue::Int64(Some(v)) => Some(*v),
+        ScalarValue::UInt8(Some(v)) => Some(*v as i64),
+        ScalarValue::UInt16(Some(v)) => Some(*v as i64),
+        ScalarValue::UInt32(Some(v)) => Some(*v as i64),
+        ScalarValue::UInt64(Some(v)) => i64::try_from(*v).ok(),
+        _ => None,
+    }
+}
+
+fn timestamp_micros(value: &ScalarValue) -> Option<i64> {
+    match value {
+        ScalarValue::Date32(Some(days)) => Some(*days as i64 * MICROS_PER_DAY),
+        ScalarValue::Date64(Some(millis)) => Some(millis * 1000),
+        ScalarValue::TimestampSecond(Some(secs), _) => Some(secs * 1_000_000),
+        ScalarValue::TimestampMillisecond(Some(millis), _) => Some(millis * 1000),
+        ScalarValue::TimestampMicrosecond(Some(micros), _) => Some(*micros),
+        ScalarValue::TimestampNanosecond(Some(nanos), _) => Some(nanos.div_euclid(1000)),
+        _ => None,
+    }
+}
+
+fn is_utc(tz: &str) -> bool {
+    matches!(
+        tz,
+        "UTC" | "utc" | "Etc/UTC" | "Z" | "+00:00" | "+0000" | "+00"
+    )
+}
+
+/// The length of the unit of a date or timestamp type in nanoseconds.
+fn temporal_unit_nanos(data_type: &DataType) -> Option<i64> {
+    match data_type {
+        DataType::Date32 => Some(MICROS_PER_DAY * 1000),
+        DataType::Date64 | DataType::Timestamp(TimeUnit::Millisecond, _) => Some(1_000_000),
+        DataType::Timestamp(TimeUnit::Second, _) => Some(1_000_000_000),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => Some(1000),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => Some(1),
+        _ => None,
+    }
+}
+
+/// 32-bit murmur3 (x86 variant) with a seed of 0.
+fn murmur3_32(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let mix = |mut k: u32| {
+        k = k.wrapping_mul(C1);
+        k = k.rotate_left(15);
+        k.wrapping_mul(C2)
+    };
+
+    let mut hash = 0u32;
+    let chunks = data.chunks_exact(4);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        hash ^= mix(u32::from_le_bytes(chunk.try_into().unwrap()));
+        hash = hash.rotate_left(13);
+        hash = hash.wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .enumerate()
+            .fold(0u32, |k, (i, b)| k ^ ((*b as u32) << (8 * i)));
+        hash ^= mix(k);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+impl fmt::Display for PartitionTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Identity => write!(f, "identity"),
+            Self::Bucket(_) => write!(f, "bucket"),
+            Self::Year => write!(f, "year"),
+            Self::Month => write!(f, "month"),
+            Self::Day => write!(f, "day"),
+            Self::Hour => write!(f, "hour"),
+        }
+    }
+}
+
+/// One field of a [`PartitionSpec`]: a transform applied to a column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionField {
+    pub column: String,
+    pub transform: PartitionTransform,
+}
+
+impl PartitionField {
+    pub fn new(column: impl Into<String>, transform: PartitionTransform) -> Self {
+        Self {
+            column: column.into(),
+            transform,
+        }
+    }
+}
+
+impl fmt::Display for PartitionField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.transform {
+            PartitionTransform::Bucket(num_buckets) => {
+                write!(f, "bucket({}, {})", self.column, num_buckets)
+            }
+            transform => write!(f, "{}({})", transform, self.column),
+        }
+    }
+}
+
+/// The partitioning of a dataset.
+///
+/// The string form is a comma separated list of transforms, e.g.
+/// `bucket(user_id, 64), day(ts)`.  The supported transforms are
+/// `identity(col)` (or just `col`), `bucket(col, N)`, `year(col)`,
+/// `month(col)`, `day(col)` and `hour(col)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionSpec {
+    fields: Vec<PartitionField>,
+}
+
+impl PartitionSpec {
+    pub fn try_new(fields: Vec<PartitionField>) -> Result<Self> {
+        if fields.is_empty() {
+            return Err(Error::invalid_input(
+                "A partition spec must have at least one field",
+                location!(),
+            ));
+        }
+        if let Some(field) = fields
+            .iter()
+            .find(|f| f.transform == PartitionTransform::Bucket(0))
+        {
+            return Err(Error::invalid_input(
+                format!("Partition field {} must have at least one bucket", field),
+                location!(),
+            ));
+        }
+        Ok(Self { fields })
+    }
+
+    pub fn fields(&self) -> &[PartitionField] {
+        &self.fields
+    }
+
+    /// The partition spec of the dataset described by `manifest`, if any.
+    pub fn from_manifest(manifest: &Manifest) -> Result<Option<Self>> {
+        manifest
+            .config
+            .get(PARTITION_SPEC_CONFIG_KEY)
+            .map(|spec| spec.parse())
+            .transpose()
+    }
+
+    /// Check that all the partition columns exist in `schema` and have a type
+    /// supported by their transform.
+    pub fn validate(&self, schema: &Schema) -> Result<()> {
+        for field in &self.fields {
+            let column = schema.field(&field.column).ok_or_else(|| {
+                Error::invalid_input(
+                    format!(
+                        "Partition column {} does not exist in the schema",
+                        field.column
+                    ),
+                    location!(),
+                )
+            })?;
+            if !field.transform.supports(&column.data_type()) {
+                return Err(Error::invalid_input(
+                    format!(
+                        "Partition transform {} does not support column {} of type {}",
+                        field.transform,
+                        field.column,
+                        column.data_type()
+                    ),
+                    location!(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Split `batch` into one batch per partition.
+    ///
+    /// The partitions are returned in the order their first row appears in the
+    /// batch, and the rows of each partition keep their relative order.
+    pub fn split_batch(
+        &self,
+        batch: &RecordBatch,
+    ) -> Result<Vec<(Vec<PartitionValue>, RecordBatch)>> {
+        let columns = self
+            .fields
+            .iter()
+            .map(|field| {
+                batch.column_by_name(&field.column).ok_or_else(|| {
+                    Error::invalid_input(
+                        format!("Partition column {} is missing from the data", field.column),
+                        location!(),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut partitions: HashMap<Vec<PartitionValue>, usize> = HashMap::new();
+        let mut rows: Vec<(Vec<PartitionValue>, Vec<u32>)> = Vec::new();
+        for row in 0..batch.num_rows() {
+            let key = self
+                .fields
+                .iter()
+                .zip(&columns)
+                .map(|(field, column)| {
+                    let value = ScalarValue::try_from_array(column, row)?;
+                    field.transform.apply(&value).ok_or_else(|| {
+                        Error::invalid_input(
+                            format!("Cannot compute partition {} of value {}", field, value),
+                            location!(),
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            match partitions.get(&key) {
+                Some(idx) => rows[*idx].1.push(row as u32),
+                None => {
+                    partitions.insert(key.clone(), rows.len());
+                    rows.push((key, vec![row as u32]));
+                }
+            }
+        }
+
+        if rows.len() == 1 {
+            let (key, _) = rows.pop().unwrap();
+            return Ok(vec![(key, batch.clone())]);
+        }
+        rows.into_iter()
+            .map(|(key, indices)| {
+                let indices = UInt32Array::from(indices);
+                Ok((key, take_record_batch(batch, &indices)?))
+            })
+            .collect()
+    }
+
+    /// Whether a fragment in `partition` may contain rows matching `filter`.
+    ///
+    /// This is conservative: it returns `true` unless the filter constrains a
+    /// partition column in a way that rules out the partition.  `schema` is the
+    /// schema of the dataset, used to tell which casts of the columns are exact.
+    pub fn may_match(&self, filter: &Expr, schema: &Schema, partition: &[PartitionValue]) -> bool {
+        if partition.len() != self.fields.len() {
+            // The partition of the fragment is unknown
+            return true;
+        }
+        match filter {
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => match op {
+                Operator::And => {
+                    self.may_match(left, schema, partition)
+                        && self.may_match(right, schema, partition)
+                }
+                Operator::Or => {
+                    self.may_match(left, schema, partition)
+                        || self.may_match(right, schema, partition)
+                }
+                Operator::Eq | Operator::Lt | Operator::LtEq | Operator::Gt | Operator::GtEq => {
+                    match (left.as_ref(), right.as_ref()) {
+                        (column, Expr::Literal(value)) => match ColumnRef::try_new(column) {
+                            Some(column) => {
+                                self.may_compare(&column, *op, value, schema, partition)
+                            }
+                            None => true,
+                        },
+                        (Expr::Literal(value), column) => match ColumnRef::try_new(column) {
+                            Some(column) => self.may_compare(
+                                &column,
+                                op.swap().unwrap(),
+                                value,
+                                schema,
+                                partition,
+                            ),
+                            None => true,
+                        },
+                        _ => true,
+                    }
+                }
+                _ => true,
+            },
+            Expr::InList(in_list) if !in_list.negated => match ColumnRef::try_new(&in_list.expr) {
+                Some(column) => in_list.list.iter().any(|value| match value {
+                    Expr::Literal(value) => {
+                        self.may_compare(&column, Operator::Eq, value, schema, partition)
+                    }
+                    _ => true,
+                }),
+                None => true,
+            },
+            Expr::Between(Between {
+                expr,
+                negated: false,
+                low,
+                high,
+            }) => match (ColumnRef::try_new(expr), low.as_ref(), high.as_ref()) {
+                (Some(column), Expr::Literal(low), Expr::Literal(high)) => {
+                    self.may_compare(&column, Operator::GtEq, low, schema, partition)
+                        && self.may_compare(&column, Operator::LtEq, high, schema, partition)
+                }
+                _ => true,
+            },
+            // A cast may turn a valid value into a null, so only plain columns prune
+            Expr::IsNull(expr) => match expr.as_ref() {
+                Expr::Column(column) => self
+                    .fields
+                    .iter()
+                    .zip(partition)
+                    .filter(|(field, _)| field.column == column.name)
+                    .all(|(_, value)| *value == PartitionValue::Null),
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+
+    /// Whether `column <op> value` may be true for a row in `partition`.
+    fn may_compare(
+        &self,
+        column: &ColumnRef,
+        op: Operator,
+        value: &ScalarValue,
+        schema: &Schema,
+        partition: &[PartitionValue],
+    ) -> bool {
+        if value.is_null() {
+            return true;
+        }
+        self.fields
+            .iter()
+            .zip(partition)
+            .filter(|(field, _)| field.column == column.name)
+            .all(|(field, actual)| {
+                if let Some(cast) = column.cast {
+                    let exact = schema.field(column.name).is_some_and(|source| {
+                        field
+                            .transform
+                            .commutes_with_cast(&source.data_type(), cast)
+                    });
+                    if !exact {
+                        return true;
+                    }
+                }
+                let Some(expected) = field.transform.apply(value) else {
+                    return true;
+                };
+                if *actual == PartitionValue::Null {
+                    // A comparison with a null value is never true
+                    return false;
+                }
+                if std::mem::discriminant(actual) != std::mem::discriminant(&expected) {
+                    return true;
+                }
+                match op {
+                    Operator::Eq => *actual == expected,
+                    _ if !field.transform.preserves_order() => true,
+                    Operator::Lt | Operator::LtEq => *actual <= expected,
+                    Operator::Gt | Operator::GtEq => *actual >= expected,
+                    _ => true,
+                }
+            })
+    }
+
+    /// The fragments that may contain rows matching `filter`.
+    pub fn prune_fragments(
+        &self,
+        filter: &Expr,
+        schema: &Schema,
+        fragments: &[Fragment],
+    ) -> Vec<Fragment> {
+        fragments
+            .iter()
+            .filter(|fragment| self.may_match(filter, schema, &fragment.partition))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A column referenced by a filter, possibly through a cast.
+struct ColumnRef<'a> {
+    name: &'a str,
+    cast: Option<&'a DataType>,
+}
+
+impl<'a> ColumnRef<'a> {
+    /// The column referenced by `expr`, if it is a column or a cast of one.
+    fn try_new(expr: &'a Expr) -> Option<Self> {
+        match expr {
+            Expr::Column(column) => Some(Self {
+                name: &column.name,
+                cast: None,
+            }),
+            Expr::Cast(Cast { expr, data_type }) => match expr.as_ref() {
+                Expr::Column(column) => Some(Self {
+                    name: &column.name,
+                    cast: Some(data_type),
+                }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PartitionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PartitionSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |message: String| {
+            Error::invalid_input(
+                format!("Invalid partition spec \"{}\": {}", s, message),
+                location!(),
+            )
+        };
+
+        // Split on the commas that are not inside parentheses
+        let mut parts = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(&s[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(&s[start..]);
+
+        let fields = parts
+            .into_iter()
+            .map(|part| {
+                let part = part.trim();
+                let Some((name, args)) = part.split_once('(') else {
+                    if part.is_empty() || part.contains(')') {
+                        return Err(invalid(format!("invalid field \"{}\"", part)));
+                    }
+                    return Ok(PartitionField::new(part, PartitionTransform::Identity));
+                };
+                let args = args
+                    .strip_suffix(')')
+                    .ok_or_else(|| invalid(format!("missing ')' in \"{}\"", part)))?
+                    .split(',')
+                    .map(str::trim)
+                    .collect::<Vec<_>>();
+                let transform = match (name.trim().to_lowercase().as_str(), args.as_slice()) {
+                    ("identity", [_]) => PartitionTransform::Identity,
+                    ("year", [_]) => PartitionTransform::Year,
+                    ("month", [_]) => PartitionTransform::Month,
+                    ("day", [_]) => PartitionTransform::Day,
+                    ("hour", [_]) => PartitionTransform::Hour,
+                    ("bucket", [_, num_buckets]) => {
+                        PartitionTransform::Bucket(num_buckets.parse().map_err(|_| {
+                            invalid(format!("invalid number of buckets \"{}\"", num_buckets))
+                        })?)
+                    }
+                    _ => return Err(invalid(format!("unknown transform \"{}\"", part))),
+                };
+                if args[0].is_empty() {
+                    return Err(invalid(format!("missing column in \"{}\"", part)));
+                }
+                Ok(PartitionField::new(args[0], transform))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::try_new(fields)
+    }
+}
+
+/// Write `data` into one or more fragments per partition of `spec`.
+///
+/// Rows are buffered per partition until the partition has enough rows to fill
+/// a file, so memory use grows with the number of partitions in the data.
+pub(crate) async fn write_partitioned_fragments(
+    dataset: Option<&Dataset>,
+    object_store: Arc<ObjectStore>,
+    base_dir: &Path,
+    schema: Schema,
+    mut data: SendableRecordBatchStream,
+    params: WriteParams,
+    spec: &PartitionSpec,
+) -> Result<WrittenFragments> {
+    spec.validate(&schema)?;
+    if schema.fields.iter().any(|f| !f.is_default_storage()) {
+        return Err(Error::NotSupported {
+            source: "Partitioned datasets do not support the blob storage class".into(),
+            location: location!(),
+        });
+    }
+
+    let arrow_schema = data.schema();
+    let write_partition = |partition: Vec<PartitionValue>, batches: Vec<RecordBatch>| {
+        let stream = RecordBatchStreamAdapter::new(
+            arrow_schema.clone(),
+            futures::stream::iter(batches.into_iter().map(Ok)),
+        );
+        let object_store = object_store.clone();
+        let schema = schema.clone();
+        let params = params.clone();
+        async move {
+            let mut written = write_fragments_internal(
+                dataset,
+                object_store,
+                base_dir,
+                schema,
+                Box::pin(stream),
+                params,
+            )
+            .await?;
+            for fragment in written.default.0.iter_mut() {
+                fragment.partition.clone_from(&partition);
+            }
+            Result::Ok(written)
+        }
+    };
+
+    let mut buffered: HashMap<Vec<PartitionValue>, (Vec<RecordBatch>, usize)> = HashMap::new();
+    // Keep the partitions in the order they first appear so writes are deterministic
+    let mut order: Vec<Vec<PartitionValue>> = Vec::new();
+    let mut fragments = Vec::new();
+    let mut frag_schema = None;
+    while let Some(batch) = data.next().await {
+        for (partition, batch) in spec.split_batch(&batch?)? {
+            let (batches, num_rows) = buffered.entry(partition.clone()).or_insert_with(|| {
+                order.push(partition.clone());
+                (Vec::new(), 0)
+            });
+            *num_rows += batch.num_rows();
+            batches.push(batch);
+            if *num_rows >= params.max_rows_per_file {
+                let batches = std::mem::take(batches);
+                *num_rows = 0;
+                let written = write_partition(partition, batches).await?;
+                fragments.extend(written.default.0);
+                frag_schema = Some(written.default.1);
+            }
+        }
+    }
+    for partition in order {
+        let (batches, num_rows) = buffered.remove(&partition).unwrap();
+        if num_rows > 0 {
+            let written = write_partition(partition, batches).await?;
+            fragments.extend(written.default.0);
+            frag_schema = Some(written.default.1);
+        }
+    }
+
+    let frag_schema = match frag_schema {
+        Some(frag_schema) => frag_schema,
+        // No data, but we still need the schema of the fragments
+        None => write_partition(vec![], vec![]).await?.default.1,
+    };
+    Ok(WrittenFragments {
+        default: (fragments, frag_schema),
+        blob: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{Int64Array, StringArray, TimestampMicrosecondArray};
+    use arrow_schema::{Field as ArrowField, Schema as ArrowSchema, TimeUnit};
+    use datafusion::physical_plan::ExecutionPlan;
+    use datafusion::prelude::{cast, col, lit};
+    use lance_datagen::{array, gen, BatchCount, RowCount};
+
+    use crate::dataset::optimize::{compact_files, CompactionOptions};
+    use crate::dataset::{InsertBuilder, WriteMode};
+    use crate::io::exec::LanceScanExec;
+
+    #[test]
+    fn test_parse_partition_spec() {
+        let spec: PartitionSpec = "bucket(user_id, 64), day(ts)".parse().unwrap();
+        assert_eq!(
+            spec.fields(),
+            &[
+                PartitionField::new("user_id", PartitionTransform::Bucket(64)),
+                PartitionField::new("ts", PartitionTransform::Day),
+            ]
+        );
+        assert_eq!(spec.to_string(), "bucket(user_id, 64), day(ts)");
+
+        let spec: PartitionSpec = "region, Month(ts)".parse().unwrap();
+        assert_eq!(spec.to_string(), "identity(region), month(ts)");
+
+        for invalid in ["", "bucket(x)", "bucket(x, 0)", "week(ts)", "day(ts"] {
+            assert!(invalid.parse::<PartitionSpec>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_transforms() {
+        // Test vectors from the Iceberg spec
+        assert_eq!(murmur3_32(&34i64.to_le_bytes()), 2017239379);
+        assert_eq!(murmur3_32(b"iceberg"), 1210000089);
+
+        let ts = ScalarValue::TimestampMicrosecond(Some(1_700_000_000_000_000), None);
+        assert_eq!(
+            PartitionTransform::Day.apply(&ts),
+            Some(PartitionValue::Int(19675))
+        );
+        assert_eq!(
+            PartitionTransform::Hour.apply(&ts),
+            Some(PartitionValue::Int(472222))
+        );
+        assert_eq!(
+            PartitionTransform::Month.apply(&ts),
+            Some(PartitionValue::Int(53 * 12 + 10))
+        );
+        assert_eq!(
+            PartitionTransform::Year.apply(&ScalarValue::Date32(Some(-1))),
+            Some(PartitionValue::Int(-1))
+        );
+        // Integers hash the same regardless of their width
+        assert_eq!(
+            PartitionTransform::Bucket(16).apply(&ScalarValue::Int32(Some(34))),
+            PartitionTransform::Bucket(16).apply(&ScalarValue::Int64(Some(34)))
+        );
+        assert_eq!(
+            PartitionTransform::Bucket(u32::MAX).apply(&ScalarValue::Int64(Some(34))),
+            Some(PartitionValue::Int(2017239379))
+        );
+        assert_eq!(
+            PartitionTransform::Bucket(16).apply(&ScalarValue::Int32(None)),
+            Some(PartitionValue::Null)
+        );
+        assert_eq!(
+            PartitionTransform::Day.apply(&ScalarValue::Utf8(Some("a".into()))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_may_match() {
+        let spec: PartitionSpec = "bucket(id, 4), day(ts)".parse().unwrap();
+        let day = |d: i64| ScalarValue::TimestampMicrosecond(Some(d * MICROS_PER_DAY + 7), None);
+        let id_bucket = PartitionTransform::Bucket(4)
+            .apply(&ScalarValue::Int64(Some(7)))
+            .unwrap();
+        let partition = vec![id_bucket, PartitionValue::Int(10)];
+        let schema = Schema::try_from(&ArrowSchema::new(vec![
+            ArrowField::new("id", DataType::Int64, false),
+            ArrowField::new("ts", DataType::Timestamp(TimeUnit::Microsecond, None), true),
+        ]))
+        .unwrap();
+
+        let matches = |expr: Expr| spec.may_match(&expr, &schema, &partition);
+        assert!(matches(col("id").eq(lit(7i64))));
+        assert!(matches(
+            col("id").eq(lit(7i64)).and(col("ts").lt(lit(day(10))))
+        ));
+        assert!(!matches(
+            col("id").eq(lit(7i64)).and(col("ts").lt(lit(day(9))))
+        ));
+        assert!(matches(col("ts").lt_eq(lit(day(10)))));
+        assert!(matches(col("ts").gt_eq(lit(day(10)))));
+        assert!(!matches(col("ts").gt(lit(day(11)))));
+        assert!(!matches(col("ts").lt(lit(day(9)))));
+        assert!(matches(col("ts").between(lit(day(9)), lit(day(12)))));
+        assert!(!matches(col("ts").between(lit(day(11)), lit(day(12)))));
+        assert!(matches(lit(day(10)).eq(col("ts"))));
+        assert!(!matches(
+            col("ts").in_list(vec![lit(day(1)), lit(day(2))], false)
+        ));
+        assert!(matches(
+            col("ts").eq(lit(day(1))).or(col("ts").eq(lit(day(10))))
+        ));
+        assert!(!matches(col("ts").is_null()));
+        // Filters on other columns or expressions cannot prune
+        assert!(matches(col("other").eq(lit(1))));
+        assert!(matches(col("id").gt(lit(100i64))));
+        assert!(matches(!col("ts").eq(lit(day(1)))));
+        // Fragments with an unknown partition are never pruned
+        assert!(spec.may_match(&col("ts").eq(lit(day(1))), &schema, &[]));
+
+        // Casts prune only when they keep the partition of the value
+        let date = |d: i32| ScalarValue::Date32(Some(d));
+        let nanos =
+            |d: i64| ScalarValue::TimestampNanosecond(Some((d * MICROS_PER_DAY + 7) * 1000), None);
+        let cast_ts = |data_type: DataType| cast(col("ts"), data_type);
+        assert!(!matches(cast_ts(DataType::Date32).eq(lit(date(9)))));
+        assert!(matches(cast_ts(DataType::Date32).eq(lit(date(10)))));
+        let ns = DataType::Timestamp(TimeUnit::Nanosecond, None);
+        assert!(!matches(cast_ts(ns.clone()).gt(lit(nanos(11)))));
+        assert!(matches(cast_ts(ns).eq(lit(nanos(10)))));
+        // A cast to a coarser unit than the column is not exact
+        let secs = DataType::Timestamp(TimeUnit::Second, None);
+        assert!(matches(
+            cast_ts(secs).eq(lit(ScalarValue::TimestampSecond(Some(0), None)))
+        ));
+        assert!(matches(cast_ts(DataType::Int64).eq(lit(0i64))));
+        assert!(matches(cast_ts(DataType::Utf8).is_null()));
+
+        let spec: PartitionSpec = "hour(ts)".parse().unwrap();
+        let noon = 10 * 24 + 13;
+        // CAST(ts AS DATE) = day 10 matches every hour of day 10
+        assert!(spec.may_match(
+            &cast_ts(DataType::Date32).eq(lit(date(10))),
+            &schema,
+            &[PartitionValue::Int(noon)]
+        ));
+        assert!(!spec.may_match(
+            &col("ts").eq(lit(day(10))),
+            &schema,
+            &[PartitionValue::Int(noon)]
+        ));
+    }
+
+    #[test]
+    fn test_may_match_cast_timezone() {
+        let spec: PartitionSpec = "day(ts)".parse().unwrap();
+        let date = |d: i32| ScalarValue::Date32(Some(d));
+        for (tz, exact) in [("UTC", true), ("+00:00", true), ("America/New_York", false)] {
+            let schema = Schema::try_from(&ArrowSchema::new(vec![ArrowField::new(
+                "ts",
+                DataType::Timestamp(TimeUnit::Microsecond, Some(tz.into())),
+                true,
+            )]))
+            .unwrap();
+            // 2:00 UTC on day 10 is still day 9 in New York
+            let partition = [PartitionValue::Int(10)];
+            let filter = cast(col("ts"), DataType::Date32).eq(lit(date(9)));
+            assert_eq!(
+                spec.may_match(&filter, &schema, &partition),
+                !exact,
+                "{}",
+                tz
+            );
+            let filter = cast(col("ts"), DataType::Date32).eq(lit(date(10)));
+            assert!(spec.may_match(&filter, &schema, &partition), "{}", tz);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_write_scan_and_compact() {
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("id", DataType::Int64, false),
+            ArrowField::new("category", DataType::Utf8, false),
+            ArrowField::new(
+                "ts",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+        ]));
+        let make_batch = |offset: i64| {
+            let ids = (offset..offset + 100).collect::<Vec<_>>();
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int64Array::from(ids.clone())),
+                    Arc::new(StringArray::from_iter_values(
+                        ids.iter().map(|i| ["a", "b", "c"][*i as usize % 3]),
+                    )),
+                    Arc::new(TimestampMicrosecondArray::from_iter_values(
+                        ids.iter().map(|i| (i % 4) * MICROS_PER_DAY),
+                    )),
+                ],
+            )
+            .unwrap()
+        };
+
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let spec: PartitionSpec = "category, day(ts)".parse().unwrap();
+        let params = WriteParams {
+            partition_spec: Some(spec.clone()),
+            ..Default::default()
+        };
+        let dataset = InsertBuilder::new(test_uri)
+            .with_params(&params)
+            .execute(vec![make_batch(0)])
+            .await
+            .unwrap();
+        assert_eq!(
+            PartitionSpec::from_manifest(dataset.manifest()).unwrap(),
+            Some(spec.clone())
+        );
+        // 3 categories x 4 days
+        assert_eq!(dataset.get_fragments().len(), 12);
+
+        // Appends pick up the spec from the dataset
+        let dataset = InsertBuilder::new(Arc::new(dataset))
+            .with_params(&WriteParams {
+                mode: WriteMode::Append,
+                ..Default::default()
+            })
+            .execute(vec![make_batch(100)])
+            .await
+            .unwrap();
+        assert_eq!(dataset.get_fragments().len(), 24);
+        for fragment in dataset.get_fragments() {
+            let batch = fragment.scan().try_into_batch().await.unwrap();
+            let (partition, _) = spec.split_batch(&batch).unwrap().pop().unwrap();
+            assert_eq!(partition, fragment.metadata().partition);
+        }
+
+        let filter = "category = 'b' AND ts >= TIMESTAMP '1970-01-03 00:00:00'";
+        let mut scanner = dataset.scan();
+        scanner.filter(filter).unwrap();
+        let plan = scanner.create_plan().await.unwrap();
+        assert_eq!(scanned_fragments(plan.as_ref()), 4);
+        let batch = scanner.try_into_batch().await.unwrap();
+        let expected = (0..200)
+            .filter(|i| i % 3 == 1 && i % 4 >= 2)
+            .collect::<Vec<i64>>();
+        let mut ids = batch["id"]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap()
+            .values()
+            .to_vec();
+        ids.sort();
+        assert_eq!(ids, expected);
+        assert_eq!(
+            dataset.count_rows(Some(filter.to_string())).await.unwrap(),
+            expected.len()
+        );
+
+        // Compaction merges the fragments of each partition, but not across partitions
+        let mut dataset = dataset;
+        compact_files(&mut dataset, CompactionOptions::default(), None)
+            .await
+            .unwrap();
+        let fragments = dataset.get_fragments();
+        assert_eq!(fragments.len(), 12);
+        for fragment in fragments {
+            let batch = fragment.scan().try_into_batch().await.unwrap();
+            let partitions = spec.split_batch(&batch).unwrap();
+            assert_eq!(partitions.len(), 1);
+            assert_eq!(partitions[0].0, fragment.metadata().partition);
+        }
+        assert_eq!(dataset.count_rows(None).await.unwrap(), 200);
+    }
+
+    fn scanned_fragments(plan: &dyn ExecutionPlan) -> usize {
+        match plan.as_any().downcast_ref::<LanceScanExec>() {
+            Some(scan) => scan.fragments().len(),
+            None => plan
+                .children()
+                .into_iter()
+                .map(|child| scanned_fragments(child.as_ref()))
+                .sum(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_partition_spec() {
+        let reader = gen()
+            .col("x", array::step::<arrow_array::types::Float32Type>())
+            .into_reader_rows(RowCount::from(10), BatchCount::from(1));
+        let batches = reader.collect::<std::result::Result<Vec<_>, _>>().unwrap();
+        let params = WriteParams {
+            partition_spec: Some("bucket(x, 4)".parse().unwrap()),
+            ..Default::default()
+        };
+        let err = InsertBuilder::new("memory://")
+            .with_params(&params)
+            .execute(batches)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("does not support column x"),
+            "{}",
+            err
+        );
+    }
+}
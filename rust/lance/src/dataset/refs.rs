@@ -3,23 +3,34 @@
 
 use std::ops::Range;
 
+use chrono::{DateTime, Utc};
 use futures::future;
 use futures::stream::{StreamExt, TryStreamExt};
 use itertools::Itertools;
 use lance_io::object_store::ObjectStore;
-use lance_table::io::commit::CommitHandler;
+use lance_table::io::commit::{CommitError, CommitHandler, ManifestNamingScheme};
+use lance_table::io::manifest::{read_manifest, read_manifest_indexes};
 use object_store::path::Path;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use super::{write_manifest_file, ManifestWriteConfig};
+use crate::utils::temporal::timestamp_to_nanos;
 use crate::{Error, Result};
 use std::collections::HashMap;
 
+/// The name of the branch that holds the dataset's root history.
+pub const MAIN_BRANCH: &str = "main";
+
 /// Lance Ref
 #[derive(Debug, Clone)]
 pub enum Ref {
     Version(u64),
     Tag(String),
+    /// The latest version of a branch.
+    Branch(String),
+    /// The latest version committed at or before the given instant.
+    Timestamp(DateTime<Utc>),
 }
 
 impl From<u64> for Ref {
@@ -34,6 +45,50 @@ impl From<&str> for Ref {
     }
 }
 
+impl From<DateTime<Utc>> for Ref {
+    fn from(ref_: DateTime<Utc>) -> Self {
+        Self::Timestamp(ref_)
+    }
+}
+
+/// Find the latest version in the manifest chain at `base` that was committed
+/// at or before `timestamp`.
+///
+/// The timestamps come from the clocks of the writers, so they may not increase
+/// with the version number.  A binary search over the versions would assume they
+/// do, and could skip over a version committed at or before the timestamp by a
+/// writer whose clock was behind.  So the manifests are read from the latest
+/// version backwards, until one was committed at or before the timestamp, which
+/// costs one manifest read per version committed after it.
+///
+/// With the V2 naming scheme on stores that list in lexical order, the listing
+/// is streamed from the latest version, so only the versions committed after
+/// the timestamp are listed too.  Other datasets list all the versions first.
+pub(crate) async fn resolve_version_as_of(
+    object_store: &ObjectStore,
+    commit_handler: &dyn CommitHandler,
+    base: &Path,
+    timestamp: DateTime<Utc>,
+) -> Result<u64> {
+    // The locations are listed from the latest version
+    let mut manifests = commit_handler
+        .list_manifest_locations(base, object_store, true)
+        .map(|location| async move {
+            let location = location?;
+            let manifest = read_manifest(object_store, &location.path, location.size).await?;
+            Ok::<_, Error>((location.version, manifest.timestamp()))
+        })
+        .buffered(object_store.io_parallelism());
+    while let Some((version, committed_at)) = manifests.try_next().await? {
+        if committed_at <= timestamp {
+            return Ok(version);
+        }
+    }
+    Err(Error::VersionNotFound {
+        message: format!("no version was committed at or before {}", timestamp),
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct Tags {
     object_store: Arc<ObjectStore>,
@@ -207,6 +262,246 @@ impl Tags {
     }
 }
 
+/// Git-style branches of a dataset.
+///
+/// Every branch has its own manifest chain, stored under `tree/{branch}`, while
+/// data, deletion, index and transaction files are shared with the rest of the
+/// dataset.  A branch starts as a copy of the manifest it was created from, so
+/// it can be committed to without moving the main history.
+#[derive(Debug, Clone)]
+pub struct Branches {
+    object_store: Arc<ObjectStore>,
+    commit_handler: Arc<dyn CommitHandler>,
+    base: Path,
+}
+
+impl Branches {
+    pub fn new(
+        object_store: Arc<ObjectStore>,
+        commit_handler: Arc<dyn CommitHandler>,
+        base: Path,
+    ) -> Self {
+        Self {
+            object_store,
+            commit_handler,
+            base,
+        }
+    }
+
+    /// Get all branches, not including the main branch.
+    pub async fn list(&self) -> Result<HashMap<String, BranchContents>> {
+        let mut branches = HashMap::<String, BranchContents>::new();
+
+        let branch_files = self
+            .object_store()
+            .read_dir(base_branches_path(&self.base))
+            .await?;
+
+        let branch_names: Vec<String> = branch_files
+            .iter()
+            .filter_map(|name| name.strip_suffix(".json"))
+            .map(|name| name.to_string())
+            .collect_vec();
+
+        futures::stream::iter(branch_names)
+            .map(|branch_name| {
+                let branch_file = branch_path(&self.base, &branch_name);
+                async move {
+                    let contents =
+                        BranchContents::from_path(&branch_file, self.object_store()).await?;
+                    Ok((branch_name, contents))
+                }
+            })
+            .buffer_unordered(10)
+            .try_for_each(|result| {
+                let (branch_name, contents) = result;
+                branches.insert(branch_name, contents);
+                future::ready(Ok::<(), Error>(()))
+            })
+            .await?;
+
+        Ok(branches)
+    }
+
+    pub async fn get(&self, branch: &str) -> Result<BranchContents> {
+        check_valid_branch(branch)?;
+
+        let branch_file = branch_path(&self.base, branch);
+
+        if !self.object_store().exists(&branch_file).await? {
+            return Err(Error::RefNotFound {
+                message: format!("branch {} does not exist", branch),
+            });
+        }
+
+        BranchContents::from_path(&branch_file, self.object_store()).await
+    }
+
+    /// Create a branch forked from `version` of `parent_branch`.
+    ///
+    /// `parent_branch` is `None` for the main branch.
+    pub async fn create(
+        &mut self,
+        branch: &str,
+        parent_branch: Option<&str>,
+        version: u64,
+    ) -> Result<()> {
+        check_valid_branch(branch)?;
+
+        let branch_file = branch_path(&self.base, branch);
+
+        if self.object_store().exists(&branch_file).await? {
+            return Err(Error::RefConflict {
+                message: format!("branch {} already exists", branch),
+            });
+        }
+
+        if let Some(parent) = parent_branch {
+            self.get(parent).await?;
+        }
+
+        let parent_base = branch_base_path(&self.base, parent_branch);
+        let manifest_file = self
+            .commit_handler
+            .resolve_version_location(&parent_base, version, &self.object_store.inner)
+            .await?;
+
+        if !self.object_store().exists(&manifest_file.path).await? {
+            return Err(Error::VersionNotFound {
+                message: format!("version {} does not exist", version),
+            });
+        }
+
+        // The branch starts with a copy of the parent manifest, which references the
+        // same fragments and indices, so no data needs to be copied.
+        let mut manifest =
+            read_manifest(self.object_store(), &manifest_file.path, manifest_file.size).await?;
+        let indices = read_manifest_indexes(self.object_store(), &manifest_file, &manifest).await?;
+        write_manifest_file(
+            self.object_store(),
+            self.commit_handler.as_ref(),
+            &branch_base_path(&self.base, Some(branch)),
+            &mut manifest,
+            if indices.is_empty() {
+                None
+            } else {
+                Some(indices)
+            },
+            &ManifestWriteConfig::default(),
+            ManifestNamingScheme::V2,
+        )
+        .await
+        .map_err(|e| match e {
+            CommitError::CommitConflict => Error::RefConflict {
+                message: format!("branch {} already exists", branch),
+            },
+            CommitError::OtherError(e) => e,
+        })?;
+
+        let branch_contents = BranchContents {
+            parent_branch: parent_branch.map(|b| b.to_string()),
+            parent_version: version,
+            created_at: (timestamp_to_nanos(None) / 1_000_000_000) as u64,
+        };
+
+        self.object_store()
+            .put(
+                &branch_file,
+                serde_json::to_string_pretty(&branch_contents)?.as_bytes(),
+            )
+            .await
+            .map(|_| ())
+    }
+
+    /// Delete a branch and its manifests.
+    ///
+    /// Data files that were only referenced by the branch are removed by the next
+    /// [`crate::Dataset::cleanup_old_versions`].
+    pub async fn delete(&mut self, branch: &str) -> Result<()> {
+        check_valid_branch(branch)?;
+
+        let branch_file = branch_path(&self.base, branch);
+
+        if !self.object_store().exists(&branch_file).await? {
+            return Err(Error::RefNotFound {
+                message: format!("branch {} does not exist", branch),
+            });
+        }
+
+        let children = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|(_, contents)| contents.parent_branch.as_deref() == Some(branch))
+            .map(|(name, _)| name)
+            .sorted()
+            .collect_vec();
+        if !children.is_empty() {
+            return Err(Error::RefConflict {
+                message: format!(
+                    "branch {} cannot be deleted, it is the parent of branches {:?}",
+                    branch, children
+                ),
+            });
+        }
+
+        self.object_store()
+            .remove_dir_all(branch_base_path(&self.base, Some(branch)))
+            .await?;
+        self.object_store().delete(&branch_file).await
+    }
+
+    pub(crate) fn object_store(&self) -> &ObjectStore {
+        &self.object_store
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchContents {
+    /// The branch this branch was forked from, `None` for the main branch.
+    pub parent_branch: Option<String>,
+    /// The version of the parent branch this branch was forked from.
+    pub parent_version: u64,
+    /// Creation time, in seconds since the epoch.
+    pub created_at: u64,
+}
+
+impl BranchContents {
+    pub async fn from_path(path: &Path, object_store: &ObjectStore) -> Result<Self> {
+        let bytes = object_store.read_one_all(path).await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+pub fn base_branches_path(base_path: &Path) -> Path {
+    base_path.child("_refs").child("branches")
+}
+
+pub fn branch_path(base_path: &Path, branch: &str) -> Path {
+    base_branches_path(base_path).child(format!("{}.json", branch))
+}
+
+/// The root under which the manifests of `branch` are stored.
+///
+/// The main branch (`None`) keeps its manifests in the dataset root.
+pub fn branch_base_path(base_path: &Path, branch: Option<&str>) -> Path {
+    match branch {
+        Some(branch) => base_path.child("tree").child(branch),
+        None => base_path.clone(),
+    }
+}
+
+fn check_valid_branch(s: &str) -> Result<()> {
+    check_valid_ref(s)?;
+    if s == MAIN_BRANCH {
+        return Err(Error::InvalidRef {
+            message: format!("Ref {} is reserved for the main branch", MAIN_BRANCH),
+        });
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TagContents {
@@ -17,8 +17,12 @@ pub async fn load_row_id_sequence(
     dataset: &Dataset,
     fragment: &Fragment,
 ) -> Result<Arc<RowIdSequence>> {
-    // Virtual path to prevent collisions in the cache.
-    let path = dataset.base.child(fragment.id.to_string()).child("row_ids");
+    // Virtual path to prevent collisions in the cache. Fragment ids are only
+    // unique within a branch, so the path is rooted at the branch.
+    let path = dataset
+        .manifest_base()
+        .child(fragment.id.to_string())
+        .child("row_ids");
     match &fragment.row_id_meta {
         None => Err(Error::Internal {
             message: "Missing row id meta".into(),
@@ -73,7 +77,7 @@ pub async fn get_row_id_index(
     if dataset.manifest.uses_move_stable_row_ids() {
         // The path here isn't real, it's just used to prevent collisions in the cache.
         let path = dataset
-            .base
+            .manifest_base()
             .child("row_ids")
             .child(dataset.manifest.version.to_string());
         let index = dataset
@@ -8,7 +8,7 @@ use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use arrow::array::AsArray;
-use arrow_array::{Array, Float32Array, Int64Array, RecordBatch};
+use arrow_array::{Array, FixedSizeListArray, Float32Array, Int64Array, RecordBatch};
 use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef, SortOptions};
 use arrow_select::concat::concat_batches;
 use async_recursion::async_recursion;
@@ -39,19 +39,25 @@ use futures::future::BoxFuture;
 use futures::stream::{Stream, StreamExt};
 use futures::{FutureExt, TryStreamExt};
 use lance_arrow::floats::{coerce_float_vector, FloatType};
-use lance_arrow::DataTypeExt;
-use lance_core::datatypes::{Field, OnMissing, Projection};
+use lance_arrow::sparse::{is_sparse_vector_field, SparseVector, SPARSE_VECTOR_EXT_NAME};
+use lance_arrow::{DataTypeExt, FixedSizeListArrayExt};
+use lance_core::datatypes::{Field, OnMissing, Projection, BLOB_META_KEY};
 use lance_core::utils::tokio::get_num_compute_intensive_cpus;
 use lance_core::{ROW_ADDR, ROW_ADDR_FIELD, ROW_ID, ROW_ID_FIELD};
 use lance_datafusion::exec::{analyze_plan, execute_plan, LanceExecutionOptions};
 use lance_datafusion::projection::ProjectionPlan;
 use lance_index::scalar::expression::PlannerIndexExt;
+use lance_index::scalar::inverted::highlight::{
+    HighlightParams, HIGHLIGHT_COL, HIGHLIGHT_FIELD, MATCH_OFFSETS_COL, MATCH_OFFSETS_FIELD,
+};
 use lance_index::scalar::inverted::query::{
-    fill_fts_query_column, FtsQuery, FtsSearchParams, MatchQuery,
+    fill_fts_query_column, FtsQuery, FtsQueryNode, FtsSearchParams, MatchQuery, MultiMatchQuery,
 };
+use lance_index::scalar::inverted::scorer::ScoringModel;
 use lance_index::scalar::inverted::SCORE_COL;
+use lance_index::scalar::sparse::SparseQuery;
 use lance_index::scalar::{FullTextSearchQuery, ScalarIndexType};
-use lance_index::vector::{Query, DIST_COL};
+use lance_index::vector::{Query, DIST_COL, QUERY_INDEX_COL};
 use lance_index::ScalarIndexCriteria;
 use lance_index::{metrics::NoOpMetricsCollector, scalar::inverted::FTS_SCHEMA};
 use lance_index::{scalar::expression::ScalarIndexExpr, DatasetIndexExt};
@@ -61,13 +67,26 @@ use lance_table::format::{Fragment, Index};
 use roaring::RoaringBitmap;
 use tracing::{info_span, instrument, Span};
 
+use super::hybrid::{Reranker, RELEVANCE_SCORE_COL};
+use super::partition::PartitionSpec;
+use super::statistics::{FragmentMatch, FragmentStatsPruner};
 use super::Dataset;
 use crate::index::scalar::detect_scalar_index_type;
+use crate::index::vector::ivf::IVFIndex;
 use crate::index::vector::utils::{get_vector_dim, get_vector_type};
-use crate::index::DatasetIndexInternalExt;
-use crate::io::exec::fts::{BoostQueryExec, FlatMatchQueryExec, MatchQueryExec, PhraseQueryExec};
-use crate::io::exec::knn::MultivectorScoringExec;
+use crate::index::{vector_index_refine_factor, DatasetIndexInternalExt};
+use crate::io::exec::diversify::{CandidatesPlanner, DiversifyExec, DiversityParams};
+use crate::io::exec::fts::{
+    BoostQueryExec, FlatMatchQueryExec, FlatPatternQueryExec, HighlightExec, MatchQueryExec,
+    MultiMatchQueryExec, PatternQueryExec, PhraseQueryExec,
+};
+use crate::io::exec::hybrid::HybridSearchExec;
+use crate::io::exec::knn::{
+    BatchANNExec, BatchKNNExec, MultivectorScoringExec, MultivectorSearchExec,
+};
 use crate::io::exec::scalar_index::{MaterializeIndexExec, ScalarIndexExec};
+use crate::io::exec::sparse::{FlatSparseSearchExec, SparseSearchExec};
+use crate::io::exec::utils::CachedExec;
 use crate::io::exec::{get_physical_optimizer, LanceFilterExec, LanceScanConfig};
 use crate::io::exec::{
     knn::new_knn_exec, project, AddRowAddrExec, FilterPlan, KNNVectorDistanceExec,
@@ -97,6 +116,12 @@ lazy_static::lazy_static! {
 
     pub static ref DEFAULT_XTR_OVERFETCH: u32 = std::env::var("LANCE_XTR_OVERFETCH")
         .map(|val| val.parse().unwrap()).unwrap_or(10);
+
+    pub static ref DEFAULT_MULTIVECTOR_CANDIDATES_FACTOR: usize = std::env::var("LANCE_MULTIVECTOR_CANDIDATES_FACTOR")
+        .map(|val| val.parse().unwrap()).unwrap_or(16);
+
+    pub static ref DEFAULT_DIVERSITY_OVERFETCH: usize = std::env::var("LANCE_DIVERSITY_OVERFETCH")
+        .map(|val| val.parse().unwrap()).unwrap_or(4);
 }
 
 // We want to support ~256 concurrent reads to maximize throughput on cloud storage systems
@@ -111,6 +136,7 @@ lazy_static::lazy_static! {
 ///
 /// Floats are sorted using the IEEE 754 total ordering
 /// Strings are sorted using UTF-8 lexicographic order (i.e. we sort the binary)
+#[derive(Clone)]
 pub struct ColumnOrdering {
     pub ascending: bool,
     pub nulls_first: bool,
@@ -164,6 +190,7 @@ impl ColumnOrdering {
 ///
 /// This parameter only affects scans.  Vector search and full text search
 /// always use late materialization.
+#[derive(Clone)]
 pub enum MaterializationStyle {
     /// Heuristic-based materialization style
     ///
@@ -198,7 +225,7 @@ impl MaterializationStyle {
 }
 
 /// Filter for filtering rows
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LanceFilter {
     /// The filter is an SQL string
     Sql(String),
@@ -260,6 +287,7 @@ impl LanceFilter {
 ///   .buffered(16)
 ///   .sum()
 /// ```
+#[derive(Clone)]
 pub struct Scanner {
     dataset: Arc<Dataset>,
 
@@ -277,6 +305,18 @@ pub struct Scanner {
     /// Optional full text search query
     full_text_query: Option<FullTextSearchQuery>,
 
+    /// Optional top-k dot product query over a sparse vector column
+    sparse_query: Option<SparseQuery>,
+
+    /// Fuses the results of `nearest` and `full_text_search` if set
+    reranker: Option<Arc<dyn Reranker>>,
+
+    /// Whether the key of `nearest` is a batch of flattened queries
+    nearest_batch: bool,
+
+    /// How the results of `nearest` are diversified
+    diversity: DiversityParams,
+
     /// The batch size controls the maximum size of rows to return for each read.
     batch_size: Option<usize>,
 
@@ -349,6 +389,42 @@ pub struct Scanner {
     /// Mainly, if the result is returned strictly according to the batch_size,
     /// batching and waiting are required, and the performance will decrease.
     strict_batch_size: bool,
+
+    /// Whether string and binary columns are returned as `Utf8View` / `BinaryView`
+    use_view_types: bool,
+}
+
+/// Casts the top level string and binary columns of `plan` that are not view types yet
+fn cast_to_view_types(plan: Arc<dyn ExecutionPlan>) -> Result<Arc<dyn ExecutionPlan>> {
+    let schema = plan.schema();
+    let mut needs_cast = false;
+    let exprs = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let column: Arc<dyn PhysicalExpr> = Arc::new(Column::new(field.name(), idx));
+            let view_type = match field.data_type() {
+                _ if field.metadata().contains_key(BLOB_META_KEY) => None,
+                DataType::Utf8 | DataType::LargeUtf8 => Some(DataType::Utf8View),
+                DataType::Binary | DataType::LargeBinary => Some(DataType::BinaryView),
+                _ => None,
+            };
+            let expr = match view_type {
+                Some(view_type) => {
+                    needs_cast = true;
+                    expressions::cast(column, &schema, view_type)?
+                }
+                None => column,
+            };
+            Ok((expr, field.name().clone()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if needs_cast {
+        Ok(Arc::new(DFProjectionExec::try_new(exprs, plan)?))
+    } else {
+        Ok(plan)
+    }
 }
 
 fn escape_column_name(name: &str) -> String {
@@ -372,6 +448,10 @@ impl Scanner {
             materialization_style: MaterializationStyle::Heuristic,
             filter: None,
             full_text_query: None,
+            sparse_query: None,
+            reranker: None,
+            nearest_batch: false,
+            diversity: DiversityParams::default(),
             batch_size: None,
             batch_readahead: get_num_compute_intensive_cpus(),
             fragment_readahead: None,
@@ -390,6 +470,7 @@ impl Scanner {
             include_deleted_rows: false,
             scan_stats_callback: None,
             strict_batch_size: false,
+            use_view_types: false,
         }
     }
 
@@ -556,6 +637,76 @@ impl Scanner {
         Ok(self)
     }
 
+    /// Find the k rows of a sparse vector column with the highest dot product with the query.
+    ///
+    /// Only rows with a positive score are returned, and the score is returned in the
+    /// `_score` column. A sparse index is used if the column has one.
+    ///
+    /// ```rust,ignore
+    /// let query = SparseVector::from_pairs([(17, 0.5), (1024, 1.2)]);
+    /// let stream = dataset.scan()
+    ///    .nearest_sparse("splade", query, 10).unwrap()
+    ///    .into_stream();
+    /// ```
+    pub fn nearest_sparse(
+        &mut self,
+        column: &str,
+        query: SparseVector,
+        k: usize,
+    ) -> Result<&mut Self> {
+        if k == 0 {
+            return Err(Error::invalid_input(
+                "k must be positive".to_string(),
+                location!(),
+            ));
+        }
+        let field = self
+            .dataset
+            .schema()
+            .field(column)
+            .ok_or(Error::invalid_input(
+                format!("Column {} not found", column),
+                location!(),
+            ))?;
+        if !is_sparse_vector_field(&ArrowField::from(field)) {
+            return Err(Error::invalid_input(
+                format!(
+                    "Column {} is not a sparse vector column, it has type {} without the {} extension name",
+                    column,
+                    field.data_type(),
+                    SPARSE_VECTOR_EXT_NAME
+                ),
+                location!(),
+            ));
+        }
+
+        self.sparse_query = Some(SparseQuery {
+            column: column.to_string(),
+            vector: query,
+            k,
+        });
+        Ok(self)
+    }
+
+    /// Run the [`Self::nearest`] and [`Self::full_text_search`] queries together, and fuse
+    /// their results with the reranker.
+    ///
+    /// The results have the `_distance` of the vector search, the `_score` of the full
+    /// text search, and the fused `_relevance_score`, sorted by the relevance score.
+    /// Both searches share the same prefilter.
+    ///
+    /// ```rust,ignore
+    /// let stream = dataset.scan()
+    ///    .nearest("vector", &query_vector, 10).unwrap()
+    ///    .full_text_search(FullTextSearchQuery::new("hello".to_owned())).unwrap()
+    ///    .hybrid(Arc::new(RRFReranker::default()))
+    ///    .into_stream();
+    /// ```
+    pub fn hybrid(&mut self, reranker: Arc<dyn Reranker>) -> &mut Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
     /// Set a filter using a Substrait ExtendedExpression message
     ///
     /// The message must contain exactly one expression and that expression
@@ -667,6 +818,17 @@ impl Scanner {
         self
     }
 
+    /// Set whether to return string and binary columns as `Utf8View` / `BinaryView`
+    ///
+    /// When enabled, the fragment readers build the views directly over the decoded
+    /// pages.  Columns that do not come from the scan itself (e.g. columns taken after
+    /// a vector search, or any column of a legacy dataset) are cast to the view types
+    /// before they are returned.  Blob columns are not changed.  Defaults to false.
+    pub fn use_view_types(&mut self, use_view_types: bool) -> &mut Self {
+        self.use_view_types = use_view_types;
+        self
+    }
+
     /// Set limit and offset.
     ///
     /// If offset is set, the first offset rows will be skipped. If limit is set,
@@ -696,6 +858,12 @@ impl Scanner {
     /// Find k-nearest neighbor within the vector column.
     /// the query can be a Float16Array, Float32Array, Float64Array, UInt8Array,
     /// or a ListArray/FixedSizeListArray of the above types.
+    ///
+    /// If the column is not a multivector column, a FixedSizeListArray is a batch of
+    /// queries. The k nearest neighbors of each query are returned, and the results are
+    /// tagged with the `query_index` column. An indexed search probes the partitions of
+    /// all the queries together, so each partition is searched only once, and every query
+    /// probes exactly `minimum_nprobes` partitions.
     pub fn nearest(&mut self, column: &str, q: &dyn Array, k: usize) -> Result<&mut Self> {
         if !self.prefilter {
             // We can allow fragment scan if the input to nearest is a prefilter.
@@ -719,7 +887,32 @@ impl Scanner {
         let (vector_type, element_type) = get_vector_type(self.dataset.schema(), column)?;
         let dim = get_vector_dim(self.dataset.schema(), column)?;
 
+        let mut nearest_batch = false;
         let q = match q.data_type() {
+            DataType::FixedSizeList(_, _)
+                if matches!(vector_type, DataType::FixedSizeList(_, _)) =>
+            {
+                let fsl = q.as_fixed_size_list();
+                if fsl.value_length() as usize != dim {
+                    return Err(Error::invalid_input(
+                        format!(
+                            "query dim({}) doesn't match the column {} vector dim({})",
+                            fsl.value_length(),
+                            column,
+                            dim,
+                        ),
+                        location!(),
+                    ));
+                }
+                if fsl.null_count() > 0 {
+                    return Err(Error::invalid_input(
+                        "The batch of queries must not contain nulls".to_string(),
+                        location!(),
+                    ));
+                }
+                nearest_batch = true;
+                fsl.values().slice(fsl.offset() * dim, fsl.len() * dim)
+            }
             DataType::List(_) | DataType::FixedSizeList(_, _) => {
                 if !matches!(vector_type, DataType::List(_)) {
                     return Err(Error::invalid_input(
@@ -811,6 +1004,45 @@ impl Scanner {
             metric_type: MetricType::L2,
             use_index: true,
         });
+        self.nearest_batch = nearest_batch;
+        Ok(self)
+    }
+
+    /// Diversify the nearest neighbors by maximal marginal relevance.
+    ///
+    /// Each result is selected by `lambda * -distance + (1 - lambda) * min_distance`,
+    /// where `min_distance` is the distance from its vector to the nearest result already
+    /// selected. `lambda` must be in `[0, 1]`, 1 doesn't diversify the results at all.
+    pub fn mmr(&mut self, lambda: f32) -> Result<&mut Self> {
+        if !(0.0..=1.0).contains(&lambda) {
+            return Err(Error::invalid_input(
+                format!("mmr lambda must be in the range [0, 1], got {}", lambda),
+                location!(),
+            ));
+        }
+        self.diversity.mmr_lambda = Some(lambda);
+        Ok(self)
+    }
+
+    /// Return at most `limit` nearest neighbors for each value of the column,
+    /// e.g. at most 2 chunks of each document.
+    ///
+    /// The candidates are over-fetched internally, so `k` results are returned
+    /// as long as there are enough distinct values.
+    pub fn group_limit(&mut self, column: &str, limit: usize) -> Result<&mut Self> {
+        if limit == 0 {
+            return Err(Error::invalid_input(
+                "group limit must be positive".to_string(),
+                location!(),
+            ));
+        }
+        self.dataset.schema().field(column).ok_or_else(|| {
+            Error::invalid_input(
+                format!("Column {} does not exist in the dataset", column),
+                location!(),
+            )
+        })?;
+        self.diversity.group_limit = Some((column.to_string(), limit));
         Ok(self)
     }
 
@@ -1000,14 +1232,31 @@ impl Scanner {
     fn get_extra_columns(&self, force_row_id: bool) -> Vec<ArrowField> {
         let mut extra_columns = vec![];
 
+        if self.nearest_batch {
+            extra_columns.push(ArrowField::new(QUERY_INDEX_COL, DataType::UInt32, true));
+        }
+
         if self.nearest.as_ref().is_some() {
             extra_columns.push(ArrowField::new(DIST_COL, DataType::Float32, true));
         };
 
-        if self.full_text_query.is_some() {
+        if self.full_text_query.is_some() || self.sparse_query.is_some() {
             extra_columns.push(ArrowField::new(SCORE_COL, DataType::Float32, true));
         }
 
+        if self.is_highlighted() {
+            extra_columns.push(HIGHLIGHT_FIELD.clone());
+            extra_columns.push(MATCH_OFFSETS_FIELD.clone());
+        }
+
+        if self.is_hybrid() {
+            extra_columns.push(ArrowField::new(
+                RELEVANCE_SCORE_COL,
+                DataType::Float32,
+                true,
+            ));
+        }
+
         if self.with_row_id || force_row_id {
             extra_columns.push(ROW_ID_FIELD.clone());
         }
@@ -1019,6 +1268,16 @@ impl Scanner {
         extra_columns
     }
 
+    fn is_highlighted(&self) -> bool {
+        self.full_text_query
+            .as_ref()
+            .is_some_and(|query| query.highlight.is_some())
+    }
+
+    fn is_hybrid(&self) -> bool {
+        self.reranker.is_some() && self.nearest.is_some() && self.full_text_query.is_some()
+    }
+
     pub(crate) fn scan_input_schema(&self) -> Result<Arc<Schema>> {
         let extra_columns = self.get_extra_columns(false);
 
@@ -1064,26 +1323,65 @@ impl Scanner {
         }
     }
 
-    pub(crate) fn output_expr(&self) -> Result<Vec<(Arc<dyn PhysicalExpr>, String)>> {
+    pub(crate) fn output_expr(
+        &self,
+        input_schema: SchemaRef,
+    ) -> Result<Vec<(Arc<dyn PhysicalExpr>, String)>> {
         // Append the extra columns
-        let mut output_expr = self.projection_plan.to_physical_exprs()?;
+        let mut output_expr = match &self.projection_plan.requested_output_expr {
+            // The projection was planned against the offset based types, so plan
+            // the requested expressions again against the view typed input
+            Some(requested_output_expr) if self.use_view_types => {
+                let planner = Planner::new(input_schema);
+                requested_output_expr
+                    .iter()
+                    .map(|(expr, name)| Ok((planner.create_physical_expr(expr)?, name.clone())))
+                    .collect::<Result<Vec<_>>>()?
+            }
+            _ => self.projection_plan.to_physical_exprs()?,
+        };
 
         let physical_schema = ArrowSchema::from(
             self.scan_output_schema(&self.projection_plan.physical_schema, false)?
                 .as_ref(),
         );
 
+        if self.nearest_batch && output_expr.iter().all(|(_, name)| name != QUERY_INDEX_COL) {
+            let query_index_expr = expressions::col(QUERY_INDEX_COL, &physical_schema)?;
+            output_expr.push((query_index_expr, QUERY_INDEX_COL.to_string()));
+        }
+
         // distance goes before the row_id column
         if self.nearest.is_some() && output_expr.iter().all(|(_, name)| name != DIST_COL) {
             let vector_expr = expressions::col(DIST_COL, &physical_schema)?;
             output_expr.push((vector_expr, DIST_COL.to_string()));
         }
 
-        if self.full_text_query.is_some() && output_expr.iter().all(|(_, name)| name != SCORE_COL) {
+        if (self.full_text_query.is_some() || self.sparse_query.is_some())
+            && output_expr.iter().all(|(_, name)| name != SCORE_COL)
+        {
             let score_expr = expressions::col(SCORE_COL, &physical_schema)?;
             output_expr.push((score_expr, SCORE_COL.to_string()));
         }
 
+        if self.is_highlighted() {
+            for column in [HIGHLIGHT_COL, MATCH_OFFSETS_COL] {
+                if output_expr.iter().all(|(_, name)| name != column) {
+                    let highlight_expr = expressions::col(column, &physical_schema)?;
+                    output_expr.push((highlight_expr, column.to_string()));
+                }
+            }
+        }
+
+        if self.is_hybrid()
+            && output_expr
+                .iter()
+                .all(|(_, name)| name != RELEVANCE_SCORE_COL)
+        {
+            let relevance_expr = expressions::col(RELEVANCE_SCORE_COL, &physical_schema)?;
+            output_expr.push((relevance_expr, RELEVANCE_SCORE_COL.to_string()));
+        }
+
         if self.with_row_id && output_expr.iter().all(|(_, name)| name != ROW_ID) {
             let row_id_expr = expressions::col(ROW_ID, &physical_schema)?;
             output_expr.push((row_id_expr, ROW_ID.to_string()));
@@ -1376,8 +1674,13 @@ impl Scanner {
         let filter_schema = self.scan_input_schema()?;
         let planner = Planner::new(Arc::new(filter_schema.as_ref().into()));
 
-        let mut filter_plan = if let Some(filter) = self.filter.as_ref() {
-            let filter = filter.to_datafusion(self.dataset.schema(), filter_schema.as_ref())?;
+        let filter_expr = self
+            .filter
+            .as_ref()
+            .map(|filter| filter.to_datafusion(self.dataset.schema(), filter_schema.as_ref()))
+            .transpose()?;
+
+        let mut filter_plan = if let Some(filter) = filter_expr.as_ref() {
             let index_info = self.dataset.scalar_index_info().await?;
             let filter_plan =
                 planner.create_filter_plan(filter.clone(), &index_info, use_scalar_index)?;
@@ -1434,6 +1737,14 @@ impl Scanner {
         let mut use_limit_node = true;
 
         // Stage 1: source (either an (K|A)NN search, full text search or or a (full|indexed) scan)
+        if self.sparse_query.is_some() && (self.nearest.is_some() || self.full_text_query.is_some())
+        {
+            return Err(Error::InvalidInput {
+                source: "Cannot combine sparse vector search with nearest or full text search"
+                    .into(),
+                location: location!(),
+            });
+        }
         let mut plan: Arc<dyn ExecutionPlan> = match (&self.nearest, &self.full_text_query) {
             (Some(_), None) => {
                 if self.include_deleted_rows {
@@ -1446,14 +1757,14 @@ impl Scanner {
                 // The source is an nearest neighbor search
                 if self.prefilter {
                     // If we are prefiltering then the knn node will take care of the filter
-                    let source = self.knn(&filter_plan).await?;
+                    let source = self.knn(&filter_plan, None).await?;
                     filter_plan = FilterPlan::default();
                     source
                 } else {
                     // If we are postfiltering then we can't use scalar indices for the filter
                     // and will need to run the postfilter in memory
                     filter_plan.make_refine_only();
-                    self.knn(&FilterPlan::default()).await?
+                    self.knn(&FilterPlan::default(), None).await?
                 }
             }
             (None, Some(query)) => {
@@ -1467,14 +1778,35 @@ impl Scanner {
                 // The source is an FTS search
                 if self.prefilter {
                     // If we are prefiltering then the fts node will take care of the filter
-                    let source = self.fts(&filter_plan, query).await?;
+                    let source = self.fts(&filter_plan, query, None).await?;
+                    filter_plan = FilterPlan::default();
+                    source
+                } else {
+                    // If we are postfiltering then we can't use scalar indices for the filter
+                    // and will need to run the postfilter in memory
+                    filter_plan.make_refine_only();
+                    self.fts(&FilterPlan::default(), query, None).await?
+                }
+            }
+            (None, None) if self.sparse_query.is_some() => {
+                if self.include_deleted_rows {
+                    return Err(Error::InvalidInput {
+                        source: "Cannot include deleted rows in a sparse vector search".into(),
+                        location: location!(),
+                    });
+                }
+
+                let query = self.sparse_query.as_ref().unwrap();
+                if self.prefilter {
+                    // If we are prefiltering then the sparse search node will take care of the filter
+                    let source = self.sparse_search(&filter_plan, query).await?;
                     filter_plan = FilterPlan::default();
                     source
                 } else {
                     // If we are postfiltering then we can't use scalar indices for the filter
                     // and will need to run the postfilter in memory
                     filter_plan.make_refine_only();
-                    self.fts(&FilterPlan::default(), query).await?
+                    self.sparse_search(&FilterPlan::default(), query).await?
                 }
             }
             (None, None) => {
@@ -1543,21 +1875,70 @@ impl Scanner {
                             // so we don't apply it twice)
                             use_limit_node = false;
                         }
-                        self.scan(
+                        let fragments = self.pruned_fragments(filter_expr.as_ref())?;
+                        self.scan_with_fragments(
                             with_row_id,
                             self.with_row_address,
                             self.include_deleted_rows,
                             scan_range,
                             eager_schema,
+                            fragments,
                         )
                     }
                 }
             }
-            _ => {
-                return Err(Error::InvalidInput {
-                    source: "Cannot have both nearest and full text search".into(),
-                    location: location!(),
-                })
+            (Some(_), Some(query)) => {
+                let Some(reranker) = self.reranker.clone() else {
+                    return Err(Error::InvalidInput {
+                        source: "Cannot have both nearest and full text search without a reranker, use hybrid() to fuse them".into(),
+                        location: location!(),
+                    });
+                };
+                if self.include_deleted_rows {
+                    return Err(Error::InvalidInput {
+                        source: "Cannot include deleted rows in a hybrid search".into(),
+                        location: location!(),
+                    });
+                }
+                if self.nearest_batch {
+                    return Err(Error::InvalidInput {
+                        source: "Cannot use a batch of queries in a hybrid search".into(),
+                        location: location!(),
+                    });
+                }
+
+                // The source is a vector search and an FTS search fused together
+                if self.prefilter {
+                    // Both searches take care of the filter, with the same prefilter
+                    let prefilter_source = self
+                        .prefilter_source(&filter_plan, self.get_fragments_as_bitmap())
+                        .await?;
+                    let prefilter_source = match prefilter_source {
+                        PreFilterSource::FilteredRowIds(src) => {
+                            PreFilterSource::FilteredRowIds(Arc::new(CachedExec::new(src)))
+                        }
+                        PreFilterSource::ScalarIndexQuery(src) => {
+                            PreFilterSource::ScalarIndexQuery(Arc::new(CachedExec::new(src)))
+                        }
+                        PreFilterSource::None => PreFilterSource::None,
+                    };
+                    let (knn, fts) = futures::try_join!(
+                        self.knn(&filter_plan, Some(&prefilter_source)),
+                        self.fts(&filter_plan, query, Some(&prefilter_source))
+                    )?;
+                    filter_plan = FilterPlan::default();
+                    Arc::new(HybridSearchExec::try_new(knn, fts, reranker)?)
+                } else {
+                    // If we are postfiltering then we can't use scalar indices for the filter
+                    // and will need to run the postfilter in memory
+                    filter_plan.make_refine_only();
+                    let no_filter = FilterPlan::default();
+                    let (knn, fts) = futures::try_join!(
+                        self.knn(&no_filter, None),
+                        self.fts(&no_filter, query, None)
+                    )?;
+                    Arc::new(HybridSearchExec::try_new(knn, fts, reranker)?)
+                }
             }
         };
 
@@ -1622,6 +2003,13 @@ impl Scanner {
             plan = self.limit_node(plan);
         }
 
+        // Stage 4.5: highlight the matched terms of the full text search
+        if let Some(query) = &self.full_text_query {
+            if let Some(params) = &query.highlight {
+                plan = self.highlight(plan, query, params).await?;
+            }
+        }
+
         // Stage 5: take remaining columns required for projection
         let physical_schema =
             self.scan_output_schema(&self.projection_plan.physical_schema, false)?;
@@ -1636,9 +2024,15 @@ impl Scanner {
         if plan.schema().as_ref() != &output_arrow_schema {
             plan = Arc::new(project(plan, &physical_schema.as_ref().into())?);
         }
+        if self.use_view_types {
+            plan = cast_to_view_types(plan)?;
+        }
 
         // Stage 7: final projection
-        plan = Arc::new(DFProjectionExec::try_new(self.output_expr()?, plan)?);
+        plan = Arc::new(DFProjectionExec::try_new(
+            self.output_expr(plan.schema())?,
+            plan,
+        )?);
 
         let optimizer = get_physical_optimizer();
         let options = Default::default();
@@ -1714,6 +2108,19 @@ impl Scanner {
                 }
                 Ok(true)
             }
+            FtsQuery::Prefix(_) | FtsQuery::Wildcard(_) | FtsQuery::Regex(_) => {
+                self.fragments_covered_by_fts_leaf(
+                    query
+                        .as_pattern_query()
+                        .and_then(|query| query.column())
+                        .ok_or(Error::invalid_input(
+                            "the column must be specified in the query".to_string(),
+                            location!(),
+                        ))?,
+                    accum,
+                )
+                .await
+            }
             FtsQuery::Phrase(phrase_query) => {
                 self.fragments_covered_by_fts_leaf(
                     phrase_query.column.as_ref().ok_or(Error::invalid_input(
@@ -1733,7 +2140,7 @@ impl Scanner {
                         return Ok(false);
                     }
                 }
-                for query in &bool_query.should {
+                for query in bool_query.should.iter().chain(&bool_query.must_not) {
                     if !self
                         .fragments_covered_by_fts_query_helper(query, accum)
                         .await?
@@ -1762,75 +2169,138 @@ impl Scanner {
         }
     }
 
+    /// Fill the columns of the full text search query,
+    /// the query searches all the indexed columns if the column is not specified
+    async fn resolve_fts_query(&self, query: &FullTextSearchQuery) -> Result<FtsQuery> {
+        if !query.columns().is_empty() {
+            return Ok(query.query.clone());
+        }
+
+        // the field is not specified,
+        // try to search over all indexed fields
+        let string_columns =
+            self.dataset
+                .schema()
+                .fields
+                .iter()
+                .filter_map(|f| match f.data_type() {
+                    DataType::Utf8 | DataType::LargeUtf8 => Some(&f.name),
+                    DataType::List(field) | DataType::LargeList(field) => {
+                        if matches!(field.data_type(), DataType::Utf8 | DataType::LargeUtf8) {
+                            Some(&f.name)
+                        } else {
+                            None
+                        }
+                    }
+                    _ => None,
+                });
+
+        let mut indexed_columns = Vec::new();
+        for column in string_columns {
+            let index = self
+                .dataset
+                .load_scalar_index(
+                    ScalarIndexCriteria::default()
+                        .for_column(column)
+                        .with_type(ScalarIndexType::Inverted),
+                )
+                .await?;
+            if let Some(index) = index {
+                let index_type =
+                    detect_scalar_index_type(&self.dataset, &index, column, &self.dataset.session)
+                        .await?;
+                if matches!(index_type, ScalarIndexType::Inverted) {
+                    indexed_columns.push(column.clone());
+                }
+            }
+        }
+
+        fill_fts_query_column(&query.query, &indexed_columns, false)
+    }
+
+    async fn highlight(
+        &self,
+        input: Arc<dyn ExecutionPlan>,
+        query: &FullTextSearchQuery,
+        params: &HighlightParams,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let query = self.resolve_fts_query(query).await?;
+        let column = match &params.column {
+            Some(column) => column.clone(),
+            None => {
+                let columns = query.columns();
+                if columns.len() != 1 {
+                    return Err(Error::invalid_input(
+                        format!(
+                            "the column to highlight must be specified if the query searches {} columns",
+                            columns.len()
+                        ),
+                        location!(),
+                    ));
+                }
+                columns.into_iter().next().unwrap()
+            }
+        };
+        let field = self.dataset.schema().field(&column).ok_or_else(|| {
+            Error::invalid_input(
+                format!("column {} to highlight does not exist", column),
+                location!(),
+            )
+        })?;
+        if !matches!(
+            field.data_type(),
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+        ) {
+            return Err(Error::invalid_input(
+                format!(
+                    "column {} to highlight must be a string column, but got {}",
+                    column,
+                    field.data_type()
+                ),
+                location!(),
+            ));
+        }
+
+        let projection = self
+            .dataset
+            .empty_projection()
+            .union_column(&column, OnMissing::Error)?;
+        let input = self.take(input, projection)?;
+        Ok(Arc::new(HighlightExec::try_new(
+            self.dataset.clone(),
+            input,
+            column,
+            query,
+            params.clone(),
+        )?))
+    }
+
     // Create an execution plan to do full text search
     async fn fts(
         &self,
         filter_plan: &FilterPlan,
         query: &FullTextSearchQuery,
+        shared_prefilter: Option<&PreFilterSource>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
-        let columns = query.columns();
         let mut params = query.params();
         if params.limit.is_none() {
             params = params.with_limit(self.limit.map(|l| l as usize));
         }
-        let query = if columns.is_empty() {
-            // the field is not specified,
-            // try to search over all indexed fields
-            let string_columns =
-                self.dataset
-                    .schema()
-                    .fields
-                    .iter()
-                    .filter_map(|f| match f.data_type() {
-                        DataType::Utf8 | DataType::LargeUtf8 => Some(&f.name),
-                        DataType::List(field) | DataType::LargeList(field) => {
-                            if matches!(field.data_type(), DataType::Utf8 | DataType::LargeUtf8) {
-                                Some(&f.name)
-                            } else {
-                                None
-                            }
-                        }
-                        _ => None,
-                    });
-
-            let mut indexed_columns = Vec::new();
-            for column in string_columns {
-                let index = self
-                    .dataset
-                    .load_scalar_index(
-                        ScalarIndexCriteria::default()
-                            .for_column(column)
-                            .with_type(ScalarIndexType::Inverted),
-                    )
-                    .await?;
-                if let Some(index) = index {
-                    let index_type = detect_scalar_index_type(
-                        &self.dataset,
-                        &index,
-                        column,
-                        &self.dataset.session,
-                    )
-                    .await?;
-                    if matches!(index_type, ScalarIndexType::Inverted) {
-                        indexed_columns.push(column.clone());
-                    }
-                }
-            }
-
-            fill_fts_query_column(&query.query, &indexed_columns, false)?
-        } else {
-            query.query.clone()
-        };
+        let query = self.resolve_fts_query(query).await?;
 
         // TODO: Could maybe walk the query here to find all the indices that will be
         // involved in the query to calculate a more accuarate required_fragments than
         // get_fragments_as_bitmap but this is safe for now.
-        let prefilter_source = self
-            .prefilter_source(
-                filter_plan,
-                self.fragments_covered_by_fts_query(&query).await?,
-            )
-            .await?;
+        let prefilter_source = match shared_prefilter {
+            Some(prefilter_source) => prefilter_source.clone(),
+            None => {
+                self.prefilter_source(
+                    filter_plan,
+                    self.fragments_covered_by_fts_query(&query).await?,
+                )
+                .await?
+            }
+        };
         let fts_exec = self
             .plan_fts(&query, &params, filter_plan, &prefilter_source)
             .await?;
@@ -1856,6 +2326,11 @@ impl Scanner {
                 prefilter_source.clone(),
             )),
 
+            FtsQuery::Prefix(_) | FtsQuery::Wildcard(_) | FtsQuery::Regex(_) => {
+                self.plan_pattern_query(query, params, filter_plan, prefilter_source)
+                    .await?
+            }
+
             FtsQuery::Boost(query) => {
                 // for boost query, we need to erase the limit so that we can find
                 // the documents that are not in the top-k results of the positive query,
@@ -1883,6 +2358,10 @@ impl Scanner {
                 ))
             }
 
+            FtsQuery::MultiMatch(query) if matches!(params.scoring, ScoringModel::BM25F { .. }) => {
+                self.plan_bm25f_query(query, params, filter_plan, prefilter_source)
+                    .await?
+            }
             FtsQuery::MultiMatch(query) => {
                 let mut children = Vec::with_capacity(query.match_queries.len());
                 for match_query in &query.match_queries {
@@ -1992,11 +2471,34 @@ impl Scanner {
                         must = Some(plan);
                     }
                 }
+
+                // For must_not queries, union the results to exclude
+                let mut must_not = Vec::with_capacity(query.must_not.len());
+                for subquery in &query.must_not {
+                    let plan = Box::pin(self.plan_fts(
+                        subquery,
+                        &unlimited_params,
+                        filter_plan,
+                        prefilter_source,
+                    ))
+                    .await?;
+                    must_not.push(plan);
+                }
+                let must_not: Option<Arc<dyn ExecutionPlan>> = match must_not.len() {
+                    0 => None,
+                    1 => must_not.pop(),
+                    _ => Some(Arc::new(RepartitionExec::try_new(
+                        Arc::new(UnionExec::new(must_not)),
+                        Partitioning::RoundRobinBatch(1),
+                    )?)),
+                };
+
                 Arc::new(BooleanQueryExec::new(
                     query.clone(),
                     params.clone(),
                     should,
                     must,
+                    must_not,
                 ))
             }
         };
@@ -2037,75 +2539,361 @@ impl Scanner {
             ))?;
 
         let unindexed_fragments = self.dataset.unindexed_fragments(&index.name).await?;
-        let mut match_plan: Arc<dyn ExecutionPlan> = Arc::new(MatchQueryExec::new(
+        let match_plan: Arc<dyn ExecutionPlan> = Arc::new(MatchQueryExec::new(
             self.dataset.clone(),
             query.clone(),
             params.clone(),
             prefilter_source.clone(),
         ));
-        if !unindexed_fragments.is_empty() {
-            let mut columns = vec![column.clone()];
-            if let Some(expr) = filter_plan.full_expr.as_ref() {
-                let filter_columns = Planner::column_names_in_expr(expr);
-                columns.extend(filter_columns);
-            }
-            let flat_fts_scan_schema = Arc::new(self.dataset.schema().project(&columns).unwrap());
-            let mut scan_node = self.scan_fragments(
-                true,
-                false,
-                true,
-                flat_fts_scan_schema,
-                Arc::new(unindexed_fragments),
-                None,
-                false,
-            );
+        if unindexed_fragments.is_empty() {
+            return Ok(match_plan);
+        }
+        let scan_node = self.plan_flat_fts_scan(&column, unindexed_fragments, filter_plan)?;
+        let flat_match_plan = Arc::new(FlatMatchQueryExec::new(
+            self.dataset.clone(),
+            query.clone(),
+            params.clone(),
+            scan_node,
+        ));
+        Self::union_fts_plans(match_plan, flat_match_plan, params)
+    }
 
-            if let Some(expr) = filter_plan.full_expr.as_ref() {
-                // If there is a prefilter we need to manually apply it to the new data
-                scan_node = Arc::new(LanceFilterExec::try_new(expr.clone(), scan_node)?);
-            }
+    // BM25F combines the term frequencies of the fields before scoring,
+    // so the fields are searched by a single node instead of one per field
+    async fn plan_bm25f_query(
+        &self,
+        query: &MultiMatchQuery,
+        params: &FtsSearchParams,
+        filter_plan: &FilterPlan,
+        prefilter_source: &PreFilterSource,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut unindexed_inputs = Vec::with_capacity(query.match_queries.len());
+        for match_query in &query.match_queries {
+            let column = match_query.column.as_ref().ok_or(Error::invalid_input(
+                "the column must be specified in the query".to_string(),
+                location!(),
+            ))?;
+            let index = self
+                .dataset
+                .load_scalar_index(
+                    ScalarIndexCriteria::default()
+                        .for_column(column)
+                        .with_type(ScalarIndexType::Inverted),
+                )
+                .await?
+                .ok_or(Error::invalid_input(
+                    format!("Column {} has no inverted index", column),
+                    location!(),
+                ))?;
+            let unindexed_fragments = self.dataset.unindexed_fragments(&index.name).await?;
+            let unindexed_input = match unindexed_fragments.is_empty() {
+                true => None,
+                false => Some(self.plan_flat_fts_scan(column, unindexed_fragments, filter_plan)?),
+            };
+            unindexed_inputs.push(unindexed_input);
+        }
+        Ok(Arc::new(MultiMatchQueryExec::new(
+            self.dataset.clone(),
+            query.clone(),
+            params.clone(),
+            prefilter_source.clone(),
+            unindexed_inputs,
+        )))
+    }
+
+    async fn plan_pattern_query(
+        &self,
+        query: &FtsQuery,
+        params: &FtsSearchParams,
+        filter_plan: &FilterPlan,
+        prefilter_source: &PreFilterSource,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let column = query
+            .as_pattern_query()
+            .and_then(|query| query.column())
+            .ok_or(Error::invalid_input(
+                "the column must be specified in the query".to_string(),
+                location!(),
+            ))?
+            .to_owned();
+
+        let index = self
+            .dataset
+            .load_scalar_index(
+                ScalarIndexCriteria::default()
+                    .for_column(&column)
+                    .with_type(ScalarIndexType::Inverted),
+            )
+            .await?
+            .ok_or(Error::invalid_input(
+                format!("Column {} has no inverted index", column),
+                location!(),
+            ))?;
+
+        let unindexed_fragments = self.dataset.unindexed_fragments(&index.name).await?;
+        let pattern_plan: Arc<dyn ExecutionPlan> = Arc::new(PatternQueryExec::try_new(
+            self.dataset.clone(),
+            query.clone(),
+            params.clone(),
+            prefilter_source.clone(),
+        )?);
+        if unindexed_fragments.is_empty() {
+            return Ok(pattern_plan);
+        }
+        let scan_node = self.plan_flat_fts_scan(&column, unindexed_fragments, filter_plan)?;
+        let flat_pattern_plan = Arc::new(FlatPatternQueryExec::try_new(
+            self.dataset.clone(),
+            query.clone(),
+            params.clone(),
+            scan_node,
+        )?);
+        Self::union_fts_plans(pattern_plan, flat_pattern_plan, params)
+    }
+
+    // scan the column of the unindexed fragments for the flat full text search
+    fn plan_flat_fts_scan(
+        &self,
+        column: &str,
+        unindexed_fragments: Vec<Fragment>,
+        filter_plan: &FilterPlan,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut columns = vec![column.to_owned()];
+        if let Some(expr) = filter_plan.full_expr.as_ref() {
+            let filter_columns = Planner::column_names_in_expr(expr);
+            columns.extend(filter_columns);
+        }
+        let flat_fts_scan_schema = Arc::new(self.dataset.schema().project(&columns).unwrap());
+        let mut scan_node = self.scan_fragments(
+            true,
+            false,
+            true,
+            flat_fts_scan_schema,
+            Arc::new(unindexed_fragments),
+            None,
+            false,
+        );
+
+        if let Some(expr) = filter_plan.full_expr.as_ref() {
+            // If there is a prefilter we need to manually apply it to the new data
+            scan_node = Arc::new(LanceFilterExec::try_new(expr.clone(), scan_node)?);
+        }
+        Ok(scan_node)
+    }
+
+    // merge the results of the indexed and the flat search, ordered by score
+    fn union_fts_plans(
+        indexed_plan: Arc<dyn ExecutionPlan>,
+        flat_plan: Arc<dyn ExecutionPlan>,
+        params: &FtsSearchParams,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let plan = Arc::new(UnionExec::new(vec![indexed_plan, flat_plan]));
+        let plan = Arc::new(RepartitionExec::try_new(
+            plan,
+            Partitioning::RoundRobinBatch(1),
+        )?);
+        let sort_expr = PhysicalSortExpr {
+            expr: expressions::col(SCORE_COL, plan.schema().as_ref())?,
+            options: SortOptions {
+                descending: true,
+                nulls_first: false,
+            },
+        };
+        Ok(Arc::new(
+            SortExec::new(LexOrdering::new(vec![sort_expr]), plan).with_fetch(params.limit),
+        ))
+    }
+
+    // Top-k sparse vector search, using the sparse index for the indexed fragments
+    // and a flat search for the rest
+    async fn sparse_search(
+        &self,
+        filter_plan: &FilterPlan,
+        query: &SparseQuery,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        // The index search covers all the indexed fragments, so a scan of some
+        // fragments searches them with a flat search
+        let index = if self.is_fragment_scan() {
+            None
+        } else {
+            self.dataset
+                .load_scalar_index(
+                    ScalarIndexCriteria::default()
+                        .for_column(&query.column)
+                        .with_type(ScalarIndexType::Sparse),
+                )
+                .await?
+        };
 
-            let flat_match_plan = Arc::new(FlatMatchQueryExec::new(
+        let mut plans: Vec<Arc<dyn ExecutionPlan>> = Vec::with_capacity(2);
+        let unindexed_fragments = if let Some(index) = index {
+            let all_fragments = self.get_fragments_as_bitmap();
+            let required_frags = match &index.fragment_bitmap {
+                Some(fragmap) => all_fragments & fragmap,
+                None => all_fragments,
+            };
+            let prefilter_source = self.prefilter_source(filter_plan, required_frags).await?;
+            plans.push(Arc::new(SparseSearchExec::new(
                 self.dataset.clone(),
                 query.clone(),
-                params.clone(),
-                scan_node,
-            ));
+                prefilter_source,
+            )));
+            self.dataset.unindexed_fragments(&index.name).await?
+        } else if let Some(fragments) = self.fragments.as_ref() {
+            fragments.clone()
+        } else {
+            self.dataset.fragments().to_vec()
+        };
+        if unindexed_fragments.is_empty() {
+            // the index search already returns the top-k results
+            return Ok(plans
+                .pop()
+                .unwrap_or_else(|| Arc::new(EmptyExec::new(FTS_SCHEMA.clone()))));
+        }
 
-            match_plan = Arc::new(UnionExec::new(vec![match_plan, flat_match_plan]));
-            match_plan = Arc::new(RepartitionExec::try_new(
-                match_plan,
-                Partitioning::RoundRobinBatch(1),
-            )?);
-            let sort_expr = PhysicalSortExpr {
-                expr: expressions::col(SCORE_COL, match_plan.schema().as_ref())?,
-                options: SortOptions {
-                    descending: true,
-                    nulls_first: false,
-                },
-            };
-            match_plan = Arc::new(
-                SortExec::new(LexOrdering::new(vec![sort_expr]), match_plan)
-                    .with_fetch(params.limit),
-            );
+        let mut columns = vec![query.column.clone()];
+        if let Some(expr) = filter_plan.full_expr.as_ref() {
+            columns.extend(Planner::column_names_in_expr(expr));
+        }
+        let flat_scan_schema = Arc::new(self.dataset.schema().project(&columns)?);
+        let mut scan_node = self.scan_fragments(
+            true,
+            false,
+            false,
+            flat_scan_schema,
+            Arc::new(unindexed_fragments),
+            None,
+            false,
+        );
+        if let Some(expr) = filter_plan.full_expr.as_ref() {
+            // If there is a prefilter we need to manually apply it to the new data
+            scan_node = Arc::new(LanceFilterExec::try_new(expr.clone(), scan_node)?);
         }
-        Ok(match_plan)
+        plans.push(Arc::new(FlatSparseSearchExec::new(
+            query.clone(),
+            scan_node,
+        )));
+
+        let mut plan: Arc<dyn ExecutionPlan> = Arc::new(UnionExec::new(plans));
+        plan = Arc::new(RepartitionExec::try_new(
+            plan,
+            Partitioning::RoundRobinBatch(1),
+        )?);
+        let sort_expr = PhysicalSortExpr {
+            expr: expressions::col(SCORE_COL, plan.schema().as_ref())?,
+            options: SortOptions {
+                descending: true,
+                nulls_first: false,
+            },
+        };
+        Ok(Arc::new(
+            SortExec::new(LexOrdering::new(vec![sort_expr]), plan).with_fetch(Some(query.k)),
+        ))
     }
 
     // ANN/KNN search execution node with optional prefilter
-    async fn knn(&self, filter_plan: &FilterPlan) -> Result<Arc<dyn ExecutionPlan>> {
+    async fn knn(
+        &self,
+        filter_plan: &FilterPlan,
+        shared_prefilter: Option<&PreFilterSource>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
         let Some(q) = self.nearest.as_ref() else {
             return Err(Error::invalid_input(
                 "No nearest query".to_string(),
                 location!(),
             ));
         };
+        if self.diversity.is_empty() {
+            return self.knn_query(q, filter_plan, shared_prefilter).await;
+        }
+        if self.nearest_batch {
+            return Err(Error::invalid_input(
+                "Cannot diversify the results of a batch of queries".to_string(),
+                location!(),
+            ));
+        }
+
+        // The candidates are over-fetched until there are k results after diversifying,
+        // or there are no more candidates
+        let mut projection = self.dataset.empty_projection();
+        if self.diversity.mmr_lambda.is_some() {
+            projection = projection.union_column(&q.column, OnMissing::Error)?;
+        }
+        if let Some((column, _)) = &self.diversity.group_limit {
+            projection = projection.union_column(column, OnMissing::Error)?;
+        }
+        let plan_candidates: CandidatesPlanner = {
+            let scanner = Arc::new(self.clone());
+            let q = q.clone();
+            let filter_plan = filter_plan.clone();
+            let shared_prefilter = shared_prefilter.cloned();
+            Arc::new(move |fetch_size| {
+                let scanner = scanner.clone();
+                let mut q = q.clone();
+                q.k = fetch_size;
+                let filter_plan = filter_plan.clone();
+                let shared_prefilter = shared_prefilter.clone();
+                let projection = projection.clone();
+                async move {
+                    let candidates = scanner
+                        .knn_query(&q, &filter_plan, shared_prefilter.as_ref())
+                        .await?;
+                    scanner.take(candidates, projection)
+                }
+                .boxed()
+            })
+        };
+        let fetch_size = q.k.saturating_mul(*DEFAULT_DIVERSITY_OVERFETCH);
+        let input = plan_candidates(fetch_size).await?;
+
+        // The distances between the results must be comparable with the distances to the query
+        let column_id = self.dataset.schema().field_id(q.column.as_str())?;
+        let index = match q.use_index {
+            true => self
+                .dataset
+                .load_indices()
+                .await?
+                .iter()
+                .find(|i| i.fields.contains(&column_id))
+                .cloned(),
+            false => None,
+        };
+        let distance_type = match index {
+            Some(index) => self
+                .dataset
+                .open_vector_index(
+                    q.column.as_str(),
+                    &index.uuid.to_string(),
+                    &NoOpMetricsCollector,
+                )
+                .await?
+                .metric_type(),
+            None => q.metric_type,
+        };
 
+        Ok(Arc::new(DiversifyExec::try_new(
+            input,
+            fetch_size,
+            *DEFAULT_DIVERSITY_OVERFETCH,
+            plan_candidates,
+            q.k,
+            self.diversity.clone(),
+            q.column.clone(),
+            distance_type,
+        )?))
+    }
+
+    // Create an Execution plan to find the nearest neighbors of the query
+    async fn knn_query(
+        &self,
+        q: &Query,
+        filter_plan: &FilterPlan,
+        shared_prefilter: Option<&PreFilterSource>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
         // Sanity check
         let (vector_type, _) = get_vector_type(self.dataset.schema(), &q.column)?;
 
         let column_id = self.dataset.schema().field_id(q.column.as_str())?;
-        let use_index = self.nearest.as_ref().map(|q| q.use_index).unwrap_or(false);
+        let use_index = q.use_index;
         let indices = if use_index {
             self.dataset.load_indices().await?
         } else {
@@ -2121,11 +2909,51 @@ impl Scanner {
                 ));
             }
 
+            // IVF_RQ ranks the candidates by lower bounds of their distances, so
+            // they are reranked with the raw vectors by default, and only the
+            // reranked distances are compared with the lower end of the range.
+            // The default refine factor is kept in the index details, so the
+            // index doesn't have to be opened to plan the query.
+            let default_refine_factor = vector_index_refine_factor(index)?;
+            let mut q = q.clone();
+            if q.refine_factor.is_none() && matches!(vector_type, DataType::FixedSizeList(_, _)) {
+                q.refine_factor = default_refine_factor;
+            }
+            let q = &q;
+            let mut ann_q = q.clone();
+            if default_refine_factor.is_some() && q.refine_factor.is_some() {
+                ann_q.lower_bound = None;
+            }
+
             // Find all deltas with the same index name.
             let deltas = self.dataset.load_indices_by_name(&index.name).await?;
             let ann_node = match vector_type {
-                DataType::FixedSizeList(_, _) => self.ann(q, &deltas, filter_plan).await?,
-                DataType::List(_) => self.multivec_ann(q, &deltas, filter_plan).await?,
+                DataType::FixedSizeList(_, _) if self.nearest_batch => {
+                    self.batch_ann(&ann_q, &deltas, filter_plan, shared_prefilter)
+                        .await?
+                }
+                DataType::FixedSizeList(_, _) => {
+                    self.ann(&ann_q, &deltas, filter_plan, shared_prefilter)
+                        .await?
+                }
+                DataType::List(_) => {
+                    let idx = self
+                        .dataset
+                        .open_vector_index(
+                            q.column.as_str(),
+                            &index.uuid.to_string(),
+                            &NoOpMetricsCollector,
+                        )
+                        .await?;
+                    // the legacy index can't score the documents by centroids
+                    if idx.as_any().is::<IVFIndex>() {
+                        self.xtr_multivec_ann(&ann_q, &deltas, filter_plan, shared_prefilter)
+                            .await?
+                    } else {
+                        self.multivec_ann(&ann_q, &deltas, filter_plan, shared_prefilter)
+                            .await?
+                    }
+                }
                 _ => unreachable!(),
             };
 
@@ -2497,6 +3325,25 @@ impl Scanner {
         } else {
             self.dataset.fragments().clone()
         };
+        self.scan_with_fragments(
+            with_row_id,
+            with_row_address,
+            with_make_deletions_null,
+            range,
+            projection,
+            fragments,
+        )
+    }
+
+    fn scan_with_fragments(
+        &self,
+        with_row_id: bool,
+        with_row_address: bool,
+        with_make_deletions_null: bool,
+        range: Option<Range<u64>>,
+        projection: Arc<Schema>,
+        fragments: Arc<Vec<Fragment>>,
+    ) -> Arc<dyn ExecutionPlan> {
         let ordered = if self.ordering.is_some() || self.nearest.is_some() {
             // If we are sorting the results there is no need to scan in order
             false
@@ -2514,6 +3361,33 @@ impl Scanner {
         )
     }
 
+    /// The fragments to scan, skipping the ones whose partition or column
+    /// statistics show they cannot contain rows matching `filter`.
+    fn pruned_fragments(&self, filter: Option<&Expr>) -> Result<Arc<Vec<Fragment>>> {
+        let fragments = if let Some(fragment) = self.fragments.as_ref() {
+            Arc::new(fragment.clone())
+        } else {
+            self.dataset.fragments().clone()
+        };
+        let Some(filter) = filter else {
+            return Ok(fragments);
+        };
+        let fragments = match PartitionSpec::from_manifest(&self.dataset.manifest)? {
+            Some(spec) => spec.prune_fragments(filter, self.dataset.schema(), &fragments),
+            None => fragments.as_ref().clone(),
+        };
+        let schema = self.dataset.schema();
+        Ok(Arc::new(
+            fragments
+                .into_iter()
+                .filter(|fragment| {
+                    FragmentStatsPruner::new(schema, fragment).evaluate(filter)
+                        != FragmentMatch::None
+                })
+                .collect(),
+        ))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn scan_fragments(
         &self,
@@ -2535,6 +3409,7 @@ impl Scanner {
             with_make_deletions_null,
             ordered_output: ordered,
             strict_batch_size: self.strict_batch_size,
+            use_view_types: self.use_view_types,
         };
         Arc::new(LanceScanExec::new(
             self.dataset.clone(),
@@ -2578,6 +3453,19 @@ impl Scanner {
 
     /// Add a knn search node to the input plan
     fn flat_knn(&self, input: Arc<dyn ExecutionPlan>, q: &Query) -> Result<Arc<dyn ExecutionPlan>> {
+        if self.nearest_batch {
+            let dim = get_vector_dim(self.dataset.schema(), &q.column)?;
+            let queries = FixedSizeListArray::try_new_from_values(q.key.clone(), dim as i32)?;
+            return Ok(Arc::new(BatchKNNExec::try_new(
+                input,
+                &q.column,
+                queries,
+                q.k,
+                (q.lower_bound, q.upper_bound),
+                q.metric_type,
+            )?));
+        }
+
         let flat_dist = Arc::new(KNNVectorDistanceExec::try_new(
             input,
             &q.column,
@@ -2667,39 +3555,102 @@ impl Scanner {
             }
         }
 
-        all_indexed_frags & all_fragments
+        all_indexed_frags & all_fragments
+    }
+
+    /// Create an Execution plan to do indexed ANN search
+    async fn ann(
+        &self,
+        q: &Query,
+        index: &[Index],
+        filter_plan: &FilterPlan,
+        shared_prefilter: Option<&PreFilterSource>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let prefilter_source = self
+            .index_prefilter_source(filter_plan, index, shared_prefilter)
+            .await?;
+        let inner_fanout_search = new_knn_exec(self.dataset.clone(), index, q, prefilter_source)?;
+        let sort_expr = PhysicalSortExpr {
+            expr: expressions::col(DIST_COL, inner_fanout_search.schema().as_ref())?,
+            options: SortOptions {
+                descending: false,
+                nulls_first: false,
+            },
+        };
+        Ok(Arc::new(
+            SortExec::new(LexOrdering::new(vec![sort_expr]), inner_fanout_search)
+                .with_fetch(Some(q.k * q.refine_factor.unwrap_or(1) as usize)),
+        ))
+    }
+
+    // Create an Execution plan to do ANN for a batch of queries
+    async fn batch_ann(
+        &self,
+        q: &Query,
+        index: &[Index],
+        filter_plan: &FilterPlan,
+        shared_prefilter: Option<&PreFilterSource>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let prefilter_source = self
+            .index_prefilter_source(filter_plan, index, shared_prefilter)
+            .await?;
+        let dim = get_vector_dim(self.dataset.schema(), &q.column)?;
+        // the results are already the top k * refine_factor of each query
+        Ok(Arc::new(BatchANNExec::try_new(
+            self.dataset.clone(),
+            index.to_vec(),
+            q.clone(),
+            dim,
+            prefilter_source,
+        )?))
     }
 
-    /// Create an Execution plan to do indexed ANN search
-    async fn ann(
+    // Create an Execution plan to do ANN over multivectors
+    async fn multivec_ann(
         &self,
         q: &Query,
         index: &[Index],
         filter_plan: &FilterPlan,
+        shared_prefilter: Option<&PreFilterSource>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
+        // the documents are scored by the centroids of their vectors first,
+        // and only the best candidates are scored by their vectors
+        let num_candidates =
+            q.k * q.refine_factor.unwrap_or(1) as usize * *DEFAULT_MULTIVECTOR_CANDIDATES_FACTOR;
+
         let prefilter_source = self
-            .prefilter_source(filter_plan, self.get_indexed_frags(index))
+            .index_prefilter_source(filter_plan, index, shared_prefilter)
             .await?;
-        let inner_fanout_search = new_knn_exec(self.dataset.clone(), index, q, prefilter_source)?;
+        let ann_node = Arc::new(MultivectorSearchExec::try_new(
+            self.dataset.clone(),
+            index.to_vec(),
+            q.clone(),
+            num_candidates,
+            prefilter_source,
+        )?);
+
         let sort_expr = PhysicalSortExpr {
-            expr: expressions::col(DIST_COL, inner_fanout_search.schema().as_ref())?,
+            expr: expressions::col(DIST_COL, ann_node.schema().as_ref())?,
             options: SortOptions {
                 descending: false,
                 nulls_first: false,
             },
         };
-        Ok(Arc::new(
-            SortExec::new(LexOrdering::new(vec![sort_expr]), inner_fanout_search)
+        let ann_node = Arc::new(
+            SortExec::new(LexOrdering::new(vec![sort_expr]), ann_node)
                 .with_fetch(Some(q.k * q.refine_factor.unwrap_or(1) as usize)),
-        ))
+        );
+
+        Ok(ann_node)
     }
 
-    // Create an Execution plan to do ANN over multivectors
-    async fn multivec_ann(
+    // Create an Execution plan to do ANN over multivectors by searching each query vector
+    async fn xtr_multivec_ann(
         &self,
         q: &Query,
         index: &[Index],
         filter_plan: &FilterPlan,
+        shared_prefilter: Option<&PreFilterSource>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
         // we split the query procedure into two steps:
         // 1. collect the candidates by vector searching on each query vector
@@ -2708,7 +3659,7 @@ impl Scanner {
         let over_fetch_factor = *DEFAULT_XTR_OVERFETCH;
 
         let prefilter_source = self
-            .prefilter_source(filter_plan, self.get_indexed_frags(index))
+            .index_prefilter_source(filter_plan, index, shared_prefilter)
             .await?;
         let dim = get_vector_dim(self.dataset.schema(), &q.column)?;
 
@@ -2720,7 +3671,6 @@ impl Scanner {
                 new_query.key = query_vec;
                 // with XTR, we don't need to refine the result with original vectors,
                 // but here we really need to over-fetch the candidates to reach good enough recall.
-                // TODO: improve the recall with WARP, expose this parameter to the users.
                 new_query.refine_factor = Some(over_fetch_factor);
                 new_query
             });
@@ -2764,6 +3714,22 @@ impl Scanner {
         Ok(ann_node)
     }
 
+    // Create prefilter source for searching the vector index, unless a prefilter is shared
+    async fn index_prefilter_source(
+        &self,
+        filter_plan: &FilterPlan,
+        index: &[Index],
+        shared_prefilter: Option<&PreFilterSource>,
+    ) -> Result<PreFilterSource> {
+        match shared_prefilter {
+            Some(prefilter_source) => Ok(prefilter_source.clone()),
+            None => {
+                self.prefilter_source(filter_plan, self.get_indexed_frags(index))
+                    .await
+            }
+        }
+    }
+
     /// Create prefilter source from filter plan
     async fn prefilter_source(
         &self,
@@ -2932,7 +3898,6 @@ pub mod test_dataset {
     };
     use tempfile::{tempdir, TempDir};
 
-    use crate::arrow::*;
     use crate::dataset::WriteParams;
     use crate::index::vector::VectorIndexParams;
 
@@ -3095,7 +4060,7 @@ pub mod test_dataset {
 #[cfg(test)]
 mod test {
 
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeSet, HashSet};
     use std::sync::Mutex;
     use std::vec;
 
@@ -6377,4 +7342,558 @@ mod test {
 
         assert_eq!(tracker.new_iops(), 0);
     }
+
+    #[tokio::test]
+    async fn test_sparse_vector_search() {
+        use lance_arrow::sparse::{sparse_vector_field, SparseVectorArray};
+        use lance_index::scalar::ScalarIndexType;
+        use lance_linalg::distance::sparse::sparse_dot;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut random_vectors = |n: usize| {
+            (0..n)
+                .map(|_| {
+                    let nnz = rng.gen_range(1..16);
+                    SparseVector::from_pairs(
+                        (0..nnz).map(|_| (rng.gen_range(0..200), rng.gen_range(0.0..1.0))),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("id", DataType::Int32, false),
+            sparse_vector_field("vec", true),
+        ]));
+        let make_batch = |start: i32, vectors: &[SparseVector]| {
+            let array: ArrayRef = vectors
+                .iter()
+                .map(Some)
+                .collect::<SparseVectorArray>()
+                .into();
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(Int32Array::from_iter_values(
+                        start..start + vectors.len() as i32,
+                    )),
+                    array,
+                ],
+            )
+            .unwrap()
+        };
+
+        let mut vectors = random_vectors(600);
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let batches = RecordBatchIterator::new(vec![Ok(make_batch(0, &vectors))], schema.clone());
+        let mut dataset = Dataset::write(
+            batches,
+            test_uri,
+            Some(WriteParams {
+                max_rows_per_file: 200,
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+
+        let query = SparseVector::from_pairs((0..200).step_by(7).map(|dim| (dim, 1.0)));
+        let k = 20;
+        let check = |dataset: Dataset, vectors: Vec<SparseVector>, filter: Option<&'static str>| {
+            let query = query.clone();
+            async move {
+                let mut expected = vectors
+                    .iter()
+                    .enumerate()
+                    .filter(|(id, _)| filter.is_none() || id % 2 == 0)
+                    .map(|(id, v)| (id as i32, sparse_dot(query.as_ref(), v.as_ref())))
+                    .filter(|(_, score)| *score > 0.0)
+                    .collect::<Vec<_>>();
+                expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+                expected.truncate(k);
+
+                let mut scanner = dataset.scan();
+                scanner
+                    .nearest_sparse("vec", query.clone(), k)
+                    .unwrap()
+                    .project(&["id"])
+                    .unwrap();
+                if let Some(filter) = filter {
+                    scanner.filter(filter).unwrap().prefilter(true);
+                }
+                let results = scanner.try_into_batch().await.unwrap();
+                assert_eq!(results.schema().field(1).name(), SCORE_COL);
+                let ids = results["id"].as_primitive::<Int32Type>().values();
+                let scores = results[SCORE_COL].as_primitive::<Float32Type>().values();
+                assert_eq!(ids.len(), expected.len());
+                // compare the scores rather than the ids, because ties may be returned in any order
+                for ((id, score), (_, expected_score)) in
+                    ids.iter().zip(scores.iter()).zip(expected.iter())
+                {
+                    assert!((score - expected_score).abs() < 1e-4);
+                    let actual = sparse_dot(query.as_ref(), vectors[*id as usize].as_ref());
+                    assert!((actual - score).abs() < 1e-4);
+                    assert!(filter.is_none() || id % 2 == 0);
+                }
+            }
+        };
+
+        // flat search without an index
+        check(dataset.clone(), vectors.clone(), None).await;
+
+        dataset
+            .create_index(
+                &["vec"],
+                IndexType::Sparse,
+                None,
+                &ScalarIndexParams::new(ScalarIndexType::Sparse),
+                true,
+            )
+            .await
+            .unwrap();
+        check(dataset.clone(), vectors.clone(), None).await;
+        check(dataset.clone(), vectors.clone(), Some("id % 2 = 0")).await;
+        let plan = dataset
+            .scan()
+            .nearest_sparse("vec", query.clone(), k)
+            .unwrap()
+            .explain_plan(false)
+            .await
+            .unwrap();
+        assert!(plan.contains("SparseSearch"), "{}", plan);
+
+        // new data is searched with a flat search
+        let new_vectors = random_vectors(100);
+        let batches = RecordBatchIterator::new(
+            vec![Ok(make_batch(vectors.len() as i32, &new_vectors))],
+            schema.clone(),
+        );
+        dataset.append(batches, None).await.unwrap();
+        vectors.extend(new_vectors);
+        check(dataset.clone(), vectors.clone(), None).await;
+        check(dataset.clone(), vectors.clone(), Some("id % 2 = 0")).await;
+
+        // deleted rows are not returned
+        let top_id = dataset
+            .scan()
+            .nearest_sparse("vec", query.clone(), 1)
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap()["id"]
+            .as_primitive::<Int32Type>()
+            .value(0);
+        dataset.delete(&format!("id = {}", top_id)).await.unwrap();
+        vectors[top_id as usize] = SparseVector::default();
+        check(dataset.clone(), vectors.clone(), None).await;
+
+        // a scan of some fragments only searches them, indexed or not
+        for fragment in [1, dataset.fragments().len() - 1] {
+            let fragment = dataset.fragments()[fragment].clone();
+            let ids = dataset
+                .scan()
+                .with_fragments(vec![fragment.clone()])
+                .nearest_sparse("vec", query.clone(), k)
+                .unwrap()
+                .project(&["id"])
+                .unwrap()
+                .try_into_batch()
+                .await
+                .unwrap()["id"]
+                .as_primitive::<Int32Type>()
+                .values()
+                .to_vec();
+            let fragment_ids = dataset
+                .scan()
+                .with_fragments(vec![fragment])
+                .project(&["id"])
+                .unwrap()
+                .try_into_batch()
+                .await
+                .unwrap()["id"]
+                .as_primitive::<Int32Type>()
+                .values()
+                .iter()
+                .copied()
+                .collect::<HashSet<_>>();
+            assert!(!ids.is_empty());
+            assert!(ids.iter().all(|id| fragment_ids.contains(id)), "{:?}", ids);
+        }
+
+        // the sparse index can't be created on other columns
+        assert!(dataset
+            .create_index(
+                &["id"],
+                IndexType::Sparse,
+                None,
+                &ScalarIndexParams::new(ScalarIndexType::Sparse),
+                true,
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        use lance_index::scalar::inverted::tokenizer::InvertedIndexParams;
+
+        use crate::dataset::hybrid::{LinearCombinationReranker, RRFReranker, UdfReranker};
+
+        const DIM: usize = 4;
+        let num_rows = 100;
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("id", DataType::Int32, false),
+            ArrowField::new("text", DataType::Utf8, false),
+            ArrowField::new(
+                "vector",
+                DataType::FixedSizeList(
+                    Arc::new(ArrowField::new("item", DataType::Float32, true)),
+                    DIM as i32,
+                ),
+                false,
+            ),
+        ]));
+        let texts = (0..num_rows).map(|i| if i % 3 == 0 { "apple pie" } else { "cherry" });
+        let vectors = (0..num_rows).flat_map(|i| std::iter::repeat_n(i as f32, DIM));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from_iter_values(0..num_rows)),
+                Arc::new(StringArray::from_iter_values(texts)),
+                Arc::new(
+                    FixedSizeListArray::try_new_from_values(
+                        Float32Array::from_iter_values(vectors),
+                        DIM as i32,
+                    )
+                    .unwrap(),
+                ),
+            ],
+        )
+        .unwrap();
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let batches = RecordBatchIterator::new(vec![Ok(batch)], schema.clone());
+        let mut dataset = Dataset::write(batches, test_uri, None).await.unwrap();
+        dataset
+            .create_index(
+                &["text"],
+                IndexType::Inverted,
+                None,
+                &InvertedIndexParams::default(),
+                true,
+            )
+            .await
+            .unwrap();
+        dataset
+            .create_index(
+                &["vector"],
+                IndexType::Vector,
+                None,
+                &VectorIndexParams::ivf_flat(2, DistanceType::L2),
+                true,
+            )
+            .await
+            .unwrap();
+
+        let query = Float32Array::from(vec![10.0; DIM]);
+        let search = |reranker: Arc<dyn Reranker>, filter: Option<&str>| {
+            let mut scanner = dataset.scan();
+            scanner
+                .nearest("vector", &query, 5)
+                .unwrap()
+                .minimum_nprobes(2)
+                .full_text_search(FullTextSearchQuery::new("apple".to_owned()))
+                .unwrap()
+                .hybrid(reranker)
+                .project(&["id"])
+                .unwrap();
+            if let Some(filter) = filter {
+                scanner.filter(filter).unwrap().prefilter(true);
+            }
+            scanner
+        };
+        let ids =
+            |results: &RecordBatch| results["id"].as_primitive::<Int32Type>().values().to_vec();
+
+        // the documents found by both searches are the most relevant
+        let results = search(Arc::new(RRFReranker::default()), None)
+            .try_into_batch()
+            .await
+            .unwrap();
+        let schema = results.schema();
+        let columns = schema.fields().iter().map(|f| f.name()).collect::<Vec<_>>();
+        assert_eq!(columns, ["id", DIST_COL, SCORE_COL, RELEVANCE_SCORE_COL]);
+        assert_eq!(results.num_rows(), 5 + num_rows as usize / 3 + 1 - 2);
+        let top = ids(&results)[..2].iter().copied().collect::<BTreeSet<_>>();
+        assert_eq!(top, BTreeSet::from([9, 12]));
+        let relevance = results[RELEVANCE_SCORE_COL].as_primitive::<Float32Type>();
+        assert!(relevance.values().windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(
+            results[DIST_COL].null_count(),
+            num_rows as usize / 3 + 1 - 2
+        );
+        assert_eq!(results[SCORE_COL].null_count(), 3);
+
+        // with the vector search weighted fully, the closest document is the most relevant
+        let results = search(
+            Arc::new(LinearCombinationReranker::try_new(1.0).unwrap()),
+            None,
+        )
+        .try_into_batch()
+        .await
+        .unwrap();
+        assert_eq!(ids(&results)[0], 10);
+
+        // a user-defined reranker
+        let results = search(
+            Arc::new(UdfReranker::new(
+                "lowest_row_id",
+                Arc::new(|batch: &RecordBatch| -> Result<Float32Array> {
+                    Ok(batch[ROW_ID]
+                        .as_primitive::<UInt64Type>()
+                        .values()
+                        .iter()
+                        .map(|row_id| -(*row_id as f32))
+                        .collect())
+                }),
+            )),
+            None,
+        )
+        .try_into_batch()
+        .await
+        .unwrap();
+        assert_eq!(ids(&results)[0], 0);
+
+        // both searches share the prefilter
+        let scanner = search(Arc::new(RRFReranker::default()), Some("id > 10"));
+        let plan = scanner.explain_plan(true).await.unwrap();
+        assert!(plan.contains("HybridSearch"), "{}", plan);
+        assert!(plan.matches("Cached").count() >= 2, "{}", plan);
+        let results = scanner.try_into_batch().await.unwrap();
+        assert!(ids(&results).iter().all(|id| *id > 10));
+        let top = ids(&results)[..2].iter().copied().collect::<BTreeSet<_>>();
+        assert_eq!(top, BTreeSet::from([12, 15]));
+
+        // both queries must be fused by a reranker
+        let mut scanner = dataset.scan();
+        scanner
+            .nearest("vector", &query, 5)
+            .unwrap()
+            .full_text_search(FullTextSearchQuery::new("apple".to_owned()))
+            .unwrap();
+        assert!(scanner.try_into_batch().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_nearest() {
+        use arrow::datatypes::UInt32Type;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        use std::collections::HashSet;
+
+        let data = gen()
+            .col("id", array::step::<Int32Type>())
+            .col("vec", array::rand_vec::<Float32Type>(Dimension::from(16)))
+            .into_reader_rows(RowCount::from(250), BatchCount::from(4));
+        let mut dataset = Dataset::write(data, "memory://test", None).await.unwrap();
+        dataset
+            .create_index(
+                &["vec"],
+                IndexType::Vector,
+                None,
+                &VectorIndexParams::ivf_flat(4, DistanceType::L2),
+                true,
+            )
+            .await
+            .unwrap();
+        // the new rows are not indexed
+        let mut rng = StdRng::seed_from_u64(42);
+        let values = (0..50 * 16)
+            .map(|_| rng.gen::<f32>())
+            .collect::<Float32Array>();
+        let new_data = RecordBatch::try_from_iter(vec![
+            (
+                "id",
+                Arc::new(Int32Array::from_iter_values(1000..1050)) as ArrayRef,
+            ),
+            (
+                "vec",
+                Arc::new(FixedSizeListArray::try_new_from_values(values, 16).unwrap()),
+            ),
+        ])
+        .unwrap();
+        let schema = new_data.schema();
+        let new_data = RecordBatchIterator::new(vec![Ok(new_data)], schema);
+        dataset.append(new_data, None).await.unwrap();
+
+        let vectors = dataset
+            .scan()
+            .project(&["vec"])
+            .unwrap()
+            .limit(Some(5), None)
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        let queries = vectors["vec"].as_fixed_size_list().clone();
+        let k = 10;
+
+        for filter in [None, Some("id % 2 = 0")] {
+            for (use_index, refine_factor) in [(true, None), (true, Some(2)), (false, None)] {
+                let search = |query: &dyn Array| {
+                    let mut scanner = dataset.scan();
+                    scanner
+                        .nearest("vec", query, k)
+                        .unwrap()
+                        .minimum_nprobes(2)
+                        .use_index(use_index)
+                        .prefilter(true);
+                    if let Some(refine_factor) = refine_factor {
+                        scanner.refine(refine_factor);
+                    }
+                    if let Some(filter) = filter {
+                        scanner.filter(filter).unwrap();
+                    }
+                    async move { scanner.try_into_batch().await.unwrap() }
+                };
+
+                let results = search(&queries).await;
+                let columns = results
+                    .schema()
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().clone())
+                    .collect::<Vec<_>>();
+                assert_eq!(columns, ["id", "vec", QUERY_INDEX_COL, DIST_COL]);
+                let query_indices = results[QUERY_INDEX_COL].as_primitive::<UInt32Type>();
+                let ids = results["id"].as_primitive::<Int32Type>();
+
+                for i in 0..queries.len() {
+                    let expected = search(queries.value(i).as_ref()).await;
+                    let expected = expected["id"]
+                        .as_primitive::<Int32Type>()
+                        .values()
+                        .iter()
+                        .copied()
+                        .collect::<HashSet<_>>();
+                    let actual = ids
+                        .values()
+                        .iter()
+                        .zip(query_indices.values())
+                        .filter(|(_, query_index)| **query_index == i as u32)
+                        .map(|(id, _)| *id)
+                        .collect::<HashSet<_>>();
+                    assert_eq!(actual.len(), k);
+                    assert_eq!(
+                        actual, expected,
+                        "query {} with filter {:?}, use_index {}, refine {:?}",
+                        i, filter, use_index, refine_factor
+                    );
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_diversify_nearest() {
+        use arrow::datatypes::UInt32Type;
+        use arrow_array::UInt32Array;
+        use itertools::Itertools;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // 100 documents of 10 chunks, the chunks of a document are close to each other
+        const DIM: usize = 8;
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut values = Vec::new();
+        for _ in 0..100 {
+            let center = (0..DIM).map(|_| rng.gen::<f32>()).collect::<Vec<_>>();
+            for _ in 0..10 {
+                values.extend(center.iter().map(|v| v + rng.gen::<f32>() * 0.01));
+            }
+        }
+        let vectors =
+            FixedSizeListArray::try_new_from_values(Float32Array::from(values), DIM as i32)
+                .unwrap();
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "doc",
+                Arc::new(UInt32Array::from_iter_values((0..1000).map(|i| i / 10))) as ArrayRef,
+            ),
+            ("vec", Arc::new(vectors.clone())),
+        ])
+        .unwrap();
+        let schema = batch.schema();
+        let mut dataset = Dataset::write(
+            RecordBatchIterator::new(vec![Ok(batch)], schema),
+            "memory://test",
+            None,
+        )
+        .await
+        .unwrap();
+
+        let query = vectors.value(0);
+        let k = 10;
+        let search = |dataset: Dataset, lambda: Option<f32>, group_limit: Option<usize>| {
+            let query = query.clone();
+            async move {
+                let mut scanner = dataset.scan();
+                scanner.nearest("vec", &query, k).unwrap().nprobs(4);
+                if let Some(lambda) = lambda {
+                    scanner.mmr(lambda).unwrap();
+                }
+                if let Some(limit) = group_limit {
+                    scanner.group_limit("doc", limit).unwrap();
+                }
+                scanner.try_into_batch().await.unwrap()
+            }
+        };
+
+        for indexed in [false, true] {
+            if indexed {
+                dataset
+                    .create_index(
+                        &["vec"],
+                        IndexType::Vector,
+                        None,
+                        &VectorIndexParams::ivf_flat(4, DistanceType::L2),
+                        true,
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            // without diversification, all the results are chunks of the same document
+            let results = search(dataset.clone(), None, None).await;
+            let docs = results["doc"].as_primitive::<UInt32Type>();
+            assert_eq!(results.num_rows(), k);
+            assert!(docs.values().iter().all(|doc| *doc == 0));
+
+            // at most 2 chunks of each document, over-fetching more than one round of candidates
+            let results = search(dataset.clone(), None, Some(2)).await;
+            let docs = results["doc"].as_primitive::<UInt32Type>();
+            assert_eq!(results.num_rows(), k);
+            let counts = docs.values().iter().counts();
+            assert_eq!(counts.len(), 5);
+            assert!(counts.values().all(|count| *count == 2));
+            let distances = results[DIST_COL].as_primitive::<Float32Type>();
+            assert!(distances.values().windows(2).all(|w| w[0] <= w[1]));
+
+            // mmr with lambda 1 ranks the results by distance only
+            let expected = search(dataset.clone(), None, None).await;
+            let results = search(dataset.clone(), Some(1.0), None).await;
+            assert_eq!(&results[DIST_COL], &expected[DIST_COL]);
+
+            // mmr selects the nearest result first, and then spreads over the documents
+            let results = search(dataset.clone(), Some(0.5), Some(1)).await;
+            let docs = results["doc"].as_primitive::<UInt32Type>();
+            assert_eq!(results.num_rows(), k);
+            assert_eq!(docs.value(0), 0);
+            assert_eq!(docs.values().iter().unique().count(), k);
+        }
+
+        let mut scanner = dataset.scan();
+        assert!(scanner.mmr(1.5).is_err());
+        assert!(scanner.group_limit("doc", 0).is_err());
+        assert!(scanner.group_limit("missing", 1).is_err());
+    }
 }
@@ -10,12 +10,14 @@ use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema};
 use datafusion::execution::SendableRecordBatchStream;
 use futures::stream::{StreamExt, TryStreamExt};
 use lance_arrow::SchemaExt;
-use lance_core::datatypes::{Field, Schema};
+use lance_core::datatypes::{Field, Schema, LANCE_GENERATED_EXPRESSION_META_KEY};
 use lance_datafusion::utils::StreamingWriteSource;
 use lance_table::format::Fragment;
 use snafu::location;
 
 use super::fragment::FileFragment;
+use super::write::defaults::validate_default_expression;
+use super::write::generated::{generated_column_inputs, validate_generated_columns};
 use super::{
     transaction::{Operation, Transaction},
     Dataset,
@@ -61,6 +63,12 @@ pub enum NewColumnTransform {
     BatchUDF(BatchUDF),
     /// A set of SQL expressions that define new columns.
     SqlExpressions(Vec<(String, String)>),
+    /// A set of SQL expressions that define new generated columns.
+    ///
+    /// Unlike [`Self::SqlExpressions`], the expressions are stored in the schema
+    /// and re-evaluated for every row written afterwards.  An expression may only
+    /// read columns that are not generated themselves.
+    GeneratedColumns(Vec<(String, String)>),
     /// A stream of RecordBatches that define new columns.
     Stream(SendableRecordBatchStream),
     /// An iterator of RecordBatches that define new columns.
@@ -79,6 +87,11 @@ pub struct ColumnAlteration {
     pub nullable: Option<bool>,
     /// The new data type of the column. If None, the data type will not be changed.
     pub data_type: Option<DataType>,
+    /// Turn a generated column into a plain column that keeps its current values.
+    pub materialize: bool,
+    /// The new default expression of the column. `Some(None)` removes the
+    /// default. If None, the default will not be changed.
+    pub default_expression: Option<Option<String>>,
 }
 
 impl ColumnAlteration {
@@ -88,6 +101,8 @@ impl ColumnAlteration {
             rename: None,
             nullable: None,
             data_type: None,
+            materialize: false,
+            default_expression: None,
         }
     }
 
@@ -105,6 +120,18 @@ impl ColumnAlteration {
         self.data_type = Some(data_type);
         self
     }
+
+    pub fn materialize(mut self) -> Self {
+        self.materialize = true;
+        self
+    }
+
+    /// Set the SQL expression of the default value of the column, or remove
+    /// the default with `None`.
+    pub fn set_default(mut self, expression: Option<String>) -> Self {
+        self.default_expression = Some(expression);
+        self
+    }
 }
 
 /// Limit casts to same type. This is mostly to filter out weird casts like
@@ -175,17 +202,29 @@ pub(super) async fn add_columns_to_fragments(
             .await?;
             Result::Ok((udf.output_schema, fragments))
         }
-        NewColumnTransform::SqlExpressions(expressions) => {
+        transform @ (NewColumnTransform::SqlExpressions(_)
+        | NewColumnTransform::GeneratedColumns(_)) => {
+            let (expressions, generated) = match transform {
+                NewColumnTransform::SqlExpressions(expressions) => (expressions, false),
+                NewColumnTransform::GeneratedColumns(expressions) => (expressions, true),
+                _ => unreachable!(),
+            };
+            if generated {
+                for (name, expr) in &expressions {
+                    generated_column_inputs(dataset.schema(), name, expr)?;
+                }
+            }
+
             // We just transform the SQL expression into a UDF backed by DataFusion
             // physical expressions.
             let arrow_schema = Arc::new(ArrowSchema::from(dataset.schema()));
             let planner = Planner::new(arrow_schema);
             let exprs = expressions
-                .into_iter()
+                .iter()
                 .map(|(name, expr)| {
-                    let expr = planner.parse_expr(&expr)?;
+                    let expr = planner.parse_expr(expr)?;
                     let expr = planner.optimize_expr(expr)?;
-                    Ok((name, expr))
+                    Ok((name.clone(), expr))
                 })
                 .collect::<Result<Vec<_>>>()?;
 
@@ -211,12 +250,21 @@ pub(super) async fn add_columns_to_fragments(
             let output_schema = Arc::new(ArrowSchema::new(
                 exprs
                     .iter()
-                    .map(|(name, expr)| {
-                        Ok(ArrowField::new(
+                    .zip(&expressions)
+                    .map(|((name, expr), (_, sql))| {
+                        let field = ArrowField::new(
                             name,
                             expr.data_type(read_schema.as_ref())?,
                             expr.nullable(read_schema.as_ref())?,
-                        ))
+                        );
+                        if generated {
+                            Ok(field.with_metadata(
+                                [(LANCE_GENERATED_EXPRESSION_META_KEY.to_string(), sql.clone())]
+                                    .into(),
+                            ))
+                        } else {
+                            Ok(field)
+                        }
                     })
                     .collect::<Result<Vec<_>>>()?,
             ));
@@ -502,7 +550,34 @@ pub(super) async fn alter_columns(
             }
         }
 
+        if alteration.materialize && field_src.generated_expression().is_none() {
+            return Err(Error::invalid_input(
+                format!(
+                    "Column \"{}\" is not a generated column and cannot be materialized",
+                    alteration.path
+                ),
+                location!(),
+            ));
+        }
+        if field_src.generated_expression().is_some()
+            && !alteration.materialize
+            && alteration.data_type.is_some()
+        {
+            return Err(Error::invalid_input(
+                format!(
+                    "Cannot cast generated column \"{}\", materialize it first",
+                    alteration.path
+                ),
+                location!(),
+            ));
+        }
+
         let field_dest = new_schema.mut_field_by_id(field_src.id).unwrap();
+        if alteration.materialize {
+            field_dest
+                .metadata
+                .remove(LANCE_GENERATED_EXPRESSION_META_KEY);
+        }
         if let Some(rename) = &alteration.rename {
             field_dest.name.clone_from(rename);
         }
@@ -532,12 +607,29 @@ pub(super) async fn alter_columns(
             );
             *field_dest = Field::try_from(&arrow_field)?;
             field_dest.set_id(field_src.parent_id, &mut next_field_id);
+            field_dest.set_default_expression(field_src.default_expression().map(String::from));
 
             cast_fields.push((field_src.clone(), field_dest.clone()));
         }
+
+        if let Some(default_expression) = &alteration.default_expression {
+            if field_src.parent_id != -1 {
+                return Err(Error::NotSupported {
+                    source: format!(
+                        "Column \"{}\" is a nested field and cannot have a default",
+                        alteration.path
+                    )
+                    .into(),
+                    location: location!(),
+                });
+            }
+            field_dest.set_default_expression(default_expression.clone());
+        }
+        validate_default_expression(field_dest)?;
     }
 
     new_schema.validate()?;
+    validate_generated_columns(&new_schema)?;
 
     // If we aren't casting a column, we don't need to touch the fragments.
     let transaction = if cast_fields.is_empty() {
@@ -663,6 +755,7 @@ pub(super) async fn drop_columns(dataset: &mut Dataset, columns: &[&str]) -> Res
 
     let columns_to_remove = dataset.manifest.schema.project(columns)?;
     let new_schema = dataset.manifest.schema.exclude(columns_to_remove)?;
+    validate_generated_columns(&new_schema)?;
 
     if new_schema.fields.is_empty() {
         return Err(Error::invalid_input(
@@ -965,6 +1058,7 @@ mod test {
                         deletion_file: None,
                         row_id_meta: None,
                         physical_rows: Some(50),
+                        partition: vec![],
                     }))
                 } else {
                     Ok(None)
@@ -3,10 +3,26 @@
 
 //! Module for statistics related to the dataset.
 
-use std::{collections::HashMap, future::Future, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, future::Future, sync::Arc};
 
+use arrow::compute::cast;
+use arrow_arith::aggregate::{
+    max, max_binary, max_binary_view, max_boolean, max_string, max_string_view, min, min_binary,
+    min_binary_view, min_boolean, min_string, min_string_view,
+};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, ArrowPrimitiveType, RecordBatch};
+use arrow_schema::DataType;
+use datafusion::logical_expr::{Between, BinaryExpr, Expr, Operator};
+use datafusion::scalar::ScalarValue;
+use lance_core::datatypes::Schema;
 use lance_core::Result;
 use lance_io::scheduler::{ScanScheduler, SchedulerConfig};
+use lance_table::format::{ColumnStats, Fragment, StatValue};
 
 use super::{fragment::FileFragment, Dataset};
 
@@ -67,3 +83,734 @@ impl DatasetStatisticsExt for Dataset {
         })
     }
 }
+
+/// Largest string or binary value kept as a min/max statistic.  Columns with
+/// larger values only record their null count.
+const MAX_STAT_VALUE_LEN: usize = 256;
+
+/// Collects [`ColumnStats`] for the top-level columns of the batches written
+/// to a data file.
+pub(crate) struct ColumnStatsCollector {
+    columns: Vec<ColumnStatsAccumulator>,
+}
+
+struct ColumnStatsAccumulator {
+    field_id: i32,
+    name: String,
+    null_count: u64,
+    min_max: Option<(StatValue, StatValue)>,
+    /// False once the column had non-null values that could not be summarized
+    summarizable: bool,
+    /// False if the column was missing from a batch
+    complete: bool,
+}
+
+impl ColumnStatsCollector {
+    pub fn new(schema: &Schema) -> Self {
+        let columns = schema
+            .fields
+            .iter()
+            .map(|field| ColumnStatsAccumulator {
+                field_id: field.id,
+                name: field.name.clone(),
+                null_count: 0,
+                min_max: None,
+                summarizable: true,
+                complete: true,
+            })
+            .collect();
+        Self { columns }
+    }
+
+    pub fn update(&mut self, batch: &RecordBatch) -> Result<()> {
+        for column in self.columns.iter_mut() {
+            let Some(array) = batch.column_by_name(&column.name) else {
+                column.complete = false;
+                continue;
+            };
+            let null_count = array.logical_null_count();
+            column.null_count += null_count as u64;
+            if !column.summarizable || null_count == array.len() {
+                continue;
+            }
+            match array_min_max(array)? {
+                Some((min, max)) => {
+                    column.min_max = match column.min_max.take() {
+                        None => Some((min, max)),
+                        Some((old_min, old_max)) => {
+                            let min = if min < old_min { min } else { old_min };
+                            let max = if max > old_max { max } else { old_max };
+                            Some((min, max))
+                        }
+                    };
+                }
+                None => {
+                    column.summarizable = false;
+                    column.min_max = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The statistics of all the batches seen so far.  Resets the collector.
+    pub fn finish(&mut self) -> Vec<ColumnStats> {
+        std::mem::take(&mut self.columns)
+            .into_iter()
+            .filter(|column| column.complete)
+            .map(|column| ColumnStats {
+                field_id: column.field_id,
+                null_count: column.null_count,
+                min_max: column.min_max,
+            })
+            .collect()
+    }
+}
+
+fn primitive_min_max<T: ArrowPrimitiveType>(
+    array: &dyn Array,
+    to_value: impl Fn(T::Native) -> StatValue,
+) -> Option<(StatValue, StatValue)> {
+    let array = array.as_primitive::<T>();
+    Some((to_value(min(array)?), to_value(max(array)?)))
+}
+
+fn float_min_max<T: ArrowPrimitiveType>(array: &dyn Array) -> Option<(StatValue, StatValue)>
+where
+    T::Native: Into<f64>,
+{
+    // NaN sorts after all other values, so it can only show up as the max
+    let (min, max) = primitive_min_max::<T>(array, |v| StatValue::Float(v.into()))?;
+    match max {
+        StatValue::Float(v) if v.is_nan() => None,
+        max => Some((min, max)),
+    }
+}
+
+fn bounded_string(value: &str) -> Option<StatValue> {
+    (value.len() <= MAX_STAT_VALUE_LEN).then(|| StatValue::String(value.to_string()))
+}
+
+fn bounded_binary(value: &[u8]) -> Option<StatValue> {
+    (value.len() <= MAX_STAT_VALUE_LEN).then(|| StatValue::Binary(value.to_vec()))
+}
+
+/// The smallest and largest non-null values in `array`.
+///
+/// Returns `None` if the type is not supported, there are no non-null values
+/// or the values cannot be summarized.
+fn array_min_max(array: &dyn Array) -> Result<Option<(StatValue, StatValue)>> {
+    let min_max = match array.data_type() {
+        DataType::Int8 => primitive_min_max::<Int8Type>(array, |v| StatValue::Int(v as i64)),
+        DataType::Int16 => primitive_min_max::<Int16Type>(array, |v| StatValue::Int(v as i64)),
+        DataType::Int32 => primitive_min_max::<Int32Type>(array, |v| StatValue::Int(v as i64)),
+        DataType::Int64 => primitive_min_max::<Int64Type>(array, StatValue::Int),
+        DataType::UInt8 => primitive_min_max::<UInt8Type>(array, |v| StatValue::UInt(v as u64)),
+        DataType::UInt16 => primitive_min_max::<UInt16Type>(array, |v| StatValue::UInt(v as u64)),
+        DataType::UInt32 => primitive_min_max::<UInt32Type>(array, |v| StatValue::UInt(v as u64)),
+        DataType::UInt64 => primitive_min_max::<UInt64Type>(array, StatValue::UInt),
+        DataType::Float32 => float_min_max::<Float32Type>(array),
+        DataType::Float64 => float_min_max::<Float64Type>(array),
+        DataType::Date32 | DataType::Time32(_) => {
+            let array = cast(array, &DataType::Int32)?;
+            primitive_min_max::<Int32Type>(&array, |v| StatValue::Int(v as i64))
+        }
+        DataType::Date64
+        | DataType::Time64(_)
+        | DataType::Timestamp(_, _)
+        | DataType::Duration(_) => {
+            let array = cast(array, &DataType::Int64)?;
+            primitive_min_max::<Int64Type>(&array, StatValue::Int)
+        }
+        DataType::Boolean => {
+            let array = array.as_boolean();
+            min_boolean(array)
+                .zip(max_boolean(array))
+                .map(|(min, max)| (StatValue::Bool(min), StatValue::Bool(max)))
+        }
+        DataType::Utf8 => {
+            let array = array.as_string::<i32>();
+            min_string(array)
+                .and_then(bounded_string)
+                .zip(max_string(array).and_then(bounded_string))
+        }
+        DataType::LargeUtf8 => {
+            let array = array.as_string::<i64>();
+            min_string(array)
+                .and_then(bounded_string)
+                .zip(max_string(array).and_then(bounded_string))
+        }
+        DataType::Utf8View => {
+            let array = array.as_string_view();
+            min_string_view(array)
+                .and_then(bounded_string)
+                .zip(max_string_view(array).and_then(bounded_string))
+        }
+        DataType::Binary => {
+            let array = array.as_binary::<i32>();
+            min_binary(array)
+                .and_then(bounded_binary)
+                .zip(max_binary(array).and_then(bounded_binary))
+        }
+        DataType::LargeBinary => {
+            let array = array.as_binary::<i64>();
+            min_binary(array)
+                .and_then(bounded_binary)
+                .zip(max_binary(array).and_then(bounded_binary))
+        }
+        DataType::BinaryView => {
+            let array = array.as_binary_view();
+            min_binary_view(array)
+                .and_then(bounded_binary)
+                .zip(max_binary_view(array).and_then(bounded_binary))
+        }
+        _ => None,
+    };
+    Ok(min_max)
+}
+
+/// Convert a literal to a [`StatValue`] comparable with the statistics of a
+/// column of type `data_type`.
+fn literal_stat_value(value: &ScalarValue, data_type: &DataType) -> Option<StatValue> {
+    let literal_type = value.data_type();
+    let compatible = literal_type == *data_type
+        || (literal_type.is_integer() && data_type.is_integer())
+        || (literal_type.is_floating() && data_type.is_floating());
+    if !compatible {
+        // The planner normally casts the literal to the column type.  Other
+        // representations (e.g. different timestamp units) are not comparable.
+        let both_strings = [&literal_type, data_type]
+            .iter()
+            .all(|t| matches!(t, DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View));
+        let both_binary = [&literal_type, data_type].iter().all(|t| {
+            matches!(
+                t,
+                DataType::Binary | DataType::LargeBinary | DataType::BinaryView
+            )
+        });
+        if !both_strings && !both_binary {
+            return None;
+        }
+    }
+    let array = value.to_array().ok()?;
+    let (min, _) = array_min_max(array.as_ref()).ok()??;
+    Some(min)
+}
+
+/// Compare two statistic values, if they are of the same kind.
+fn compare_stat_values(a: &StatValue, b: &StatValue) -> Option<Ordering> {
+    match (a, b) {
+        (StatValue::Int(a), StatValue::Int(b)) => Some(a.cmp(b)),
+        (StatValue::UInt(a), StatValue::UInt(b)) => Some(a.cmp(b)),
+        (StatValue::Int(a), StatValue::UInt(b)) => Some((*a as i128).cmp(&(*b as i128))),
+        (StatValue::UInt(a), StatValue::Int(b)) => Some((*a as i128).cmp(&(*b as i128))),
+        // Same total order as the arrow comparison kernels, where -0.0 < 0.0
+        (StatValue::Float(a), StatValue::Float(b)) => Some(a.total_cmp(b)),
+        (StatValue::String(a), StatValue::String(b)) => Some(a.cmp(b)),
+        (StatValue::Binary(a), StatValue::Binary(b)) => Some(a.cmp(b)),
+        (StatValue::Bool(a), StatValue::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Which rows of a fragment can match a filter, according to its statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FragmentMatch {
+    /// No row matches
+    None,
+    /// Every row matches
+    All,
+    /// Some rows may match
+    Unknown,
+}
+
+impl FragmentMatch {
+    fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::None, _) | (_, Self::None) => Self::None,
+            (Self::All, Self::All) => Self::All,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn or(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::All, _) | (_, Self::All) => Self::All,
+            (Self::None, Self::None) => Self::None,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn from_bounds(none: bool, all: bool) -> Self {
+        if none {
+            Self::None
+        } else if all {
+            Self::All
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+/// Evaluates filters against the column statistics of a fragment.
+pub(crate) struct FragmentStatsPruner<'a> {
+    schema: &'a Schema,
+    fragment: &'a Fragment,
+}
+
+impl<'a> FragmentStatsPruner<'a> {
+    pub fn new(schema: &'a Schema, fragment: &'a Fragment) -> Self {
+        Self { schema, fragment }
+    }
+
+    fn column_stats(&self, column: &str) -> Option<(&ColumnStats, DataType, u64)> {
+        let num_rows = self.fragment.physical_rows? as u64;
+        let field = self.schema.field(column)?;
+        // The newest file holding the field has its current values
+        let stats = self
+            .fragment
+            .files
+            .iter()
+            .rev()
+            .find_map(|file| file.column_stats(field.id))?;
+        Some((stats, field.data_type(), num_rows))
+    }
+
+    /// Which rows of the fragment can match `filter`.
+    ///
+    /// Rows that have been deleted are not taken into account: `All` means that
+    /// every remaining row matches.
+    pub fn evaluate(&self, filter: &Expr) -> FragmentMatch {
+        match filter {
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => match op {
+                Operator::And => self.evaluate(left).and(self.evaluate(right)),
+                Operator::Or => self.evaluate(left).or(self.evaluate(right)),
+                _ => match (left.as_ref(), right.as_ref()) {
+                    (Expr::Column(column), Expr::Literal(value)) => {
+                        self.compare(&column.name, *op, value)
+                    }
+                    (Expr::Literal(value), Expr::Column(column)) => match op.swap() {
+                        Some(op) => self.compare(&column.name, op, value),
+                        None => FragmentMatch::Unknown,
+                    },
+                    _ => FragmentMatch::Unknown,
+                },
+            },
+            Expr::Not(expr) => match self.evaluate(expr) {
+                FragmentMatch::All => FragmentMatch::None,
+                // Rows with nulls match neither the expression nor its negation
+                _ => FragmentMatch::Unknown,
+            },
+            Expr::InList(in_list) if !in_list.negated => match in_list.expr.as_ref() {
+                Expr::Column(column) => {
+                    in_list
+                        .list
+                        .iter()
+                        .fold(FragmentMatch::None, |acc, value| match value {
+                            Expr::Literal(value) => {
+                                acc.or(self.compare(&column.name, Operator::Eq, value))
+                            }
+                            _ => FragmentMatch::Unknown,
+                        })
+                }
+                _ => FragmentMatch::Unknown,
+            },
+            Expr::Between(Between {
+                expr,
+                negated: false,
+                low,
+                high,
+            }) => match (expr.as_ref(), low.as_ref(), high.as_ref()) {
+                (Expr::Column(column), Expr::Literal(low), Expr::Literal(high)) => self
+                    .compare(&column.name, Operator::GtEq, low)
+                    .and(self.compare(&column.name, Operator::LtEq, high)),
+                _ => FragmentMatch::Unknown,
+            },
+            Expr::IsNull(expr) | Expr::IsNotNull(expr) => {
+                let Expr::Column(column) = expr.as_ref() else {
+                    return FragmentMatch::Unknown;
+                };
+                let Some((stats, _, num_rows)) = self.column_stats(&column.name) else {
+                    return FragmentMatch::Unknown;
+                };
+                let (no_nulls, all_nulls) = (stats.null_count == 0, stats.null_count == num_rows);
+                match filter {
+                    Expr::IsNull(_) => FragmentMatch::from_bounds(no_nulls, all_nulls),
+                    _ => FragmentMatch::from_bounds(all_nulls, no_nulls),
+                }
+            }
+            _ => FragmentMatch::Unknown,
+        }
+    }
+
+    fn compare(&self, column: &str, op: Operator, value: &ScalarValue) -> FragmentMatch {
+        let Some((stats, data_type, num_rows)) = self.column_stats(column) else {
+            return FragmentMatch::Unknown;
+        };
+        if value.is_null() {
+            return FragmentMatch::Unknown;
+        }
+        if stats.null_count == num_rows {
+            // Comparisons with null are never true
+            return FragmentMatch::None;
+        }
+        let Some((min, max)) = stats.min_max.as_ref() else {
+            return FragmentMatch::Unknown;
+        };
+        let Some(value) = literal_stat_value(value, &data_type) else {
+            return FragmentMatch::Unknown;
+        };
+        let (Some(min_cmp), Some(max_cmp)) = (
+            compare_stat_values(min, &value),
+            compare_stat_values(max, &value),
+        ) else {
+            return FragmentMatch::Unknown;
+        };
+        let no_nulls = stats.null_count == 0;
+        use Ordering::*;
+        match op {
+            Operator::Eq => FragmentMatch::from_bounds(
+                min_cmp == Greater || max_cmp == Less,
+                no_nulls && min_cmp == Equal && max_cmp == Equal,
+            ),
+            Operator::NotEq => FragmentMatch::from_bounds(
+                min_cmp == Equal && max_cmp == Equal,
+                no_nulls && (min_cmp == Greater || max_cmp == Less),
+            ),
+            Operator::Lt => {
+                FragmentMatch::from_bounds(min_cmp != Less, no_nulls && max_cmp == Less)
+            }
+            Operator::LtEq => {
+                FragmentMatch::from_bounds(min_cmp == Greater, no_nulls && max_cmp != Greater)
+            }
+            Operator::Gt => {
+                FragmentMatch::from_bounds(max_cmp != Greater, no_nulls && min_cmp == Greater)
+            }
+            Operator::GtEq => {
+                FragmentMatch::from_bounds(max_cmp == Less, no_nulls && min_cmp != Less)
+            }
+            _ => FragmentMatch::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{Float64Array, Int64Array, RecordBatchIterator, StringArray};
+    use arrow_schema::{Field as ArrowField, Schema as ArrowSchema};
+    use datafusion::physical_plan::ExecutionPlan;
+    use datafusion::prelude::{col, lit};
+
+    use crate::dataset::optimize::{compact_files, CompactionOptions};
+    use crate::dataset::WriteParams;
+    use crate::dataset::{MergeInsertBuilder, WhenMatched, WhenNotMatched};
+    use crate::io::exec::LanceScanExec;
+
+    fn test_batch(range: std::ops::Range<i64>) -> RecordBatch {
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("i", DataType::Int64, false),
+            ArrowField::new("f", DataType::Float64, true),
+            ArrowField::new("s", DataType::Utf8, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from_iter_values(range.clone())),
+                Arc::new(Float64Array::from_iter(
+                    range
+                        .clone()
+                        .map(|i| (i % 2 == 0).then_some(i as f64 / 2.0)),
+                )),
+                Arc::new(StringArray::from_iter(
+                    range.map(|i| (i % 3 != 0).then(|| format!("s{:04}", i))),
+                )),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn write_dataset(uri: &str) -> Dataset {
+        let batches = vec![
+            test_batch(0..100),
+            test_batch(100..200),
+            test_batch(200..300),
+        ];
+        let schema = batches[0].schema();
+        let reader = RecordBatchIterator::new(batches.into_iter().map(Ok), schema);
+        let params = WriteParams {
+            max_rows_per_file: 100,
+            ..Default::default()
+        };
+        Dataset::write(reader, uri, Some(params)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_collect_column_stats() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = write_dataset(test_uri).await;
+        let fragments = dataset.get_fragments();
+        assert_eq!(fragments.len(), 3);
+
+        let file = &fragments[1].metadata().files[0];
+        let field_id = |name: &str| dataset.schema().field(name).unwrap().id;
+        assert_eq!(
+            file.column_stats(field_id("i")),
+            Some(&ColumnStats {
+                field_id: field_id("i"),
+                null_count: 0,
+                min_max: Some((StatValue::Int(100), StatValue::Int(199))),
+            })
+        );
+        assert_eq!(
+            file.column_stats(field_id("f")),
+            Some(&ColumnStats {
+                field_id: field_id("f"),
+                null_count: 50,
+                min_max: Some((StatValue::Float(50.0), StatValue::Float(99.0))),
+            })
+        );
+        assert_eq!(
+            file.column_stats(field_id("s")),
+            Some(&ColumnStats {
+                field_id: field_id("s"),
+                null_count: 33,
+                min_max: Some((
+                    StatValue::String("s0100".into()),
+                    StatValue::String("s0199".into())
+                )),
+            })
+        );
+
+        // The statistics survive a round trip through the manifest
+        let dataset = Dataset::open(test_uri).await.unwrap();
+        assert_eq!(
+            &dataset.get_fragments()[1].metadata().files[0].column_stats,
+            &file.column_stats
+        );
+    }
+
+    #[test]
+    fn test_collect_unsummarizable_values() {
+        let schema = ArrowSchema::new(vec![
+            ArrowField::new("f", DataType::Float64, true),
+            ArrowField::new("s", DataType::Utf8, true),
+        ]);
+        let mut collector = ColumnStatsCollector::new(&Schema::try_from(&schema).unwrap());
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Float64Array::from(vec![Some(1.0), None, Some(f64::NAN)])),
+                Arc::new(StringArray::from(vec![
+                    Some("a".to_string()),
+                    None,
+                    Some("x".repeat(MAX_STAT_VALUE_LEN + 1)),
+                ])),
+            ],
+        )
+        .unwrap();
+        collector.update(&batch).unwrap();
+        let stats = collector.finish();
+        assert_eq!(stats.len(), 2);
+        for stats in stats {
+            assert_eq!(stats.null_count, 1);
+            assert_eq!(stats.min_max, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_pruning() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = write_dataset(test_uri).await;
+        let fragments = dataset.get_fragments();
+        let evaluate = |expr: Expr| {
+            fragments
+                .iter()
+                .map(|f| FragmentStatsPruner::new(dataset.schema(), f.metadata()).evaluate(&expr))
+                .collect::<Vec<_>>()
+        };
+        use FragmentMatch::{All, None as No, Unknown};
+        assert_eq!(evaluate(col("i").lt(lit(100i64))), vec![All, No, No]);
+        assert_eq!(
+            evaluate(col("i").lt_eq(lit(100i64))),
+            vec![All, Unknown, No]
+        );
+        assert_eq!(evaluate(lit(250i64).lt(col("i"))), vec![No, No, Unknown]);
+        assert_eq!(evaluate(col("i").eq(lit(150i64))), vec![No, Unknown, No]);
+        assert_eq!(
+            evaluate(col("i").between(lit(100i64), lit(299i64))),
+            vec![No, All, All]
+        );
+        assert_eq!(
+            evaluate(col("i").in_list(vec![lit(5i64), lit(205i64)], false)),
+            vec![Unknown, No, Unknown]
+        );
+        assert_eq!(
+            evaluate(col("i").lt(lit(100i64)).or(col("i").gt_eq(lit(200i64)))),
+            vec![All, No, All]
+        );
+        // Nullable columns never match all rows
+        assert_eq!(evaluate(col("f").gt_eq(lit(0.0))), vec![Unknown; 3]);
+        assert_eq!(evaluate(col("f").lt(lit(50.0))), vec![Unknown, No, No]);
+        assert_eq!(evaluate(col("s").gt(lit("s0200"))), vec![No, No, Unknown]);
+        assert_eq!(evaluate(col("i").is_null()), vec![No; 3]);
+        assert_eq!(evaluate(col("i").is_not_null()), vec![All; 3]);
+        assert_eq!(
+            evaluate(!col("i").lt(lit(100i64))),
+            vec![No, Unknown, Unknown]
+        );
+        // Literals that cannot be compared with the statistics never prune
+        assert_eq!(evaluate(col("i").eq(lit("a"))), vec![Unknown; 3]);
+
+        let mut scanner = dataset.scan();
+        scanner.filter("i >= 120 AND i < 150").unwrap();
+        let plan = scanner.create_plan().await.unwrap();
+        assert_eq!(scanned_fragments(plan.as_ref()), 1);
+        let batch = scanner.try_into_batch().await.unwrap();
+        assert_eq!(batch.num_rows(), 30);
+
+        // Counts decided by the statistics alone never open the data files
+        assert_eq!(
+            dataset
+                .count_rows(Some("i >= 150 OR s IS NULL".into()))
+                .await
+                .unwrap(),
+            150 + 50
+        );
+        let mut dataset = dataset;
+        dataset.delete("i < 10").await.unwrap();
+        for fragment in dataset.get_fragments() {
+            for file in &fragment.metadata().files {
+                let path = dataset.data_dir().child(file.path.as_str());
+                dataset.object_store().delete(&path).await.unwrap();
+            }
+        }
+        assert_eq!(
+            dataset
+                .count_rows(Some("i < 100 OR i >= 200".into()))
+                .await
+                .unwrap(),
+            190
+        );
+    }
+
+    fn scanned_fragments(plan: &dyn ExecutionPlan) -> usize {
+        match plan.as_any().downcast_ref::<LanceScanExec>() {
+            Some(scan) => scan.fragments().len(),
+            None => plan
+                .children()
+                .into_iter()
+                .map(|child| scanned_fragments(child.as_ref()))
+                .sum(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signed_zero_stats() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "z",
+            DataType::Float64,
+            false,
+        )]));
+        let batches = [0.0, -0.0].map(|zero| {
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![Arc::new(Float64Array::from_iter_values([zero; 10]))],
+            )
+        });
+        let params = WriteParams {
+            max_rows_per_file: 10,
+            ..Default::default()
+        };
+        let dataset = Dataset::write(
+            RecordBatchIterator::new(batches, schema.clone()),
+            test_uri,
+            Some(params),
+        )
+        .await
+        .unwrap();
+        let fragments = dataset.get_fragments();
+        let evaluate = |expr: Expr| {
+            fragments
+                .iter()
+                .map(|f| FragmentStatsPruner::new(dataset.schema(), f.metadata()).evaluate(&expr))
+                .collect::<Vec<_>>()
+        };
+        use FragmentMatch::{All, None as No};
+        assert_eq!(evaluate(col("z").gt(lit(-0.0))), vec![All, No]);
+        assert_eq!(evaluate(col("z").lt(lit(0.0))), vec![No, All]);
+        assert_eq!(evaluate(col("z").eq(lit(-0.0))), vec![No, All]);
+
+        let mut scanner = dataset.scan();
+        scanner.filter_expr(col("z").gt(lit(-0.0)));
+        let batch = scanner.try_into_batch().await.unwrap();
+        assert_eq!(batch.num_rows(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_partial_update_stats() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = Arc::new(write_dataset(test_uri).await);
+
+        // Only update the column `f`, the old values stay in the old data files
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("i", DataType::Int64, false),
+            ArrowField::new("f", DataType::Float64, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from_iter_values(0..10)),
+                Arc::new(Float64Array::from_iter_values((0..10).map(|_| 1000.0))),
+            ],
+        )
+        .unwrap();
+        let job = MergeInsertBuilder::try_new(dataset, vec!["i".to_string()])
+            .unwrap()
+            .when_matched(WhenMatched::UpdateAll)
+            .when_not_matched(WhenNotMatched::DoNothing)
+            .try_build()
+            .unwrap();
+        let (dataset, _) = job
+            .execute_reader(RecordBatchIterator::new(vec![Ok(batch)], schema))
+            .await
+            .unwrap();
+        assert!(dataset.get_fragments()[0].metadata().files.len() > 1);
+
+        assert_eq!(
+            dataset.count_rows(Some("f >= 1000".into())).await.unwrap(),
+            10
+        );
+        let mut scanner = dataset.scan();
+        scanner.filter("f >= 1000").unwrap();
+        let batch = scanner.try_into_batch().await.unwrap();
+        assert_eq!(batch.num_rows(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_updates_stats() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = write_dataset(test_uri).await;
+        dataset.delete("i >= 280").await.unwrap();
+        compact_files(&mut dataset, CompactionOptions::default(), None)
+            .await
+            .unwrap();
+        let fragments = dataset.get_fragments();
+        assert_eq!(fragments.len(), 1);
+        let field_id = dataset.schema().field("i").unwrap().id;
+        let stats = fragments[0].metadata().files[0]
+            .column_stats(field_id)
+            .unwrap();
+        assert_eq!(
+            stats.min_max,
+            Some((StatValue::Int(0), StatValue::Int(279)))
+        );
+    }
+}
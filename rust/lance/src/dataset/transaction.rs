@@ -67,7 +67,7 @@ use lance_table::{
         commit::CommitHandler,
         manifest::{read_manifest, read_manifest_indexes},
     },
-    rowids::{write_row_ids, RowIdSequence},
+    rowids::{read_row_ids, write_row_ids, RowIdSequence},
 };
 use object_store::path::Path;
 use roaring::RoaringBitmap;
@@ -204,6 +204,11 @@ pub enum Operation {
         new_fragments: Vec<Fragment>,
         /// The fields that have been modified
         fields_modified: Vec<u32>,
+        /// The rows replaced by the rows of `new_fragments`, in the same order.
+        ///
+        /// Only set when each new row replaces exactly one existing row, as is
+        /// the case for the dataset updater.
+        replaced_row_ids: Option<RowIdSequence>,
     },
 
     /// Project to a new schema. This only changes the schema, not the data.
@@ -328,18 +333,21 @@ impl PartialEq for Operation {
                     updated_fragments: a_updated,
                     new_fragments: a_new,
                     fields_modified: a_fields,
+                    replaced_row_ids: a_replaced,
                 },
                 Self::Update {
                     removed_fragment_ids: b_removed,
                     updated_fragments: b_updated,
                     new_fragments: b_new,
                     fields_modified: b_fields,
+                    replaced_row_ids: b_replaced,
                 },
             ) => {
                 compare_vec(a_removed, b_removed)
                     && compare_vec(a_updated, b_updated)
                     && compare_vec(a_new, b_new)
                     && compare_vec(a_fields, b_fields)
+                    && a_replaced == b_replaced
             }
             (Self::Project { schema: a }, Self::Project { schema: b }) => a == b,
             (
@@ -1116,6 +1124,7 @@ impl Transaction {
                 updated_fragments,
                 new_fragments,
                 fields_modified,
+                ..
             } => {
                 final_fragments.extend(maybe_existing_fragments?.iter().filter_map(|f| {
                     if removed_fragment_ids.contains(&f.id) {
@@ -1207,6 +1216,29 @@ impl Transaction {
             Operation::Merge { ref fragments, .. } => {
                 final_fragments.extend(fragments.clone());
 
+                // Fragments merged in from another version may carry row ids
+                // that were never allocated in this lineage.
+                if let Some(next_row_id) = &mut next_row_id {
+                    let max_fragment_id = current_manifest.and_then(|m| m.max_fragment_id());
+                    for fragment in fragments.iter().filter(|f| Some(f.id) > max_fragment_id) {
+                        if let Some(RowIdMeta::Inline(data)) = &fragment.row_id_meta {
+                            if let Some(max_row_id) = read_row_ids(data)?.iter().max() {
+                                *next_row_id = (*next_row_id).max(max_row_id + 1);
+                            }
+                        }
+                    }
+                }
+
+                // The data of indexed fields may have been replaced, e.g. when
+                // merging another version, so those fragments are no longer indexed.
+                if let Some(current_manifest) = current_manifest {
+                    Self::unindex_replaced_fragments(
+                        &mut final_indices,
+                        &current_manifest.fragments,
+                        fragments,
+                    );
+                }
+
                 // Some fields that have indices may have been removed, so we should
                 // remove those indices as well.
                 Self::retain_relevant_indices(&mut final_indices, &schema, &final_fragments)
@@ -1293,9 +1325,10 @@ impl Transaction {
                             && file.file_major_version == new_file.file_major_version
                             && file.file_minor_version == new_file.file_minor_version
                         {
-                            // assign the new file path / size to the fragment
+                            // assign the new file path / size / stats to the fragment
                             file.path = new_file.path.clone();
                             file.file_size_bytes = new_file.file_size_bytes.clone();
+                            file.column_stats = new_file.column_stats.clone();
                         }
                         columns_covered.extend(file.fields.iter());
                     }
@@ -1496,6 +1529,57 @@ impl Transaction {
         });
     }
 
+    /// Remove the fragments whose data files for an indexed field differ
+    /// between `old_fragments` and `new_fragments` from the fragment bitmap of
+    /// the index.  Indices that do not record the fragments they cover are
+    /// removed instead.
+    fn unindex_replaced_fragments(
+        indices: &mut Vec<Index>,
+        old_fragments: &[Fragment],
+        new_fragments: &[Fragment],
+    ) {
+        let old_fragments = old_fragments
+            .iter()
+            .map(|f| (f.id, f))
+            .collect::<HashMap<_, _>>();
+        let field_files = |fragment: &Fragment, field_id: i32| {
+            fragment
+                .files
+                .iter()
+                .filter(|file| file.fields.contains(&field_id))
+                .map(|file| file.path.clone())
+                .collect::<Vec<_>>()
+        };
+        indices.retain_mut(|index| {
+            if index.name == FRAG_REUSE_INDEX_NAME {
+                return true;
+            }
+            let replaced = new_fragments
+                .iter()
+                .filter(|new| {
+                    old_fragments.get(&new.id).is_some_and(|old| {
+                        index.fields.iter().any(|field_id| {
+                            field_files(old, *field_id) != field_files(new, *field_id)
+                        })
+                    })
+                })
+                .map(|f| f.id as u32)
+                .collect::<Vec<_>>();
+            if replaced.is_empty() {
+                return true;
+            }
+            match &mut index.fragment_bitmap {
+                Some(bitmap) => {
+                    for fragment_id in replaced {
+                        bitmap.remove(fragment_id);
+                    }
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
     fn recalculate_fragment_bitmap(
         old: &RoaringBitmap,
         groups: &[RewriteGroup],
@@ -1771,6 +1855,7 @@ impl TryFrom<pb::Transaction> for Transaction {
                 updated_fragments,
                 new_fragments,
                 fields_modified,
+                replaced_row_ids,
             })) => Operation::Update {
                 removed_fragment_ids,
                 updated_fragments: updated_fragments
@@ -1782,6 +1867,10 @@ impl TryFrom<pb::Transaction> for Transaction {
                     .map(Fragment::try_from)
                     .collect::<Result<Vec<_>>>()?,
                 fields_modified,
+                replaced_row_ids: match replaced_row_ids.len() {
+                    0 => None,
+                    _ => Some(read_row_ids(&replaced_row_ids)?),
+                },
             },
             Some(pb::transaction::Operation::Project(pb::transaction::Project { schema })) => {
                 Operation::Project {
@@ -2011,6 +2100,7 @@ impl From<&Transaction> for pb::Transaction {
                 updated_fragments,
                 new_fragments,
                 fields_modified,
+                replaced_row_ids,
             } => pb::transaction::Operation::Update(pb::transaction::Update {
                 removed_fragment_ids: removed_fragment_ids.clone(),
                 updated_fragments: updated_fragments
@@ -2019,6 +2109,10 @@ impl From<&Transaction> for pb::Transaction {
                     .collect(),
                 new_fragments: new_fragments.iter().map(pb::DataFragment::from).collect(),
                 fields_modified: fields_modified.clone(),
+                replaced_row_ids: replaced_row_ids
+                    .as_ref()
+                    .map(write_row_ids)
+                    .unwrap_or_default(),
             }),
             Operation::Project { schema } => {
                 pb::transaction::Operation::Project(pb::transaction::Project {
@@ -2205,6 +2299,64 @@ fn schema_fragments_valid(schema: &Schema, fragments: &[Fragment]) -> Result<()>
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_unindex_replaced_fragments() {
+        let version = LanceFileVersion::V2_0;
+        let old_fragments = vec![
+            Fragment::new(0)
+                .with_file("a.lance", vec![0], vec![0], &version, None)
+                .with_file("b.lance", vec![1], vec![0], &version, None),
+            Fragment::new(1).with_file("c.lance", vec![0, 1], vec![0, 1], &version, None),
+        ];
+        // The data of field 1 in fragment 0 was replaced and fragment 2 was added.
+        let new_fragments = vec![
+            Fragment::new(0)
+                .with_file("a.lance", vec![0], vec![0], &version, None)
+                .with_file("b2.lance", vec![1], vec![0], &version, None),
+            old_fragments[1].clone(),
+            Fragment::new(2).with_file("d.lance", vec![0, 1], vec![0, 1], &version, None),
+        ];
+        let index = |name: &str, field_id: i32, fragment_bitmap: Option<RoaringBitmap>| Index {
+            uuid: Uuid::new_v4(),
+            fields: vec![field_id],
+            name: name.to_string(),
+            dataset_version: 1,
+            fragment_bitmap,
+            index_details: None,
+            index_version: 0,
+        };
+        let mut indices = vec![
+            index("a_idx", 0, Some(RoaringBitmap::from_iter([0, 1]))),
+            index("b_idx", 1, Some(RoaringBitmap::from_iter([0, 1]))),
+            index("b_idx_0", 1, Some(RoaringBitmap::from_iter([0]))),
+            index("b_idx_legacy", 1, None),
+        ];
+
+        Transaction::unindex_replaced_fragments(&mut indices, &old_fragments, &new_fragments);
+        let bitmaps = indices
+            .iter()
+            .map(|index| {
+                (
+                    index.name.as_str(),
+                    index
+                        .fragment_bitmap
+                        .as_ref()
+                        .unwrap()
+                        .iter()
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bitmaps,
+            vec![
+                ("a_idx", vec![0, 1]),
+                ("b_idx", vec![1]),
+                ("b_idx_0", vec![])
+            ]
+        );
+    }
+
     #[test]
     fn test_rewrite_fragments() {
         let existing_fragments: Vec<Fragment> = (0..10).map(Fragment::new).collect();
@@ -4,13 +4,17 @@
 use std::num::NonZero;
 use std::sync::Arc;
 
-use arrow_array::RecordBatch;
+use arrow_array::cast::AsArray;
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_buffer::NullBuffer;
+use arrow_schema::{DataType, Field as ArrowField};
 use chrono::TimeDelta;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use futures::{Stream, StreamExt, TryStreamExt};
+use lance_arrow::sparse::{is_sparse_vector_field, SparseVectorArray};
 use lance_core::datatypes::{
-    NullabilityComparison, OnMissing, OnTypeMismatch, SchemaCompareOptions, StorageClass,
+    Field, NullabilityComparison, OnMissing, OnTypeMismatch, SchemaCompareOptions, StorageClass,
 };
 use lance_core::error::LanceOptionExt;
 use lance_core::utils::tracing::{AUDIT_MODE_CREATE, AUDIT_TYPE_DATA, TRACE_FILE_AUDIT};
@@ -35,11 +39,16 @@ use crate::session::Session;
 use crate::Dataset;
 
 use super::blob::BlobStreamExt;
+use super::partition::PartitionSpec;
 use super::progress::{NoopFragmentWriteProgress, WriteFragmentProgress};
+use super::statistics::ColumnStatsCollector;
 use super::transaction::Transaction;
 use super::DATA_DIR;
 
 mod commit;
+mod computed;
+pub mod defaults;
+pub mod generated;
 mod insert;
 pub mod merge_insert;
 pub mod update;
@@ -200,6 +209,13 @@ pub struct WriteParams {
     /// to set lance.auto_cleanup.interval and lance.auto_cleanup.older_than.
     /// Both parameters must be set to invoke autocleaning.
     pub auto_cleanup: Option<AutoCleanupParams>,
+
+    /// If Some and this is a new dataset (or an overwrite), the dataset will be
+    /// partitioned according to this spec.  Rows are routed into per-partition
+    /// fragments, and the spec is stored in the manifest config under
+    /// [`super::partition::PARTITION_SPEC_CONFIG_KEY`].  Appends always use the
+    /// spec of the existing dataset.
+    pub partition_spec: Option<PartitionSpec>,
 }
 
 impl Default for WriteParams {
@@ -219,6 +235,7 @@ impl Default for WriteParams {
             enable_v2_manifest_paths: false,
             session: None,
             auto_cleanup: Some(AutoCleanupParams::default()),
+            partition_spec: None,
         }
     }
 }
@@ -265,6 +282,128 @@ pub async fn write_fragments(
         .await
 }
 
+/// Find the first row in which `array` or, for structs, one of its children
+/// is null even though its field is not nullable.
+///
+/// Nulls of children are only reported for rows in which the parent is valid.
+fn find_invalid_null(
+    array: &ArrayRef,
+    field: &Field,
+    valid_parent: Option<&NullBuffer>,
+) -> Option<(String, usize)> {
+    let nulls = array.logical_nulls();
+    if let (false, Some(nulls)) = (field.nullable, &nulls) {
+        let row = (0..array.len())
+            .find(|&row| nulls.is_null(row) && valid_parent.is_none_or(|p| p.is_valid(row)));
+        if let Some(row) = row {
+            return Some((field.name.clone(), row));
+        }
+    }
+    if let Some(struct_array) = array.as_struct_opt() {
+        let valid = NullBuffer::union(valid_parent, nulls.as_ref());
+        for child_field in &field.children {
+            let Some(child) = struct_array.column_by_name(&child_field.name) else {
+                continue;
+            };
+            if let Some((name, row)) = find_invalid_null(child, child_field, valid.as_ref()) {
+                return Some((format!("{}.{}", field.name, name), row));
+            }
+        }
+    }
+    None
+}
+
+/// Check that `batch` has no nulls in the non-nullable fields of `schema`.
+///
+/// `offset` is the number of rows written before the batch, so the error
+/// names the row in the written data.
+fn check_nullability(schema: &Schema, batch: &RecordBatch, offset: usize) -> Result<()> {
+    for field in &schema.fields {
+        let Some(column) = batch.column_by_name(&field.name) else {
+            continue;
+        };
+        if let Some((name, row)) = find_invalid_null(column, field, None) {
+            return Err(Error::invalid_input(
+                format!(
+                    "The field `{}` contained null values even though the field is marked non-null in the schema: row {} is null",
+                    name,
+                    offset + row
+                ),
+                location!(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check that the sparse vectors of `batch` in the sparse vector fields of
+/// `schema` have strictly increasing indices.
+///
+/// `offset` is the number of rows written before the batch.
+fn check_sparse_vectors(schema: &Schema, batch: &RecordBatch, offset: usize) -> Result<()> {
+    for field in &schema.fields {
+        let Some(column) = batch.column_by_name(&field.name) else {
+            continue;
+        };
+        if !is_sparse_vector_field(&ArrowField::from(field)) {
+            continue;
+        }
+        if let Err(e) = SparseVectorArray::try_new(column.as_ref()) {
+            return Err(Error::invalid_input(
+                format!(
+                    "The field `{}` contains an invalid sparse vector in the rows starting at row {}: {}",
+                    field.name, offset, e
+                ),
+                location!(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Wrap `stream` so that every batch is checked to have no nulls in the
+/// non-nullable fields of `schema`, and only sorted, unique indices in its
+/// sparse vectors.
+///
+/// Only writes of user data are checked. Rewrites of existing data, like
+/// compaction, must keep working on datasets that already contain nulls in
+/// non-nullable fields. Updates only check the columns they change.
+pub(crate) fn with_write_checks(
+    schema: &Schema,
+    stream: SendableRecordBatchStream,
+) -> SendableRecordBatchStream {
+    let schema = schema.clone();
+    let output_schema = stream.schema();
+    let mut num_rows_checked = 0;
+    let stream = stream.map(move |batch| {
+        let batch = batch?;
+        check_nullability(&schema, &batch, num_rows_checked)?;
+        check_sparse_vectors(&schema, &batch, num_rows_checked)?;
+        num_rows_checked += batch.num_rows();
+        Ok(batch)
+    });
+    Box::pin(RecordBatchStreamAdapter::new(output_schema, stream))
+}
+
+/// Check that `data_schema` has all the non-nullable fields of the dataset,
+/// because rows written without them would be null.
+fn check_non_nullable_fields_present(dataset_schema: &Schema, data_schema: &Schema) -> Result<()> {
+    if let Some(field) = dataset_schema
+        .fields
+        .iter()
+        .find(|f| !f.nullable && data_schema.field(&f.name).is_none())
+    {
+        return Err(Error::SchemaMismatch {
+            difference: format!(
+                "The field `{}` is missing from the data even though the field is marked non-null in the schema and has no default",
+                field.name
+            ),
+            location: location!(),
+        });
+    }
+    Ok(())
+}
+
 pub async fn do_write_fragments(
     object_store: Arc<ObjectStore>,
     base_dir: &Path,
@@ -360,6 +499,7 @@ pub async fn write_fragments_internal(
     let (schema, storage_version) = if let Some(dataset) = dataset {
         match params.mode {
             WriteMode::Append | WriteMode::Create => {
+                check_non_nullable_fields_present(dataset.schema(), &schema)?;
                 // Append mode, so we need to check compatibility
                 schema.check_compatible(
                     dataset.schema(),
@@ -433,6 +573,24 @@ pub async fn write_fragments_internal(
         ));
     }
 
+    // Union fields only have an encoding in the 2.0 file format
+    if storage_version.resolve() != LanceFileVersion::V2_0 {
+        if let Some(field) = schema
+            .fields_pre_order()
+            .find(|field| matches!(field.data_type(), DataType::Union(_, _)))
+        {
+            return Err(Error::NotSupported {
+                source: format!(
+                    "Union field `{}` cannot be written with the {} file format, union fields are only supported in the 2.0 file format",
+                    field.name,
+                    storage_version.resolve()
+                )
+                .into(),
+                location: location!(),
+            });
+        }
+    }
+
     let frag_schema = schema.retain_storage_class(StorageClass::Default);
     let fragments_fut = do_write_fragments(
         object_store.clone(),
@@ -543,6 +701,31 @@ impl GenericWriter for V2WriterAdapter {
     }
 }
 
+/// Collects the column statistics of the data written to `inner` and records
+/// them in the finished [`DataFile`].
+struct StatsCollectingWriter {
+    inner: Box<dyn GenericWriter>,
+    stats: ColumnStatsCollector,
+}
+
+#[async_trait::async_trait]
+impl GenericWriter for StatsCollectingWriter {
+    async fn write(&mut self, batches: &[RecordBatch]) -> Result<()> {
+        for batch in batches {
+            self.stats.update(batch)?;
+        }
+        self.inner.write(batches).await
+    }
+    async fn tell(&mut self) -> Result<u64> {
+        self.inner.tell().await
+    }
+    async fn finish(&mut self) -> Result<(u32, DataFile)> {
+        let (num_rows, mut data_file) = self.inner.finish().await?;
+        data_file.column_stats = self.stats.finish();
+        Ok((num_rows, data_file))
+    }
+}
+
 pub async fn open_writer(
     object_store: &ObjectStore,
     schema: &Schema,
@@ -580,7 +763,10 @@ pub async fn open_writer(
         };
         Box::new(writer_adapter) as Box<dyn GenericWriter>
     };
-    Ok(writer)
+    Ok(Box::new(StatsCollectingWriter {
+        inner: writer,
+        stats: ColumnStatsCollector::new(schema),
+    }))
 }
 
 /// Creates new file writers for a given dataset.
@@ -764,14 +950,21 @@ impl Iterator for SpillStreamIter {
 mod tests {
     use super::*;
 
-    use arrow_array::{Int32Array, RecordBatchReader, StructArray};
-    use arrow_schema::{DataType, Field as ArrowField, Fields, Schema as ArrowSchema};
+    use arrow_array::{
+        Int32Array, RecordBatchIterator, RecordBatchReader, StringArray, StructArray, UnionArray,
+    };
+    use arrow_schema::{
+        Field as ArrowField, Fields, Schema as ArrowSchema, UnionFields, UnionMode,
+    };
     use datafusion::{error::DataFusionError, physical_plan::stream::RecordBatchStreamAdapter};
     use futures::TryStreamExt;
     use lance_datagen::{array, gen, BatchCount, RowCount};
     use lance_file::reader::FileReader;
     use lance_io::traits::Reader;
 
+    use crate::dataset::optimize::{compact_files, CompactionOptions};
+    use crate::dataset::transaction::Operation;
+
     #[tokio::test]
     async fn test_chunking_large_batches() {
         // Create a stream of 3 batches of 10 rows
@@ -816,6 +1009,211 @@ mod tests {
         assert_eq!(chunks[3][1].num_rows(), 2);
     }
 
+    #[test]
+    fn test_check_nullability() {
+        let schema = ArrowSchema::new(vec![
+            ArrowField::new("a", DataType::Int32, false),
+            ArrowField::new(
+                "s",
+                DataType::Struct(vec![ArrowField::new("x", DataType::Int32, false)].into()),
+                true,
+            ),
+        ]);
+        let lance_schema = Schema::try_from(&schema).unwrap();
+        // Arrow doesn't allow building arrays with invalid nulls, so the data
+        // claims to be nullable.
+        let schema = ArrowSchema::new(vec![
+            ArrowField::new("a", DataType::Int32, true),
+            ArrowField::new(
+                "s",
+                DataType::Struct(vec![ArrowField::new("x", DataType::Int32, true)].into()),
+                true,
+            ),
+        ]);
+        let struct_array = |values: Vec<Option<i32>>, valid: Vec<bool>| {
+            Arc::new(StructArray::new(
+                vec![ArrowField::new("x", DataType::Int32, true)].into(),
+                vec![Arc::new(Int32Array::from(values))],
+                Some(valid.into()),
+            )) as ArrayRef
+        };
+        // Children may be null where their parent is null
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                struct_array(vec![Some(1), None], vec![true, false]),
+            ],
+        )
+        .unwrap();
+        check_nullability(&lance_schema, &batch, 0).unwrap();
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                struct_array(vec![Some(1), None, None], vec![true, false, true]),
+            ],
+        )
+        .unwrap();
+        let err = check_nullability(&lance_schema, &batch, 10).unwrap_err();
+        assert!(err.to_string().contains("`s.x`"), "{}", err);
+        assert!(err.to_string().contains("row 12"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_compact_existing_nulls_in_non_nullable_field() {
+        // Datasets written before nullability was enforced may have nulls in
+        // non-nullable fields, and must still be rewritable.
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "a",
+            DataType::Int32,
+            true,
+        )]));
+        let reader = |values: Vec<Option<i32>>| {
+            let batch =
+                RecordBatch::try_new(schema.clone(), vec![Arc::new(Int32Array::from(values))])
+                    .unwrap();
+            RecordBatchIterator::new(vec![Ok(batch)], schema.clone())
+        };
+        let mut dataset = Dataset::write(reader(vec![Some(1), None]), test_uri, None)
+            .await
+            .unwrap();
+        dataset
+            .append(reader(vec![None, Some(4)]), None)
+            .await
+            .unwrap();
+
+        let mut non_nullable = dataset.schema().clone();
+        non_nullable.fields[0].nullable = false;
+        let transaction = Transaction::new(
+            dataset.manifest.version,
+            Operation::Project {
+                schema: non_nullable,
+            },
+            /*blobs_op=*/ None,
+            None,
+        );
+        let mut dataset = CommitBuilder::new(Arc::new(dataset))
+            .execute(transaction)
+            .await
+            .unwrap();
+
+        compact_files(&mut dataset, CompactionOptions::default(), None)
+            .await
+            .unwrap();
+        assert_eq!(dataset.get_fragments().len(), 1);
+        assert_eq!(dataset.count_rows(None).await.unwrap(), 4);
+
+        // New nulls are still rejected
+        let err = dataset
+            .append(reader(vec![Some(5), None]), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("`a`"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_write_unsorted_sparse_vectors() {
+        use arrow_array::{Float32Array, ListArray, UInt32Array};
+        use arrow_buffer::OffsetBuffer;
+        use lance_arrow::sparse::{sparse_vector_data_type, sparse_vector_field};
+
+        let schema = Arc::new(ArrowSchema::new(vec![sparse_vector_field("vec", true)]));
+        let reader = |indices: Vec<u32>| {
+            let DataType::List(item) = sparse_vector_data_type() else {
+                unreachable!()
+            };
+            let DataType::Struct(fields) = item.data_type().clone() else {
+                unreachable!()
+            };
+            let values = Float32Array::from(vec![1.0; indices.len()]);
+            let entries = StructArray::new(
+                fields,
+                vec![
+                    Arc::new(UInt32Array::from(indices)) as ArrayRef,
+                    Arc::new(values) as ArrayRef,
+                ],
+                None,
+            );
+            let array = ListArray::new(
+                item,
+                OffsetBuffer::from_lengths([2, 2]),
+                Arc::new(entries),
+                None,
+            );
+            let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(array)]).unwrap();
+            RecordBatchIterator::new(vec![Ok(batch)], schema.clone())
+        };
+
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = Dataset::write(reader(vec![0, 1, 2, 3]), test_uri, None)
+            .await
+            .unwrap();
+        for indices in [vec![0, 1, 3, 2], vec![0, 1, 2, 2]] {
+            let err = dataset.append(reader(indices), None).await.unwrap_err();
+            assert!(err.to_string().contains("strictly increasing"), "{}", err);
+        }
+        assert_eq!(dataset.count_rows(None).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_write_union() {
+        let union_fields = UnionFields::new(
+            vec![0, 1],
+            vec![
+                ArrowField::new("int", DataType::Int32, true),
+                ArrowField::new("str", DataType::Utf8, true),
+            ],
+        );
+        let schema = Arc::new(ArrowSchema::new(vec![ArrowField::new(
+            "u",
+            DataType::Union(union_fields.clone(), UnionMode::Sparse),
+            false,
+        )]));
+        let union = UnionArray::try_new(
+            union_fields,
+            vec![0, 1, 0].into(),
+            None,
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), None, Some(3)])) as ArrayRef,
+                Arc::new(StringArray::from(vec![None, Some("b"), None])),
+            ],
+        )
+        .unwrap();
+        let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(union)]).unwrap();
+
+        // Union fields are rejected before anything is written in 2.1 files
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let reader = RecordBatchIterator::new(vec![Ok(batch.clone())], schema.clone());
+        let err = Dataset::write(
+            reader,
+            test_uri,
+            Some(WriteParams::with_storage_version(LanceFileVersion::V2_1)),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::NotSupported { .. }), "{}", err);
+        assert!(err.to_string().contains("Union field `u`"), "{}", err);
+        assert!(Dataset::open(test_uri).await.is_err());
+
+        // and written in 2.0 files
+        let reader = RecordBatchIterator::new(vec![Ok(batch.clone())], schema.clone());
+        let dataset = Dataset::write(
+            reader,
+            test_uri,
+            Some(WriteParams::with_storage_version(LanceFileVersion::V2_0)),
+        )
+        .await
+        .unwrap();
+        let actual = dataset.scan().try_into_batch().await.unwrap();
+        assert_eq!(actual, batch);
+    }
+
     #[tokio::test]
     async fn test_chunking_small_batches() {
         // Create a stream of 10 batches of 3 rows
@@ -16,7 +16,7 @@ use crate::{
     dataset::{
         builder::DatasetBuilder,
         commit_detached_transaction, commit_new_dataset, commit_transaction,
-        refs::Tags,
+        refs::{Branches, Tags},
         transaction::{Operation, Transaction},
         ManifestWriteConfig, ReadParams,
     },
@@ -323,6 +323,11 @@ impl<'a> CommitBuilder<'a> {
             commit_handler.clone(),
             base_path.clone(),
         );
+        let branches = Branches::new(
+            object_store.clone(),
+            commit_handler.clone(),
+            base_path.clone(),
+        );
 
         match &self.dest {
             WriteDestination::Dataset(dataset) => Ok(Dataset {
@@ -340,6 +345,8 @@ impl<'a> CommitBuilder<'a> {
                 session,
                 commit_handler,
                 tags,
+                branches,
+                branch: None,
             }),
         }
     }
@@ -442,10 +449,12 @@ mod tests {
                 file_major_version: 2,
                 file_minor_version: 0,
                 file_size_bytes: CachedFileSize::new(100),
+                column_stats: vec![],
             }],
             deletion_file: None,
             row_id_meta: None,
             physical_rows: Some(10),
+            partition: vec![],
         }
     }
 
@@ -725,6 +734,7 @@ mod tests {
                 new_fragments: vec![],
                 removed_fragment_ids: vec![],
                 fields_modified: vec![],
+                replaced_row_ids: None,
             },
             read_version: 1,
             blobs_op: None,
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Columns computed by SQL expressions while writing.
+//!
+//! Shared by default values, which fill the columns missing from the data, and
+//! generated columns, which are computed from the other columns of the row.
+
+use std::sync::Arc;
+
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{PhysicalExpr, SendableRecordBatchStream};
+use futures::StreamExt;
+use lance_core::datatypes::{Field, Schema};
+
+use crate::Result;
+
+/// An expression computing the values of a column.
+pub struct ComputedColumn {
+    expr: Arc<dyn PhysicalExpr>,
+    data_type: DataType,
+    cast_options: CastOptions<'static>,
+}
+
+impl ComputedColumn {
+    /// Values of `expr` that are not of `data_type` are cast with `cast_options`.
+    pub fn new(
+        expr: Arc<dyn PhysicalExpr>,
+        data_type: DataType,
+        cast_options: CastOptions<'static>,
+    ) -> Self {
+        Self {
+            expr,
+            data_type,
+            cast_options,
+        }
+    }
+
+    pub fn evaluate(&self, batch: &RecordBatch) -> Result<ArrayRef> {
+        let values = self.expr.evaluate(batch)?.into_array(batch.num_rows())?;
+        if values.data_type() == &self.data_type {
+            Ok(values)
+        } else {
+            Ok(cast_with_options(
+                &values,
+                &self.data_type,
+                &self.cast_options,
+            )?)
+        }
+    }
+}
+
+enum OutputColumn {
+    Input(usize),
+    Computed(ComputedColumn),
+}
+
+/// Computes columns of a schema over incoming record batches.
+pub struct ComputedColumns {
+    output_schema: SchemaRef,
+    columns: Vec<OutputColumn>,
+}
+
+impl ComputedColumns {
+    /// Plan the columns of `schema` for data with `input_schema`.
+    ///
+    /// `compute` is called with every field of `schema` and the index of the
+    /// field in the input, if any.  It returns how to compute the field, or
+    /// `None` to take the field from the input.
+    ///
+    /// The output has the columns of `schema` that are in the input or computed,
+    /// in the order of `schema`, followed by the input columns not in `schema`.
+    pub fn try_new(
+        schema: &Schema,
+        input_schema: &ArrowSchema,
+        mut compute: impl FnMut(&Field, Option<usize>) -> Result<Option<ComputedColumn>>,
+    ) -> Result<Self> {
+        let mut fields = Vec::with_capacity(schema.fields.len());
+        let mut columns = Vec::with_capacity(schema.fields.len());
+        for field in &schema.fields {
+            let input_column = input_schema.index_of(&field.name).ok();
+            if let Some(computed) = compute(field, input_column)? {
+                fields.push(Arc::new(ArrowField::from(field)));
+                columns.push(OutputColumn::Computed(computed));
+            } else if let Some(index) = input_column {
+                fields.push(input_schema.fields[index].clone());
+                columns.push(OutputColumn::Input(index));
+            }
+        }
+        for (index, field) in input_schema.fields.iter().enumerate() {
+            if schema.field(field.name()).is_none() {
+                fields.push(field.clone());
+                columns.push(OutputColumn::Input(index));
+            }
+        }
+
+        Ok(Self {
+            output_schema: Arc::new(ArrowSchema::new_with_metadata(
+                fields,
+                input_schema.metadata().clone(),
+            )),
+            columns,
+        })
+    }
+
+    pub fn apply(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| match column {
+                OutputColumn::Input(index) => Ok(batch.column(*index).clone()),
+                OutputColumn::Computed(computed) => computed.evaluate(batch),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RecordBatch::try_new(self.output_schema.clone(), columns)?)
+    }
+
+    /// Wrap `stream` so that the columns are computed for every batch.
+    pub fn apply_to_stream(self, stream: SendableRecordBatchStream) -> SendableRecordBatchStream {
+        let output_schema = self.output_schema.clone();
+        let stream = stream.map(move |batch| Ok(self.apply(&batch?)?));
+        Box::pin(RecordBatchStreamAdapter::new(output_schema, stream))
+    }
+}
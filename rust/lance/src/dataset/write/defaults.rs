@@ -0,0 +1,339 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Default values of columns.
+//!
+//! A top-level field can have a default, a SQL expression stored in the field
+//! metadata under [`lance_core::datatypes::LANCE_DEFAULT_EXPRESSION_META_KEY`].
+//! Rows appended without the field get the value of the expression.  The
+//! expression cannot read other columns, but it is evaluated for every batch,
+//! so functions like `now()` give the time of the write.
+
+use std::sync::Arc;
+
+use arrow::compute::CastOptions;
+use arrow_array::{RecordBatch, RecordBatchOptions};
+use arrow_schema::{DataType, Schema as ArrowSchema};
+use datafusion::physical_plan::{PhysicalExpr, SendableRecordBatchStream};
+use lance_core::datatypes::{Field, Schema};
+use snafu::location;
+
+use super::computed::{ComputedColumn, ComputedColumns};
+use crate::io::exec::Planner;
+use crate::{Error, Result};
+
+/// Plan the default expression of `field`, if it has one.
+fn default_expr(field: &Field) -> Result<Option<Arc<dyn PhysicalExpr>>> {
+    let Some(expression) = field.default_expression() else {
+        return Ok(None);
+    };
+    let invalid = |e: Error| {
+        Error::invalid_input(
+            format!(
+                "Invalid default expression for column '{}': {}",
+                field.name, e
+            ),
+            location!(),
+        )
+    };
+    let planner = Planner::new(Arc::new(ArrowSchema::empty()));
+    let expr = planner.parse_expr(expression).map_err(invalid)?;
+    let expr = planner.optimize_expr(expr).map_err(invalid)?;
+    Ok(Some(planner.create_physical_expr(&expr).map_err(invalid)?))
+}
+
+fn computed_default(expr: Arc<dyn PhysicalExpr>, data_type: DataType) -> ComputedColumn {
+    // Fail instead of writing nulls if the value cannot be cast
+    ComputedColumn::new(
+        expr,
+        data_type,
+        CastOptions {
+            safe: false,
+            ..Default::default()
+        },
+    )
+}
+
+/// Check that the default expression of `field`, if any, can be evaluated to
+/// a value of the type of the field.
+pub fn validate_default_expression(field: &Field) -> Result<()> {
+    if let Some(expr) = default_expr(field)? {
+        let batch = RecordBatch::try_new_with_options(
+            Arc::new(ArrowSchema::empty()),
+            vec![],
+            &RecordBatchOptions::new().with_row_count(Some(1)),
+        )?;
+        computed_default(expr, field.data_type())
+            .evaluate(&batch)
+            .map_err(|e| {
+                Error::invalid_input(
+                    format!(
+                        "Invalid default expression for column '{}': {}",
+                        field.name, e
+                    ),
+                    location!(),
+                )
+            })?;
+    }
+    Ok(())
+}
+
+/// Whether some fields of `schema` with a default are missing from `data_schema`.
+pub fn has_missing_defaults(schema: &Schema, data_schema: &Schema) -> bool {
+    schema
+        .fields
+        .iter()
+        .any(|f| f.default_expression().is_some() && data_schema.field(&f.name).is_none())
+}
+
+/// Wrap `stream` so that the fields of `schema` that are missing from the data
+/// and have a default are filled with their default values.
+///
+/// See [`ComputedColumns::try_new`] for the order of the output columns.
+pub fn with_default_values(
+    schema: &Schema,
+    stream: SendableRecordBatchStream,
+) -> Result<SendableRecordBatchStream> {
+    let input_schema = stream.schema();
+    if schema
+        .fields
+        .iter()
+        .all(|f| f.default_expression().is_none() || input_schema.field_with_name(&f.name).is_ok())
+    {
+        return Ok(stream);
+    }
+
+    // Default expressions read no columns, so they can be evaluated over the
+    // input batches.
+    let defaults = ComputedColumns::try_new(schema, input_schema.as_ref(), |field, input| {
+        if input.is_some() {
+            return Ok(None);
+        }
+        Ok(default_expr(field)?.map(|expr| computed_default(expr, field.data_type())))
+    })?;
+    Ok(defaults.apply_to_stream(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{
+        Array, Float64Array, Int64Array, RecordBatchIterator, RecordBatchReader, StringArray,
+    };
+    use arrow_schema::Field as ArrowField;
+    use futures::TryStreamExt;
+    use lance_core::datatypes::LANCE_DEFAULT_EXPRESSION_META_KEY;
+
+    use crate::dataset::{ColumnAlteration, MergeInsertBuilder, WhenMatched, WhenNotMatched};
+    use crate::Dataset;
+
+    fn reader(batches: Vec<RecordBatch>) -> impl RecordBatchReader + Send + 'static {
+        let schema = batches[0].schema();
+        RecordBatchIterator::new(batches.into_iter().map(Ok), schema)
+    }
+
+    fn ids(ids: &[i64]) -> RecordBatch {
+        RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![ArrowField::new(
+                "id",
+                DataType::Int64,
+                false,
+            )])),
+            vec![Arc::new(Int64Array::from(ids.to_vec()))],
+        )
+        .unwrap()
+    }
+
+    fn rows(ids: &[i64], categories: &[Option<&str>], scores: &[f64]) -> RecordBatch {
+        RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![
+                ArrowField::new("id", DataType::Int64, false),
+                ArrowField::new("category", DataType::Utf8, true),
+                ArrowField::new("score", DataType::Float64, true),
+            ])),
+            vec![
+                Arc::new(Int64Array::from(ids.to_vec())),
+                Arc::new(StringArray::from(categories.to_vec())),
+                Arc::new(Float64Array::from(scores.to_vec())),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn make_dataset(test_uri: &str) -> Dataset {
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("id", DataType::Int64, false),
+            ArrowField::new("category", DataType::Utf8, false).with_metadata(
+                [(
+                    LANCE_DEFAULT_EXPRESSION_META_KEY.to_string(),
+                    "'unknown'".to_string(),
+                )]
+                .into(),
+            ),
+            ArrowField::new("score", DataType::Float64, true),
+        ]));
+        let batch = rows(&[1, 2], &[Some("a"), Some("b")], &[0.5, 0.25]);
+        let batch = RecordBatch::try_new(schema, batch.columns().to_vec()).unwrap();
+        let mut dataset = Dataset::write(reader(vec![batch]), test_uri, None)
+            .await
+            .unwrap();
+        dataset
+            .alter_columns(&[
+                ColumnAlteration::new("score".into()).set_default(Some("1 + 0.5".into()))
+            ])
+            .await
+            .unwrap();
+        dataset
+    }
+
+    async fn scan(dataset: &Dataset) -> Vec<(i64, String, Option<f64>)> {
+        let batches = dataset
+            .scan()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let mut values = Vec::new();
+        for batch in batches {
+            let ids = batch["id"].as_any().downcast_ref::<Int64Array>().unwrap();
+            let categories = batch["category"]
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap();
+            let scores = batch["score"]
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap();
+            for i in 0..batch.num_rows() {
+                values.push((
+                    ids.value(i),
+                    categories.value(i).to_string(),
+                    scores.is_valid(i).then(|| scores.value(i)),
+                ));
+            }
+        }
+        values.sort_by_key(|(id, _, _)| *id);
+        values
+    }
+
+    #[tokio::test]
+    async fn test_append_with_defaults() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = make_dataset(test_uri).await;
+        assert_eq!(
+            dataset
+                .schema()
+                .field("score")
+                .unwrap()
+                .default_expression(),
+            Some("1 + 0.5")
+        );
+
+        dataset
+            .append(reader(vec![ids(&[3, 4])]), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            scan(&dataset).await,
+            vec![
+                (1, "a".to_string(), Some(0.5)),
+                (2, "b".to_string(), Some(0.25)),
+                (3, "unknown".to_string(), Some(1.5)),
+                (4, "unknown".to_string(), Some(1.5)),
+            ]
+        );
+
+        // Without a default, a non-nullable column must be written
+        dataset
+            .alter_columns(&[ColumnAlteration::new("category".into()).set_default(None)])
+            .await
+            .unwrap();
+        let err = dataset
+            .append(reader(vec![ids(&[5])]), None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, Error::SchemaMismatch { difference, .. } if difference.contains("`category`")),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_nulls_to_non_nullable() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = make_dataset(test_uri).await;
+
+        let batches = vec![
+            rows(&[3, 4], &[Some("c"), Some("d")], &[1.0, 2.0]),
+            rows(&[5, 6], &[Some("e"), None], &[3.0, 4.0]),
+        ];
+        let err = dataset.append(reader(batches), None).await.unwrap_err();
+        assert!(
+            matches!(&err, Error::InvalidInput { source, .. }
+                if source.to_string().contains("`category`") && source.to_string().contains("row 3")),
+            "{}",
+            err
+        );
+        assert_eq!(scan(&dataset).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_merge_insert_with_defaults() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = Arc::new(make_dataset(test_uri).await);
+
+        let source = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![
+                ArrowField::new("id", DataType::Int64, false),
+                ArrowField::new("score", DataType::Float64, true),
+            ])),
+            vec![
+                Arc::new(Int64Array::from(vec![2, 3])),
+                Arc::new(Float64Array::from(vec![2.0, 3.0])),
+            ],
+        )
+        .unwrap();
+        let (dataset, _) = MergeInsertBuilder::try_new(dataset, vec!["id".to_string()])
+            .unwrap()
+            .when_matched(WhenMatched::UpdateAll)
+            .when_not_matched(WhenNotMatched::InsertAll)
+            .try_build()
+            .unwrap()
+            .execute_reader(Box::new(reader(vec![source])) as Box<dyn RecordBatchReader + Send>)
+            .await
+            .unwrap();
+        // Matched rows keep their values, new rows get the defaults
+        assert_eq!(
+            scan(&dataset).await,
+            vec![
+                (1, "a".to_string(), Some(0.5)),
+                (2, "b".to_string(), Some(2.0)),
+                (3, "unknown".to_string(), Some(3.0)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_default() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = make_dataset(test_uri).await;
+
+        for expression in ["id + 1", "'abc'", "1 +"] {
+            let err = dataset
+                .alter_columns(&[
+                    ColumnAlteration::new("score".into()).set_default(Some(expression.into()))
+                ])
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+        }
+    }
+}
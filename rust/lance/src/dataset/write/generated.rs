@@ -0,0 +1,460 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Generated columns.
+//!
+//! A generated column is a top-level column whose values are computed from the
+//! other columns of the same row by a SQL expression.  The expression is stored
+//! in the field metadata under
+//! [`lance_core::datatypes::LANCE_GENERATED_EXPRESSION_META_KEY`] and is
+//! evaluated whenever rows are written, so the values never go stale.  Values
+//! provided for a generated column by the writer are replaced.
+
+use std::sync::Arc;
+
+use arrow::compute::CastOptions;
+use arrow_schema::Schema as ArrowSchema;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use lance_core::datatypes::Schema;
+use snafu::location;
+
+use super::computed::{ComputedColumn, ComputedColumns};
+use crate::io::exec::Planner;
+use crate::{Error, Result};
+
+/// Parse the expression of a generated column of `schema`.
+///
+/// Returns the names of the columns the expression reads.  Generated columns
+/// may only read plain columns.
+pub fn generated_column_inputs(
+    schema: &Schema,
+    column: &str,
+    expression: &str,
+) -> Result<Vec<String>> {
+    let planner = Planner::new(Arc::new(ArrowSchema::from(schema)));
+    let expr = planner.parse_expr(expression).map_err(|e| {
+        Error::invalid_input(
+            format!(
+                "Invalid expression for generated column '{}': {}",
+                column, e
+            ),
+            location!(),
+        )
+    })?;
+    let inputs = Planner::column_names_in_expr(&expr)
+        .into_iter()
+        .map(|name| name.split('.').next().unwrap().to_string())
+        .collect::<Vec<_>>();
+    for input in &inputs {
+        let Some(field) = schema.field(input) else {
+            return Err(Error::invalid_input(
+                format!(
+                    "Generated column '{}' reads column '{}' which does not exist",
+                    column, input
+                ),
+                location!(),
+            ));
+        };
+        if input == column || field.generated_expression().is_some() {
+            return Err(Error::invalid_input(
+                format!(
+                    "Generated column '{}' cannot read generated column '{}'",
+                    column, input
+                ),
+                location!(),
+            ));
+        }
+    }
+    Ok(inputs)
+}
+
+/// Check that the expressions of all generated columns of `schema` are still
+/// valid, e.g. after columns have been renamed or dropped.
+pub fn validate_generated_columns(schema: &Schema) -> Result<()> {
+    for field in &schema.fields {
+        if let Some(expression) = field.generated_expression() {
+            generated_column_inputs(schema, &field.name, expression)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `schema` has any generated columns.
+pub fn has_generated_columns(schema: &Schema) -> bool {
+    schema
+        .fields
+        .iter()
+        .any(|f| f.generated_expression().is_some())
+}
+
+/// Wrap `stream` so that the generated columns of `schema` are computed for
+/// every batch.  Values the data has for generated columns are replaced.
+///
+/// See [`ComputedColumns::try_new`] for the order of the output columns.
+///
+/// If `allow_missing_inputs` is set, generated columns whose inputs are all
+/// missing from the data are left out, which is used for writes of a subset
+/// of the columns.  Otherwise, every input must be present.
+pub fn with_generated_columns(
+    schema: &Schema,
+    stream: SendableRecordBatchStream,
+    allow_missing_inputs: bool,
+) -> Result<SendableRecordBatchStream> {
+    if !has_generated_columns(schema) {
+        return Ok(stream);
+    }
+
+    let input_schema = stream.schema();
+    let planner = Planner::new(input_schema.clone());
+    let generated = ComputedColumns::try_new(schema, input_schema.as_ref(), |field, input| {
+        let Some(expression) = field.generated_expression() else {
+            return Ok(None);
+        };
+
+        let inputs = generated_column_inputs(schema, &field.name, expression)?;
+        let missing = inputs
+            .iter()
+            .filter(|name| input_schema.field_with_name(name).is_err())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            if allow_missing_inputs && missing.len() == inputs.len() && input.is_none() {
+                return Ok(None);
+            }
+            return Err(Error::invalid_input(
+                format!(
+                    "Cannot compute generated column '{}': column '{}' is missing from the data",
+                    field.name, missing[0]
+                ),
+                location!(),
+            ));
+        }
+
+        let expr = planner.parse_expr(expression)?;
+        let expr = planner.optimize_expr(expr)?;
+        let expr = planner.create_physical_expr(&expr)?;
+        // Fail instead of writing nulls if the value cannot be cast
+        Ok(Some(ComputedColumn::new(
+            expr,
+            field.data_type(),
+            CastOptions {
+                safe: false,
+                ..Default::default()
+            },
+        )))
+    })?;
+    Ok(generated.apply_to_stream(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use arrow_array::{Int64Array, RecordBatch, RecordBatchIterator, StringArray};
+    use arrow_schema::{DataType, Field as ArrowField};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use futures::TryStreamExt;
+    use lance_core::datatypes::LANCE_GENERATED_EXPRESSION_META_KEY;
+
+    use crate::dataset::{
+        ColumnAlteration, MergeInsertBuilder, NewColumnTransform, UpdateBuilder, WhenMatched,
+        WhenNotMatched,
+    };
+    use crate::Dataset;
+
+    fn make_batch(ids: &[i64], titles: &[&str]) -> RecordBatch {
+        let schema = Arc::new(ArrowSchema::new(vec![
+            ArrowField::new("id", DataType::Int64, false),
+            ArrowField::new("title", DataType::Utf8, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(ids.to_vec())),
+                Arc::new(StringArray::from(titles.to_vec())),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn write(dataset: &mut Dataset, batch: RecordBatch) {
+        let schema = batch.schema();
+        let reader = RecordBatchIterator::new(vec![Ok(batch)], schema);
+        dataset.append(reader, None).await.unwrap();
+    }
+
+    async fn generated_values(dataset: &Dataset) -> Vec<(i64, Option<String>)> {
+        let batches = dataset
+            .scan()
+            .project(&["id", "lower_title"])
+            .unwrap()
+            .try_into_stream()
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let mut values = batches
+            .iter()
+            .flat_map(|batch| {
+                let ids = batch["id"].as_any().downcast_ref::<Int64Array>().unwrap();
+                let titles = batch["lower_title"]
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                ids.values()
+                    .iter()
+                    .zip(titles.iter())
+                    .map(|(id, title)| (*id, title.map(String::from)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        values.sort();
+        values
+    }
+
+    fn expected(values: &[(i64, &str)]) -> Vec<(i64, Option<String>)> {
+        values
+            .iter()
+            .map(|(id, title)| (*id, Some(title.to_string())))
+            .collect()
+    }
+
+    async fn make_dataset(test_uri: &str) -> Dataset {
+        let batch = make_batch(&[1, 2], &["Foo", "Bar"]);
+        let schema = batch.schema();
+        let reader = RecordBatchIterator::new(vec![Ok(batch)], schema);
+        let mut dataset = Dataset::write(reader, test_uri, None).await.unwrap();
+        dataset
+            .add_columns(
+                NewColumnTransform::GeneratedColumns(vec![(
+                    "lower_title".into(),
+                    "lower(title)".into(),
+                )]),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        dataset
+    }
+
+    #[tokio::test]
+    async fn test_generated_column_on_append_and_update() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = make_dataset(test_uri).await;
+        assert_eq!(
+            dataset
+                .schema()
+                .field("lower_title")
+                .unwrap()
+                .generated_expression(),
+            Some("lower(title)")
+        );
+        assert_eq!(
+            generated_values(&dataset).await,
+            expected(&[(1, "foo"), (2, "bar")])
+        );
+
+        write(&mut dataset, make_batch(&[3], &["BaZ"])).await;
+        // Values provided for a generated column are replaced
+        let stale = make_batch(&[4], &["Qux"]);
+        let stale = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![
+                stale.schema().field(0).clone(),
+                stale.schema().field(1).clone(),
+                ArrowField::new("lower_title", DataType::Utf8, true),
+            ])),
+            vec![
+                stale.column(0).clone(),
+                stale.column(1).clone(),
+                Arc::new(StringArray::from(vec!["stale"])),
+            ],
+        )
+        .unwrap();
+        write(&mut dataset, stale).await;
+        assert_eq!(
+            generated_values(&dataset).await,
+            expected(&[(1, "foo"), (2, "bar"), (3, "baz"), (4, "qux")])
+        );
+
+        let dataset = UpdateBuilder::new(Arc::new(dataset))
+            .update_where("id = 2")
+            .unwrap()
+            .set("title", "'BAR BAR'")
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute()
+            .await
+            .unwrap()
+            .new_dataset;
+        assert_eq!(
+            generated_values(&dataset).await,
+            expected(&[(1, "foo"), (2, "bar bar"), (3, "baz"), (4, "qux")])
+        );
+
+        let err = UpdateBuilder::new(dataset.clone())
+            .set("lower_title", "'x'")
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_generated_column_on_merge_insert() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let dataset = Arc::new(make_dataset(test_uri).await);
+
+        let merge = |dataset: Arc<Dataset>, batch: RecordBatch| async move {
+            let job = MergeInsertBuilder::try_new(dataset, vec!["id".to_string()])
+                .unwrap()
+                .when_matched(WhenMatched::UpdateAll)
+                .when_not_matched(WhenNotMatched::InsertAll)
+                .try_build()
+                .unwrap();
+            let schema = batch.schema();
+            let reader = Box::new(RecordBatchIterator::new(vec![Ok(batch)], schema));
+            job.execute_reader(reader).await.map(|(dataset, _)| dataset)
+        };
+
+        let dataset = merge(dataset, make_batch(&[2, 3], &["Two", "Three"]))
+            .await
+            .unwrap();
+        assert_eq!(
+            generated_values(&dataset).await,
+            expected(&[(1, "foo"), (2, "two"), (3, "three")])
+        );
+
+        // A subset of the columns that includes the inputs of the generated column
+        let ids_and_titles = make_batch(&[1], &["ONE"]);
+        let dataset = MergeInsertBuilder::try_new(dataset, vec!["id".to_string()])
+            .unwrap()
+            .when_matched(WhenMatched::UpdateAll)
+            .when_not_matched(WhenNotMatched::DoNothing)
+            .try_build()
+            .unwrap()
+            .execute_reader(Box::new(RecordBatchIterator::new(
+                vec![Ok(ids_and_titles.clone())],
+                ids_and_titles.schema(),
+            )))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(
+            generated_values(&dataset).await,
+            expected(&[(1, "one"), (2, "two"), (3, "three")])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alter_generated_column() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = make_dataset(test_uri).await;
+
+        // Inputs of generated columns cannot be dropped or renamed
+        let err = dataset.drop_columns(&["title"]).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+        let err = dataset
+            .alter_columns(&[ColumnAlteration::new("title".into()).rename("name".into())])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+        let err = dataset
+            .alter_columns(&[ColumnAlteration::new("title".into()).materialize()])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+
+        dataset
+            .alter_columns(&[ColumnAlteration::new("lower_title".into()).materialize()])
+            .await
+            .unwrap();
+        assert_eq!(
+            dataset
+                .schema()
+                .field("lower_title")
+                .unwrap()
+                .generated_expression(),
+            None
+        );
+
+        // Plain columns keep the values written and are not computed anymore
+        write(&mut dataset, make_batch(&[3], &["Baz"])).await;
+        let mut values = expected(&[(1, "foo"), (2, "bar")]);
+        values.push((3, None));
+        assert_eq!(generated_values(&dataset).await, values);
+        dataset.drop_columns(&["title"]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_generated_column() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let mut dataset = make_dataset(test_uri).await;
+
+        let err = dataset
+            .add_columns(
+                NewColumnTransform::GeneratedColumns(vec![(
+                    "upper_title".into(),
+                    "upper(lower_title)".into(),
+                )]),
+                None,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+
+        // Appends must provide the inputs of the generated columns
+        let ids = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![ArrowField::new(
+                "id",
+                DataType::Int64,
+                false,
+            )])),
+            vec![Arc::new(Int64Array::from(vec![3]))],
+        )
+        .unwrap();
+        let err = dataset
+            .append(
+                RecordBatchIterator::new(vec![Ok(ids.clone())], ids.schema()),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_generated_column_cast_failure() {
+        let arrow_schema = ArrowSchema::new(vec![
+            ArrowField::new("id", DataType::Int64, false),
+            ArrowField::new("small", DataType::Int8, true).with_metadata(HashMap::from([(
+                LANCE_GENERATED_EXPRESSION_META_KEY.to_string(),
+                "id * 100".to_string(),
+            )])),
+        ]);
+        let schema = Schema::try_from(&arrow_schema).unwrap();
+        let ids = RecordBatch::try_new(
+            Arc::new(ArrowSchema::new(vec![arrow_schema.field(0).clone()])),
+            vec![Arc::new(Int64Array::from(vec![1, 3]))],
+        )
+        .unwrap();
+        let stream = Box::pin(RecordBatchStreamAdapter::new(
+            ids.schema(),
+            futures::stream::iter(vec![Ok(ids)]),
+        ));
+
+        // 300 does not fit in the column, which must fail rather than write a null
+        let err = with_generated_columns(&schema, stream, false)
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("300"), "{}", err);
+    }
+}
@@ -20,6 +20,9 @@ use object_store::path::Path;
 use snafu::location;
 
 use crate::dataset::builder::DatasetBuilder;
+use crate::dataset::partition::{
+    write_partitioned_fragments, PartitionSpec, PARTITION_SPEC_CONFIG_KEY,
+};
 use crate::dataset::transaction::Operation;
 use crate::dataset::transaction::Transaction;
 use crate::dataset::write::write_fragments_internal;
@@ -27,8 +30,12 @@ use crate::dataset::ReadParams;
 use crate::Dataset;
 use crate::{Error, Result};
 
+use super::check_non_nullable_fields_present;
 use super::commit::CommitBuilder;
+use super::defaults::{has_missing_defaults, validate_default_expression, with_default_values};
+use super::generated::{has_generated_columns, with_generated_columns};
 use super::resolve_commit_handler;
+use super::with_write_checks;
 use super::WriteDestination;
 use super::WriteMode;
 use super::WriteParams;
@@ -181,17 +188,58 @@ impl<'a> InsertBuilder<'a> {
     ) -> Result<(Transaction, WriteContext<'_>)> {
         let mut context = self.resolve_context().await?;
 
+        // Appends fill the columns missing from the data with their defaults and
+        // compute the generated columns of the dataset, other writes compute
+        // those declared in the schema of the data.
+        let (stream, schema) = match (&context.params.mode, context.dest.dataset()) {
+            (WriteMode::Append, Some(dataset))
+                if has_missing_defaults(dataset.schema(), &schema)
+                    || has_generated_columns(dataset.schema()) =>
+            {
+                let stream = with_default_values(dataset.schema(), stream)?;
+                let stream = with_generated_columns(dataset.schema(), stream, false)?;
+                let schema = Schema::try_from(stream.schema().as_ref())?;
+                (stream, schema)
+            }
+            (WriteMode::Append, Some(_)) => (stream, schema),
+            _ if has_generated_columns(&schema) => {
+                let stream = with_generated_columns(&schema, stream, false)?;
+                let schema = Schema::try_from(stream.schema().as_ref())?;
+                (stream, schema)
+            }
+            _ => (stream, schema),
+        };
+
         self.validate_write(&mut context, &schema)?;
 
-        let written_frags = write_fragments_internal(
-            context.dest.dataset(),
-            context.object_store.clone(),
-            &context.base_path,
-            schema.clone(),
-            stream,
-            context.params.clone(),
-        )
-        .await?;
+        // Appends are checked against the schema of the dataset.
+        let stream = match (&context.params.mode, context.dest.dataset()) {
+            (WriteMode::Overwrite, _) | (_, None) => with_write_checks(&schema, stream),
+            (_, Some(dataset)) => with_write_checks(dataset.schema(), stream),
+        };
+
+        let written_frags = if let Some(partition_spec) = &context.partition_spec {
+            write_partitioned_fragments(
+                context.dest.dataset(),
+                context.object_store.clone(),
+                &context.base_path,
+                schema.clone(),
+                stream,
+                context.params.clone(),
+                partition_spec,
+            )
+            .await?
+        } else {
+            write_fragments_internal(
+                context.dest.dataset(),
+                context.object_store.clone(),
+                &context.base_path,
+                schema.clone(),
+                stream,
+                context.params.clone(),
+            )
+            .await?
+        };
 
         let transaction = Self::build_transaction(schema, written_frags, &context)?;
 
@@ -203,6 +251,11 @@ impl<'a> InsertBuilder<'a> {
         written_frags: WrittenFragments,
         context: &WriteContext<'_>,
     ) -> Result<Transaction> {
+        // A new partition spec is stored in the config of the dataset
+        let partition_config =
+            context.params.partition_spec.as_ref().map(|spec| {
+                HashMap::from([(PARTITION_SPEC_CONFIG_KEY.to_string(), spec.to_string())])
+            });
         let operation = match context.params.mode {
             WriteMode::Create => {
                 // Fetch auto_cleanup params from context
@@ -234,6 +287,13 @@ impl<'a> InsertBuilder<'a> {
                     }
                     None => None,
                 };
+                let config_upsert_values = match (config_upsert_values, partition_config) {
+                    (Some(mut upsert_values), Some(partition_config)) => {
+                        upsert_values.extend(partition_config);
+                        Some(upsert_values)
+                    }
+                    (upsert_values, partition_config) => upsert_values.or(partition_config),
+                };
                 Operation::Overwrite {
                     // Use the full schema, not the written schema
                     schema,
@@ -245,7 +305,7 @@ impl<'a> InsertBuilder<'a> {
                 // Use the full schema, not the written schema
                 schema,
                 fragments: written_frags.default.0,
-                config_upsert_values: None,
+                config_upsert_values: partition_config,
             },
             WriteMode::Append => Operation::Append {
                 fragments: written_frags.default.0,
@@ -319,10 +379,17 @@ impl<'a> InsertBuilder<'a> {
                     schema_cmp_opts.allow_missing_if_nullable = true;
                 }
 
+                check_non_nullable_fields_present(&m.schema, data_schema)?;
                 data_schema.check_compatible(&m.schema, &schema_cmp_opts)?;
             }
         }
 
+        if !matches!(context.params.mode, WriteMode::Append) {
+            for field in &data_schema.fields {
+                validate_default_expression(field)?;
+            }
+        }
+
         // If we are writing a dataset with non-default storage, we need to enable move stable row ids
         if context.dest.dataset().is_none()
             && !context.params.enable_move_stable_row_ids
@@ -417,6 +484,28 @@ impl<'a> InsertBuilder<'a> {
             (_, WriteDestination::Uri(_)) => params.storage_version_or_default(),
         };
 
+        let partition_spec = match (&dest, &params.mode) {
+            (WriteDestination::Dataset(dataset), WriteMode::Append) => {
+                // Appends must follow the partitioning of the dataset
+                let existing = PartitionSpec::from_manifest(&dataset.manifest)?;
+                if params.partition_spec.is_some() && params.partition_spec != existing {
+                    return Err(Error::invalid_input(
+                        format!(
+                            "Cannot append with partition spec {} to a dataset with partition spec {}",
+                            params.partition_spec.as_ref().unwrap(),
+                            existing.map(|spec| spec.to_string()).unwrap_or("none".into())
+                        ),
+                        location!(),
+                    ));
+                }
+                existing
+            }
+            (WriteDestination::Dataset(dataset), _) if params.partition_spec.is_none() => {
+                PartitionSpec::from_manifest(&dataset.manifest)?
+            }
+            _ => params.partition_spec.clone(),
+        };
+
         Ok(WriteContext {
             params,
             dest,
@@ -424,6 +513,7 @@ impl<'a> InsertBuilder<'a> {
             base_path,
             commit_handler,
             storage_version,
+            partition_spec,
         })
     }
 }
@@ -436,4 +526,5 @@ struct WriteContext<'a> {
     base_path: Path,
     commit_handler: Arc<dyn CommitHandler>,
     storage_version: LanceFileVersion,
+    partition_spec: Option<PartitionSpec>,
 }
@@ -94,6 +94,9 @@ use crate::{
     Dataset,
 };
 
+use super::defaults::with_default_values;
+use super::generated::with_generated_columns;
+use super::with_write_checks;
 use super::{write_fragments_internal, CommitBuilder, WriteParams};
 
 // "update if" expressions typically compare fields from the source table to the target table.
@@ -914,9 +917,14 @@ impl MergeInsertJob {
                     .map(move |batch| batch.project(&projection));
                 let reader = RecordBatchIterator::new(batches, write_schema.clone());
                 let stream = reader_to_stream(Box::new(reader));
+                // New rows get the defaults of the columns missing from the source,
+                // which may in turn be read by generated columns.
+                let stream = with_default_values(dataset.schema(), stream)?;
+                let stream = with_generated_columns(dataset.schema(), stream, true)?;
+                let stream = with_write_checks(dataset.schema(), stream);
 
                 let write_schema = dataset.schema().project_by_schema(
-                    write_schema.as_ref(),
+                    stream.schema().as_ref(),
                     OnMissing::Error,
                     OnTypeMismatch::Error,
                 )?;
@@ -1170,6 +1178,10 @@ impl MergeInsertJob {
         self,
         source: SendableRecordBatchStream,
     ) -> Result<UncommittedMergeInsert> {
+        // Compute the generated columns of the source rows. Sources with a subset
+        // of the columns keep the generated columns whose inputs they don't touch.
+        let source = with_generated_columns(self.dataset.schema(), source, true)?;
+
         // Erase metadata on source / dataset schemas to avoid comparing metadata
         let schema = lance_core::datatypes::Schema::try_from(source.schema().as_ref())?;
         let full_schema = self.dataset.local_schema();
@@ -1211,6 +1223,7 @@ impl MergeInsertJob {
                 updated_fragments,
                 new_fragments,
                 fields_modified,
+                replaced_row_ids: None,
             };
             // We have rewritten the fragments, not just the deletion files, so
             // we can't use affected rows here.
@@ -1221,7 +1234,7 @@ impl MergeInsertJob {
                 self.dataset.object_store.clone(),
                 &self.dataset.base,
                 self.dataset.schema().clone(),
-                Box::pin(stream),
+                with_write_checks(self.dataset.schema(), Box::pin(stream)),
                 WriteParams::default(),
             )
             .await?;
@@ -1243,6 +1256,7 @@ impl MergeInsertJob {
                 // On this path we only make deletions against updated_fragments and will not
                 // modify any field values.
                 fields_modified: vec![],
+                replaced_row_ids: None,
             };
 
             let affected_rows = Some(RowIdTreeMap::from(removed_row_ids));
@@ -2,17 +2,18 @@
 // SPDX-FileCopyrightText: Copyright The Lance Authors
 
 use std::collections::{BTreeMap, HashMap};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use super::super::utils::make_rowid_capture_stream;
-use super::{write_fragments_internal, CommitBuilder, WriteParams};
-use arrow_array::RecordBatch;
+use super::generated::with_generated_columns;
+use super::{with_write_checks, write_fragments_internal, CommitBuilder, WriteParams};
+use arrow_array::{cast::AsArray, types::UInt64Type, RecordBatch};
 use arrow_schema::{ArrowError, DataType, Schema as ArrowSchema};
 use datafusion::common::DFSchema;
 use datafusion::error::{DataFusionError, Result as DFResult};
 use datafusion::logical_expr::ExprSchemable;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
-use datafusion::physical_plan::PhysicalExpr;
+use datafusion::physical_plan::{PhysicalExpr, SendableRecordBatchStream};
 use datafusion::prelude::Expr;
 use datafusion::scalar::ScalarValue;
 use futures::StreamExt;
@@ -20,8 +21,10 @@ use lance_arrow::RecordBatchExt;
 use lance_core::error::{box_error, InvalidInputSnafu};
 use lance_core::utils::mask::RowIdTreeMap;
 use lance_core::utils::tokio::get_num_compute_intensive_cpus;
+use lance_core::ROW_ID;
 use lance_datafusion::expr::safe_coerce_scalar;
 use lance_table::format::Fragment;
+use lance_table::rowids::RowIdSequence;
 use roaring::RoaringTreemap;
 use snafu::{location, ResultExt};
 
@@ -96,6 +99,16 @@ impl UpdateBuilder {
                 )
             })?;
 
+        if field.generated_expression().is_some() {
+            return Err(Error::invalid_input(
+                format!(
+                    "Column '{}' is a generated column and cannot be updated",
+                    column.as_ref()
+                ),
+                location!(),
+            ));
+        }
+
         // TODO: support nested column references. This is mostly blocked on the
         // ability to insert them into the RecordBatch properly.
         if column.as_ref().contains('.') {
@@ -229,7 +242,34 @@ impl UpdateJob {
             scanner.filter_expr(expr.clone());
         }
 
-        let stream = scanner.try_into_stream().await?.into();
+        let stream: SendableRecordBatchStream = scanner.try_into_stream().await?.into();
+
+        // With stable row ids, each row written by this job replaces one of the
+        // scanned rows, in scan order. Record that order so the new rows can be
+        // paired with the rows they replace.
+        let replaced_row_ids = self
+            .dataset
+            .manifest
+            .uses_move_stable_row_ids()
+            .then(|| Arc::new(Mutex::new(Vec::new())));
+        let stream: SendableRecordBatchStream = match &replaced_row_ids {
+            Some(replaced_row_ids) => {
+                let replaced_row_ids = replaced_row_ids.clone();
+                let schema = stream.schema();
+                let stream = stream.map(move |batch| -> DFResult<RecordBatch> {
+                    let batch = batch?;
+                    if let Some(row_ids) = batch.column_by_name(ROW_ID) {
+                        replaced_row_ids
+                            .lock()
+                            .unwrap()
+                            .extend_from_slice(row_ids.as_primitive::<UInt64Type>().values());
+                    }
+                    Ok(batch)
+                });
+                Box::pin(RecordBatchStreamAdapter::new(schema, stream))
+            }
+            None => stream,
+        };
 
         // We keep track of seen row ids so we can delete them from the existing
         // fragments.
@@ -259,6 +299,19 @@ impl UpdateJob {
                 Err(e) => Err(DataFusionError::Execution(e.to_string())),
             });
         let stream = RecordBatchStreamAdapter::new(schema, stream);
+        // Recompute the generated columns from the updated values
+        let stream = with_generated_columns(self.dataset.schema(), Box::pin(stream), false)?;
+        // Only the updated and generated columns get new values, nulls already
+        // in the other columns are written back as they are.
+        let changed_columns = self
+            .dataset
+            .schema()
+            .fields
+            .iter()
+            .filter(|f| self.updates.contains_key(&f.name) || f.generated_expression().is_some())
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        let stream = with_write_checks(&self.dataset.schema().project(&changed_columns)?, stream);
 
         let version = self
             .dataset
@@ -270,7 +323,7 @@ impl UpdateJob {
             self.dataset.object_store.clone(),
             &self.dataset.base,
             self.dataset.schema().clone(),
-            Box::pin(stream),
+            stream,
             WriteParams::with_storage_version(version),
         )
         .await?;
@@ -290,6 +343,10 @@ impl UpdateJob {
             .unwrap();
         let (old_fragments, removed_fragment_ids) = self.apply_deletions(&removed_row_ids).await?;
         let affected_rows = RowIdTreeMap::from(removed_row_ids);
+        let replaced_row_ids = replaced_row_ids.map(|row_ids| {
+            let row_ids = Arc::into_inner(row_ids).unwrap().into_inner().unwrap();
+            RowIdSequence::from(row_ids.as_slice())
+        });
 
         let num_updated_rows = new_fragments
             .iter()
@@ -301,6 +358,7 @@ impl UpdateJob {
                 removed_fragment_ids,
                 old_fragments,
                 new_fragments,
+                replaced_row_ids,
                 affected_rows,
             )
             .await?;
@@ -375,6 +433,7 @@ impl UpdateJob {
         removed_fragment_ids: Vec<u64>,
         updated_fragments: Vec<Fragment>,
         new_fragments: Vec<Fragment>,
+        replaced_row_ids: Option<RowIdSequence>,
         affected_rows: RowIdTreeMap,
     ) -> Result<Arc<Dataset>> {
         let operation = Operation::Update {
@@ -383,6 +442,7 @@ impl UpdateJob {
             new_fragments,
             // This job only deletes rows, it does not modify any field values.
             fields_modified: vec![],
+            replaced_row_ids,
         };
         let transaction = Transaction::new(
             self.dataset.manifest.version,
@@ -497,6 +557,41 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_update_non_nullable_to_null() {
+        let (dataset, _test_dir) = make_test_dataset(LanceFileVersion::V2_0).await;
+
+        let err = UpdateBuilder::new(dataset.clone())
+            .update_where("id < 5")
+            .unwrap()
+            .set("name", "NULL")
+            .unwrap()
+            .build()
+            .unwrap()
+            .execute()
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("marked non-null"),
+            "unexpected error: {}",
+            err
+        );
+
+        // Nothing was committed.
+        let dataset = DatasetBuilder::from_uri(dataset.uri())
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(dataset.version().version, 1);
+        assert_eq!(
+            dataset
+                .count_rows(Some("name IS NULL".into()))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_update_all(
@@ -7,12 +7,13 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, OnceLock};
 
-use arrow_schema::{DataType, Schema};
+use arrow_schema::{DataType, Field as ArrowField, Schema};
 use async_trait::async_trait;
 use datafusion::execution::SendableRecordBatchStream;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use futures::{stream, StreamExt, TryStreamExt};
 use itertools::Itertools;
+use lance_arrow::sparse::is_sparse_vector_field;
 use lance_core::utils::address::RowAddress;
 use lance_core::utils::parse::str_is_truthy;
 use lance_core::utils::tracing::{
@@ -29,6 +30,7 @@ use lance_index::scalar::expression::{
 };
 use lance_index::scalar::lance_format::LanceIndexStore;
 use lance_index::scalar::{ScalarIndex, ScalarIndexType};
+use lance_index::vector::bq::RabitQuantizer;
 use lance_index::vector::flat::index::{FlatBinQuantizer, FlatIndex, FlatQuantizer};
 use lance_index::vector::hnsw::HNSW;
 use lance_index::vector::pq::ProductQuantizer;
@@ -249,6 +251,26 @@ fn vector_index_details() -> prost_types::Any {
     prost_types::Any::from_msg(&details).unwrap()
 }
 
+fn vector_index_details_for(params: &VectorIndexParams) -> prost_types::Any {
+    let details = lance_table::format::pb::VectorIndexDetails {
+        refine_factor: params.default_refine_factor().unwrap_or_default(),
+    };
+    prost_types::Any::from_msg(&details).unwrap()
+}
+
+/// The refine factor of the queries on the vector index that do not set one.
+///
+/// It's read from the index details, so the index doesn't have to be opened.
+pub(crate) fn vector_index_refine_factor(index: &IndexMetadata) -> Result<Option<u32>> {
+    match &index.index_details {
+        Some(details) if details.type_url.ends_with("VectorIndexDetails") => {
+            let details = details.to_msg::<lance_table::format::pb::VectorIndexDetails>()?;
+            Ok((details.refine_factor > 0).then_some(details.refine_factor))
+        }
+        _ => Ok(None),
+    }
+}
+
 #[async_trait]
 impl DatasetIndexExt for Dataset {
     #[instrument(skip_all)]
@@ -305,7 +327,8 @@ impl DatasetIndexExt for Dataset {
                 | IndexType::BTree
                 | IndexType::Inverted
                 | IndexType::NGram
-                | IndexType::LabelList,
+                | IndexType::LabelList
+                | IndexType::Sparse,
                 LANCE_SCALAR_INDEX,
             ) => {
                 let params = ScalarIndexParams::new(index_type.try_into()?);
@@ -322,6 +345,48 @@ impl DatasetIndexExt for Dataset {
                     })?;
                 build_scalar_index(self, column, &index_id.to_string(), params).await?
             }
+            (index_type, name)
+                if index_type.is_scalar()
+                    && self
+                        .session
+                        .index_extensions
+                        .contains_key(&(index_type, name.to_string())) =>
+            {
+                let ext = self
+                    .session
+                    .index_extensions
+                    .get(&(index_type, name.to_string()))
+                    .expect("already checked")
+                    .clone()
+                    .to_scalar()
+                    // this should never happen because we control the registration
+                    // if this fails, the registration logic has a bug
+                    .ok_or(Error::Internal {
+                        message: "unable to cast index extension to scalar".to_string(),
+                        location: location!(),
+                    })?;
+
+                let training_request = Box::new(TrainingRequest::new(
+                    Arc::new(self.clone()),
+                    column.to_string(),
+                ));
+                let index_store = LanceIndexStore::from_dataset(self, &index_id.to_string());
+                let details = ext
+                    .create_index(self, column, training_request, &index_store, params)
+                    .await?;
+                if details.type_url != ext.index_details_type_url() {
+                    return Err(Error::Index {
+                        message: format!(
+                            "Index extension {} returned index details of type {}, expected {}",
+                            name,
+                            details.type_url,
+                            ext.index_details_type_url()
+                        ),
+                        location: location!(),
+                    });
+                }
+                details
+            }
             (IndexType::Inverted, _) => {
                 // Inverted index params.
                 let inverted_params = params
@@ -354,7 +419,7 @@ impl DatasetIndexExt for Dataset {
                     vec_params,
                 ))
                 .await?;
-                vector_index_details()
+                vector_index_details_for(vec_params)
             }
             // Can't use if let Some(...) here because it's not stable yet.
             // TODO: fix after https://github.com/rust-lang/rust/issues/51114
@@ -470,7 +535,7 @@ impl DatasetIndexExt for Dataset {
         let indices = match self
             .session
             .index_cache
-            .get_metadata(self.base.as_ref(), self.version().version)
+            .get_metadata(self.manifest_base().as_ref(), self.version().version)
         {
             Some(indices) => indices,
             None => {
@@ -482,7 +547,7 @@ impl DatasetIndexExt for Dataset {
                 .await?;
                 let loaded_indices = Arc::new(loaded_indices);
                 self.session.index_cache.insert_metadata(
-                    self.base.as_ref(),
+                    self.manifest_base().as_ref(),
                     self.version().version,
                     loaded_indices.clone(),
                 );
@@ -1066,6 +1131,17 @@ impl DatasetIndexInternalExt for Dataset {
                         Ok(Arc::new(ivf) as Arc<dyn VectorIndex>)
                     }
 
+                    "IVF_RQ" => {
+                        let ivf = IVFIndex::<FlatIndex, RabitQuantizer>::try_new(
+                            self.object_store.clone(),
+                            self.indices_dir(),
+                            uuid.to_owned(),
+                            Arc::downgrade(&self.session),
+                        )
+                        .await?;
+                        Ok(Arc::new(ivf) as Arc<dyn VectorIndex>)
+                    }
+
                     "IVF_HNSW_SQ" => {
                         let ivf = IVFIndex::<HNSW, ScalarQuantizer>::try_new(
                             self.object_store.clone(),
@@ -1157,7 +1233,22 @@ impl DatasetIndexInternalExt for Dataset {
                 location: location!(),
             })?;
 
+            if let Some(details) = &index.index_details {
+                if let Some(ext) = self.session.scalar_index_extension(details) {
+                    if let Some(query_parser) = ext.query_parser(index.name.clone(), field) {
+                        indexed_fields
+                            .push((field.name.clone(), (field.data_type(), query_parser)));
+                    }
+                    continue;
+                } else if infer_index_type(index).is_none() {
+                    // The index is created by an extension which is not registered in the session
+                    continue;
+                }
+            }
+
             let query_parser = match field.data_type() {
+                // Sparse vector indices can only be used for top-k search, not for filtering
+                DataType::List(_) if is_sparse_vector_field(&ArrowField::from(field)) => continue,
                 DataType::List(_) => Box::new(LabelListQueryParser::new(index.name.clone()))
                     as Box<dyn ScalarQueryParser>,
                 DataType::Utf8 | DataType::LargeUtf8 => {
@@ -3,20 +3,22 @@
 
 use std::sync::Arc;
 
+use lance_core::datatypes::Field;
 use lance_core::{Error, Result};
 use lance_index::optimize::OptimizeOptions;
 use lance_index::scalar::lance_format::LanceIndexStore;
 use lance_index::IndexType;
 use lance_index::{metrics::NoOpMetricsCollector, scalar::inverted::InvertedIndex};
-use lance_table::format::Index as IndexMetadata;
+use lance_table::format::{Fragment, Index as IndexMetadata};
 use roaring::RoaringBitmap;
 use snafu::location;
 use uuid::Uuid;
 
+use super::vector::diskann::optimize_diskann_index;
 use super::vector::ivf::optimize_vector_indices;
 use super::DatasetIndexInternalExt;
 use crate::dataset::index::LanceIndexStoreExt;
-use crate::dataset::scanner::ColumnOrdering;
+use crate::dataset::scanner::{ColumnOrdering, DatasetRecordBatchStream};
 use crate::dataset::Dataset;
 
 pub struct IndexMergeResults<'a> {
@@ -119,7 +121,7 @@ pub async fn merge_indices<'a>(
 
             let mut scanner = dataset.scan();
             let orodering = match index.index_type() {
-                IndexType::Inverted => None,
+                IndexType::Inverted | IndexType::Sparse => None,
                 _ => Some(vec![ColumnOrdering::asc_nulls_first(column.name.clone())]),
             };
             scanner
@@ -138,6 +140,22 @@ pub async fn merge_indices<'a>(
 
             Ok((new_uuid, 1))
         }
+        IndexType::DiskAnn => {
+            // There are no delta indices for DiskANN, the new vectors are
+            // inserted into the graph of the last index.
+            let old_index = old_indices[old_indices.len() - 1];
+            let new_data_stream = scan_unindexed_vectors(&dataset, column, unindexed).await?;
+            let new_uuid = optimize_diskann_index(
+                dataset.as_ref(),
+                new_data_stream,
+                &column.name,
+                &old_index.name,
+                &indices[indices.len() - 1],
+                options,
+            )
+            .await?;
+            Ok((new_uuid, 1))
+        }
         it if it.is_vector() => {
             let start_pos = old_indices
                 .len()
@@ -147,19 +165,7 @@ pub async fn merge_indices<'a>(
                 frag_bitmap.extend(idx.fragment_bitmap.as_ref().unwrap().iter());
             });
 
-            let new_data_stream = if unindexed.is_empty() {
-                None
-            } else {
-                let mut scanner = dataset.scan();
-                scanner
-                    .with_fragments(unindexed)
-                    .with_row_id()
-                    .project(&[&column.name])?;
-                if column.nullable {
-                    scanner.filter_expr(datafusion_expr::col(&column.name).is_not_null());
-                }
-                Some(scanner.try_into_stream().await?)
-            };
+            let new_data_stream = scan_unindexed_vectors(&dataset, column, unindexed).await?;
 
             optimize_vector_indices(
                 dataset.as_ref().clone(),
@@ -192,6 +198,26 @@ pub async fn merge_indices<'a>(
     }))
 }
 
+/// Scan the non-null vectors of the `unindexed` fragments, with their row ids.
+async fn scan_unindexed_vectors(
+    dataset: &Dataset,
+    column: &Field,
+    unindexed: Vec<Fragment>,
+) -> Result<Option<DatasetRecordBatchStream>> {
+    if unindexed.is_empty() {
+        return Ok(None);
+    }
+    let mut scanner = dataset.scan();
+    scanner
+        .with_fragments(unindexed)
+        .with_row_id()
+        .project(&[&column.name])?;
+    if column.nullable {
+        scanner.filter_expr(datafusion_expr::col(&column.name).is_not_null());
+    }
+    Ok(Some(scanner.try_into_stream().await?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
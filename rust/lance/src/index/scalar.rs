@@ -6,11 +6,12 @@
 
 use std::sync::Arc;
 
-use arrow_schema::DataType;
+use arrow_schema::{DataType, Field as ArrowField};
 use async_trait::async_trait;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use futures::TryStreamExt;
+use lance_arrow::sparse::{is_sparse_vector_field, SPARSE_VECTOR_EXT_NAME};
 use lance_core::datatypes::Field;
 use lance_core::{Error, Result};
 use lance_datafusion::{chunker::chunk_concat_stream, exec::LanceExecutionOptions};
@@ -20,6 +21,7 @@ use lance_index::scalar::{
 use lance_index::scalar::{
     inverted::METADATA_FILE,
     ngram::{train_ngram_index, NGramIndex},
+    sparse::{train_sparse_index, SparseIndex},
 };
 use lance_index::ScalarIndexCriteria;
 use lance_index::{
@@ -175,6 +177,11 @@ fn ngram_index_details() -> prost_types::Any {
     prost_types::Any::from_msg(&details).unwrap()
 }
 
+fn sparse_index_details() -> prost_types::Any {
+    let details = lance_table::format::pb::SparseIndexDetails {};
+    prost_types::Any::from_msg(&details).unwrap()
+}
+
 pub(super) fn inverted_index_details() -> prost_types::Any {
     let details = lance_table::format::pb::InvertedIndexDetails::default();
     prost_types::Any::from_msg(&details).unwrap()
@@ -210,6 +217,12 @@ impl ScalarIndexDetails for lance_table::format::pb::NGramIndexDetails {
     }
 }
 
+impl ScalarIndexDetails for lance_table::format::pb::SparseIndexDetails {
+    fn get_type(&self) -> ScalarIndexType {
+        ScalarIndexType::Sparse
+    }
+}
+
 fn get_scalar_index_details(
     details: &prost_types::Any,
 ) -> Result<Option<Box<dyn ScalarIndexDetails>>> {
@@ -233,6 +246,10 @@ fn get_scalar_index_details(
         Ok(Some(Box::new(
             details.to_msg::<lance_table::format::pb::NGramIndexDetails>()?,
         )))
+    } else if details.type_url.ends_with("SparseIndexDetails") {
+        Ok(Some(Box::new(
+            details.to_msg::<lance_table::format::pb::SparseIndexDetails>()?,
+        )))
     } else {
         Ok(None)
     }
@@ -285,10 +302,27 @@ pub(super) async fn build_scalar_index(
         });
     }
 
+    if matches!(params.force_index_type, Some(ScalarIndexType::Sparse))
+        && !is_sparse_vector_field(&ArrowField::from(field))
+    {
+        return Err(Error::InvalidInput {
+            source: format!(
+                "Sparse index can only be created on sparse vector columns, with the {} extension name. Column '{}' has type {:?}",
+                SPARSE_VECTOR_EXT_NAME,
+                column,
+                field.data_type()
+            )
+            .into(),
+            location: location!(),
+        });
+    }
+
     // In theory it should be possible to create a btree/bitmap index on a nested field but
     // performance would be poor and I'm not sure we want to allow that unless there is a need.
-    if !matches!(params.force_index_type, Some(ScalarIndexType::LabelList))
-        && field.data_type().is_nested()
+    if !matches!(
+        params.force_index_type,
+        Some(ScalarIndexType::LabelList | ScalarIndexType::Sparse)
+    ) && field.data_type().is_nested()
     {
         return Err(Error::InvalidInput {
             source: "A scalar index can only be created on a non-nested field.".into(),
@@ -324,6 +358,10 @@ pub(super) async fn build_scalar_index(
             train_ngram_index(training_request, &index_store).await?;
             Ok(ngram_index_details())
         }
+        Some(ScalarIndexType::Sparse) => {
+            train_sparse_index(training_request, &index_store).await?;
+            Ok(sparse_index_details())
+        }
         _ => {
             let flat_index_trainer = FlatIndexMetadata::new(field.data_type());
             train_btree_index(
@@ -361,6 +399,13 @@ pub async fn open_scalar_index(
 ) -> Result<Arc<dyn ScalarIndex>> {
     let uuid_str = index.uuid.to_string();
     let index_store = Arc::new(LanceIndexStore::from_dataset(dataset, &uuid_str));
+    if let Some(ext) = index
+        .index_details
+        .as_ref()
+        .and_then(|details| dataset.session.scalar_index_extension(details))
+    {
+        return ext.load_index(index_store).await;
+    }
     let index_type = detect_scalar_index_type(dataset, index, column, &dataset.session).await?;
     match index_type {
         ScalarIndexType::Bitmap => {
@@ -379,6 +424,10 @@ pub async fn open_scalar_index(
             let ngram_index = NGramIndex::load(index_store).await?;
             Ok(ngram_index as Arc<dyn ScalarIndex>)
         }
+        ScalarIndexType::Sparse => {
+            let sparse_index = SparseIndex::load(index_store).await?;
+            Ok(sparse_index as Arc<dyn ScalarIndex>)
+        }
         ScalarIndexType::BTree => {
             let btree_index = BTreeIndex::load(index_store).await?;
             Ok(btree_index as Arc<dyn ScalarIndex>)
@@ -504,16 +553,21 @@ pub fn index_matches_criteria(
             if index_type != expected_type {
                 return Ok(false);
             }
-            // We should not use FTS / NGram indices for exact equality queries
+            // We should not use FTS / NGram / sparse indices for exact equality queries
             // (i.e. merge insert with a join on the indexed column)
             if criteria.supports_exact_equality {
                 match index_type {
-                    ScalarIndexType::Inverted | ScalarIndexType::NGram => {
+                    ScalarIndexType::Inverted
+                    | ScalarIndexType::NGram
+                    | ScalarIndexType::Sparse => {
                         return Ok(false);
                     }
                     _ => {}
                 }
             }
+        } else if index.index_details.is_some() {
+            // The details are not one of the built-in types, the index is created by an extension
+            return Ok(false);
         } else if has_multiple_indices {
             return Err(Error::InvalidInput {
                 source: format!(
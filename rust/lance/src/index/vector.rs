@@ -8,17 +8,22 @@ use std::sync::Arc;
 use std::{any::Any, collections::HashMap};
 
 pub mod builder;
+pub mod diskann;
+pub mod distributed;
 pub mod ivf;
 pub mod pq;
+pub mod tune;
 pub mod utils;
 
 #[cfg(test)]
 mod fixture_test;
 
 use arrow_schema::DataType;
-use builder::IvfIndexBuilder;
+use builder::{IvfBuildPhase, IvfIndexBuilder};
 use lance_file::reader::FileReader;
 use lance_index::metrics::NoOpMetricsCollector;
+use lance_index::vector::bq::{builder::RQBuildParams, RabitQuantizer};
+use lance_index::vector::diskann::{DiskANNIndex, DiskANNParams};
 use lance_index::vector::flat::index::{FlatBinQuantizer, FlatIndex, FlatQuantizer};
 use lance_index::vector::hnsw::HNSW;
 use lance_index::vector::ivf::storage::IvfModel;
@@ -56,9 +61,11 @@ pub const LANCE_VECTOR_INDEX: &str = "__lance_vector_index";
 #[derive(Debug, Clone)]
 pub enum StageParams {
     Ivf(IvfBuildParams),
+    DiskANN(DiskANNParams),
     Hnsw(HnswBuildParams),
     PQ(PQBuildParams),
     SQ(SQBuildParams),
+    RQ(RQBuildParams),
 }
 
 // The version of the index file.
@@ -98,6 +105,16 @@ impl VectorIndexParams {
         }
     }
 
+    /// Create index parameters for `IVF_FLAT` index with `IVF` parameters.
+    pub fn with_ivf_flat_params(metric_type: MetricType, ivf: IvfBuildParams) -> Self {
+        let stages = vec![StageParams::Ivf(ivf)];
+        Self {
+            stages,
+            metric_type,
+            version: IndexFileVersion::V3,
+        }
+    }
+
     /// Create index parameters for `IVF_PQ` index.
     ///
     /// Parameters
@@ -145,6 +162,41 @@ impl VectorIndexParams {
         }
     }
 
+    /// Create index parameters with `IVF` and `RQ` (RaBitQ) parameters, respectively.
+    ///
+    /// The results are reranked with the raw vectors, fetching
+    /// `RQBuildParams::refine_factor * k` candidates unless the query sets a
+    /// refine factor.
+    pub fn with_ivf_rq_params(
+        metric_type: MetricType,
+        ivf: IvfBuildParams,
+        rq: RQBuildParams,
+    ) -> Self {
+        let stages = vec![StageParams::Ivf(ivf), StageParams::RQ(rq)];
+        Self {
+            stages,
+            metric_type,
+            version: IndexFileVersion::V3,
+        }
+    }
+
+    /// Create index parameters with `DiskANN` and `PQ` parameters, respectively.
+    ///
+    /// The PQ codes are kept in memory to walk the graph, while the full vectors
+    /// and the graph are read from storage.
+    pub fn with_diskann_params(
+        metric_type: MetricType,
+        diskann: DiskANNParams,
+        pq: PQBuildParams,
+    ) -> Self {
+        let stages = vec![StageParams::DiskANN(diskann), StageParams::PQ(pq)];
+        Self {
+            stages,
+            metric_type,
+            version: IndexFileVersion::V3,
+        }
+    }
+
     /// Create index parameters with `IVF`, `PQ` and `HNSW` parameters, respectively.
     /// This is used for `IVF_HNSW_PQ` index.
     pub fn with_ivf_hnsw_pq_params(
@@ -184,6 +236,15 @@ impl VectorIndexParams {
             version: IndexFileVersion::V3,
         }
     }
+
+    /// The refine factor of the queries that do not set one, if the results
+    /// of the index are reranked with the raw vectors by default.
+    pub fn default_refine_factor(&self) -> Option<u32> {
+        self.stages.iter().find_map(|stage| match stage {
+            StageParams::RQ(rq) => Some(rq.refine_factor),
+            _ => None,
+        })
+    }
 }
 
 impl IndexParams for VectorIndexParams {
@@ -218,6 +279,22 @@ fn is_ivf_pq(stages: &[StageParams]) -> bool {
         && matches!(&stages[len - 2], StageParams::Ivf(_))
 }
 
+fn is_ivf_rq(stages: &[StageParams]) -> bool {
+    if stages.len() != 2 {
+        return false;
+    }
+
+    matches!(&stages[0], StageParams::Ivf(_)) && matches!(&stages[1], StageParams::RQ(_))
+}
+
+fn is_diskann(stages: &[StageParams]) -> bool {
+    if stages.len() != 2 {
+        return false;
+    }
+
+    matches!(&stages[0], StageParams::DiskANN(_)) && matches!(&stages[1], StageParams::PQ(_))
+}
+
 fn is_ivf_hnsw(stages: &[StageParams]) -> bool {
     if stages.len() < 2 {
         return false;
@@ -234,6 +311,20 @@ pub(crate) async fn build_vector_index(
     name: &str,
     uuid: &str,
     params: &VectorIndexParams,
+) -> Result<()> {
+    build_vector_index_phase(dataset, column, name, uuid, params, IvfBuildPhase::Full).await
+}
+
+/// Run one phase of building a Vector Index.
+///
+/// Only the IVF indices of V3 version can be built in phases.
+pub(crate) async fn build_vector_index_phase(
+    dataset: &Dataset,
+    column: &str,
+    name: &str,
+    uuid: &str,
+    params: &VectorIndexParams,
+    phase: IvfBuildPhase,
 ) -> Result<()> {
     let stages = &params.stages;
 
@@ -244,6 +335,45 @@ pub(crate) async fn build_vector_index(
         });
     };
 
+    if !matches!(phase, IvfBuildPhase::Full)
+        && (is_diskann(stages) || matches!(params.version, IndexFileVersion::Legacy))
+    {
+        return Err(Error::Index {
+            message:
+                "Build Vector Index: only the IVF indices of V3 version can be built in phases"
+                    .to_string(),
+            location: location!(),
+        });
+    }
+
+    if is_diskann(stages) {
+        let (StageParams::DiskANN(diskann_params), StageParams::PQ(pq_params)) =
+            (&stages[0], &stages[1])
+        else {
+            unreachable!()
+        };
+        let (vector_type, _) = get_vector_type(dataset.schema(), column)?;
+        if !matches!(vector_type, DataType::FixedSizeList(..)) {
+            return Err(Error::Index {
+                message: format!(
+                    "Build Vector Index: DiskANN does not support {} vectors",
+                    vector_type
+                ),
+                location: location!(),
+            });
+        }
+        return diskann::build_diskann_index(
+            dataset,
+            column,
+            name,
+            uuid,
+            params.metric_type,
+            diskann_params,
+            pq_params,
+        )
+        .await;
+    }
+
     let StageParams::Ivf(ivf_params) = &stages[0] else {
         return Err(Error::Index {
             message: format!("Build Vector Index: invalid stages: {:?}", stages),
@@ -253,10 +383,11 @@ pub(crate) async fn build_vector_index(
 
     let (vector_type, element_type) = get_vector_type(dataset.schema(), column)?;
     if let DataType::List(_) = vector_type {
-        if params.metric_type != DistanceType::Cosine {
+        if params.metric_type == DistanceType::Hamming {
             return Err(Error::Index {
-                message: "Build Vector Index: multivector type supports only cosine distance"
-                    .to_string(),
+                message:
+                    "Build Vector Index: multivector type supports only l2, cosine and dot distance"
+                        .to_string(),
                 location: location!(),
             });
         }
@@ -278,7 +409,7 @@ pub(crate) async fn build_vector_index(
                     Some(()),
                     (),
                 )?
-                .build()
+                .build_phase(phase)
                 .await?;
             }
             DataType::UInt8 => {
@@ -292,7 +423,7 @@ pub(crate) async fn build_vector_index(
                     Some(()),
                     (),
                 )?
-                .build()
+                .build_phase(phase)
                 .await?;
             }
             _ => {
@@ -335,9 +466,38 @@ pub(crate) async fn build_vector_index(
                     Some(pq_params.clone()),
                     (),
                 )?
-                .build()
+                .build_phase(phase)
+                .await?;
+            }
+        }
+    } else if is_ivf_rq(stages) {
+        let StageParams::RQ(rq_params) = &stages[1] else {
+            unreachable!()
+        };
+        match element_type {
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+                IvfIndexBuilder::<FlatIndex, RabitQuantizer>::new(
+                    dataset.clone(),
+                    column.to_owned(),
+                    dataset.indices_dir().child(uuid),
+                    params.metric_type,
+                    Box::new(shuffler),
+                    Some(ivf_params.clone()),
+                    Some(rq_params.clone()),
+                    (),
+                )?
+                .build_phase(phase)
                 .await?;
             }
+            _ => {
+                return Err(Error::Index {
+                    message: format!(
+                        "Build Vector Index: IVF_RQ does not support {} vectors",
+                        element_type
+                    ),
+                    location: location!(),
+                });
+            }
         }
     } else if is_ivf_hnsw(stages) {
         let len = stages.len();
@@ -362,7 +522,7 @@ pub(crate) async fn build_vector_index(
                         Some(pq_params.clone()),
                         hnsw_params.clone(),
                     )?
-                    .build()
+                    .build_phase(phase)
                     .await?;
                 }
                 StageParams::SQ(sq_params) => {
@@ -376,7 +536,7 @@ pub(crate) async fn build_vector_index(
                         Some(sq_params.clone()),
                         hnsw_params.clone(),
                     )?
-                    .build()
+                    .build_phase(phase)
                     .await?;
                 }
                 _ => {
@@ -426,6 +586,17 @@ pub(crate) async fn remap_vector_index(
             vec![],
         )
         .await?;
+    } else if let Some(diskann_index) = old_index.as_any().downcast_ref::<DiskANNIndex>() {
+        diskann::remap_diskann_index(
+            dataset.as_ref(),
+            column,
+            &old_uuid.to_string(),
+            &new_uuid.to_string(),
+            &old_metadata.name,
+            diskann_index,
+            mapping,
+        )
+        .await?;
     } else {
         // it's v3 index
         remap_index_file_v3(
@@ -491,11 +662,27 @@ pub(crate) async fn open_vector_index(
                 let pq = ProductQuantizer::from_proto(pq_proto, metric_type)?;
                 last_stage = Some(Arc::new(PQIndex::new(pq, metric_type)));
             }
-            Some(Stage::Diskann(_)) => {
-                return Err(Error::Index {
-                    message: "DiskANN support is removed from Lance.".to_string(),
-                    location: location!(),
-                });
+            Some(Stage::Diskann(diskann_pb)) => {
+                let Some(pq) = last_stage
+                    .as_ref()
+                    .and_then(|stage| stage.as_any().downcast_ref::<PQIndex>())
+                else {
+                    return Err(Error::Index {
+                        message: format!("Invalid vector index stages: {:?}", vec_idx.stages),
+                        location: location!(),
+                    });
+                };
+                last_stage = Some(
+                    diskann::open_diskann_index(
+                        dataset.as_ref(),
+                        uuid,
+                        diskann_pb,
+                        pq.pq.clone(),
+                        metric_type,
+                        reader.clone(),
+                    )
+                    .await?,
+                );
             }
             _ => {}
         }
@@ -23,7 +23,7 @@ use lance_index::vector::pq::storage::transpose;
 use lance_index::vector::quantizer::{
     QuantizationMetadata, QuantizationType, QuantizerBuildParams,
 };
-use lance_index::vector::storage::STORAGE_METADATA_KEY;
+use lance_index::vector::storage::{IvfQuantizationStorage, STORAGE_METADATA_KEY};
 use lance_index::vector::utils::is_finite;
 use lance_index::vector::v3::shuffler::IvfShufflerReader;
 use lance_index::vector::v3::subindex::SubIndexType;
@@ -34,12 +34,12 @@ use lance_index::{
         ivf::{storage::IVF_METADATA_KEY, IvfBuildParams},
         quantizer::Quantization,
         storage::{StorageBuilder, VectorStore},
-        transform::Transformer,
+        transform::{PoolMultivectors, Transformer},
         v3::{
             shuffler::{ShuffleReader, Shuffler},
             subindex::IvfSubIndex,
         },
-        DISTANCE_TYPE_KEY,
+        DISTANCE_TYPE_KEY, TOKEN_POOL_FACTOR_KEY,
     },
     INDEX_AUXILIARY_FILE_NAME, INDEX_FILE_NAME,
 };
@@ -52,6 +52,7 @@ use lance_io::{
     ReadBatchParams,
 };
 use lance_linalg::distance::DistanceType;
+use lance_table::format::Fragment;
 use log::info;
 use object_store::path::Path;
 use prost::Message;
@@ -66,6 +67,30 @@ use crate::Dataset;
 use super::utils::{self, get_vector_type};
 use super::v2::IVFIndex;
 
+/// The file to persist the trained IVF model and quantizer,
+/// so that the partitions of the index can be built by independent workers.
+pub const IVF_TRAINING_FILE_NAME: &str = "training.idx";
+
+/// The phases of building an IVF index.
+pub(crate) enum IvfBuildPhase {
+    /// Build the whole index in this process.
+    Full,
+    /// Train the IVF model and quantizer, and persist them to the index directory.
+    Train,
+    /// Build a partial index over the given fragments,
+    /// with the IVF model and quantizer persisted in the training file.
+    Partial {
+        training_file: Path,
+        fragments: Vec<Fragment>,
+    },
+    /// Merge the partial indices,
+    /// which are built with the IVF model and quantizer persisted in the training file.
+    Merge {
+        training_file: Path,
+        partial_indices: Vec<Arc<dyn VectorIndex>>,
+    },
+}
+
 // Builder for IVF index
 // The builder will train the IVF model and quantizer, shuffle the dataset, and build the sub index
 // for each partition.
@@ -84,6 +109,10 @@ pub struct IvfIndexBuilder<S: IvfSubIndex, Q: Quantization> {
     ivf_params: Option<IvfBuildParams>,
     quantizer_params: Option<Q::BuildParams>,
     sub_index_params: Option<S::BuildParams>,
+    // pool the multivectors by this factor before indexing them
+    token_pool_factor: Option<usize>,
+    // index only these fragments if set, otherwise the whole dataset
+    fragments: Option<Vec<Fragment>>,
     _temp_dir: TempDir, // store this for keeping the temp dir alive and clean up after build
     temp_dir: Path,
 
@@ -109,6 +138,15 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
         quantizer_params: Option<Q::BuildParams>,
         sub_index_params: S::BuildParams,
     ) -> Result<Self> {
+        let token_pool_factor = ivf_params
+            .as_ref()
+            .and_then(|params| params.token_pool_factor);
+        if token_pool_factor == Some(0) {
+            return Err(Error::invalid_input(
+                "token_pool_factor must be greater than 0",
+                location!(),
+            ));
+        }
         let temp_dir = tempdir()?;
         let temp_dir_path = Path::from_filesystem_path(temp_dir.path())?;
         Ok(Self {
@@ -122,6 +160,8 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
             ivf_params,
             quantizer_params,
             sub_index_params: Some(sub_index_params),
+            token_pool_factor,
+            fragments: None,
             _temp_dir: temp_dir,
             temp_dir: temp_dir_path,
             // fields will be set during build
@@ -181,6 +221,8 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
             ivf_params: None,
             quantizer_params: None,
             sub_index_params: None,
+            token_pool_factor: ivf_index.token_pool_factor(),
+            fragments: None,
             _temp_dir: temp_dir,
             temp_dir: temp_dir_path,
             ivf: Some(ivf_index.ivf_model().clone()),
@@ -217,6 +259,142 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
         Ok(())
     }
 
+    pub(crate) async fn build_phase(&mut self, phase: IvfBuildPhase) -> Result<()> {
+        match phase {
+            IvfBuildPhase::Full => self.build().await,
+            IvfBuildPhase::Train => self.train().await,
+            IvfBuildPhase::Partial {
+                training_file,
+                fragments,
+            } => {
+                self.load_training(&training_file).await?;
+                self.with_fragments(fragments).build().await
+            }
+            IvfBuildPhase::Merge {
+                training_file,
+                partial_indices,
+            } => {
+                self.load_training(&training_file).await?;
+                self.with_existing_indices(partial_indices).merge().await
+            }
+        }
+    }
+
+    // train the IVF model & quantizer, and write them to the training file in the index directory,
+    // the training file can be loaded by `load_training` to build the partial indices.
+    pub async fn train(&mut self) -> Result<()> {
+        self.with_ivf(self.load_or_build_ivf().await?);
+        self.with_quantizer(self.load_or_build_quantizer().await?);
+
+        let ivf = self.ivf.as_ref().unwrap();
+        let quantizer = self.quantizer.as_ref().unwrap();
+        let schema = arrow_schema::Schema::new(vec![ROW_ID_FIELD.clone()]);
+        let mut writer = FileWriter::try_new(
+            self.store
+                .create(&self.index_dir.child(IVF_TRAINING_FILE_NAME))
+                .await?,
+            (&schema).try_into()?,
+            Default::default(),
+        )?;
+        writer.add_schema_metadata(DISTANCE_TYPE_KEY, self.distance_type.to_string());
+        let ivf_pb = pb::Ivf::try_from(&IvfModel::new(ivf.centroids.clone().unwrap(), None))?;
+        let ivf_buffer_pos = writer
+            .add_global_buffer(ivf_pb.encode_to_vec().into())
+            .await?;
+        writer.add_schema_metadata(IVF_METADATA_KEY, ivf_buffer_pos.to_string());
+        let quantizer_metadata = vec![quantizer
+            .metadata(Some(QuantizationMetadata {
+                codebook_position: Some(0),
+                codebook: None,
+                transposed: true,
+            }))?
+            .to_string()];
+        writer.add_schema_metadata(
+            STORAGE_METADATA_KEY,
+            serde_json::to_string(&quantizer_metadata)?,
+        );
+        if let Some(token_pool_factor) = self.token_pool_factor {
+            writer.add_schema_metadata(TOKEN_POOL_FACTOR_KEY, token_pool_factor.to_string());
+        }
+        writer.finish().await?;
+        Ok(())
+    }
+
+    // load the IVF model & quantizer from the training file written by `train`
+    pub async fn load_training(&mut self, training_file: &Path) -> Result<&mut Self> {
+        let scheduler = ScanScheduler::new(
+            Arc::new(self.store.clone()),
+            SchedulerConfig::max_bandwidth(&self.store),
+        );
+        let reader = FileReader::try_open(
+            scheduler
+                .open_file(training_file, &CachedFileSize::unknown())
+                .await?,
+            None,
+            Arc::<DecoderPlugins>::default(),
+            &FileMetadataCache::no_cache(),
+            FileReaderOptions::default(),
+        )
+        .await?;
+        let token_pool_factor = reader
+            .schema()
+            .metadata
+            .get(TOKEN_POOL_FACTOR_KEY)
+            .map(|factor| factor.parse())
+            .transpose()
+            .map_err(|e| Error::Index {
+                message: format!("Failed to decode token pool factor: {}", e),
+                location: location!(),
+            })?;
+        let storage = IvfQuantizationStorage::try_new(reader).await?;
+        if storage.distance_type() != self.distance_type {
+            return Err(Error::invalid_input(
+                format!(
+                    "the index is trained with distance type {}, but {} is requested",
+                    storage.distance_type(),
+                    self.distance_type
+                ),
+                location!(),
+            ));
+        }
+        self.token_pool_factor = token_pool_factor;
+        self.ivf = Some(storage.ivf_model().clone());
+        self.quantizer = Some(storage.quantizer::<Q>()?.try_into()?);
+        Ok(self)
+    }
+
+    // index only the given fragments instead of the whole dataset
+    pub fn with_fragments(&mut self, fragments: Vec<Fragment>) -> &mut Self {
+        self.fragments = Some(fragments);
+        self
+    }
+
+    // merge the existing indices into one index, without indexing any new data
+    pub async fn merge(&mut self) -> Result<()> {
+        let num_partitions = self
+            .ivf
+            .as_ref()
+            .ok_or(Error::invalid_input(
+                "IVF not set before merging indices",
+                location!(),
+            ))?
+            .num_partitions();
+        self.shuffle_reader = Some(Arc::new(IvfShufflerReader::new(
+            Arc::new(self.store.clone()),
+            self.temp_dir.clone(),
+            vec![0; num_partitions],
+            0.0,
+        )));
+        self.build_partitions().await?;
+        let loss = self
+            .existing_indices
+            .iter()
+            .filter_map(|index| index.ivf_model().loss)
+            .sum::<f64>();
+        self.ivf.as_mut().unwrap().loss = Some(loss);
+        self.merge_partitions().await
+    }
+
     pub async fn remap(&mut self, mapping: &HashMap<u64, Option<u64>>) -> Result<()> {
         debug_assert_eq!(self.existing_indices.len(), 1);
         let ivf_index = self.existing_indices[0]
@@ -294,6 +472,11 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
         self
     }
 
+    pub fn with_token_pool_factor(&mut self, token_pool_factor: Option<usize>) -> &mut Self {
+        self.token_pool_factor = token_pool_factor;
+        self
+    }
+
     pub fn retrain(&mut self, retrain: bool) -> &mut Self {
         self.retrain = retrain;
         self
@@ -424,6 +607,9 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
             .batch_readahead(get_num_compute_intensive_cpus())
             .project(&[self.column.as_str()])?
             .with_row_id();
+        if let Some(fragments) = &self.fragments {
+            builder.with_fragments(fragments.clone());
+        }
 
         let (vector_type, _) = get_vector_type(dataset.schema(), &self.column)?;
         let is_multivector = matches!(vector_type, datatypes::DataType::List(_));
@@ -469,10 +655,20 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
                 None,
             )?,
         );
+        let pooling = self
+            .token_pool_factor
+            .map(|pool_factor| Arc::new(PoolMultivectors::new(&self.column, pool_factor)));
         let mut transformed_stream = Box::pin(
             data.map(move |batch| {
                 let ivf_transformer = transformer.clone();
-                tokio::spawn(async move { ivf_transformer.transform(&batch?) })
+                let pooling = pooling.clone();
+                tokio::spawn(async move {
+                    let mut batch = batch?;
+                    if let Some(pooling) = pooling {
+                        batch = pooling.transform(&batch)?;
+                    }
+                    ivf_transformer.transform(&batch)
+                })
             })
             .buffered(get_num_compute_intensive_cpus())
             .map(|x| x.unwrap())
@@ -839,6 +1035,9 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> IvfIndexBuilder<S, Q>
             S::metadata_key(),
             serde_json::to_string(&partition_index_metadata)?,
         );
+        if let Some(token_pool_factor) = self.token_pool_factor {
+            index_writer.add_schema_metadata(TOKEN_POOL_FACTOR_KEY, token_pool_factor.to_string());
+        }
 
         storage_writer.finish().await?;
         index_writer.finish().await?;
@@ -0,0 +1,1171 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! DiskANN index.
+//!
+//! The index directory has two files:
+//!
+//! - `index.idx`: the PQ codes and the row ids of the vectors, followed by the
+//!   index proto whose stages are `[DiskAnn, Pq]`.
+//! - the graph file named in the `DiskAnn` stage, with the full vectors and the
+//!   neighbors of the nodes, read on demand through the I/O scheduler.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow::datatypes::{Float32Type, UInt64Type, UInt8Type};
+use arrow_array::{
+    cast::AsArray, Array, ArrayRef, FixedSizeListArray, Float32Array, RecordBatch, UInt64Array,
+    UInt8Array,
+};
+use arrow_schema::{DataType, Field, Schema};
+use futures::{StreamExt, TryStreamExt};
+use lance_arrow::FixedSizeListArrayExt;
+use lance_core::utils::tokio::{get_num_compute_intensive_cpus, spawn_cpu};
+use lance_core::{Error, Result, ROW_ID, ROW_ID_FIELD};
+use lance_file::format::MAGIC;
+use lance_index::optimize::OptimizeOptions;
+use lance_index::vector::diskann::{
+    build_vamana_graph, robust_prune, BlockLayout, DiskANNIndex, DiskANNParams,
+    DISKANN_GRAPH_FILE_NAME, DISKANN_SPEC_VERSION,
+};
+use lance_index::vector::graph::OrderedNode;
+use lance_index::vector::kmeans::train_kmeans;
+use lance_index::vector::pq::storage::{transpose, ProductQuantizationStorage};
+use lance_index::vector::pq::PQBuildParams;
+use lance_index::vector::storage::VectorStore;
+use lance_index::vector::{pq::ProductQuantizer, quantizer::Quantization, VectorIndex};
+use lance_index::{vector::PQ_CODE_COLUMN, Index, INDEX_FILE_NAME};
+use lance_io::encodings::plain::PlainEncoder;
+use lance_io::object_store::ObjectStore;
+use lance_io::object_writer::ObjectWriter;
+use lance_io::scheduler::{ScanScheduler, SchedulerConfig};
+use lance_io::stream::RecordBatchStream;
+use lance_io::traits::{Reader, WriteExt};
+use lance_io::utils::{read_fixed_stride_array, CachedFileSize};
+use lance_linalg::distance::{l2_distance_batch, DistanceType, MetricType};
+use lance_linalg::kernels::normalize_fsl;
+use log::info;
+use object_store::path::Path;
+use snafu::location;
+use tempfile::{tempdir, TempDir};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use super::pq::build_pq_model;
+use super::utils::{get_vector_dim, maybe_sample_training_data};
+use crate::index::pb;
+use crate::Dataset;
+
+/// Size of the buffer used to write the graph file.
+const GRAPH_WRITE_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// Number of nodes read at a time from the spilled vectors or the graph file.
+const READ_BATCH_SIZE: usize = 64 * 1024;
+
+/// Number of shards each vector is assigned to, when building over shards.
+const SHARD_OVERLAP: usize = 2;
+
+/// Number of sampled vectors per shard to train the centroids of the shards.
+const SHARD_SAMPLE_RATE: usize = 256;
+
+/// Number of bytes of the PQ code of a vector.
+fn code_width(pq: &ProductQuantizer) -> usize {
+    pq.num_sub_vectors * pq.num_bits as usize / 8
+}
+
+/// Normalize the vectors for the cosine distance.
+fn normalize(vectors: &dyn Array, metric_type: MetricType) -> Result<FixedSizeListArray> {
+    let Some(vectors) = vectors.as_fixed_size_list_opt() else {
+        return Err(Error::Index {
+            message: format!(
+                "DiskANN index requires a FixedSizeList column, got {}",
+                vectors.data_type()
+            ),
+            location: location!(),
+        });
+    };
+    if metric_type == MetricType::Cosine {
+        normalize_fsl(vectors)
+    } else {
+        Ok(vectors.clone())
+    }
+}
+
+/// Cast the vectors to `Float32`, the type of the vectors in the graph file.
+fn to_float32(vectors: &FixedSizeListArray) -> Result<FixedSizeListArray> {
+    Ok(FixedSizeListArray::try_new_from_values(
+        arrow::compute::cast(vectors.values(), &DataType::Float32)?,
+        vectors.value_length(),
+    )?)
+}
+
+/// PQ codes of the vectors, in row-major order.
+fn quantize(pq: &ProductQuantizer, vectors: &FixedSizeListArray) -> Result<UInt8Array> {
+    let codes = pq.quantize(vectors)?;
+    Ok(codes
+        .as_fixed_size_list()
+        .values()
+        .as_primitive::<UInt8Type>()
+        .clone())
+}
+
+/// PQ storage of the nodes, from their row-major `codes`.
+fn pq_storage(
+    pq: &ProductQuantizer,
+    metric_type: MetricType,
+    codes: UInt8Array,
+    row_ids: ArrayRef,
+) -> Result<ProductQuantizationStorage> {
+    let codes = FixedSizeListArray::try_new_from_values(codes, code_width(pq) as i32)?;
+    let batch = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![
+            ROW_ID_FIELD.clone(),
+            Field::new(PQ_CODE_COLUMN, codes.data_type().clone(), true),
+        ])),
+        vec![row_ids, Arc::new(codes)],
+    )?;
+    ProductQuantizationStorage::new(
+        pq.codebook.clone(),
+        batch,
+        pq.num_bits,
+        pq.num_sub_vectors,
+        pq.dimension,
+        metric_type,
+        false,
+    )
+}
+
+/// PQ codes of the nodes of `index`, in row-major order.
+fn row_major_codes(index: &DiskANNIndex) -> UInt8Array {
+    let batch = index.storage().batch();
+    transpose(
+        batch[PQ_CODE_COLUMN]
+            .as_fixed_size_list()
+            .values()
+            .as_primitive(),
+        code_width(index.pq()),
+        batch.num_rows(),
+    )
+}
+
+/// Writes the full vectors to a local file while building the index.
+struct SpillWriter {
+    tmpdir: TempDir,
+    store: ObjectStore,
+    path: Path,
+    writer: ObjectWriter,
+    dim: usize,
+    len: usize,
+}
+
+impl SpillWriter {
+    async fn try_new(dim: usize) -> Result<Self> {
+        let tmpdir = tempdir()?;
+        let store = ObjectStore::local();
+        let path = Path::from_filesystem_path(tmpdir.path())?.child("vectors");
+        let writer = store.create(&path).await?;
+        Ok(Self {
+            tmpdir,
+            store,
+            path,
+            writer,
+            dim,
+            len: 0,
+        })
+    }
+
+    /// Append `Float32` vectors.
+    async fn write(&mut self, vectors: &FixedSizeListArray) -> Result<()> {
+        let values = vectors.values().as_primitive::<Float32Type>().values();
+        let buf = values
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        self.writer.write_all(&buf).await?;
+        self.len += vectors.len();
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<SpilledVectors> {
+        self.writer.shutdown().await?;
+        let reader = self.store.open(&self.path).await?;
+        Ok(SpilledVectors {
+            _tmpdir: self.tmpdir,
+            reader,
+            dim: self.dim,
+            len: self.len,
+        })
+    }
+}
+
+/// Full vectors of the nodes spilled to a local file, in the order of the
+/// node ids, so that only a shard of them is in memory at a time.
+struct SpilledVectors {
+    _tmpdir: TempDir,
+    reader: Box<dyn Reader>,
+    dim: usize,
+    len: usize,
+}
+
+impl SpilledVectors {
+    /// Ranges of nodes to read the vectors a batch at a time.
+    fn batches(&self) -> impl Iterator<Item = Range<usize>> {
+        let len = self.len;
+        (0..len)
+            .step_by(READ_BATCH_SIZE)
+            .map(move |start| start..(start + READ_BATCH_SIZE).min(len))
+    }
+
+    /// Read the flattened vectors of the nodes in `nodes`.
+    async fn read(&self, nodes: Range<usize>) -> Result<Vec<f32>> {
+        let width = self.dim * std::mem::size_of::<f32>();
+        let bytes = self
+            .reader
+            .get_range(nodes.start * width..nodes.end * width)
+            .await?;
+        Ok(bytes
+            .chunks_exact(std::mem::size_of::<f32>())
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect())
+    }
+
+    /// Read the vectors of `nodes`, which must be sorted, in one pass over
+    /// the file.
+    async fn read_nodes(&self, nodes: &[u32]) -> Result<FixedSizeListArray> {
+        let mut values = Vec::with_capacity(nodes.len() * self.dim);
+        let mut nodes = nodes.iter().peekable();
+        for batch in self.batches() {
+            if !nodes.peek().is_some_and(|&&id| (id as usize) < batch.end) {
+                continue;
+            }
+            let batch_values = self.read(batch.clone()).await?;
+            while let Some(&&id) = nodes.peek() {
+                if id as usize >= batch.end {
+                    break;
+                }
+                let offset = (id as usize - batch.start) * self.dim;
+                values.extend_from_slice(&batch_values[offset..offset + self.dim]);
+                nodes.next();
+            }
+        }
+        Ok(FixedSizeListArray::try_new_from_values(
+            Float32Array::from(values),
+            self.dim as i32,
+        )?)
+    }
+}
+
+/// Writes the node blocks to the graph file, in the order of the node ids.
+struct GraphWriter {
+    writer: ObjectWriter,
+    layout: BlockLayout,
+    buf: Vec<u8>,
+}
+
+impl GraphWriter {
+    async fn try_new(dataset: &Dataset, index_dir: &Path, layout: BlockLayout) -> Result<Self> {
+        let writer = dataset
+            .object_store()
+            .create(&index_dir.child(DISKANN_GRAPH_FILE_NAME))
+            .await?;
+        Ok(Self {
+            writer,
+            layout,
+            buf: Vec::with_capacity(GRAPH_WRITE_BUFFER_SIZE + layout.block_size()),
+        })
+    }
+
+    async fn write(&mut self, vector: &[f32], neighbors: &[u32]) -> Result<()> {
+        self.layout.encode(vector, neighbors, &mut self.buf);
+        if self.buf.len() >= GRAPH_WRITE_BUFFER_SIZE {
+            self.writer.write_all(&self.buf).await?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<()> {
+        self.writer.write_all(&self.buf).await?;
+        self.writer.shutdown().await?;
+        Ok(())
+    }
+}
+
+/// Build a DiskANN index over `column`.
+///
+/// The PQ model is trained on a sample of the vectors.  The vectors are then
+/// streamed from the dataset: their PQ codes and row ids are kept in memory,
+/// while the full vectors are spilled to a local file from which the graph is
+/// built, one shard at a time if there are more than `params.shard_size`.
+pub(super) async fn build_diskann_index(
+    dataset: &Dataset,
+    column: &str,
+    name: &str,
+    uuid: &str,
+    metric_type: MetricType,
+    params: &DiskANNParams,
+    pq_params: &PQBuildParams,
+) -> Result<()> {
+    if metric_type == MetricType::Hamming {
+        return Err(Error::NotSupported {
+            source: "DiskANN index does not support hamming distance".into(),
+            location: location!(),
+        });
+    }
+    let dim = get_vector_dim(dataset.schema(), column)?;
+    let pq = build_pq_model(dataset, column, dim, metric_type, pq_params, None).await?;
+
+    let mut scanner = dataset.scan();
+    scanner.project(&[column])?.with_row_id();
+    scanner.filter_expr(datafusion_expr::col(column).is_not_null());
+    let mut stream = scanner.try_into_stream().await?;
+
+    let mut spill = SpillWriter::try_new(dim).await?;
+    let mut codes = Vec::new();
+    let mut row_ids = Vec::new();
+    while let Some(batch) = stream.try_next().await? {
+        let vectors = normalize(batch[column].as_ref(), metric_type)?;
+        codes.extend_from_slice(quantize(&pq, &vectors)?.values());
+        row_ids.extend_from_slice(batch[ROW_ID].as_primitive::<UInt64Type>().values());
+        spill.write(&to_float32(&vectors)?).await?;
+    }
+    let vectors = spill.finish().await?;
+
+    info!(
+        "Building DiskANN graph over {} vectors: {:?}",
+        vectors.len, params
+    );
+    let start = std::time::Instant::now();
+    let (neighbors, entries) = build_graph(dataset, column, &vectors, metric_type, params).await?;
+    info!("Built DiskANN graph in {}s", start.elapsed().as_secs_f32());
+
+    let index_dir = dataset.indices_dir().child(uuid);
+    let mut writer =
+        GraphWriter::try_new(dataset, &index_dir, BlockLayout::new(dim, params.r)).await?;
+    for nodes in vectors.batches() {
+        let values = vectors.read(nodes.clone()).await?;
+        for (vector, id) in values.chunks_exact(dim).zip(nodes) {
+            writer.write(vector, &neighbors[id]).await?;
+        }
+    }
+    writer.finish().await?;
+
+    write_index_file(
+        dataset,
+        &index_dir,
+        column,
+        name,
+        metric_type,
+        params,
+        &entries,
+        &pq,
+        &UInt8Array::from(codes),
+        &UInt64Array::from(row_ids),
+    )
+    .await
+}
+
+/// Build the neighbors of the nodes, and the entry points of the searches.
+///
+/// Up to `params.shard_size` vectors, the graph is built in memory over all of
+/// them.  Otherwise, as in the DiskANN paper, the vectors are clustered into
+/// overlapping shards, a graph of degree `r / SHARD_OVERLAP` is built over
+/// every shard, and the graph is the union of the graphs of the shards, with
+/// their entry points.
+async fn build_graph(
+    dataset: &Dataset,
+    column: &str,
+    vectors: &SpilledVectors,
+    metric_type: MetricType,
+    params: &DiskANNParams,
+) -> Result<(Vec<Vec<u32>>, Vec<u32>)> {
+    if vectors.len == 0 {
+        return Ok((vec![], vec![]));
+    }
+    if vectors.len <= params.shard_size {
+        let all = FixedSizeListArray::try_new_from_values(
+            Float32Array::from(vectors.read(0..vectors.len).await?),
+            vectors.dim as i32,
+        )?;
+        let params = params.clone();
+        let graph = spawn_cpu(move || build_vamana_graph(&all, metric_type, &params)).await?;
+        return Ok((graph.neighbors, vec![graph.entry_point]));
+    }
+
+    let shards = assign_shards(dataset, column, vectors, metric_type, params).await?;
+    let shard_params = params.clone().r((params.r / SHARD_OVERLAP).max(1));
+    let mut neighbors = vec![Vec::new(); vectors.len];
+    let mut entries = Vec::with_capacity(shards.len());
+    for (i, shard) in shards.into_iter().enumerate() {
+        if shard.is_empty() {
+            continue;
+        }
+        info!(
+            "Building DiskANN graph of shard {} over {} vectors",
+            i,
+            shard.len()
+        );
+        let shard_vectors = vectors.read_nodes(&shard).await?;
+        let shard_params = shard_params.clone();
+        let (graph, shard) = spawn_cpu(move || {
+            let graph = build_vamana_graph(&shard_vectors, metric_type, &shard_params)?;
+            Ok((graph, shard))
+        })
+        .await?;
+
+        for (&id, shard_neighbors) in shard.iter().zip(graph.neighbors) {
+            let node_neighbors: &mut Vec<u32> = &mut neighbors[id as usize];
+            for n in shard_neighbors {
+                let n = shard[n as usize];
+                if !node_neighbors.contains(&n) {
+                    node_neighbors.push(n);
+                }
+            }
+            node_neighbors.truncate(params.r);
+        }
+        entries.push(shard[graph.entry_point as usize]);
+    }
+    Ok((neighbors, entries))
+}
+
+/// Cluster the vectors into shards of about `params.shard_size` vectors, each
+/// vector being assigned to its `SHARD_OVERLAP` closest shards.
+///
+/// Returns the sorted node ids of every shard.
+async fn assign_shards(
+    dataset: &Dataset,
+    column: &str,
+    vectors: &SpilledVectors,
+    metric_type: MetricType,
+    params: &DiskANNParams,
+) -> Result<Vec<Vec<u32>>> {
+    const MAX_ITERS: u32 = 50;
+    const REDOS: usize = 1;
+
+    let dim = vectors.dim;
+    let num_shards = (vectors.len * SHARD_OVERLAP).div_ceil(params.shard_size);
+    let sample =
+        maybe_sample_training_data(dataset, column, num_shards * SHARD_SAMPLE_RATE).await?;
+    let sample = to_float32(&normalize(&sample, metric_type)?)?;
+    // The vectors are normalized for cosine, so L2 clusters them the same way
+    let centroids = spawn_cpu(move || {
+        let kmeans = train_kmeans::<Float32Type>(
+            None,
+            sample.values().as_primitive(),
+            dim,
+            num_shards,
+            MAX_ITERS,
+            REDOS,
+            DistanceType::L2,
+            SHARD_SAMPLE_RATE,
+        )?;
+        Ok(kmeans
+            .centroids
+            .as_primitive::<Float32Type>()
+            .values()
+            .to_vec())
+    })
+    .await?;
+
+    let mut shards = vec![Vec::new(); num_shards];
+    for nodes in vectors.batches() {
+        let values = vectors.read(nodes.clone()).await?;
+        for (vector, id) in values.chunks_exact(dim).zip(nodes) {
+            let mut dists = l2_distance_batch(vector, &centroids, dim)
+                .enumerate()
+                .collect::<Vec<_>>();
+            dists.sort_unstable_by(|a, b| a.1.total_cmp(&b.1));
+            for (shard, _) in dists.into_iter().take(SHARD_OVERLAP) {
+                shards[shard].push(id as u32);
+            }
+        }
+    }
+    Ok(shards)
+}
+
+/// Write `index.idx`: the PQ codes, the row ids and the index proto.
+#[allow(clippy::too_many_arguments)]
+async fn write_index_file(
+    dataset: &Dataset,
+    index_dir: &Path,
+    column: &str,
+    name: &str,
+    metric_type: MetricType,
+    params: &DiskANNParams,
+    entries: &[u32],
+    pq: &ProductQuantizer,
+    codes: &UInt8Array,
+    row_ids: &UInt64Array,
+) -> Result<()> {
+    let mut writer = dataset
+        .object_store()
+        .create(&index_dir.child(INDEX_FILE_NAME))
+        .await?;
+    PlainEncoder::write(&mut writer, &[codes]).await?;
+    PlainEncoder::write(&mut writer, &[row_ids]).await?;
+
+    let stages = vec![
+        pb::VectorIndexStage {
+            stage: Some(pb::vector_index_stage::Stage::Diskann(pb::DiskAnn {
+                spec: DISKANN_SPEC_VERSION,
+                filename: DISKANN_GRAPH_FILE_NAME.to_string(),
+                r: params.r as u32,
+                alpha: params.alpha,
+                l: params.l as u32,
+                entries: entries.iter().map(|&e| e as u64).collect(),
+            })),
+        },
+        pb::VectorIndexStage {
+            stage: Some(pb::vector_index_stage::Stage::Pq(pb::Pq::try_from(pq)?)),
+        },
+    ];
+    let metadata = pb::Index {
+        name: name.to_string(),
+        columns: vec![column.to_string()],
+        dataset_version: dataset.version().version,
+        index_type: pb::IndexType::Vector.into(),
+        implementation: Some(pb::index::Implementation::VectorIndex(pb::VectorIndex {
+            spec_version: 1,
+            dimension: pq.dimension as u32,
+            stages,
+            metric_type: pb::VectorMetricType::from(metric_type).into(),
+        })),
+    };
+    let pos = writer.write_protobuf(&metadata).await?;
+    writer.write_magics(pos, 0, 1, MAGIC).await?;
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// Open a DiskANN index from the `DiskAnn` stage of its proto.
+pub(super) async fn open_diskann_index(
+    dataset: &Dataset,
+    uuid: &str,
+    stage: &pb::DiskAnn,
+    pq: ProductQuantizer,
+    metric_type: MetricType,
+    reader: Arc<dyn Reader>,
+) -> Result<Arc<dyn VectorIndex>> {
+    if stage.spec != DISKANN_SPEC_VERSION {
+        return Err(Error::Index {
+            message: format!("Unsupported DiskANN graph spec version: {}", stage.spec),
+            location: location!(),
+        });
+    }
+    let params = DiskANNParams::new(stage.r as usize, stage.alpha, stage.l as usize);
+
+    let scheduler = ScanScheduler::new(
+        dataset.object_store.clone(),
+        SchedulerConfig::max_bandwidth(&dataset.object_store),
+    );
+    let graph_path = dataset
+        .indices_dir()
+        .child(uuid)
+        .child(stage.filename.as_str());
+    let graph = scheduler
+        .open_file(&graph_path, &CachedFileSize::unknown())
+        .await?;
+    let graph_size = graph.reader().size().await?;
+    let block_size = BlockLayout::new(pq.dimension, params.r).block_size();
+    if graph_size % block_size != 0 {
+        return Err(Error::Index {
+            message: format!(
+                "DiskANN graph file {} has {} bytes, not a multiple of the block size {}",
+                graph_path, graph_size, block_size
+            ),
+            location: location!(),
+        });
+    }
+    let num_nodes = graph_size / block_size;
+
+    let code_width = code_width(&pq);
+    let codes = read_fixed_stride_array(
+        reader.as_ref(),
+        &DataType::UInt8,
+        0,
+        num_nodes * code_width,
+        ..,
+    )
+    .await?;
+    let row_ids = read_fixed_stride_array(
+        reader.as_ref(),
+        &DataType::UInt64,
+        num_nodes * code_width,
+        num_nodes,
+        ..,
+    )
+    .await?;
+    let storage = pq_storage(&pq, metric_type, codes.as_primitive().clone(), row_ids)?;
+
+    let entries = stage.entries.iter().map(|&e| e as u32).collect();
+    Ok(Arc::new(DiskANNIndex::try_new(
+        params,
+        metric_type,
+        entries,
+        pq,
+        storage,
+        graph,
+    )?))
+}
+
+/// Write the index with its row ids remapped to the `new_uuid` directory.
+///
+/// The graph file is copied as is, the nodes of deleted rows stay in the graph.
+pub(super) async fn remap_diskann_index(
+    dataset: &Dataset,
+    column: &str,
+    old_uuid: &str,
+    new_uuid: &str,
+    name: &str,
+    index: &DiskANNIndex,
+    mapping: &HashMap<u64, Option<u64>>,
+) -> Result<()> {
+    let mut index = index.clone();
+    index.remap(mapping).await?;
+
+    let old_dir = dataset.indices_dir().child(old_uuid);
+    let new_dir = dataset.indices_dir().child(new_uuid);
+    dataset
+        .object_store()
+        .copy(
+            &old_dir.child(DISKANN_GRAPH_FILE_NAME),
+            &new_dir.child(DISKANN_GRAPH_FILE_NAME),
+        )
+        .await?;
+
+    write_index_file(
+        dataset,
+        &new_dir,
+        column,
+        name,
+        index.metric_type(),
+        index.params(),
+        index.entries(),
+        index.pq(),
+        &row_major_codes(&index),
+        index.storage().batch()[ROW_ID].as_primitive(),
+    )
+    .await
+}
+
+/// Optimize a DiskANN index by inserting the `unindexed` vectors into its
+/// graph, written under the `name` of the index.
+///
+/// The existing nodes keep their ids and the new nodes are appended after
+/// them.  A new node is linked to the nodes found by searching the existing
+/// graph for its vector, and to its neighbors in a graph built over the new
+/// vectors, which are held in memory.  The existing nodes gain the reverse
+/// edges, pruned with the PQ distances, while their blocks are copied to the
+/// new graph file.
+///
+/// With `options.retrain`, the index is rebuilt over all the vectors instead.
+///
+/// Returns the uuid of the new index.
+pub(crate) async fn optimize_diskann_index(
+    dataset: &Dataset,
+    unindexed: Option<impl RecordBatchStream + Unpin + 'static>,
+    vector_column: &str,
+    name: &str,
+    index: &Arc<dyn Index>,
+    options: &OptimizeOptions,
+) -> Result<Uuid> {
+    let index = index
+        .as_any()
+        .downcast_ref::<DiskANNIndex>()
+        .ok_or(Error::Index {
+            message: "optimizing DiskANN index: the index isn't DiskANN".to_string(),
+            location: location!(),
+        })?;
+    let metric_type = index.metric_type();
+    let params = index.params();
+    let pq = index.pq();
+    let new_uuid = Uuid::new_v4();
+
+    if options.retrain {
+        build_diskann_index(
+            dataset,
+            vector_column,
+            name,
+            &new_uuid.to_string(),
+            metric_type,
+            params,
+            &PQBuildParams::new(pq.num_sub_vectors, pq.num_bits as usize),
+        )
+        .await?;
+        return Ok(new_uuid);
+    }
+
+    let dim = pq.dimension;
+    let num_old = index.storage().len();
+    let mut codes = row_major_codes(index).values().to_vec();
+    let mut row_ids = index.storage().row_ids().copied().collect::<Vec<_>>();
+    let mut new_values = Vec::new();
+    if let Some(mut unindexed) = unindexed {
+        while let Some(batch) = unindexed.try_next().await? {
+            let vectors = normalize(batch[vector_column].as_ref(), metric_type)?;
+            codes.extend_from_slice(quantize(pq, &vectors)?.values());
+            row_ids.extend_from_slice(batch[ROW_ID].as_primitive::<UInt64Type>().values());
+            let vectors = to_float32(&vectors)?;
+            new_values.extend_from_slice(vectors.values().as_primitive::<Float32Type>().values());
+        }
+    }
+    let codes = UInt8Array::from(codes);
+    let row_ids = UInt64Array::from(row_ids);
+    let storage = pq_storage(pq, metric_type, codes.clone(), Arc::new(row_ids.clone()))?;
+
+    let new_vectors =
+        FixedSizeListArray::try_new_from_values(Float32Array::from(new_values), dim as i32)?;
+    let num_new = new_vectors.len();
+    info!(
+        "Inserting {} vectors into DiskANN graph of {} nodes",
+        num_new, num_old
+    );
+    let new_graph = {
+        let new_vectors = new_vectors.clone();
+        let params = params.clone();
+        spawn_cpu(move || build_vamana_graph(&new_vectors, metric_type, &params)).await?
+    };
+
+    // Search the existing graph for the neighbors of the new nodes
+    let old_candidates = futures::stream::iter(0..num_new)
+        .map(|i| {
+            let key = new_vectors.value(i).as_primitive::<Float32Type>().clone();
+            async move { index.search_nodes(&key, params.l).await }
+        })
+        .buffered(get_num_compute_intensive_cpus())
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let new_values = new_vectors.values().as_primitive::<Float32Type>().values();
+    let new_vector = |i: usize| &new_values[i * dim..(i + 1) * dim];
+    let dist_func = metric_type.func::<f32>();
+    let dist_between = |u: u32, v: u32| storage.dist_between(u, v);
+    let new_neighbors = old_candidates
+        .into_iter()
+        .zip(new_graph.neighbors.iter())
+        .enumerate()
+        .map(|(i, (mut candidates, new_graph_neighbors))| {
+            candidates.extend(new_graph_neighbors.iter().map(|&n| {
+                let dist = dist_func(new_vector(i), new_vector(n as usize));
+                OrderedNode::new((num_old + n as usize) as u32, dist.into())
+            }));
+            candidates.sort_unstable();
+            robust_prune(&candidates, params.alpha, params.r, dist_between)
+        })
+        .collect::<Vec<_>>();
+
+    // Add the reverse edges, and prune the nodes with too many neighbors
+    let mut reverse_edges: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (i, selected) in new_neighbors.iter().enumerate() {
+        for &n in selected.iter() {
+            reverse_edges
+                .entry(n)
+                .or_default()
+                .push((num_old + i) as u32);
+        }
+    }
+    let add_reverse_edges = |id: u32, mut selected: Vec<u32>| {
+        let Some(new_edges) = reverse_edges.get(&id) else {
+            return selected;
+        };
+        for &n in new_edges {
+            if !selected.contains(&n) {
+                selected.push(n);
+            }
+        }
+        if selected.len() > params.r {
+            let mut candidates = selected
+                .iter()
+                .map(|&n| OrderedNode::new(n, dist_between(id, n).into()))
+                .collect::<Vec<_>>();
+            candidates.sort_unstable();
+            selected = robust_prune(&candidates, params.alpha, params.r, dist_between);
+        }
+        selected
+    };
+
+    let index_dir = dataset.indices_dir().child(new_uuid.to_string());
+    let mut writer =
+        GraphWriter::try_new(dataset, &index_dir, BlockLayout::new(dim, params.r)).await?;
+    for start in (0..num_old).step_by(READ_BATCH_SIZE) {
+        let end = (start + READ_BATCH_SIZE).min(num_old);
+        let nodes = index.read_nodes(start as u32..end as u32).await?;
+        for (id, (vector, selected)) in (start..end).zip(nodes) {
+            writer
+                .write(&vector, &add_reverse_edges(id as u32, selected))
+                .await?;
+        }
+    }
+    for (i, selected) in new_neighbors.into_iter().enumerate() {
+        let id = (num_old + i) as u32;
+        writer
+            .write(new_vector(i), &add_reverse_edges(id, selected))
+            .await?;
+    }
+    writer.finish().await?;
+
+    let mut entries = index.entries().to_vec();
+    if entries.is_empty() && !new_graph.is_empty() {
+        entries.push((num_old + new_graph.entry_point as usize) as u32);
+    }
+    write_index_file(
+        dataset,
+        &index_dir,
+        vector_column,
+        name,
+        metric_type,
+        params,
+        &entries,
+        pq,
+        &codes,
+        &row_ids,
+    )
+    .await?;
+    Ok(new_uuid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    use arrow_array::{Float32Array, Int32Array, RecordBatchIterator};
+    use lance_index::metrics::NoOpMetricsCollector;
+    use lance_index::{optimize::OptimizeOptions, DatasetIndexExt, IndexType};
+    use lance_linalg::distance::DistanceType;
+    use lance_testing::datagen::generate_random_array;
+    use tempfile::tempdir;
+
+    use crate::dataset::optimize::{compact_files, CompactionOptions};
+    use crate::dataset::WriteParams;
+    use crate::index::vector::VectorIndexParams;
+    use crate::index::DatasetIndexInternalExt;
+
+    const DIM: usize = 32;
+
+    fn make_batch(start: i32, num_rows: usize) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new(
+                "vector",
+                DataType::FixedSizeList(
+                    Arc::new(Field::new("item", DataType::Float32, true)),
+                    DIM as i32,
+                ),
+                true,
+            ),
+        ]));
+        let vectors = FixedSizeListArray::try_new_from_values(
+            generate_random_array(num_rows * DIM),
+            DIM as i32,
+        )
+        .unwrap();
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from_iter_values(start..start + num_rows as i32)),
+                Arc::new(vectors),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn make_dataset(test_uri: &str, num_rows: usize) -> Dataset {
+        let batch = make_batch(0, num_rows);
+        let schema = batch.schema();
+        let reader = RecordBatchIterator::new(vec![Ok(batch)], schema);
+        let params = WriteParams {
+            max_rows_per_file: num_rows / 2,
+            ..Default::default()
+        };
+        Dataset::write(reader, test_uri, Some(params))
+            .await
+            .unwrap()
+    }
+
+    async fn create_index(dataset: &mut Dataset, distance_type: DistanceType) {
+        let params = VectorIndexParams::with_diskann_params(
+            distance_type,
+            DiskANNParams::new(16, 1.2, 40),
+            PQBuildParams::new(8, 8),
+        );
+        dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, true)
+            .await
+            .unwrap();
+    }
+
+    async fn search(
+        dataset: &Dataset,
+        key: &Float32Array,
+        k: usize,
+        filter: Option<&str>,
+        use_index: bool,
+    ) -> Vec<i32> {
+        let mut scan = dataset.scan();
+        scan.nearest("vector", key, k).unwrap().use_index(use_index);
+        if let Some(filter) = filter {
+            scan.filter(filter).unwrap().prefilter(true);
+        }
+        let batch = scan.try_into_batch().await.unwrap();
+        batch["id"]
+            .as_primitive::<arrow::datatypes::Int32Type>()
+            .values()
+            .to_vec()
+    }
+
+    /// Recall of the index over queries of the first rows.
+    async fn compute_recall(dataset: &Dataset, k: usize, filter: Option<&str>) -> f32 {
+        let vectors = dataset
+            .scan()
+            .project(&["vector"])
+            .unwrap()
+            .limit(Some(20), None)
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        let vectors = vectors["vector"].as_fixed_size_list().clone();
+        let mut found = 0;
+        for i in 0..vectors.len() {
+            let key = vectors.value(i).as_primitive::<Float32Type>().clone();
+            let expected = search(dataset, &key, k, filter, false).await;
+            let actual = search(dataset, &key, k, filter, true).await;
+            let expected = expected.into_iter().collect::<HashSet<_>>();
+            found += actual.iter().filter(|id| expected.contains(id)).count();
+        }
+        found as f32 / (k * vectors.len()) as f32
+    }
+
+    #[tokio::test]
+    async fn test_diskann_search() {
+        let test_dir = tempdir().unwrap();
+        let mut dataset = make_dataset(test_dir.path().to_str().unwrap(), 2000).await;
+        create_index(&mut dataset, DistanceType::L2).await;
+
+        let indices = dataset.load_indices().await.unwrap();
+        let index = dataset
+            .open_vector_index(
+                "vector",
+                &indices[0].uuid.to_string(),
+                &NoOpMetricsCollector,
+            )
+            .await
+            .unwrap();
+        let index = index.as_any().downcast_ref::<DiskANNIndex>().unwrap();
+        assert_eq!(index.num_rows(), 2000);
+        assert_eq!(index.entries().len(), 1);
+
+        let plan = dataset
+            .scan()
+            .nearest("vector", &generate_random_array(DIM), 10)
+            .unwrap()
+            .explain_plan(false)
+            .await
+            .unwrap();
+        assert!(plan.contains("ANNSubIndex"), "{}", plan);
+
+        let recall = compute_recall(&dataset, 10, None).await;
+        assert!(recall >= 0.9, "recall: {}", recall);
+
+        // Prefilter keeps only the rows passing the filter
+        let key = generate_random_array(DIM);
+        let ids = search(&dataset, &key, 10, Some("id % 7 = 0"), true).await;
+        assert_eq!(ids.len(), 10);
+        assert!(ids.iter().all(|id| id % 7 == 0));
+        let recall = compute_recall(&dataset, 10, Some("id % 7 = 0")).await;
+        assert!(recall >= 0.9, "recall: {}", recall);
+
+        // A selective prefilter ranks the selected rows directly.  The 20
+        // selected rows all fit in the list, so the results are exact
+        let recall = compute_recall(&dataset, 10, Some("id % 100 = 0")).await;
+        assert_eq!(recall, 1.0);
+        let mut ids = search(&dataset, &key, 10, Some("id < 3"), true).await;
+        ids.sort();
+        assert_eq!(ids, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_diskann_cosine() {
+        let test_dir = tempdir().unwrap();
+        let mut dataset = make_dataset(test_dir.path().to_str().unwrap(), 1000).await;
+        create_index(&mut dataset, DistanceType::Cosine).await;
+
+        let key = generate_random_array(DIM);
+        let mut scan = dataset.scan();
+        scan.nearest("vector", &key, 5)
+            .unwrap()
+            .distance_metric(DistanceType::Cosine);
+        let batch = scan.try_into_batch().await.unwrap();
+        let distances = batch["_distance"].as_primitive::<Float32Type>();
+        assert_eq!(distances.len(), 5);
+        assert!(distances.values().iter().all(|d| (0.0..=2.0).contains(d)));
+        assert!(distances.values().windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_diskann_delete_compact_optimize() {
+        let test_dir = tempdir().unwrap();
+        let mut dataset = make_dataset(test_dir.path().to_str().unwrap(), 2000).await;
+        create_index(&mut dataset, DistanceType::L2).await;
+
+        // The nodes of deleted rows stay in the graph, but are not returned
+        dataset.delete("id < 500").await.unwrap();
+        compact_files(&mut dataset, CompactionOptions::default(), None)
+            .await
+            .unwrap();
+        let indices = dataset.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        let index = dataset
+            .open_vector_index(
+                "vector",
+                &indices[0].uuid.to_string(),
+                &NoOpMetricsCollector,
+            )
+            .await
+            .unwrap();
+        assert_eq!(index.num_rows(), 2000);
+        let key = generate_random_array(DIM);
+        let ids = search(&dataset, &key, 10, None, true).await;
+        assert_eq!(ids.len(), 10);
+        assert!(ids.iter().all(|id| *id >= 500), "{:?}", ids);
+        let recall = compute_recall(&dataset, 10, None).await;
+        assert!(recall >= 0.9, "recall: {}", recall);
+
+        // Appended rows are searched with a flat search until the index is optimized
+        let batch = make_batch(2000, 500);
+        let schema = batch.schema();
+        dataset
+            .append(RecordBatchIterator::new(vec![Ok(batch)], schema), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            dataset
+                .unindexed_fragments("vector_idx")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        dataset
+            .optimize_indices(&OptimizeOptions::default())
+            .await
+            .unwrap();
+        assert!(dataset
+            .unindexed_fragments("vector_idx")
+            .await
+            .unwrap()
+            .is_empty());
+        let indices = dataset.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        let index = dataset
+            .open_vector_index(
+                "vector",
+                &indices[0].uuid.to_string(),
+                &NoOpMetricsCollector,
+            )
+            .await
+            .unwrap();
+        // The appended vectors are inserted into the existing graph
+        assert_eq!(index.num_rows(), 2500);
+        let recall = compute_recall(&dataset, 10, None).await;
+        assert!(recall >= 0.9, "recall: {}", recall);
+        let appended = dataset
+            .scan()
+            .project(&["id", "vector"])
+            .unwrap()
+            .filter("id >= 2000")
+            .unwrap()
+            .limit(Some(20), None)
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        let mut found = 0;
+        for i in 0..appended.num_rows() {
+            let key = appended["vector"].as_fixed_size_list().value(i);
+            let ids = search(&dataset, key.as_primitive(), 1, None, true).await;
+            let id = appended["id"]
+                .as_primitive::<arrow::datatypes::Int32Type>()
+                .value(i);
+            found += (ids == vec![id]) as usize;
+        }
+        assert!(found >= 18, "found {} of 20 appended vectors", found);
+    }
+
+    #[tokio::test]
+    async fn test_diskann_optimize_keeps_name() {
+        let test_dir = tempdir().unwrap();
+        let mut dataset = make_dataset(test_dir.path().to_str().unwrap(), 1000).await;
+        let params = VectorIndexParams::with_diskann_params(
+            DistanceType::L2,
+            DiskANNParams::new(16, 1.2, 40),
+            PQBuildParams::new(8, 8),
+        );
+        dataset
+            .create_index(
+                &["vector"],
+                IndexType::Vector,
+                Some("graph".to_string()),
+                &params,
+                true,
+            )
+            .await
+            .unwrap();
+
+        let batch = make_batch(1000, 200);
+        let schema = batch.schema();
+        dataset
+            .append(RecordBatchIterator::new(vec![Ok(batch)], schema), None)
+            .await
+            .unwrap();
+        dataset
+            .optimize_indices(&OptimizeOptions::default())
+            .await
+            .unwrap();
+
+        let indices = dataset.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        assert_eq!(indices[0].name, "graph");
+        assert!(dataset
+            .unindexed_fragments("graph")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_diskann_sharded() {
+        let test_dir = tempdir().unwrap();
+        let mut dataset = make_dataset(test_dir.path().to_str().unwrap(), 2000).await;
+        // About 5 shards of 800 vectors, each vector in 2 of them
+        let params = VectorIndexParams::with_diskann_params(
+            DistanceType::L2,
+            DiskANNParams::new(32, 1.2, 60).shard_size(800),
+            PQBuildParams::new(8, 8),
+        );
+        dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, true)
+            .await
+            .unwrap();
+
+        let indices = dataset.load_indices().await.unwrap();
+        let index = dataset
+            .open_vector_index(
+                "vector",
+                &indices[0].uuid.to_string(),
+                &NoOpMetricsCollector,
+            )
+            .await
+            .unwrap();
+        let index = index.as_any().downcast_ref::<DiskANNIndex>().unwrap();
+        assert_eq!(index.num_rows(), 2000);
+        assert!(index.entries().len() > 1);
+
+        let recall = compute_recall(&dataset, 10, None).await;
+        assert!(recall >= 0.9, "recall: {}", recall);
+    }
+}
@@ -0,0 +1,423 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Build IVF vector indices on independent workers.
+//!
+//! Building a vector index is split into three phases:
+//! 1. [`train_vector_index`] trains the IVF centroids and the quantizer once, and persists them.
+//! 2. [`build_partial_vector_index`] builds the IVF partitions for a subset of the fragments,
+//!    with the persisted training, and writes them as an uncommitted partial index.
+//!    Each call can run on a different worker.
+//! 3. [`commit_partial_vector_indices`] merges the partial indices, and commits the merged index
+//!    in a single `CreateIndex` transaction.
+//!
+//! The training and partial index files are not referenced by the dataset,
+//! so they are removed by `cleanup_old_versions` once they are old enough.
+
+use lance_index::metrics::NoOpMetricsCollector;
+use lance_index::{DatasetIndexExt, IndexType};
+use lance_table::format::Index as IndexMetadata;
+use object_store::path::Path;
+use roaring::RoaringBitmap;
+use snafu::location;
+use uuid::Uuid;
+
+use super::builder::{IvfBuildPhase, IVF_TRAINING_FILE_NAME};
+use super::{build_vector_index_phase, VectorIndexParams};
+use crate::dataset::transaction::{Operation, Transaction};
+use crate::index::{vector_index_details_for, DatasetIndexInternalExt};
+use crate::{Dataset, Error, Result};
+
+/// A vector index built over a subset of the fragments, which is not committed yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialVectorIndex {
+    /// The id of the partial index.
+    pub uuid: Uuid,
+    /// The fragments indexed by the partial index.
+    pub fragment_ids: Vec<u32>,
+}
+
+fn training_file(dataset: &Dataset, training_id: &Uuid) -> Path {
+    dataset
+        .indices_dir()
+        .child(training_id.to_string())
+        .child(IVF_TRAINING_FILE_NAME)
+}
+
+/// Train the IVF centroids and the quantizer of a vector index, and persist them.
+///
+/// Returns the id of the training, which is passed to [`build_partial_vector_index`]
+/// and [`commit_partial_vector_indices`].
+pub async fn train_vector_index(
+    dataset: &Dataset,
+    column: &str,
+    params: &VectorIndexParams,
+) -> Result<Uuid> {
+    let training_id = Uuid::new_v4();
+    build_vector_index_phase(
+        dataset,
+        column,
+        &format!("{column}_idx"),
+        &training_id.to_string(),
+        params,
+        IvfBuildPhase::Train,
+    )
+    .await?;
+    Ok(training_id)
+}
+
+/// Build a partial vector index over the given fragments,
+/// with the IVF centroids and quantizer trained by [`train_vector_index`].
+///
+/// The partial index is not visible to the dataset until it's committed
+/// by [`commit_partial_vector_indices`].
+pub async fn build_partial_vector_index(
+    dataset: &Dataset,
+    column: &str,
+    params: &VectorIndexParams,
+    training_id: &Uuid,
+    fragment_ids: &[u32],
+) -> Result<PartialVectorIndex> {
+    if fragment_ids.is_empty() {
+        return Err(Error::invalid_input(
+            "no fragment to build the partial index",
+            location!(),
+        ));
+    }
+    let fragments = fragment_ids
+        .iter()
+        .map(|id| {
+            dataset
+                .get_fragment(*id as usize)
+                .map(|fragment| fragment.metadata().clone())
+                .ok_or_else(|| {
+                    Error::invalid_input(format!("fragment {} does not exist", id), location!())
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let uuid = Uuid::new_v4();
+    build_vector_index_phase(
+        dataset,
+        column,
+        &format!("{column}_idx"),
+        &uuid.to_string(),
+        params,
+        IvfBuildPhase::Partial {
+            training_file: training_file(dataset, training_id),
+            fragments,
+        },
+    )
+    .await?;
+    Ok(PartialVectorIndex {
+        uuid,
+        fragment_ids: fragment_ids.to_vec(),
+    })
+}
+
+/// Merge the partial vector indices built by [`build_partial_vector_index`],
+/// and commit the merged index.
+///
+/// The partial indices must be built with the same training and index parameters,
+/// must not index the same fragment twice, and the fragments they index must
+/// still exist in the dataset.
+/// The fragments not covered by the partial indices are left unindexed.
+pub async fn commit_partial_vector_indices(
+    dataset: &mut Dataset,
+    column: &str,
+    name: Option<String>,
+    params: &VectorIndexParams,
+    training_id: &Uuid,
+    partial_indices: &[PartialVectorIndex],
+) -> Result<()> {
+    let Some(field) = dataset.schema().field(column) else {
+        return Err(Error::Index {
+            message: format!("CreateIndex: column '{column}' does not exist"),
+            location: location!(),
+        });
+    };
+    let field_id = field.id;
+    if partial_indices.is_empty() {
+        return Err(Error::invalid_input(
+            "no partial index to commit",
+            location!(),
+        ));
+    }
+
+    let index_name = name.unwrap_or(format!("{column}_idx"));
+    let indices = dataset.load_indices().await?;
+    if indices.iter().any(|idx| idx.name == index_name) {
+        return Err(Error::Index {
+            message: format!(
+                "Index name '{index_name} already exists, please specify a different name"
+            ),
+            location: location!(),
+        });
+    }
+
+    let mut fragment_bitmap = RoaringBitmap::new();
+    for partial_index in partial_indices {
+        for fragment_id in &partial_index.fragment_ids {
+            if !fragment_bitmap.insert(*fragment_id) {
+                return Err(Error::invalid_input(
+                    format!(
+                        "fragment {} is indexed by more than one partial index",
+                        fragment_id
+                    ),
+                    location!(),
+                ));
+            }
+        }
+    }
+    // The fragments may have been removed since the partial indices were built,
+    // e.g. by compaction, and the index must not cover fragments that don't exist
+    if let Some(fragment_id) = fragment_bitmap
+        .iter()
+        .find(|id| dataset.get_fragment(*id as usize).is_none())
+    {
+        return Err(Error::invalid_input(
+            format!(
+                "fragment {} indexed by a partial index does not exist anymore",
+                fragment_id
+            ),
+            location!(),
+        ));
+    }
+
+    let mut indices_to_merge = Vec::with_capacity(partial_indices.len());
+    for partial_index in partial_indices {
+        indices_to_merge.push(
+            dataset
+                .open_vector_index(
+                    column,
+                    &partial_index.uuid.to_string(),
+                    &NoOpMetricsCollector,
+                )
+                .await?,
+        );
+    }
+
+    let index_id = Uuid::new_v4();
+    // this is a large future so move it to heap
+    Box::pin(build_vector_index_phase(
+        dataset,
+        column,
+        &index_name,
+        &index_id.to_string(),
+        params,
+        IvfBuildPhase::Merge {
+            training_file: training_file(dataset, training_id),
+            partial_indices: indices_to_merge,
+        },
+    ))
+    .await?;
+
+    let new_idx = IndexMetadata {
+        uuid: index_id,
+        name: index_name,
+        fields: vec![field_id],
+        dataset_version: dataset.manifest.version,
+        fragment_bitmap: Some(fragment_bitmap),
+        index_details: Some(vector_index_details_for(params)),
+        index_version: IndexType::Vector.version(),
+    };
+    let transaction = Transaction::new(
+        dataset.manifest.version,
+        Operation::CreateIndex {
+            new_indices: vec![new_idx],
+            removed_indices: vec![],
+        },
+        /*blobs_op= */ None,
+        None,
+    );
+
+    dataset
+        .apply_commit(transaction, &Default::default(), &Default::default())
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float32Type, UInt64Type};
+    use lance_datagen::{array, gen, BatchCount, Dimension, RowCount};
+    use lance_linalg::distance::DistanceType;
+
+    use crate::dataset::optimize::{compact_files, CompactionOptions};
+    use crate::dataset::WriteParams;
+
+    const DIM: u32 = 16;
+    const NUM_ROWS: u64 = 1000;
+
+    async fn make_dataset() -> Dataset {
+        let reader = gen()
+            .col("vec", array::rand_vec::<Float32Type>(Dimension::from(DIM)))
+            .into_reader_rows(RowCount::from(NUM_ROWS / 4), BatchCount::from(4));
+        Dataset::write(
+            reader,
+            "memory://",
+            Some(WriteParams {
+                max_rows_per_file: (NUM_ROWS / 4) as usize,
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[rstest::rstest]
+    #[case::ivf_flat(VectorIndexParams::ivf_flat(4, DistanceType::L2))]
+    #[case::ivf_pq(VectorIndexParams::ivf_pq(4, 8, 4, DistanceType::L2, 10))]
+    #[case::ivf_hnsw_sq(VectorIndexParams::with_ivf_hnsw_sq_params(
+        DistanceType::Cosine,
+        lance_index::vector::ivf::IvfBuildParams::new(4),
+        Default::default(),
+        Default::default(),
+    ))]
+    #[tokio::test]
+    async fn test_build_partial_indices(#[case] params: VectorIndexParams) {
+        let mut dataset = make_dataset().await;
+        assert_eq!(dataset.get_fragments().len(), 4);
+
+        let training_id = train_vector_index(&dataset, "vec", &params).await.unwrap();
+        let mut partial_indices = Vec::new();
+        for fragment_ids in [[0, 1], [2, 3]] {
+            partial_indices.push(
+                build_partial_vector_index(&dataset, "vec", &params, &training_id, &fragment_ids)
+                    .await
+                    .unwrap(),
+            );
+        }
+        // nothing is committed yet
+        assert!(dataset.load_indices().await.unwrap().is_empty());
+
+        commit_partial_vector_indices(
+            &mut dataset,
+            "vec",
+            None,
+            &params,
+            &training_id,
+            &partial_indices,
+        )
+        .await
+        .unwrap();
+
+        let indices = dataset.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        assert_eq!(
+            indices[0].fragment_bitmap.as_ref().unwrap(),
+            &RoaringBitmap::from_iter(0..4)
+        );
+        let stats: serde_json::Value =
+            serde_json::from_str(&dataset.index_statistics("vec_idx").await.unwrap()).unwrap();
+        assert_eq!(stats["num_indexed_rows"], NUM_ROWS);
+        assert_eq!(stats["num_unindexed_rows"], 0);
+
+        // all the rows are in the merged index, so an exhaustive search finds all of them
+        let batch = dataset
+            .scan()
+            .limit(Some(1), Some(10))
+            .unwrap()
+            .try_into_batch()
+            .await
+            .unwrap();
+        let query = batch["vec"].as_fixed_size_list().value(0);
+        let results = dataset
+            .scan()
+            .nearest("vec", &query, NUM_ROWS as usize)
+            .unwrap()
+            .nprobs(4)
+            .ef(NUM_ROWS as usize)
+            .refine(1)
+            .with_row_id()
+            .try_into_batch()
+            .await
+            .unwrap();
+        assert_eq!(results.num_rows(), NUM_ROWS as usize);
+        assert_eq!(
+            results[lance_core::ROW_ID]
+                .as_primitive::<UInt64Type>()
+                .value(0),
+            10
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_overlapped_partial_indices() {
+        let mut dataset = make_dataset().await;
+        let params = VectorIndexParams::ivf_flat(4, DistanceType::L2);
+        let training_id = train_vector_index(&dataset, "vec", &params).await.unwrap();
+        let mut partial_indices = Vec::new();
+        for fragment_ids in [[0, 1], [1, 2]] {
+            partial_indices.push(
+                build_partial_vector_index(&dataset, "vec", &params, &training_id, &fragment_ids)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let result = commit_partial_vector_indices(
+            &mut dataset,
+            "vec",
+            None,
+            &params,
+            &training_id,
+            &partial_indices,
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(dataset.load_indices().await.unwrap().is_empty());
+
+        // the partial indices must be built with the same distance type as the training
+        let params = VectorIndexParams::ivf_flat(4, DistanceType::Dot);
+        assert!(
+            build_partial_vector_index(&dataset, "vec", &params, &training_id, &[3])
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commit_partial_indices_of_removed_fragments() {
+        let mut dataset = make_dataset().await;
+        let params = VectorIndexParams::ivf_flat(4, DistanceType::L2);
+        let training_id = train_vector_index(&dataset, "vec", &params).await.unwrap();
+        let mut partial_indices = Vec::new();
+        for fragment_ids in [[0, 1], [2, 3]] {
+            partial_indices.push(
+                build_partial_vector_index(&dataset, "vec", &params, &training_id, &fragment_ids)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // the compaction replaces the fragments the partial indices were built over
+        compact_files(
+            &mut dataset,
+            CompactionOptions {
+                target_rows_per_fragment: NUM_ROWS as usize,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(dataset.get_fragments().len(), 1);
+
+        let err = commit_partial_vector_indices(
+            &mut dataset,
+            "vec",
+            None,
+            &params,
+            &training_id,
+            &partial_indices,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput { .. }), "{}", err);
+        assert!(dataset.load_indices().await.unwrap().is_empty());
+    }
+}
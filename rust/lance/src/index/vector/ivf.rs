@@ -37,6 +37,7 @@ use lance_file::{
 };
 use lance_index::metrics::MetricsCollector;
 use lance_index::metrics::NoOpMetricsCollector;
+use lance_index::vector::bq::RabitQuantizer;
 use lance_index::vector::flat::index::{FlatBinQuantizer, FlatIndex, FlatQuantizer};
 use lance_index::vector::ivf::storage::IvfModel;
 use lance_index::vector::pq::storage::transpose;
@@ -367,6 +368,8 @@ pub(crate) async fn optimize_vector_indices_v2(
     let distance_type = existing_indices[0].metric_type();
     let num_partitions = ivf_model.num_partitions();
     let index_type = existing_indices[0].sub_index_type();
+    // the new data must be pooled in the same way as the existing indices
+    let token_pool_factor = existing_indices[0].token_pool_factor();
 
     let num_indices_to_merge = if options.retrain {
         existing_indices.len()
@@ -400,6 +403,7 @@ pub(crate) async fn optimize_vector_indices_v2(
                 .with_ivf(ivf_model.clone())
                 .with_quantizer(quantizer.try_into()?)
                 .with_existing_indices(indices_to_merge)
+                .with_token_pool_factor(token_pool_factor)
                 .retrain(options.retrain)
                 .shuffle_data(unindexed)
                 .await?
@@ -417,6 +421,7 @@ pub(crate) async fn optimize_vector_indices_v2(
                 .with_ivf(ivf_model.clone())
                 .with_quantizer(quantizer.try_into()?)
                 .with_existing_indices(indices_to_merge)
+                .with_token_pool_factor(token_pool_factor)
                 .retrain(options.retrain)
                 .shuffle_data(unindexed)
                 .await?
@@ -437,6 +442,27 @@ pub(crate) async fn optimize_vector_indices_v2(
             .with_ivf(ivf_model.clone())
             .with_quantizer(quantizer.try_into()?)
             .with_existing_indices(indices_to_merge)
+            .with_token_pool_factor(token_pool_factor)
+            .retrain(options.retrain)
+            .shuffle_data(unindexed)
+            .await?
+            .build()
+            .await?;
+        }
+        // IVF_RQ
+        (SubIndexType::Flat, QuantizationType::Rabit) => {
+            IvfIndexBuilder::<FlatIndex, RabitQuantizer>::new_incremental(
+                dataset.clone(),
+                vector_column.to_owned(),
+                index_dir,
+                distance_type,
+                shuffler,
+                (),
+            )?
+            .with_ivf(ivf_model.clone())
+            .with_quantizer(quantizer.try_into()?)
+            .with_existing_indices(indices_to_merge)
+            .with_token_pool_factor(token_pool_factor)
             .retrain(options.retrain)
             .shuffle_data(unindexed)
             .await?
@@ -459,6 +485,7 @@ pub(crate) async fn optimize_vector_indices_v2(
             .with_ivf(ivf_model.clone())
             .with_quantizer(quantizer.try_into()?)
             .with_existing_indices(indices_to_merge)
+            .with_token_pool_factor(token_pool_factor)
             .retrain(options.retrain)
             .shuffle_data(unindexed)
             .await?
@@ -481,6 +508,7 @@ pub(crate) async fn optimize_vector_indices_v2(
             .with_ivf(ivf_model.clone())
             .with_quantizer(quantizer.try_into()?)
             .with_existing_indices(indices_to_merge)
+            .with_token_pool_factor(token_pool_factor)
             .retrain(options.retrain)
             .shuffle_data(unindexed)
             .await?
@@ -690,7 +718,7 @@ async fn optimize_ivf_hnsw_indices<Q: Quantization>(
                 ..Default::default()
             })
         }
-        Quantizer::Scalar(_) => None,
+        Quantizer::Scalar(_) | Quantizer::Rabit(_) => None,
     };
 
     aux_writer.add_metadata(
@@ -1647,7 +1675,7 @@ async fn write_ivf_hnsw_file(
                 ..Default::default()
             })
         }
-        Quantizer::Scalar(_) => None,
+        Quantizer::Scalar(_) | Quantizer::Rabit(_) => None,
     };
 
     aux_writer.add_metadata(
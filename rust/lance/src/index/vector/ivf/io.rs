@@ -327,7 +327,7 @@ pub(super) async fn write_hnsw_quantization_index_partitions(
             Quantizer::Flat(_) => None,
             Quantizer::FlatBin(_) => None,
             Quantizer::Product(pq) => Some(pq.column()),
-            Quantizer::Scalar(_) => None,
+            Quantizer::Scalar(_) | Quantizer::Rabit(_) => None,
         };
         merge_streams(
             &mut streams_heap,
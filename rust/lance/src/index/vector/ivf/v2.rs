@@ -12,34 +12,38 @@ use std::{
 
 use arrow::compute::concat_batches;
 use arrow_arith::numeric::sub;
-use arrow_array::{RecordBatch, UInt32Array};
+use arrow_array::{Float32Array, RecordBatch, UInt32Array, UInt64Array};
+use arrow_schema::DataType;
 use async_trait::async_trait;
 use datafusion::execution::SendableRecordBatchStream;
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use deepsize::DeepSizeOf;
-use futures::prelude::stream::{self, TryStreamExt};
+use futures::prelude::stream::{self, StreamExt, TryStreamExt};
+use itertools::Itertools;
 use lance_arrow::RecordBatchExt;
 use lance_core::cache::FileMetadataCache;
-use lance_core::utils::tokio::spawn_cpu;
+use lance_core::utils::tokio::{get_num_compute_intensive_cpus, spawn_cpu};
 use lance_core::utils::tracing::{IO_TYPE_LOAD_VECTOR_PART, TRACE_IO_EVENTS};
 use lance_core::{Error, Result, ROW_ID};
 use lance_encoding::decoder::{DecoderPlugins, FilterExpression};
 use lance_file::v2::reader::{FileReader, FileReaderOptions};
 use lance_index::metrics::{LocalMetricsCollector, MetricsCollector};
+use lance_index::vector::bq::RabitQuantizer;
 use lance_index::vector::flat::index::{FlatIndex, FlatQuantizer};
 use lance_index::vector::hnsw::HNSW;
 use lance_index::vector::ivf::storage::IvfModel;
 use lance_index::vector::pq::ProductQuantizer;
 use lance_index::vector::quantizer::{QuantizationType, Quantizer};
 use lance_index::vector::sq::ScalarQuantizer;
-use lance_index::vector::storage::VectorStore;
+use lance_index::vector::storage::{DistCalculator, VectorStore};
 use lance_index::vector::v3::subindex::SubIndexType;
 use lance_index::vector::VectorIndexCacheEntry;
 use lance_index::{
     pb,
     vector::{
         ivf::storage::IVF_METADATA_KEY, quantizer::Quantization, storage::IvfQuantizationStorage,
-        v3::subindex::IvfSubIndex, Query, DISTANCE_TYPE_KEY,
+        v3::subindex::IvfSubIndex, Query, DISTANCE_TYPE_KEY, TOKEN_POOL_FACTOR_KEY,
+        VECTOR_RESULT_SCHEMA,
     },
     Index, IndexType, INDEX_AUXILIARY_FILE_NAME, INDEX_FILE_NAME,
 };
@@ -99,6 +103,9 @@ pub struct IVFIndex<S: IvfSubIndex + 'static, Q: Quantization + 'static> {
 
     distance_type: DistanceType,
 
+    // the multivectors are pooled by this factor before being indexed
+    token_pool_factor: Option<usize>,
+
     // The session cache holds an Arc to this object so we need to
     // hold a weak pointer to avoid cycles
     /// The session cache, used when fetching pages
@@ -166,6 +173,16 @@ impl<S: IvfSubIndex + 'static, Q: Quantization> IVFIndex<S, Q> {
                 message: format!("Failed to decode IVF position: {}", e),
                 location: location!(),
             })?;
+        let token_pool_factor = index_reader
+            .schema()
+            .metadata
+            .get(TOKEN_POOL_FACTOR_KEY)
+            .map(|factor| factor.parse())
+            .transpose()
+            .map_err(|e| Error::Index {
+                message: format!("Failed to decode token pool factor: {}", e),
+                location: location!(),
+            })?;
         let ivf_pb_bytes = index_reader.read_global_buffer(ivf_pos).await?;
         let ivf = IvfModel::try_from(pb::Ivf::decode(ivf_pb_bytes)?)?;
 
@@ -206,6 +223,7 @@ impl<S: IvfSubIndex + 'static, Q: Quantization> IVFIndex<S, Q> {
             partition_locks: PartitionLoadLock::new(num_partitions),
             sub_index_metadata,
             distance_type,
+            token_pool_factor,
             session,
             _marker: PhantomData,
         })
@@ -303,6 +321,23 @@ impl<S: IvfSubIndex + 'static, Q: Quantization> IVFIndex<S, Q> {
     /// Internal API with no stability guarantees.
     #[instrument(level = "debug", skip(self))]
     pub fn preprocess_query(&self, partition_id: usize, query: &Query) -> Result<Query> {
+        let mut query = query.clone();
+        // RaBitQ estimates the distances with float32 queries
+        if matches!(Q::quantization_type(), QuantizationType::Rabit)
+            && query.key.data_type() != &DataType::Float32
+        {
+            if !query.key.data_type().is_floating() {
+                return Err(Error::invalid_input(
+                    format!(
+                        "IVF_RQ query must be a float vector, got {}",
+                        query.key.data_type()
+                    ),
+                    location!(),
+                ));
+            }
+            query.key = arrow::compute::cast(&query.key, &DataType::Float32)?;
+        }
+
         if Q::use_residual(self.distance_type) {
             let partition_centroids =
                 self.ivf
@@ -311,13 +346,9 @@ impl<S: IvfSubIndex + 'static, Q: Quantization> IVFIndex<S, Q> {
                         message: format!("partition centroid {} does not exist", partition_id),
                         location: location!(),
                     })?;
-            let residual_key = sub(&query.key, &partition_centroids)?;
-            let mut part_query = query.clone();
-            part_query.key = residual_key;
-            Ok(part_query)
-        } else {
-            Ok(query.clone())
+            query.key = sub(&query.key, &partition_centroids)?;
         }
+        Ok(query)
     }
 }
 
@@ -345,6 +376,7 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> Index for IVFIndex<S,
             (SubIndexType::Flat, QuantizationType::Flat) => IndexType::IvfFlat,
             (SubIndexType::Flat, QuantizationType::Product) => IndexType::IvfPq,
             (SubIndexType::Flat, QuantizationType::Scalar) => IndexType::IvfSq,
+            (SubIndexType::Flat, QuantizationType::Rabit) => IndexType::IvfRq,
             (SubIndexType::Hnsw, QuantizationType::Product) => IndexType::IvfHnswPq,
             (SubIndexType::Hnsw, QuantizationType::Scalar) => IndexType::IvfHnswSq,
             _ => IndexType::Vector,
@@ -489,6 +521,231 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> VectorIndex for IVFInd
         Ok(batch)
     }
 
+    #[instrument(level = "debug", skip(self, queries, pre_filter, metrics))]
+    async fn search_in_partition_batch(
+        &self,
+        partition_id: usize,
+        queries: &[Query],
+        pre_filter: Arc<dyn PreFilter>,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<Vec<RecordBatch>> {
+        let part_entry = self.load_partition(partition_id, true, metrics).await?;
+        pre_filter.wait_for_ready().await?;
+        let queries = queries
+            .iter()
+            .map(|query| self.preprocess_query(partition_id, query))
+            .collect::<Result<Vec<_>>>()?;
+
+        let (batches, local_metrics) = spawn_cpu(move || {
+            let local_metrics = LocalMetricsCollector::default();
+            let part = part_entry
+                .as_any()
+                .downcast_ref::<PartitionEntry<S, Q>>()
+                .ok_or(Error::Internal {
+                    message: "failed to downcast partition entry".to_string(),
+                    location: location!(),
+                })?;
+            let batches = queries
+                .into_iter()
+                .map(|query| {
+                    let param = (&query).into();
+                    let k = query.k * query.refine_factor.unwrap_or(1) as usize;
+                    part.index.search(
+                        query.key,
+                        k,
+                        param,
+                        &part.storage,
+                        pre_filter.clone(),
+                        &local_metrics,
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok((batches, local_metrics))
+        })
+        .await?;
+
+        local_metrics.dump_into(metrics);
+
+        Ok(batches)
+    }
+
+    #[instrument(level = "debug", skip(self, pre_filter, metrics))]
+    async fn multivector_search(
+        &self,
+        query: &Query,
+        num_candidates: usize,
+        pre_filter: Arc<dyn PreFilter>,
+        metrics: &dyn MetricsCollector,
+    ) -> Result<RecordBatch> {
+        let centroids = self.ivf.centroids.as_ref().ok_or(Error::Index {
+            message: "IVF centroids are required by multivector search".to_string(),
+            location: location!(),
+        })?;
+        let dim = self.ivf.dimension();
+        let num_queries = query.key.len() / dim;
+        if num_queries == 0 || num_candidates == 0 {
+            return Ok(RecordBatch::new_empty(VECTOR_RESULT_SCHEMA.clone()));
+        }
+        let query_vectors = (0..num_queries)
+            .map(|i| query.key.slice(i * dim, dim))
+            .collect::<Vec<_>>();
+
+        // the distances between each query vector and all the centroids
+        let centroid_distance_type = if self.distance_type == DistanceType::Cosine {
+            DistanceType::L2
+        } else {
+            self.distance_type
+        };
+        let centroid_dists = query_vectors
+            .iter()
+            .map(|vector| {
+                Ok(
+                    centroid_distance_type.arrow_batch_func()(vector.as_ref(), centroids)?
+                        .values()
+                        .to_vec(),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // probe the closest partitions of each query vector
+        let nprobes = query.minimum_nprobes.clamp(1, self.ivf.num_partitions());
+        let partitions = centroid_dists
+            .iter()
+            .flat_map(|dists| {
+                dists
+                    .iter()
+                    .enumerate()
+                    .sorted_unstable_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .take(nprobes)
+                    .map(|(part_id, _)| part_id)
+            })
+            .unique()
+            .collect::<Vec<_>>();
+        let partition_queries = partitions
+            .iter()
+            .map(|&part_id| {
+                query_vectors
+                    .iter()
+                    .map(|vector| {
+                        let mut vector_query = query.clone();
+                        vector_query.key = vector.clone();
+                        Ok(self.preprocess_query(part_id, &vector_query)?.key)
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let entries = stream::iter(partitions.iter().copied())
+            .map(|part_id| self.load_partition(part_id, true, metrics))
+            .buffered(get_num_compute_intensive_cpus())
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        pre_filter.wait_for_ready().await?;
+        let mask = pre_filter.mask();
+        let limit = query.k * query.refine_factor.unwrap_or(1) as usize;
+        let (batch, local_metrics) = spawn_cpu(move || {
+            let local_metrics = LocalMetricsCollector::default();
+            let parts = entries
+                .iter()
+                .map(|entry| {
+                    entry
+                        .as_any()
+                        .downcast_ref::<PartitionEntry<S, Q>>()
+                        .ok_or(Error::Internal {
+                            message: "failed to downcast partition entry".to_string(),
+                            location: location!(),
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            // the (partition, position) of the vectors of each document
+            let mut docs = HashMap::<u64, Vec<(u32, u32)>>::new();
+            for (i, part) in parts.iter().enumerate() {
+                for (pos, row_id) in part.storage.row_ids().enumerate() {
+                    if mask.selected(*row_id) {
+                        docs.entry(*row_id)
+                            .or_default()
+                            .push((i as u32, pos as u32));
+                    }
+                }
+            }
+
+            // score the documents by the centroids of their partitions,
+            // and keep only the best candidates
+            let mut candidates = docs
+                .into_iter()
+                .map(|(row_id, vectors)| {
+                    let score = centroid_dists
+                        .iter()
+                        .map(|dists| {
+                            vectors
+                                .iter()
+                                .map(|(i, _)| dists[partitions[*i as usize]])
+                                .fold(f32::INFINITY, f32::min)
+                        })
+                        .sum::<f32>();
+                    (score, row_id, vectors)
+                })
+                .collect::<Vec<_>>();
+            if candidates.len() > num_candidates {
+                candidates.select_nth_unstable_by(num_candidates, |a, b| a.0.total_cmp(&b.0));
+                candidates.truncate(num_candidates);
+            }
+
+            // score the candidates by the sum of the minimum distances to their vectors
+            let mut vectors_by_partition = vec![Vec::new(); parts.len()];
+            for (doc, (_, _, vectors)) in candidates.iter().enumerate() {
+                for &(i, pos) in vectors {
+                    vectors_by_partition[i as usize].push((doc, pos));
+                }
+            }
+            let mut min_dists = vec![f32::INFINITY; candidates.len() * num_queries];
+            let mut num_comparisons = 0;
+            for ((part, queries), vectors) in parts
+                .iter()
+                .zip(partition_queries)
+                .zip(vectors_by_partition)
+            {
+                if vectors.is_empty() {
+                    continue;
+                }
+                for (i, key) in queries.into_iter().enumerate() {
+                    let dist_calc = part.storage.dist_calculator(key);
+                    for &(doc, pos) in &vectors {
+                        let min_dist = &mut min_dists[doc * num_queries + i];
+                        *min_dist = min_dist.min(dist_calc.distance(pos));
+                    }
+                    num_comparisons += vectors.len();
+                }
+            }
+            local_metrics.record_comparisons(num_comparisons);
+
+            // the same distance as the flat search, so that the results can be merged
+            let (dists, row_ids): (Vec<_>, Vec<_>) = candidates
+                .iter()
+                .zip(min_dists.chunks_exact(num_queries))
+                .map(|((_, row_id, _), dists)| {
+                    (1.0 - dists.iter().map(|d| 1.0 - d).sum::<f32>(), *row_id)
+                })
+                .sorted_unstable_by(|a, b| a.0.total_cmp(&b.0))
+                .take(limit)
+                .unzip();
+            let batch = RecordBatch::try_new(
+                VECTOR_RESULT_SCHEMA.clone(),
+                vec![
+                    Arc::new(Float32Array::from(dists)),
+                    Arc::new(UInt64Array::from(row_ids)),
+                ],
+            )?;
+            Ok((batch, local_metrics))
+        })
+        .await?;
+
+        local_metrics.dump_into(metrics);
+
+        Ok(batch)
+    }
+
     fn is_loadable(&self) -> bool {
         false
     }
@@ -593,6 +850,10 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> VectorIndex for IVFInd
         self.storage.quantizer::<Q>().unwrap()
     }
 
+    fn token_pool_factor(&self) -> Option<usize> {
+        self.token_pool_factor
+    }
+
     /// the index type of this vector index.
     fn sub_index_type(&self) -> (SubIndexType, QuantizationType) {
         (S::name().try_into().unwrap(), Q::quantization_type())
@@ -605,6 +866,7 @@ impl<S: IvfSubIndex + 'static, Q: Quantization + 'static> VectorIndex for IVFInd
 
 pub type IvfFlatIndex = IVFIndex<FlatIndex, FlatQuantizer>;
 pub type IvfPq = IVFIndex<FlatIndex, ProductQuantizer>;
+pub type IvfRqIndex = IVFIndex<FlatIndex, RabitQuantizer>;
 pub type IvfHnswSqIndex = IVFIndex<HNSW, ScalarQuantizer>;
 pub type IvfHnswPqIndex = IVFIndex<HNSW, ProductQuantizer>;
 
@@ -628,6 +890,7 @@ mod tests {
     use lance_core::ROW_ID;
     use lance_index::metrics::NoOpMetricsCollector;
     use lance_index::optimize::OptimizeOptions;
+    use lance_index::vector::bq::builder::RQBuildParams;
     use lance_index::vector::hnsw::builder::HnswBuildParams;
     use lance_index::vector::ivf::storage::IvfModel;
     use lance_index::vector::ivf::IvfBuildParams;
@@ -644,7 +907,7 @@ mod tests {
 
     use crate::dataset::optimize::{compact_files, CompactionOptions};
     use crate::dataset::{UpdateBuilder, WriteParams};
-    use crate::index::DatasetIndexInternalExt;
+    use crate::index::{vector_index_refine_factor, DatasetIndexInternalExt};
     use crate::{index::vector::VectorIndexParams, Dataset};
 
     const NUM_ROWS: usize = 500;
@@ -1155,7 +1418,7 @@ mod tests {
     ) {
         let params = VectorIndexParams::ivf_flat(nlist, distance_type);
         test_index(params.clone(), nlist, recall_requirement, None).await;
-        if distance_type == DistanceType::Cosine {
+        if distance_type != DistanceType::Hamming {
             test_index_multivec(params.clone(), nlist, recall_requirement).await;
         }
         test_distance_range(Some(params.clone()), nlist).await;
@@ -1186,6 +1449,62 @@ mod tests {
         test_remap(params, nlist).await;
     }
 
+    #[rstest]
+    #[case(4, DistanceType::L2, 0.9)]
+    #[case(4, DistanceType::Cosine, 0.9)]
+    #[case(4, DistanceType::Dot, 0.85)]
+    #[tokio::test]
+    async fn test_build_ivf_rq(
+        #[case] nlist: usize,
+        #[case] distance_type: DistanceType,
+        #[case] recall_requirement: f32,
+    ) {
+        let ivf_params = IvfBuildParams::new(nlist);
+        let rq_params = RQBuildParams::default();
+        let params = VectorIndexParams::with_ivf_rq_params(distance_type, ivf_params, rq_params);
+        test_index(params.clone(), nlist, recall_requirement, None).await;
+        test_remap(params.clone(), nlist).await;
+        test_optimize_strategy(params).await;
+    }
+
+    #[tokio::test]
+    async fn test_ivf_rq_default_refine_factor() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let (mut dataset, vectors) = generate_test_dataset::<Float32Type>(test_uri, 0.0..1.0).await;
+        let params = VectorIndexParams::with_ivf_rq_params(
+            DistanceType::L2,
+            IvfBuildParams::new(4),
+            RQBuildParams::new(5),
+        );
+        dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, true)
+            .await
+            .unwrap();
+
+        // the default refine factor is planned from the index details
+        let indices = dataset.load_indices().await.unwrap();
+        assert_eq!(vector_index_refine_factor(&indices[0]).unwrap(), Some(5));
+        let query = vectors.value(0);
+        let plan = dataset
+            .scan()
+            .nearest("vector", query.as_primitive::<Float32Type>(), 10)
+            .unwrap()
+            .explain_plan(false)
+            .await
+            .unwrap();
+        assert!(plan.contains("KNNVectorDistance"), "{}", plan);
+
+        // the IVF_PQ results are not reranked by default
+        let params = VectorIndexParams::ivf_pq(4, 8, 4, DistanceType::L2, 50);
+        dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, true)
+            .await
+            .unwrap();
+        let indices = dataset.load_indices().await.unwrap();
+        assert_eq!(vector_index_refine_factor(&indices[0]).unwrap(), None);
+    }
+
     #[rstest]
     #[case(1, DistanceType::L2, 0.9)]
     #[case(1, DistanceType::Cosine, 0.9)]
@@ -1311,7 +1630,7 @@ mod tests {
     }
 
     async fn test_index_multivec(params: VectorIndexParams, nlist: usize, recall_requirement: f32) {
-        // we introduce XTR for performance, which would reduce the recall a little bit
+        // the documents are pruned by their centroids first, which would reduce the recall a little bit
         let recall_requirement = recall_requirement * 0.9;
         match params.metric_type {
             DistanceType::Hamming => {
@@ -1393,6 +1712,82 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_index_multivec_token_pooling() {
+        let test_dir = tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let (mut dataset, vectors) =
+            generate_multivec_test_dataset::<Float32Type>(test_uri, 0.0..1.0).await;
+
+        let nlist = 4;
+        let ivf_params = IvfBuildParams {
+            token_pool_factor: Some(2),
+            ..IvfBuildParams::new(nlist)
+        };
+        let params = VectorIndexParams::with_ivf_flat_params(DistanceType::Cosine, ivf_params);
+        dataset
+            .create_index(&["vector"], IndexType::Vector, None, &params, true)
+            .await
+            .unwrap();
+
+        async fn num_indexed_vectors(dataset: &Dataset) -> Vec<u64> {
+            let indices = dataset.load_indices_by_name("vector_idx").await.unwrap();
+            let mut num_rows = vec![];
+            for idx in indices {
+                let index = dataset
+                    .open_vector_index("vector", &idx.uuid.to_string(), &NoOpMetricsCollector)
+                    .await
+                    .unwrap();
+                assert_eq!(index.token_pool_factor(), Some(2));
+                num_rows.push(index.num_rows());
+            }
+            num_rows
+        }
+
+        // each row has 3 vectors, which are pooled into 2
+        assert_eq!(
+            num_indexed_vectors(&dataset).await,
+            vec![NUM_ROWS as u64 * 2]
+        );
+
+        // the delta index keeps pooling the new vectors
+        append_dataset::<Float32Type>(&mut dataset, NUM_ROWS / 5, 0.0..1.0).await;
+        dataset
+            .optimize_indices(&OptimizeOptions::append())
+            .await
+            .unwrap();
+        assert_eq!(
+            num_indexed_vectors(&dataset).await,
+            vec![NUM_ROWS as u64 * 2, NUM_ROWS as u64 / 5 * 2]
+        );
+
+        // the pooled vectors are used only to find the candidates,
+        // the candidates are reranked by the original vectors
+        let query = vectors.value(0);
+        let plan = dataset
+            .scan()
+            .nearest("vector", &query, 10)
+            .unwrap()
+            .minimum_nprobes(nlist)
+            .refine(4)
+            .explain_plan(true)
+            .await
+            .unwrap();
+        assert!(plan.contains("MultivectorSearch"), "{}", plan);
+        let result = dataset
+            .scan()
+            .nearest("vector", &query, 10)
+            .unwrap()
+            .minimum_nprobes(nlist)
+            .refine(4)
+            .with_row_id()
+            .try_into_batch()
+            .await
+            .unwrap();
+        let row_ids = result[ROW_ID].as_primitive::<UInt64Type>().values();
+        assert_eq!(row_ids[0], 0);
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_migrate_v1_to_v3() {
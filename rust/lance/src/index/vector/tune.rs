@@ -0,0 +1,481 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Recall evaluation and search parameter tuning for vector indices.
+//!
+//! The ground truth is computed by the flat KNN search, then the ANN search is run
+//! with each combination of `nprobes`, `refine_factor` and `ef`, reporting the recall@k,
+//! latency and I/O of each combination.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use arrow::array::AsArray;
+use arrow::datatypes::{UInt32Type, UInt64Type};
+use arrow_array::{Array, FixedSizeListArray};
+use lance_core::ROW_ID;
+use lance_index::metrics::NoOpMetricsCollector;
+use lance_index::vector::quantizer::QuantizationType;
+use lance_index::vector::v3::subindex::SubIndexType;
+use lance_index::vector::QUERY_INDEX_COL;
+use lance_index::{DatasetIndexExt, IndexType};
+use snafu::location;
+
+use super::utils::maybe_sample_training_data;
+use crate::dataset::scanner::ExecutionSummaryCounts;
+use crate::index::scalar::infer_index_type;
+use crate::index::DatasetIndexInternalExt;
+use crate::{Dataset, Error, Result};
+
+/// Parameters for tuning the search parameters of a vector index.
+#[derive(Debug, Clone)]
+pub struct TuningParams {
+    /// The number of nearest neighbors to search, recall@k is evaluated.
+    pub k: usize,
+    /// The number of query vectors sampled from the dataset,
+    /// ignored if `queries` is set.
+    pub num_queries: usize,
+    /// The query vectors, sampled from the dataset if not set.
+    pub queries: Option<FixedSizeListArray>,
+    /// The recall to reach with the recommended settings.
+    pub target_recall: f64,
+    /// The values of `nprobes` to evaluate,
+    /// powers of 2 up to the number of IVF partitions if empty.
+    pub nprobes: Vec<usize>,
+    /// The values of `refine_factor` to evaluate, `None` means no refinement.
+    /// Defaults to no refinement and a few factors if empty, or only no refinement
+    /// if the index doesn't quantize the vectors.
+    pub refine_factors: Vec<Option<u32>>,
+    /// The values of `ef` to evaluate, only for HNSW sub indices.
+    /// Defaults to multiples of `k` if empty.
+    pub ef: Vec<usize>,
+}
+
+impl Default for TuningParams {
+    fn default() -> Self {
+        Self {
+            k: 10,
+            num_queries: 100,
+            queries: None,
+            target_recall: 0.95,
+            nprobes: Vec::new(),
+            refine_factors: Vec::new(),
+            ef: Vec::new(),
+        }
+    }
+}
+
+/// The search parameters of a vector query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchSettings {
+    pub nprobes: usize,
+    pub refine_factor: Option<u32>,
+    pub ef: Option<usize>,
+}
+
+impl fmt::Display for SearchSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "nprobes={}", self.nprobes)?;
+        if let Some(refine_factor) = self.refine_factor {
+            write!(f, ", refine_factor={}", refine_factor)?;
+        }
+        if let Some(ef) = self.ef {
+            write!(f, ", ef={}", ef)?;
+        }
+        Ok(())
+    }
+}
+
+/// The recall and cost of searching with some settings, averaged over the queries.
+#[derive(Debug, Clone)]
+pub struct RecallEvaluation {
+    pub settings: SearchSettings,
+    /// recall@k
+    pub recall: f64,
+    /// The mean latency of a query.
+    pub latency: Duration,
+    /// The mean number of bytes read by a query.
+    pub bytes_read: usize,
+    /// The mean number of I/O operations of a query.
+    pub iops: usize,
+}
+
+/// The evaluations of all the settings, and the recommended one.
+#[derive(Debug, Clone)]
+pub struct TuningReport {
+    pub k: usize,
+    pub num_queries: usize,
+    pub target_recall: f64,
+    pub evaluations: Vec<RecallEvaluation>,
+    /// The index of the fastest evaluation reaching the target recall,
+    /// `None` if no settings reach it.
+    pub recommended: Option<usize>,
+}
+
+impl TuningReport {
+    pub fn recommendation(&self) -> Option<&RecallEvaluation> {
+        self.recommended.map(|i| &self.evaluations[i])
+    }
+}
+
+/// Compute the exact `k` nearest neighbors of each query by the flat KNN search.
+///
+/// Returns the row ids of the nearest neighbors of each query, ordered by distance.
+pub async fn ground_truth(
+    dataset: &Dataset,
+    column: &str,
+    queries: &FixedSizeListArray,
+    k: usize,
+) -> Result<Vec<Vec<u64>>> {
+    let results = dataset
+        .scan()
+        .nearest(column, queries as &dyn Array, k)?
+        .use_index(false)
+        .project(&Vec::<String>::new())?
+        .with_row_id()
+        .try_into_batch()
+        .await?;
+
+    let row_ids = results[ROW_ID].as_primitive::<UInt64Type>();
+    let query_indices = results[QUERY_INDEX_COL].as_primitive::<UInt32Type>();
+    let mut neighbors = vec![Vec::with_capacity(k); queries.len()];
+    for (query_index, row_id) in query_indices.values().iter().zip(row_ids.values()) {
+        neighbors[*query_index as usize].push(*row_id);
+    }
+    Ok(neighbors)
+}
+
+/// Evaluate the recall@k of the ANN search with the given settings against the ground truth.
+pub async fn evaluate_recall(
+    dataset: &Dataset,
+    column: &str,
+    queries: &FixedSizeListArray,
+    ground_truth: &[Vec<u64>],
+    k: usize,
+    settings: &SearchSettings,
+) -> Result<RecallEvaluation> {
+    if queries.len() != ground_truth.len() {
+        return Err(Error::invalid_input(
+            format!(
+                "got {} queries but {} ground truth results",
+                queries.len(),
+                ground_truth.len()
+            ),
+            location!(),
+        ));
+    }
+    if queries.is_empty() {
+        return Err(Error::invalid_input("no query to evaluate", location!()));
+    }
+
+    let stats = Arc::new(Mutex::new(ExecutionSummaryCounts::default()));
+    let mut latency = Duration::ZERO;
+    let mut num_found = 0;
+    let mut num_expected = 0;
+    for (i, expected) in ground_truth.iter().enumerate() {
+        let query = queries.value(i);
+        let mut scanner = dataset.scan();
+        scanner
+            .nearest(column, query.as_ref(), k)?
+            .nprobs(settings.nprobes)
+            .project(&Vec::<String>::new())?
+            .with_row_id()
+            .scan_stats_callback({
+                let stats = stats.clone();
+                Arc::new(move |counts| {
+                    let mut stats = stats.lock().unwrap();
+                    stats.bytes_read += counts.bytes_read;
+                    stats.iops += counts.iops;
+                })
+            });
+        if let Some(refine_factor) = settings.refine_factor {
+            scanner.refine(refine_factor);
+        }
+        if let Some(ef) = settings.ef {
+            scanner.ef(ef);
+        }
+
+        let start = Instant::now();
+        let results = scanner.try_into_batch().await?;
+        latency += start.elapsed();
+
+        let row_ids = results[ROW_ID].as_primitive::<UInt64Type>();
+        num_found += row_ids
+            .values()
+            .iter()
+            .filter(|row_id| expected.contains(row_id))
+            .count();
+        num_expected += expected.len();
+    }
+
+    let num_queries = queries.len();
+    let stats = stats.lock().unwrap();
+    Ok(RecallEvaluation {
+        settings: *settings,
+        recall: if num_expected == 0 {
+            1.0
+        } else {
+            num_found as f64 / num_expected as f64
+        },
+        latency: latency / num_queries as u32,
+        bytes_read: stats.bytes_read / num_queries,
+        iops: stats.iops / num_queries,
+    })
+}
+
+/// Sweep the search parameters of the vector index on the column,
+/// and recommend the fastest settings reaching the target recall.
+///
+/// The queries are run one by one after a warm-up round,
+/// so the latency and I/O are measured with the index partitions cached.
+pub async fn tune_vector_index(
+    dataset: &Dataset,
+    column: &str,
+    params: &TuningParams,
+) -> Result<TuningReport> {
+    if params.k == 0 {
+        return Err(Error::invalid_input(
+            "k must be greater than 0",
+            location!(),
+        ));
+    }
+    if !(0.0..=1.0).contains(&params.target_recall) {
+        return Err(Error::invalid_input(
+            format!(
+                "target recall must be in [0, 1], got {}",
+                params.target_recall
+            ),
+            location!(),
+        ));
+    }
+
+    let field = dataset.schema().field(column).ok_or(Error::invalid_input(
+        format!("column {} does not exist", column),
+        location!(),
+    ))?;
+    let indices = dataset.load_indices().await?;
+    // Skip the scalar indices on the column, the indices written by older versions
+    // have no details and are assumed to be vector indices
+    let index_meta = indices
+        .iter()
+        .find(|idx| {
+            idx.fields == [field.id]
+                && matches!(infer_index_type(idx), Some(IndexType::Vector) | None)
+        })
+        .ok_or(Error::invalid_input(
+            format!("no vector index on column {}", column),
+            location!(),
+        ))?;
+    let index = dataset
+        .open_vector_index(column, &index_meta.uuid.to_string(), &NoOpMetricsCollector)
+        .await?;
+    // The search parameters tuned here are those of the IVF indices
+    if index.index_type() == IndexType::DiskAnn {
+        return Err(Error::NotSupported {
+            source: format!(
+                "tuning {} index on column {} is not supported, only IVF indices can be tuned",
+                index.index_type(),
+                column
+            )
+            .into(),
+            location: location!(),
+        });
+    }
+    let num_partitions = index.ivf_model().num_partitions();
+    let (sub_index_type, quantization_type) = index.sub_index_type();
+
+    let queries = match &params.queries {
+        Some(queries) => queries.clone(),
+        None => {
+            let num_queries = params.num_queries.max(1);
+            // the sample may be larger than requested, e.g. for nullable columns
+            let queries = maybe_sample_training_data(dataset, column, num_queries).await?;
+            queries.slice(0, queries.len().min(num_queries))
+        }
+    };
+    let ground_truth = ground_truth(dataset, column, &queries, params.k).await?;
+
+    let nprobes = if params.nprobes.is_empty() {
+        let mut nprobes = std::iter::successors(Some(1), |n| Some(n * 2))
+            .take_while(|n| *n < num_partitions)
+            .collect::<Vec<_>>();
+        nprobes.push(num_partitions);
+        nprobes
+    } else {
+        params.nprobes.clone()
+    };
+    let refine_factors = if !params.refine_factors.is_empty() {
+        params.refine_factors.clone()
+    } else if matches!(quantization_type, QuantizationType::Flat) {
+        vec![None]
+    } else {
+        vec![None, Some(2), Some(5), Some(10)]
+    };
+    let ef = if !matches!(sub_index_type, SubIndexType::Hnsw) {
+        vec![None]
+    } else if params.ef.is_empty() {
+        [1, 2, 4, 8]
+            .iter()
+            .map(|factor| Some((params.k * factor).max(10)))
+            .collect()
+    } else {
+        params.ef.iter().copied().map(Some).collect()
+    };
+
+    // warm up the index cache with the most expensive settings
+    let warm_up = SearchSettings {
+        nprobes: nprobes.iter().copied().max().unwrap_or(1),
+        refine_factor: None,
+        ef: ef.iter().copied().max().flatten(),
+    };
+    evaluate_recall(dataset, column, &queries, &ground_truth, params.k, &warm_up).await?;
+
+    let mut evaluations = Vec::new();
+    for refine_factor in &refine_factors {
+        for ef in &ef {
+            for nprobes in &nprobes {
+                let settings = SearchSettings {
+                    nprobes: *nprobes,
+                    refine_factor: *refine_factor,
+                    ef: *ef,
+                };
+                let evaluation = evaluate_recall(
+                    dataset,
+                    column,
+                    &queries,
+                    &ground_truth,
+                    params.k,
+                    &settings,
+                )
+                .await?;
+                let is_exact = evaluation.recall >= 1.0;
+                evaluations.push(evaluation);
+                // probing more partitions can't improve the recall
+                if is_exact {
+                    break;
+                }
+            }
+        }
+    }
+
+    let recommended = evaluations
+        .iter()
+        .enumerate()
+        .filter(|(_, evaluation)| evaluation.recall >= params.target_recall)
+        .min_by_key(|(_, evaluation)| (evaluation.latency, evaluation.bytes_read))
+        .map(|(i, _)| i);
+    Ok(TuningReport {
+        k: params.k,
+        num_queries: queries.len(),
+        target_recall: params.target_recall,
+        evaluations,
+        recommended,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::types::Float32Type;
+    use lance_datagen::{array, gen, BatchCount, Dimension, RowCount};
+    use lance_index::vector::diskann::DiskANNParams;
+    use lance_index::vector::pq::PQBuildParams;
+    use lance_index::IndexType;
+    use lance_linalg::distance::DistanceType;
+
+    use crate::index::vector::VectorIndexParams;
+
+    async fn make_dataset(params: &VectorIndexParams) -> Dataset {
+        let reader = gen()
+            .col("vec", array::rand_vec::<Float32Type>(Dimension::from(16)))
+            .into_reader_rows(RowCount::from(500), BatchCount::from(2));
+        let mut dataset = Dataset::write(reader, "memory://", None).await.unwrap();
+        dataset
+            .create_index(&["vec"], IndexType::Vector, None, params, true)
+            .await
+            .unwrap();
+        dataset
+    }
+
+    #[tokio::test]
+    async fn test_tune_ivf_flat() {
+        let dataset = make_dataset(&VectorIndexParams::ivf_flat(8, DistanceType::L2)).await;
+        let params = TuningParams {
+            num_queries: 20,
+            ..Default::default()
+        };
+        let report = tune_vector_index(&dataset, "vec", &params).await.unwrap();
+        assert_eq!(report.num_queries, 20);
+        assert!(!report.evaluations.is_empty());
+        // no refinement for the flat vectors
+        assert!(report.evaluations.iter().all(|evaluation| evaluation
+            .settings
+            .refine_factor
+            .is_none()
+            && evaluation.settings.ef.is_none()));
+        // the recall increases with nprobes, and searching all the partitions is exact
+        assert!(report
+            .evaluations
+            .windows(2)
+            .all(|w| w[0].recall <= w[1].recall));
+        assert_eq!(report.evaluations.last().unwrap().recall, 1.0);
+
+        let recommendation = report.recommendation().unwrap();
+        assert!(recommendation.recall >= params.target_recall);
+    }
+
+    #[tokio::test]
+    async fn test_tune_ivf_pq() {
+        let dataset = make_dataset(&VectorIndexParams::ivf_pq(4, 8, 4, DistanceType::L2, 10)).await;
+        let queries = maybe_sample_training_data(&dataset, "vec", 10)
+            .await
+            .unwrap();
+        let params = TuningParams {
+            queries: Some(queries.clone()),
+            // ignored since the queries are given
+            num_queries: 5,
+            nprobes: vec![1, 4],
+            refine_factors: vec![None, Some(10)],
+            target_recall: 0.9,
+            ..Default::default()
+        };
+        let report = tune_vector_index(&dataset, "vec", &params).await.unwrap();
+        assert_eq!(report.num_queries, 10);
+        assert_eq!(report.evaluations.len(), 4);
+        let exhaustive = report
+            .evaluations
+            .iter()
+            .find(|evaluation| {
+                evaluation.settings
+                    == SearchSettings {
+                        nprobes: 4,
+                        refine_factor: Some(10),
+                        ef: None,
+                    }
+            })
+            .unwrap();
+        assert!(exhaustive.recall >= 0.9);
+        assert!(report.recommendation().unwrap().recall >= 0.9);
+
+        // the ground truth has k neighbors for each query
+        let truth = ground_truth(&dataset, "vec", &queries, 10).await.unwrap();
+        assert_eq!(truth.len(), 10);
+        assert!(truth.iter().all(|neighbors| neighbors.len() == 10));
+    }
+
+    #[tokio::test]
+    async fn test_tune_diskann_not_supported() {
+        let dataset = make_dataset(&VectorIndexParams::with_diskann_params(
+            DistanceType::L2,
+            DiskANNParams::new(16, 1.2, 40),
+            PQBuildParams::new(4, 8),
+        ))
+        .await;
+        let err = tune_vector_index(&dataset, "vec", &TuningParams::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::NotSupported { .. }), "{}", err);
+    }
+}
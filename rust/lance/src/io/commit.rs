@@ -574,7 +574,7 @@ pub(crate) async fn do_commit_detached_transaction(
                 Transaction::restore_old_manifest(
                     object_store,
                     commit_handler,
-                    &dataset.base,
+                    &dataset.manifest_base(),
                     version,
                     write_config,
                     &transaction_file,
@@ -604,7 +604,7 @@ pub(crate) async fn do_commit_detached_transaction(
         let result = write_manifest_file(
             object_store,
             commit_handler,
-            &dataset.base,
+            &dataset.manifest_base(),
             &mut manifest,
             if indices.is_empty() {
                 None
@@ -789,7 +789,7 @@ pub(crate) async fn commit_transaction(
                 Transaction::restore_old_manifest(
                     object_store,
                     commit_handler,
-                    &dataset.base,
+                    &dataset.manifest_base(),
                     version,
                     write_config,
                     &transaction_file,
@@ -826,7 +826,7 @@ pub(crate) async fn commit_transaction(
         let result = write_manifest_file(
             object_store,
             commit_handler,
-            &dataset.base,
+            &dataset.manifest_base(),
             &mut manifest,
             if indices.is_empty() {
                 None
@@ -841,7 +841,8 @@ pub(crate) async fn commit_transaction(
         match result {
             Ok(manifest_location) => {
                 // Cache both the transaction file and manifest
-                let cache_path = transaction_file_cache_path(&dataset.base, target_version);
+                let cache_path =
+                    transaction_file_cache_path(&dataset.manifest_base(), target_version);
                 dataset
                     .session()
                     .file_metadata_cache
@@ -852,7 +853,7 @@ pub(crate) async fn commit_transaction(
                 );
                 if !indices.is_empty() {
                     dataset.session().index_cache.insert_metadata(
-                        dataset.base.as_ref(),
+                        dataset.manifest_base().as_ref(),
                         target_version,
                         Arc::new(indices),
                     );
@@ -1389,6 +1390,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             },
             Fragment {
                 id: 1,
@@ -1399,6 +1401,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             },
         ];
 
@@ -1430,6 +1433,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             },
             Fragment {
                 id: 1,
@@ -1440,6 +1444,7 @@ mod tests {
                 deletion_file: None,
                 row_id_meta: None,
                 physical_rows: None,
+                partition: vec![],
             },
         ];
         assert_eq!(manifest.fragments.as_ref(), &expected_fragments);
@@ -5,9 +5,11 @@
 //!
 //! WARNING: Internal API with no stability guarantees.
 
+pub mod diversify;
 mod filter;
 pub mod filtered_read;
 pub mod fts;
+pub mod hybrid;
 pub(crate) mod knn;
 mod optimizer;
 mod projection;
@@ -15,6 +17,7 @@ mod pushdown_scan;
 mod rowids;
 pub mod scalar_index;
 mod scan;
+pub mod sparse;
 mod take;
 #[cfg(test)]
 pub mod testing;
@@ -0,0 +1,516 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Execution node that diversifies the results of a vector search
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::AsArray;
+use arrow::compute::{concat_batches, take_record_batch};
+use arrow::datatypes::Float32Type;
+use arrow_array::{Array, RecordBatch, UInt32Array};
+use arrow_row::{RowConverter, SortField};
+use datafusion::common::Statistics;
+use datafusion::error::{DataFusionError, Result as DataFusionResult};
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::physical_plan::execution_plan::{Boundedness, EmissionType};
+use datafusion::physical_plan::metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    collect, DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties,
+};
+use datafusion_physical_expr::{EquivalenceProperties, Partitioning};
+use futures::future::BoxFuture;
+use futures::{stream, StreamExt};
+use lance_core::utils::tracing::StreamTracingExt;
+use lance_index::vector::DIST_COL;
+use lance_linalg::distance::DistanceType;
+use snafu::location;
+use tracing::instrument;
+
+use crate::{Error, Result};
+
+/// How the nearest neighbors are diversified.
+#[derive(Debug, Clone, Default)]
+pub struct DiversityParams {
+    /// The lambda of maximal marginal relevance, in `[0, 1]`.
+    ///
+    /// 1 ranks the results only by their distances to the query, and 0 only by
+    /// their distances to the results already selected.
+    pub mmr_lambda: Option<f32>,
+
+    /// At most this many results are returned for each value of the column.
+    pub group_limit: Option<(String, usize)>,
+}
+
+impl DiversityParams {
+    pub fn is_empty(&self) -> bool {
+        self.mmr_lambda.is_none() && self.group_limit.is_none()
+    }
+}
+
+/// Plans the search of the given number of nearest candidates
+pub type CandidatesPlanner =
+    Arc<dyn Fn(usize) -> BoxFuture<'static, Result<Arc<dyn ExecutionPlan>>> + Send + Sync>;
+
+/// Selects the `k` results of a vector search that satisfy the [`DiversityParams`].
+///
+/// The input fetches the `fetch_size` nearest candidates. If they are not enough to
+/// select `k` results, the candidates are searched again, fetching `overfetch` times
+/// more of them each time, until there are `k` results or the search returns fewer
+/// candidates than it fetches, i.e. there are no more candidates to fetch.
+///
+/// With maximal marginal relevance, each result is selected by
+/// `lambda * -distance + (1 - lambda) * min_distance_to_selected`, where the distances
+/// between the results are computed from their vectors with the distance type of the search.
+/// The results are returned in the order they are selected.
+pub struct DiversifyExec {
+    input: Arc<dyn ExecutionPlan>,
+    fetch_size: usize,
+    overfetch: usize,
+    plan_candidates: CandidatesPlanner,
+    k: usize,
+    params: DiversityParams,
+    vector_column: String,
+    distance_type: DistanceType,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl std::fmt::Debug for DiversifyExec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiversifyExec")
+            .field("input", &self.input)
+            .field("fetch_size", &self.fetch_size)
+            .field("overfetch", &self.overfetch)
+            .field("k", &self.k)
+            .field("params", &self.params)
+            .field("vector_column", &self.vector_column)
+            .field("distance_type", &self.distance_type)
+            .finish()
+    }
+}
+
+impl DisplayAs for DiversifyExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mmr = self
+            .params
+            .mmr_lambda
+            .map(|lambda| format!("mmr_lambda={}", lambda));
+        let group_limit = self
+            .params
+            .group_limit
+            .as_ref()
+            .map(|(column, limit)| format!("group_limit={}:{}", column, limit));
+        let params = [mmr, group_limit].into_iter().flatten();
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "Diversify: k={}, fetch_size={}, overfetch={}",
+                    self.k, self.fetch_size, self.overfetch
+                )?;
+                for param in params {
+                    write!(f, ", {}", param)?;
+                }
+                Ok(())
+            }
+            DisplayFormatType::TreeRender => {
+                write!(
+                    f,
+                    "Diversify\nk={}\nfetch_size={}\noverfetch={}",
+                    self.k, self.fetch_size, self.overfetch
+                )?;
+                for param in params {
+                    write!(f, "\n{}", param)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl DiversifyExec {
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(
+        input: Arc<dyn ExecutionPlan>,
+        fetch_size: usize,
+        overfetch: usize,
+        plan_candidates: CandidatesPlanner,
+        k: usize,
+        params: DiversityParams,
+        vector_column: String,
+        distance_type: DistanceType,
+    ) -> Result<Self> {
+        if overfetch < 2 {
+            return Err(Error::invalid_input(
+                format!(
+                    "the candidates must be over-fetched at least 2 times, got {}",
+                    overfetch
+                ),
+                location!(),
+            ));
+        }
+        let schema = input.schema();
+        let mut required_columns = vec![DIST_COL];
+        if params.mmr_lambda.is_some() {
+            required_columns.push(&vector_column);
+        }
+        if let Some((column, _)) = &params.group_limit {
+            required_columns.push(column);
+        }
+        for column in &required_columns {
+            if schema.column_with_name(column).is_none() {
+                return Err(Error::Internal {
+                    message: format!("DiversifyExec: the input is missing the column {}", column),
+                    location: location!(),
+                });
+            }
+        }
+
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(schema),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Ok(Self {
+            input,
+            fetch_size,
+            overfetch,
+            plan_candidates,
+            k,
+            params,
+            vector_column,
+            distance_type,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+// select the k results that satisfy the diversity params from the candidates
+fn diversify(
+    candidates: &RecordBatch,
+    k: usize,
+    params: &DiversityParams,
+    vector_column: &str,
+    distance_type: DistanceType,
+) -> Result<RecordBatch> {
+    let distances = candidates[DIST_COL].as_primitive::<Float32Type>();
+    let mut order = (0..candidates.num_rows())
+        .filter(|&i| distances.is_valid(i))
+        .collect::<Vec<_>>();
+    order.sort_by(|&a, &b| distances.value(a).total_cmp(&distances.value(b)));
+
+    // the group of each candidate, and the number of results selected from each group
+    let groups = params
+        .group_limit
+        .as_ref()
+        .map(|(column, limit)| -> Result<_> {
+            let values = candidates[column.as_str()].clone();
+            let converter = RowConverter::new(vec![SortField::new(values.data_type().clone())])?;
+            Ok((converter.convert_columns(&[values])?, *limit))
+        })
+        .transpose()?;
+    let mut group_counts = HashMap::new();
+    let is_group_full = |i: usize, counts: &HashMap<_, usize>| match &groups {
+        Some((rows, limit)) => counts.get(&rows.row(i)).copied().unwrap_or_default() >= *limit,
+        None => false,
+    };
+
+    let mut selected = Vec::with_capacity(k);
+    match params.mmr_lambda {
+        None => {
+            for i in order {
+                if selected.len() >= k {
+                    break;
+                }
+                if !is_group_full(i, &group_counts) {
+                    if let Some((rows, _)) = &groups {
+                        *group_counts.entry(rows.row(i)).or_default() += 1;
+                    }
+                    selected.push(i as u32);
+                }
+            }
+        }
+        Some(lambda) => {
+            let vectors = candidates[vector_column]
+                .as_fixed_size_list_opt()
+                .ok_or_else(|| {
+                    Error::invalid_input(
+                        format!(
+                            "maximal marginal relevance requires a vector column, but {} is {}",
+                            vector_column,
+                            candidates[vector_column].data_type()
+                        ),
+                        location!(),
+                    )
+                })?;
+            let dist_func = distance_type.arrow_batch_func();
+            // the distance of each candidate to the nearest selected result
+            let mut min_distances = vec![f32::INFINITY; candidates.num_rows()];
+            let mut remaining = order;
+            while selected.len() < k {
+                remaining.retain(|&i| !is_group_full(i, &group_counts));
+                let best = remaining
+                    .iter()
+                    .enumerate()
+                    .map(|(pos, &i)| {
+                        let score = match selected.is_empty() {
+                            true => -distances.value(i),
+                            false => {
+                                -lambda * distances.value(i) + (1.0 - lambda) * min_distances[i]
+                            }
+                        };
+                        (pos, score)
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+                let Some((pos, _)) = best else {
+                    break;
+                };
+                let i = remaining.remove(pos);
+                if let Some((rows, _)) = &groups {
+                    *group_counts.entry(rows.row(i)).or_default() += 1;
+                }
+                selected.push(i as u32);
+
+                let dists = dist_func(vectors.value(i).as_ref(), vectors)?;
+                for (min_dist, dist) in min_distances.iter_mut().zip(dists.iter()) {
+                    if let Some(dist) = dist {
+                        *min_dist = min_dist.min(dist);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(take_record_batch(candidates, &UInt32Array::from(selected))?)
+}
+
+impl ExecutionPlan for DiversifyExec {
+    fn name(&self) -> &str {
+        "DiversifyExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(
+                "DiversifyExec: invalid number of children".to_string(),
+            ));
+        }
+        Ok(Arc::new(Self::try_new(
+            children.remove(0),
+            self.fetch_size,
+            self.overfetch,
+            self.plan_candidates.clone(),
+            self.k,
+            self.params.clone(),
+            self.vector_column.clone(),
+            self.distance_type,
+        )?))
+    }
+
+    #[instrument(name = "diversify_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let mut input = self.input.clone();
+        let mut fetch_size = self.fetch_size;
+        let overfetch = self.overfetch;
+        let plan_candidates = self.plan_candidates.clone();
+        let k = self.k;
+        let params = self.params.clone();
+        let vector_column = self.vector_column.clone();
+        let distance_type = self.distance_type;
+        let schema = self.schema();
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+
+        let stream_schema = schema.clone();
+        let stream = stream::once(async move {
+            let results = loop {
+                let batches = collect(input, context.clone()).await?;
+                let candidates = concat_batches(&stream_schema, &batches)?;
+
+                let timer = baseline_metrics.elapsed_compute().timer();
+                let results = diversify(&candidates, k, &params, &vector_column, distance_type)?;
+                timer.done();
+                if results.num_rows() >= k || candidates.num_rows() < fetch_size {
+                    break results;
+                }
+                fetch_size = fetch_size.saturating_mul(overfetch);
+                input = plan_candidates(fetch_size).await?;
+            };
+            baseline_metrics.record_output(results.num_rows());
+            Ok::<_, DataFusionError>(results)
+        });
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream.stream_in_current_span().boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        Ok(Statistics::new_unknown(&self.schema()))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arrow_array::{FixedSizeListArray, Float32Array, Int32Array};
+    use datafusion::execution::TaskContext;
+    use futures::FutureExt;
+    use lance_arrow::FixedSizeListArrayExt;
+
+    use crate::io::exec::testing::TestingExec;
+
+    fn candidates() -> RecordBatch {
+        // 0 and 1 are the same vector, 2 is far from both
+        let vectors = Float32Array::from(vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.1]);
+        RecordBatch::try_from_iter(vec![
+            (
+                "vec",
+                Arc::new(FixedSizeListArray::try_new_from_values(vectors, 2).unwrap()) as _,
+            ),
+            ("doc", Arc::new(Int32Array::from(vec![1, 1, 2, 3])) as _),
+            (
+                DIST_COL,
+                Arc::new(Float32Array::from(vec![0.1, 0.1, 0.5, 0.2])) as _,
+            ),
+        ])
+        .unwrap()
+    }
+
+    fn selected_docs(batch: &RecordBatch) -> Vec<i32> {
+        batch["doc"]
+            .as_primitive::<arrow::datatypes::Int32Type>()
+            .values()
+            .to_vec()
+    }
+
+    #[test]
+    fn test_group_limit() {
+        let params = DiversityParams {
+            mmr_lambda: None,
+            group_limit: Some(("doc".to_string(), 1)),
+        };
+        let results = diversify(&candidates(), 3, &params, "vec", DistanceType::L2).unwrap();
+        assert_eq!(selected_docs(&results), [1, 3, 2]);
+
+        // fewer results than k if there are not enough groups
+        let results = diversify(&candidates(), 4, &params, "vec", DistanceType::L2).unwrap();
+        assert_eq!(results.num_rows(), 3);
+    }
+
+    #[test]
+    fn test_mmr() {
+        // lambda 1 ranks by the distances to the query only
+        let params = DiversityParams {
+            mmr_lambda: Some(1.0),
+            group_limit: None,
+        };
+        let results = diversify(&candidates(), 3, &params, "vec", DistanceType::L2).unwrap();
+        assert_eq!(selected_docs(&results), [1, 1, 3]);
+
+        // lambda 0.5 prefers the candidates far from the selected ones
+        let params = DiversityParams {
+            mmr_lambda: Some(0.5),
+            group_limit: None,
+        };
+        let results = diversify(&candidates(), 2, &params, "vec", DistanceType::L2).unwrap();
+        assert_eq!(selected_docs(&results), [1, 2]);
+
+        // with both constraints
+        let params = DiversityParams {
+            mmr_lambda: Some(0.5),
+            group_limit: Some(("doc".to_string(), 1)),
+        };
+        let results = diversify(&candidates(), 4, &params, "vec", DistanceType::L2).unwrap();
+        assert_eq!(selected_docs(&results), [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_skewed_groups() {
+        // the 2000 nearest candidates are all in the group 0, then one candidate per group
+        let num_rows = 2010;
+        let candidates = RecordBatch::try_from_iter(vec![
+            (
+                "doc",
+                Arc::new(Int32Array::from_iter_values(
+                    (0..num_rows).map(|i| (i - 1999).max(0)),
+                )) as _,
+            ),
+            (
+                DIST_COL,
+                Arc::new(Float32Array::from_iter_values(
+                    (0..num_rows).map(|i| i as f32),
+                )) as _,
+            ),
+        ])
+        .unwrap();
+        let plan_candidates: CandidatesPlanner = Arc::new(move |fetch_size| {
+            let batch = candidates.slice(0, fetch_size.min(candidates.num_rows()));
+            async move { Ok(Arc::new(TestingExec::new(vec![batch])) as Arc<dyn ExecutionPlan>) }
+                .boxed()
+        });
+        let params = DiversityParams {
+            mmr_lambda: None,
+            group_limit: Some(("doc".to_string(), 1)),
+        };
+        let diversify = |k: usize| {
+            let plan_candidates = plan_candidates.clone();
+            let params = params.clone();
+            async move {
+                let exec = DiversifyExec::try_new(
+                    plan_candidates(k * 4).await.unwrap(),
+                    k * 4,
+                    4,
+                    plan_candidates,
+                    k,
+                    params,
+                    "vec".to_string(),
+                    DistanceType::L2,
+                )
+                .unwrap();
+                let results = collect(Arc::new(exec), Arc::new(TaskContext::default()))
+                    .await
+                    .unwrap();
+                concat_batches(&results[0].schema(), &results).unwrap()
+            }
+        };
+
+        // the candidates are fetched until there are enough groups
+        let results = diversify(5).await;
+        assert_eq!(selected_docs(&results), [0, 1, 2, 3, 4]);
+
+        // or until there are no more candidates
+        let results = diversify(20).await;
+        assert_eq!(selected_docs(&results), (0..=10).collect::<Vec<_>>());
+    }
+}
@@ -1,12 +1,13 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-FileCopyrightText: Copyright The Lance Authors
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use arrow::array::AsArray;
 use arrow::datatypes::{Float32Type, UInt64Type};
 use arrow_array::{Float32Array, RecordBatch, UInt64Array};
+use arrow_schema::Schema;
 use datafusion::common::Statistics;
 use datafusion::error::{DataFusionError, Result as DataFusionResult};
 use datafusion::execution::SendableRecordBatchStream;
@@ -18,16 +19,24 @@ use datafusion_physical_expr::{Distribution, EquivalenceProperties, Partitioning
 use futures::stream::{self};
 use futures::{FutureExt, StreamExt, TryStreamExt};
 use itertools::Itertools;
-use lance_core::{utils::tracing::StreamTracingExt, ROW_ID};
+use lance_arrow::RecordBatchExt;
+use lance_core::{utils::tracing::StreamTracingExt, Error, Result, ROW_ID};
+use lance_index::scalar::inverted::highlight::{
+    HighlightParams, Highlighter, HIGHLIGHT_FIELD, MATCH_OFFSETS_FIELD,
+};
 use lance_index::scalar::inverted::query::{
-    collect_tokens, BoostQuery, FtsSearchParams, MatchQuery, PhraseQuery,
+    collect_tokens, BoostQuery, FtsQuery, FtsSearchParams, MatchQuery, MultiMatchQuery, Operator,
+    PhraseQuery,
 };
+use lance_index::scalar::inverted::scorer::{BM25FField, BM25FScorer, ScoringModel};
 use lance_index::scalar::inverted::{
-    flat_bm25_search_stream, InvertedIndex, FTS_SCHEMA, SCORE_COL,
+    flat_bm25_search_stream, flat_pattern_search_stream, flat_token_freqs, InvertedIndex,
+    FTS_SCHEMA, SCORE_COL,
 };
 use lance_index::scalar::ScalarIndexType;
 use lance_index::{prefilter::PreFilter, scalar::inverted::query::BooleanQuery};
 use lance_index::{DatasetIndexExt, ScalarIndexCriteria};
+use snafu::location;
 use tracing::instrument;
 
 use crate::{index::DatasetIndexInternalExt, Dataset};
@@ -120,50 +129,680 @@ impl ExecutionPlan for MatchQueryExec {
                     ));
                 }
 
-                Self {
-                    dataset: self.dataset.clone(),
-                    query: self.query.clone(),
-                    params: self.params.clone(),
-                    prefilter_source: PreFilterSource::None,
-                    properties: self.properties.clone(),
-                    metrics: ExecutionPlanMetricsSet::new(),
-                }
+                Self {
+                    dataset: self.dataset.clone(),
+                    query: self.query.clone(),
+                    params: self.params.clone(),
+                    prefilter_source: PreFilterSource::None,
+                    properties: self.properties.clone(),
+                    metrics: ExecutionPlanMetricsSet::new(),
+                }
+            }
+            1 => {
+                let src = children.pop().unwrap();
+                let prefilter_source = match &self.prefilter_source {
+                    PreFilterSource::FilteredRowIds(_) => {
+                        PreFilterSource::FilteredRowIds(src.clone())
+                    }
+                    PreFilterSource::ScalarIndexQuery(_) => {
+                        PreFilterSource::ScalarIndexQuery(src.clone())
+                    }
+                    PreFilterSource::None => {
+                        return Err(DataFusionError::Internal(
+                            "Unexpected prefilter source".to_string(),
+                        ));
+                    }
+                };
+
+                Self {
+                    dataset: self.dataset.clone(),
+                    query: self.query.clone(),
+                    params: self.params.clone(),
+                    prefilter_source,
+                    properties: self.properties.clone(),
+                    metrics: ExecutionPlanMetricsSet::new(),
+                }
+            }
+            _ => {
+                return Err(DataFusionError::Internal(
+                    "Unexpected number of children".to_string(),
+                ));
+            }
+        };
+        Ok(Arc::new(plan))
+    }
+
+    #[instrument(name = "match_query_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let query = self.query.clone();
+        let params = self.params.clone();
+        let ds = self.dataset.clone();
+        let prefilter_source = self.prefilter_source.clone();
+        let metrics = Arc::new(IndexMetrics::new(&self.metrics, partition));
+        let column = query.column.ok_or(DataFusionError::Execution(format!(
+            "column not set for MatchQuery {}",
+            query.terms
+        )))?;
+
+        let stream = stream::once(async move {
+            let index_meta = ds
+                .load_scalar_index(
+                    ScalarIndexCriteria::default()
+                        .for_column(&column)
+                        .with_type(ScalarIndexType::Inverted),
+                )
+                .await?
+                .ok_or(DataFusionError::Execution(format!(
+                    "No Inverted index found for column {}",
+                    column,
+                )))?;
+            let uuid = index_meta.uuid.to_string();
+            let index = ds
+                .open_generic_index(&column, &uuid, metrics.as_ref())
+                .await?;
+
+            let pre_filter = build_prefilter(
+                context.clone(),
+                partition,
+                &prefilter_source,
+                ds,
+                &[index_meta],
+            )?;
+
+            let inverted_idx = index
+                .as_any()
+                .downcast_ref::<InvertedIndex>()
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "Index for column {} is not an inverted index",
+                        column,
+                    ))
+                })?;
+
+            let is_fuzzy = matches!(query.fuzziness, Some(n) if n != 0);
+            let params = params
+                .with_fuzziness(query.fuzziness)
+                .with_max_expansions(query.max_expansions)
+                .with_prefix_length(query.prefix_length);
+            let mut tokenizer = match is_fuzzy {
+                false => inverted_idx.tokenizer(),
+                true => tantivy::tokenizer::TextAnalyzer::from(
+                    tantivy::tokenizer::SimpleTokenizer::default(),
+                ),
+            };
+            let tokens = collect_tokens(&query.terms, &mut tokenizer, None);
+
+            pre_filter.wait_for_ready().await?;
+            let (doc_ids, mut scores) = inverted_idx
+                .bm25_search(
+                    tokens.into(),
+                    params.into(),
+                    query.operator,
+                    pre_filter,
+                    metrics,
+                )
+                .boxed()
+                .await?;
+            scores.iter_mut().for_each(|s| {
+                *s *= query.boost;
+            });
+
+            let batch = RecordBatch::try_new(
+                FTS_SCHEMA.clone(),
+                vec![
+                    Arc::new(UInt64Array::from(doc_ids)),
+                    Arc::new(Float32Array::from(scores)),
+                ],
+            )?;
+            Ok::<_, DataFusionError>(batch)
+        });
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.stream_in_current_span().boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<datafusion::physical_plan::Statistics> {
+        Ok(Statistics::new_unknown(&FTS_SCHEMA))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
+/// Calculates the FTS score for each row in the input
+#[derive(Debug)]
+pub struct FlatMatchQueryExec {
+    dataset: Arc<Dataset>,
+    query: MatchQuery,
+    params: FtsSearchParams,
+    unindexed_input: Arc<dyn ExecutionPlan>,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DisplayAs for FlatMatchQueryExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "FlatMatchQuery: query={}", self.query.terms)
+            }
+            DisplayFormatType::TreeRender => {
+                write!(f, "FlatMatchQuery\nquery={}", self.query.terms)
+            }
+        }
+    }
+}
+
+impl FlatMatchQueryExec {
+    pub fn new(
+        dataset: Arc<Dataset>,
+        query: MatchQuery,
+        params: FtsSearchParams,
+        unindexed_input: Arc<dyn ExecutionPlan>,
+    ) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(FTS_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Incremental,
+            Boundedness::Bounded,
+        );
+        Self {
+            dataset,
+            query,
+            params,
+            unindexed_input,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl ExecutionPlan for FlatMatchQueryExec {
+    fn name(&self) -> &str {
+        "FlatMatchQueryExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.unindexed_input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(
+                "Unexpected number of children".to_string(),
+            ));
+        }
+        let unindexed_input = children.pop().unwrap();
+        Ok(Arc::new(Self {
+            dataset: self.dataset.clone(),
+            query: self.query.clone(),
+            params: self.params.clone(),
+            unindexed_input,
+            properties: self.properties.clone(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        }))
+    }
+
+    #[instrument(name = "flat_match_query_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let query = self.query.clone();
+        let params = self.params.clone();
+        let ds = self.dataset.clone();
+        let metrics = Arc::new(IndexMetrics::new(&self.metrics, partition));
+        let unindexed_input = self.unindexed_input.execute(partition, context)?;
+
+        let column = query.column.ok_or(DataFusionError::Execution(format!(
+            "column not set for MatchQuery {}",
+            query.terms
+        )))?;
+
+        let stream = stream::once(async move {
+            let index_meta = ds
+                .load_scalar_index(
+                    ScalarIndexCriteria::default()
+                        .for_column(&column)
+                        .with_type(ScalarIndexType::Inverted),
+                )
+                .await?
+                .ok_or(DataFusionError::Execution(format!(
+                    "No Inverted index found for column {}",
+                    column,
+                )))?;
+            let uuid = index_meta.uuid.to_string();
+            let index = ds
+                .open_generic_index(&column, &uuid, metrics.as_ref())
+                .await?;
+            let inverted_idx = index
+                .as_any()
+                .downcast_ref::<InvertedIndex>()
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "Index for column {} is not an inverted index",
+                        column,
+                    ))
+                })?;
+            Ok::<_, DataFusionError>(flat_bm25_search_stream(
+                unindexed_input,
+                column,
+                query.terms,
+                &params.scoring,
+                inverted_idx,
+            ))
+        })
+        .try_flatten_unordered(None);
+        Ok(Box::pin(InstrumentedRecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.stream_in_current_span().boxed(),
+            partition,
+            &self.metrics,
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<datafusion::physical_plan::Statistics> {
+        Ok(Statistics::new_unknown(&FTS_SCHEMA))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
+/// Scores the documents matching a `MultiMatchQuery` across the fields with BM25F
+#[derive(Debug)]
+pub struct MultiMatchQueryExec {
+    dataset: Arc<Dataset>,
+    query: MultiMatchQuery,
+    params: FtsSearchParams,
+    prefilter_source: PreFilterSource,
+    // the scans of the unindexed fragments of each field
+    unindexed_inputs: Vec<Option<Arc<dyn ExecutionPlan>>>,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DisplayAs for MultiMatchQueryExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let columns = self
+            .query
+            .match_queries
+            .iter()
+            .filter_map(|query| query.column.as_deref())
+            .join(",");
+        let terms = self
+            .query
+            .match_queries
+            .first()
+            .map(|query| query.terms.as_str())
+            .unwrap_or_default();
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "MultiMatchQuery: query={}, columns=[{}]", terms, columns)
+            }
+            DisplayFormatType::TreeRender => {
+                write!(f, "MultiMatchQuery\nquery={}\ncolumns=[{}]", terms, columns)
+            }
+        }
+    }
+}
+
+impl MultiMatchQueryExec {
+    pub fn new(
+        dataset: Arc<Dataset>,
+        query: MultiMatchQuery,
+        params: FtsSearchParams,
+        prefilter_source: PreFilterSource,
+        unindexed_inputs: Vec<Option<Arc<dyn ExecutionPlan>>>,
+    ) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(FTS_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Self {
+            dataset,
+            query,
+            params,
+            prefilter_source,
+            unindexed_inputs,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl ExecutionPlan for MultiMatchQueryExec {
+    fn name(&self) -> &str {
+        "MultiMatchQueryExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        let prefilter = match &self.prefilter_source {
+            PreFilterSource::None => None,
+            PreFilterSource::FilteredRowIds(src) => Some(src),
+            PreFilterSource::ScalarIndexQuery(src) => Some(src),
+        };
+        prefilter
+            .into_iter()
+            .chain(self.unindexed_inputs.iter().flatten())
+            .collect()
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        self.children()
+            .iter()
+            .map(|_| Distribution::SinglePartition)
+            .collect()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if children.len() != self.children().len() {
+            return Err(DataFusionError::Internal(
+                "Unexpected number of children".to_string(),
+            ));
+        }
+        let mut children = children.into_iter();
+        let prefilter_source = match &self.prefilter_source {
+            PreFilterSource::None => PreFilterSource::None,
+            PreFilterSource::FilteredRowIds(_) => {
+                PreFilterSource::FilteredRowIds(children.next().unwrap())
+            }
+            PreFilterSource::ScalarIndexQuery(_) => {
+                PreFilterSource::ScalarIndexQuery(children.next().unwrap())
+            }
+        };
+        let unindexed_inputs = self
+            .unindexed_inputs
+            .iter()
+            .map(|input| input.as_ref().map(|_| children.next().unwrap()))
+            .collect();
+        Ok(Arc::new(Self {
+            dataset: self.dataset.clone(),
+            query: self.query.clone(),
+            params: self.params.clone(),
+            prefilter_source,
+            unindexed_inputs,
+            properties: self.properties.clone(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        }))
+    }
+
+    #[instrument(name = "multi_match_query_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let query = self.query.clone();
+        let params = self.params.clone();
+        let ds = self.dataset.clone();
+        let prefilter_source = self.prefilter_source.clone();
+        let metrics = Arc::new(IndexMetrics::new(&self.metrics, partition));
+        let ScoringModel::BM25F { k1, b, .. } = params.scoring else {
+            return Err(DataFusionError::Internal(
+                "MultiMatchQueryExec requires the BM25F scoring model".to_string(),
+            ));
+        };
+        let unindexed_inputs = self
+            .unindexed_inputs
+            .iter()
+            .map(|input| {
+                input
+                    .as_ref()
+                    .map(|input| input.execute(partition, context.clone()))
+                    .transpose()
+            })
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        let stream = stream::once(async move {
+            let mut index_metas = Vec::with_capacity(query.match_queries.len());
+            let mut indices = Vec::with_capacity(query.match_queries.len());
+            for match_query in &query.match_queries {
+                let column = match_query.column.as_ref().ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "column not set for MatchQuery {}",
+                        match_query.terms
+                    ))
+                })?;
+                let index_meta = ds
+                    .load_scalar_index(
+                        ScalarIndexCriteria::default()
+                            .for_column(column)
+                            .with_type(ScalarIndexType::Inverted),
+                    )
+                    .await?
+                    .ok_or(DataFusionError::Execution(format!(
+                        "No Inverted index found for column {}",
+                        column,
+                    )))?;
+                let uuid = index_meta.uuid.to_string();
+                let index = ds
+                    .open_generic_index(column, &uuid, metrics.as_ref())
+                    .await?;
+                if index.as_any().downcast_ref::<InvertedIndex>().is_none() {
+                    return Err(DataFusionError::Execution(format!(
+                        "Index for column {} is not an inverted index",
+                        column,
+                    )));
+                }
+                index_metas.push(index_meta);
+                indices.push((column.clone(), match_query, index));
+            }
+
+            let pre_filter = build_prefilter(
+                context.clone(),
+                partition,
+                &prefilter_source,
+                ds,
+                &index_metas,
+            )?;
+            pre_filter.wait_for_ready().await?;
+
+            let mut fields = Vec::with_capacity(indices.len());
+            let mut field_freqs = Vec::with_capacity(indices.len());
+            for ((column, match_query, index), unindexed_input) in
+                indices.iter().zip(unindexed_inputs)
+            {
+                let inverted_idx = index.as_any().downcast_ref::<InvertedIndex>().unwrap();
+                let mut tokenizer = inverted_idx.tokenizer();
+                let tokens = collect_tokens(&match_query.terms, &mut tokenizer, None);
+                let mut freqs = inverted_idx
+                    .token_freqs(&tokens, pre_filter.clone(), metrics.as_ref())
+                    .await?;
+                if let Some(mut unindexed_input) = unindexed_input {
+                    while let Some(batch) = unindexed_input.try_next().await? {
+                        freqs.extend(flat_token_freqs(&batch, column, &tokens, &mut tokenizer)?);
+                    }
+                }
+                fields.push(BM25FField {
+                    weight: match_query.boost * params.scoring.field_weight(column),
+                    tokens,
+                    stats: Box::new(inverted_idx.stats()),
+                });
+                field_freqs.push(freqs);
+            }
+
+            let scorer = BM25FScorer::try_new(k1, b, &fields)?;
+            let operator = query
+                .match_queries
+                .first()
+                .map(|query| query.operator)
+                .unwrap_or_default();
+            let (doc_ids, scores) = scorer.search(&field_freqs, operator, params.limit);
+            let batch = RecordBatch::try_new(
+                FTS_SCHEMA.clone(),
+                vec![
+                    Arc::new(UInt64Array::from(doc_ids)),
+                    Arc::new(Float32Array::from(scores)),
+                ],
+            )?;
+            Ok::<_, DataFusionError>(batch)
+        });
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.stream_in_current_span().boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<datafusion::physical_plan::Statistics> {
+        Ok(Statistics::new_unknown(&FTS_SCHEMA))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
+/// Searches the tokens matching the prefix, wildcard or regex query
+#[derive(Debug)]
+pub struct PatternQueryExec {
+    dataset: Arc<Dataset>,
+    query: FtsQuery,
+    params: FtsSearchParams,
+    prefilter_source: PreFilterSource,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DisplayAs for PatternQueryExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "PatternQuery: query={}", self.query.query())
+            }
+            DisplayFormatType::TreeRender => {
+                write!(f, "PatternQuery\nquery={}", self.query.query())
+            }
+        }
+    }
+}
+
+impl PatternQueryExec {
+    /// Create the plan, the query must be a prefix, wildcard or regex query
+    pub fn try_new(
+        dataset: Arc<Dataset>,
+        query: FtsQuery,
+        params: FtsSearchParams,
+        prefilter_source: PreFilterSource,
+    ) -> Result<Self> {
+        if query.as_pattern_query().is_none() {
+            return Err(Error::invalid_input(
+                format!("{} is not a pattern query", query),
+                location!(),
+            ));
+        }
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(FTS_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Ok(Self {
+            dataset,
+            query,
+            params,
+            prefilter_source,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+impl ExecutionPlan for PatternQueryExec {
+    fn name(&self) -> &str {
+        "PatternQueryExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        match &self.prefilter_source {
+            PreFilterSource::None => vec![],
+            PreFilterSource::FilteredRowIds(src) => vec![&src],
+            PreFilterSource::ScalarIndexQuery(src) => vec![&src],
+        }
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        // Prefilter inputs must be a single partition
+        self.children()
+            .iter()
+            .map(|_| Distribution::SinglePartition)
+            .collect()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let prefilter_source = match (children.len(), &self.prefilter_source) {
+            (0, PreFilterSource::None) => PreFilterSource::None,
+            (1, PreFilterSource::FilteredRowIds(_)) => {
+                PreFilterSource::FilteredRowIds(children.pop().unwrap())
             }
-            1 => {
-                let src = children.pop().unwrap();
-                let prefilter_source = match &self.prefilter_source {
-                    PreFilterSource::FilteredRowIds(_) => {
-                        PreFilterSource::FilteredRowIds(src.clone())
-                    }
-                    PreFilterSource::ScalarIndexQuery(_) => {
-                        PreFilterSource::ScalarIndexQuery(src.clone())
-                    }
-                    PreFilterSource::None => {
-                        return Err(DataFusionError::Internal(
-                            "Unexpected prefilter source".to_string(),
-                        ));
-                    }
-                };
-
-                Self {
-                    dataset: self.dataset.clone(),
-                    query: self.query.clone(),
-                    params: self.params.clone(),
-                    prefilter_source,
-                    properties: self.properties.clone(),
-                    metrics: ExecutionPlanMetricsSet::new(),
-                }
+            (1, PreFilterSource::ScalarIndexQuery(_)) => {
+                PreFilterSource::ScalarIndexQuery(children.pop().unwrap())
             }
             _ => {
                 return Err(DataFusionError::Internal(
-                    "Unexpected number of children".to_string(),
+                    "Unexpected children of PatternQueryExec".to_string(),
                 ));
             }
         };
-        Ok(Arc::new(plan))
+        Ok(Arc::new(Self {
+            dataset: self.dataset.clone(),
+            query: self.query.clone(),
+            params: self.params.clone(),
+            prefilter_source,
+            properties: self.properties.clone(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        }))
     }
 
-    #[instrument(name = "match_query_exec", level = "debug", skip_all)]
+    #[instrument(name = "pattern_query_exec", level = "debug", skip_all)]
     fn execute(
         &self,
         partition: usize,
@@ -174,16 +813,19 @@ impl ExecutionPlan for MatchQueryExec {
         let ds = self.dataset.clone();
         let prefilter_source = self.prefilter_source.clone();
         let metrics = Arc::new(IndexMetrics::new(&self.metrics, partition));
-        let column = query.column.ok_or(DataFusionError::Execution(format!(
-            "column not set for MatchQuery {}",
-            query.terms
-        )))?;
 
         let stream = stream::once(async move {
+            let pattern_query = query.as_pattern_query().unwrap();
+            let column = pattern_query
+                .column()
+                .ok_or(DataFusionError::Execution(format!(
+                    "column not set for {}",
+                    query
+                )))?;
             let index_meta = ds
                 .load_scalar_index(
                     ScalarIndexCriteria::default()
-                        .for_column(&column)
+                        .for_column(column)
                         .with_type(ScalarIndexType::Inverted),
                 )
                 .await?
@@ -193,7 +835,7 @@ impl ExecutionPlan for MatchQueryExec {
                 )))?;
             let uuid = index_meta.uuid.to_string();
             let index = ds
-                .open_generic_index(&column, &uuid, metrics.as_ref())
+                .open_generic_index(column, &uuid, metrics.as_ref())
                 .await?;
 
             let pre_filter = build_prefilter(
@@ -214,32 +856,23 @@ impl ExecutionPlan for MatchQueryExec {
                     ))
                 })?;
 
-            let is_fuzzy = matches!(query.fuzziness, Some(n) if n != 0);
-            let params = params
-                .with_fuzziness(query.fuzziness)
-                .with_max_expansions(query.max_expansions)
-                .with_prefix_length(query.prefix_length);
-            let mut tokenizer = match is_fuzzy {
-                false => inverted_idx.tokenizer(),
-                true => tantivy::tokenizer::TextAnalyzer::from(
-                    tantivy::tokenizer::SimpleTokenizer::default(),
-                ),
-            };
-            let tokens = collect_tokens(&query.terms, &mut tokenizer, None);
-
+            // search the expanded tokens as the terms of a match query
+            let tokens = inverted_idx.expand_pattern(pattern_query)?;
+            let params = params.with_fuzziness(Some(0)).with_phrase_slop(None);
             pre_filter.wait_for_ready().await?;
             let (doc_ids, mut scores) = inverted_idx
                 .bm25_search(
                     tokens.into(),
                     params.into(),
-                    query.operator,
+                    Operator::Or,
                     pre_filter,
                     metrics,
                 )
                 .boxed()
                 .await?;
+            let boost = pattern_query.boost();
             scores.iter_mut().for_each(|s| {
-                *s *= query.boost;
+                *s *= boost;
             });
 
             let batch = RecordBatch::try_new(
@@ -271,11 +904,11 @@ impl ExecutionPlan for MatchQueryExec {
     }
 }
 
-/// Calculates the FTS score for each row in the input
+/// Calculates the score of the prefix, wildcard or regex query for each row in the input
 #[derive(Debug)]
-pub struct FlatMatchQueryExec {
+pub struct FlatPatternQueryExec {
     dataset: Arc<Dataset>,
-    query: MatchQuery,
+    query: FtsQuery,
     params: FtsSearchParams,
     unindexed_input: Arc<dyn ExecutionPlan>,
 
@@ -283,46 +916,53 @@ pub struct FlatMatchQueryExec {
     metrics: ExecutionPlanMetricsSet,
 }
 
-impl DisplayAs for FlatMatchQueryExec {
+impl DisplayAs for FlatPatternQueryExec {
     fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match t {
             DisplayFormatType::Default | DisplayFormatType::Verbose => {
-                write!(f, "FlatMatchQuery: query={}", self.query.terms)
+                write!(f, "FlatPatternQuery: query={}", self.query.query())
             }
             DisplayFormatType::TreeRender => {
-                write!(f, "FlatMatchQuery\nquery={}", self.query.terms)
+                write!(f, "FlatPatternQuery\nquery={}", self.query.query())
             }
         }
     }
 }
 
-impl FlatMatchQueryExec {
-    pub fn new(
+impl FlatPatternQueryExec {
+    /// Create the plan, the query must be a prefix, wildcard or regex query
+    pub fn try_new(
         dataset: Arc<Dataset>,
-        query: MatchQuery,
+        query: FtsQuery,
         params: FtsSearchParams,
         unindexed_input: Arc<dyn ExecutionPlan>,
-    ) -> Self {
+    ) -> Result<Self> {
+        if query.as_pattern_query().is_none() {
+            return Err(Error::invalid_input(
+                format!("{} is not a pattern query", query),
+                location!(),
+            ));
+        }
         let properties = PlanProperties::new(
             EquivalenceProperties::new(FTS_SCHEMA.clone()),
             Partitioning::RoundRobinBatch(1),
             EmissionType::Incremental,
             Boundedness::Bounded,
         );
-        Self {
+        Ok(Self {
             dataset,
             query,
             params,
             unindexed_input,
             properties,
             metrics: ExecutionPlanMetricsSet::new(),
-        }
+        })
     }
 }
 
-impl ExecutionPlan for FlatMatchQueryExec {
+impl ExecutionPlan for FlatPatternQueryExec {
     fn name(&self) -> &str {
-        "FlatMatchQueryExec"
+        "FlatPatternQueryExec"
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
@@ -353,27 +993,30 @@ impl ExecutionPlan for FlatMatchQueryExec {
         }))
     }
 
-    #[instrument(name = "flat_match_query_exec", level = "debug", skip_all)]
+    #[instrument(name = "flat_pattern_query_exec", level = "debug", skip_all)]
     fn execute(
         &self,
         partition: usize,
         context: Arc<datafusion::execution::TaskContext>,
     ) -> DataFusionResult<SendableRecordBatchStream> {
         let query = self.query.clone();
+        let params = self.params.clone();
         let ds = self.dataset.clone();
         let metrics = Arc::new(IndexMetrics::new(&self.metrics, partition));
         let unindexed_input = self.unindexed_input.execute(partition, context)?;
 
-        let column = query.column.ok_or(DataFusionError::Execution(format!(
-            "column not set for MatchQuery {}",
-            query.terms
-        )))?;
-
         let stream = stream::once(async move {
+            let pattern_query = query.as_pattern_query().unwrap();
+            let column = pattern_query
+                .column()
+                .ok_or(DataFusionError::Execution(format!(
+                    "column not set for {}",
+                    query
+                )))?;
             let index_meta = ds
                 .load_scalar_index(
                     ScalarIndexCriteria::default()
-                        .for_column(&column)
+                        .for_column(column)
                         .with_type(ScalarIndexType::Inverted),
                 )
                 .await?
@@ -383,7 +1026,7 @@ impl ExecutionPlan for FlatMatchQueryExec {
                 )))?;
             let uuid = index_meta.uuid.to_string();
             let index = ds
-                .open_generic_index(&column, &uuid, metrics.as_ref())
+                .open_generic_index(column, &uuid, metrics.as_ref())
                 .await?;
             let inverted_idx = index
                 .as_any()
@@ -394,12 +1037,16 @@ impl ExecutionPlan for FlatMatchQueryExec {
                         column,
                     ))
                 })?;
-            Ok::<_, DataFusionError>(flat_bm25_search_stream(
+            let boost = pattern_query.boost();
+            let stream = flat_pattern_search_stream(
                 unindexed_input,
-                column,
-                query.terms,
+                column.to_owned(),
+                pattern_query,
+                &params.scoring,
                 inverted_idx,
-            ))
+            )?
+            .map(move |batch| batch.and_then(|batch| boost_scores(batch, boost)));
+            Ok::<_, DataFusionError>(stream)
         })
         .try_flatten_unordered(None);
         Ok(Box::pin(InstrumentedRecordBatchStreamAdapter::new(
@@ -423,6 +1070,16 @@ impl ExecutionPlan for FlatMatchQueryExec {
     }
 }
 
+fn boost_scores(batch: RecordBatch, boost: f32) -> DataFusionResult<RecordBatch> {
+    if boost == 1.0 {
+        return Ok(batch);
+    }
+    let scores = batch[SCORE_COL]
+        .as_primitive::<Float32Type>()
+        .unary::<_, Float32Type>(|score| score * boost);
+    Ok(batch.replace_column_by_name(SCORE_COL, Arc::new(scores))?)
+}
+
 #[derive(Debug)]
 pub struct PhraseQueryExec {
     dataset: Arc<Dataset>,
@@ -597,7 +1254,7 @@ impl ExecutionPlan for PhraseQueryExec {
             let tokens = collect_tokens(&query.terms, &mut tokenizer, None);
 
             pre_filter.wait_for_ready().await?;
-            let (doc_ids, scores) = index
+            let (doc_ids, mut scores) = index
                 .bm25_search(
                     tokens.into(),
                     params.into(),
@@ -607,6 +1264,9 @@ impl ExecutionPlan for PhraseQueryExec {
                 )
                 .boxed()
                 .await?;
+            scores.iter_mut().for_each(|s| {
+                *s *= query.boost;
+            });
             let batch = RecordBatch::try_new(
                 FTS_SCHEMA.clone(),
                 vec![
@@ -809,6 +1469,7 @@ pub struct BooleanQueryExec {
     params: FtsSearchParams,
     should: Arc<dyn ExecutionPlan>,
     must: Option<Arc<dyn ExecutionPlan>>,
+    must_not: Option<Arc<dyn ExecutionPlan>>,
 
     properties: PlanProperties,
     metrics: ExecutionPlanMetricsSet,
@@ -820,17 +1481,19 @@ impl DisplayAs for BooleanQueryExec {
             DisplayFormatType::Default | DisplayFormatType::Verbose => {
                 write!(
                     f,
-                    "BooleanQuery: must={}, should={}",
+                    "BooleanQuery: must={}, should={}, must_not={}",
                     self.query.must.len(),
-                    self.query.should.len()
+                    self.query.should.len(),
+                    self.query.must_not.len()
                 )
             }
             DisplayFormatType::TreeRender => {
                 write!(
                     f,
-                    "BooleanQuery\nmust={}\nshould={}",
+                    "BooleanQuery\nmust={}\nshould={}\nmust_not={}",
                     self.query.must.len(),
-                    self.query.should.len()
+                    self.query.should.len(),
+                    self.query.must_not.len()
                 )
             }
         }
@@ -843,6 +1506,7 @@ impl BooleanQueryExec {
         params: FtsSearchParams,
         should: Arc<dyn ExecutionPlan>,
         must: Option<Arc<dyn ExecutionPlan>>,
+        must_not: Option<Arc<dyn ExecutionPlan>>,
     ) -> Self {
         let properties = PlanProperties::new(
             EquivalenceProperties::new(FTS_SCHEMA.clone()),
@@ -855,6 +1519,7 @@ impl BooleanQueryExec {
             params,
             must,
             should,
+            must_not,
             properties,
             metrics: ExecutionPlanMetricsSet::new(),
         }
@@ -871,10 +1536,10 @@ impl ExecutionPlan for BooleanQueryExec {
     }
 
     fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
-        match &self.must {
-            Some(must) => vec![&self.should, must],
-            None => vec![&self.should],
-        }
+        let mut children = vec![&self.should];
+        children.extend(self.must.as_ref());
+        children.extend(self.must_not.as_ref());
+        children
     }
 
     fn required_input_distribution(&self) -> Vec<Distribution> {
@@ -888,36 +1553,26 @@ impl ExecutionPlan for BooleanQueryExec {
 
     fn with_new_children(
         self: Arc<Self>,
-        mut children: Vec<Arc<dyn ExecutionPlan>>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
     ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
-        match children.len() {
-            1 => {
-                let should = children.pop().unwrap();
-                Ok(Arc::new(Self {
-                    query: self.query.clone(),
-                    params: self.params.clone(),
-                    should,
-                    must: None,
-                    properties: self.properties.clone(),
-                    metrics: ExecutionPlanMetricsSet::new(),
-                }))
-            }
-            2 => {
-                let should = children.pop().unwrap();
-                let must = children.pop().unwrap();
-                Ok(Arc::new(Self {
-                    query: self.query.clone(),
-                    params: self.params.clone(),
-                    should,
-                    must: Some(must),
-                    properties: self.properties.clone(),
-                    metrics: ExecutionPlanMetricsSet::new(),
-                }))
-            }
-            _ => Err(DataFusionError::Internal(
+        if children.len() != self.children().len() {
+            return Err(DataFusionError::Internal(
                 "Unexpected number of children".to_string(),
-            )),
+            ));
         }
+        let mut children = children.into_iter();
+        let should = children.next().unwrap();
+        let must = self.must.as_ref().and_then(|_| children.next());
+        let must_not = self.must_not.as_ref().and_then(|_| children.next());
+        Ok(Arc::new(Self {
+            query: self.query.clone(),
+            params: self.params.clone(),
+            should,
+            must,
+            must_not,
+            properties: self.properties.clone(),
+            metrics: ExecutionPlanMetricsSet::new(),
+        }))
     }
 
     #[instrument(name = "bool_query_exec", level = "debug", skip_all)]
@@ -932,30 +1587,59 @@ impl ExecutionPlan for BooleanQueryExec {
             .as_ref()
             .map(|m| m.execute(partition, context.clone()))
             .transpose()?;
+        let must_not = self
+            .must_not
+            .as_ref()
+            .map(|m| m.execute(partition, context.clone()))
+            .transpose()?;
         let mut should = self.should.execute(partition, context)?;
 
-        let Some(mut must) = must else {
-            // If there is no must clause, we can just return the should clause
+        if must.is_none() && must_not.is_none() {
+            // If there is no must or must_not clause, we can just return the should clause
             return Ok(should);
-        };
+        }
 
         let stream = stream::once(async move {
-            let mut res = HashMap::new();
-            while let Some(batch) = must.try_next().await? {
-                let row_ids = batch[ROW_ID].as_primitive::<UInt64Type>().values();
-                let scores = batch[SCORE_COL].as_primitive::<Float32Type>().values();
-                res.extend(std::iter::zip(
-                    row_ids.iter().copied(),
-                    scores.iter().copied(),
-                ));
+            let mut excluded = HashSet::new();
+            if let Some(mut must_not) = must_not {
+                while let Some(batch) = must_not.try_next().await? {
+                    let row_ids = batch[ROW_ID].as_primitive::<UInt64Type>().values();
+                    excluded.extend(row_ids.iter().copied());
+                }
             }
 
-            while let Some(batch) = should.try_next().await? {
-                let row_ids = batch[ROW_ID].as_primitive::<UInt64Type>().values();
-                let scores = batch[SCORE_COL].as_primitive::<Float32Type>().values();
-                for (row_id, score) in std::iter::zip(row_ids, scores) {
-                    if let Some(existing_score) = res.get_mut(row_id) {
-                        *existing_score += score;
+            let mut res = HashMap::new();
+            match must {
+                Some(mut must) => {
+                    while let Some(batch) = must.try_next().await? {
+                        let row_ids = batch[ROW_ID].as_primitive::<UInt64Type>().values();
+                        let scores = batch[SCORE_COL].as_primitive::<Float32Type>().values();
+                        res.extend(
+                            std::iter::zip(row_ids.iter().copied(), scores.iter().copied())
+                                .filter(|(row_id, _)| !excluded.contains(row_id)),
+                        );
+                    }
+
+                    while let Some(batch) = should.try_next().await? {
+                        let row_ids = batch[ROW_ID].as_primitive::<UInt64Type>().values();
+                        let scores = batch[SCORE_COL].as_primitive::<Float32Type>().values();
+                        for (row_id, score) in std::iter::zip(row_ids, scores) {
+                            if let Some(existing_score) = res.get_mut(row_id) {
+                                *existing_score += score;
+                            }
+                        }
+                    }
+                }
+                None => {
+                    // without must clauses, the documents matching any should clause are returned
+                    while let Some(batch) = should.try_next().await? {
+                        let row_ids = batch[ROW_ID].as_primitive::<UInt64Type>().values();
+                        let scores = batch[SCORE_COL].as_primitive::<Float32Type>().values();
+                        for (row_id, score) in std::iter::zip(row_ids, scores) {
+                            if !excluded.contains(row_id) {
+                                *res.entry(*row_id).or_insert(0.0) += score;
+                            }
+                        }
                     }
                 }
             }
@@ -993,6 +1677,168 @@ impl ExecutionPlan for BooleanQueryExec {
     }
 }
 
+/// Highlights the terms of a full text search query in the text of a column.
+///
+/// Appends the `_highlight` and `_match_offsets` columns to the input,
+/// the text is tokenized with the tokenizer of the inverted index on the column.
+#[derive(Debug)]
+pub struct HighlightExec {
+    dataset: Arc<Dataset>,
+    input: Arc<dyn ExecutionPlan>,
+    column: String,
+    query: FtsQuery,
+    params: HighlightParams,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DisplayAs for HighlightExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "Highlight: column={}", self.column)
+            }
+            DisplayFormatType::TreeRender => {
+                write!(f, "Highlight\ncolumn={}", self.column)
+            }
+        }
+    }
+}
+
+impl HighlightExec {
+    pub fn try_new(
+        dataset: Arc<Dataset>,
+        input: Arc<dyn ExecutionPlan>,
+        column: String,
+        query: FtsQuery,
+        params: HighlightParams,
+    ) -> DataFusionResult<Self> {
+        let schema = Arc::new(Schema::try_merge(vec![
+            input.schema().as_ref().clone(),
+            Schema::new(vec![HIGHLIGHT_FIELD.clone(), MATCH_OFFSETS_FIELD.clone()]),
+        ])?);
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(schema),
+            input.properties().partitioning.clone(),
+            EmissionType::Incremental,
+            Boundedness::Bounded,
+        );
+        Ok(Self {
+            dataset,
+            input,
+            column,
+            query,
+            params,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+impl ExecutionPlan for HighlightExec {
+    fn name(&self) -> &str {
+        "HighlightExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(
+                "Unexpected number of children".to_string(),
+            ));
+        }
+        Ok(Arc::new(Self::try_new(
+            self.dataset.clone(),
+            children.pop().unwrap(),
+            self.column.clone(),
+            self.query.clone(),
+            self.params.clone(),
+        )?))
+    }
+
+    #[instrument(name = "highlight_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let ds = self.dataset.clone();
+        let column = self.column.clone();
+        let query = self.query.clone();
+        let params = self.params.clone();
+        let metrics = Arc::new(IndexMetrics::new(&self.metrics, partition));
+        let input = self.input.execute(partition, context)?;
+
+        let stream = stream::once(async move {
+            let index_meta = ds
+                .load_scalar_index(
+                    ScalarIndexCriteria::default()
+                        .for_column(&column)
+                        .with_type(ScalarIndexType::Inverted),
+                )
+                .await?
+                .ok_or(DataFusionError::Execution(format!(
+                    "No Inverted index found for column {}",
+                    column,
+                )))?;
+            let uuid = index_meta.uuid.to_string();
+            let index = ds
+                .open_generic_index(&column, &uuid, metrics.as_ref())
+                .await?;
+            let inverted_idx = index
+                .as_any()
+                .downcast_ref::<InvertedIndex>()
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "Index for column {} is not an inverted index",
+                        column,
+                    ))
+                })?;
+            let mut highlighter =
+                Highlighter::try_new(&query, &column, inverted_idx.tokenizer(), params)?;
+
+            Ok::<_, DataFusionError>(input.map(move |batch| {
+                let batch = batch?;
+                let (snippets, offsets) = highlighter.highlight_array(&batch[&column])?;
+                let batch = batch
+                    .try_with_column(HIGHLIGHT_FIELD.clone(), snippets)?
+                    .try_with_column(MATCH_OFFSETS_FIELD.clone(), offsets)?;
+                Ok(batch)
+            }))
+        })
+        .try_flatten();
+        Ok(Box::pin(InstrumentedRecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.stream_in_current_span().boxed(),
+            partition,
+            &self.metrics,
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<datafusion::physical_plan::Statistics> {
+        Ok(Statistics::new_unknown(&self.schema()))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use std::sync::Arc;
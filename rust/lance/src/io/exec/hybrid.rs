@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Execution node that fuses the results of a vector search and a full text search
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::AsArray;
+use arrow::compute::{sort_to_indices, take_record_batch, SortOptions};
+use arrow::datatypes::{Float32Type, UInt64Type};
+use arrow_array::{Float32Array, RecordBatch, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use datafusion::common::Statistics;
+use datafusion::error::{DataFusionError, Result as DataFusionResult};
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::physical_plan::execution_plan::{Boundedness, EmissionType};
+use datafusion::physical_plan::metrics::{ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    collect, DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties,
+};
+use datafusion_physical_expr::{EquivalenceProperties, Partitioning};
+use futures::{stream, StreamExt};
+use lance_core::{utils::tracing::StreamTracingExt, ROW_ID, ROW_ID_FIELD};
+use lance_index::scalar::inverted::SCORE_COL;
+use lance_index::vector::DIST_COL;
+use lazy_static::lazy_static;
+use tracing::instrument;
+
+use crate::dataset::hybrid::{Reranker, RELEVANCE_SCORE_COL};
+
+lazy_static! {
+    pub static ref HYBRID_SCHEMA: SchemaRef = Arc::new(Schema::new(vec![
+        ROW_ID_FIELD.clone(),
+        Field::new(DIST_COL, DataType::Float32, true),
+        Field::new(SCORE_COL, DataType::Float32, true),
+        Field::new(RELEVANCE_SCORE_COL, DataType::Float32, false),
+    ]));
+}
+
+/// Runs a vector search and a full text search concurrently, and fuses their results
+/// with a [`Reranker`]
+///
+/// The output contains the `_distance` of the vector search, the `_score` of the full
+/// text search, and the fused `_relevance_score`, sorted by the relevance score.
+#[derive(Debug)]
+pub struct HybridSearchExec {
+    vector_search: Arc<dyn ExecutionPlan>,
+    fts: Arc<dyn ExecutionPlan>,
+    reranker: Arc<dyn Reranker>,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DisplayAs for HybridSearchExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "HybridSearch: reranker={:?}", self.reranker)
+            }
+            DisplayFormatType::TreeRender => {
+                write!(f, "HybridSearch\nreranker={:?}", self.reranker)
+            }
+        }
+    }
+}
+
+impl HybridSearchExec {
+    pub fn try_new(
+        vector_search: Arc<dyn ExecutionPlan>,
+        fts: Arc<dyn ExecutionPlan>,
+        reranker: Arc<dyn Reranker>,
+    ) -> DataFusionResult<Self> {
+        let vector_schema = vector_search.schema();
+        let fts_schema = fts.schema();
+        for (schema, column) in [
+            (&vector_schema, ROW_ID),
+            (&vector_schema, DIST_COL),
+            (&fts_schema, ROW_ID),
+            (&fts_schema, SCORE_COL),
+        ] {
+            if schema.column_with_name(column).is_none() {
+                return Err(DataFusionError::Internal(format!(
+                    "HybridSearchExec: the input is missing the column {}",
+                    column
+                )));
+            }
+        }
+
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(HYBRID_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Ok(Self {
+            vector_search,
+            fts,
+            reranker,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+// merge the results into one batch, with a row for each document found by either search
+fn merge_results(
+    vector_results: &[RecordBatch],
+    fts_results: &[RecordBatch],
+) -> DataFusionResult<RecordBatch> {
+    let mut positions = HashMap::new();
+    let mut row_ids = Vec::new();
+    let mut dists = Vec::new();
+    let mut scores = Vec::new();
+    for batch in vector_results {
+        let ids = batch[ROW_ID].as_primitive::<UInt64Type>();
+        let batch_dists = batch[DIST_COL].as_primitive::<Float32Type>();
+        for (row_id, dist) in ids.values().iter().zip(batch_dists.iter()) {
+            positions.entry(*row_id).or_insert_with(|| {
+                row_ids.push(*row_id);
+                dists.push(dist);
+                scores.push(None);
+                row_ids.len() - 1
+            });
+        }
+    }
+    for batch in fts_results {
+        let ids = batch[ROW_ID].as_primitive::<UInt64Type>();
+        let batch_scores = batch[SCORE_COL].as_primitive::<Float32Type>();
+        for (row_id, score) in ids.values().iter().zip(batch_scores.iter()) {
+            let pos = *positions.entry(*row_id).or_insert_with(|| {
+                row_ids.push(*row_id);
+                dists.push(None);
+                scores.push(None);
+                row_ids.len() - 1
+            });
+            scores[pos] = score;
+        }
+    }
+
+    let schema = Arc::new(Schema::new(HYBRID_SCHEMA.fields()[..3].to_vec()));
+    Ok(RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(UInt64Array::from(row_ids)),
+            Arc::new(Float32Array::from(dists)),
+            Arc::new(Float32Array::from(scores)),
+        ],
+    )?)
+}
+
+impl ExecutionPlan for HybridSearchExec {
+    fn name(&self) -> &str {
+        "HybridSearchExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.vector_search, &self.fts]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if children.len() != 2 {
+            return Err(DataFusionError::Internal(
+                "HybridSearchExec requires exactly two children".to_string(),
+            ));
+        }
+        let fts = children.pop().unwrap();
+        let vector_search = children.pop().unwrap();
+        Ok(Arc::new(Self::try_new(
+            vector_search,
+            fts,
+            self.reranker.clone(),
+        )?))
+    }
+
+    #[instrument(name = "hybrid_search_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        _partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let vector_search = self.vector_search.clone();
+        let fts = self.fts.clone();
+        let reranker = self.reranker.clone();
+
+        let stream = stream::once(async move {
+            let (vector_results, fts_results) = futures::try_join!(
+                collect(vector_search, context.clone()),
+                collect(fts, context)
+            )?;
+            let results = merge_results(&vector_results, &fts_results)?;
+
+            let relevance_scores = reranker.rerank(&results)?;
+            if relevance_scores.len() != results.num_rows() {
+                return Err(DataFusionError::Execution(format!(
+                    "the reranker {:?} returned {} scores for {} rows",
+                    reranker,
+                    relevance_scores.len(),
+                    results.num_rows()
+                )));
+            }
+            let mut columns = results.columns().to_vec();
+            columns.push(Arc::new(relevance_scores));
+            let batch = RecordBatch::try_new(HYBRID_SCHEMA.clone(), columns)?;
+
+            let indices = sort_to_indices(
+                batch[RELEVANCE_SCORE_COL].as_ref(),
+                Some(SortOptions {
+                    descending: true,
+                    nulls_first: false,
+                }),
+                None,
+            )?;
+            Ok(take_record_batch(&batch, &indices)?)
+        });
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.stream_in_current_span().boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        Ok(Statistics::new_unknown(&HYBRID_SCHEMA))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
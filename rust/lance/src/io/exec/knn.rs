@@ -6,13 +6,14 @@ use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
+use arrow::compute::{concat_batches, take_record_batch};
 use arrow::datatypes::{Float32Type, UInt32Type, UInt64Type};
 use arrow_array::{
     builder::{ListBuilder, UInt32Builder},
     cast::AsArray,
     ArrayRef, RecordBatch, StringArray,
 };
-use arrow_array::{Array, Float32Array, UInt32Array, UInt64Array};
+use arrow_array::{Array, FixedSizeListArray, Float32Array, UInt32Array, UInt64Array};
 use arrow_schema::{DataType, Field, Schema, SchemaRef};
 use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::PlanProperties;
@@ -34,8 +35,9 @@ use datafusion_physical_plan::metrics::{BaselineMetrics, Count};
 use futures::{future, stream, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use itertools::Itertools;
 use lance_core::utils::futures::FinallyStreamExt;
+use lance_core::utils::tokio::{get_num_compute_intensive_cpus, spawn_cpu};
 use lance_core::ROW_ID;
-use lance_core::{utils::tokio::get_num_compute_intensive_cpus, ROW_ID_FIELD};
+use lance_core::ROW_ID_FIELD;
 use lance_datafusion::utils::{
     ExecutionPlanMetricsSetExt, DELTAS_SEARCHED_METRIC, PARTITIONS_RANKED_METRIC,
     PARTITIONS_SEARCHED_METRIC,
@@ -43,10 +45,10 @@ use lance_datafusion::utils::{
 use lance_index::prefilter::PreFilter;
 use lance_index::vector::VectorIndex;
 use lance_index::vector::{
-    flat::compute_distance, Query, DIST_COL, INDEX_UUID_COLUMN, PART_ID_COLUMN,
+    flat::compute_distance, Query, DIST_COL, INDEX_UUID_COLUMN, PART_ID_COLUMN, QUERY_INDEX_COL,
 };
 use lance_linalg::distance::DistanceType;
-use lance_linalg::kernels::normalize_arrow;
+use lance_linalg::kernels::{normalize_arrow, normalize_fsl};
 use lance_table::format::Index;
 use snafu::location;
 use tokio::sync::Notify;
@@ -59,8 +61,8 @@ use crate::{Error, Result};
 use lance_arrow::*;
 
 use super::utils::{
-    FilteredRowIdsToPrefilter, IndexMetrics, InstrumentedRecordBatchStreamAdapter, PreFilterSource,
-    SelectionVectorToPrefilter,
+    build_prefilter, FilteredRowIdsToPrefilter, IndexMetrics, InstrumentedRecordBatchStreamAdapter,
+    PreFilterSource, SelectionVectorToPrefilter,
 };
 
 pub struct AnnPartitionMetrics {
@@ -1144,7 +1146,7 @@ impl ExecutionPlan for MultivectorScoringExec {
                     }
                     visited_row_ids.insert(row_id);
                     new_row_ids.push(*row_id);
-                    // it's cosine distance, so we need to convert it to similarity
+                    // 1 - distance is a similarity for all the distance types
                     new_sims.push(1.0 - *dist);
                 }
                 let new_row_ids = UInt64Array::from(new_row_ids);
@@ -1198,7 +1200,7 @@ impl ExecutionPlan for MultivectorScoringExec {
             let (row_ids, sims): (Vec<_>, Vec<_>) = results.into_iter().unzip();
             let dists = sims
                 .into_iter()
-                // it's similarity, so we need to convert it back to distance
+                // convert the similarity back to distance
                 .map(|sim| num_queries - sim)
                 .collect::<Vec<_>>();
             let row_ids = UInt64Array::from(row_ids);
@@ -1229,6 +1231,811 @@ impl ExecutionPlan for MultivectorScoringExec {
     }
 }
 
+/// [ExecutionPlan] to search multivector indices with PLAID-style candidate generation.
+///
+/// Each index delta is searched by [`VectorIndex::multivector_search`], which scores the
+/// documents of the probed partitions by their centroids first, and then only scores the
+/// best `num_candidates` documents by their quantized vectors. The results of all deltas
+/// are returned with the schema of [KNN_INDEX_SCHEMA], where the distance is the sum of
+/// the minimum distances of each query vector.
+#[derive(Debug)]
+pub struct MultivectorSearchExec {
+    dataset: Arc<Dataset>,
+    indices: Vec<Index>,
+    query: Query,
+    num_candidates: usize,
+    prefilter_source: PreFilterSource,
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl MultivectorSearchExec {
+    pub fn try_new(
+        dataset: Arc<Dataset>,
+        indices: Vec<Index>,
+        query: Query,
+        num_candidates: usize,
+        prefilter_source: PreFilterSource,
+    ) -> Result<Self> {
+        if num_candidates == 0 {
+            return Err(Error::invalid_input(
+                "the number of multivector search candidates must be greater than 0",
+                location!(),
+            ));
+        }
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(KNN_INDEX_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Ok(Self {
+            dataset,
+            indices,
+            query,
+            num_candidates,
+            prefilter_source,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+impl DisplayAs for MultivectorSearchExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "MultivectorSearch: k={}, nprobes={}, candidates={}, deltas={}",
+                    self.query.k,
+                    self.query.minimum_nprobes,
+                    self.num_candidates,
+                    self.indices.len()
+                )
+            }
+            DisplayFormatType::TreeRender => {
+                write!(
+                    f,
+                    "MultivectorSearch\nk={}\nnprobes={}\ncandidates={}\ndeltas={}",
+                    self.query.k,
+                    self.query.minimum_nprobes,
+                    self.num_candidates,
+                    self.indices.len()
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for MultivectorSearchExec {
+    fn name(&self) -> &str {
+        "MultivectorSearchExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> arrow_schema::SchemaRef {
+        KNN_INDEX_SCHEMA.clone()
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        match &self.prefilter_source {
+            PreFilterSource::None => vec![],
+            PreFilterSource::FilteredRowIds(src) => vec![&src],
+            PreFilterSource::ScalarIndexQuery(src) => vec![&src],
+        }
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        // Prefilter inputs must be a single partition
+        self.children()
+            .iter()
+            .map(|_| Distribution::SinglePartition)
+            .collect()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let prefilter_source = match (children.len(), &self.prefilter_source) {
+            (0, PreFilterSource::None) => PreFilterSource::None,
+            (1, PreFilterSource::FilteredRowIds(_)) => {
+                PreFilterSource::FilteredRowIds(children.pop().unwrap())
+            }
+            (1, PreFilterSource::ScalarIndexQuery(_)) => {
+                PreFilterSource::ScalarIndexQuery(children.pop().unwrap())
+            }
+            _ => {
+                return Err(DataFusionError::Internal(
+                    "Unexpected children for MultivectorSearchExec".to_string(),
+                ));
+            }
+        };
+        Ok(Arc::new(Self::try_new(
+            self.dataset.clone(),
+            self.indices.clone(),
+            self.query.clone(),
+            self.num_candidates,
+            prefilter_source,
+        )?))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::context::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let ds = self.dataset.clone();
+        let indices = self.indices.clone();
+        let query = self.query.clone();
+        let num_candidates = self.num_candidates;
+        let metrics = Arc::new(AnnIndexMetrics::new(&self.metrics, partition));
+        let pre_filter = build_prefilter(
+            context,
+            partition,
+            &self.prefilter_source,
+            ds.clone(),
+            &indices,
+        )?;
+
+        let stream = stream::iter(indices)
+            .map(move |index| {
+                let ds = ds.clone();
+                let query = query.clone();
+                let pre_filter = pre_filter.clone();
+                let metrics = metrics.clone();
+                async move {
+                    let _timer = metrics.baseline_metrics.elapsed_compute().timer();
+                    let index = ds
+                        .open_vector_index(
+                            &query.column,
+                            &index.uuid.to_string(),
+                            &metrics.index_metrics,
+                        )
+                        .await?;
+                    let mut query = query;
+                    if index.metric_type() == DistanceType::Cosine {
+                        // normalize each of the query vectors
+                        let dim = index.ivf_model().dimension();
+                        let vectors =
+                            FixedSizeListArray::try_new_from_values(query.key.clone(), dim as i32)?;
+                        query.key = normalize_fsl(&vectors)?.values().clone();
+                    }
+                    let batch = index
+                        .multivector_search(
+                            &query,
+                            num_candidates,
+                            pre_filter,
+                            &metrics.index_metrics,
+                        )
+                        .await?;
+                    metrics.baseline_metrics.record_output(batch.num_rows());
+                    Ok::<_, DataFusionError>(batch.with_schema(KNN_INDEX_SCHEMA.clone())?)
+                }
+            })
+            .buffered(get_num_compute_intensive_cpus());
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        Ok(Statistics {
+            num_rows: Precision::Inexact(
+                self.query.k * self.query.refine_factor.unwrap_or(1) as usize * self.indices.len(),
+            ),
+            ..Statistics::new_unknown(self.schema().as_ref())
+        })
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref BATCH_KNN_INDEX_SCHEMA: SchemaRef = Arc::new(Schema::new(vec![
+        Field::new(QUERY_INDEX_COL, DataType::UInt32, false),
+        Field::new(DIST_COL, DataType::Float32, true),
+        ROW_ID_FIELD.clone(),
+    ]));
+}
+
+// keep the k nearest rows of each query, sorted by the query index and then the distance
+fn top_k_per_query(batch: &RecordBatch, k: usize) -> Result<RecordBatch> {
+    let query_indices = batch[QUERY_INDEX_COL].as_primitive::<UInt32Type>();
+    let distances = batch[DIST_COL].as_primitive::<Float32Type>();
+    let mut rows = (0..batch.num_rows() as u32)
+        .filter(|&i| distances.is_valid(i as usize))
+        .collect::<Vec<_>>();
+    rows.sort_by(|&a, &b| {
+        query_indices
+            .value(a as usize)
+            .cmp(&query_indices.value(b as usize))
+            .then(
+                distances
+                    .value(a as usize)
+                    .total_cmp(&distances.value(b as usize)),
+            )
+    });
+    let mut current = None;
+    let mut count = 0;
+    rows.retain(|&i| {
+        let query_index = query_indices.value(i as usize);
+        if current != Some(query_index) {
+            current = Some(query_index);
+            count = 0;
+        }
+        count += 1;
+        count <= k
+    });
+    Ok(take_record_batch(batch, &UInt32Array::from(rows))?)
+}
+
+/// [ExecutionPlan] to search the vector index for a batch of queries.
+///
+/// The queries are flattened in `query.key`. The partitions to probe are found for each
+/// query, and then every probed partition is searched once for all the queries that probe
+/// it, so each partition is loaded only once. Every query probes exactly
+/// `query.minimum_nprobes` partitions.
+///
+/// The output has the schema of [BATCH_KNN_INDEX_SCHEMA], with the nearest
+/// `k * refine_factor` rows of each query, sorted by the query index and then the distance.
+#[derive(Debug)]
+pub struct BatchANNExec {
+    dataset: Arc<Dataset>,
+    indices: Vec<Index>,
+    query: Query,
+    num_queries: usize,
+    prefilter_source: PreFilterSource,
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl BatchANNExec {
+    pub fn try_new(
+        dataset: Arc<Dataset>,
+        indices: Vec<Index>,
+        query: Query,
+        dim: usize,
+        prefilter_source: PreFilterSource,
+    ) -> Result<Self> {
+        if dim == 0 || query.key.len() % dim != 0 {
+            return Err(Error::invalid_input(
+                format!(
+                    "the batch of queries has {} values which is not a multiple of the dimension {}",
+                    query.key.len(),
+                    dim
+                ),
+                location!(),
+            ));
+        }
+        let num_queries = query.key.len() / dim;
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(BATCH_KNN_INDEX_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Ok(Self {
+            dataset,
+            indices,
+            query,
+            num_queries,
+            prefilter_source,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+
+    fn dim(&self) -> usize {
+        self.query.key.len() / self.num_queries
+    }
+}
+
+impl DisplayAs for BatchANNExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "BatchANN: queries={}, k={}, nprobes={}, deltas={}",
+                    self.num_queries,
+                    self.query.k,
+                    self.query.minimum_nprobes,
+                    self.indices.len()
+                )
+            }
+            DisplayFormatType::TreeRender => {
+                write!(
+                    f,
+                    "BatchANN\nqueries={}\nk={}\nnprobes={}\ndeltas={}",
+                    self.num_queries,
+                    self.query.k,
+                    self.query.minimum_nprobes,
+                    self.indices.len()
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for BatchANNExec {
+    fn name(&self) -> &str {
+        "BatchANNExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> arrow_schema::SchemaRef {
+        BATCH_KNN_INDEX_SCHEMA.clone()
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        match &self.prefilter_source {
+            PreFilterSource::None => vec![],
+            PreFilterSource::FilteredRowIds(src) => vec![&src],
+            PreFilterSource::ScalarIndexQuery(src) => vec![&src],
+        }
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        // Prefilter inputs must be a single partition
+        self.children()
+            .iter()
+            .map(|_| Distribution::SinglePartition)
+            .collect()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let prefilter_source = match (children.len(), &self.prefilter_source) {
+            (0, PreFilterSource::None) => PreFilterSource::None,
+            (1, PreFilterSource::FilteredRowIds(_)) => {
+                PreFilterSource::FilteredRowIds(children.pop().unwrap())
+            }
+            (1, PreFilterSource::ScalarIndexQuery(_)) => {
+                PreFilterSource::ScalarIndexQuery(children.pop().unwrap())
+            }
+            _ => {
+                return Err(DataFusionError::Internal(
+                    "Unexpected children for BatchANNExec".to_string(),
+                ));
+            }
+        };
+        Ok(Arc::new(Self::try_new(
+            self.dataset.clone(),
+            self.indices.clone(),
+            self.query.clone(),
+            self.dim(),
+            prefilter_source,
+        )?))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::context::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let ds = self.dataset.clone();
+        let indices = self.indices.clone();
+        let query = self.query.clone();
+        let dim = self.dim();
+        let metrics = Arc::new(AnnIndexMetrics::new(&self.metrics, partition));
+        let pre_filter = build_prefilter(
+            context,
+            partition,
+            &self.prefilter_source,
+            ds.clone(),
+            &indices,
+        )?;
+
+        let stream = stream::once(async move {
+            let k = query.k * query.refine_factor.unwrap_or(1) as usize;
+            let mut results = Vec::new();
+            for index in indices {
+                let index = ds
+                    .open_vector_index(
+                        &query.column,
+                        &index.uuid.to_string(),
+                        &metrics.index_metrics,
+                    )
+                    .await?;
+                let mut key = query.key.clone();
+                if index.metric_type() == DistanceType::Cosine {
+                    let vectors = FixedSizeListArray::try_new_from_values(key, dim as i32)?;
+                    key = normalize_fsl(&vectors)?.values().clone();
+                }
+                let queries = (0..key.len() / dim)
+                    .map(|i| Query {
+                        key: key.slice(i * dim, dim),
+                        ..query.clone()
+                    })
+                    .collect::<Vec<_>>();
+
+                // group the queries by the partitions they probe
+                let mut partitions = HashMap::<u32, Vec<u32>>::new();
+                {
+                    let _timer = metrics.baseline_metrics.elapsed_compute().timer();
+                    for (query_index, query) in queries.iter().enumerate() {
+                        let probes = index.find_partitions(query)?;
+                        for &partition_id in probes.values().iter().take(query.minimum_nprobes) {
+                            partitions
+                                .entry(partition_id)
+                                .or_default()
+                                .push(query_index as u32);
+                        }
+                    }
+                }
+                metrics.partitions_searched.add(partitions.len());
+
+                let queries = Arc::new(queries);
+                let batches = stream::iter(partitions)
+                    .map(|(partition_id, query_indices)| {
+                        let index = index.clone();
+                        let queries = queries.clone();
+                        let pre_filter = pre_filter.clone();
+                        let metrics = metrics.clone();
+                        async move {
+                            let partition_queries = query_indices
+                                .iter()
+                                .map(|&i| queries[i as usize].clone())
+                                .collect::<Vec<_>>();
+                            let batches = index
+                                .search_in_partition_batch(
+                                    partition_id as usize,
+                                    &partition_queries,
+                                    pre_filter,
+                                    &metrics.index_metrics,
+                                )
+                                .await?;
+                            let batches = batches
+                                .into_iter()
+                                .zip(query_indices)
+                                .map(|(batch, query_index)| {
+                                    let num_rows = batch.num_rows();
+                                    let query_indices =
+                                        UInt32Array::from(vec![query_index; num_rows]);
+                                    RecordBatch::try_new(
+                                        BATCH_KNN_INDEX_SCHEMA.clone(),
+                                        vec![
+                                            Arc::new(query_indices),
+                                            batch[DIST_COL].clone(),
+                                            batch[ROW_ID].clone(),
+                                        ],
+                                    )
+                                })
+                                .collect::<std::result::Result<Vec<_>, _>>()?;
+                            Ok::<_, Error>(batches)
+                        }
+                    })
+                    .buffer_unordered(get_num_compute_intensive_cpus())
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                results.extend(batches.into_iter().flatten());
+            }
+
+            let _timer = metrics.baseline_metrics.elapsed_compute().timer();
+            let batch = concat_batches(&BATCH_KNN_INDEX_SCHEMA, &results)?;
+            let batch = top_k_per_query(&batch, k)?;
+            metrics.baseline_metrics.record_output(batch.num_rows());
+            Ok::<_, DataFusionError>(batch)
+        });
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        Ok(Statistics {
+            num_rows: Precision::Inexact(
+                self.num_queries * self.query.k * self.query.refine_factor.unwrap_or(1) as usize,
+            ),
+            ..Statistics::new_unknown(self.schema().as_ref())
+        })
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
+// compute the distances between the vectors and the queries, within the distance range.
+//
+// If the batch has the query index column, each row is only compared with its own query,
+// otherwise each row is compared with every query. The returned batch has a row for
+// each (row, query) pair, with the query index and the distance columns appended.
+fn compute_batch_distances(
+    batch: RecordBatch,
+    column: &str,
+    queries: &FixedSizeListArray,
+    distance_type: DistanceType,
+    range: (Option<f32>, Option<f32>),
+    output_schema: SchemaRef,
+) -> Result<RecordBatch> {
+    let vectors = batch
+        .column_by_name(column)
+        .and_then(|col| col.as_fixed_size_list_opt())
+        .ok_or_else(|| Error::Schema {
+            message: format!("column {} must be a vector column", column),
+            location: location!(),
+        })?;
+    let row_ids = batch.column_by_name(ROW_ID);
+    let is_valid =
+        |i: usize| vectors.is_valid(i) && row_ids.map(|ids| ids.is_valid(i)).unwrap_or(true);
+    let dist_func = distance_type.arrow_batch_func();
+    let (lower_bound, upper_bound) = range;
+
+    let mut rows = Vec::new();
+    let mut query_indices = Vec::new();
+    let mut distances = Vec::new();
+    let mut push_distances = |group: &UInt32Array, query_index: u32| -> Result<()> {
+        let group_vectors = arrow_select::take::take(vectors, group, None)?;
+        let dists = dist_func(
+            queries.value(query_index as usize).as_ref(),
+            group_vectors.as_fixed_size_list(),
+        )?;
+        for (row, dist) in group.values().iter().zip(dists.iter()) {
+            let Some(dist) = dist else {
+                continue;
+            };
+            if lower_bound.is_some_and(|lb| dist < lb) || upper_bound.is_some_and(|ub| dist >= ub) {
+                continue;
+            }
+            rows.push(*row);
+            query_indices.push(query_index);
+            distances.push(dist);
+        }
+        Ok(())
+    };
+
+    if let Some(batch_query_indices) = batch.column_by_name(QUERY_INDEX_COL) {
+        let batch_query_indices = batch_query_indices.as_primitive::<UInt32Type>();
+        let mut groups = HashMap::<u32, Vec<u32>>::new();
+        for i in (0..batch.num_rows()).filter(|&i| is_valid(i)) {
+            groups
+                .entry(batch_query_indices.value(i))
+                .or_default()
+                .push(i as u32);
+        }
+        for (query_index, group) in groups {
+            push_distances(&UInt32Array::from(group), query_index)?;
+        }
+    } else {
+        let valid_rows = (0..batch.num_rows())
+            .filter(|&i| is_valid(i))
+            .map(|i| i as u32)
+            .collect::<UInt32Array>();
+        for query_index in 0..queries.len() {
+            push_distances(&valid_rows, query_index as u32)?;
+        }
+    }
+
+    let mut batch = batch;
+    for name in [QUERY_INDEX_COL, DIST_COL] {
+        if batch.column_by_name(name).is_some() {
+            batch = batch.drop_column(name)?;
+        }
+    }
+    let mut columns = take_record_batch(&batch, &UInt32Array::from(rows))?
+        .columns()
+        .to_vec();
+    columns.push(Arc::new(UInt32Array::from(query_indices)));
+    columns.push(Arc::new(Float32Array::from(distances)));
+    Ok(RecordBatch::try_new(output_schema, columns)?)
+}
+
+/// [ExecutionPlan] to find the nearest neighbors of a batch of queries by flat search.
+///
+/// If the input has the query index column (i.e. it contains the candidates of a batched
+/// index search), the distance of each row is computed only with its own query. Otherwise
+/// each row of the input is a candidate for every query.
+///
+/// The output has the columns of the input, followed by the query index and the distance.
+/// It contains the `k` nearest rows of each query within the distance range, sorted by
+/// the query index and then the distance.
+#[derive(Debug)]
+pub struct BatchKNNExec {
+    input: Arc<dyn ExecutionPlan>,
+    column: String,
+    queries: FixedSizeListArray,
+    k: usize,
+    range: (Option<f32>, Option<f32>),
+    distance_type: DistanceType,
+
+    output_schema: SchemaRef,
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl BatchKNNExec {
+    pub fn try_new(
+        input: Arc<dyn ExecutionPlan>,
+        column: &str,
+        queries: FixedSizeListArray,
+        k: usize,
+        range: (Option<f32>, Option<f32>),
+        distance_type: DistanceType,
+    ) -> Result<Self> {
+        let mut output_schema = input.schema().as_ref().clone();
+        get_vector_type(&(&output_schema).try_into()?, column)?;
+
+        for name in [QUERY_INDEX_COL, DIST_COL] {
+            if output_schema.column_with_name(name).is_some() {
+                output_schema = output_schema.without_column(name);
+            }
+        }
+        let output_schema = Arc::new(
+            output_schema
+                .try_with_column(Field::new(QUERY_INDEX_COL, DataType::UInt32, false))?
+                .try_with_column(Field::new(DIST_COL, DataType::Float32, true))?,
+        );
+
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(output_schema.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Ok(Self {
+            input,
+            column: column.to_string(),
+            queries,
+            k,
+            range,
+            distance_type,
+            output_schema,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+impl DisplayAs for BatchKNNExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "BatchKNN: queries={}, k={}, metric={}",
+                    self.queries.len(),
+                    self.k,
+                    self.distance_type
+                )
+            }
+            DisplayFormatType::TreeRender => {
+                write!(
+                    f,
+                    "BatchKNN\nqueries={}\nk={}\nmetric={}",
+                    self.queries.len(),
+                    self.k,
+                    self.distance_type
+                )
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for BatchKNNExec {
+    fn name(&self) -> &str {
+        "BatchKNNExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> arrow_schema::SchemaRef {
+        self.output_schema.clone()
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        vec![Distribution::SinglePartition]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(
+                "BatchKNNExec node must have exactly one child".to_string(),
+            ));
+        }
+        Ok(Arc::new(Self::try_new(
+            children.pop().expect("length checked"),
+            &self.column,
+            self.queries.clone(),
+            self.k,
+            self.range,
+            self.distance_type,
+        )?))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::context::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let input_stream = self.input.execute(partition, context)?;
+        let column = self.column.clone();
+        let queries = self.queries.clone();
+        let k = self.k;
+        let range = self.range;
+        let dt = self.distance_type;
+        let schema = self.schema();
+        let baseline_metrics = BaselineMetrics::new(&self.metrics, partition);
+
+        let candidates_schema = schema.clone();
+        let stream = stream::once(async move {
+            // keep only the top k of each input batch, so the candidates stay small
+            let candidates = input_stream
+                .try_filter(|batch| future::ready(batch.num_rows() > 0))
+                .map_err(Error::from)
+                .map_ok(|batch| {
+                    let column = column.clone();
+                    let queries = queries.clone();
+                    let schema = candidates_schema.clone();
+                    spawn_cpu(move || {
+                        let batch =
+                            compute_batch_distances(batch, &column, &queries, dt, range, schema)?;
+                        top_k_per_query(&batch, k)
+                    })
+                })
+                .try_buffer_unordered(get_num_compute_intensive_cpus())
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            let _timer = baseline_metrics.elapsed_compute().timer();
+            let batch = concat_batches(&candidates_schema, &candidates)?;
+            let batch = top_k_per_query(&batch, k)?;
+            baseline_metrics.record_output(batch.num_rows());
+            Ok::<_, DataFusionError>(batch)
+        });
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            schema,
+            stream.boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<Statistics> {
+        Ok(Statistics {
+            num_rows: Precision::Inexact(self.queries.len() * self.k),
+            ..Statistics::new_unknown(self.schema().as_ref())
+        })
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
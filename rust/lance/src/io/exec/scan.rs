@@ -373,7 +373,8 @@ impl LanceStream {
                             project_schema,
                             FragReadConfig::default()
                                 .with_row_id(config.with_row_id)
-                                .with_row_address(config.with_row_address),
+                                .with_row_address(config.with_row_address)
+                                .with_view_types(config.use_view_types),
                             config.with_make_deletions_null,
                             Some((scan_scheduler, priority as u32)),
                         )
@@ -472,7 +473,8 @@ impl LanceStream {
                         project_schema.clone(),
                         FragReadConfig::default()
                             .with_row_id(config.with_row_id)
-                            .with_row_address(config.with_row_address),
+                            .with_row_address(config.with_row_address)
+                            .with_view_types(config.use_view_types),
                         config.with_make_deletions_null,
                         None,
                     ))
@@ -501,7 +503,8 @@ impl LanceStream {
                         project_schema.clone(),
                         FragReadConfig::default()
                             .with_row_id(config.with_row_id)
-                            .with_row_address(config.with_row_address),
+                            .with_row_address(config.with_row_address)
+                            .with_view_types(config.use_view_types),
                         config.with_make_deletions_null,
                         None,
                     ))
@@ -560,7 +563,11 @@ impl core::fmt::Debug for LanceStream {
 
 impl RecordBatchStream for LanceStream {
     fn schema(&self) -> SchemaRef {
-        let mut schema: ArrowSchema = self.projection.as_ref().into();
+        let mut schema: ArrowSchema = if self.config.use_view_types {
+            (&self.projection.with_view_types()).into()
+        } else {
+            self.projection.as_ref().into()
+        };
         if self.config.with_row_id {
             schema = schema.try_with_column(ROW_ID_FIELD.clone()).unwrap();
         }
@@ -582,6 +589,7 @@ pub struct LanceScanConfig {
     pub with_make_deletions_null: bool,
     pub ordered_output: bool,
     pub strict_batch_size: bool,
+    pub use_view_types: bool,
 }
 
 // This is mostly for testing purposes, end users are unlikely to create this
@@ -598,6 +606,7 @@ impl Default for LanceScanConfig {
             with_make_deletions_null: false,
             ordered_output: false,
             strict_batch_size: false,
+            use_view_types: false,
         }
     }
 }
@@ -658,9 +667,15 @@ impl LanceScanExec {
         fragments: Arc<Vec<Fragment>>,
         range: Option<Range<u64>>,
         projection: Arc<Schema>,
-        config: LanceScanConfig,
+        mut config: LanceScanConfig,
     ) -> Self {
-        let mut output_schema: ArrowSchema = projection.as_ref().into();
+        // Legacy files are always read with offset based string / binary types
+        config.use_view_types &= !dataset.is_legacy_storage();
+        let mut output_schema: ArrowSchema = if config.use_view_types {
+            (&projection.with_view_types()).into()
+        } else {
+            projection.as_ref().into()
+        };
 
         if config.with_row_id {
             output_schema = output_schema.try_with_column(ROW_ID_FIELD.clone()).unwrap();
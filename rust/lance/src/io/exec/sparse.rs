@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-FileCopyrightText: Copyright The Lance Authors
+
+//! Execution nodes for top-k dot product search over sparse vectors
+
+use std::sync::Arc;
+
+use arrow::compute::filter;
+use arrow_array::{BooleanArray, Float32Array, RecordBatch, UInt64Array};
+use datafusion::common::Statistics;
+use datafusion::error::{DataFusionError, Result as DataFusionResult};
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::physical_plan::execution_plan::{Boundedness, EmissionType};
+use datafusion::physical_plan::metrics::{ExecutionPlanMetricsSet, MetricsSet};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties};
+use datafusion_physical_expr::{Distribution, EquivalenceProperties, Partitioning};
+use futures::stream;
+use futures::{StreamExt, TryStreamExt};
+use lance_core::{utils::tracing::StreamTracingExt, ROW_ID};
+use lance_index::prefilter::PreFilter;
+use lance_index::scalar::inverted::FTS_SCHEMA;
+use lance_index::scalar::sparse::{SparseIndex, SparseQuery};
+use lance_index::scalar::ScalarIndexType;
+use lance_index::{DatasetIndexExt, ScalarIndexCriteria};
+use lance_linalg::distance::sparse::sparse_dot_arrow_batch;
+use tracing::instrument;
+
+use crate::{index::DatasetIndexInternalExt, Dataset};
+
+use super::utils::{build_prefilter, IndexMetrics, InstrumentedRecordBatchStreamAdapter};
+use super::PreFilterSource;
+
+/// Searches the top-k rows of a sparse vector column with a sparse index
+#[derive(Debug)]
+pub struct SparseSearchExec {
+    dataset: Arc<Dataset>,
+    query: SparseQuery,
+    prefilter_source: PreFilterSource,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DisplayAs for SparseSearchExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(
+                    f,
+                    "SparseSearch: column={}, k={}",
+                    self.query.column, self.query.k
+                )
+            }
+            DisplayFormatType::TreeRender => {
+                write!(
+                    f,
+                    "SparseSearch\ncolumn={}\nk={}",
+                    self.query.column, self.query.k
+                )
+            }
+        }
+    }
+}
+
+impl SparseSearchExec {
+    pub fn new(
+        dataset: Arc<Dataset>,
+        query: SparseQuery,
+        prefilter_source: PreFilterSource,
+    ) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(FTS_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Final,
+            Boundedness::Bounded,
+        );
+        Self {
+            dataset,
+            query,
+            prefilter_source,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl ExecutionPlan for SparseSearchExec {
+    fn name(&self) -> &str {
+        "SparseSearchExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        match &self.prefilter_source {
+            PreFilterSource::None => vec![],
+            PreFilterSource::FilteredRowIds(src) => vec![&src],
+            PreFilterSource::ScalarIndexQuery(src) => vec![&src],
+        }
+    }
+
+    fn required_input_distribution(&self) -> Vec<Distribution> {
+        // Prefilter inputs must be a single partition
+        self.children()
+            .iter()
+            .map(|_| Distribution::SinglePartition)
+            .collect()
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let prefilter_source = match (children.len(), &self.prefilter_source) {
+            (0, PreFilterSource::None) => PreFilterSource::None,
+            (1, PreFilterSource::FilteredRowIds(_)) => {
+                PreFilterSource::FilteredRowIds(children.pop().unwrap())
+            }
+            (1, PreFilterSource::ScalarIndexQuery(_)) => {
+                PreFilterSource::ScalarIndexQuery(children.pop().unwrap())
+            }
+            _ => {
+                return Err(DataFusionError::Internal(
+                    "Unexpected children for SparseSearchExec".to_string(),
+                ));
+            }
+        };
+        Ok(Arc::new(Self::new(
+            self.dataset.clone(),
+            self.query.clone(),
+            prefilter_source,
+        )))
+    }
+
+    #[instrument(name = "sparse_search_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let query = self.query.clone();
+        let ds = self.dataset.clone();
+        let prefilter_source = self.prefilter_source.clone();
+        let metrics = Arc::new(IndexMetrics::new(&self.metrics, partition));
+
+        let stream = stream::once(async move {
+            let index_meta = ds
+                .load_scalar_index(
+                    ScalarIndexCriteria::default()
+                        .for_column(&query.column)
+                        .with_type(ScalarIndexType::Sparse),
+                )
+                .await?
+                .ok_or(DataFusionError::Execution(format!(
+                    "No sparse index found for column {}",
+                    query.column,
+                )))?;
+            let uuid = index_meta.uuid.to_string();
+            let index = ds
+                .open_generic_index(&query.column, &uuid, metrics.as_ref())
+                .await?;
+
+            let pre_filter = build_prefilter(
+                context.clone(),
+                partition,
+                &prefilter_source,
+                ds,
+                &[index_meta],
+            )?;
+
+            let sparse_idx = index
+                .as_any()
+                .downcast_ref::<SparseIndex>()
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "Index for column {} is not a sparse index",
+                        query.column,
+                    ))
+                })?;
+
+            pre_filter.wait_for_ready().await?;
+            let (row_ids, scores) = sparse_idx.search_sparse(
+                query.vector.as_ref(),
+                query.k,
+                pre_filter.mask().as_ref(),
+                metrics.as_ref(),
+            );
+
+            let batch = RecordBatch::try_new(
+                FTS_SCHEMA.clone(),
+                vec![
+                    Arc::new(UInt64Array::from(row_ids)),
+                    Arc::new(Float32Array::from(scores)),
+                ],
+            )?;
+            Ok::<_, DataFusionError>(batch)
+        });
+
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.stream_in_current_span().boxed(),
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<datafusion::physical_plan::Statistics> {
+        Ok(Statistics::new_unknown(&FTS_SCHEMA))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
+
+/// Calculates the dot product with the query for each row in the input
+///
+/// Only rows with a positive score are emitted, to match the results of the index.
+#[derive(Debug)]
+pub struct FlatSparseSearchExec {
+    query: SparseQuery,
+    input: Arc<dyn ExecutionPlan>,
+
+    properties: PlanProperties,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl DisplayAs for FlatSparseSearchExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => {
+                write!(f, "FlatSparseSearch: column={}", self.query.column)
+            }
+            DisplayFormatType::TreeRender => {
+                write!(f, "FlatSparseSearch\ncolumn={}", self.query.column)
+            }
+        }
+    }
+}
+
+impl FlatSparseSearchExec {
+    pub fn new(query: SparseQuery, input: Arc<dyn ExecutionPlan>) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(FTS_SCHEMA.clone()),
+            Partitioning::RoundRobinBatch(1),
+            EmissionType::Incremental,
+            Boundedness::Bounded,
+        );
+        Self {
+            query,
+            input,
+            properties,
+            metrics: ExecutionPlanMetricsSet::new(),
+        }
+    }
+}
+
+impl ExecutionPlan for FlatSparseSearchExec {
+    fn name(&self) -> &str {
+        "FlatSparseSearchExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(
+                "Unexpected number of children".to_string(),
+            ));
+        }
+        Ok(Arc::new(Self::new(
+            self.query.clone(),
+            children.pop().unwrap(),
+        )))
+    }
+
+    #[instrument(name = "flat_sparse_search_exec", level = "debug", skip_all)]
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let query = self.query.clone();
+        let input = self.input.execute(partition, context)?;
+
+        let stream = input.and_then(move |batch| {
+            let query = query.clone();
+            async move {
+                let vectors = batch.column_by_name(&query.column).ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "column {} not found in the input of FlatSparseSearchExec",
+                        query.column
+                    ))
+                })?;
+                let scores = sparse_dot_arrow_batch(query.vector.as_ref(), vectors.as_ref())?;
+                let positive = scores
+                    .iter()
+                    .map(|score| Some(score.is_some_and(|score| score > 0.0)))
+                    .collect::<BooleanArray>();
+                let row_ids = filter(batch[ROW_ID].as_ref(), &positive)?;
+                let scores = filter(scores.as_ref(), &positive)?;
+                Ok(RecordBatch::try_new(
+                    FTS_SCHEMA.clone(),
+                    vec![row_ids, scores],
+                )?)
+            }
+        });
+        Ok(Box::pin(InstrumentedRecordBatchStreamAdapter::new(
+            self.schema(),
+            stream.stream_in_current_span().boxed(),
+            partition,
+            &self.metrics,
+        )))
+    }
+
+    fn statistics(&self) -> DataFusionResult<datafusion::physical_plan::Statistics> {
+        Ok(Statistics::new_unknown(&FTS_SCHEMA))
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+}
@@ -9,7 +9,7 @@ use lance_table::format::Index;
 // SPDX-FileCopyrightText: Copyright The Lance Authors
 use pin_project::pin_project;
 use std::borrow::Cow;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::task::Poll;
 
 use arrow::array::AsArray;
@@ -20,10 +20,12 @@ use datafusion::error::{DataFusionError, Result as DataFusionResult};
 use datafusion::physical_plan::metrics::{
     BaselineMetrics, Count, ExecutionPlanMetricsSet, MetricBuilder, MetricValue,
 };
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
 use datafusion::physical_plan::{
     DisplayAs, DisplayFormatType, ExecutionPlan, RecordBatchStream, SendableRecordBatchStream,
 };
-use futures::{Stream, StreamExt, TryStreamExt};
+use futures::future::{BoxFuture, Shared};
+use futures::{FutureExt, Stream, StreamExt, TryStreamExt};
 use lance_core::error::{CloneableResult, Error};
 use lance_core::utils::futures::{Capacity, SharedStreamExt};
 use lance_core::utils::mask::{RowIdMask, RowIdTreeMap};
@@ -368,6 +370,147 @@ impl ExecutionPlan for ReplayExec {
     }
 }
 
+type CachedBatches = Shared<BoxFuture<'static, CloneableResult<Vec<RecordBatch>>>>;
+
+/// The materialized output of one input partition, for one execution of the plan
+#[derive(Debug)]
+struct CachedPartition {
+    /// The context the input was executed with.  Holding a weak reference keeps
+    /// the address from being reused, so a new execution is never mistaken
+    /// for the one that filled the cache.
+    context: Weak<datafusion::execution::TaskContext>,
+    batches: CachedBatches,
+}
+
+/// An execution node that can be used as an input any number of times
+///
+/// Each input partition is executed only once per task context, and its results
+/// are materialized in memory and replayed to every output reading that
+/// partition.  Unlike [`ReplayExec`], the outputs never block each other, so
+/// this should only be used for small inputs, like prefilters.
+#[derive(Debug)]
+pub struct CachedExec {
+    input: Arc<dyn ExecutionPlan>,
+    cached: Arc<Mutex<Vec<Option<CachedPartition>>>>,
+}
+
+impl CachedExec {
+    pub fn new(input: Arc<dyn ExecutionPlan>) -> Self {
+        let num_partitions = input.output_partitioning().partition_count();
+        Self {
+            input,
+            cached: Arc::new(Mutex::new(
+                std::iter::repeat_with(|| None)
+                    .take(num_partitions)
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl DisplayAs for CachedExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => write!(f, "Cached"),
+            DisplayFormatType::TreeRender => write!(f, "Cached"),
+        }
+    }
+}
+
+impl ExecutionPlan for CachedExec {
+    fn name(&self) -> &str {
+        "CachedExec"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn schema(&self) -> arrow_schema::SchemaRef {
+        self.input.schema()
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        mut children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
+        if children.len() != 1 {
+            return Err(DataFusionError::Internal(
+                "CachedExec requires exactly one child".to_string(),
+            ));
+        }
+        let input = children.pop().unwrap();
+        if Arc::ptr_eq(&input, &self.input) {
+            // this node may be reached by several parents, which must keep sharing the results
+            return Ok(self);
+        }
+        // the results of the old child cannot be replayed for the new one
+        Ok(Arc::new(Self::new(input)))
+    }
+
+    fn benefits_from_input_partitioning(&self) -> Vec<bool> {
+        vec![false]
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<datafusion::execution::TaskContext>,
+    ) -> datafusion::error::Result<SendableRecordBatchStream> {
+        let cached = {
+            let mut cached = self.cached.lock().unwrap();
+            let num_partitions = cached.len();
+            let Some(slot) = cached.get_mut(partition) else {
+                return Err(DataFusionError::Internal(format!(
+                    "CachedExec got partition {} but its input only has {} partitions",
+                    partition, num_partitions
+                )));
+            };
+            match slot {
+                Some(entry) if std::ptr::eq(entry.context.as_ptr(), Arc::as_ptr(&context)) => {
+                    entry.batches.clone()
+                }
+                _ => {
+                    let weak_context = Arc::downgrade(&context);
+                    let input = self.input.execute(partition, context)?;
+                    let batches = async move {
+                        CloneableResult::from(
+                            input.try_collect::<Vec<_>>().await.map_err(Error::from),
+                        )
+                    }
+                    .boxed()
+                    .shared();
+                    *slot = Some(CachedPartition {
+                        context: weak_context,
+                        batches: batches.clone(),
+                    });
+                    batches
+                }
+            }
+        };
+        let stream = futures::stream::once(async move {
+            cached
+                .await
+                .0
+                .map_err(|e| DataFusionError::External(e.0.into()))
+        })
+        .map_ok(|batches| futures::stream::iter(batches.into_iter().map(Ok)))
+        .try_flatten();
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream,
+        )))
+    }
+
+    fn properties(&self) -> &datafusion::physical_plan::PlanProperties {
+        self.input.properties()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IoMetrics {
     iops: Count,
@@ -428,9 +571,12 @@ mod tests {
 
     use std::sync::Arc;
 
+    use arrow::compute::concat_batches;
     use arrow_array::{types::UInt32Type, RecordBatchReader};
     use arrow_schema::SortOptions;
     use datafusion::{
+        datasource::memory::MemorySourceConfig,
+        execution::TaskContext,
         logical_expr::JoinType,
         physical_expr::expressions::Column,
         physical_plan::{
@@ -442,7 +588,7 @@ mod tests {
     use lance_datafusion::exec::OneShotExec;
     use lance_datagen::{array, BatchCount, RowCount};
 
-    use super::ReplayExec;
+    use super::{CachedExec, ReplayExec};
 
     #[tokio::test]
     async fn test_replay() {
@@ -481,4 +627,42 @@ mod tests {
             assert_eq!(batch.unwrap().num_columns(), 2);
         }
     }
+
+    #[tokio::test]
+    async fn test_cached_per_partition_and_context() {
+        let data = lance_datagen::gen()
+            .col("x", array::step::<UInt32Type>())
+            .into_batch_rows(RowCount::from(8))
+            .unwrap();
+        let input = MemorySourceConfig::try_new_exec(
+            &[vec![data.slice(0, 4)], vec![data.slice(4, 4)]],
+            data.schema(),
+            None,
+        )
+        .unwrap();
+        let cached = Arc::new(CachedExec::new(input));
+
+        let collect = |partition: usize, context: Arc<TaskContext>| {
+            let cached = cached.clone();
+            async move {
+                let batches = cached
+                    .execute(partition, context)
+                    .unwrap()
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap();
+                concat_batches(&batches[0].schema(), &batches).unwrap()
+            }
+        };
+
+        let context = Arc::new(TaskContext::default());
+        assert_eq!(collect(0, context.clone()).await, data.slice(0, 4));
+        assert_eq!(collect(1, context.clone()).await, data.slice(4, 4));
+        assert_eq!(collect(0, context).await, data.slice(0, 4));
+        assert_eq!(
+            collect(1, Arc::new(TaskContext::default())).await,
+            data.slice(4, 4)
+        );
+        assert!(cached.execute(2, Arc::new(TaskContext::default())).is_err());
+    }
 }
@@ -14,7 +14,7 @@ use snafu::location;
 use crate::dataset::{DEFAULT_INDEX_CACHE_SIZE, DEFAULT_METADATA_CACHE_SIZE};
 use crate::index::cache::IndexCache;
 
-use self::index_extension::IndexExtension;
+use self::index_extension::{IndexExtension, ScalarIndexExtension};
 
 pub mod index_extension;
 
@@ -124,10 +124,31 @@ impl Session {
                     ));
                 }
             }
+            index_type if index_type.is_scalar() => {
+                if self
+                    .index_extensions
+                    .contains_key(&(index_type, name.clone()))
+                {
+                    return Err(Error::invalid_input(
+                        format!("{name} is already registered"),
+                        location!(),
+                    ));
+                }
+
+                if let Some(ext) = extension.to_scalar() {
+                    self.index_extensions
+                        .insert((index_type, name), ext.to_generic());
+                } else {
+                    return Err(Error::invalid_input(
+                        format!("{name} is not a scalar index extension"),
+                        location!(),
+                    ));
+                }
+            }
             _ => {
                 return Err(Error::invalid_input(
                     format!(
-                        "scalar index extension is not support yet: {}",
+                        "index extension is not supported for index type: {}",
                         extension.index_type()
                     ),
                     location!(),
@@ -138,6 +159,17 @@ impl Session {
         Ok(())
     }
 
+    /// Find the scalar index extension which creates the indices with the given details.
+    pub(crate) fn scalar_index_extension(
+        &self,
+        details: &prost_types::Any,
+    ) -> Option<Arc<dyn ScalarIndexExtension>> {
+        self.index_extensions
+            .values()
+            .filter_map(|ext| ext.clone().to_scalar())
+            .find(|ext| ext.index_details_type_url() == details.type_url)
+    }
+
     /// Return the current size of the session in bytes
     pub fn size_bytes(&self) -> u64 {
         // We re-expose deep_size_of here so that users don't
@@ -4,8 +4,12 @@
 use std::sync::Arc;
 
 use deepsize::DeepSizeOf;
+use lance_core::datatypes::Field;
 use lance_core::Result;
 use lance_file::reader::FileReader;
+use lance_index::scalar::btree::TrainingSource;
+use lance_index::scalar::expression::ScalarQueryParser;
+use lance_index::scalar::{IndexStore, ScalarIndex};
 use lance_index::{vector::VectorIndex, IndexParams, IndexType};
 
 use crate::Dataset;
@@ -22,8 +26,41 @@ pub trait IndexExtension: Send + Sync + DeepSizeOf {
     fn to_vector(self: Arc<Self>) -> Option<Arc<dyn VectorIndexExtension>>;
 }
 
+/// An extension to create and load scalar indices which are not built into Lance.
+///
+/// The index is created by [`lance_index::DatasetIndexExt::create_index`] with
+/// the index type of the extension, and params whose `index_name` is the registered name.
+/// The [`ScalarIndex`] loaded by the extension takes part in filtering through the
+/// query parser, and in `update` / `remap` when the dataset is optimized or compacted,
+/// so its `index_type` must be a scalar index type.
+#[async_trait::async_trait]
 pub trait ScalarIndexExtension: IndexExtension {
-    // TODO: implement this trait and wire it in
+    /// The type url of the index details returned by `create_index`.
+    ///
+    /// The index details are stored in the manifest, and used to find the extension
+    /// to load the index, so it must be unique among the registered extensions.
+    fn index_details_type_url(&self) -> &str;
+
+    /// Train the index on the column, and write it to the index store.
+    ///
+    /// The training data contains the column and the row ids.
+    /// Returns the index details to store in the manifest.
+    async fn create_index(
+        &self,
+        dataset: &Dataset,
+        column: &str,
+        data: Box<dyn TrainingSource + Send>,
+        index_store: &dyn IndexStore,
+        params: &dyn IndexParams,
+    ) -> Result<prost_types::Any>;
+
+    /// Load a scalar index from the index store.
+    async fn load_index(&self, index_store: Arc<dyn IndexStore>) -> Result<Arc<dyn ScalarIndex>>;
+
+    /// The parser turning filters on the indexed field into queries against the index,
+    /// returns `None` if the index can't be used for filtering.
+    fn query_parser(&self, index_name: String, field: &Field)
+        -> Option<Box<dyn ScalarQueryParser>>;
 }
 
 #[async_trait::async_trait]
@@ -52,7 +89,12 @@ pub trait VectorIndexExtension: IndexExtension {
 #[cfg(test)]
 mod test {
     use crate::{
-        dataset::{builder::DatasetBuilder, scanner::test_dataset::TestVectorDataset},
+        dataset::{
+            builder::DatasetBuilder,
+            optimize::{compact_files, CompactionOptions},
+            scanner::test_dataset::TestVectorDataset,
+            WriteParams,
+        },
         index::{DatasetIndexInternalExt, PreFilter},
         session::Session,
     };
@@ -65,12 +107,17 @@ mod test {
         sync::{atomic::AtomicBool, Arc},
     };
 
-    use arrow_array::{RecordBatch, UInt32Array};
+    use arrow_array::{types::Int32Type, RecordBatch, UInt32Array};
     use arrow_schema::Schema;
     use datafusion::execution::SendableRecordBatchStream;
     use deepsize::DeepSizeOf;
+    use lance_datagen::{array, gen, BatchCount, RowCount};
     use lance_file::version::LanceFileVersion;
     use lance_file::writer::{FileWriter, FileWriterOptions};
+    use lance_index::optimize::OptimizeOptions;
+    use lance_index::scalar::btree::{train_btree_index, BTreeIndex, DEFAULT_BTREE_BATCH_SIZE};
+    use lance_index::scalar::expression::SargableQueryParser;
+    use lance_index::scalar::flat::FlatIndexMetadata;
     use lance_index::vector::v3::subindex::SubIndexType;
     use lance_index::{
         metrics::MetricsCollector,
@@ -405,4 +452,228 @@ mod test {
         // should be able to downcast to the mock index
         let _downcasted = vector_index.as_any().downcast_ref::<MockIndex>().unwrap();
     }
+
+    const MOCK_SCALAR_DETAILS_TYPE_URL: &str = "/test.MockScalarIndexDetails";
+
+    /// A scalar index extension backed by the btree index.
+    struct MockScalarIndexExtension {
+        create_index_called: AtomicBool,
+        load_index_called: AtomicBool,
+    }
+
+    impl MockScalarIndexExtension {
+        fn new() -> Self {
+            Self {
+                create_index_called: AtomicBool::new(false),
+                load_index_called: AtomicBool::new(false),
+            }
+        }
+    }
+
+    impl DeepSizeOf for MockScalarIndexExtension {
+        fn deep_size_of_children(&self, _context: &mut deepsize::Context) -> usize {
+            0
+        }
+    }
+
+    impl IndexExtension for MockScalarIndexExtension {
+        fn index_type(&self) -> IndexType {
+            IndexType::Scalar
+        }
+
+        fn to_generic(self: Arc<Self>) -> Arc<dyn IndexExtension> {
+            self
+        }
+
+        fn to_scalar(self: Arc<Self>) -> Option<Arc<dyn ScalarIndexExtension>> {
+            Some(self)
+        }
+
+        fn to_vector(self: Arc<Self>) -> Option<Arc<dyn VectorIndexExtension>> {
+            None
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ScalarIndexExtension for MockScalarIndexExtension {
+        fn index_details_type_url(&self) -> &str {
+            MOCK_SCALAR_DETAILS_TYPE_URL
+        }
+
+        async fn create_index(
+            &self,
+            dataset: &Dataset,
+            column: &str,
+            data: Box<dyn TrainingSource + Send>,
+            index_store: &dyn IndexStore,
+            _params: &dyn IndexParams,
+        ) -> Result<prost_types::Any> {
+            let field = dataset.schema().field(column).unwrap();
+            train_btree_index(
+                data,
+                &FlatIndexMetadata::new(field.data_type()),
+                index_store,
+                DEFAULT_BTREE_BATCH_SIZE as u32,
+            )
+            .await?;
+
+            self.create_index_called
+                .store(true, std::sync::atomic::Ordering::Release);
+
+            Ok(prost_types::Any {
+                type_url: MOCK_SCALAR_DETAILS_TYPE_URL.to_string(),
+                value: vec![],
+            })
+        }
+
+        async fn load_index(
+            &self,
+            index_store: Arc<dyn IndexStore>,
+        ) -> Result<Arc<dyn ScalarIndex>> {
+            self.load_index_called
+                .store(true, std::sync::atomic::Ordering::Release);
+
+            Ok(BTreeIndex::load(index_store).await?)
+        }
+
+        fn query_parser(
+            &self,
+            index_name: String,
+            _field: &Field,
+        ) -> Option<Box<dyn ScalarQueryParser>> {
+            Some(Box::new(SargableQueryParser::new(index_name)))
+        }
+    }
+
+    struct MockScalarIndexParams;
+
+    impl IndexParams for MockScalarIndexParams {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn index_type(&self) -> IndexType {
+            IndexType::Scalar
+        }
+
+        fn index_name(&self) -> &str {
+            "TEST_SCALAR"
+        }
+    }
+
+    async fn count_rows_with_plan(dataset: &Dataset, filter: &str) -> (usize, String) {
+        let mut scanner = dataset.scan();
+        scanner.filter(filter).unwrap();
+        let plan = scanner.explain_plan(false).await.unwrap();
+        let num_rows = scanner.try_into_batch().await.unwrap().num_rows();
+        (num_rows, plan)
+    }
+
+    #[tokio::test]
+    async fn test_scalar_index_extension_roundtrip() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let test_uri = test_dir.path().to_str().unwrap();
+        let reader = gen()
+            .col("i", array::step::<Int32Type>())
+            .into_reader_rows(RowCount::from(250), BatchCount::from(4));
+        Dataset::write(
+            reader,
+            test_uri,
+            Some(WriteParams {
+                max_rows_per_file: 250,
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+
+        let idx_ext = Arc::new(MockScalarIndexExtension::new());
+        let mut session = Session::default();
+        session
+            .register_index_extension("TEST_SCALAR".into(), idx_ext.clone())
+            .unwrap();
+        // an extension can only be registered once
+        assert!(session
+            .register_index_extension("TEST_SCALAR".into(), idx_ext.clone())
+            .is_err());
+        let session = Arc::new(session);
+
+        let mut ds_with_extension = DatasetBuilder::from_uri(test_uri)
+            .with_session(session.clone())
+            .load()
+            .await
+            .unwrap();
+        ds_with_extension
+            .create_index(
+                &["i"],
+                IndexType::Scalar,
+                None,
+                &MockScalarIndexParams,
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(idx_ext
+            .create_index_called
+            .load(std::sync::atomic::Ordering::Acquire));
+
+        let indices = ds_with_extension.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        assert_eq!(
+            indices[0].index_details.as_ref().unwrap().type_url,
+            MOCK_SCALAR_DETAILS_TYPE_URL
+        );
+
+        // the filter is answered by the extension index
+        let (num_rows, plan) = count_rows_with_plan(&ds_with_extension, "i = 42").await;
+        assert_eq!(num_rows, 1);
+        assert!(plan.contains("@i_idx"), "{plan}");
+        assert!(idx_ext
+            .load_index_called
+            .load(std::sync::atomic::Ordering::Acquire));
+
+        // without the extension, the index is ignored and the filter falls back to a scan
+        let ds_without_extension = DatasetBuilder::from_uri(test_uri).load().await.unwrap();
+        let (num_rows, plan) = count_rows_with_plan(&ds_without_extension, "i = 42").await;
+        assert_eq!(num_rows, 1);
+        assert!(!plan.contains("@i_idx"), "{plan}");
+
+        // the appended rows are merged into the index by optimize_indices
+        let reader = gen()
+            .col("i", array::step_custom::<Int32Type>(1000, 1))
+            .into_reader_rows(RowCount::from(100), BatchCount::from(1));
+        ds_with_extension.append(reader, None).await.unwrap();
+        ds_with_extension
+            .optimize_indices(&OptimizeOptions::default())
+            .await
+            .unwrap();
+        let indices = ds_with_extension.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        assert_eq!(
+            indices[0].index_details.as_ref().unwrap().type_url,
+            MOCK_SCALAR_DETAILS_TYPE_URL
+        );
+        assert_eq!(
+            indices[0].fragment_bitmap.as_ref().unwrap(),
+            &RoaringBitmap::from_iter(0..5)
+        );
+        let (num_rows, plan) = count_rows_with_plan(&ds_with_extension, "i >= 1050").await;
+        assert_eq!(num_rows, 50);
+        assert!(plan.contains("@i_idx"), "{plan}");
+
+        // the index is remapped by compaction
+        ds_with_extension.delete("i < 100").await.unwrap();
+        compact_files(&mut ds_with_extension, CompactionOptions::default(), None)
+            .await
+            .unwrap();
+        let indices = ds_with_extension.load_indices().await.unwrap();
+        assert_eq!(indices.len(), 1);
+        assert_eq!(
+            indices[0].index_details.as_ref().unwrap().type_url,
+            MOCK_SCALAR_DETAILS_TYPE_URL
+        );
+        let (num_rows, plan) = count_rows_with_plan(&ds_with_extension, "i < 200").await;
+        assert_eq!(num_rows, 100);
+        assert!(plan.contains("@i_idx"), "{plan}");
+    }
 }